@@ -163,3 +163,16 @@ impl ConnectedPoint {
         }
     }
 }
+
+/// The protocols negotiated while upgrading a connection.
+///
+/// Recorded by the upgrade process of [`Builder`](crate::transport::upgrade::Builder) and made
+/// available on the resulting [`StreamMuxer`](crate::StreamMuxer) via
+/// [`StreamMuxer::negotiated_protocols`](crate::StreamMuxer::negotiated_protocols).
+#[derive(PartialEq, Eq, Debug, Clone, Hash, Default)]
+pub struct NegotiatedProtocols {
+    /// The security protocol negotiated to authenticate the remote, e.g. `/noise`.
+    pub security: Option<String>,
+    /// The protocol of the negotiated stream multiplexer, e.g. `/yamux/1.0.0`.
+    pub muxer: Option<String>,
+}
@@ -18,7 +18,8 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::muxing::StreamMuxerEvent;
+use crate::connection::NegotiatedProtocols;
+use crate::muxing::{CloseReason, DatagramError, StreamMuxerEvent};
 use crate::{
     muxing::StreamMuxer,
     transport::{ListenerId, Transport, TransportError, TransportEvent},
@@ -27,6 +28,7 @@ use crate::{
 use either::Either;
 use futures::prelude::*;
 use pin_project::pin_project;
+use std::time::Duration;
 use std::{pin::Pin, task::Context, task::Poll};
 
 impl<A, B> StreamMuxer for future::Either<A, B>
@@ -85,6 +87,44 @@ where
             future::Either::Right(inner) => inner.poll(cx).map_err(Either::Right),
         }
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        match self.as_pin_mut() {
+            future::Either::Left(inner) => inner.set_close_reason(reason),
+            future::Either::Right(inner) => inner.set_close_reason(reason),
+        }
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        match self {
+            future::Either::Left(inner) => inner.max_datagram_size(),
+            future::Either::Right(inner) => inner.max_datagram_size(),
+        }
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        match self.as_pin_mut() {
+            future::Either::Left(inner) => inner.send_datagram(data),
+            future::Either::Right(inner) => inner.send_datagram(data),
+        }
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        match self.as_pin_mut() {
+            future::Either::Left(inner) => inner.poll_datagram(cx),
+            future::Either::Right(inner) => inner.poll_datagram(cx),
+        }
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        match self {
+            future::Either::Left(inner) => inner.negotiated_protocols(),
+            future::Either::Right(inner) => inner.negotiated_protocols(),
+        }
+    }
 }
 
 /// Implements `Future` and dispatches all method calls to either `First` or `Second`.
@@ -216,4 +256,25 @@ where
             Either::Right(b) => b.address_translation(server, observed),
         }
     }
+
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        match self {
+            Either::Left(a) => a.dial_timeout(addr),
+            Either::Right(b) => b.dial_timeout(addr),
+        }
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        match self {
+            Either::Left(a) => a.address_score(addr),
+            Either::Right(b) => b.address_score(addr),
+        }
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        match self {
+            Either::Left(a) => a.supports_dial(addr),
+            Either::Right(b) => b.supports_dial(addr),
+        }
+    }
 }
@@ -55,7 +55,7 @@ pub mod signed_envelope;
 pub mod transport;
 pub mod upgrade;
 
-pub use connection::{ConnectedPoint, Endpoint};
+pub use connection::{ConnectedPoint, Endpoint, NegotiatedProtocols};
 pub use multiaddr::Multiaddr;
 pub use multihash;
 pub use muxing::StreamMuxer;
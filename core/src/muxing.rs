@@ -50,15 +50,19 @@
 //! The upgrade process will take ownership of the connection, which makes it possible for the
 //! implementation of `StreamMuxer` to control everything that happens on the wire.
 
+use crate::connection::NegotiatedProtocols;
 use futures::{task::Context, task::Poll, AsyncRead, AsyncWrite};
 use multiaddr::Multiaddr;
 use std::future::Future;
 use std::pin::Pin;
+use std::{error::Error, fmt, io};
 
 pub use self::boxed::StreamMuxerBox;
 pub use self::boxed::SubstreamBox;
+pub use self::negotiated::WithNegotiatedProtocols;
 
 mod boxed;
+mod negotiated;
 
 /// Provides multiplexing for a connection by allowing users to open substreams.
 ///
@@ -109,8 +113,138 @@ pub trait StreamMuxer {
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>>;
+
+    /// Sets the [`CloseReason`] to signal to the remote once the connection is closed via
+    /// [`StreamMuxer::poll_close`].
+    ///
+    /// Muxers without support for application close codes ignore the reason, which is the
+    /// default implementation.
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        let _ = reason;
+    }
+
+    /// Returns the maximum size of an unreliable datagram that can currently be sent via
+    /// [`StreamMuxer::send_datagram`], or `None` if datagrams are not supported.
+    ///
+    /// Datagrams are only supported if both the muxer, e.g. QUIC, and the remote support them.
+    /// The maximum size may change over the lifetime of the connection, e.g. with the path MTU.
+    ///
+    /// Muxers without support for datagrams, the default implementation, return `None`.
+    fn max_datagram_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Sends an unreliable datagram to the remote.
+    ///
+    /// Datagrams may be dropped, reordered or duplicated and are not subject to flow control.
+    /// Callers are expected to fall back to a substream if [`DatagramError::Unsupported`] is
+    /// returned, which is the default implementation.
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        let _ = data;
+        Err(DatagramError::Unsupported)
+    }
+
+    /// Poll for an unreliable datagram sent by the remote.
+    ///
+    /// Muxers without support for datagrams, the default implementation, return
+    /// [`DatagramError::Unsupported`].
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        let _ = cx;
+        Poll::Ready(Err(DatagramError::Unsupported))
+    }
+
+    /// Returns the security and multiplexing protocols negotiated while upgrading the
+    /// underlying connection, if they were recorded.
+    ///
+    /// Muxers obtained from [`Authenticated::multiplex`](crate::transport::upgrade::Authenticated::multiplex)
+    /// record them. Other muxers, the default implementation, return `None`.
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        None
+    }
+}
+
+/// An error of the unreliable datagram operations of a [`StreamMuxer`].
+#[derive(Debug)]
+pub enum DatagramError {
+    /// The muxer or the remote does not support datagrams.
+    Unsupported,
+    /// The datagram exceeds [`StreamMuxer::max_datagram_size`].
+    TooLarge,
+    /// The connection failed.
+    Connection(io::Error),
+}
+
+impl fmt::Display for DatagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatagramError::Unsupported => write!(f, "datagrams are not supported"),
+            DatagramError::TooLarge => write!(f, "datagram exceeds the maximum size"),
+            DatagramError::Connection(_) => write!(f, "connection failed"),
+        }
+    }
+}
+
+impl Error for DatagramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatagramError::Connection(e) => Some(e),
+            DatagramError::Unsupported | DatagramError::TooLarge => None,
+        }
+    }
 }
 
+/// The application-level reason for closing a connection, e.g. the error code and reason phrase
+/// of a QUIC `CONNECTION_CLOSE` frame.
+///
+/// Muxers supporting it report a connection closed by the remote with a reason as an error
+/// having a [`CloseReason`] as (transitive) source, see [`CloseReason::from_error`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloseReason {
+    /// The application error code.
+    pub code: u64,
+    /// The human-readable reason for closing the connection.
+    pub reason: Vec<u8>,
+}
+
+impl CloseReason {
+    pub fn new(code: u64, reason: impl Into<Vec<u8>>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    /// Searches the chain of sources of the given error for a [`CloseReason`].
+    pub fn from_error<'a>(mut error: &'a (dyn Error + 'static)) -> Option<&'a CloseReason> {
+        loop {
+            if let Some(reason) = error.downcast_ref::<CloseReason>() {
+                return Some(reason);
+            }
+            // `io::Error` does not return the error it wraps as its source.
+            error = match error.downcast_ref::<io::Error>().and_then(|e| e.get_ref()) {
+                Some(inner) => inner,
+                None => error.source()?,
+            };
+        }
+    }
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connection closed with code {}: {}",
+            self.code,
+            String::from_utf8_lossy(&self.reason)
+        )
+    }
+}
+
+impl Error for CloseReason {}
+
 /// An event produced by a [`StreamMuxer`].
 #[derive(Debug)]
 pub enum StreamMuxerEvent {
@@ -158,6 +292,14 @@ pub trait StreamMuxerExt: StreamMuxer + Sized {
         Pin::new(self).poll_close(cx)
     }
 
+    /// Convenience function for calling [`StreamMuxer::poll_datagram`] for [`StreamMuxer`]s that are `Unpin`.
+    fn poll_datagram_unpin(&mut self, cx: &mut Context<'_>) -> Poll<Result<Vec<u8>, DatagramError>>
+    where
+        Self: Unpin,
+    {
+        Pin::new(self).poll_datagram(cx)
+    }
+
     /// Returns a future for closing this [`StreamMuxer`].
     fn close(self) -> Close<Self> {
         Close(self)
@@ -178,3 +320,41 @@ where
         self.0.poll_close_unpin(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapper(CloseReason);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "wrapper")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn close_reason_from_error_chain() {
+        let reason = CloseReason::new(42, "bye");
+        let error = io::Error::new(
+            io::ErrorKind::Other,
+            either::Either::<_, io::Error>::Left(io::Error::new(
+                io::ErrorKind::Other,
+                Wrapper(reason.clone()),
+            )),
+        );
+
+        assert_eq!(CloseReason::from_error(&error), Some(&reason));
+        assert_eq!(
+            CloseReason::from_error(&io::Error::from(io::ErrorKind::ConnectionReset)),
+            None
+        );
+    }
+}
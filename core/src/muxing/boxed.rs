@@ -1,4 +1,5 @@
-use crate::muxing::{StreamMuxer, StreamMuxerEvent};
+use crate::connection::NegotiatedProtocols;
+use crate::muxing::{CloseReason, DatagramError, StreamMuxer, StreamMuxerEvent};
 use futures::{AsyncRead, AsyncWrite};
 use pin_project::pin_project;
 use std::error::Error;
@@ -76,6 +77,29 @@ where
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
         self.project().inner.poll(cx).map_err(into_io_error)
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        self.project().inner.set_close_reason(reason)
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.inner.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        self.project().inner.send_datagram(data)
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        self.project().inner.poll_datagram(cx)
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        self.inner.negotiated_protocols()
+    }
 }
 
 fn into_io_error<E>(err: E) -> io::Error
@@ -136,6 +160,29 @@ impl StreamMuxer for StreamMuxerBox {
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
         self.project().poll(cx)
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        self.project().set_close_reason(reason)
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.inner.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        self.project().send_datagram(data)
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        self.project().poll_datagram(cx)
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        self.inner.negotiated_protocols()
+    }
 }
 
 impl SubstreamBox {
@@ -0,0 +1,89 @@
+use crate::connection::NegotiatedProtocols;
+use crate::muxing::{CloseReason, DatagramError, StreamMuxer, StreamMuxerEvent};
+use pin_project::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A [`StreamMuxer`] that records the protocols negotiated while upgrading its connection.
+///
+/// All other methods are forwarded to the wrapped muxer.
+#[pin_project]
+#[derive(Debug)]
+pub struct WithNegotiatedProtocols<M> {
+    #[pin]
+    inner: M,
+    protocols: NegotiatedProtocols,
+}
+
+impl<M> WithNegotiatedProtocols<M> {
+    /// Wraps the given muxer, recording the given protocols.
+    pub fn new(inner: M, protocols: NegotiatedProtocols) -> Self {
+        Self { inner, protocols }
+    }
+
+    /// Returns a reference to the wrapped muxer.
+    pub fn get_ref(&self) -> &M {
+        &self.inner
+    }
+
+    /// Returns the wrapped muxer, discarding the recorded protocols.
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M> StreamMuxer for WithNegotiatedProtocols<M>
+where
+    M: StreamMuxer,
+{
+    type Substream = M::Substream;
+    type Error = M::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.project().inner.poll_inbound(cx)
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        self.project().inner.poll_outbound(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.project().inner.poll(cx)
+    }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        self.project().inner.set_close_reason(reason)
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.inner.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        self.project().inner.send_datagram(data)
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        self.project().inner.poll_datagram(cx)
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        Some(&self.protocols)
+    }
+}
@@ -33,6 +33,7 @@ use std::{
     pin::Pin,
     sync::atomic::{AtomicUsize, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
 pub mod and_then;
@@ -175,6 +176,44 @@ pub trait Transport {
     /// `None` should be returned as well.
     fn address_translation(&self, listen: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr>;
 
+    /// The default timeout for dialing the given [`Multiaddr`] with this transport.
+    ///
+    /// Transports may use different timeouts depending on their characteristics, e.g. a
+    /// connection-less transport may fail faster than a connection-oriented one. The timeout is
+    /// not enforced by the transport itself but by the caller of [`Transport::dial`], e.g. the
+    /// `Swarm`, which can override it per dial.
+    ///
+    /// Returns `None` if the address is not supported by this transport or no timeout applies.
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        let _ = addr;
+        None
+    }
+
+    /// The score of the given [`Multiaddr`] when dialing with this transport.
+    ///
+    /// When dialing a peer with multiple addresses, the caller of [`Transport::dial`], e.g. the
+    /// `Swarm`, dials the addresses with a higher score first. Together with a limited number of
+    /// concurrent dials per peer, this allows preferring one transport over another for the same
+    /// host, e.g. QUIC over TCP, see [`OrTransport::with_address_score`].
+    ///
+    /// Returns `None` if the address is not supported by this transport or no score applies,
+    /// which is equivalent to a score of `0`.
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        let _ = addr;
+        None
+    }
+
+    /// Whether this transport accepts the given [`Multiaddr`] for dialing, i.e. whether
+    /// [`Transport::dial`] does not fail with [`TransportError::MultiaddrNotSupported`].
+    ///
+    /// Composed transports like [`OrTransport`] use this to apply the [`Transport::dial_timeout`]
+    /// and [`Transport::address_score`] of the transport that actually dials the address.
+    ///
+    /// Defaults to whether the transport reports a dial timeout or score for the address.
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.dial_timeout(addr).is_some() || self.address_score(addr).is_some()
+    }
+
     /// Boxes the transport, including custom transport errors.
     fn boxed(self) -> boxed::Boxed<Self::Output>
     where
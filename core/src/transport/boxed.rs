@@ -21,6 +21,7 @@
 use crate::transport::{ListenerId, Transport, TransportError, TransportEvent};
 use futures::{prelude::*, stream::FusedStream};
 use multiaddr::Multiaddr;
+use std::time::Duration;
 use std::{
     error::Error,
     fmt, io,
@@ -61,6 +62,9 @@ trait Abstract<O> {
     fn dial(&mut self, addr: Multiaddr) -> Result<Dial<O>, TransportError<io::Error>>;
     fn dial_as_listener(&mut self, addr: Multiaddr) -> Result<Dial<O>, TransportError<io::Error>>;
     fn address_translation(&self, server: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr>;
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration>;
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32>;
+    fn supports_dial(&self, addr: &Multiaddr) -> bool;
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -104,6 +108,18 @@ where
         Transport::address_translation(self, server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        Transport::dial_timeout(self, addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        Transport::address_score(self, addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        Transport::supports_dial(self, addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -158,6 +174,18 @@ impl<O> Transport for Boxed<O> {
         self.inner.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.inner.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.inner.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.inner.supports_dial(addr)
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
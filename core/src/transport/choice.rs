@@ -23,16 +23,36 @@ use crate::transport::{ListenerId, Transport, TransportError, TransportEvent};
 use either::Either;
 use futures::future;
 use multiaddr::Multiaddr;
+use std::time::Duration;
 use std::{pin::Pin, task::Context, task::Poll};
 
 /// Struct returned by `or_transport()`.
 #[derive(Debug, Copy, Clone)]
 #[pin_project::pin_project]
-pub struct OrTransport<A, B>(#[pin] A, #[pin] B);
+pub struct OrTransport<A, B>(#[pin] A, #[pin] B, Option<fn(&Multiaddr) -> Option<i32>>);
 
 impl<A, B> OrTransport<A, B> {
     pub fn new(a: A, b: B) -> OrTransport<A, B> {
-        OrTransport(a, b)
+        OrTransport(a, b, None)
+    }
+
+    /// Scores addresses with the given function instead of the scores of the two transports,
+    /// see [`Transport::address_score`].
+    ///
+    /// For example, to dial QUIC addresses before TCP addresses of the same host:
+    ///
+    /// ```
+    /// # use libp2p_core::{multiaddr::Protocol, transport::{dummy::DummyTransport, OrTransport}};
+    /// let transport = OrTransport::new(DummyTransport::<()>::new(), DummyTransport::<()>::new())
+    ///     .with_address_score(|addr| {
+    ///         addr.iter()
+    ///             .any(|p| matches!(p, Protocol::QuicV1))
+    ///             .then_some(1)
+    ///     });
+    /// ```
+    pub fn with_address_score(mut self, score: fn(&Multiaddr) -> Option<i32>) -> Self {
+        self.2 = Some(score);
+        self
     }
 }
 
@@ -167,6 +187,31 @@ where
         }
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        // Like `dial`, prefer the first transport if it accepts the address.
+        if self.0.supports_dial(addr) {
+            self.0.dial_timeout(addr)
+        } else {
+            self.1.dial_timeout(addr)
+        }
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        if let Some(score) = self.2 {
+            return score(addr);
+        }
+
+        if self.0.supports_dial(addr) {
+            self.0.address_score(addr)
+        } else {
+            self.1.address_score(addr)
+        }
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.0.supports_dial(addr) || self.1.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -187,3 +232,81 @@ where
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{dummy::DummyTransport, memory::MemoryTransport};
+    use std::io;
+
+    /// A transport reporting a dial timeout and score for any address.
+    struct AnyAddress(DummyTransport<()>);
+
+    impl Transport for AnyAddress {
+        type Output = ();
+        type Error = io::Error;
+        type ListenerUpgrade = <DummyTransport<()> as Transport>::ListenerUpgrade;
+        type Dial = <DummyTransport<()> as Transport>::Dial;
+
+        fn listen_on(
+            &mut self,
+            id: ListenerId,
+            addr: Multiaddr,
+        ) -> Result<(), TransportError<Self::Error>> {
+            self.0.listen_on(id, addr)
+        }
+
+        fn remove_listener(&mut self, id: ListenerId) -> bool {
+            self.0.remove_listener(id)
+        }
+
+        fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            self.0.dial(addr)
+        }
+
+        fn dial_as_listener(
+            &mut self,
+            addr: Multiaddr,
+        ) -> Result<Self::Dial, TransportError<Self::Error>> {
+            self.0.dial_as_listener(addr)
+        }
+
+        fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+            None
+        }
+
+        fn dial_timeout(&self, _: &Multiaddr) -> Option<Duration> {
+            Some(Duration::from_secs(1))
+        }
+
+        fn address_score(&self, _: &Multiaddr) -> Option<i32> {
+            Some(1)
+        }
+
+        fn poll(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn dial_timeout_and_score_of_the_accepting_transport() {
+        let transport = OrTransport::new(
+            MemoryTransport::default(),
+            AnyAddress(DummyTransport::new()),
+        );
+        let memory: Multiaddr = "/memory/1234".parse().unwrap();
+        let tcp: Multiaddr = "/ip4/127.0.0.1/tcp/1234".parse().unwrap();
+
+        // The memory transport dials memory addresses, without timeout or score.
+        assert!(transport.supports_dial(&memory));
+        assert_eq!(transport.dial_timeout(&memory), None);
+        assert_eq!(transport.address_score(&memory), None);
+
+        assert!(transport.supports_dial(&tcp));
+        assert_eq!(transport.dial_timeout(&tcp), Some(Duration::from_secs(1)));
+        assert_eq!(transport.address_score(&tcp), Some(1));
+    }
+}
@@ -22,6 +22,8 @@ use crate::{
     multiaddr::{Multiaddr, Protocol},
     transport::{ListenerId, TransportError, TransportEvent},
 };
+use std::net::IpAddr;
+use std::time::Duration;
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -129,6 +131,12 @@ mod ipv4_global {
     #[must_use]
     #[inline]
     pub(crate) const fn is_global(a: Ipv4Addr) -> bool {
+        // The Port Control Protocol and TURN anycast addresses are the only globally reachable
+        // addresses in `192.0.0.0/24`.
+        if let [192, 0, 0, 9 | 10] = a.octets() {
+            return true;
+        }
+
         !(a.octets()[0] == 0 // "This network"
             || is_private(a)
             || is_shared(a)
@@ -139,7 +147,9 @@ mod ipv4_global {
             || a.is_documentation()
             || is_benchmarking(a)
             || is_reserved(a)
-            || a.is_broadcast())
+            || a.is_broadcast()
+            // Multicast addresses (`224.0.0.0/4`) are no unicast destination.
+            || a.is_multicast())
     }
 }
 
@@ -259,7 +269,22 @@ mod ipv6_global {
                 ))
             || is_documentation(a)
             || is_unique_local(a)
-            || is_unicast_link_local(a))
+            || is_unicast_link_local(a)
+            // Multicast addresses (`ff00::/8`) are no unicast destination.
+            || a.is_multicast())
+    }
+}
+
+/// Returns `true` if the IP address appears to be globally reachable, i.e. it is none of the
+/// private, shared, loopback, link-local, multicast or otherwise reserved addresses listed in
+/// the IANA IPv4 and IPv6 Special-Purpose Address Registries.
+///
+/// IPv4-mapped IPv6 addresses are not considered globally reachable. [`Transport`] only dials
+/// addresses for which this returns `true`.
+pub fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(a) => ipv4_global::is_global(a),
+        IpAddr::V6(a) => ipv6_global::is_global(a),
     }
 }
 
@@ -340,6 +365,22 @@ impl<T: crate::Transport + Unpin> crate::Transport for Transport<T> {
         self.inner.address_translation(listen, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.inner.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.inner.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        match addr.iter().next() {
+            Some(Protocol::Ip4(a)) if !ipv4_global::is_global(a) => false,
+            Some(Protocol::Ip6(a)) if !ipv6_global::is_global(a) => false,
+            _ => self.inner.supports_dial(addr),
+        }
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -347,3 +388,36 @@ impl<T: crate::Transport + Unpin> crate::Transport for Transport<T> {
         Pin::new(&mut self.inner).poll(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_addresses() {
+        for (ip, global) in [
+            ("8.8.8.8", true),
+            ("192.0.0.9", true),
+            ("10.0.0.1", false),
+            ("100.64.0.1", false),
+            ("127.0.0.1", false),
+            ("169.254.0.1", false),
+            ("192.0.0.1", false),
+            ("198.18.0.1", false),
+            ("224.0.0.1", false),
+            ("240.0.0.1", false),
+            ("255.255.255.255", false),
+            ("2606:4700::1111", true),
+            ("::1", false),
+            ("fe80::1", false),
+            ("fd00::1", false),
+            ("ff02::1", false),
+            ("ff0e::1", false),
+            ("2001:db8::1", false),
+            ("::ffff:8.8.8.8", false),
+        ] {
+            let parsed: IpAddr = ip.parse().unwrap();
+            assert_eq!(is_global(parsed), global, "{ip}");
+        }
+    }
+}
@@ -24,6 +24,7 @@ use crate::{
 };
 use futures::prelude::*;
 use multiaddr::Multiaddr;
+use std::time::Duration;
 use std::{pin::Pin, task::Context, task::Poll};
 
 use super::ListenerId;
@@ -104,6 +105,18 @@ where
         self.transport.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.transport.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.transport.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.transport.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -21,6 +21,7 @@
 use crate::transport::{ListenerId, Transport, TransportError, TransportEvent};
 use futures::prelude::*;
 use multiaddr::Multiaddr;
+use std::time::Duration;
 use std::{error, pin::Pin, task::Context, task::Poll};
 
 /// See `Transport::map_err`.
@@ -94,6 +95,18 @@ where
         self.transport.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.transport.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.transport.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.transport.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -233,6 +233,10 @@ impl Transport for MemoryTransport {
         None
     }
 
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        parse_memory_addr(addr).is_ok()
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -20,6 +20,7 @@
 
 use crate::transport::{ListenerId, Transport, TransportError, TransportEvent};
 use multiaddr::Multiaddr;
+use std::time::Duration;
 use std::{pin::Pin, task::Context, task::Poll};
 
 /// Transport that is possibly disabled.
@@ -107,6 +108,20 @@ where
         }
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.0.as_ref()?.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.0.as_ref()?.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|inner| inner.supports_dial(addr))
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
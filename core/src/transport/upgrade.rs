@@ -23,15 +23,15 @@
 pub use crate::upgrade::Version;
 
 use crate::{
-    connection::ConnectedPoint,
-    muxing::{StreamMuxer, StreamMuxerBox},
+    connection::{ConnectedPoint, NegotiatedProtocols},
+    muxing::{StreamMuxer, StreamMuxerBox, WithNegotiatedProtocols},
     transport::{
         and_then::AndThen, boxed::boxed, timeout::TransportTimeout, ListenerId, Transport,
         TransportError, TransportEvent,
     },
     upgrade::{
         self, apply_inbound, apply_outbound, InboundConnectionUpgrade, InboundUpgradeApply,
-        OutboundConnectionUpgrade, OutboundUpgradeApply, UpgradeError,
+        NegotiationObserver, OutboundConnectionUpgrade, OutboundUpgradeApply, UpgradeError,
     },
     Negotiated,
 };
@@ -42,6 +42,7 @@ use std::{
     error::Error,
     fmt,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
@@ -69,6 +70,7 @@ use std::{
 pub struct Builder<T> {
     inner: T,
     version: upgrade::Version,
+    observer: Option<Arc<dyn NegotiationObserver>>,
 }
 
 impl<T> Builder<T>
@@ -78,7 +80,18 @@ where
 {
     /// Creates a `Builder` over the given (base) `Transport`.
     pub fn new(inner: T, version: upgrade::Version) -> Builder<T> {
-        Builder { inner, version }
+        Builder {
+            inner,
+            version,
+            observer: None,
+        }
+    }
+
+    /// Reports the negotiations of the security and multiplexing protocols of every connection
+    /// to the given observer.
+    pub fn with_negotiation_observer(mut self, observer: Arc<dyn NegotiationObserver>) -> Self {
+        self.observer = Some(observer);
+        self
     }
 
     /// Upgrades the transport to perform authentication of the remote.
@@ -89,6 +102,9 @@ where
     /// involves the use of a cryptographic authentication protocol in the
     /// context of establishing a secure channel.
     ///
+    /// The negotiated security protocol is recorded and made available on the
+    /// muxer obtained from [`Authenticated::multiplex`].
+    ///
     /// ## Transitions
     ///
     ///   * I/O upgrade: `C -> (PeerId, D)`.
@@ -106,12 +122,14 @@ where
         E: Error + 'static,
     {
         let version = self.version;
-        Authenticated(Builder::new(
-            self.inner.and_then(move |conn, endpoint| Authenticate {
-                inner: upgrade::apply(conn, upgrade, endpoint, version),
+        let observer = self.observer.clone();
+        Authenticated(Builder {
+            inner: self.inner.and_then(move |conn, endpoint| Authenticate {
+                inner: upgrade::apply(conn, upgrade, endpoint, version, observer),
             }),
             version,
-        ))
+            observer: self.observer,
+        })
     }
 }
 
@@ -129,21 +147,25 @@ where
     inner: EitherUpgrade<C, U>,
 }
 
-impl<C, U> Future for Authenticate<C, U>
+impl<C, D, U, E> Future for Authenticate<C, U>
 where
     C: AsyncRead + AsyncWrite + Unpin,
-    U: InboundConnectionUpgrade<Negotiated<C>>
-        + OutboundConnectionUpgrade<
-            Negotiated<C>,
-            Output = <U as InboundConnectionUpgrade<Negotiated<C>>>::Output,
-            Error = <U as InboundConnectionUpgrade<Negotiated<C>>>::Error,
-        >,
+    U: InboundConnectionUpgrade<Negotiated<C>, Output = (PeerId, D), Error = E>,
+    U: OutboundConnectionUpgrade<Negotiated<C>, Output = (PeerId, D), Error = E>,
 {
-    type Output = <EitherUpgrade<C, U> as Future>::Output;
+    type Output = Result<((PeerId, NegotiatedProtocols), D), UpgradeError<E>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-        Future::poll(this.inner, cx)
+        let mut this = self.project();
+        let (peer_id, d) = match ready!(Future::poll(this.inner.as_mut(), cx)) {
+            Ok(v) => v,
+            Err(err) => return Poll::Ready(Err(err)),
+        };
+        let protocols = NegotiatedProtocols {
+            security: negotiated_protocol(this.inner.as_ref().get_ref()),
+            muxer: None,
+        };
+        Poll::Ready(Ok(((peer_id, protocols), d)))
     }
 }
 
@@ -157,7 +179,7 @@ where
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>> + OutboundConnectionUpgrade<Negotiated<C>>,
 {
-    peer_id: Option<PeerId>,
+    authenticated: Option<(PeerId, NegotiatedProtocols)>,
     #[pin]
     upgrade: EitherUpgrade<C, U>,
 }
@@ -168,19 +190,20 @@ where
     U: InboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E>,
     U: OutboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E>,
 {
-    type Output = Result<(PeerId, M), UpgradeError<E>>;
+    type Output = Result<(PeerId, WithNegotiatedProtocols<M>), UpgradeError<E>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-        let m = match ready!(Future::poll(this.upgrade, cx)) {
+        let mut this = self.project();
+        let m = match ready!(Future::poll(this.upgrade.as_mut(), cx)) {
             Ok(m) => m,
             Err(err) => return Poll::Ready(Err(err)),
         };
-        let i = this
-            .peer_id
+        let (i, mut protocols) = this
+            .authenticated
             .take()
             .expect("Multiplex future polled after completion.");
-        Poll::Ready(Ok((i, m)))
+        protocols.muxer = negotiated_protocol(this.upgrade.as_ref().get_ref());
+        Poll::Ready(Ok((i, WithNegotiatedProtocols::new(m, protocols))))
     }
 }
 
@@ -205,17 +228,18 @@ where
     ///   * Transport output: `(PeerId, C) -> (PeerId, D)`.
     pub fn apply<C, D, U, E>(self, upgrade: U) -> Authenticated<Upgrade<T, U>>
     where
-        T: Transport<Output = (PeerId, C)>,
+        T: Transport<Output = ((PeerId, NegotiatedProtocols), C)>,
         C: AsyncRead + AsyncWrite + Unpin,
         D: AsyncRead + AsyncWrite + Unpin,
         U: InboundConnectionUpgrade<Negotiated<C>, Output = D, Error = E>,
         U: OutboundConnectionUpgrade<Negotiated<C>, Output = D, Error = E> + Clone,
         E: Error + 'static,
     {
-        Authenticated(Builder::new(
-            Upgrade::new(self.0.inner, upgrade),
-            self.0.version,
-        ))
+        Authenticated(Builder {
+            inner: Upgrade::new(self.0.inner, upgrade),
+            version: self.0.version,
+            observer: self.0.observer,
+        })
     }
 
     /// Upgrades the transport with a (sub)stream multiplexer.
@@ -224,16 +248,25 @@ where
     /// produce a [`StreamMuxer`] `M`. The transport must already be authenticated.
     /// This ends the (regular) transport upgrade process.
     ///
+    /// The negotiated security and multiplexing protocols are recorded on the
+    /// resulting muxer, see [`StreamMuxer::negotiated_protocols`].
+    ///
     /// ## Transitions
     ///
     ///   * I/O upgrade: `C -> M`.
-    ///   * Transport output: `(PeerId, C) -> (PeerId, M)`.
+    ///   * Transport output: `(PeerId, C) -> (PeerId, WithNegotiatedProtocols<M>)`.
+    #[allow(clippy::type_complexity)]
     pub fn multiplex<C, M, U, E>(
         self,
         upgrade: U,
-    ) -> Multiplexed<AndThen<T, impl FnOnce((PeerId, C), ConnectedPoint) -> Multiplex<C, U> + Clone>>
+    ) -> Multiplexed<
+        AndThen<
+            T,
+            impl FnOnce(((PeerId, NegotiatedProtocols), C), ConnectedPoint) -> Multiplex<C, U> + Clone,
+        >,
+    >
     where
-        T: Transport<Output = (PeerId, C)>,
+        T: Transport<Output = ((PeerId, NegotiatedProtocols), C)>,
         C: AsyncRead + AsyncWrite + Unpin,
         M: StreamMuxer,
         U: InboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E>,
@@ -241,10 +274,11 @@ where
         E: Error + 'static,
     {
         let version = self.0.version;
-        Multiplexed(self.0.inner.and_then(move |(i, c), endpoint| {
-            let upgrade = upgrade::apply(c, upgrade, endpoint, version);
+        let observer = self.0.observer;
+        Multiplexed(self.0.inner.and_then(move |(authenticated, c), endpoint| {
+            let upgrade = upgrade::apply(c, upgrade, endpoint, version, observer);
             Multiplex {
-                peer_id: Some(i),
+                authenticated: Some(authenticated),
                 upgrade,
             }
         }))
@@ -260,13 +294,19 @@ where
     /// ## Transitions
     ///
     ///   * I/O upgrade: `C -> M`.
-    ///   * Transport output: `(PeerId, C) -> (PeerId, M)`.
+    ///   * Transport output: `(PeerId, C) -> (PeerId, WithNegotiatedProtocols<M>)`.
+    #[allow(clippy::type_complexity)]
     pub fn multiplex_ext<C, M, U, E, F>(
         self,
         up: F,
-    ) -> Multiplexed<AndThen<T, impl FnOnce((PeerId, C), ConnectedPoint) -> Multiplex<C, U> + Clone>>
+    ) -> Multiplexed<
+        AndThen<
+            T,
+            impl FnOnce(((PeerId, NegotiatedProtocols), C), ConnectedPoint) -> Multiplex<C, U> + Clone,
+        >,
+    >
     where
-        T: Transport<Output = (PeerId, C)>,
+        T: Transport<Output = ((PeerId, NegotiatedProtocols), C)>,
         C: AsyncRead + AsyncWrite + Unpin,
         M: StreamMuxer,
         U: InboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E>,
@@ -275,13 +315,19 @@ where
         F: for<'a> FnOnce(&'a PeerId, &'a ConnectedPoint) -> U + Clone,
     {
         let version = self.0.version;
-        Multiplexed(self.0.inner.and_then(move |(peer_id, c), endpoint| {
-            let upgrade = upgrade::apply(c, up(&peer_id, &endpoint), endpoint, version);
-            Multiplex {
-                peer_id: Some(peer_id),
-                upgrade,
-            }
-        }))
+        let observer = self.0.observer;
+        Multiplexed(
+            self.0
+                .inner
+                .and_then(move |((peer_id, protocols), c), endpoint| {
+                    let upgrade =
+                        upgrade::apply(c, up(&peer_id, &endpoint), endpoint, version, observer);
+                    Multiplex {
+                        authenticated: Some((peer_id, protocols)),
+                        upgrade,
+                    }
+                }),
+        )
     }
 }
 
@@ -362,6 +408,18 @@ where
         self.0.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.0.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.0.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.0.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -373,6 +431,19 @@ where
 /// An inbound or outbound upgrade.
 type EitherUpgrade<C, U> = future::Either<InboundUpgradeApply<C, U>, OutboundUpgradeApply<C, U>>;
 
+/// Returns the name of the protocol negotiated by the given upgrade, once negotiation completed.
+fn negotiated_protocol<C, U>(upgrade: &EitherUpgrade<C, U>) -> Option<String>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: InboundConnectionUpgrade<Negotiated<C>> + OutboundConnectionUpgrade<Negotiated<C>>,
+{
+    match upgrade {
+        future::Either::Left(inbound) => inbound.negotiated_protocol(),
+        future::Either::Right(outbound) => outbound.negotiated_protocol(),
+    }
+    .map(ToOwned::to_owned)
+}
+
 /// A custom upgrade on an [`Authenticated`] transport.
 ///
 /// See [`Transport::upgrade`]
@@ -390,19 +461,19 @@ impl<T, U> Upgrade<T, U> {
     }
 }
 
-impl<T, C, D, U, E> Transport for Upgrade<T, U>
+impl<T, I, C, D, U, E> Transport for Upgrade<T, U>
 where
-    T: Transport<Output = (PeerId, C)>,
+    T: Transport<Output = (I, C)>,
     T::Error: 'static,
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>, Output = D, Error = E>,
     U: OutboundConnectionUpgrade<Negotiated<C>, Output = D, Error = E> + Clone,
     E: Error + 'static,
 {
-    type Output = (PeerId, D);
+    type Output = (I, D);
     type Error = TransportUpgradeError<T::Error, E>;
-    type ListenerUpgrade = ListenerUpgradeFuture<T::ListenerUpgrade, U, C>;
-    type Dial = DialUpgradeFuture<T::Dial, U, C>;
+    type ListenerUpgrade = ListenerUpgradeFuture<T::ListenerUpgrade, U, C, I>;
+    type Dial = DialUpgradeFuture<T::Dial, U, C, I>;
 
     fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let future = self
@@ -447,6 +518,18 @@ where
         self.inner.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.inner.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.inner.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.inner.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -500,23 +583,23 @@ where
 }
 
 /// The [`Transport::Dial`] future of an [`Upgrade`]d transport.
-pub struct DialUpgradeFuture<F, U, C>
+pub struct DialUpgradeFuture<F, U, C, I = PeerId>
 where
     U: OutboundConnectionUpgrade<Negotiated<C>>,
     C: AsyncRead + AsyncWrite + Unpin,
 {
     future: Pin<Box<F>>,
-    upgrade: future::Either<Option<U>, (PeerId, OutboundUpgradeApply<C, U>)>,
+    upgrade: future::Either<Option<U>, (Option<I>, OutboundUpgradeApply<C, U>)>,
 }
 
-impl<F, U, C, D> Future for DialUpgradeFuture<F, U, C>
+impl<F, U, C, D, I> Future for DialUpgradeFuture<F, U, C, I>
 where
-    F: TryFuture<Ok = (PeerId, C)>,
+    F: TryFuture<Ok = (I, C)>,
     C: AsyncRead + AsyncWrite + Unpin,
     U: OutboundConnectionUpgrade<Negotiated<C>, Output = D>,
     U::Error: Error,
 {
-    type Output = Result<(PeerId, D), TransportUpgradeError<F::Error, U::Error>>;
+    type Output = Result<(I, D), TransportUpgradeError<F::Error, U::Error>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // We use a `this` variable because the compiler can't mutably borrow multiple times
@@ -535,15 +618,19 @@ where
                     let u = up
                         .take()
                         .expect("DialUpgradeFuture is constructed with Either::Left(Some).");
-                    future::Either::Right((i, apply_outbound(c, u, upgrade::Version::V1)))
+                    future::Either::Right((
+                        Some(i),
+                        apply_outbound(c, u, upgrade::Version::V1, None),
+                    ))
                 }
-                future::Either::Right((i, ref mut up)) => {
+                future::Either::Right((ref mut i, ref mut up)) => {
                     let d = match ready!(
                         Future::poll(Pin::new(up), cx).map_err(TransportUpgradeError::Upgrade)
                     ) {
                         Ok(d) => d,
                         Err(err) => return Poll::Ready(Err(err)),
                     };
+                    let i = i.take().expect("Upgrade future polled after completion.");
                     return Poll::Ready(Ok((i, d)));
                 }
             }
@@ -551,7 +638,7 @@ where
     }
 }
 
-impl<F, U, C> Unpin for DialUpgradeFuture<F, U, C>
+impl<F, U, C, I> Unpin for DialUpgradeFuture<F, U, C, I>
 where
     U: OutboundConnectionUpgrade<Negotiated<C>>,
     C: AsyncRead + AsyncWrite + Unpin,
@@ -559,23 +646,23 @@ where
 }
 
 /// The [`Transport::ListenerUpgrade`] future of an [`Upgrade`]d transport.
-pub struct ListenerUpgradeFuture<F, U, C>
+pub struct ListenerUpgradeFuture<F, U, C, I = PeerId>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>>,
 {
     future: Pin<Box<F>>,
-    upgrade: future::Either<Option<U>, (PeerId, InboundUpgradeApply<C, U>)>,
+    upgrade: future::Either<Option<U>, (Option<I>, InboundUpgradeApply<C, U>)>,
 }
 
-impl<F, U, C, D> Future for ListenerUpgradeFuture<F, U, C>
+impl<F, U, C, D, I> Future for ListenerUpgradeFuture<F, U, C, I>
 where
-    F: TryFuture<Ok = (PeerId, C)>,
+    F: TryFuture<Ok = (I, C)>,
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>, Output = D>,
     U::Error: Error,
 {
-    type Output = Result<(PeerId, D), TransportUpgradeError<F::Error, U::Error>>;
+    type Output = Result<(I, D), TransportUpgradeError<F::Error, U::Error>>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // We use a `this` variable because the compiler can't mutably borrow multiple times
@@ -594,15 +681,16 @@ where
                     let u = up
                         .take()
                         .expect("ListenerUpgradeFuture is constructed with Either::Left(Some).");
-                    future::Either::Right((i, apply_inbound(c, u)))
+                    future::Either::Right((Some(i), apply_inbound(c, u, None)))
                 }
-                future::Either::Right((i, ref mut up)) => {
+                future::Either::Right((ref mut i, ref mut up)) => {
                     let d = match ready!(TryFuture::try_poll(Pin::new(up), cx)
                         .map_err(TransportUpgradeError::Upgrade))
                     {
                         Ok(v) => v,
                         Err(err) => return Poll::Ready(Err(err)),
                     };
+                    let i = i.take().expect("Upgrade future polled after completion.");
                     return Poll::Ready(Ok((i, d)));
                 }
             }
@@ -610,7 +698,7 @@ where
     }
 }
 
-impl<F, U, C> Unpin for ListenerUpgradeFuture<F, U, C>
+impl<F, U, C, I> Unpin for ListenerUpgradeFuture<F, U, C, I>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>>,
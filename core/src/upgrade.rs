@@ -75,7 +75,10 @@ pub use self::{
     denied::DeniedUpgrade, pending::PendingUpgrade, ready::ReadyUpgrade, select::SelectUpgrade,
 };
 pub use crate::Negotiated;
-pub use multistream_select::{NegotiatedComplete, NegotiationError, ProtocolError, Version};
+pub use multistream_select::{
+    NegotiatedComplete, Negotiation, NegotiationError, NegotiationObserver, NegotiationRole,
+    ProtocolError, Version,
+};
 
 /// Common trait for upgrades that can be applied on inbound substreams, outbound substreams,
 /// or both.
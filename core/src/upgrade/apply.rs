@@ -21,8 +21,8 @@
 use crate::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeError};
 use crate::{connection::ConnectedPoint, Negotiated};
 use futures::{future::Either, prelude::*};
-use multistream_select::{DialerSelectFuture, ListenerSelectFuture};
-use std::{mem, pin::Pin, task::Context, task::Poll};
+use multistream_select::{DialerSelectFuture, ListenerSelectFuture, NegotiationObserver};
+use std::{mem, pin::Pin, sync::Arc, task::Context, task::Poll};
 
 pub(crate) use multistream_select::Version;
 
@@ -33,6 +33,7 @@ pub(crate) fn apply<C, U>(
     up: U,
     cp: ConnectedPoint,
     v: Version,
+    observer: Option<Arc<dyn NegotiationObserver>>,
 ) -> Either<InboundUpgradeApply<C, U>, OutboundUpgradeApply<C, U>>
 where
     C: AsyncRead + AsyncWrite + Unpin,
@@ -40,47 +41,73 @@ where
 {
     match cp {
         ConnectedPoint::Dialer { role_override, .. } if role_override.is_dialer() => {
-            Either::Right(apply_outbound(conn, up, v))
+            Either::Right(apply_outbound(conn, up, v, observer))
         }
-        _ => Either::Left(apply_inbound(conn, up)),
+        _ => Either::Left(apply_inbound(conn, up, observer)),
     }
 }
 
 /// Tries to perform an upgrade on an inbound connection or substream.
-pub(crate) fn apply_inbound<C, U>(conn: C, up: U) -> InboundUpgradeApply<C, U>
+pub(crate) fn apply_inbound<C, U>(
+    conn: C,
+    up: U,
+    observer: Option<Arc<dyn NegotiationObserver>>,
+) -> InboundUpgradeApply<C, U>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>>,
 {
     InboundUpgradeApply {
+        negotiated: None,
         inner: InboundUpgradeApplyState::Init {
-            future: multistream_select::listener_select_proto(conn, up.protocol_info()),
+            future: multistream_select::listener_select_proto(conn, up.protocol_info())
+                .with_observer(observer),
             upgrade: up,
         },
     }
 }
 
 /// Tries to perform an upgrade on an outbound connection or substream.
-pub(crate) fn apply_outbound<C, U>(conn: C, up: U, v: Version) -> OutboundUpgradeApply<C, U>
+pub(crate) fn apply_outbound<C, U>(
+    conn: C,
+    up: U,
+    v: Version,
+    observer: Option<Arc<dyn NegotiationObserver>>,
+) -> OutboundUpgradeApply<C, U>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: OutboundConnectionUpgrade<Negotiated<C>>,
 {
     OutboundUpgradeApply {
+        negotiated: None,
         inner: OutboundUpgradeApplyState::Init {
-            future: multistream_select::dialer_select_proto(conn, up.protocol_info(), v),
+            future: multistream_select::dialer_select_proto(conn, up.protocol_info(), v)
+                .with_observer(observer),
             upgrade: up,
         },
     }
 }
 
 /// Future returned by `apply_inbound`. Drives the upgrade process.
-pub struct InboundUpgradeApply<C, U>
+pub(crate) struct InboundUpgradeApply<C, U>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: InboundConnectionUpgrade<Negotiated<C>>,
 {
     inner: InboundUpgradeApplyState<C, U>,
+    /// The protocol that was negotiated, once negotiation completed.
+    negotiated: Option<String>,
+}
+
+impl<C, U> InboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: InboundConnectionUpgrade<Negotiated<C>>,
+{
+    /// Returns the name of the negotiated protocol, once negotiation completed.
+    pub(crate) fn negotiated_protocol(&self) -> Option<&str> {
+        self.negotiated.as_deref()
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -128,6 +155,7 @@ where
                             return Poll::Pending;
                         }
                     };
+                    self.negotiated = Some(info.as_ref().to_owned());
                     self.inner = InboundUpgradeApplyState::Upgrade {
                         future: Box::pin(upgrade.upgrade_inbound(io, info.clone())),
                         name: info.as_ref().to_owned(),
@@ -158,12 +186,25 @@ where
 }
 
 /// Future returned by `apply_outbound`. Drives the upgrade process.
-pub struct OutboundUpgradeApply<C, U>
+pub(crate) struct OutboundUpgradeApply<C, U>
 where
     C: AsyncRead + AsyncWrite + Unpin,
     U: OutboundConnectionUpgrade<Negotiated<C>>,
 {
     inner: OutboundUpgradeApplyState<C, U>,
+    /// The protocol that was negotiated, once negotiation completed.
+    negotiated: Option<String>,
+}
+
+impl<C, U> OutboundUpgradeApply<C, U>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: OutboundConnectionUpgrade<Negotiated<C>>,
+{
+    /// Returns the name of the negotiated protocol, once negotiation completed.
+    pub(crate) fn negotiated_protocol(&self) -> Option<&str> {
+        self.negotiated.as_deref()
+    }
 }
 
 enum OutboundUpgradeApplyState<C, U>
@@ -210,6 +251,7 @@ where
                             return Poll::Pending;
                         }
                     };
+                    self.negotiated = Some(info.as_ref().to_owned());
                     self.inner = OutboundUpgradeApplyState::Upgrade {
                         future: Box::pin(upgrade.upgrade_outbound(connection, info.clone())),
                         name: info.as_ref().to_owned(),
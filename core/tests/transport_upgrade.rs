@@ -19,10 +19,12 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures::prelude::*;
+use libp2p_core::muxing::StreamMuxer;
 use libp2p_core::transport::{ListenerId, MemoryTransport, Transport};
 use libp2p_core::upgrade::{
     self, InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo,
 };
+use libp2p_core::NegotiatedProtocols;
 use libp2p_identity as identity;
 use libp2p_mplex::MplexConfig;
 use libp2p_noise as noise;
@@ -115,16 +117,25 @@ fn upgrade_pipeline() {
             else {
                 continue;
             };
-            let (peer, _mplex) = upgrade.await.unwrap();
+            let (peer, mplex) = upgrade.await.unwrap();
             assert_eq!(peer, dialer_id);
+            assert_eq!(mplex.negotiated_protocols(), Some(&expected_protocols()));
         }
     };
 
     let client = async move {
-        let (peer, _mplex) = dialer_transport.dial(listen_addr2).unwrap().await.unwrap();
+        let (peer, mplex) = dialer_transport.dial(listen_addr2).unwrap().await.unwrap();
         assert_eq!(peer, listener_id);
+        assert_eq!(mplex.negotiated_protocols(), Some(&expected_protocols()));
     };
 
     async_std::task::spawn(server);
     async_std::task::block_on(client);
 }
+
+fn expected_protocols() -> NegotiatedProtocols {
+    NegotiatedProtocols {
+        security: Some("/noise".to_owned()),
+        muxer: Some("/mplex/6.7.0".to_owned()),
+    }
+}
@@ -25,7 +25,7 @@ use futures::{executor::block_on, future::FutureExt, stream::StreamExt};
 use libp2p::{
     core::multiaddr::{Multiaddr, Protocol},
     dcutr, identify, identity, noise, ping, relay,
-    swarm::{NetworkBehaviour, SwarmEvent},
+    swarm::{NetworkBehaviour, Reachability, SwarmEvent},
     tcp, yamux, PeerId,
 };
 use std::str::FromStr;
@@ -103,7 +103,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     "/TODO/0.0.1".to_string(),
                     keypair.public(),
                 )),
-                dcutr: dcutr::Behaviour::new(keypair.public().to_peer_id()),
+                dcutr: dcutr::Behaviour::new(keypair.public().to_peer_id())
+                    // Both peers are behind a NAT, we don't run AutoNAT to confirm it.
+                    .with_reachability(Reachability::Private { confidence: 0 }),
             })?
             .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
             .build();
@@ -294,7 +294,10 @@ impl EventLoop {
                     .send(Err(Box::new(error)));
             }
             SwarmEvent::Behaviour(BehaviourEvent::RequestResponse(
-                request_response::Event::ResponseSent { .. },
+                request_response::Event::ResponseSent { .. }
+                | request_response::Event::InboundRequestDropped { .. }
+                | request_response::Event::StoredRequest { .. }
+                | request_response::Event::Subscription { .. },
             )) => {}
             SwarmEvent::NewListenAddr { address, .. } => {
                 let local_peer_id = *self.swarm.local_peer_id();
@@ -27,7 +27,7 @@ use libp2p::swarm::ConnectionId;
 use libp2p::{
     core::multiaddr::{Multiaddr, Protocol},
     dcutr, identify, noise, ping, relay,
-    swarm::{NetworkBehaviour, SwarmEvent},
+    swarm::{NetworkBehaviour, Reachability, SwarmEvent},
     tcp, yamux, Swarm,
 };
 use redis::AsyncCommands;
@@ -77,7 +77,9 @@ async fn main() -> Result<()> {
                     "/hole-punch-tests/1".to_owned(),
                     key.public(),
                 )),
-                dcutr: dcutr::Behaviour::new(key.public().to_peer_id()),
+                dcutr: dcutr::Behaviour::new(key.public().to_peer_id())
+                    // Both peers are behind a NAT, we don't run AutoNAT to confirm it.
+                    .with_reachability(Reachability::Private { confidence: 0 }),
                 ping: ping::Behaviour::new(
                     ping::Config::default().with_interval(Duration::from_secs(1)),
                 ),
@@ -219,6 +221,7 @@ async fn client_listen_on_transport(
         if let SwarmEvent::NewListenAddr {
             listener_id,
             address,
+            ..
         } = swarm.next().await.unwrap()
         {
             if listener_id == expected_listener_id {
@@ -77,7 +77,10 @@ impl DecodingError {
         }
     }
 
-    #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+    #[cfg(any(
+        all(feature = "rsa", not(target_arch = "wasm32")),
+        all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa"))
+    ))]
     pub(crate) fn encoding_unsupported(key_type: &'static str) -> Self {
         Self {
             msg: format!("encoding {key_type} key to Protobuf is unsupported"),
@@ -107,7 +110,10 @@ pub struct SigningError {
 
 /// An error during encoding of key material.
 impl SigningError {
-    #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+    #[cfg(any(
+        all(feature = "rsa", not(target_arch = "wasm32")),
+        all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa"))
+    ))]
     pub(crate) fn new<S: ToString>(msg: S) -> Self {
         Self {
             msg: msg.to_string(),
@@ -115,7 +121,10 @@ impl SigningError {
         }
     }
 
-    #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+    #[cfg(any(
+        all(feature = "rsa", not(target_arch = "wasm32")),
+        all(feature = "webcrypto", feature = "ecdsa")
+    ))]
     pub(crate) fn source(self, source: impl Error + Send + Sync + 'static) -> Self {
         Self {
             source: Some(Box::new(source)),
@@ -57,6 +57,8 @@ use crate::secp256k1;
 
 #[cfg(feature = "ecdsa")]
 use crate::ecdsa;
+#[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+use crate::webcrypto;
 use crate::KeyType;
 
 /// Identity keypair of a node.
@@ -96,6 +98,9 @@ enum KeyPairInner {
     /// An ECDSA keypair.
     #[cfg(feature = "ecdsa")]
     Ecdsa(ecdsa::Keypair),
+    /// A keypair held by WebCrypto.
+    #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+    WebCrypto(webcrypto::Keypair),
 }
 
 impl Keypair {
@@ -174,6 +179,51 @@ impl Keypair {
         })
     }
 
+    /// Deterministically derive an Ed25519 keypair from the given seed and derivation path.
+    ///
+    /// Starting from the seed, each index of the path derives a child secret from the secret of
+    /// its parent via HKDF-SHA256, the last of which becomes the Ed25519 secret key. The same
+    /// seed and path thus always result in the same keypair, while distinct paths result in
+    /// distinct keypairs, e.g. to give each node of a reproducible simulation its own identity.
+    ///
+    /// The keypair is only as secret as the seed, so this is meant for test networks and
+    /// simulations rather than for production identities.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use libp2p_identity as identity;
+    /// let seed = b"simulation 42";
+    /// let nodes = (0..1000)
+    ///     .map(|i| identity::Keypair::ed25519_from_seed_path(seed, &[0, i]))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(
+    ///     nodes[7].public(),
+    ///     identity::Keypair::ed25519_from_seed_path(seed, &[0, 7]).public()
+    /// );
+    /// ```
+    #[cfg(feature = "ed25519")]
+    pub fn ed25519_from_seed_path(seed: &[u8], path: &[u32]) -> Keypair {
+        use zeroize::Zeroize;
+
+        const SALT: &[u8] = b"libp2p-identity-ed25519-seed-path";
+
+        let mut secret = [0u8; 32];
+        hkdf::Hkdf::<sha2::Sha256>::new(Some(SALT), seed)
+            .expand(b"root", &mut secret)
+            .expect("secret.len() == 32");
+        for index in path {
+            let mut parent = secret;
+            hkdf::Hkdf::<sha2::Sha256>::new(Some(SALT), &parent)
+                .expand(&index.to_be_bytes(), &mut secret)
+                .expect("secret.len() == 32");
+            parent.zeroize();
+        }
+
+        Keypair::ed25519_from_bytes(&mut secret).expect("any 32 bytes are a valid ed25519 secret")
+    }
+
     /// Sign a message using the private key of this keypair, producing
     /// a signature that can be verified using the corresponding public key.
     #[allow(unused_variables)]
@@ -187,6 +237,21 @@ impl Keypair {
             KeyPairInner::Secp256k1(ref pair) => Ok(pair.secret().sign(msg)),
             #[cfg(feature = "ecdsa")]
             KeyPairInner::Ecdsa(ref pair) => Ok(pair.secret().sign(msg)),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(_) => Err(SigningError::new(
+                "WebCrypto signs asynchronously, use `Keypair::sign_async`",
+            )),
+        }
+    }
+
+    /// Like [`Keypair::sign`], additionally supporting keypairs held by WebCrypto, which sign
+    /// asynchronously.
+    pub async fn sign_async(&self, msg: &[u8]) -> Result<Vec<u8>, SigningError> {
+        match self.keypair {
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(ref pair) => pair.sign(msg).await,
+            #[allow(unreachable_patterns)]
+            _ => self.sign(msg),
         }
     }
 
@@ -209,6 +274,8 @@ impl Keypair {
             KeyPairInner::Ecdsa(ref pair) => PublicKey {
                 publickey: PublicKeyInner::Ecdsa(pair.public().clone()),
             },
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(ref pair) => pair.public(),
         }
     }
 
@@ -240,6 +307,10 @@ impl Keypair {
                     Type: proto::KeyType::ECDSA,
                     Data: data.secret().encode_der(),
                 },
+                #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+                KeyPairInner::WebCrypto(_) => {
+                    return Err(DecodingError::encoding_unsupported("WebCrypto"))
+                }
             };
 
             let mut buf = Vec::with_capacity(pk.get_size());
@@ -338,12 +409,14 @@ impl Keypair {
             KeyPairInner::Secp256k1(_) => KeyType::Secp256k1,
             #[cfg(feature = "ecdsa")]
             KeyPairInner::Ecdsa(_) => KeyType::Ecdsa,
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(ref pair) => pair.public().key_type(),
         }
     }
 
     /// Deterministically derive a new secret from this [`Keypair`], taking into account the provided domain.
     ///
-    /// This works for all key types except RSA where it returns `None`.
+    /// This works for all key types except RSA and keys held by WebCrypto where it returns `None`.
     ///
     /// # Example
     ///
@@ -400,6 +473,8 @@ impl Keypair {
                     .try_into()
                     .expect("Ecdsa's private key should be 32 bytes"),
             ),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(_) => None,
         }
     }
 }
@@ -431,6 +506,15 @@ impl From<secp256k1::Keypair> for Keypair {
     }
 }
 
+#[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+impl From<webcrypto::Keypair> for Keypair {
+    fn from(kp: webcrypto::Keypair) -> Self {
+        Keypair {
+            keypair: KeyPairInner::WebCrypto(kp),
+        }
+    }
+}
+
 #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
 impl From<rsa::Keypair> for Keypair {
     fn from(kp: rsa::Keypair) -> Self {
@@ -453,6 +537,10 @@ impl TryInto<ed25519::Keypair> for Keypair {
             KeyPairInner::Secp256k1(_) => Err(OtherVariantError::new(crate::KeyType::Secp256k1)),
             #[cfg(feature = "ecdsa")]
             KeyPairInner::Ecdsa(_) => Err(OtherVariantError::new(crate::KeyType::Ecdsa)),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(inner) => {
+                Err(OtherVariantError::new(inner.public().key_type()))
+            }
         }
     }
 }
@@ -470,6 +558,10 @@ impl TryInto<ecdsa::Keypair> for Keypair {
             KeyPairInner::Rsa(_) => Err(OtherVariantError::new(crate::KeyType::RSA)),
             #[cfg(feature = "secp256k1")]
             KeyPairInner::Secp256k1(_) => Err(OtherVariantError::new(crate::KeyType::Secp256k1)),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(inner) => {
+                Err(OtherVariantError::new(inner.public().key_type()))
+            }
         }
     }
 }
@@ -487,6 +579,10 @@ impl TryInto<secp256k1::Keypair> for Keypair {
             KeyPairInner::Rsa(_) => Err(OtherVariantError::new(crate::KeyType::RSA)),
             #[cfg(feature = "ecdsa")]
             KeyPairInner::Ecdsa(_) => Err(OtherVariantError::new(crate::KeyType::Ecdsa)),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(inner) => {
+                Err(OtherVariantError::new(inner.public().key_type()))
+            }
         }
     }
 }
@@ -504,6 +600,10 @@ impl TryInto<rsa::Keypair> for Keypair {
             KeyPairInner::Secp256k1(_) => Err(OtherVariantError::new(crate::KeyType::Secp256k1)),
             #[cfg(feature = "ecdsa")]
             KeyPairInner::Ecdsa(_) => Err(OtherVariantError::new(crate::KeyType::Ecdsa)),
+            #[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+            KeyPairInner::WebCrypto(inner) => {
+                Err(OtherVariantError::new(inner.public().key_type()))
+            }
         }
     }
 }
@@ -815,6 +915,82 @@ impl From<rsa::PublicKey> for PublicKey {
     }
 }
 
+/// Serializes the protobuf encoding of the key, as base58 string for human-readable formats.
+#[cfg(all(
+    feature = "serde",
+    any(
+        feature = "ecdsa",
+        feature = "secp256k1",
+        feature = "ed25519",
+        feature = "rsa"
+    )
+))]
+impl serde::Serialize for PublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&bs58::encode(self.encode_protobuf()).into_string())
+        } else {
+            serializer.serialize_bytes(&self.encode_protobuf())
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "serde",
+    any(
+        feature = "ecdsa",
+        feature = "secp256k1",
+        feature = "ed25519",
+        feature = "rsa"
+    )
+))]
+impl<'de> serde::Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::*;
+
+        struct PublicKeyVisitor;
+
+        impl<'de> Visitor<'de> for PublicKeyVisitor {
+            type Value = PublicKey;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "valid public key")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                PublicKey::try_decode_protobuf(v)
+                    .map_err(|_| Error::invalid_value(Unexpected::Bytes(v), &self))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                bs58::decode(v)
+                    .into_vec()
+                    .ok()
+                    .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+                    .ok_or_else(|| Error::invalid_value(Unexpected::Str(v), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(PublicKeyVisitor)
+        } else {
+            deserializer.deserialize_bytes(PublicKeyVisitor)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -836,6 +1012,29 @@ mod tests {
         roundtrip_protobuf_encoding(&priv_key, &pub_key, KeyType::Ed25519);
     }
 
+    #[test]
+    #[cfg(all(feature = "ed25519", feature = "peerid"))]
+    fn ed25519_from_seed_path_is_deterministic() {
+        let key = Keypair::ed25519_from_seed_path(b"seed", &[1, 2]);
+
+        assert_eq!(
+            key.public().to_peer_id().to_base58(),
+            "12D3KooWBJY3RcjELToR34qX7YqynDseHFKWX5yfd6mCykoNKoCB"
+        );
+        assert_eq!(
+            key.public(),
+            Keypair::ed25519_from_seed_path(b"seed", &[1, 2]).public()
+        );
+        for other in [
+            Keypair::ed25519_from_seed_path(b"seed", &[2, 1]),
+            Keypair::ed25519_from_seed_path(b"seed", &[1]),
+            Keypair::ed25519_from_seed_path(b"seed", &[1, 2, 0]),
+            Keypair::ed25519_from_seed_path(b"other seed", &[1, 2]),
+        ] {
+            assert_ne!(key.public(), other.public());
+        }
+    }
+
     #[test]
     #[cfg(all(feature = "ecdsa", feature = "peerid"))]
     fn keypair_protobuf_roundtrip_ecdsa() {
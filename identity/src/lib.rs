@@ -58,6 +58,9 @@ pub mod rsa;
 #[cfg(feature = "secp256k1")]
 pub mod secp256k1;
 
+#[cfg(all(feature = "webcrypto", any(feature = "ed25519", feature = "ecdsa")))]
+pub mod webcrypto;
+
 mod error;
 mod keypair;
 #[cfg(feature = "peerid")]
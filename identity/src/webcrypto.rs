@@ -0,0 +1,300 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Keys held by the [WebCrypto API] of the browser.
+//!
+//! The private key of a [`Keypair`] is a non-extractable [`CryptoKey`]: its material never
+//! enters the memory of the wasm module or JavaScript. The keys can be persisted in IndexedDB
+//! as they are, see [`Keypair::to_crypto_key_pair`] and [`Keypair::from_crypto_key_pair`].
+//!
+//! A [`Keypair`] converts into a [`crate::Keypair`], e.g. to derive the
+//! [`PeerId`](crate::PeerId) or to pass it to the `SwarmBuilder`. WebCrypto signs asynchronously,
+//! thus [`crate::Keypair::sign`] fails for such a keypair, sign via
+//! [`crate::Keypair::sign_async`] instead. Signatures are encoded like those of the respective
+//! [`crate::Keypair`] and verified via [`PublicKey::verify`].
+//!
+//! The module compiles on all targets, WebCrypto is only available in a browser or worker
+//! though.
+//!
+//! [WebCrypto API]: https://developer.mozilla.org/en-US/docs/Web/API/Web_Crypto_API
+
+use std::fmt;
+
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use send_wrapper::SendWrapper;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Crypto, CryptoKey, CryptoKeyPair, SubtleCrypto};
+
+use crate::error::SigningError;
+use crate::PublicKey;
+
+/// The signature algorithm of a [`Keypair`].
+///
+/// Support for Ed25519 in WebCrypto varies between browsers, ECDSA with the P-256 curve is
+/// universally supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// Ed25519, compatible with [`crate::ed25519`].
+    #[cfg(feature = "ed25519")]
+    Ed25519,
+    /// ECDSA with the P-256 curve and SHA-256, compatible with [`crate::ecdsa`].
+    #[cfg(feature = "ecdsa")]
+    EcdsaP256,
+}
+
+impl Algorithm {
+    /// The `algorithm` parameter of `generateKey()`.
+    fn key_params(&self) -> Object {
+        match self {
+            #[cfg(feature = "ed25519")]
+            Algorithm::Ed25519 => object(&[("name", "Ed25519")]),
+            #[cfg(feature = "ecdsa")]
+            Algorithm::EcdsaP256 => object(&[("name", "ECDSA"), ("namedCurve", "P-256")]),
+        }
+    }
+
+    /// The `algorithm` parameter of `sign()`.
+    fn sign_params(&self) -> Object {
+        match self {
+            #[cfg(feature = "ed25519")]
+            Algorithm::Ed25519 => object(&[("name", "Ed25519")]),
+            #[cfg(feature = "ecdsa")]
+            Algorithm::EcdsaP256 => object(&[("name", "ECDSA"), ("hash", "SHA-256")]),
+        }
+    }
+
+    /// Determines the algorithm of the given key.
+    fn of(key: &CryptoKey) -> Result<Self, Error> {
+        let algorithm = key.algorithm().map_err(Error::js)?;
+        let name = Reflect::get(&algorithm, &"name".into())
+            .map_err(Error::js)?
+            .as_string();
+        let curve = Reflect::get(&algorithm, &"namedCurve".into())
+            .map_err(Error::js)?
+            .as_string();
+
+        match (name.as_deref(), curve.as_deref()) {
+            #[cfg(feature = "ed25519")]
+            (Some("Ed25519"), _) => Ok(Algorithm::Ed25519),
+            #[cfg(feature = "ecdsa")]
+            (Some("ECDSA"), Some("P-256")) => Ok(Algorithm::EcdsaP256),
+            _ => Err(Error::new(format!(
+                "unsupported key algorithm {name:?} with curve {curve:?}"
+            ))),
+        }
+    }
+
+    /// Decodes the public key exported in the format returned by [`Algorithm::export_format`].
+    fn decode_public_key(&self, bytes: &[u8]) -> Result<PublicKey, Error> {
+        let public = match self {
+            #[cfg(feature = "ed25519")]
+            Algorithm::Ed25519 => crate::ed25519::PublicKey::try_from_bytes(bytes)
+                .map(PublicKey::from)
+                .map_err(|e| Error::new(e.to_string()))?,
+            #[cfg(feature = "ecdsa")]
+            Algorithm::EcdsaP256 => crate::ecdsa::PublicKey::try_decode_der(bytes)
+                .map(PublicKey::from)
+                .map_err(|e| Error::new(e.to_string()))?,
+        };
+
+        Ok(public)
+    }
+
+    /// The format to export the public key in.
+    fn export_format(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "ed25519")]
+            Algorithm::Ed25519 => "raw",
+            #[cfg(feature = "ecdsa")]
+            Algorithm::EcdsaP256 => "spki",
+        }
+    }
+
+    /// Converts a signature returned by WebCrypto to the encoding of [`crate::Keypair::sign`].
+    fn encode_signature(&self, signature: Vec<u8>) -> Result<Vec<u8>, SigningError> {
+        match self {
+            #[cfg(feature = "ed25519")]
+            Algorithm::Ed25519 => Ok(signature),
+            // WebCrypto returns the concatenated `r` and `s`, libp2p uses DER.
+            #[cfg(feature = "ecdsa")]
+            Algorithm::EcdsaP256 => p256::ecdsa::Signature::from_slice(&signature)
+                .map(|signature| signature.to_der().as_bytes().to_vec())
+                .map_err(|e| SigningError::new("invalid ECDSA signature").source(e)),
+        }
+    }
+}
+
+/// A keypair whose private key is held by WebCrypto.
+///
+/// JavaScript values can't be shared across threads, thus the keys may only be used on the
+/// thread that created the keypair.
+#[derive(Clone)]
+pub struct Keypair {
+    algorithm: Algorithm,
+    private: SendWrapper<CryptoKey>,
+    public_key: SendWrapper<CryptoKey>,
+    public: PublicKey,
+}
+
+impl Keypair {
+    /// Generates a new keypair with a non-extractable private key.
+    pub async fn generate(algorithm: Algorithm) -> Result<Keypair, Error> {
+        let usages = Array::of2(&"sign".into(), &"verify".into());
+        let pair = JsFuture::from(
+            subtle()?
+                .generate_key_with_object(&algorithm.key_params(), false, &usages)
+                .map_err(Error::js)?,
+        )
+        .await
+        .map_err(Error::js)?;
+
+        Keypair::from_crypto_key_pair(pair.unchecked_ref()).await
+    }
+
+    /// Creates a keypair from the given WebCrypto keys, e.g. as loaded from IndexedDB.
+    ///
+    /// The private key has to support signing, the public key has to be extractable.
+    pub async fn from_crypto_key_pair(pair: &CryptoKeyPair) -> Result<Keypair, Error> {
+        let private: CryptoKey = key_of(pair, "privateKey")?;
+        let public_key: CryptoKey = key_of(pair, "publicKey")?;
+
+        let algorithm = Algorithm::of(&private)?;
+        if Algorithm::of(&public_key)? != algorithm {
+            return Err(Error::new(
+                "private and public key use different algorithms",
+            ));
+        }
+
+        let exported = JsFuture::from(
+            subtle()?
+                .export_key(algorithm.export_format(), &public_key)
+                .map_err(Error::js)?,
+        )
+        .await
+        .map_err(Error::js)?;
+        let public = algorithm.decode_public_key(&Uint8Array::new(&exported).to_vec())?;
+
+        Ok(Keypair {
+            algorithm,
+            private: SendWrapper::new(private),
+            public_key: SendWrapper::new(public_key),
+            public,
+        })
+    }
+
+    /// Returns the WebCrypto keys, e.g. to store them in IndexedDB.
+    pub fn to_crypto_key_pair(&self) -> CryptoKeyPair {
+        CryptoKeyPair::new(&self.private, &self.public_key)
+    }
+
+    /// The signature algorithm of this keypair.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Get the public key of this keypair.
+    pub fn public(&self) -> PublicKey {
+        self.public.clone()
+    }
+
+    /// Sign a message using the private key of this keypair, producing a signature that can be
+    /// verified using the corresponding public key.
+    pub async fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, SigningError> {
+        let promise = subtle()
+            .map_err(|e| SigningError::new(e.msg))?
+            .sign_with_object_and_u8_array(&self.algorithm.sign_params(), &self.private, msg)
+            .map_err(|e| SigningError::new(js_error_message(&e)))?;
+        // No JavaScript value is held across the `await`, the future is thus `Send`.
+        let signature = SendWrapper::new(JsFuture::from(promise))
+            .await
+            .map(|signature| Uint8Array::new(&signature).to_vec())
+            .map_err(|e| SigningError::new(js_error_message(&e)))?;
+
+        self.algorithm.encode_signature(signature)
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("algorithm", &self.algorithm)
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An error of the WebCrypto API.
+#[derive(Debug)]
+pub struct Error {
+    msg: String,
+}
+
+impl Error {
+    fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+
+    fn js(value: JsValue) -> Self {
+        Self::new(js_error_message(&value))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebCrypto error: {}", self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns the `SubtleCrypto` interface of the window or worker.
+fn subtle() -> Result<SubtleCrypto, Error> {
+    let crypto = Reflect::get(&js_sys::global(), &"crypto".into()).map_err(Error::js)?;
+    if crypto.is_undefined() {
+        return Err(Error::new("WebCrypto is not available"));
+    }
+
+    Ok(crypto.unchecked_into::<Crypto>().subtle())
+}
+
+fn key_of(pair: &CryptoKeyPair, field: &str) -> Result<CryptoKey, Error> {
+    Reflect::get(pair, &field.into())
+        .map_err(Error::js)?
+        .dyn_into()
+        .map_err(|_| Error::new(format!("`{field}` is no `CryptoKey`")))
+}
+
+fn object(fields: &[(&str, &str)]) -> Object {
+    let object = Object::new();
+    for (key, value) in fields {
+        Reflect::set(&object, &(*key).into(), &(*value).into())
+            .expect("setting a property of a new object to succeed");
+    }
+    object
+}
+
+fn js_error_message(value: &JsValue) -> String {
+    value
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| value.as_string())
+        .unwrap_or_else(|| format!("{value:?}"))
+}
@@ -2,7 +2,7 @@
 
 use std::str::FromStr;
 
-use libp2p_identity::PeerId;
+use libp2p_identity::{PeerId, PublicKey};
 
 #[test]
 pub fn serialize_peer_id_json() {
@@ -48,3 +48,43 @@ pub fn deserialize_peer_id_msgpack() {
 
     assert_eq!(peer_id, rmp_serde::from_read(&mut &buf[..]).unwrap());
 }
+
+#[cfg(feature = "ed25519")]
+const PUBLIC_KEY_BS58: &str = "4XTTM3DMhRitSML5JfMaThJcJuY6chcU1tDWYLszbX1QeNpbw";
+
+#[cfg(feature = "ed25519")]
+fn public_key() -> PublicKey {
+    PublicKey::try_decode_protobuf(&[
+        0x08, 0x01, 0x12, 0x20, 0x1e, 0xd1, 0xe8, 0xfa, 0xe2, 0xc4, 0xa1, 0x44, 0xb8, 0xbe, 0x8f,
+        0xd4, 0xb4, 0x7b, 0xf3, 0xd3, 0xb3, 0x4b, 0x87, 0x1c, 0x3c, 0xac, 0xf6, 0x01, 0x0f, 0x0e,
+        0x42, 0xd4, 0x74, 0xfc, 0xe2, 0x7e,
+    ])
+    .unwrap()
+}
+
+#[test]
+#[cfg(feature = "ed25519")]
+pub fn public_key_json_roundtrip() {
+    let json = serde_json::to_string(&public_key()).unwrap();
+    assert_eq!(json, format!(r#""{PUBLIC_KEY_BS58}""#));
+    assert_eq!(
+        public_key(),
+        serde_json::from_str::<PublicKey>(&json).unwrap()
+    );
+}
+
+#[test]
+#[cfg(feature = "ed25519")]
+pub fn public_key_msgpack_roundtrip() {
+    let buf = rmp_serde::to_vec(&public_key()).unwrap();
+    assert_eq!(&buf[..2], &[0xc4, 36]); // msgpack buffer header
+    assert_eq!(&buf[2..], &public_key().encode_protobuf()[..]);
+    assert_eq!(public_key(), rmp_serde::from_read(&mut &buf[..]).unwrap());
+}
+
+#[test]
+#[cfg(feature = "ed25519")]
+pub fn public_key_rejects_invalid_encoding() {
+    assert!(serde_json::from_str::<PublicKey>(r#""3yZe7d""#).is_err());
+    assert!(rmp_serde::from_slice::<PublicKey>(&[0xc4, 2, 0x08, 0x01]).is_err());
+}
@@ -110,6 +110,7 @@ pub async fn run_test(
                 if let Some(SwarmEvent::NewListenAddr {
                     listener_id,
                     address,
+                    ..
                 }) = swarm.next().await
                 {
                     if address.to_string().contains("127.0.0.1") {
@@ -20,7 +20,8 @@
 
 #![allow(deprecated)]
 
-use crate::core::muxing::{StreamMuxer, StreamMuxerEvent};
+use crate::core::muxing::{CloseReason, DatagramError, StreamMuxer, StreamMuxerEvent};
+use crate::core::NegotiatedProtocols;
 
 use futures::{
     io::{IoSlice, IoSliceMut},
@@ -100,6 +101,43 @@ where
         let this = self.project();
         this.inner.poll_close(cx)
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        let this = self.project();
+        this.inner.set_close_reason(reason)
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.inner.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        let this = self.project();
+        let num_bytes = data.len();
+        this.inner.send_datagram(data)?;
+        this.sinks.outbound.fetch_add(
+            u64::try_from(num_bytes).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        let this = self.project();
+        let datagram = ready!(this.inner.poll_datagram(cx))?;
+        this.sinks.inbound.fetch_add(
+            u64::try_from(datagram.len()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        Poll::Ready(Ok(datagram))
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        self.inner.negotiated_protocols()
+    }
 }
 
 /// Allows obtaining the average bandwidth of the streams.
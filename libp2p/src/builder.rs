@@ -119,6 +119,72 @@ mod tests {
             .build();
     }
 
+    #[test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux"
+    ))]
+    fn tcp_defaults() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp_defaults()
+            .unwrap()
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "async-std",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux"
+    ))]
+    fn async_std_tcp_defaults() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_async_std()
+            .with_tcp_defaults()
+            .unwrap()
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .build();
+    }
+
+    #[tokio::test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+        feature = "quic",
+        feature = "dns",
+        feature = "relay"
+    ))]
+    async fn tcp_quic_dns_defaults() {
+        #[derive(libp2p_swarm::NetworkBehaviour)]
+        #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+        struct Behaviour {
+            dummy: libp2p_swarm::dummy::Behaviour,
+            relay: libp2p_relay::client::Behaviour,
+        }
+
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp_quic_dns_defaults()
+            .unwrap()
+            .with_relay_client(libp2p_noise::Config::new, libp2p_yamux::Config::default)
+            .unwrap()
+            .with_behaviour(|_, relay| Behaviour {
+                dummy: libp2p_swarm::dummy::Behaviour,
+                relay,
+            })
+            .unwrap()
+            .build();
+    }
+
     #[test]
     #[cfg(all(feature = "tokio", feature = "quic"))]
     fn quic() {
@@ -98,6 +98,97 @@ macro_rules! impl_tcp_builder {
 impl_tcp_builder!("async-std", super::provider::AsyncStd, async_io);
 impl_tcp_builder!("tokio", super::provider::Tokio, tokio);
 
+// Presets
+macro_rules! impl_tcp_defaults {
+    ($providerPascalCase:ty) => {
+        #[cfg(all(
+            not(target_arch = "wasm32"),
+            feature = "tcp",
+            feature = "noise",
+            feature = "yamux",
+        ))]
+        impl SwarmBuilder<$providerPascalCase, TcpPhase> {
+            /// Adds a TCP based transport with the default configuration, authenticated via
+            /// Noise and multiplexed via Yamux.
+            ///
+            /// Shorthand for:
+            ///
+            /// ``` rust,ignore
+            /// .with_tcp(
+            ///     Default::default(),
+            ///     libp2p_noise::Config::new,
+            ///     libp2p_yamux::Config::default,
+            /// )
+            /// ```
+            pub fn with_tcp_defaults(
+                self,
+            ) -> Result<
+                SwarmBuilder<
+                    $providerPascalCase,
+                    QuicPhase<impl AuthenticatedMultiplexedTransport>,
+                >,
+                libp2p_noise::Error,
+            > {
+                self.with_tcp(
+                    Default::default(),
+                    libp2p_noise::Config::new,
+                    libp2p_yamux::Config::default,
+                )
+            }
+        }
+    };
+}
+
+#[cfg(feature = "async-std")]
+impl_tcp_defaults!(super::provider::AsyncStd);
+#[cfg(feature = "tokio")]
+impl_tcp_defaults!(super::provider::Tokio);
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "tokio",
+    feature = "tcp",
+    feature = "noise",
+    feature = "yamux",
+    feature = "quic",
+    feature = "dns",
+))]
+impl SwarmBuilder<super::provider::Tokio, TcpPhase> {
+    /// Adds TCP, authenticated via Noise and multiplexed via Yamux, and QUIC, both wrapped in
+    /// DNS resolution using the system's configuration, all with their default configuration.
+    ///
+    /// The relay client and bandwidth metrics are not included. Add them via
+    /// [`SwarmBuilder::with_relay_client`] and [`SwarmBuilder::with_bandwidth_metrics`], or
+    /// continue directly with [`SwarmBuilder::with_behaviour`].
+    ///
+    /// ``` rust
+    /// # use libp2p::SwarmBuilder;
+    /// # use std::error::Error;
+    /// # async fn build_swarm() -> Result<(), Box<dyn Error>> {
+    /// let swarm = SwarmBuilder::with_new_identity()
+    ///     .with_tokio()
+    ///     .with_tcp_quic_dns_defaults()?
+    ///     .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)?
+    ///     .build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_tcp_quic_dns_defaults(
+        self,
+    ) -> Result<
+        SwarmBuilder<
+            super::provider::Tokio,
+            WebsocketPhase<impl AuthenticatedMultiplexedTransport>,
+        >,
+        std::io::Error,
+    > {
+        self.with_tcp_defaults()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .with_quic()
+            .with_dns()
+    }
+}
+
 impl<Provider> SwarmBuilder<Provider, TcpPhase> {
     pub(crate) fn without_tcp(
         self,
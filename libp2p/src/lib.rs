@@ -44,8 +44,14 @@ pub use libp2p_allow_block_list as allow_block_list;
 #[cfg(feature = "autonat")]
 #[doc(inline)]
 pub use libp2p_autonat as autonat;
+#[cfg(feature = "bitswap")]
+#[doc(inline)]
+pub use libp2p_bitswap as bitswap;
 #[doc(inline)]
 pub use libp2p_connection_limits as connection_limits;
+#[cfg(feature = "connection-manager")]
+#[doc(inline)]
+pub use libp2p_connection_manager as connection_manager;
 #[doc(inline)]
 pub use libp2p_core as core;
 #[cfg(feature = "dcutr")]
@@ -59,6 +65,11 @@ pub use libp2p_dns as dns;
 #[cfg(feature = "floodsub")]
 #[doc(inline)]
 pub use libp2p_floodsub as floodsub;
+#[cfg(feature = "gateway")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gateway")))]
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(inline)]
+pub use libp2p_gateway as gateway;
 #[cfg(feature = "gossipsub")]
 #[doc(inline)]
 pub use libp2p_gossipsub as gossipsub;
@@ -146,6 +157,9 @@ mod transport_ext;
 
 pub mod bandwidth;
 
+#[cfg(feature = "nat-traversal")]
+pub mod nat_traversal;
+
 #[cfg(doc)]
 pub mod tutorials;
 
@@ -0,0 +1,665 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] coordinating the individual NAT traversal protocols.
+//!
+//! Getting a node behind a NAT reachable requires several protocols to work together:
+//!
+//! 1. [`autonat`] determines whether we are publicly reachable.
+//! 2. If we are not, [`upnp`](crate::upnp) (if enabled) tries to map a port on the gateway.
+//! 3. If that is not possible either, the [`relay`] client obtains reservations on the configured relays.
+//! 4. Once peers connect to us through a relay, [`dcutr`] attempts to hole-punch a direct connection.
+//!
+//! [`Behaviour`] sequences these steps and exposes the result as a single [`Reachability`] state,
+//! reporting each transition via [`Event`].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::ListenerId;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+#[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+use libp2p_swarm::behaviour::toggle::Toggle;
+use libp2p_swarm::behaviour::ListenerClosed;
+use libp2p_swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, ListenOpts, NetworkBehaviour, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+
+use crate::{autonat, dcutr, relay};
+
+/// Configuration of the NAT traversal [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    autonat: autonat::Config,
+    relays: Vec<Multiaddr>,
+    relay_relisten_delay: Duration,
+    #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+    upnp: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            autonat: Default::default(),
+            relays: Vec::new(),
+            relay_relisten_delay: Duration::from_secs(10),
+            #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+            upnp: false,
+        }
+    }
+}
+
+impl Config {
+    /// Creates a new configuration without any relays and with UPnP disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the configuration of the underlying [`autonat::Behaviour`].
+    pub fn with_autonat_config(mut self, config: autonat::Config) -> Self {
+        self.autonat = config;
+        self
+    }
+
+    /// Adds a relay on which a reservation is made once we are found to be private.
+    ///
+    /// The address must contain the relay's `/p2p` component.
+    pub fn with_relay(mut self, address: Multiaddr) -> Self {
+        self.relays.push(address);
+        self
+    }
+
+    /// Sets the delay after which we listen on a relay again once its listener closed, e.g.
+    /// because the relay went away, while we are still not publicly reachable.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_relay_relisten_delay(mut self, delay: Duration) -> Self {
+        self.relay_relisten_delay = delay;
+        self
+    }
+
+    /// Enables port mapping via UPnP before falling back to relays.
+    #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+    pub fn with_upnp(mut self) -> Self {
+        self.upnp = true;
+        self
+    }
+}
+
+/// The reachability of the local node as determined by the NAT traversal [`Behaviour`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reachability {
+    /// Reachability has not been determined yet.
+    Unknown,
+    /// The node is directly reachable on the given address.
+    Public(Multiaddr),
+    /// The node is not directly reachable and has no relay reservation (yet).
+    Private,
+    /// The node is not directly reachable but holds a reservation on at least one relay.
+    Relayed,
+}
+
+/// Events emitted by the NAT traversal [`Behaviour`].
+#[derive(Debug)]
+pub enum Event {
+    /// The reachability of the local node changed.
+    ReachabilityChanged {
+        old: Reachability,
+        new: Reachability,
+    },
+    /// A port mapping was created on the gateway.
+    PortMapped(Multiaddr),
+    /// No port mapping could be created on the gateway.
+    PortMappingFailed,
+    /// A reservation on a relay was accepted.
+    ReservationAccepted {
+        relay_peer_id: PeerId,
+        renewal: bool,
+    },
+    /// A direct connection to a peer connected through a relay was established.
+    HolePunchSucceeded {
+        remote_peer_id: PeerId,
+        connection_id: ConnectionId,
+    },
+    /// Hole-punching a direct connection to a peer connected through a relay failed.
+    HolePunchFailed {
+        remote_peer_id: PeerId,
+        error: dcutr::Error,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    not(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+enum PortMapping {
+    Disabled,
+    Pending,
+    Mapped,
+    Failed,
+}
+
+mod inner {
+    #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+    use libp2p_swarm::behaviour::toggle::Toggle;
+
+    use crate::{autonat, dcutr, relay};
+
+    /// The composition of the individual protocols, exposed only through
+    /// [`NetworkBehaviour::ConnectionHandler`](libp2p_swarm::NetworkBehaviour::ConnectionHandler).
+    #[derive(libp2p_swarm::NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    pub struct Inner {
+        pub(super) autonat: autonat::Behaviour,
+        pub(super) relay_client: relay::client::Behaviour,
+        pub(super) dcutr: dcutr::Behaviour,
+        #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+        pub(super) upnp: Toggle<crate::upnp::tokio::Behaviour>,
+    }
+}
+
+use inner::{Inner, InnerEvent};
+
+/// [`NetworkBehaviour`] sequencing [`autonat`], UPnP, the [`relay`] client and [`dcutr`].
+///
+/// The [`relay::client::Behaviour`] passed to [`Behaviour::new`] must be created together with
+/// the relay client transport used by the [`Swarm`](crate::Swarm), e.g. via
+/// [`SwarmBuilder::with_relay_client`](crate::SwarmBuilder).
+pub struct Behaviour {
+    inner: Inner,
+
+    relays: Vec<Multiaddr>,
+    reachability: Reachability,
+    port_mapping: PortMapping,
+
+    /// The listeners we opened on the configured relays.
+    relay_listeners: HashMap<ListenerId, Multiaddr>,
+    /// Relays we currently hold a reservation on.
+    reservations: HashSet<PeerId>,
+    relay_relisten_delay: Duration,
+    /// Fires once we should listen again on the relays whose listeners closed.
+    relay_relisten: Option<Delay>,
+
+    pending_actions: VecDeque<ToSwarm<Event, THandlerInEvent<Inner>>>,
+}
+
+impl Behaviour {
+    /// Creates a new NAT traversal [`Behaviour`] with the given [`Config`].
+    ///
+    /// The `relay_client` is the [`relay::client::Behaviour`] created together with the relay
+    /// client transport of the [`Swarm`](crate::Swarm).
+    pub fn new(
+        local_peer_id: PeerId,
+        config: Config,
+        relay_client: relay::client::Behaviour,
+    ) -> Self {
+        #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+        let (upnp, port_mapping) = if config.upnp {
+            (Toggle::from(Some(Default::default())), PortMapping::Pending)
+        } else {
+            (Toggle::from(None), PortMapping::Disabled)
+        };
+        #[cfg(not(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32"))))]
+        let port_mapping = PortMapping::Disabled;
+
+        Self {
+            inner: Inner {
+                autonat: autonat::Behaviour::new(local_peer_id, config.autonat),
+                relay_client,
+                dcutr: dcutr::Behaviour::new(local_peer_id),
+                #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+                upnp,
+            },
+            relays: config.relays,
+            reachability: Reachability::Unknown,
+            port_mapping,
+            relay_listeners: HashMap::new(),
+            reservations: HashSet::new(),
+            relay_relisten_delay: config.relay_relisten_delay,
+            relay_relisten: None,
+            pending_actions: VecDeque::new(),
+        }
+    }
+
+    /// The current reachability of the local node.
+    pub fn reachability(&self) -> &Reachability {
+        &self.reachability
+    }
+
+    /// Mutable access to the underlying [`autonat::Behaviour`], e.g. to add servers.
+    pub fn autonat_mut(&mut self) -> &mut autonat::Behaviour {
+        &mut self.inner.autonat
+    }
+
+    fn set_reachability(&mut self, new: Reachability) {
+        if self.reachability == new {
+            return;
+        }
+
+        let old = std::mem::replace(&mut self.reachability, new.clone());
+        self.pending_actions
+            .push_back(ToSwarm::GenerateEvent(Event::ReachabilityChanged {
+                old,
+                new,
+            }));
+    }
+
+    /// Listen on all configured relays we aren't already listening on.
+    fn listen_on_relays(&mut self) {
+        for relay in &self.relays {
+            if self.relay_listeners.values().any(|a| a == relay) {
+                continue;
+            }
+
+            let opts = ListenOpts::new(relay.clone().with(Protocol::P2pCircuit));
+            self.relay_listeners
+                .insert(opts.listener_id(), relay.clone());
+            self.pending_actions.push_back(ToSwarm::ListenOn { opts });
+        }
+    }
+
+    fn remove_relay_listeners(&mut self) {
+        for (id, _) in self.relay_listeners.drain() {
+            self.pending_actions
+                .push_back(ToSwarm::RemoveListener { id });
+        }
+        self.reservations.clear();
+        self.relay_relisten = None;
+    }
+
+    /// Whether we are found to be not directly reachable and thus rely on relays.
+    fn is_private(&self) -> bool {
+        matches!(
+            self.reachability,
+            Reachability::Private | Reachability::Relayed
+        )
+    }
+
+    /// Proceed to relays unless we are still waiting for the outcome of a port mapping.
+    fn on_private(&mut self) {
+        if self.port_mapping != PortMapping::Pending {
+            self.listen_on_relays();
+        }
+        if self.reservations.is_empty() {
+            self.set_reachability(Reachability::Private);
+        } else {
+            self.set_reachability(Reachability::Relayed);
+        }
+    }
+
+    fn on_autonat_event(&mut self, event: autonat::Event) {
+        match event {
+            autonat::Event::StatusChanged { new, .. } => match new {
+                autonat::NatStatus::Public(address) => {
+                    self.remove_relay_listeners();
+                    self.set_reachability(Reachability::Public(address));
+                }
+                autonat::NatStatus::Private => self.on_private(),
+                autonat::NatStatus::Unknown => self.set_reachability(Reachability::Unknown),
+            },
+            // A mapped port that still can't be dialed back is of no use, fall back to relays.
+            autonat::Event::OutboundProbe(autonat::OutboundProbeEvent::Error { .. })
+                if self.port_mapping == PortMapping::Mapped
+                    && self.reachability == Reachability::Private =>
+            {
+                self.listen_on_relays();
+            }
+            _ => {}
+        }
+    }
+
+    fn on_relay_client_event(&mut self, event: relay::client::Event) {
+        if let relay::client::Event::ReservationReqAccepted {
+            relay_peer_id,
+            renewal,
+            ..
+        } = event
+        {
+            self.reservations.insert(relay_peer_id);
+            if self.reachability == Reachability::Private {
+                self.set_reachability(Reachability::Relayed);
+            }
+            self.pending_actions
+                .push_back(ToSwarm::GenerateEvent(Event::ReservationAccepted {
+                    relay_peer_id,
+                    renewal,
+                }));
+        }
+    }
+
+    fn on_dcutr_event(&mut self, event: dcutr::Event) {
+        let event = match event.result {
+            Ok(connection_id) => Event::HolePunchSucceeded {
+                remote_peer_id: event.remote_peer_id,
+                connection_id,
+            },
+            Err(error) => Event::HolePunchFailed {
+                remote_peer_id: event.remote_peer_id,
+                error,
+            },
+        };
+        self.pending_actions
+            .push_back(ToSwarm::GenerateEvent(event));
+    }
+
+    #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+    fn on_upnp_event(&mut self, event: crate::upnp::Event) {
+        use crate::upnp::Event as UpnpEvent;
+
+        match event {
+            UpnpEvent::NewExternalAddr(address) => {
+                self.port_mapping = PortMapping::Mapped;
+                self.pending_actions
+                    .push_back(ToSwarm::GenerateEvent(Event::PortMapped(address)));
+            }
+            UpnpEvent::GatewayNotFound | UpnpEvent::NonRoutableGateway => {
+                self.port_mapping = PortMapping::Failed;
+                self.pending_actions
+                    .push_back(ToSwarm::GenerateEvent(Event::PortMappingFailed));
+                if self.reachability == Reachability::Private {
+                    self.listen_on_relays();
+                }
+            }
+            UpnpEvent::ExpiredExternalAddr(_) => {
+                self.port_mapping = PortMapping::Failed;
+                if self.reachability == Reachability::Private {
+                    self.listen_on_relays();
+                }
+            }
+        }
+    }
+
+    fn on_listener_closed(&mut self, ListenerClosed { listener_id, .. }: ListenerClosed) {
+        let Some(relay) = self.relay_listeners.remove(&listener_id) else {
+            return;
+        };
+
+        if let Some(Protocol::P2p(relay_peer_id)) = relay.iter().last() {
+            self.reservations.remove(&relay_peer_id);
+        }
+        if self.reachability == Reachability::Relayed && self.reservations.is_empty() {
+            self.set_reachability(Reachability::Private);
+        }
+        if self.is_private() && self.relay_relisten.is_none() {
+            self.relay_relisten = Some(Delay::new(self.relay_relisten_delay));
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = THandler<Inner>;
+    type ToSwarm = Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ListenerClosed(listener_closed) = event {
+            self.on_listener_closed(listener_closed);
+        }
+
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(action) = self.pending_actions.pop_front() {
+                return Poll::Ready(action);
+            }
+
+            if let Some(Poll::Ready(())) = self.relay_relisten.as_mut().map(|d| d.poll_unpin(cx)) {
+                self.relay_relisten = None;
+                if self.is_private() {
+                    self.listen_on_relays();
+                }
+                continue;
+            }
+
+            let event = match self.inner.poll(cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(event)) => event,
+                Poll::Ready(action) => {
+                    return Poll::Ready(action.map_out(|_| unreachable!("handled above")))
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match event {
+                InnerEvent::Autonat(event) => self.on_autonat_event(event),
+                InnerEvent::RelayClient(event) => self.on_relay_client_event(event),
+                InnerEvent::Dcutr(event) => self.on_dcutr_event(event),
+                #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+                InnerEvent::Upnp(event) => self.on_upnp_event(event),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_identity::Keypair;
+
+    fn behaviour(relay: &Multiaddr) -> Behaviour {
+        let local_peer_id = Keypair::generate_ed25519().public().to_peer_id();
+        let (_, relay_client) = relay::client::new(local_peer_id);
+
+        Behaviour::new(
+            local_peer_id,
+            Config::new()
+                .with_relay(relay.clone())
+                .with_relay_relisten_delay(Duration::ZERO),
+            relay_client,
+        )
+    }
+
+    fn relay() -> (PeerId, Multiaddr) {
+        let relay_peer_id = PeerId::random();
+        let relay = format!("/memory/1234/p2p/{relay_peer_id}").parse().unwrap();
+        (relay_peer_id, relay)
+    }
+
+    fn found_private(behaviour: &mut Behaviour) {
+        behaviour.on_autonat_event(autonat::Event::StatusChanged {
+            old: autonat::NatStatus::Unknown,
+            new: autonat::NatStatus::Private,
+        });
+    }
+
+    fn reservation_accepted(behaviour: &mut Behaviour, relay_peer_id: PeerId) -> ListenerId {
+        let listener_id = *behaviour.relay_listeners.keys().next().unwrap();
+        behaviour.on_relay_client_event(relay::client::Event::ReservationReqAccepted {
+            relay_peer_id,
+            listener_id,
+            renewal: false,
+            limit: None,
+        });
+        listener_id
+    }
+
+    fn listen_on_count(behaviour: &Behaviour) -> usize {
+        behaviour
+            .pending_actions
+            .iter()
+            .filter(|a| matches!(a, ToSwarm::ListenOn { .. }))
+            .count()
+    }
+
+    #[test]
+    fn private_node_listens_on_relays_until_public() {
+        let (relay_peer_id, relay) = relay();
+        let mut behaviour = behaviour(&relay);
+
+        found_private(&mut behaviour);
+        assert_eq!(behaviour.reachability(), &Reachability::Private);
+        assert_eq!(listen_on_count(&behaviour), 1);
+
+        // Being found private again must not open a second listener on the same relay.
+        behaviour.on_private();
+        assert_eq!(listen_on_count(&behaviour), 1);
+
+        reservation_accepted(&mut behaviour, relay_peer_id);
+        assert_eq!(behaviour.reachability(), &Reachability::Relayed);
+
+        let public: Multiaddr = "/ip4/1.2.3.4/tcp/1234".parse().unwrap();
+        behaviour.on_autonat_event(autonat::Event::StatusChanged {
+            old: autonat::NatStatus::Private,
+            new: autonat::NatStatus::Public(public.clone()),
+        });
+        assert_eq!(behaviour.reachability(), &Reachability::Public(public));
+        assert!(behaviour
+            .pending_actions
+            .iter()
+            .any(|a| matches!(a, ToSwarm::RemoveListener { .. })));
+        assert!(behaviour.relay_listeners.is_empty());
+    }
+
+    #[test]
+    fn relistens_on_relay_once_its_listener_closed() {
+        let (relay_peer_id, relay) = relay();
+        let mut behaviour = behaviour(&relay);
+        found_private(&mut behaviour);
+        let listener_id = reservation_accepted(&mut behaviour, relay_peer_id);
+        behaviour.pending_actions.clear();
+
+        behaviour.on_listener_closed(ListenerClosed {
+            listener_id,
+            reason: Ok(()),
+            metadata: None,
+        });
+        assert_eq!(behaviour.reachability(), &Reachability::Private);
+        assert!(behaviour.relay_listeners.is_empty());
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let relistened = loop {
+            match behaviour.poll(&mut cx) {
+                Poll::Ready(ToSwarm::ListenOn { opts }) => break opts,
+                Poll::Ready(_) => {}
+                Poll::Pending => panic!("Expected to listen on the relay again"),
+            }
+        };
+        assert_eq!(
+            behaviour.relay_listeners.get(&relistened.listener_id()),
+            Some(&relay)
+        );
+    }
+
+    #[test]
+    fn public_node_does_not_relisten_on_relay() {
+        let (relay_peer_id, relay) = relay();
+        let mut behaviour = behaviour(&relay);
+        found_private(&mut behaviour);
+        let listener_id = reservation_accepted(&mut behaviour, relay_peer_id);
+        behaviour.on_autonat_event(autonat::Event::StatusChanged {
+            old: autonat::NatStatus::Private,
+            new: autonat::NatStatus::Public("/ip4/1.2.3.4/tcp/1234".parse().unwrap()),
+        });
+
+        behaviour.on_listener_closed(ListenerClosed {
+            listener_id,
+            reason: Ok(()),
+            metadata: None,
+        });
+        assert!(behaviour.relay_relisten.is_none());
+    }
+
+    #[cfg(all(feature = "upnp", feature = "tokio", not(target_arch = "wasm32")))]
+    #[test]
+    fn falls_back_to_relays_once_port_mapping_failed() {
+        let (_, relay) = relay();
+        let mut behaviour = behaviour(&relay);
+        behaviour.port_mapping = PortMapping::Pending;
+
+        // Relays are only used once the port mapping turned out not to help.
+        found_private(&mut behaviour);
+        assert_eq!(listen_on_count(&behaviour), 0);
+
+        behaviour.on_upnp_event(crate::upnp::Event::GatewayNotFound);
+        assert_eq!(listen_on_count(&behaviour), 1);
+        assert_eq!(behaviour.relay_listeners.values().next(), Some(&relay));
+    }
+}
@@ -0,0 +1,590 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Watermark based connection management, modelled after the connection manager of go-libp2p.
+//!
+//! Once the number of established connections exceeds the high watermark, the [`Behaviour`]
+//! closes the connections to the least valuable peers until the low watermark is reached.
+//! The value of a peer is the sum of all tags attached to it via [`Handle::tag_peer`].
+//! Peers can be exempted from trimming altogether via [`Handle::protect`].
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use instant::Instant;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    behaviour::{ConnectionClosed, ConnectionEstablished},
+    dummy, CloseConnection, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use void::Void;
+
+/// A [`NetworkBehaviour`] that keeps the number of established connections between a low and a
+/// high watermark.
+///
+/// For the trimming to take effect, this needs to be composed into the behaviour tree of your
+/// application.
+///
+/// Connections are trimmed as soon as the number of established connections exceeds
+/// [`Config::with_watermarks`]'s high watermark, as well as periodically every
+/// [`Config::with_trim_interval`]. Peers whose connections are younger than
+/// [`Config::with_grace_period`] are never trimmed.
+///
+/// Inbound connections are denied if the high watermark is reached and none of the existing
+/// connections can be trimmed, i.e. all of them are protected or within their grace period.
+/// In that case, a [`SwarmEvent::IncomingConnectionError`](libp2p_swarm::SwarmEvent::IncomingConnectionError)
+/// is emitted whose [`ListenError::Denied`](libp2p_swarm::ListenError::Denied) variant contains a
+/// [`ConnectionDenied`] that can be downcast to [`HighWatermarkExceeded`].
+///
+/// Other [`NetworkBehaviour`]s can protect and tag peers through a [`Handle`], see
+/// [`Behaviour::handle`].
+///
+/// # Example
+///
+/// ```rust
+/// # use libp2p_connection_manager as connection_manager;
+/// # use libp2p_identity::PeerId;
+/// let behaviour = connection_manager::Behaviour::new(
+///     connection_manager::Config::default().with_watermarks(50, 100),
+/// );
+///
+/// let handle = behaviour.handle();
+/// let peer = PeerId::random();
+/// handle.protect(peer, "bootstrap");
+/// handle.tag_peer(peer, "kad", 10);
+/// ```
+pub struct Behaviour {
+    config: Config,
+    handle: Handle,
+
+    /// All established connections.
+    connections: HashMap<ConnectionId, Connection>,
+    /// Peers whose connections we are closing.
+    closing: HashSet<PeerId>,
+    /// Peers whose connections are yet to be closed via [`ToSwarm::CloseConnection`].
+    pending_closes: VecDeque<PeerId>,
+
+    trim_timer: Delay,
+}
+
+struct Connection {
+    peer: PeerId,
+    established: Instant,
+}
+
+impl Behaviour {
+    /// Creates a new [`Behaviour`] trimming connections according to the given [`Config`].
+    pub fn new(config: Config) -> Self {
+        Self {
+            trim_timer: Delay::new(config.trim_interval),
+            config,
+            handle: Handle::default(),
+            connections: Default::default(),
+            closing: Default::default(),
+            pending_closes: Default::default(),
+        }
+    }
+
+    /// Returns a [`Handle`] to protect and tag peers, e.g. from within other [`NetworkBehaviour`]s.
+    pub fn handle(&self) -> Handle {
+        self.handle.clone()
+    }
+
+    /// Protects the given peer from being trimmed, see [`Handle::protect`].
+    pub fn protect(&mut self, peer: PeerId, tag: impl Into<String>) {
+        self.handle.protect(peer, tag)
+    }
+
+    /// Removes the given protection of a peer, see [`Handle::unprotect`].
+    pub fn unprotect(&mut self, peer: PeerId, tag: &str) -> bool {
+        self.handle.unprotect(peer, tag)
+    }
+
+    /// Sets the value of the given tag of a peer, see [`Handle::tag_peer`].
+    pub fn tag_peer(&mut self, peer: PeerId, tag: impl Into<String>, value: i32) {
+        self.handle.tag_peer(peer, tag, value)
+    }
+
+    /// Removes the given tag of a peer, see [`Handle::untag_peer`].
+    pub fn untag_peer(&mut self, peer: PeerId, tag: &str) {
+        self.handle.untag_peer(peer, tag)
+    }
+
+    /// The number of established connections that are not being trimmed.
+    fn num_active_connections(&self) -> usize {
+        self.connections
+            .values()
+            .filter(|c| !self.closing.contains(&c.peer))
+            .count()
+    }
+
+    /// Returns the connected peers that may be trimmed, along with their number of connections.
+    fn trimmable_peers(&self, now: Instant) -> HashMap<PeerId, usize> {
+        let mut peers = HashMap::<PeerId, usize>::new();
+        let mut in_grace_period = HashSet::new();
+
+        for connection in self.connections.values() {
+            if self.closing.contains(&connection.peer) {
+                continue;
+            }
+            if now.duration_since(connection.established) < self.config.grace_period {
+                in_grace_period.insert(connection.peer);
+            }
+            *peers.entry(connection.peer).or_default() += 1;
+        }
+
+        peers.retain(|peer, _| !in_grace_period.contains(peer) && !self.handle.is_protected(peer));
+        peers
+    }
+
+    /// Selects the least valuable peers for closing until the low watermark is reached,
+    /// provided the high watermark is exceeded.
+    fn trim(&mut self) {
+        let num_active = self.num_active_connections();
+        if num_active <= self.config.high_watermark {
+            return;
+        }
+
+        let mut candidates = self
+            .trimmable_peers(Instant::now())
+            .into_iter()
+            .map(|(peer, num_connections)| (peer, num_connections, self.handle.peer_value(&peer)))
+            .collect::<Vec<_>>();
+        // Least valuable first, preferring peers with many connections among equally valuable ones.
+        candidates.sort_by(|(_, a_conns, a_value), (_, b_conns, b_value)| {
+            a_value.cmp(b_value).then(b_conns.cmp(a_conns))
+        });
+
+        let mut excess = num_active - self.config.low_watermark;
+        for (peer, num_connections, value) in candidates {
+            if excess == 0 {
+                break;
+            }
+
+            tracing::debug!(%peer, %value, "Trimming connections to peer");
+
+            self.closing.insert(peer);
+            self.pending_closes.push_back(peer);
+            excess = excess.saturating_sub(num_connections);
+        }
+
+        if excess > 0 {
+            tracing::debug!(
+                %excess,
+                "Unable to trim down to low watermark, remaining peers are protected or in their grace period"
+            );
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        if self.num_active_connections() >= self.config.high_watermark
+            && !self.handle.is_protected(&peer)
+            && self.trimmable_peers(Instant::now()).is_empty()
+        {
+            return Err(ConnectionDenied::new(HighWatermarkExceeded {
+                high_watermark: self.config.high_watermark,
+            }));
+        }
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                ..
+            }) => {
+                self.connections.insert(
+                    connection_id,
+                    Connection {
+                        peer: peer_id,
+                        established: Instant::now(),
+                    },
+                );
+                self.trim();
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                connection_id,
+                remaining_established,
+                ..
+            }) => {
+                self.connections.remove(&connection_id);
+
+                if remaining_established == 0 {
+                    self.closing.remove(&peer_id);
+                    self.pending_closes.retain(|p| p != &peer_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(peer_id) = self.pending_closes.pop_front() {
+                return Poll::Ready(ToSwarm::CloseConnection {
+                    peer_id,
+                    connection: CloseConnection::All,
+                });
+            }
+
+            if self.trim_timer.poll_unpin(cx).is_ready() {
+                self.trim_timer.reset(self.config.trim_interval);
+                self.trim();
+                continue;
+            }
+
+            return Poll::Pending;
+        }
+    }
+}
+
+/// The configuration of a connection manager [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    low_watermark: usize,
+    high_watermark: usize,
+    grace_period: Duration,
+    trim_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            low_watermark: 160,
+            high_watermark: 192,
+            grace_period: Duration::from_secs(60),
+            trim_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Config {
+    /// Sets the number of connections to trim down to, once the number of established connections
+    /// exceeds the high watermark.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `low` is greater than `high`.
+    pub fn with_watermarks(mut self, low: usize, high: usize) -> Self {
+        assert!(
+            low <= high,
+            "low watermark must not be greater than the high watermark"
+        );
+
+        self.low_watermark = low;
+        self.high_watermark = high;
+        self
+    }
+
+    /// Sets the duration after establishing a connection during which the peer is not trimmed.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets the interval at which connections are trimmed, regardless of new connections being
+    /// established.
+    ///
+    /// This allows trimming peers which were in their grace period when the high watermark was
+    /// exceeded.
+    pub fn with_trim_interval(mut self, trim_interval: Duration) -> Self {
+        self.trim_interval = trim_interval;
+        self
+    }
+}
+
+/// A cloneable handle to protect and tag peers of a connection manager [`Behaviour`].
+#[derive(Debug, Clone, Default)]
+pub struct Handle {
+    peers: Arc<Mutex<HashMap<PeerId, PeerInfo>>>,
+}
+
+#[derive(Debug, Default)]
+struct PeerInfo {
+    protections: HashSet<String>,
+    tags: HashMap<String, i32>,
+}
+
+impl PeerInfo {
+    fn is_empty(&self) -> bool {
+        self.protections.is_empty() && self.tags.is_empty()
+    }
+}
+
+impl Handle {
+    /// Protects the peer from being trimmed.
+    ///
+    /// A peer stays protected as long as at least one protection is in place, allowing multiple
+    /// components to protect the same peer independently under different tags.
+    pub fn protect(&self, peer: PeerId, tag: impl Into<String>) {
+        self.lock()
+            .entry(peer)
+            .or_default()
+            .protections
+            .insert(tag.into());
+    }
+
+    /// Removes the protection with the given tag.
+    ///
+    /// Returns whether the peer remains protected by other tags.
+    pub fn unprotect(&self, peer: PeerId, tag: &str) -> bool {
+        self.update(peer, |info| {
+            info.protections.remove(tag);
+            !info.protections.is_empty()
+        })
+        .unwrap_or(false)
+    }
+
+    /// Whether the peer is protected from being trimmed.
+    pub fn is_protected(&self, peer: &PeerId) -> bool {
+        self.lock()
+            .get(peer)
+            .is_some_and(|info| !info.protections.is_empty())
+    }
+
+    /// Sets the value of the given tag of a peer, replacing any previous value.
+    ///
+    /// Peers with a lower total value are trimmed first.
+    pub fn tag_peer(&self, peer: PeerId, tag: impl Into<String>, value: i32) {
+        self.lock()
+            .entry(peer)
+            .or_default()
+            .tags
+            .insert(tag.into(), value);
+    }
+
+    /// Removes the given tag of a peer.
+    pub fn untag_peer(&self, peer: PeerId, tag: &str) {
+        self.update(peer, |info| {
+            info.tags.remove(tag);
+        });
+    }
+
+    /// Returns the value of the peer, i.e. the sum of all of its tags.
+    pub fn peer_value(&self, peer: &PeerId) -> i32 {
+        self.lock().get(peer).map_or(0, |info| {
+            info.tags.values().fold(0, |acc, v| acc.saturating_add(*v))
+        })
+    }
+
+    fn update<T>(&self, peer: PeerId, f: impl FnOnce(&mut PeerInfo) -> T) -> Option<T> {
+        let mut peers = self.lock();
+        let info = peers.get_mut(&peer)?;
+        let result = f(info);
+
+        if info.is_empty() {
+            peers.remove(&peer);
+        }
+
+        Some(result)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PeerId, PeerInfo>> {
+        self.peers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// An inbound connection has been denied because the high watermark is reached and none of the
+/// existing connections can be trimmed.
+#[derive(Debug, Clone, Copy)]
+pub struct HighWatermarkExceeded {
+    high_watermark: usize,
+}
+
+impl HighWatermarkExceeded {
+    /// Returns the high watermark that was reached.
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark
+    }
+}
+
+impl std::error::Error for HighWatermarkExceeded {}
+
+impl fmt::Display for HighWatermarkExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "high watermark of {} connections reached and no connection can be trimmed",
+            self.high_watermark
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_swarm::{ListenError, Swarm, SwarmEvent};
+    use libp2p_swarm_test::SwarmExt;
+
+    fn new_swarm(config: Config) -> Swarm<Behaviour> {
+        Swarm::new_ephemeral(|_| Behaviour::new(config))
+    }
+
+    #[async_std::test]
+    async fn trims_least_valuable_unprotected_peer() {
+        let mut hub = new_swarm(
+            Config::default()
+                .with_watermarks(2, 2)
+                .with_grace_period(Duration::ZERO),
+        );
+        hub.listen().with_memory_addr_external().await;
+
+        let mut protected = new_swarm(Config::default());
+        let mut valuable = new_swarm(Config::default());
+        let mut worthless = new_swarm(Config::default());
+
+        hub.behaviour_mut()
+            .protect(*protected.local_peer_id(), "test");
+        hub.behaviour_mut()
+            .tag_peer(*valuable.local_peer_id(), "test", 10);
+        let worthless_peer = *worthless.local_peer_id();
+
+        protected.connect(&mut hub).await;
+        async_std::task::spawn(protected.loop_on_next());
+        valuable.connect(&mut hub).await;
+        async_std::task::spawn(valuable.loop_on_next());
+        worthless.connect(&mut hub).await;
+        async_std::task::spawn(worthless.loop_on_next());
+
+        let closed = hub
+            .wait(|e| match e {
+                SwarmEvent::ConnectionClosed { peer_id, .. } => Some(peer_id),
+                _ => None,
+            })
+            .await;
+
+        assert_eq!(closed, worthless_peer);
+        assert_eq!(hub.connected_peers().count(), 2);
+    }
+
+    #[async_std::test]
+    async fn denies_inbound_connection_if_nothing_can_be_trimmed() {
+        let mut hub = new_swarm(
+            Config::default()
+                .with_watermarks(1, 1)
+                .with_grace_period(Duration::ZERO),
+        );
+        let (listen_addr, _) = hub.listen().with_memory_addr_external().await;
+
+        let mut protected = new_swarm(Config::default());
+        hub.behaviour_mut()
+            .protect(*protected.local_peer_id(), "test");
+        protected.connect(&mut hub).await;
+        async_std::task::spawn(protected.loop_on_next());
+
+        let mut other = new_swarm(Config::default());
+        other.dial(listen_addr).unwrap();
+        async_std::task::spawn(other.loop_on_next());
+
+        let cause = hub
+            .wait(|e| match e {
+                SwarmEvent::IncomingConnectionError {
+                    error: ListenError::Denied { cause },
+                    ..
+                } => Some(cause),
+                _ => None,
+            })
+            .await;
+
+        assert_eq!(
+            cause
+                .downcast::<HighWatermarkExceeded>()
+                .unwrap()
+                .high_watermark(),
+            1
+        );
+    }
+
+    #[test]
+    fn unprotect_reports_remaining_protections() {
+        let handle = Handle::default();
+        let peer = PeerId::random();
+
+        handle.protect(peer, "a");
+        handle.protect(peer, "b");
+
+        assert!(handle.unprotect(peer, "a"));
+        assert!(handle.is_protected(&peer));
+        assert!(!handle.unprotect(peer, "b"));
+        assert!(!handle.is_protected(&peer));
+    }
+
+    #[test]
+    fn peer_value_is_sum_of_tags() {
+        let handle = Handle::default();
+        let peer = PeerId::random();
+
+        handle.tag_peer(peer, "a", 5);
+        handle.tag_peer(peer, "b", -2);
+        assert_eq!(handle.peer_value(&peer), 3);
+
+        handle.tag_peer(peer, "a", 1);
+        assert_eq!(handle.peer_value(&peer), -1);
+
+        handle.untag_peer(peer, "b");
+        assert_eq!(handle.peer_value(&peer), 1);
+        assert_eq!(handle.peer_value(&PeerId::random()), 0);
+    }
+}
@@ -0,0 +1,489 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A local HTTP endpoint exposing the status of a libp2p node for operational tooling.
+//!
+//! [`Gateway::bind`] starts an HTTP server on the tokio runtime. Each HTTP request is turned
+//! into a [`Request`] that the [`Gateway`] stream yields to the event loop owning the
+//! [`Swarm`], which answers it via [`Request::serve`]. The [`Swarm`] is thus never shared with
+//! the HTTP server and all answers reflect the state of the [`Swarm`] at the time they are
+//! served.
+//!
+//! The following endpoints are exposed:
+//!
+//! - `GET /status`: the local peer ID, the listen and external addresses and the number of
+//!   connected peers.
+//! - `GET /peers`: the connected peers.
+//! - `GET /kad/routing-table`: the entries of the Kademlia routing table, see
+//!   [`Request::serve_with_kad`]. Responds with `404 Not Found` if Kademlia is not served.
+//! - `POST /dial`: dials the address given as `{"address": "<multiaddr>"}`. Responds with
+//!   `202 Accepted` once the dial is initiated, or `400 Bad Request` if it can't be.
+//!   Disabled unless a token is set via [`Config::with_dial_token`], which has to be passed as
+//!   `Authorization: Bearer <token>`.
+//!
+//! All responses are JSON. The gateway only exposes the state of the [`Swarm`] and the
+//! Kademlia routing table. Protocol-level queries, e.g. pinging a peer, looking up its
+//! identify information or running a Kademlia query, are not served, as their results arrive
+//! as events of the respective behaviours, which are handled by the event loop.
+//!
+//! # Security
+//!
+//! The read endpoints require no authentication and the dial token is sent in plain text, so
+//! the gateway must not be reachable by untrusted parties. [`Gateway::bind`] thus refuses
+//! non-loopback addresses unless allowed via [`Config::with_non_loopback`].
+//!
+//! Unless bound to a non-loopback address, requests whose `Host` header does not name a
+//! loopback address are rejected with `403 Forbidden`. Otherwise, a website could make the
+//! browser of the local user reach the gateway via a domain resolving to a loopback address
+//! (DNS rebinding).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use futures::StreamExt;
+//! # use libp2p_swarm::{dummy, Swarm};
+//! # async fn run(mut swarm: Swarm<dummy::Behaviour>) -> std::io::Result<()> {
+//! let mut gateway = libp2p_gateway::Gateway::bind(
+//!     ([127, 0, 0, 1], 5001).into(),
+//!     libp2p_gateway::Config::default(),
+//! )
+//! .await?;
+//!
+//! loop {
+//!     tokio::select! {
+//!         Some(request) = gateway.next() => request.serve(&mut swarm),
+//!         event = swarm.select_next_some() => {
+//!             // Handle the swarm event.
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    stream::FusedStream,
+    SinkExt, Stream, StreamExt,
+};
+use libp2p_core::Multiaddr;
+use libp2p_swarm::{NetworkBehaviour, Swarm};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    io,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// The number of HTTP requests that may wait for the event loop before further requests are
+/// held back.
+const MAX_PENDING_REQUESTS: usize = 32;
+
+/// The configuration of a [`Gateway`].
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    dial_token: Option<String>,
+    non_loopback: bool,
+}
+
+impl Config {
+    /// Enables `POST /dial` for requests passing the given token as
+    /// `Authorization: Bearer <token>`.
+    ///
+    /// Dialing is disabled by default, responding with `403 Forbidden`.
+    pub fn with_dial_token(mut self, token: impl Into<String>) -> Self {
+        self.dial_token = Some(token.into());
+        self
+    }
+
+    /// Sets whether the gateway may be bound to an address other than a loopback address.
+    ///
+    /// Only enable this if the address is not reachable by untrusted parties, e.g. behind a
+    /// firewall, see the [security considerations](crate#security). Disabled by default.
+    pub fn with_non_loopback(mut self, allowed: bool) -> Self {
+        self.non_loopback = allowed;
+        self
+    }
+}
+
+/// The state shared by the HTTP handlers.
+#[derive(Clone)]
+struct Shared {
+    requests: mpsc::Sender<Request>,
+    dial_token: Option<Arc<str>>,
+}
+
+/// A local HTTP server exposing the status of a [`Swarm`].
+///
+/// The [`Gateway`] is a [`Stream`] of [`Request`]s, which need to be answered by the event loop
+/// owning the [`Swarm`]. Dropping the [`Gateway`] stops the HTTP server.
+pub struct Gateway {
+    requests: mpsc::Receiver<Request>,
+    local_addr: SocketAddr,
+    server: JoinHandle<()>,
+}
+
+impl Gateway {
+    /// Binds the HTTP server to the given address and spawns it on the current tokio runtime.
+    ///
+    /// Bind to port `0` to let the operating system pick a port, see [`Gateway::local_addr`].
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the address is not a loopback address,
+    /// unless allowed via [`Config::with_non_loopback`].
+    pub async fn bind(addr: SocketAddr, config: Config) -> io::Result<Self> {
+        if !addr.ip().is_loopback() && !config.non_loopback {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Refusing to bind the gateway to non-loopback address {addr}"),
+            ));
+        }
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (sender, requests) = mpsc::channel(MAX_PENDING_REQUESTS);
+
+        let mut app = Router::new()
+            .route("/status", get(|s| forward(s, Query::Status)))
+            .route("/peers", get(|s| forward(s, Query::Peers)))
+            .route(
+                "/kad/routing-table",
+                get(|s| forward(s, Query::RoutingTable)),
+            )
+            .route("/dial", post(dial))
+            .with_state(Shared {
+                requests: sender,
+                dial_token: config.dial_token.map(Arc::from),
+            });
+        if !config.non_loopback {
+            app = app.layer(middleware::from_fn(check_host));
+        }
+
+        let server = tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, app).await {
+                tracing::warn!(%error, "Gateway HTTP server failed");
+            }
+        });
+        tracing::info!(address=%local_addr, "Gateway listening");
+
+        Ok(Self {
+            requests,
+            local_addr,
+            server,
+        })
+    }
+
+    /// Returns the address the HTTP server is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Stream for Gateway {
+    type Item = Request;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.requests.poll_next_unpin(cx)
+    }
+}
+
+impl FusedStream for Gateway {
+    fn is_terminated(&self) -> bool {
+        self.requests.is_terminated()
+    }
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// An HTTP request waiting to be answered from the state of the [`Swarm`].
+///
+/// Dropping the [`Request`] without serving it responds with `503 Service Unavailable`.
+#[derive(Debug)]
+pub struct Request {
+    query: Query,
+    responder: oneshot::Sender<(StatusCode, Value)>,
+}
+
+#[derive(Debug)]
+enum Query {
+    Status,
+    Peers,
+    RoutingTable,
+    Dial(Multiaddr),
+}
+
+impl Request {
+    /// Answers the request from the state of the given [`Swarm`].
+    ///
+    /// Queries of the Kademlia routing table are answered with `404 Not Found`, use
+    /// [`Request::serve_with_kad`] to serve them.
+    pub fn serve<TBehaviour>(self, swarm: &mut Swarm<TBehaviour>)
+    where
+        TBehaviour: NetworkBehaviour,
+    {
+        let response = answer(swarm, self.query, |_| {
+            not_found("Kademlia is not served by this node")
+        });
+        let _ = self.responder.send(response);
+    }
+
+    /// Answers the request from the state of the given [`Swarm`], including queries of the
+    /// routing table of the Kademlia behaviour returned by `kad`.
+    #[cfg(feature = "kad")]
+    pub fn serve_with_kad<TBehaviour, TStore>(
+        self,
+        swarm: &mut Swarm<TBehaviour>,
+        kad: impl FnOnce(&mut TBehaviour) -> &mut libp2p_kad::Behaviour<TStore>,
+    ) where
+        TBehaviour: NetworkBehaviour,
+        TStore: libp2p_kad::store::RecordStore + Send + 'static,
+    {
+        let response = answer(swarm, self.query, |behaviour| {
+            (StatusCode::OK, routing_table(kad(behaviour)))
+        });
+        let _ = self.responder.send(response);
+    }
+}
+
+fn answer<TBehaviour>(
+    swarm: &mut Swarm<TBehaviour>,
+    query: Query,
+    routing_table: impl FnOnce(&mut TBehaviour) -> (StatusCode, Value),
+) -> (StatusCode, Value)
+where
+    TBehaviour: NetworkBehaviour,
+{
+    match query {
+        Query::Status => {
+            let external_addrs = swarm
+                .external_addresses()
+                .map(|address| {
+                    json!({
+                        "address": address,
+                        "origin": swarm.external_address_origin(address).map(|o| o.to_string()),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let status = json!({
+                "peer_id": swarm.local_peer_id().to_string(),
+                "listen_addrs": swarm.listeners().collect::<Vec<_>>(),
+                "external_addrs": external_addrs,
+                "connected_peers": swarm.network_info().num_peers(),
+            });
+            (StatusCode::OK, status)
+        }
+        Query::Peers => {
+            let peers = swarm
+                .connected_peers()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            (StatusCode::OK, json!(peers))
+        }
+        Query::RoutingTable => routing_table(swarm.behaviour_mut()),
+        Query::Dial(address) => match swarm.dial(address.clone()) {
+            Ok(()) => (StatusCode::ACCEPTED, json!({ "address": address })),
+            Err(error) => {
+                tracing::debug!(%address, %error, "Gateway failed to dial");
+                bad_request(error.to_string())
+            }
+        },
+    }
+}
+
+#[cfg(feature = "kad")]
+fn routing_table<TStore>(kad: &mut libp2p_kad::Behaviour<TStore>) -> Value
+where
+    TStore: libp2p_kad::store::RecordStore + Send + 'static,
+{
+    let entries = kad
+        .kbuckets()
+        .flat_map(|bucket| {
+            let index = bucket.range().0.ilog2();
+            bucket
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "peer_id": entry.node.key.preimage().to_string(),
+                        "addresses": entry.node.value.iter().collect::<Vec<_>>(),
+                        "connected": matches!(entry.status, libp2p_kad::NodeStatus::Connected),
+                        "bucket": index,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    json!(entries)
+}
+
+#[derive(Deserialize)]
+struct DialBody {
+    address: String,
+}
+
+async fn dial(
+    state: State<Shared>,
+    headers: HeaderMap,
+    body: Result<Json<DialBody>, JsonRejection>,
+) -> (StatusCode, Json<Value>) {
+    let Some(token) = state.dial_token.as_deref() else {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "Dialing is disabled" })),
+        );
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Missing or invalid token" })),
+        );
+    }
+
+    let address = match body {
+        Ok(Json(body)) => body.address.parse::<Multiaddr>(),
+        Err(rejection) => {
+            let (status, body) = bad_request(rejection.body_text());
+            return (status, Json(body));
+        }
+    };
+    match address {
+        Ok(address) => forward(state, Query::Dial(address)).await,
+        Err(error) => {
+            let (status, body) = bad_request(format!("Invalid multiaddr: {error}"));
+            (status, Json(body))
+        }
+    }
+}
+
+/// Rejects requests whose `Host` header does not name a loopback address.
+async fn check_host(request: axum::extract::Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| request.uri().host());
+    if !host.is_some_and(is_loopback_host) {
+        tracing::debug!(?host, "Gateway rejected request for non-loopback host");
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "Host is not a loopback address" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether the given host, optionally followed by a port, names a loopback address.
+fn is_loopback_host(host: &str) -> bool {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest
+            .split_once(']')
+            .and_then(|(ip, _)| ip.parse::<Ipv6Addr>().ok())
+            .is_some_and(|ip| ip.is_loopback());
+    }
+    let name = host.split(':').next().unwrap_or_default();
+    name.eq_ignore_ascii_case("localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Forwards the query to the event loop and waits for its answer.
+async fn forward(State(mut shared): State<Shared>, query: Query) -> (StatusCode, Json<Value>) {
+    let (responder, response) = oneshot::channel();
+    if shared
+        .requests
+        .send(Request { query, responder })
+        .await
+        .is_err()
+    {
+        return unavailable();
+    }
+    match response.await {
+        Ok((status, body)) => (status, Json(body)),
+        Err(oneshot::Canceled) => unavailable(),
+    }
+}
+
+fn unavailable() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({ "error": "Request was not served" })),
+    )
+}
+
+fn not_found(error: &str) -> (StatusCode, Value) {
+    (StatusCode::NOT_FOUND, json!({ "error": error }))
+}
+
+fn bad_request(error: String) -> (StatusCode, Value) {
+    (StatusCode::BAD_REQUEST, json!({ "error": error }))
+}
+
+/// Compares the given byte strings in time independent of their contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_hosts() {
+        for host in [
+            "localhost",
+            "LOCALHOST:5001",
+            "127.0.0.1",
+            "127.0.0.1:5001",
+            "[::1]",
+            "[::1]:5001",
+        ] {
+            assert!(is_loopback_host(host), "{host}");
+        }
+        for host in [
+            "example.com",
+            "localhost.example.com:5001",
+            "10.0.0.1:5001",
+            "[::2]:5001",
+            "[::1",
+            "",
+        ] {
+            assert!(!is_loopback_host(host), "{host}");
+        }
+    }
+}
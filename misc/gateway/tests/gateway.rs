@@ -0,0 +1,212 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::StreamExt;
+use libp2p_gateway::{Config, Gateway};
+use libp2p_swarm::{dummy, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use serde_json::{json, Value};
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const TOKEN: &str = "secret";
+
+#[tokio::test]
+async fn serves_status_and_dials() {
+    let swarm = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let (other_addr, _) = other.listen().with_memory_addr_external().await;
+    let other_id = other.local_peer_id().to_string();
+    let local_id = swarm.local_peer_id().to_string();
+    tokio::spawn(other.loop_on_next());
+
+    let config = Config::default().with_dial_token(TOKEN);
+    let gateway = Gateway::bind(([127, 0, 0, 1], 0).into(), config)
+        .await
+        .unwrap();
+    let http = gateway.local_addr();
+    tokio::spawn(run(swarm, gateway));
+
+    let (status, body) = request(http, "GET", "/kad/routing-table", "", None).await;
+    assert_eq!(status, 404, "{body}");
+
+    let body = r#"{"address": "foo"}"#;
+    let (status, _) = request(http, "POST", "/dial", body, Some(TOKEN)).await;
+    assert_eq!(status, 400);
+
+    let dial = json!({ "address": other_addr }).to_string();
+    let (status, _) = request(http, "POST", "/dial", &dial, None).await;
+    assert_eq!(status, 401);
+    let (status, _) = request(http, "POST", "/dial", &dial, Some("wrong")).await;
+    assert_eq!(status, 401);
+    let (status, _) = request(http, "POST", "/dial", &dial, Some(TOKEN)).await;
+    assert_eq!(status, 202);
+
+    loop {
+        let (status, peers) = request(http, "GET", "/peers", "", None).await;
+        assert_eq!(status, 200);
+        if peers == json!([other_id]) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let (status, body) = request(http, "GET", "/status", "", None).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["peer_id"], json!(local_id));
+    assert_eq!(body["connected_peers"], json!(1));
+}
+
+#[tokio::test]
+async fn dialing_is_disabled_without_token() {
+    let swarm = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let gateway = Gateway::bind(([127, 0, 0, 1], 0).into(), Config::default())
+        .await
+        .unwrap();
+    let http = gateway.local_addr();
+    tokio::spawn(run(swarm, gateway));
+
+    let dial = json!({ "address": "/memory/1" }).to_string();
+    let (status, _) = request(http, "POST", "/dial", &dial, Some("")).await;
+    assert_eq!(status, 403);
+}
+
+#[tokio::test]
+async fn refuses_non_loopback_address() {
+    let error = Gateway::bind(([0, 0, 0, 0], 0).into(), Config::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+    let config = Config::default().with_non_loopback(true);
+    Gateway::bind(([0, 0, 0, 0], 0).into(), config)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn rejects_non_loopback_host() {
+    let swarm = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let gateway = Gateway::bind(([127, 0, 0, 1], 0).into(), Config::default())
+        .await
+        .unwrap();
+    let http = gateway.local_addr();
+    tokio::spawn(run(swarm, gateway));
+
+    let (status, _) =
+        request_with_host(http, "attacker.example:5001", "GET", "/status", "", None).await;
+    assert_eq!(status, 403);
+    let (status, _) = request_with_host(http, "127.0.0.1:5001", "GET", "/status", "", None).await;
+    assert_eq!(status, 200);
+}
+
+#[cfg(feature = "kad")]
+#[tokio::test]
+async fn serves_kad_routing_table() {
+    use libp2p_kad::store::MemoryStore;
+
+    let mut swarm = Swarm::new_ephemeral(|key| {
+        let peer_id = key.public().to_peer_id();
+        libp2p_kad::Behaviour::new(peer_id, MemoryStore::new(peer_id))
+    });
+    let other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let other_id = *other.local_peer_id();
+    let address = "/ip4/8.8.8.8/tcp/4001"
+        .parse::<libp2p_core::Multiaddr>()
+        .unwrap();
+    swarm
+        .behaviour_mut()
+        .add_address(&other_id, address.clone());
+
+    let mut gateway = Gateway::bind(([127, 0, 0, 1], 0).into(), Config::default())
+        .await
+        .unwrap();
+    let http = gateway.local_addr();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(request) = gateway.next() => request.serve_with_kad(&mut swarm, |kad| kad),
+                _ = swarm.select_next_some() => {}
+            }
+        }
+    });
+
+    let (status, body) = request(http, "GET", "/kad/routing-table", "", None).await;
+    assert_eq!(status, 200, "{body}");
+    let entries = body.as_array().unwrap();
+    assert_eq!(entries.len(), 1, "{body}");
+    assert_eq!(entries[0]["peer_id"], json!(other_id.to_string()));
+    assert_eq!(
+        entries[0]["addresses"],
+        json!([address.with_p2p(other_id).unwrap()])
+    );
+    assert_eq!(entries[0]["connected"], json!(false));
+}
+
+async fn run(mut swarm: Swarm<dummy::Behaviour>, mut gateway: Gateway) {
+    loop {
+        tokio::select! {
+            Some(request) = gateway.next() => request.serve(&mut swarm),
+            _ = swarm.select_next_some() => {}
+        }
+    }
+}
+
+/// Sends a single HTTP request, returning the status code and the JSON body of the response.
+async fn request(
+    addr: SocketAddr,
+    method: &str,
+    path: &str,
+    body: &str,
+    token: Option<&str>,
+) -> (u16, Value) {
+    request_with_host(addr, "localhost", method, path, body, token).await
+}
+
+async fn request_with_host(
+    addr: SocketAddr,
+    host: &str,
+    method: &str,
+    path: &str,
+    body: &str,
+    token: Option<&str>,
+) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n{authorization}\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+
+    (status, serde_json::from_str(body).unwrap())
+}
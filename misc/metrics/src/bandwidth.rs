@@ -6,9 +6,9 @@ use futures::{
     ready,
 };
 use libp2p_core::{
-    muxing::{StreamMuxer, StreamMuxerEvent},
+    muxing::{CloseReason, DatagramError, StreamMuxer, StreamMuxerEvent},
     transport::{ListenerId, TransportError, TransportEvent},
-    Multiaddr,
+    Multiaddr, NegotiatedProtocols,
 };
 use libp2p_identity::PeerId;
 use prometheus_client::{
@@ -16,6 +16,7 @@ use prometheus_client::{
     metrics::{counter::Counter, family::Family},
     registry::{Registry, Unit},
 };
+use std::time::Duration;
 use std::{
     convert::TryFrom as _,
     io,
@@ -111,6 +112,18 @@ where
         self.transport.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.transport.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.transport.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.transport.supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -235,6 +248,41 @@ where
         let this = self.project();
         this.inner.poll_close(cx)
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        let this = self.project();
+        this.inner.set_close_reason(reason)
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.inner.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        let this = self.project();
+        let num_bytes = data.len();
+        this.inner.send_datagram(data)?;
+        this.metrics
+            .outbound
+            .inc_by(u64::try_from(num_bytes).unwrap_or(u64::MAX));
+        Ok(())
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        let this = self.project();
+        let datagram = ready!(this.inner.poll_datagram(cx))?;
+        this.metrics
+            .inbound
+            .inc_by(u64::try_from(datagram.len()).unwrap_or(u64::MAX));
+        Poll::Ready(Ok(datagram))
+    }
+
+    fn negotiated_protocols(&self) -> Option<&NegotiatedProtocols> {
+        self.inner.negotiated_protocols()
+    }
 }
 
 /// Wraps around an [`AsyncRead`] + [`AsyncWrite`] and logs the bandwidth that goes through it.
@@ -0,0 +1,334 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::collections::HashMap;
+use std::task::{Context, Poll};
+
+use instant::Instant;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, PollObserver, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::{Registry, Unit};
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct Labels {
+    behaviour: String,
+}
+
+/// Metrics shared by all [`Instrumented`] behaviours of a [`Swarm`](libp2p_swarm::Swarm).
+///
+/// ```
+/// use libp2p_metrics::{BehaviourMetrics, Registry};
+/// use libp2p_swarm::dummy;
+///
+/// let mut registry = Registry::default();
+/// let metrics = BehaviourMetrics::new(&mut registry);
+/// let behaviour = metrics.instrument("dummy", dummy::Behaviour);
+/// ```
+///
+/// The returned [`Instrumented`] behaviour can also be used as a field of a
+/// `#[derive(NetworkBehaviour)]` struct. It emits the same events as the wrapped behaviour, thus
+/// the generated event enum is unaffected. Alternatively, all members of such a struct can be
+/// instrumented at once via a [`PollMetrics`] field marked with `#[behaviour(poll_observer)]`:
+///
+/// ```
+/// use libp2p_metrics::{BehaviourMetrics, PollMetrics, Registry};
+/// use libp2p_swarm::{dummy, NetworkBehaviour};
+///
+/// #[derive(NetworkBehaviour)]
+/// #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+/// struct MyBehaviour {
+///     dummy: dummy::Behaviour,
+///     #[behaviour(poll_observer)]
+///     metrics: PollMetrics,
+/// }
+///
+/// let mut registry = Registry::default();
+/// let behaviour = MyBehaviour {
+///     dummy: dummy::Behaviour,
+///     metrics: BehaviourMetrics::new(&mut registry).poll_observer(),
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct BehaviourMetrics {
+    poll_duration: Family<Labels, Histogram>,
+    consecutive_ready: Family<Labels, Histogram>,
+}
+
+impl BehaviourMetrics {
+    pub fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry
+            .sub_registry_with_prefix("libp2p")
+            .sub_registry_with_prefix("behaviour");
+
+        let poll_duration = {
+            let constructor: fn() -> Histogram =
+                || Histogram::new(exponential_buckets(0.000_001, 4.0, 12));
+            Family::new_with_constructor(constructor)
+        };
+        sub_registry.register_with_unit(
+            "poll_duration",
+            "Time spent in a single call to `NetworkBehaviour::poll`",
+            Unit::Seconds,
+            poll_duration.clone(),
+        );
+
+        let consecutive_ready = {
+            let constructor: fn() -> Histogram =
+                || Histogram::new(exponential_buckets(1.0, 2.0, 10));
+            Family::new_with_constructor(constructor)
+        };
+        sub_registry.register(
+            "consecutive_ready_polls",
+            "Number of calls to `NetworkBehaviour::poll` in a row returning `Poll::Ready` before returning `Poll::Pending`",
+            consecutive_ready.clone(),
+        );
+
+        Self {
+            poll_duration,
+            consecutive_ready,
+        }
+    }
+
+    fn member_metrics(&self, name: String) -> MemberMetrics {
+        let labels = Labels { behaviour: name };
+
+        MemberMetrics {
+            poll_duration: self.poll_duration.get_or_create(&labels).clone(),
+            consecutive_ready: self.consecutive_ready.get_or_create(&labels).clone(),
+            ready_in_a_row: 0,
+        }
+    }
+
+    /// Wraps the given behaviour, labelling its metrics with `name`.
+    pub fn instrument<B>(&self, name: impl Into<String>, behaviour: B) -> Instrumented<B> {
+        Instrumented {
+            inner: behaviour,
+            metrics: self.member_metrics(name.into()),
+        }
+    }
+
+    /// Creates a [`PollObserver`] labelling the metrics of each member of a
+    /// `#[derive(NetworkBehaviour)]` struct with the member's name.
+    pub fn poll_observer(&self) -> PollMetrics {
+        PollMetrics {
+            metrics: self.clone(),
+            members: HashMap::new(),
+        }
+    }
+}
+
+/// The metrics of a single behaviour, labelled once on creation.
+#[derive(Debug)]
+struct MemberMetrics {
+    poll_duration: Histogram,
+    consecutive_ready: Histogram,
+    /// Number of [`Poll::Ready`]s returned since the last [`Poll::Pending`].
+    ready_in_a_row: u64,
+}
+
+impl MemberMetrics {
+    fn observe<T>(&mut self, poll: impl FnOnce() -> Poll<T>) -> Poll<T> {
+        let start = Instant::now();
+        let poll = poll();
+        self.poll_duration.observe(start.elapsed().as_secs_f64());
+
+        match poll {
+            Poll::Ready(_) => self.ready_in_a_row += 1,
+            Poll::Pending if self.ready_in_a_row > 0 => {
+                self.consecutive_ready.observe(self.ready_in_a_row as f64);
+                self.ready_in_a_row = 0;
+            }
+            Poll::Pending => {}
+        }
+
+        poll
+    }
+}
+
+/// A [`PollObserver`] measuring the time spent polling each member of a
+/// `#[derive(NetworkBehaviour)]` struct and the number of events it returned in a row.
+///
+/// Created via [`BehaviourMetrics::poll_observer`].
+#[derive(Debug)]
+pub struct PollMetrics {
+    metrics: BehaviourMetrics,
+    members: HashMap<&'static str, MemberMetrics>,
+}
+
+impl PollObserver for PollMetrics {
+    fn observe<T>(&mut self, member: &'static str, poll: impl FnOnce() -> Poll<T>) -> Poll<T> {
+        let metrics = &self.metrics;
+        self.members
+            .entry(member)
+            .or_insert_with(|| metrics.member_metrics(member.to_owned()))
+            .observe(poll)
+    }
+}
+
+/// A [`NetworkBehaviour`] measuring the time spent polling the wrapped behaviour and the number
+/// of events it returned in a row.
+///
+/// Created via [`BehaviourMetrics::instrument`].
+pub struct Instrumented<B> {
+    inner: B,
+    metrics: MemberMetrics,
+}
+
+impl<B> Instrumented<B> {
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B> NetworkBehaviour for Instrumented<B>
+where
+    B: NetworkBehaviour,
+{
+    type ConnectionHandler = B::ConnectionHandler;
+    type ToSwarm = B::ToSwarm;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.inner.on_swarm_event(event)
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let inner = &mut self.inner;
+        self.metrics.observe(|| inner.poll(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_swarm::dummy;
+    use prometheus_client::encoding::text::encode;
+
+    #[derive(libp2p_swarm::NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Behaviour {
+        instrumented: Instrumented<dummy::Behaviour>,
+        observed: dummy::Behaviour,
+        #[behaviour(poll_observer)]
+        metrics: PollMetrics,
+    }
+
+    #[test]
+    fn records_poll_duration_per_behaviour() {
+        let mut registry = Registry::default();
+        let metrics = BehaviourMetrics::new(&mut registry);
+        let mut behaviour = Behaviour {
+            instrumented: metrics.instrument("instrumented", dummy::Behaviour),
+            observed: dummy::Behaviour,
+            metrics: metrics.poll_observer(),
+        };
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        assert!(behaviour.poll(&mut cx).is_pending());
+        assert!(behaviour.poll(&mut cx).is_pending());
+
+        let mut encoded = String::new();
+        encode(&mut encoded, &registry).unwrap();
+        // Polled directly via `Instrumented` and as a member via `PollMetrics`.
+        assert!(encoded.contains(
+            "libp2p_behaviour_poll_duration_seconds_count{behaviour=\"instrumented\"} 4"
+        ));
+        assert!(encoded
+            .contains("libp2p_behaviour_poll_duration_seconds_count{behaviour=\"observed\"} 2"));
+    }
+}
@@ -53,6 +53,8 @@ pub(crate) struct Metrics {
     pushed: Counter,
     received: Counter,
     sent: Counter,
+    rate_limited: Counter,
+    agent_version_matched: Counter,
 }
 
 impl Metrics {
@@ -93,12 +95,30 @@ impl Metrics {
             sent.clone(),
         );
 
+        let rate_limited = Counter::default();
+        sub_registry.register(
+            "rate_limited",
+            "Number of identification requests and pushes dropped because the \
+             remote exceeded its rate limit",
+            rate_limited.clone(),
+        );
+
+        let agent_version_matched = Counter::default();
+        sub_registry.register(
+            "agent_version_matched",
+            "Number of identified peers whose agent version matched a rule \
+             flagging or disconnecting them",
+            agent_version_matched.clone(),
+        );
+
         Self {
             peers,
             error,
             pushed,
             received,
             sent,
+            rate_limited,
+            agent_version_matched,
         }
     }
 }
@@ -119,6 +139,12 @@ impl super::Recorder<libp2p_identify::Event> for Metrics {
             libp2p_identify::Event::Sent { .. } => {
                 self.sent.inc();
             }
+            libp2p_identify::Event::RateLimitExceeded { .. } => {
+                self.rate_limited.inc();
+            }
+            libp2p_identify::Event::AgentVersionMatched { .. } => {
+                self.agent_version_matched.inc();
+            }
         }
     }
 }
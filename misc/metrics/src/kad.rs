@@ -39,6 +39,10 @@ pub(crate) struct Metrics {
     query_result_num_failure: Family<QueryResult, Histogram>,
     query_result_duration: Family<QueryResult, Histogram>,
 
+    query_completed: Family<QueryOutcome, Counter>,
+    query_completed_num_requests: Family<QueryOutcome, Histogram>,
+    query_completed_duration: Family<QueryOutcome, Histogram>,
+
     routing_updated: Family<RoutingUpdated, Counter>,
 
     inbound_requests: Family<InboundRequest, Counter>,
@@ -123,6 +127,30 @@ impl Metrics {
             query_result_duration.clone(),
         );
 
+        let query_completed = Family::default();
+        sub_registry.register(
+            "query_completed",
+            "Number of completed Kademlia queries by type and outcome",
+            query_completed.clone(),
+        );
+
+        let query_completed_num_requests: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(1.0, 2.0, 10)));
+        sub_registry.register(
+            "query_completed_num_requests",
+            "Number of peers contacted by a completed Kademlia query",
+            query_completed_num_requests.clone(),
+        );
+
+        let query_completed_duration: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.1, 2.0, 10)));
+        sub_registry.register_with_unit(
+            "query_completed_duration",
+            "Duration of a completed Kademlia query",
+            Unit::Seconds,
+            query_completed_duration.clone(),
+        );
+
         let routing_updated = Family::default();
         sub_registry.register(
             "routing_updated",
@@ -152,6 +180,10 @@ impl Metrics {
             query_result_num_failure,
             query_result_duration,
 
+            query_completed,
+            query_completed_num_requests,
+            query_completed_duration,
+
             routing_updated,
 
             inbound_requests,
@@ -162,7 +194,12 @@ impl Metrics {
 impl super::Recorder<libp2p_kad::Event> for Metrics {
     fn record(&self, event: &libp2p_kad::Event) {
         match event {
-            libp2p_kad::Event::OutboundQueryProgressed { result, stats, .. } => {
+            libp2p_kad::Event::OutboundQueryProgressed {
+                result,
+                stats,
+                step,
+                ..
+            } => {
                 self.query_result_num_requests
                     .get_or_create(&result.into())
                     .observe(stats.num_requests().into());
@@ -178,6 +215,20 @@ impl super::Recorder<libp2p_kad::Event> for Metrics {
                         .observe(duration.as_secs_f64());
                 }
 
+                // The statistics of a query are cumulative, only record them once it finished.
+                if step.last {
+                    let outcome = QueryOutcome::from(result);
+                    self.query_completed.get_or_create(&outcome).inc();
+                    self.query_completed_num_requests
+                        .get_or_create(&outcome)
+                        .observe(stats.num_requests().into());
+                    if let Some(duration) = stats.duration() {
+                        self.query_completed_duration
+                            .get_or_create(&outcome)
+                            .observe(duration.as_secs_f64());
+                    }
+                }
+
                 match result {
                     libp2p_kad::QueryResult::GetRecord(result) => match result {
                         Ok(libp2p_kad::GetRecordOk::FoundRecord(_)) => {
@@ -306,6 +357,38 @@ impl From<&libp2p_kad::QueryResult> for QueryResult {
     }
 }
 
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct QueryOutcome {
+    r#type: QueryType,
+    outcome: Outcome,
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
+enum Outcome {
+    Ok,
+    Error,
+}
+
+impl From<&libp2p_kad::QueryResult> for QueryOutcome {
+    fn from(result: &libp2p_kad::QueryResult) -> Self {
+        let ok = match result {
+            libp2p_kad::QueryResult::Bootstrap(result) => result.is_ok(),
+            libp2p_kad::QueryResult::GetClosestPeers(result) => result.is_ok(),
+            libp2p_kad::QueryResult::GetProviders(result) => result.is_ok(),
+            libp2p_kad::QueryResult::StartProviding(result) => result.is_ok(),
+            libp2p_kad::QueryResult::RepublishProvider(result) => result.is_ok(),
+            libp2p_kad::QueryResult::GetRecord(result) => result.is_ok(),
+            libp2p_kad::QueryResult::PutRecord(result) => result.is_ok(),
+            libp2p_kad::QueryResult::RepublishRecord(result) => result.is_ok(),
+        };
+
+        QueryOutcome {
+            r#type: QueryResult::from(result).r#type,
+            outcome: if ok { Outcome::Ok } else { Outcome::Error },
+        }
+    }
+}
+
 #[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
 struct GetRecordResult {
     error: GetRecordError,
@@ -316,6 +399,7 @@ enum GetRecordError {
     NotFound,
     QuorumFailed,
     Timeout,
+    Aborted,
 }
 
 impl From<&libp2p_kad::GetRecordError> for GetRecordResult {
@@ -330,6 +414,9 @@ impl From<&libp2p_kad::GetRecordError> for GetRecordResult {
             libp2p_kad::GetRecordError::Timeout { .. } => GetRecordResult {
                 error: GetRecordError::Timeout,
             },
+            libp2p_kad::GetRecordError::Aborted { .. } => GetRecordResult {
+                error: GetRecordError::Aborted,
+            },
         }
     }
 }
@@ -342,6 +429,7 @@ struct GetClosestPeersResult {
 #[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
 enum GetClosestPeersError {
     Timeout,
+    Aborted,
 }
 
 impl From<&libp2p_kad::GetClosestPeersError> for GetClosestPeersResult {
@@ -350,6 +438,9 @@ impl From<&libp2p_kad::GetClosestPeersError> for GetClosestPeersResult {
             libp2p_kad::GetClosestPeersError::Timeout { .. } => GetClosestPeersResult {
                 error: GetClosestPeersError::Timeout,
             },
+            libp2p_kad::GetClosestPeersError::Aborted { .. } => GetClosestPeersResult {
+                error: GetClosestPeersError::Aborted,
+            },
         }
     }
 }
@@ -362,6 +453,7 @@ struct GetProvidersResult {
 #[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
 enum GetProvidersError {
     Timeout,
+    Aborted,
 }
 
 impl From<&libp2p_kad::GetProvidersError> for GetProvidersResult {
@@ -370,6 +462,9 @@ impl From<&libp2p_kad::GetProvidersError> for GetProvidersResult {
             libp2p_kad::GetProvidersError::Timeout { .. } => GetProvidersResult {
                 error: GetProvidersError::Timeout,
             },
+            libp2p_kad::GetProvidersError::Aborted { .. } => GetProvidersResult {
+                error: GetProvidersError::Aborted,
+            },
         }
     }
 }
@@ -28,6 +28,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod bandwidth;
+mod behaviour;
 #[cfg(feature = "dcutr")]
 mod dcutr;
 #[cfg(feature = "gossipsub")]
@@ -36,6 +37,7 @@ mod gossipsub;
 mod identify;
 #[cfg(feature = "kad")]
 mod kad;
+mod local;
 #[cfg(feature = "ping")]
 mod ping;
 mod protocol_stack;
@@ -44,8 +46,12 @@ mod relay;
 mod swarm;
 
 pub use bandwidth::Transport as BandwidthTransport;
+pub use behaviour::{BehaviourMetrics, Instrumented, PollMetrics};
 pub use prometheus_client::registry::Registry;
 
+use libp2p_identity::PeerId;
+use libp2p_swarm::StreamProtocol;
+
 /// Set of Swarm and protocol metrics derived from emitted events.
 pub struct Metrics {
     #[cfg(feature = "dcutr")]
@@ -56,6 +62,7 @@ pub struct Metrics {
     identify: identify::Metrics,
     #[cfg(feature = "kad")]
     kad: kad::Metrics,
+    local: local::Metrics,
     #[cfg(feature = "ping")]
     ping: ping::Metrics,
     #[cfg(feature = "relay")]
@@ -83,6 +90,7 @@ impl Metrics {
             identify: identify::Metrics::new(sub_registry),
             #[cfg(feature = "kad")]
             kad: kad::Metrics::new(sub_registry),
+            local: local::Metrics::new(sub_registry),
             #[cfg(feature = "ping")]
             ping: ping::Metrics::new(sub_registry),
             #[cfg(feature = "relay")]
@@ -90,6 +98,22 @@ impl Metrics {
             swarm: swarm::Metrics::new(sub_registry),
         }
     }
+
+    /// Export the peer id and agent version of the local node as the `libp2p_local_info`
+    /// metric, e.g. to display the identity of a node alongside its traffic metrics.
+    ///
+    /// The listen and external addresses of the local node are exported as
+    /// `libp2p_local_listen_addresses_info` and `libp2p_local_external_addresses_info`, based
+    /// on the [`SwarmEvent`](libp2p_swarm::SwarmEvent)s passed to [`Recorder::record`].
+    pub fn set_local_info(&self, peer_id: PeerId, agent_version: impl Into<String>) {
+        self.local.set_info(peer_id, agent_version.into())
+    }
+
+    /// Export the protocols supported by the local node as the `libp2p_local_protocols_info`
+    /// metric, replacing any previously set protocols.
+    pub fn set_local_protocols(&self, protocols: impl IntoIterator<Item = StreamProtocol>) {
+        self.local.set_protocols(protocols)
+    }
 }
 
 /// Recorder that can record Swarm and protocol events.
@@ -143,6 +167,7 @@ impl Recorder<libp2p_relay::Event> for Metrics {
 impl<TBvEv> Recorder<libp2p_swarm::SwarmEvent<TBvEv>> for Metrics {
     fn record(&self, event: &libp2p_swarm::SwarmEvent<TBvEv>) {
         self.swarm.record(event);
+        self.local.record(event);
 
         #[cfg(feature = "identify")]
         self.identify.record(event)
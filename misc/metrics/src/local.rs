@@ -0,0 +1,190 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+use libp2p_swarm::{StreamProtocol, SwarmEvent};
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::DescriptorEncoder;
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::Registry;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// Info metrics describing the local node.
+///
+/// The peer id, agent version and protocols are set by the user, the listen and external
+/// addresses are tracked from [`SwarmEvent`]s.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Metrics(Arc<Mutex<State>>);
+
+#[derive(Default, Debug)]
+struct State {
+    peer_id: Option<PeerId>,
+    agent_version: String,
+    protocols: BTreeSet<String>,
+    listen_addresses: BTreeSet<Multiaddr>,
+    external_addresses: BTreeSet<Multiaddr>,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let metrics = Self::default();
+        registry.register_collector(Box::new(metrics.clone()));
+        metrics
+    }
+
+    pub(crate) fn set_info(&self, peer_id: PeerId, agent_version: String) {
+        let mut state = self.0.lock().unwrap();
+        state.peer_id = Some(peer_id);
+        state.agent_version = agent_version;
+    }
+
+    pub(crate) fn set_protocols(&self, protocols: impl IntoIterator<Item = StreamProtocol>) {
+        self.0.lock().unwrap().protocols = protocols.into_iter().map(|p| p.to_string()).collect();
+    }
+}
+
+impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
+    fn record(&self, event: &SwarmEvent<TBvEv>) {
+        let mut state = self.0.lock().unwrap();
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                state.listen_addresses.insert(address.clone());
+            }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                state.listen_addresses.remove(address);
+            }
+            SwarmEvent::ListenerClosed { addresses, .. } => {
+                for address in addresses {
+                    state.listen_addresses.remove(address);
+                }
+            }
+            SwarmEvent::ExternalAddrConfirmed { address, .. } => {
+                state.external_addresses.insert(address.clone());
+            }
+            SwarmEvent::ExternalAddrExpired { address, .. } => {
+                state.external_addresses.remove(address);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Collector for Metrics {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+        let state = self.0.lock().unwrap();
+
+        if let Some(peer_id) = state.peer_id {
+            let mut metric_encoder = encoder.encode_descriptor(
+                "local",
+                "Peer id and agent version of the local node",
+                None,
+                MetricType::Info,
+            )?;
+            metric_encoder.encode_info(&[
+                ("peer_id", peer_id.to_string()),
+                ("agent_version", state.agent_version.clone()),
+            ])?;
+        }
+
+        {
+            let mut metric_encoder = encoder.encode_descriptor(
+                "local_protocols",
+                "Protocols supported by the local node",
+                None,
+                MetricType::Info,
+            )?;
+            for protocol in &state.protocols {
+                metric_encoder.encode_info(&[("protocol", protocol.clone())])?;
+            }
+        }
+
+        {
+            let mut metric_encoder = encoder.encode_descriptor(
+                "local_listen_addresses",
+                "Addresses the local node is listening on",
+                None,
+                MetricType::Info,
+            )?;
+            for address in &state.listen_addresses {
+                metric_encoder.encode_info(&[("address", address.to_string())])?;
+            }
+        }
+
+        {
+            let mut metric_encoder = encoder.encode_descriptor(
+                "local_external_addresses",
+                "Confirmed external addresses of the local node",
+                None,
+                MetricType::Info,
+            )?;
+            for address in &state.external_addresses {
+                metric_encoder.encode_info(&[("address", address.to_string())])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Recorder;
+    use libp2p_core::transport::ListenerId;
+    use prometheus_client::encoding::text::encode;
+
+    #[test]
+    fn encodes_local_info() {
+        let mut registry = Registry::default();
+        let metrics = Metrics::new(&mut registry);
+        let peer_id = PeerId::random();
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        metrics.set_info(peer_id, "test/1.0.0".to_owned());
+        metrics.set_protocols([StreamProtocol::new("/ipfs/ping/1.0.0")]);
+        metrics.record(&SwarmEvent::<()>::NewListenAddr {
+            listener_id: ListenerId::next(),
+            address: address.clone(),
+            metadata: None,
+        });
+
+        let mut encoded = String::new();
+        encode(&mut encoded, &registry).unwrap();
+        assert!(encoded.contains(&format!(
+            "local_info{{peer_id=\"{peer_id}\",agent_version=\"test/1.0.0\"}} 1"
+        )));
+        assert!(encoded.contains("local_protocols_info{protocol=\"/ipfs/ping/1.0.0\"} 1"));
+        assert!(
+            encoded.contains("local_listen_addresses_info{address=\"/ip4/127.0.0.1/tcp/4001\"} 1")
+        );
+
+        metrics.record(&SwarmEvent::<()>::ExpiredListenAddr {
+            listener_id: ListenerId::next(),
+            address,
+            metadata: None,
+        });
+
+        let mut encoded = String::new();
+        encode(&mut encoded, &registry).unwrap();
+        assert!(!encoded.contains("local_listen_addresses_info{"));
+    }
+}
@@ -21,10 +21,16 @@
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::Family;
-use prometheus_client::registry::Registry;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::registry::{Registry, Unit};
 
 pub(crate) struct Metrics {
     events: Family<EventLabels, Counter>,
+
+    reservations: Gauge,
+    circuits: Gauge,
+    bytes_relayed: Counter,
+    denials: Family<DenialLabels, Counter>,
 }
 
 impl Metrics {
@@ -38,7 +44,81 @@ impl Metrics {
             events.clone(),
         );
 
-        Self { events }
+        let reservations = Gauge::default();
+        sub_registry.register(
+            "active_reservations",
+            "Number of reservations currently held with the relay server",
+            reservations.clone(),
+        );
+
+        let circuits = Gauge::default();
+        sub_registry.register(
+            "active_circuits",
+            "Number of circuits currently relayed by the relay server",
+            circuits.clone(),
+        );
+
+        let bytes_relayed = Counter::default();
+        sub_registry.register_with_unit(
+            "relayed",
+            "Bytes relayed over closed circuits in both directions",
+            Unit::Bytes,
+            bytes_relayed.clone(),
+        );
+
+        let denials = Family::default();
+        sub_registry.register(
+            "denials",
+            "Reservation and circuit requests denied by the relay server, by reason",
+            denials.clone(),
+        );
+
+        Self {
+            events,
+            reservations,
+            circuits,
+            bytes_relayed,
+            denials,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DenialLabels {
+    request: Request,
+    reason: DenialReason,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Request {
+    Reservation,
+    Circuit,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum DenialReason {
+    PeerLimitExceeded,
+    LimitExceeded,
+    RateLimited,
+    NoReservation,
+    ConnectionFailed,
+    DestinationDenied,
+    AdmissionDenied,
+    AdmissionTimedOut,
+}
+
+impl From<&libp2p_relay::DenialReason> for DenialReason {
+    fn from(reason: &libp2p_relay::DenialReason) -> Self {
+        match reason {
+            libp2p_relay::DenialReason::PeerLimitExceeded => DenialReason::PeerLimitExceeded,
+            libp2p_relay::DenialReason::LimitExceeded => DenialReason::LimitExceeded,
+            libp2p_relay::DenialReason::RateLimited => DenialReason::RateLimited,
+            libp2p_relay::DenialReason::NoReservation => DenialReason::NoReservation,
+            libp2p_relay::DenialReason::ConnectionFailed => DenialReason::ConnectionFailed,
+            libp2p_relay::DenialReason::DestinationDenied => DenialReason::DestinationDenied,
+            libp2p_relay::DenialReason::AdmissionDenied => DenialReason::AdmissionDenied,
+            libp2p_relay::DenialReason::AdmissionTimedOut => DenialReason::AdmissionTimedOut,
+        }
     }
 }
 
@@ -54,6 +134,7 @@ enum EventType {
     ReservationReqDenied,
     ReservationReqDenyFailed,
     ReservationTimedOut,
+    ReservationClosed,
     CircuitReqDenied,
     CircuitReqDenyFailed,
     CircuitReqOutboundConnectFailed,
@@ -76,6 +157,7 @@ impl From<&libp2p_relay::Event> for EventType {
                 EventType::ReservationReqDenyFailed
             }
             libp2p_relay::Event::ReservationTimedOut { .. } => EventType::ReservationTimedOut,
+            libp2p_relay::Event::ReservationClosed { .. } => EventType::ReservationClosed,
             libp2p_relay::Event::CircuitReqDenied { .. } => EventType::CircuitReqDenied,
             #[allow(deprecated)]
             libp2p_relay::Event::CircuitReqOutboundConnectFailed { .. } => {
@@ -98,5 +180,39 @@ impl super::Recorder<libp2p_relay::Event> for Metrics {
                 event: event.into(),
             })
             .inc();
+
+        match event {
+            libp2p_relay::Event::ReservationReqAccepted { renewed: false, .. } => {
+                self.reservations.inc();
+            }
+            libp2p_relay::Event::ReservationTimedOut { .. }
+            | libp2p_relay::Event::ReservationClosed { .. } => {
+                self.reservations.dec();
+            }
+            libp2p_relay::Event::ReservationReqDenied { reason, .. } => {
+                self.denials
+                    .get_or_create(&DenialLabels {
+                        request: Request::Reservation,
+                        reason: reason.into(),
+                    })
+                    .inc();
+            }
+            libp2p_relay::Event::CircuitReqAccepted { .. } => {
+                self.circuits.inc();
+            }
+            libp2p_relay::Event::CircuitClosed { bytes_relayed, .. } => {
+                self.circuits.dec();
+                self.bytes_relayed.inc_by(*bytes_relayed);
+            }
+            libp2p_relay::Event::CircuitReqDenied { reason, .. } => {
+                self.denials
+                    .get_or_create(&DenialLabels {
+                        request: Request::Circuit,
+                        reason: reason.into(),
+                    })
+                    .inc();
+            }
+            _ => {}
+        }
     }
 }
@@ -33,6 +33,7 @@ use prometheus_client::registry::{Registry, Unit};
 pub(crate) struct Metrics {
     connections_incoming: Family<AddressLabels, Counter>,
     connections_incoming_error: Family<IncomingConnectionErrorLabels, Counter>,
+    connections_incoming_queued: Family<AddressLabels, Counter>,
 
     connections_established: Family<ConnectionLabels, Counter>,
     connections_establishment_duration: Family<ConnectionLabels, Histogram>,
@@ -72,6 +73,13 @@ impl Metrics {
             connections_incoming_error.clone(),
         );
 
+        let connections_incoming_queued = Family::default();
+        sub_registry.register(
+            "connections_incoming_queued",
+            "Number of incoming connections queued due to the limit of concurrent upgrades",
+            connections_incoming_queued.clone(),
+        );
+
         let new_listen_addr = Family::default();
         sub_registry.register(
             "new_listen_addr",
@@ -168,6 +176,7 @@ impl Metrics {
         Self {
             connections_incoming,
             connections_incoming_error,
+            connections_incoming_queued,
             connections_established,
             new_listen_addr,
             expired_listen_addr,
@@ -238,6 +247,13 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                     })
                     .inc();
             }
+            SwarmEvent::IncomingConnectionQueued { send_back_addr, .. } => {
+                self.connections_incoming_queued
+                    .get_or_create(&AddressLabels {
+                        protocols: protocol_stack::as_string(send_back_addr),
+                    })
+                    .inc();
+            }
             SwarmEvent::IncomingConnectionError {
                 error,
                 send_back_addr,
@@ -279,6 +295,7 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                     }
                     DialError::LocalPeerId { .. } => record(OutgoingConnectionError::LocalPeerId),
                     DialError::NoAddresses => record(OutgoingConnectionError::NoAddresses),
+                    DialError::SelfDial => record(OutgoingConnectionError::SelfDial),
                     DialError::DialPeerConditionFalse(_) => {
                         record(OutgoingConnectionError::DialPeerConditionFalse)
                     }
@@ -316,21 +333,21 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
             SwarmEvent::Dialing { .. } => {
                 self.dial_attempt.inc();
             }
-            SwarmEvent::NewExternalAddrCandidate { address } => {
+            SwarmEvent::NewExternalAddrCandidate { address, .. } => {
                 self.external_addr_candidates
                     .get_or_create(&AddressLabels {
                         protocols: protocol_stack::as_string(address),
                     })
                     .inc();
             }
-            SwarmEvent::ExternalAddrConfirmed { address } => {
+            SwarmEvent::ExternalAddrConfirmed { address, .. } => {
                 self.external_addr_confirmed
                     .get_or_create(&AddressLabels {
                         protocols: protocol_stack::as_string(address),
                     })
                     .inc();
             }
-            SwarmEvent::ExternalAddrExpired { address } => {
+            SwarmEvent::ExternalAddrExpired { address, .. } => {
                 self.external_addr_expired
                     .get_or_create(&AddressLabels {
                         protocols: protocol_stack::as_string(address),
@@ -406,6 +423,7 @@ enum PeerStatus {
 enum OutgoingConnectionError {
     LocalPeerId,
     NoAddresses,
+    SelfDial,
     DialPeerConditionFalse,
     Aborted,
     WrongPeerId,
@@ -428,6 +446,7 @@ enum IncomingConnectionError {
     TransportErrorOther,
     Aborted,
     Denied,
+    UpgradeQueueTimeout,
 }
 
 impl From<&libp2p_swarm::ListenError> for IncomingConnectionError {
@@ -443,6 +462,9 @@ impl From<&libp2p_swarm::ListenError> for IncomingConnectionError {
             ) => IncomingConnectionError::TransportErrorOther,
             libp2p_swarm::ListenError::Aborted => IncomingConnectionError::Aborted,
             libp2p_swarm::ListenError::Denied { .. } => IncomingConnectionError::Denied,
+            libp2p_swarm::ListenError::UpgradeQueueTimeout => {
+                IncomingConnectionError::UpgradeQueueTimeout
+            }
         }
     }
 }
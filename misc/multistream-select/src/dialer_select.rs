@@ -20,14 +20,16 @@
 
 //! Protocol negotiation strategies for the peer acting as the dialer.
 
+use crate::observer::Observation;
 use crate::protocol::{HeaderLine, Message, MessageIO, Protocol, ProtocolError};
-use crate::{Negotiated, NegotiationError, Version};
+use crate::{Negotiated, NegotiationError, NegotiationObserver, NegotiationRole, Version};
 
 use futures::prelude::*;
 use std::{
     convert::TryFrom as _,
     iter, mem,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -61,6 +63,7 @@ where
         state: State::SendHeader {
             io: MessageIO::new(inner),
         },
+        observation: None,
     }
 }
 
@@ -72,6 +75,7 @@ pub struct DialerSelectFuture<R, I: Iterator> {
     protocols: iter::Peekable<I>,
     state: State<R, I::Item>,
     version: Version,
+    observation: Option<Observation>,
 }
 
 enum State<R, N> {
@@ -92,7 +96,40 @@ where
 {
     type Output = Result<(I::Item, Negotiated<R>), NegotiationError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = futures::ready!(self.as_mut().poll_negotiate(cx));
+
+        if let Some(observation) = self.project().observation.take() {
+            observation.finish(
+                NegotiationRole::Dialer,
+                result.as_ref().map(|(protocol, _)| protocol.as_ref()),
+            );
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+impl<R, I: Iterator> DialerSelectFuture<R, I> {
+    /// Reports the negotiation to the given observer once it finished, replacing any previously
+    /// installed observer. `None` removes the observer.
+    pub fn with_observer(mut self, observer: Option<Arc<dyn NegotiationObserver>>) -> Self {
+        self.observation = observer.map(Observation::start);
+        self
+    }
+}
+
+impl<R, I> DialerSelectFuture<R, I>
+where
+    R: AsyncRead + AsyncWrite + Unpin,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    #[allow(clippy::type_complexity)]
+    fn poll_negotiate(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(I::Item, Negotiated<R>), NegotiationError>> {
         let this = self.project();
 
         loop {
@@ -132,6 +169,9 @@ where
                         return Poll::Ready(Err(From::from(err)));
                     }
                     tracing::debug!(protocol=%p, "Dialer: Proposed protocol");
+                    if let Some(observation) = this.observation.as_mut() {
+                        observation.offer(protocol.as_ref());
+                    }
 
                     if this.protocols.peek().is_some() {
                         *this.state = State::FlushProtocol { io, protocol }
@@ -45,6 +45,10 @@
 //!
 //! See [`dialer_select_proto`] and [`listener_select_proto`].
 //!
+//! A negotiation can be observed, e.g. to collect protocol usage statistics, by installing a
+//! [`NegotiationObserver`] via [`DialerSelectFuture::with_observer`] or
+//! [`ListenerSelectFuture::with_observer`].
+//!
 //! ## [`Negotiated`]
 //!
 //! A `Negotiated` represents an I/O stream that has settled on a protocol
@@ -91,11 +95,13 @@ mod dialer_select;
 mod length_delimited;
 mod listener_select;
 mod negotiated;
+mod observer;
 mod protocol;
 
 pub use self::dialer_select::{dialer_select_proto, DialerSelectFuture};
 pub use self::listener_select::{listener_select_proto, ListenerSelectFuture};
 pub use self::negotiated::{Negotiated, NegotiatedComplete, NegotiationError};
+pub use self::observer::{Negotiation, NegotiationObserver, NegotiationRole};
 pub use self::protocol::ProtocolError;
 
 /// Supported multistream-select versions.
@@ -21,8 +21,9 @@
 //! Protocol negotiation strategies for the peer acting as the listener
 //! in a multistream-select protocol negotiation.
 
+use crate::observer::Observation;
 use crate::protocol::{HeaderLine, Message, MessageIO, Protocol, ProtocolError};
-use crate::{Negotiated, NegotiationError};
+use crate::{Negotiated, NegotiationError, NegotiationObserver, NegotiationRole};
 
 use futures::prelude::*;
 use smallvec::SmallVec;
@@ -30,6 +31,7 @@ use std::{
     convert::TryFrom as _,
     mem,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -65,6 +67,7 @@ where
             io: MessageIO::new(inner),
         },
         last_sent_na: false,
+        observation: None,
     }
 }
 
@@ -83,6 +86,7 @@ pub struct ListenerSelectFuture<R, N> {
     /// considered failed, but not with a protocol violation or I/O
     /// error.
     last_sent_na: bool,
+    observation: Option<Observation>,
 }
 
 enum State<R, N> {
@@ -116,7 +120,45 @@ where
 {
     type Output = Result<(N, Negotiated<R>), NegotiationError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = futures::ready!(self.as_mut().poll_negotiate(cx));
+
+        if let Some(observation) = self.project().observation.take() {
+            observation.finish(
+                NegotiationRole::Listener,
+                result.as_ref().map(|(protocol, _)| protocol.as_ref()),
+            );
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+impl<R, N: AsRef<str>> ListenerSelectFuture<R, N> {
+    /// Reports the negotiation to the given observer once it finished, replacing any previously
+    /// installed observer. `None` removes the observer.
+    pub fn with_observer(mut self, observer: Option<Arc<dyn NegotiationObserver>>) -> Self {
+        self.observation = observer.map(|observer| {
+            let mut observation = Observation::start(observer);
+            for (name, _) in &self.protocols {
+                observation.offer(name.as_ref());
+            }
+            observation
+        });
+        self
+    }
+}
+
+impl<R, N> ListenerSelectFuture<R, N>
+where
+    R: AsyncRead + AsyncWrite + Unpin,
+    N: AsRef<str> + Clone,
+{
+    #[allow(clippy::type_complexity)]
+    fn poll_negotiate(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(N, Negotiated<R>), NegotiationError>> {
         let this = self.project();
 
         loop {
@@ -0,0 +1,101 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Observation of protocol negotiations, e.g. for protocol usage statistics.
+
+use crate::NegotiationError;
+use instant::Instant;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receives a [`Negotiation`] for every finished protocol negotiation it is installed on, see
+/// [`DialerSelectFuture::with_observer`](crate::DialerSelectFuture::with_observer) and
+/// [`ListenerSelectFuture::with_observer`](crate::ListenerSelectFuture::with_observer).
+///
+/// The observer is called on the task driving the negotiation and should thus return quickly.
+pub trait NegotiationObserver: Send + Sync + 'static {
+    fn on_negotiation(&self, negotiation: &Negotiation<'_>);
+}
+
+impl<F> NegotiationObserver for F
+where
+    F: Fn(&Negotiation<'_>) + Send + Sync + 'static,
+{
+    fn on_negotiation(&self, negotiation: &Negotiation<'_>) {
+        self(negotiation)
+    }
+}
+
+/// The role of the local node in a protocol negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NegotiationRole {
+    /// The local node proposed the protocols, see [`dialer_select_proto`](crate::dialer_select_proto).
+    Dialer,
+    /// The remote proposed the protocols, see [`listener_select_proto`](crate::listener_select_proto).
+    Listener,
+}
+
+/// A finished protocol negotiation.
+#[derive(Debug)]
+pub struct Negotiation<'a> {
+    /// The role of the local node.
+    pub role: NegotiationRole,
+    /// As dialer, the protocols proposed to the remote, in order. As listener, the protocols
+    /// supported by the local node.
+    pub offered: &'a [String],
+    /// The protocol agreed upon, or the reason the negotiation failed.
+    ///
+    /// A dialer using [`Version::V1Lazy`](crate::Version::V1Lazy) with a single protocol
+    /// settles on it without awaiting the confirmation of the remote.
+    pub outcome: Result<&'a str, &'a NegotiationError>,
+    /// The time from the start of the negotiation until it finished.
+    pub duration: Duration,
+}
+
+/// The state of an observed negotiation.
+pub(crate) struct Observation {
+    observer: Arc<dyn NegotiationObserver>,
+    started: Instant,
+    offered: Vec<String>,
+}
+
+impl Observation {
+    /// Starts observing a negotiation.
+    pub(crate) fn start(observer: Arc<dyn NegotiationObserver>) -> Self {
+        Self {
+            observer,
+            started: Instant::now(),
+            offered: Vec::new(),
+        }
+    }
+
+    pub(crate) fn offer(&mut self, protocol: &str) {
+        self.offered.push(protocol.to_owned());
+    }
+
+    pub(crate) fn finish(self, role: NegotiationRole, outcome: Result<&str, &NegotiationError>) {
+        self.observer.on_negotiation(&Negotiation {
+            role,
+            offered: &self.offered,
+            outcome,
+            duration: self.started.elapsed(),
+        });
+    }
+}
@@ -0,0 +1,71 @@
+use futures::future;
+use multistream_select::{
+    dialer_select_proto, listener_select_proto, Negotiation, NegotiationObserver, NegotiationRole,
+    Version,
+};
+use std::sync::{Arc, Mutex};
+
+type Observed = Arc<Mutex<Vec<(NegotiationRole, Vec<String>, Option<String>)>>>;
+
+#[async_std::test]
+async fn observer_reports_negotiations() {
+    let observed = Observed::default();
+    let observed_clone = observed.clone();
+    let observer: Arc<dyn NegotiationObserver> = Arc::new(move |n: &Negotiation<'_>| {
+        observed_clone.lock().unwrap().push((
+            n.role,
+            n.offered.to_vec(),
+            n.outcome.ok().map(ToOwned::to_owned),
+        ));
+    });
+
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+    let (dialer, listener) = future::join(
+        dialer_select_proto(client, ["/proto3", "/proto2"], Version::V1)
+            .with_observer(Some(observer.clone())),
+        listener_select_proto(server, ["/proto1", "/proto2"]).with_observer(Some(observer.clone())),
+    )
+    .await;
+    assert_eq!(dialer.unwrap().0, "/proto2");
+    assert_eq!(listener.unwrap().0, "/proto2");
+
+    // The listener only learns about the failure once the dialer drops the stream.
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+    let listener = async_std::task::spawn(
+        listener_select_proto(server, ["/proto1"]).with_observer(Some(observer.clone())),
+    );
+    assert!(dialer_select_proto(client, ["/proto3"], Version::V1)
+        .with_observer(Some(observer))
+        .await
+        .is_err());
+    assert!(listener.await.is_err());
+
+    // Negotiations without an observer are not reported.
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+    let (dialer, listener) = future::join(
+        dialer_select_proto(client, ["/proto1"], Version::V1),
+        listener_select_proto(server, ["/proto1"]),
+    )
+    .await;
+    assert!(dialer.is_ok() && listener.is_ok());
+
+    let mut observed = observed.lock().unwrap().clone();
+    observed.sort();
+    assert_eq!(
+        observed,
+        vec![
+            (NegotiationRole::Dialer, vec!["/proto3".to_owned()], None),
+            (
+                NegotiationRole::Dialer,
+                vec!["/proto3".to_owned(), "/proto2".to_owned()],
+                Some("/proto2".to_owned())
+            ),
+            (NegotiationRole::Listener, vec!["/proto1".to_owned()], None),
+            (
+                NegotiationRole::Listener,
+                vec!["/proto1".to_owned(), "/proto2".to_owned()],
+                Some("/proto2".to_owned())
+            ),
+        ]
+    );
+}
@@ -41,6 +41,31 @@ where
         .unwrap()
 }
 
+/// Like [`connected_muxers_on_memory_ring_buffer`] but upgrades both ends with the given config.
+pub async fn connected_muxers_on_memory_ring_buffer_with<MC, M, E>(config: MC) -> (M, M)
+where
+    MC: InboundConnectionUpgrade<futures_ringbuf::Endpoint, Error = E, Output = M>
+        + OutboundConnectionUpgrade<futures_ringbuf::Endpoint, Error = E, Output = M>
+        + Send
+        + 'static
+        + Clone,
+    <MC as UpgradeInfo>::Info: Send,
+    <<MC as UpgradeInfo>::InfoIter as IntoIterator>::IntoIter: Send,
+    <MC as InboundConnectionUpgrade<futures_ringbuf::Endpoint>>::Future: Send,
+    <MC as OutboundConnectionUpgrade<futures_ringbuf::Endpoint>>::Future: Send,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let (alice, bob) = futures_ringbuf::Endpoint::pair(100, 100);
+    let info = config.protocol_info().into_iter().next().unwrap();
+
+    let alice_upgrade = config.clone().upgrade_inbound(alice, info.clone());
+    let bob_upgrade = config.upgrade_outbound(bob, info);
+
+    futures::future::try_join(alice_upgrade, bob_upgrade)
+        .await
+        .unwrap()
+}
+
 /// Verifies that Alice can send a message and immediately close the stream afterwards and Bob can use `read_to_end` to read the entire message.
 pub async fn close_implies_flush<A, B, S, E>(alice: A, bob: B)
 where
@@ -28,6 +28,8 @@ use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent};
 use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo};
 use std::collections::VecDeque;
 use std::io::{IoSlice, IoSliceMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::Waker;
 use std::{
     io, iter,
@@ -52,6 +54,11 @@ pub struct Muxer<C> {
     inbound_stream_buffer: VecDeque<Stream>,
     /// Waker to be called when new inbound streams are available.
     inbound_stream_waker: Option<Waker>,
+    /// The per-poll read and write budgets handed to every stream of this connection.
+    budget: PollBudget,
+    /// Incremented at the start of every [`StreamMuxer::poll`], i.e. once per poll of the
+    /// connection task, telling the streams to start over with a fresh budget.
+    poll_epoch: Arc<AtomicU64>,
 }
 
 /// How many streams to buffer before we start resetting them.
@@ -65,11 +72,16 @@ where
     C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
     /// Create a new Yamux connection.
-    fn new(connection: Either<yamux012::Connection<C>, yamux013::Connection<C>>) -> Self {
+    fn new(
+        connection: Either<yamux012::Connection<C>, yamux013::Connection<C>>,
+        budget: PollBudget,
+    ) -> Self {
         Muxer {
             connection,
             inbound_stream_buffer: VecDeque::default(),
             inbound_stream_waker: None,
+            budget,
+            poll_epoch: Arc::default(),
         }
     }
 }
@@ -103,13 +115,15 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let budget = self.budget;
+        let poll_epoch = self.poll_epoch.clone();
         let stream = match self.connection.as_mut() {
             Either::Left(c) => ready!(c.poll_new_outbound(cx))
                 .map_err(|e| Error(Either::Left(e)))
-                .map(|s| Stream(Either::Left(s))),
+                .map(|s| Stream::new(Either::Left(s), budget, poll_epoch)),
             Either::Right(c) => ready!(c.poll_new_outbound(cx))
                 .map_err(|e| Error(Either::Right(e)))
-                .map(|s| Stream(Either::Right(s))),
+                .map(|s| Stream::new(Either::Right(s), budget, poll_epoch)),
         }?;
         Poll::Ready(Ok(stream))
     }
@@ -128,12 +142,13 @@ where
         cx: &mut Context<'_>,
     ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
         let this = self.get_mut();
+        this.poll_epoch.fetch_add(1, Ordering::Relaxed);
 
         let inbound_stream = ready!(this.poll_inner(cx))?;
 
         if this.inbound_stream_buffer.len() >= MAX_BUFFERED_INBOUND_STREAMS {
             tracing::warn!(
-                stream=%inbound_stream.0,
+                stream=%inbound_stream.inner,
                 "dropping stream because buffer is full"
             );
             drop(inbound_stream);
@@ -153,7 +168,32 @@ where
 
 /// A stream produced by the yamux multiplexer.
 #[derive(Debug)]
-pub struct Stream(Either<yamux012::Stream, yamux013::Stream>);
+pub struct Stream {
+    inner: Either<yamux012::Stream, yamux013::Stream>,
+    read_budget: Budget,
+    write_budget: Budget,
+    /// See [`Muxer::poll_epoch`].
+    poll_epoch: Arc<AtomicU64>,
+}
+
+impl Stream {
+    fn new(
+        inner: Either<yamux012::Stream, yamux013::Stream>,
+        budget: PollBudget,
+        poll_epoch: Arc<AtomicU64>,
+    ) -> Self {
+        Stream {
+            inner,
+            read_budget: Budget::new(budget.read),
+            write_budget: Budget::new(budget.write),
+            poll_epoch,
+        }
+    }
+
+    fn poll_epoch(&self) -> u64 {
+        self.poll_epoch.load(Ordering::Relaxed)
+    }
+}
 
 impl AsyncRead for Stream {
     fn poll_read(
@@ -161,7 +201,12 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_read(cx, buf))
+        let this = &mut *self;
+        let epoch = this.poll_epoch();
+        ready!(this.read_budget.poll_acquire(cx, epoch));
+        let res = either::for_both!(this.inner.as_mut(), s => Pin::new(s).poll_read(cx, buf));
+        this.read_budget.record(&res);
+        res
     }
 
     fn poll_read_vectored(
@@ -169,7 +214,13 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         bufs: &mut [IoSliceMut<'_>],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_read_vectored(cx, bufs))
+        let this = &mut *self;
+        let epoch = this.poll_epoch();
+        ready!(this.read_budget.poll_acquire(cx, epoch));
+        let res =
+            either::for_both!(this.inner.as_mut(), s => Pin::new(s).poll_read_vectored(cx, bufs));
+        this.read_budget.record(&res);
+        res
     }
 }
 
@@ -179,7 +230,12 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_write(cx, buf))
+        let this = &mut *self;
+        let epoch = this.poll_epoch();
+        ready!(this.write_budget.poll_acquire(cx, epoch));
+        let res = either::for_both!(this.inner.as_mut(), s => Pin::new(s).poll_write(cx, buf));
+        this.write_budget.record(&res);
+        res
     }
 
     fn poll_write_vectored(
@@ -187,15 +243,96 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_write_vectored(cx, bufs))
+        let this = &mut *self;
+        let epoch = this.poll_epoch();
+        ready!(this.write_budget.poll_acquire(cx, epoch));
+        let res =
+            either::for_both!(this.inner.as_mut(), s => Pin::new(s).poll_write_vectored(cx, bufs));
+        this.write_budget.record(&res);
+        res
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_flush(cx))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_flush(cx))
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        either::for_both!(self.0.as_mut(), s => Pin::new(s).poll_close(cx))
+        either::for_both!(self.inner.as_mut(), s => Pin::new(s).poll_close(cx))
+    }
+}
+
+/// The number of bytes a [`Stream`] may read, respectively write, before yielding to the executor.
+const DEFAULT_POLL_BUDGET: usize = 256 * 1024;
+
+/// The per-poll read and write budgets of the streams of a connection, see
+/// [`Config::set_read_budget`] and [`Config::set_write_budget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PollBudget {
+    read: usize,
+    write: usize,
+}
+
+impl Default for PollBudget {
+    fn default() -> Self {
+        Self {
+            read: DEFAULT_POLL_BUDGET,
+            write: DEFAULT_POLL_BUDGET,
+        }
+    }
+}
+
+/// Tracks the bytes transferred in one direction of a [`Stream`] within the current poll of the
+/// connection.
+///
+/// A stream that is continuously ready, e.g. during a large transfer, would otherwise never return
+/// [`Poll::Pending`] and thus never give the task driving it, usually the connection task, a chance
+/// to yield. Once the budget is exhausted we schedule an immediate wake-up and return
+/// [`Poll::Pending`] instead, allowing other tasks on the same executor to run.
+///
+/// The budget starts over whenever the connection is polled again, so bytes transferred during
+/// earlier polls never count against the current one.
+#[derive(Debug)]
+struct Budget {
+    limit: usize,
+    used: usize,
+    /// The [`Muxer::poll_epoch`] that `used` was accumulated in.
+    epoch: u64,
+}
+
+impl Budget {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: 0,
+            epoch: 0,
+        }
+    }
+
+    /// Returns [`Poll::Pending`] and schedules a wake-up if the budget of the given poll epoch is
+    /// exhausted.
+    fn poll_acquire(&mut self, cx: &mut Context<'_>, epoch: u64) -> Poll<()> {
+        if self.epoch != epoch {
+            self.epoch = epoch;
+            self.used = 0;
+        }
+
+        if self.used < self.limit {
+            return Poll::Ready(());
+        }
+
+        self.used = 0;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+
+    /// Accounts for the outcome of a read or write.
+    fn record(&mut self, res: &Poll<io::Result<usize>>) {
+        match res {
+            Poll::Ready(Ok(n)) => self.used = self.used.saturating_add(*n),
+            Poll::Ready(Err(_)) => {}
+            // The task yields anyway, thus start with a fresh budget next time.
+            Poll::Pending => self.used = 0,
+        }
     }
 }
 
@@ -204,15 +341,17 @@ where
     C: AsyncRead + AsyncWrite + Unpin + 'static,
 {
     fn poll_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<Stream, Error>> {
+        let budget = self.budget;
+        let poll_epoch = self.poll_epoch.clone();
         let stream = match self.connection.as_mut() {
             Either::Left(c) => ready!(c.poll_next_inbound(cx))
                 .ok_or(Error(Either::Left(yamux012::ConnectionError::Closed)))?
                 .map_err(|e| Error(Either::Left(e)))
-                .map(|s| Stream(Either::Left(s)))?,
+                .map(|s| Stream::new(Either::Left(s), budget, poll_epoch))?,
             Either::Right(c) => ready!(c.poll_next_inbound(cx))
                 .ok_or(Error(Either::Right(yamux013::ConnectionError::Closed)))?
                 .map_err(|e| Error(Either::Right(e)))
-                .map(|s| Stream(Either::Right(s)))?,
+                .map(|s| Stream::new(Either::Right(s), budget, poll_epoch))?,
         };
 
         Poll::Ready(Ok(stream))
@@ -221,11 +360,11 @@ where
 
 /// The yamux configuration.
 #[derive(Debug, Clone)]
-pub struct Config(Either<Config012, Config013>);
+pub struct Config(Either<Config012, Config013>, PollBudget);
 
 impl Default for Config {
     fn default() -> Self {
-        Self(Either::Right(Config013::default()))
+        Self(Either::Right(Config013::default()), PollBudget::default())
     }
 }
 
@@ -292,20 +431,26 @@ impl Config {
     /// it will be used for an inbound or outbound upgrade.
     #[deprecated(note = "Will be removed with the next breaking release.")]
     pub fn client() -> Self {
-        Self(Either::Left(Config012 {
-            mode: Some(yamux012::Mode::Client),
-            ..Default::default()
-        }))
+        Self(
+            Either::Left(Config012 {
+                mode: Some(yamux012::Mode::Client),
+                ..Default::default()
+            }),
+            PollBudget::default(),
+        )
     }
 
     /// Creates a new `YamuxConfig` in server mode, regardless of whether
     /// it will be used for an inbound or outbound upgrade.
     #[deprecated(note = "Will be removed with the next breaking release.")]
     pub fn server() -> Self {
-        Self(Either::Left(Config012 {
-            mode: Some(yamux012::Mode::Server),
-            ..Default::default()
-        }))
+        Self(
+            Either::Left(Config012 {
+                mode: Some(yamux012::Mode::Server),
+                ..Default::default()
+            }),
+            PollBudget::default(),
+        )
     }
 
     /// Sets the size (in bytes) of the receive window per substream.
@@ -327,6 +472,37 @@ impl Config {
         self.set(|cfg| cfg.set_max_num_streams(num_streams))
     }
 
+    /// Sets the number of bytes a stream may read before it yields to the executor.
+    ///
+    /// A stream that is continuously readable, e.g. during a large transfer, would otherwise keep
+    /// the task driving it busy indefinitely, starving other connections on the same executor.
+    /// Once the budget is exhausted, the stream schedules an immediate wake-up and returns
+    /// [`Poll::Pending`]. Defaults to 256 KiB.
+    ///
+    /// Unlike the other configuration options, this does not switch to the old version of `yamux`.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bytes` is zero.
+    pub fn set_read_budget(&mut self, num_bytes: usize) -> &mut Self {
+        assert!(num_bytes > 0, "read budget must be non-zero");
+        self.1.read = num_bytes;
+        self
+    }
+
+    /// Sets the number of bytes a stream may write before it yields to the executor.
+    ///
+    /// See [`Config::set_read_budget`]. Defaults to 256 KiB.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bytes` is zero.
+    pub fn set_write_budget(&mut self, num_bytes: usize) -> &mut Self {
+        assert!(num_bytes > 0, "write budget must be non-zero");
+        self.1.write = num_bytes;
+        self
+    }
+
     /// Sets the window update mode that determines when the remote
     /// is given new credit for sending more data.
     #[deprecated(
@@ -380,7 +556,7 @@ where
             }
         };
 
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(Muxer::new(connection, self.1)))
     }
 }
 
@@ -404,7 +580,7 @@ where
             }
         };
 
-        future::ready(Ok(Muxer::new(connection)))
+        future::ready(Ok(Muxer::new(connection, self.1)))
     }
 }
 
@@ -451,11 +627,65 @@ mod test {
         let mut cfg = Config::default();
         assert!(matches!(
             cfg,
-            Config(Either::Right(Config013(yamux013::Config { .. })))
+            Config(Either::Right(Config013(yamux013::Config { .. })), _)
         ));
 
         // In case a user makes any configurations, use yamux v0.12 instead.
         cfg.set_max_num_streams(42);
-        assert!(matches!(cfg, Config(Either::Left(Config012 { .. }))));
+        assert!(matches!(cfg, Config(Either::Left(Config012 { .. }), _)));
+    }
+
+    #[test]
+    fn budget_setters_keep_v013() {
+        let mut cfg = Config::default();
+        cfg.set_read_budget(1024).set_write_budget(2048);
+
+        assert!(matches!(cfg, Config(Either::Right(Config013(_)), _)));
+        assert_eq!(
+            cfg.1,
+            PollBudget {
+                read: 1024,
+                write: 2048
+            }
+        );
+    }
+
+    #[test]
+    fn budget_yields_once_exhausted() {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut budget = Budget::new(10);
+
+        assert!(budget.poll_acquire(&mut cx, 0).is_ready());
+        budget.record(&Poll::Ready(Ok(6)));
+        assert!(budget.poll_acquire(&mut cx, 0).is_ready());
+        budget.record(&Poll::Ready(Ok(6)));
+
+        // Exhausted, thus yield once and start over afterwards.
+        assert!(budget.poll_acquire(&mut cx, 0).is_pending());
+        assert!(budget.poll_acquire(&mut cx, 0).is_ready());
+
+        // Returning `Poll::Pending` resets the budget as well.
+        budget.record(&Poll::Ready(Ok(9)));
+        budget.record(&Poll::Pending);
+        budget.record(&Poll::Ready(Ok(9)));
+        assert!(budget.poll_acquire(&mut cx, 0).is_ready());
+    }
+
+    #[test]
+    fn budget_resets_on_new_poll() {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut budget = Budget::new(10);
+
+        assert!(budget.poll_acquire(&mut cx, 0).is_ready());
+        budget.record(&Poll::Ready(Ok(10)));
+
+        // Bytes of the previous poll do not count against the next one.
+        assert!(budget.poll_acquire(&mut cx, 1).is_ready());
+        budget.record(&Poll::Ready(Ok(9)));
+        assert!(budget.poll_acquire(&mut cx, 1).is_ready());
+        budget.record(&Poll::Ready(Ok(1)));
+        assert!(budget.poll_acquire(&mut cx, 1).is_pending());
     }
 }
@@ -15,3 +15,15 @@ async fn read_after_close() {
 
     libp2p_muxer_test_harness::read_after_close(alice, bob).await;
 }
+
+#[async_std::test]
+async fn close_implies_flush_with_small_budgets() {
+    let mut cfg = Config::default();
+    cfg.set_read_budget(1).set_write_budget(1);
+
+    let (alice, bob) =
+        libp2p_muxer_test_harness::connected_muxers_on_memory_ring_buffer_with::<Config, _, _>(cfg)
+            .await;
+
+    libp2p_muxer_test_harness::close_implies_flush(alice, bob).await;
+}
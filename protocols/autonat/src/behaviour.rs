@@ -36,8 +36,8 @@ use libp2p_request_response::{
 };
 use libp2p_swarm::{
     behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm},
-    ConnectionDenied, ConnectionId, ListenAddresses, NetworkBehaviour, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    ConnectionDenied, ConnectionId, ListenAddresses, NetworkBehaviour, Reachability, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use std::{
     collections::{HashMap, HashSet, VecDeque},
@@ -193,6 +193,9 @@ pub struct Behaviour {
     // Ongoing outbound probes and mapped to the inner request id.
     ongoing_outbound: HashMap<OutboundRequestId, ProbeId>,
 
+    // Ongoing outbound probes started via `Behaviour::probe_address`.
+    targeted_probes: HashSet<ProbeId>,
+
     // Connected peers with the observed address of each connection.
     // If the endpoint of a connection is relayed or not global (in case of Config::only_global_ips),
     // the observed address is `None`.
@@ -212,6 +215,9 @@ pub struct Behaviour {
 
     listen_addresses: ListenAddresses,
     other_candidates: HashSet<Multiaddr>,
+
+    // Reachability last reported to the swarm via `ToSwarm::ReachabilityChanged`.
+    reported_reachability: Reachability,
 }
 
 impl Behaviour {
@@ -230,6 +236,7 @@ impl Behaviour {
             servers: HashSet::new(),
             ongoing_inbound: HashMap::default(),
             ongoing_outbound: HashMap::default(),
+            targeted_probes: HashSet::new(),
             connected: HashMap::default(),
             nat_status: NatStatus::Unknown,
             confidence: 0,
@@ -240,6 +247,7 @@ impl Behaviour {
             probe_id: ProbeId(0),
             listen_addresses: Default::default(),
             other_candidates: Default::default(),
+            reported_reachability: Reachability::Unknown,
         }
     }
 
@@ -262,6 +270,20 @@ impl Behaviour {
         self.confidence
     }
 
+    /// Assumed reachability of the local peer, combining [`Behaviour::nat_status`] and
+    /// [`Behaviour::confidence`].
+    ///
+    /// Changes are shared with all other [`NetworkBehaviour`]s via
+    /// [`FromSwarm::ReachabilityChanged`].
+    pub fn reachability(&self) -> Reachability {
+        let confidence = self.confidence;
+        match self.nat_status {
+            NatStatus::Public(_) => Reachability::Public { confidence },
+            NatStatus::Private => Reachability::Private { confidence },
+            NatStatus::Unknown => Reachability::Unknown,
+        }
+    }
+
     /// Add a peer to the list over servers that may be used for probes.
     /// These peers are used for dial-request even if they are currently not connection, in which case a connection will be
     /// establish before sending the dial-request.
@@ -279,10 +301,23 @@ impl Behaviour {
         self.servers.retain(|p| p != peer);
     }
 
-    /// Explicitly probe the provided address for external reachability.
-    pub fn probe_address(&mut self, candidate: Multiaddr) {
-        self.other_candidates.insert(candidate);
-        self.as_client().on_new_address();
+    /// Explicitly probe the provided address for external reachability, e.g. right after
+    /// mapping a port.
+    ///
+    /// A dial-back request for only this address is sent right away, instead of waiting for the
+    /// next periodic probe. Its outcome is reported via [`OutboundProbeEvent`]s carrying the
+    /// returned [`ProbeId`]. A successful probe updates the assumed [`NatStatus`], whereas a
+    /// failure to dial the address does not, as other addresses may still be reachable.
+    ///
+    /// The address is also included in all subsequent periodic probes.
+    pub fn probe_address(&mut self, candidate: Multiaddr) -> ProbeId {
+        self.other_candidates.insert(candidate.clone());
+        let mut as_client = self.as_client();
+        let (probe_id, event) = as_client.probe_address(candidate);
+        as_client.on_new_address();
+        self.pending_actions
+            .push_back(ToSwarm::GenerateEvent(Event::OutboundProbe(event)));
+        probe_id
     }
 
     fn as_client(&mut self) -> AsClient {
@@ -297,6 +332,7 @@ impl Behaviour {
             nat_status: &mut self.nat_status,
             confidence: &mut self.confidence,
             ongoing_outbound: &mut self.ongoing_outbound,
+            targeted_probes: &mut self.targeted_probes,
             last_probe: &mut self.last_probe,
             schedule_probe: &mut self.schedule_probe,
             listen_addresses: &self.listen_addresses,
@@ -418,6 +454,12 @@ impl NetworkBehaviour for Behaviour {
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
         loop {
+            let reachability = self.reachability();
+            if reachability != self.reported_reachability {
+                self.reported_reachability = reachability;
+                return Poll::Ready(ToSwarm::ReachabilityChanged(reachability));
+            }
+
             if let Some(event) = self.pending_actions.pop_front() {
                 return Poll::Ready(event);
             }
@@ -439,7 +481,10 @@ impl NetworkBehaviour for Behaviour {
                         | request_response::Event::InboundFailure { .. } => {
                             self.as_server().handle_event(event)
                         }
-                        request_response::Event::ResponseSent { .. } => VecDeque::new(),
+                        request_response::Event::ResponseSent { .. }
+                        | request_response::Event::InboundRequestDropped { .. }
+                        | request_response::Event::StoredRequest { .. }
+                        | request_response::Event::Subscription { .. } => VecDeque::new(),
                     };
 
                     self.pending_actions.extend(actions);
@@ -536,7 +581,11 @@ impl NetworkBehaviour for Behaviour {
                 self.as_client().on_expired_address(e.addr);
             }
             FromSwarm::NewExternalAddrCandidate(e) => {
-                self.probe_address(e.addr.to_owned());
+                self.other_candidates.insert(e.addr.to_owned());
+            }
+            // Re-evaluate once for all candidates reported at once.
+            FromSwarm::NewExternalAddrCandidates(_) => {
+                self.as_client().on_new_address();
             }
             _ => {}
         }
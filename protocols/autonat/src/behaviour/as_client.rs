@@ -30,7 +30,9 @@ use instant::Instant;
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use libp2p_request_response::{self as request_response, OutboundFailure, OutboundRequestId};
-use libp2p_swarm::{ConnectionId, ListenAddresses, ToSwarm};
+use libp2p_swarm::{
+    ConnectionId, ExternalAddrExpiryReason, ExternalAddrOrigin, ListenAddresses, ToSwarm,
+};
 use rand::{seq::SliceRandom, thread_rng};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
@@ -92,6 +94,7 @@ pub(crate) struct AsClient<'a> {
     pub(crate) nat_status: &'a mut NatStatus,
     pub(crate) confidence: &'a mut usize,
     pub(crate) ongoing_outbound: &'a mut HashMap<OutboundRequestId, ProbeId>,
+    pub(crate) targeted_probes: &'a mut HashSet<ProbeId>,
     pub(crate) last_probe: &'a mut Option<Instant>,
     pub(crate) schedule_probe: &'a mut Delay,
     pub(crate) listen_addresses: &'a ListenAddresses,
@@ -118,6 +121,7 @@ impl<'a> HandleInnerEvent for AsClient<'a> {
                     .ongoing_outbound
                     .remove(&request_id)
                     .expect("OutboundRequestId exists.");
+                let targeted = self.targeted_probes.remove(&probe_id);
 
                 let event = match response.result.clone() {
                     Ok(address) => OutboundProbeEvent::Response {
@@ -136,15 +140,28 @@ impl<'a> HandleInnerEvent for AsClient<'a> {
 
                 actions.push_back(ToSwarm::GenerateEvent(Event::OutboundProbe(event)));
 
-                if let Some(old) = self.handle_reported_status(response.result.clone().into()) {
-                    actions.push_back(ToSwarm::GenerateEvent(Event::StatusChanged {
-                        old,
-                        new: self.nat_status.clone(),
-                    }));
+                // Failing to dial a single probed address doesn't tell about the other ones.
+                if !targeted || response.result.is_ok() {
+                    if let Some(old) = self.handle_reported_status(response.result.clone().into()) {
+                        // Probes stopped confirming the previously assumed public address.
+                        if let NatStatus::Public(address) = &old {
+                            actions.push_back(ToSwarm::ExternalAddrExpired {
+                                address: address.clone(),
+                                reason: ExternalAddrExpiryReason::ConfidenceDecayed,
+                            });
+                        }
+                        actions.push_back(ToSwarm::GenerateEvent(Event::StatusChanged {
+                            old,
+                            new: self.nat_status.clone(),
+                        }));
+                    }
                 }
 
                 if let Ok(address) = response.result {
-                    actions.push_back(ToSwarm::ExternalAddrConfirmed(address));
+                    actions.push_back(ToSwarm::ExternalAddrConfirmed {
+                        address,
+                        origin: ExternalAddrOrigin::Autonat,
+                    });
                 }
 
                 actions
@@ -164,7 +181,9 @@ impl<'a> HandleInnerEvent for AsClient<'a> {
                     .remove(&request_id)
                     .unwrap_or_else(|| self.probe_id.next());
 
-                self.schedule_probe.reset(Duration::ZERO);
+                if !self.targeted_probes.remove(&probe_id) {
+                    self.schedule_probe.reset(Duration::ZERO);
+                }
 
                 VecDeque::from([ToSwarm::GenerateEvent(Event::OutboundProbe(
                     OutboundProbeEvent::Error {
@@ -210,6 +229,23 @@ impl<'a> AsClient<'a> {
         }
     }
 
+    // Probe a single address right away, see `Behaviour::probe_address`.
+    pub(crate) fn probe_address(&mut self, address: Multiaddr) -> (ProbeId, OutboundProbeEvent) {
+        let probe_id = self.probe_id.next();
+        let event = match self.do_probe(probe_id, vec![address]) {
+            Ok(peer) => {
+                self.targeted_probes.insert(probe_id);
+                OutboundProbeEvent::Request { probe_id, peer }
+            }
+            Err(error) => OutboundProbeEvent::Error {
+                probe_id,
+                peer: None,
+                error,
+            },
+        };
+        (probe_id, event)
+    }
+
     // An inbound connection can indicate that we are public; adjust the delay to the next probe.
     pub(crate) fn on_inbound_connection(&mut self) {
         if *self.confidence == self.config.confidence_max {
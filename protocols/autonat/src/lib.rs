@@ -33,6 +33,7 @@ pub use self::{
     protocol::{ResponseError, DEFAULT_PROTOCOL_NAME},
 };
 pub use libp2p_request_response::{InboundFailure, OutboundFailure};
+pub use libp2p_swarm::Reachability;
 
 mod proto {
     #![allow(unreachable_pub)]
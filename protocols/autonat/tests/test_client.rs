@@ -20,7 +20,8 @@
 
 use async_std::task::JoinHandle;
 use libp2p_autonat::{
-    Behaviour, Config, Event, NatStatus, OutboundProbeError, OutboundProbeEvent, ResponseError,
+    Behaviour, Config, Event, NatStatus, OutboundProbeError, OutboundProbeEvent, Reachability,
+    ResponseError,
 };
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
@@ -98,6 +99,11 @@ async fn test_auto_probe() {
                 assert_eq!(peer, server_id);
                 assert_eq!(probe_id, id);
             }
+            SwarmEvent::ReachabilityChanged { old, new } => {
+                // The reachability is shared before the status change is reported.
+                assert_eq!(old, Reachability::Unknown);
+                assert!(new.is_public());
+            }
             SwarmEvent::Behaviour(Event::StatusChanged { old, new }) => {
                 // Expect to flip status to public
                 assert_eq!(old, NatStatus::Unknown);
@@ -158,7 +164,29 @@ async fn test_confidence() {
         client.listen().with_memory_addr_external().await;
     } else {
         let unreachable_addr = "/ip4/127.0.0.1/tcp/42".parse().unwrap();
-        client.behaviour_mut().probe_address(unreachable_addr);
+        let id = client.behaviour_mut().probe_address(unreachable_addr);
+
+        // The address is probed right away, without affecting the NAT status.
+        match client.next_behaviour_event().await {
+            Event::OutboundProbe(OutboundProbeEvent::Request { probe_id, peer }) => {
+                assert_eq!(peer, server_id);
+                assert_eq!(probe_id, id);
+            }
+            other => panic!("Unexpected behaviour event: {other:?}."),
+        }
+        match client.next_behaviour_event().await {
+            Event::OutboundProbe(OutboundProbeEvent::Error {
+                probe_id,
+                peer,
+                error: OutboundProbeError::Response(ResponseError::DialError),
+            }) => {
+                assert_eq!(peer, Some(server_id));
+                assert_eq!(probe_id, id);
+            }
+            other => panic!("Unexpected behaviour event: {other:?}."),
+        }
+        assert_eq!(client.behaviour().nat_status(), NatStatus::Unknown);
+        assert_eq!(client.behaviour().confidence(), 0);
     }
 
     for i in 0..MAX_CONFIDENCE + 1 {
@@ -97,6 +97,7 @@ async fn test_dial_back() {
                 concurrent_dial_errors,
                 established_in: _,
                 connection_id: _,
+                negotiated_protocols: _,
             } => {
                 assert_eq!(peer_id, client_id);
                 assert_eq!(num_established, NonZeroU32::new(2).unwrap());
@@ -267,7 +268,7 @@ async fn test_throttle_peer_max() {
 #[async_std::test]
 async fn test_dial_multiple_addr() {
     let (mut server, server_id, server_addr) = new_server_swarm(Some(Config {
-        throttle_clients_peer_max: 1,
+        throttle_clients_peer_max: 2,
         throttle_clients_period: Duration::from_secs(60),
         only_global_ips: false,
         ..Default::default()
@@ -281,6 +282,21 @@ async fn test_dial_multiple_addr() {
         .probe_address("/ip4/127.0.0.1/tcp/12345".parse().unwrap());
     async_std::task::spawn(client.loop_on_next());
 
+    // The probed address is dialed on its own first.
+    match server.next_behaviour_event().await {
+        Event::InboundProbe(InboundProbeEvent::Request { addresses, .. }) => {
+            assert_eq!(addresses.len(), 1);
+        }
+        other => panic!("Unexpected behaviour event: {other:?}."),
+    };
+    match server.next_behaviour_event().await {
+        Event::InboundProbe(InboundProbeEvent::Error { error, .. }) => assert!(matches!(
+            error,
+            InboundProbeError::Response(ResponseError::DialError)
+        )),
+        other => panic!("Unexpected behaviour event: {other:?}."),
+    };
+
     let dial_addresses = match server.next_behaviour_event().await {
         Event::InboundProbe(InboundProbeEvent::Request {
             peer, addresses, ..
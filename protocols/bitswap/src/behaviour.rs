@@ -0,0 +1,488 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::handler::{self, Handler};
+use crate::protocol::{Block, Entry, Message, Presence, WantType};
+use crate::store::Blockstore;
+use cid::Cid;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm};
+use libp2p_swarm::{
+    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::task::{Context, Poll};
+
+/// The configuration for a bitswap [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    max_message_size: usize,
+    max_wantlist_entries: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_message_size: 4 * 1024 * 1024,
+            max_wantlist_entries: 1024,
+        }
+    }
+}
+
+impl Config {
+    /// Sets the maximum size of a message in bytes.
+    ///
+    /// Each block is sent in its own message, thus this also limits the size of blocks that can be
+    /// exchanged. The default is 4 MiB.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets the maximum number of blocks wanted by a peer that are remembered until we can serve
+    /// them.
+    ///
+    /// Further entries are answered with a `DONT_HAVE` if the peer asked for it, but the block
+    /// won't be sent once it becomes available. The default is 1024.
+    pub fn with_max_wantlist_entries(mut self, max_wantlist_entries: usize) -> Self {
+        self.max_wantlist_entries = max_wantlist_entries;
+        self
+    }
+}
+
+/// The data exchanged with a peer, accumulated over all its connections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Ledger {
+    /// Number of block bytes sent to the peer.
+    pub bytes_sent: u64,
+    /// Number of block bytes received from the peer.
+    pub bytes_received: u64,
+    /// Number of blocks sent to the peer.
+    pub blocks_sent: u64,
+    /// Number of blocks received from the peer.
+    pub blocks_received: u64,
+}
+
+impl Ledger {
+    /// The ratio of bytes sent to bytes received, as defined by the original bitswap strategy.
+    pub fn debt_ratio(&self) -> f64 {
+        self.bytes_sent as f64 / (self.bytes_received as f64 + 1.0)
+    }
+}
+
+/// The events produced by the bitswap [`Behaviour`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A wanted block has been received and inserted into the [`Blockstore`].
+    Received { peer: PeerId, cid: Cid },
+    /// A block wanted by a peer has been written to one of its connections.
+    Sent { peer: PeerId, cid: Cid },
+    /// A peer reported whether it has a wanted block.
+    Presence { peer: PeerId, cid: Cid, have: bool },
+}
+
+#[derive(Debug, Default)]
+struct Peer {
+    connections: HashSet<ConnectionId>,
+    /// The blocks wanted by the peer which we couldn't serve yet.
+    wantlist: HashMap<Cid, WantType>,
+    ledger: Ledger,
+}
+
+/// A [`NetworkBehaviour`] exchanging blocks with other peers via bitswap.
+///
+/// Blocks are served from and received blocks inserted into the [`Blockstore`] `S`. Wanted
+/// blocks are requested from all connected peers, see [`Behaviour::want`]. Discovering peers
+/// which provide a block, e.g. via Kademlia, is up to the user.
+pub struct Behaviour<S> {
+    config: Config,
+    store: S,
+    /// The blocks we want, with their priority.
+    wantlist: HashMap<Cid, i32>,
+    peers: HashMap<PeerId, Peer>,
+    events: VecDeque<ToSwarm<Event, Message>>,
+}
+
+impl<S> Behaviour<S>
+where
+    S: Blockstore,
+{
+    /// Creates a new bitswap [`Behaviour`] using the given [`Blockstore`].
+    pub fn new(store: S, config: Config) -> Self {
+        Self {
+            config,
+            store,
+            wantlist: HashMap::new(),
+            peers: HashMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Returns a reference to the [`Blockstore`].
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns a mutable reference to the [`Blockstore`].
+    ///
+    /// Blocks inserted directly aren't served to peers which already asked for them, see
+    /// [`Behaviour::insert_block`].
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Adds a block to the wantlist and requests it from all connected peers.
+    ///
+    /// Peers connecting later receive the full wantlist. Once the block has been received,
+    /// [`Event::Received`] is reported.
+    ///
+    /// Returns `false` if the block is already present in the [`Blockstore`] or already wanted.
+    pub fn want(&mut self, cid: Cid, priority: i32) -> bool {
+        if self.store.has(&cid) || self.wantlist.contains_key(&cid) {
+            return false;
+        }
+        self.wantlist.insert(cid, priority);
+
+        let entry = Entry {
+            cid,
+            priority,
+            cancel: false,
+            want_type: WantType::Block,
+            send_dont_have: true,
+        };
+        for peer in self.peers.keys().copied().collect::<Vec<_>>() {
+            self.send(
+                peer,
+                Message {
+                    wantlist: vec![entry.clone()],
+                    ..Default::default()
+                },
+            );
+        }
+
+        true
+    }
+
+    /// Removes a block from the wantlist.
+    ///
+    /// Returns `false` if the block wasn't wanted.
+    pub fn cancel(&mut self, cid: &Cid) -> bool {
+        if self.wantlist.remove(cid).is_none() {
+            return false;
+        }
+        self.broadcast_cancel(*cid);
+
+        true
+    }
+
+    /// Returns the blocks we want.
+    pub fn wantlist(&self) -> impl Iterator<Item = &Cid> {
+        self.wantlist.keys()
+    }
+
+    /// Returns the blocks wanted by a connected peer which we don't have.
+    pub fn peer_wantlist(&self, peer: &PeerId) -> impl Iterator<Item = &Cid> {
+        self.peers
+            .get(peer)
+            .into_iter()
+            .flat_map(|peer| peer.wantlist.keys())
+    }
+
+    /// Returns the [`Ledger`] of a connected peer.
+    pub fn ledger(&self, peer: &PeerId) -> Option<&Ledger> {
+        self.peers.get(peer).map(|peer| &peer.ledger)
+    }
+
+    /// Inserts a block into the [`Blockstore`] and sends it to all peers which want it.
+    ///
+    /// The [`Cid`] isn't verified against the data.
+    pub fn insert_block(&mut self, cid: Cid, data: Vec<u8>) {
+        self.store.put(cid, data);
+        if self.wantlist.remove(&cid).is_some() {
+            self.broadcast_cancel(cid);
+        }
+        self.serve_wanting_peers(cid);
+    }
+
+    fn send(&mut self, peer_id: PeerId, message: Message) {
+        if message.is_empty() {
+            return;
+        }
+        self.events.push_back(ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: message,
+        });
+    }
+
+    fn send_block(&mut self, peer_id: PeerId, cid: Cid) {
+        let Some(data) = self.store.get(&cid) else {
+            return;
+        };
+
+        self.send(
+            peer_id,
+            Message {
+                blocks: vec![Block { cid, data }],
+                ..Default::default()
+            },
+        );
+    }
+
+    fn on_blocks_sent(&mut self, peer_id: PeerId, blocks: Vec<(Cid, usize)>) {
+        for (cid, len) in blocks {
+            if let Some(peer) = self.peers.get_mut(&peer_id) {
+                peer.ledger.bytes_sent += len as u64;
+                peer.ledger.blocks_sent += 1;
+            }
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::Sent { peer: peer_id, cid }));
+        }
+    }
+
+    fn broadcast_cancel(&mut self, cid: Cid) {
+        let entry = Entry {
+            cid,
+            priority: 0,
+            cancel: true,
+            want_type: WantType::Block,
+            send_dont_have: false,
+        };
+        for peer in self.peers.keys().copied().collect::<Vec<_>>() {
+            self.send(
+                peer,
+                Message {
+                    wantlist: vec![entry.clone()],
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    /// Serves a newly available block to all peers wanting it.
+    fn serve_wanting_peers(&mut self, cid: Cid) {
+        let wanting = self
+            .peers
+            .iter_mut()
+            .filter_map(|(peer_id, peer)| Some((*peer_id, peer.wantlist.remove(&cid)?)))
+            .collect::<Vec<_>>();
+
+        for (peer, want_type) in wanting {
+            match want_type {
+                WantType::Block => self.send_block(peer, cid),
+                WantType::Have => self.send(
+                    peer,
+                    Message {
+                        presences: vec![(cid, Presence::Have)],
+                        ..Default::default()
+                    },
+                ),
+            }
+        }
+    }
+
+    fn on_message(&mut self, peer_id: PeerId, message: Message) {
+        let Some(peer) = self.peers.get_mut(&peer_id) else {
+            return;
+        };
+        if message.full {
+            peer.wantlist.clear();
+        }
+
+        let mut response = Message::default();
+        let mut blocks_to_send = Vec::new();
+        for entry in message.wantlist {
+            if entry.cancel {
+                peer.wantlist.remove(&entry.cid);
+                continue;
+            }
+            if !self.store.has(&entry.cid) {
+                if entry.send_dont_have {
+                    response.presences.push((entry.cid, Presence::DontHave));
+                }
+                if peer.wantlist.len() >= self.config.max_wantlist_entries
+                    && !peer.wantlist.contains_key(&entry.cid)
+                {
+                    tracing::debug!(peer=%peer_id, cid=%entry.cid, "Wantlist of peer is full, ignoring entry");
+                    continue;
+                }
+                peer.wantlist.insert(entry.cid, entry.want_type);
+                continue;
+            }
+            peer.wantlist.remove(&entry.cid);
+            match entry.want_type {
+                WantType::Block => blocks_to_send.push(entry.cid),
+                WantType::Have => response.presences.push((entry.cid, Presence::Have)),
+            }
+        }
+
+        let mut received = Vec::new();
+        for Block { cid, data } in message.blocks {
+            if self.wantlist.remove(&cid).is_none() {
+                tracing::debug!(peer=%peer_id, %cid, "Ignoring unwanted block");
+                continue;
+            }
+            peer.ledger.bytes_received += data.len() as u64;
+            peer.ledger.blocks_received += 1;
+            self.store.put(cid, data);
+            received.push(cid);
+        }
+
+        for (cid, presence) in message.presences {
+            if !self.wantlist.contains_key(&cid) {
+                continue;
+            }
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::Presence {
+                    peer: peer_id,
+                    cid,
+                    have: presence == Presence::Have,
+                }));
+        }
+
+        self.send(peer_id, response);
+        for cid in blocks_to_send {
+            self.send_block(peer_id, cid);
+        }
+        for cid in received {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::Received {
+                    peer: peer_id,
+                    cid,
+                }));
+            self.broadcast_cancel(cid);
+            self.serve_wanting_peers(cid);
+        }
+    }
+
+    fn on_connection_established(
+        &mut self,
+        ConnectionEstablished {
+            peer_id,
+            connection_id,
+            ..
+        }: ConnectionEstablished,
+    ) {
+        let peer = self.peers.entry(peer_id).or_default();
+        let is_first_connection = peer.connections.is_empty();
+        peer.connections.insert(connection_id);
+
+        if is_first_connection && !self.wantlist.is_empty() {
+            let wantlist = self
+                .wantlist
+                .iter()
+                .map(|(cid, priority)| Entry {
+                    cid: *cid,
+                    priority: *priority,
+                    cancel: false,
+                    want_type: WantType::Block,
+                    send_dont_have: true,
+                })
+                .collect();
+            self.send(
+                peer_id,
+                Message {
+                    wantlist,
+                    full: true,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        ConnectionClosed {
+            peer_id,
+            connection_id,
+            remaining_established,
+            ..
+        }: ConnectionClosed,
+    ) {
+        if remaining_established == 0 {
+            self.peers.remove(&peer_id);
+        } else if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.connections.remove(&connection_id);
+        }
+    }
+}
+
+impl<S> NetworkBehaviour for Behaviour<S>
+where
+    S: Blockstore,
+{
+    type ConnectionHandler = Handler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler::new(self.config.max_message_size))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler::new(self.config.max_message_size))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(connection_established) => {
+                self.on_connection_established(connection_established)
+            }
+            FromSwarm::ConnectionClosed(connection_closed) => {
+                self.on_connection_closed(connection_closed)
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {
+            handler::Event::Received(message) => self.on_message(peer_id, message),
+            handler::Event::Sent(blocks) => self.on_blocks_sent(peer_id, blocks),
+        }
+    }
+
+    #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
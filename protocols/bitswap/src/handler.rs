@@ -0,0 +1,222 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::protocol::{self, Block, Message, Upgrade};
+use cid::Cid;
+use futures::prelude::*;
+use futures::stream::{BoxStream, SelectAll};
+use futures_bounded::Timeout;
+use libp2p_swarm::handler::{
+    ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+};
+use libp2p_swarm::{ConnectionHandler, ConnectionHandlerEvent, SubstreamProtocol};
+use std::collections::VecDeque;
+use std::io;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_CONCURRENT_OUTBOUND_STREAMS: usize = 16;
+const MAX_CONCURRENT_INBOUND_STREAMS: usize = 32;
+
+/// The events reported by the [`Handler`] to the [`Behaviour`](crate::Behaviour).
+#[derive(Debug)]
+pub enum Event {
+    /// A message has been received.
+    Received(Message),
+    /// A message has been written to the remote, containing the given blocks and their sizes.
+    Sent(Vec<(Cid, usize)>),
+}
+
+/// Protocol handler exchanging bitswap messages.
+///
+/// Every outbound message is sent on a new stream. Messages exceeding the number of concurrent
+/// outbound streams are queued until a stream becomes available. Inbound streams are read until
+/// the remote closes them, as other implementations reuse a stream for multiple messages.
+pub struct Handler {
+    max_message_size: usize,
+    /// Messages waiting for an outbound stream.
+    pending: VecDeque<Message>,
+    /// Number of outbound streams requested but not yet negotiated.
+    requested_streams: usize,
+    /// Messages being written to outbound streams, resolving to the blocks they contain.
+    outbound: futures_bounded::FuturesSet<io::Result<Vec<(Cid, usize)>>>,
+    /// Inbound streams messages are read from.
+    inbound: SelectAll<BoxStream<'static, Result<Message, quick_protobuf_codec::Error>>>,
+}
+
+impl Handler {
+    pub(crate) fn new(max_message_size: usize) -> Self {
+        Self {
+            max_message_size,
+            pending: VecDeque::new(),
+            requested_streams: 0,
+            outbound: futures_bounded::FuturesSet::new(
+                SEND_TIMEOUT,
+                MAX_CONCURRENT_OUTBOUND_STREAMS,
+            ),
+            inbound: SelectAll::new(),
+        }
+    }
+
+    fn on_fully_negotiated_inbound(
+        &mut self,
+        FullyNegotiatedInbound {
+            protocol: stream, ..
+        }: FullyNegotiatedInbound<<Self as ConnectionHandler>::InboundProtocol, ()>,
+    ) {
+        if self.inbound.len() >= MAX_CONCURRENT_INBOUND_STREAMS {
+            tracing::warn!("Dropping inbound stream because we are at capacity");
+            return;
+        }
+
+        self.inbound
+            .push(protocol::recv(stream, self.max_message_size));
+    }
+
+    fn on_fully_negotiated_outbound(
+        &mut self,
+        FullyNegotiatedOutbound {
+            protocol: (stream, protocol),
+            ..
+        }: FullyNegotiatedOutbound<<Self as ConnectionHandler>::OutboundProtocol, ()>,
+    ) {
+        self.requested_streams -= 1;
+        let Some(message) = self.pending.pop_front() else {
+            return;
+        };
+        let blocks = message
+            .blocks
+            .iter()
+            .map(|Block { cid, data }| (*cid, data.len()))
+            .collect::<Vec<_>>();
+        let max_message_size = self.max_message_size;
+
+        // Streams are only requested while below the limit, thus there is always capacity.
+        if self
+            .outbound
+            .try_push(async move {
+                protocol::send(stream, protocol, message, max_message_size).await?;
+                Ok(blocks)
+            })
+            .is_err()
+        {
+            tracing::warn!("Dropping outbound message because we are at capacity");
+        }
+    }
+}
+
+impl ConnectionHandler for Handler {
+    type FromBehaviour = Message;
+    type ToBehaviour = Event;
+    type InboundProtocol = Upgrade;
+    type OutboundProtocol = Upgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(Upgrade, ())
+    }
+
+    fn on_behaviour_event(&mut self, message: Self::FromBehaviour) {
+        self.pending.push_back(message);
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        !self.pending.is_empty()
+            || self.requested_streams > 0
+            || !self.outbound.is_empty()
+            || !self.inbound.is_empty()
+    }
+
+    #[tracing::instrument(level = "trace", name = "ConnectionHandler::poll", skip(self, cx))]
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Event>> {
+        loop {
+            match self.outbound.poll_unpin(cx) {
+                Poll::Ready(Ok(Ok(blocks))) if blocks.is_empty() => {}
+                Poll::Ready(Ok(Ok(blocks))) => {
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::Sent(
+                        blocks,
+                    )));
+                }
+                Poll::Ready(Ok(Err(e))) => {
+                    tracing::debug!("Failed to send bitswap message: {e}");
+                }
+                Poll::Ready(Err(Timeout { .. })) => {
+                    tracing::debug!("Timed out sending bitswap message");
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        loop {
+            match self.inbound.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Event::Received(
+                        message,
+                    )));
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    tracing::debug!("Failed to read bitswap message: {e}");
+                }
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
+        if self.pending.len() > self.requested_streams
+            && self.outbound.len() + self.requested_streams < MAX_CONCURRENT_OUTBOUND_STREAMS
+        {
+            self.requested_streams += 1;
+            return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                protocol: SubstreamProtocol::new(Upgrade, ()),
+            });
+        }
+
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(fully_negotiated_inbound) => {
+                self.on_fully_negotiated_inbound(fully_negotiated_inbound)
+            }
+            ConnectionEvent::FullyNegotiatedOutbound(fully_negotiated_outbound) => {
+                self.on_fully_negotiated_outbound(fully_negotiated_outbound)
+            }
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { error, .. }) => {
+                self.requested_streams -= 1;
+                self.pending.pop_front();
+                tracing::debug!("Failed to open stream for bitswap message: {error}");
+            }
+            _ => {}
+        }
+    }
+}
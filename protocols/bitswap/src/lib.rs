@@ -0,0 +1,58 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Implementation of the [Bitswap](https://specs.ipfs.tech/bitswap-protocol/) block exchange
+//! protocol.
+//!
+//! Bitswap exchanges content-addressed blocks between peers. Peers announce the blocks they want
+//! via wantlists and reply with the blocks they have, or with presence information when asked
+//! for it. Versions 1.0.0, 1.1.0 and 1.2.0 of the protocol are supported.
+//!
+//! # Usage
+//!
+//! The [`Behaviour`] struct implements a [`NetworkBehaviour`](libp2p_swarm::NetworkBehaviour)
+//! serving blocks from a [`Blockstore`] to connected peers and requesting wanted blocks from
+//! them, see [`Behaviour::want`]. The data exchanged with each peer is tracked in a [`Ledger`].
+//!
+//! Received blocks are verified against their [`Cid`](cid::Cid). Only the sha2-256 hash function
+//! is currently supported.
+
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub use self::behaviour::{Behaviour, Config, Event, Ledger};
+pub use self::store::{Blockstore, MemoryBlockstore};
+pub use cid;
+
+mod behaviour;
+mod handler;
+mod protocol;
+mod store;
+
+mod proto {
+    #![allow(unreachable_pub)]
+    include!("generated/mod.rs");
+    pub(crate) use self::bitswap::pb::{
+        mod_Message::{
+            mod_Wantlist::{Entry, WantType},
+            Block, BlockPresence, BlockPresenceType, Wantlist,
+        },
+        Message,
+    };
+}
@@ -0,0 +1,462 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::proto;
+use asynchronous_codec::Framed;
+use cid::{Cid, Version as CidVersion};
+use futures::prelude::*;
+use futures::stream::{self, BoxStream};
+use libp2p_core::{multihash::Multihash, InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p_swarm::StreamProtocol;
+use sha2::{Digest, Sha256};
+use std::{array, io};
+use void::Void;
+
+/// The supported protocols, in order of preference.
+const PROTOCOLS: [StreamProtocol; 4] = [
+    StreamProtocol::new("/ipfs/bitswap/1.2.0"),
+    StreamProtocol::new("/ipfs/bitswap/1.1.0"),
+    StreamProtocol::new("/ipfs/bitswap/1.0.0"),
+    StreamProtocol::new("/ipfs/bitswap"),
+];
+
+/// Multihash code of SHA2-256, the only hash function blocks are verified with.
+const SHA2_256: u64 = 0x12;
+/// Multicodec of DAG-PB, the implicit codec of CIDv0.
+const DAG_PB: u64 = 0x70;
+
+/// The version of the bitswap protocol negotiated on a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Version {
+    /// Blocks are sent without CID prefix, thus only CIDv0 blocks are supported.
+    V1_0,
+    /// Blocks are sent with CID prefix.
+    V1_1,
+    /// Adds `Have` wants and block presences.
+    V1_2,
+}
+
+impl Version {
+    fn from_protocol(protocol: &StreamProtocol) -> Self {
+        match protocol.as_ref() {
+            "/ipfs/bitswap/1.2.0" => Version::V1_2,
+            "/ipfs/bitswap/1.1.0" => Version::V1_1,
+            _ => Version::V1_0,
+        }
+    }
+}
+
+/// Upgrade negotiating any of the supported bitswap protocols.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Upgrade;
+
+impl UpgradeInfo for Upgrade {
+    type Info = StreamProtocol;
+    type InfoIter = array::IntoIter<StreamProtocol, 4>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        PROTOCOLS.into_iter()
+    }
+}
+
+impl<C> InboundUpgrade<C> for Upgrade {
+    type Output = C;
+    type Error = Void;
+    type Future = future::Ready<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, stream: C, _: Self::Info) -> Self::Future {
+        future::ready(Ok(stream))
+    }
+}
+
+impl<C> OutboundUpgrade<C> for Upgrade {
+    type Output = (C, StreamProtocol);
+    type Error = Void;
+    type Future = future::Ready<Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, stream: C, protocol: Self::Info) -> Self::Future {
+        future::ready(Ok((stream, protocol)))
+    }
+}
+
+/// Sends a single message on the given stream and closes it.
+pub(crate) async fn send<S>(
+    stream: S,
+    protocol: StreamProtocol,
+    message: Message,
+    max_message_size: usize,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut framed = Framed::new(
+        stream,
+        quick_protobuf_codec::Codec::<proto::Message>::new(max_message_size),
+    );
+    framed
+        .send(message.into_proto(Version::from_protocol(&protocol)))
+        .await?;
+    framed.close().await?;
+
+    Ok(())
+}
+
+/// Reads messages from the given stream until it is closed or a message fails to decode.
+pub(crate) fn recv<S>(
+    stream: S,
+    max_message_size: usize,
+) -> BoxStream<'static, Result<Message, quick_protobuf_codec::Error>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let framed = Framed::new(
+        stream,
+        quick_protobuf_codec::Codec::<proto::Message>::new(max_message_size),
+    );
+
+    stream::unfold(Some(framed), |framed| async move {
+        let mut framed = framed?;
+        match framed.next().await? {
+            Ok(message) => Some((Ok(Message::from_proto(message)), Some(framed))),
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+    .boxed()
+}
+
+/// A bitswap message, independent of the protocol version.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    pub(crate) wantlist: Vec<Entry>,
+    /// Whether `wantlist` replaces the full wantlist previously sent.
+    pub(crate) full: bool,
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) presences: Vec<(Cid, Presence)>,
+}
+
+/// An entry of a wantlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) cid: Cid,
+    pub(crate) priority: i32,
+    pub(crate) cancel: bool,
+    pub(crate) want_type: WantType,
+    pub(crate) send_dont_have: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WantType {
+    Block,
+    Have,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Block {
+    pub(crate) cid: Cid,
+    pub(crate) data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Presence {
+    Have,
+    DontHave,
+}
+
+impl Message {
+    fn into_proto(self, version: Version) -> proto::Message {
+        let entries = self
+            .wantlist
+            .into_iter()
+            .filter(|entry| version == Version::V1_2 || entry.want_type == WantType::Block)
+            .map(|entry| proto::Entry {
+                block: entry.cid.to_bytes(),
+                priority: entry.priority,
+                cancel: entry.cancel,
+                wantType: match entry.want_type {
+                    WantType::Block => proto::WantType::Block,
+                    WantType::Have => proto::WantType::Have,
+                },
+                sendDontHave: version == Version::V1_2 && entry.send_dont_have,
+            })
+            .collect::<Vec<_>>();
+        let wantlist = (!entries.is_empty() || self.full).then_some(proto::Wantlist {
+            entries,
+            full: self.full,
+        });
+
+        let (blocks, payload) = match version {
+            Version::V1_0 => {
+                let blocks = self
+                    .blocks
+                    .into_iter()
+                    .filter(|block| {
+                        let is_v0 = block.cid.version() == CidVersion::V0;
+                        if !is_v0 {
+                            tracing::debug!(cid=%block.cid, "Bitswap 1.0.0 only supports CIDv0 blocks");
+                        }
+                        is_v0
+                    })
+                    .map(|block| block.data)
+                    .collect();
+                (blocks, Vec::new())
+            }
+            Version::V1_1 | Version::V1_2 => {
+                let payload = self
+                    .blocks
+                    .into_iter()
+                    .map(|block| proto::Block {
+                        prefix: cid_prefix(&block.cid),
+                        data: block.data,
+                    })
+                    .collect();
+                (Vec::new(), payload)
+            }
+        };
+
+        let block_presences = match version {
+            Version::V1_2 => self
+                .presences
+                .into_iter()
+                .map(|(cid, presence)| proto::BlockPresence {
+                    cid: cid.to_bytes(),
+                    type_pb: match presence {
+                        Presence::Have => proto::BlockPresenceType::Have,
+                        Presence::DontHave => proto::BlockPresenceType::DontHave,
+                    },
+                })
+                .collect(),
+            Version::V1_0 | Version::V1_1 => Vec::new(),
+        };
+
+        proto::Message {
+            wantlist,
+            blocks,
+            payload,
+            blockPresences: block_presences,
+            pendingBytes: 0,
+        }
+    }
+
+    /// Converts a received message, dropping invalid entries and blocks.
+    fn from_proto(message: proto::Message) -> Self {
+        let (wantlist, full) = match message.wantlist {
+            Some(wantlist) => {
+                let entries = wantlist
+                    .entries
+                    .into_iter()
+                    .filter_map(|entry| match Cid::try_from(entry.block.as_slice()) {
+                        Ok(cid) => Some(Entry {
+                            cid,
+                            priority: entry.priority,
+                            cancel: entry.cancel,
+                            want_type: match entry.wantType {
+                                proto::WantType::Block => WantType::Block,
+                                proto::WantType::Have => WantType::Have,
+                            },
+                            send_dont_have: entry.sendDontHave,
+                        }),
+                        Err(e) => {
+                            tracing::debug!("Dropping wantlist entry with invalid CID: {e}");
+                            None
+                        }
+                    })
+                    .collect();
+                (entries, wantlist.full)
+            }
+            None => (Vec::new(), false),
+        };
+
+        let v0_blocks = message.blocks.into_iter().map(|data| {
+            let hash = Multihash::wrap(SHA2_256, &Sha256::digest(&data))
+                .expect("SHA2-256 digest to fit into 64 bytes");
+            let cid = Cid::new_v0(hash).expect("SHA2-256 multihash to be valid for CIDv0");
+            Block { cid, data }
+        });
+        let blocks = message
+            .payload
+            .into_iter()
+            .filter_map(|block| match cid_from_prefix(&block.prefix, &block.data) {
+                Ok(cid) => Some(Block {
+                    cid,
+                    data: block.data,
+                }),
+                Err(e) => {
+                    tracing::debug!("Dropping block with invalid prefix: {e}");
+                    None
+                }
+            })
+            .chain(v0_blocks)
+            .collect();
+
+        let presences = message
+            .blockPresences
+            .into_iter()
+            .filter_map(|presence| {
+                let cid = Cid::try_from(presence.cid.as_slice()).ok()?;
+                let presence = match presence.type_pb {
+                    proto::BlockPresenceType::Have => Presence::Have,
+                    proto::BlockPresenceType::DontHave => Presence::DontHave,
+                };
+                Some((cid, presence))
+            })
+            .collect();
+
+        Message {
+            wantlist,
+            full,
+            blocks,
+            presences,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.wantlist.is_empty()
+            && !self.full
+            && self.blocks.is_empty()
+            && self.presences.is_empty()
+    }
+}
+
+/// Encodes the prefix of a CID, i.e. its version, codec, multihash code and digest length.
+fn cid_prefix(cid: &Cid) -> Vec<u8> {
+    let mut prefix = Vec::new();
+    let mut buf = unsigned_varint::encode::u64_buffer();
+    for n in [
+        cid.version().into(),
+        cid.codec(),
+        cid.hash().code(),
+        cid.hash().size().into(),
+    ] {
+        prefix.extend_from_slice(unsigned_varint::encode::u64(n, &mut buf));
+    }
+    prefix
+}
+
+/// Computes the CID of a block's data with the given prefix.
+fn cid_from_prefix(prefix: &[u8], data: &[u8]) -> Result<Cid, InvalidBlock> {
+    let mut fields = [0u64; 4];
+    let mut remaining = prefix;
+    for field in fields.iter_mut() {
+        let (n, rest) =
+            unsigned_varint::decode::u64(remaining).map_err(|_| InvalidBlock::Prefix)?;
+        *field = n;
+        remaining = rest;
+    }
+    let [version, codec, code, size] = fields;
+
+    if code != SHA2_256 {
+        return Err(InvalidBlock::UnsupportedHash(code));
+    }
+    let digest = Sha256::digest(data);
+    if size != digest.len() as u64 {
+        return Err(InvalidBlock::Prefix);
+    }
+    let hash = Multihash::wrap(code, &digest).map_err(|_| InvalidBlock::Prefix)?;
+
+    match CidVersion::try_from(version).map_err(|_| InvalidBlock::Prefix)? {
+        CidVersion::V0 if codec == DAG_PB => Cid::new_v0(hash).map_err(|_| InvalidBlock::Prefix),
+        CidVersion::V0 => Err(InvalidBlock::Prefix),
+        CidVersion::V1 => Ok(Cid::new_v1(codec, hash)),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum InvalidBlock {
+    #[error("malformed CID prefix")]
+    Prefix,
+    #[error("unsupported multihash code {0:#x}")]
+    UnsupportedHash(u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_protobuf::{BytesReader, MessageRead, MessageWrite, Writer};
+
+    const RAW: u64 = 0x55;
+
+    fn block(version: CidVersion, data: &[u8]) -> Block {
+        let hash = Multihash::wrap(SHA2_256, &Sha256::digest(data)).unwrap();
+        let cid = match version {
+            CidVersion::V0 => Cid::new_v0(hash).unwrap(),
+            CidVersion::V1 => Cid::new_v1(RAW, hash),
+        };
+        Block {
+            cid,
+            data: data.to_vec(),
+        }
+    }
+
+    fn roundtrip(message: Message, version: Version) -> Message {
+        let mut bytes = Vec::new();
+        message
+            .into_proto(version)
+            .write_message(&mut Writer::new(&mut bytes))
+            .unwrap();
+        let proto =
+            proto::Message::from_reader(&mut BytesReader::from_bytes(&bytes), &bytes).unwrap();
+        Message::from_proto(proto)
+    }
+
+    #[test]
+    fn message_roundtrip() {
+        let v0 = block(CidVersion::V0, b"foo");
+        let v1 = block(CidVersion::V1, b"bar");
+        let message = Message {
+            wantlist: vec![Entry {
+                cid: v1.cid,
+                priority: 1,
+                cancel: false,
+                want_type: WantType::Have,
+                send_dont_have: true,
+            }],
+            full: true,
+            blocks: vec![v0.clone(), v1.clone()],
+            presences: vec![(v0.cid, Presence::DontHave)],
+        };
+
+        assert_eq!(roundtrip(message.clone(), Version::V1_2), message);
+
+        let v1_1 = roundtrip(message.clone(), Version::V1_1);
+        assert!(v1_1.wantlist.is_empty());
+        assert_eq!(v1_1.blocks, vec![v0.clone(), v1]);
+        assert!(v1_1.presences.is_empty());
+
+        let v1_0 = roundtrip(message, Version::V1_0);
+        assert_eq!(v1_0.blocks, vec![v0]);
+    }
+
+    #[test]
+    fn block_cid_is_computed_from_data() {
+        let block = block(CidVersion::V1, b"foo");
+        let proto = proto::Message {
+            payload: vec![proto::Block {
+                prefix: cid_prefix(&block.cid),
+                data: b"bar".to_vec(),
+            }],
+            ..Default::default()
+        };
+
+        let message = Message::from_proto(proto);
+
+        assert_eq!(message.blocks.len(), 1);
+        assert_ne!(message.blocks[0].cid, block.cid);
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use cid::Cid;
+use std::collections::HashMap;
+
+/// Storage of the blocks served to and received from other peers.
+///
+/// Blocks received from the network are only inserted after their [`Cid`] has been verified
+/// against their data.
+pub trait Blockstore: Send + 'static {
+    /// Returns the data of the block with the given [`Cid`], if present.
+    fn get(&self, cid: &Cid) -> Option<Vec<u8>>;
+
+    /// Returns `true` if the block with the given [`Cid`] is present.
+    fn has(&self, cid: &Cid) -> bool {
+        self.get(cid).is_some()
+    }
+
+    /// Stores a block.
+    fn put(&mut self, cid: Cid, data: Vec<u8>);
+}
+
+/// A [`Blockstore`] keeping blocks in memory.
+#[derive(Debug, Default)]
+pub struct MemoryBlockstore {
+    blocks: HashMap<Cid, Vec<u8>>,
+}
+
+impl MemoryBlockstore {
+    /// Returns the number of stored blocks.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns `true` if no blocks are stored.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl Blockstore for MemoryBlockstore {
+    fn get(&self, cid: &Cid) -> Option<Vec<u8>> {
+        self.blocks.get(cid).cloned()
+    }
+
+    fn has(&self, cid: &Cid) -> bool {
+        self.blocks.contains_key(cid)
+    }
+
+    fn put(&mut self, cid: Cid, data: Vec<u8>) {
+        self.blocks.insert(cid, data);
+    }
+}
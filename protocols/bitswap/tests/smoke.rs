@@ -0,0 +1,214 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_bitswap as bitswap;
+use libp2p_bitswap::cid::{multihash::Multihash, Cid};
+use libp2p_bitswap::{Blockstore, MemoryBlockstore};
+use libp2p_swarm::Swarm;
+use libp2p_swarm_test::SwarmExt;
+use sha2::{Digest, Sha256};
+use tracing_subscriber::EnvFilter;
+
+const RAW: u64 = 0x55;
+const SHA2_256: u64 = 0x12;
+
+fn block(data: &[u8]) -> (Cid, Vec<u8>) {
+    let hash = Multihash::wrap(SHA2_256, &Sha256::digest(data)).unwrap();
+
+    (Cid::new_v1(RAW, hash), data.to_vec())
+}
+
+fn new_swarm() -> Swarm<bitswap::Behaviour<MemoryBlockstore>> {
+    Swarm::new_ephemeral(|_| {
+        bitswap::Behaviour::new(MemoryBlockstore::default(), bitswap::Config::default())
+    })
+}
+
+#[async_std::test]
+async fn wanted_block_is_exchanged() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (cid, data) = block(b"hello bitswap");
+
+    let mut provider = new_swarm();
+    provider.behaviour_mut().insert_block(cid, data.clone());
+    let provider_id = *provider.local_peer_id();
+
+    let mut requester = new_swarm();
+    assert!(requester.behaviour_mut().want(cid, 1));
+    let requester_id = *requester.local_peer_id();
+
+    provider.listen().with_memory_addr_external().await;
+    requester.connect(&mut provider).await;
+
+    match libp2p_swarm_test::drive(&mut provider, &mut requester).await {
+        (
+            [bitswap::Event::Sent {
+                peer: sent_to,
+                cid: sent,
+            }],
+            [bitswap::Event::Received {
+                peer: received_from,
+                cid: received,
+            }],
+        ) => {
+            assert_eq!(sent_to, requester_id);
+            assert_eq!(sent, cid);
+            assert_eq!(received_from, provider_id);
+            assert_eq!(received, cid);
+        }
+        other => panic!("Unexpected events: {other:?}"),
+    }
+
+    assert_eq!(requester.behaviour().store().get(&cid), Some(data.clone()));
+    assert_eq!(requester.behaviour().wantlist().count(), 0);
+
+    let provider_ledger = provider.behaviour().ledger(&requester_id).unwrap();
+    assert_eq!(provider_ledger.blocks_sent, 1);
+    assert_eq!(provider_ledger.bytes_sent, data.len() as u64);
+    let requester_ledger = requester.behaviour().ledger(&provider_id).unwrap();
+    assert_eq!(requester_ledger.blocks_received, 1);
+    assert_eq!(requester_ledger.bytes_received, data.len() as u64);
+}
+
+#[async_std::test]
+async fn missing_block_is_served_once_inserted() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (cid, data) = block(b"not there yet");
+
+    let mut provider = new_swarm();
+    let mut requester = new_swarm();
+    let requester_id = *requester.local_peer_id();
+
+    provider.listen().with_memory_addr_external().await;
+    requester.connect(&mut provider).await;
+    assert!(requester.behaviour_mut().want(cid, 1));
+
+    let ([], [event]): ([bitswap::Event; 0], [bitswap::Event; 1]) =
+        libp2p_swarm_test::drive(&mut provider, &mut requester).await;
+    assert_eq!(
+        event,
+        bitswap::Event::Presence {
+            peer: *provider.local_peer_id(),
+            cid,
+            have: false
+        }
+    );
+    assert_eq!(
+        provider
+            .behaviour()
+            .peer_wantlist(&requester_id)
+            .collect::<Vec<_>>(),
+        vec![&cid]
+    );
+
+    provider.behaviour_mut().insert_block(cid, data);
+
+    match libp2p_swarm_test::drive(&mut provider, &mut requester).await {
+        (
+            [bitswap::Event::Sent { cid: sent, .. }],
+            [bitswap::Event::Received { cid: received, .. }],
+        ) => {
+            assert_eq!(sent, cid);
+            assert_eq!(received, cid);
+        }
+        other => panic!("Unexpected events: {other:?}"),
+    }
+    assert_eq!(provider.behaviour().peer_wantlist(&requester_id).count(), 0);
+}
+
+#[async_std::test]
+async fn peer_wantlist_is_bounded() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (first, _) = block(b"first");
+    let (second, _) = block(b"second");
+
+    let mut provider = Swarm::new_ephemeral(|_| {
+        bitswap::Behaviour::new(
+            MemoryBlockstore::default(),
+            bitswap::Config::default().with_max_wantlist_entries(1),
+        )
+    });
+    let mut requester = new_swarm();
+    let requester_id = *requester.local_peer_id();
+
+    provider.listen().with_memory_addr_external().await;
+    requester.connect(&mut provider).await;
+    assert!(requester.behaviour_mut().want(first, 1));
+    assert!(requester.behaviour_mut().want(second, 1));
+
+    // Both entries are answered, but only the first one is remembered.
+    let ([], [_, _]): ([bitswap::Event; 0], [bitswap::Event; 2]) =
+        libp2p_swarm_test::drive(&mut provider, &mut requester).await;
+    assert_eq!(
+        provider
+            .behaviour()
+            .peer_wantlist(&requester_id)
+            .collect::<Vec<_>>(),
+        vec![&first]
+    );
+}
+
+#[async_std::test]
+async fn blocks_beyond_concurrent_stream_limit_are_queued() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    const NUM_BLOCKS: usize = 40;
+
+    let mut provider = new_swarm();
+    let mut requester = new_swarm();
+    for i in 0..NUM_BLOCKS {
+        let (cid, data) = block(format!("block {i}").as_bytes());
+        provider.behaviour_mut().insert_block(cid, data);
+        assert!(requester.behaviour_mut().want(cid, 1));
+    }
+    let requester_id = *requester.local_peer_id();
+
+    provider.listen().with_memory_addr_external().await;
+    requester.connect(&mut provider).await;
+
+    let (sent, received): ([bitswap::Event; NUM_BLOCKS], [bitswap::Event; NUM_BLOCKS]) =
+        libp2p_swarm_test::drive(&mut provider, &mut requester).await;
+    assert!(sent
+        .iter()
+        .all(|e| matches!(e, bitswap::Event::Sent { .. })));
+    assert!(received
+        .iter()
+        .all(|e| matches!(e, bitswap::Event::Received { .. })));
+    assert_eq!(requester.behaviour().wantlist().count(), 0);
+    assert_eq!(
+        provider
+            .behaviour()
+            .ledger(&requester_id)
+            .unwrap()
+            .blocks_sent,
+        NUM_BLOCKS as u64
+    );
+}
@@ -29,8 +29,8 @@ use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{ConnectionClosed, DialFailure, FromSwarm};
 use libp2p_swarm::dial_opts::{self, DialOpts};
 use libp2p_swarm::{
-    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, NewExternalAddrCandidate, THandler,
-    THandlerOutEvent,
+    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, NewExternalAddrCandidate,
+    Reachability, ReachabilityChanged, THandler, THandlerOutEvent,
 };
 use libp2p_swarm::{NetworkBehaviour, NotifyHandler, THandlerInEvent, ToSwarm};
 use lru::LruCache;
@@ -65,6 +65,11 @@ enum InnerError {
     OutboundError(protocol::outbound::Error),
 }
 
+/// [`NetworkBehaviour`] upgrading relayed connections to direct connections via hole-punching.
+///
+/// We only attempt to hole-punch inbound relayed connections once we are confirmed to be behind a
+/// NAT or firewall, as reported by e.g. AutoNAT via [`FromSwarm::ReachabilityChanged`]. Nodes not
+/// running a protocol probing their reachability can set it via [`Behaviour::with_reachability`].
 pub struct Behaviour {
     /// Queue of actions to return when polled.
     queued_events: VecDeque<ToSwarm<Event, Either<handler::relayed::Command, Void>>>,
@@ -79,6 +84,9 @@ pub struct Behaviour {
     /// Indexed by the [`ConnectionId`] of the relayed connection and
     /// the [`PeerId`] we are trying to establish a direct connection to.
     outgoing_direct_connection_attempts: HashMap<(ConnectionId, PeerId), u8>,
+
+    /// Our reachability as reported via [`FromSwarm::ReachabilityChanged`].
+    reachability: Reachability,
 }
 
 impl Behaviour {
@@ -89,9 +97,18 @@ impl Behaviour {
             address_candidates: Candidates::new(local_peer_id),
             direct_to_relayed_connections: Default::default(),
             outgoing_direct_connection_attempts: Default::default(),
+            reachability: Reachability::Unknown,
         }
     }
 
+    /// Sets the reachability assumed until another one is reported via
+    /// [`FromSwarm::ReachabilityChanged`], e.g. [`Reachability::Private`] for nodes known to be
+    /// behind a NAT that don't run AutoNAT.
+    pub fn with_reachability(mut self, reachability: Reachability) -> Self {
+        self.reachability = reachability;
+        self
+    }
+
     fn observed_addresses(&self) -> Vec<Multiaddr> {
         self.address_candidates.iter().cloned().collect()
     }
@@ -181,7 +198,17 @@ impl NetworkBehaviour for Behaviour {
             };
             let mut handler =
                 handler::relayed::Handler::new(connected_point, self.observed_addresses());
-            handler.on_behaviour_event(handler::relayed::Command::Connect);
+
+            // Only hole-punch if a remote can't dial us directly.
+            if self.reachability.is_private() {
+                handler.on_behaviour_event(handler::relayed::Command::Connect);
+            } else {
+                tracing::debug!(
+                    %peer,
+                    reachability=?self.reachability,
+                    "Not attempting to hole-punch because we are not known to be private"
+                );
+            }
 
             return Ok(Either::Left(handler)); // TODO: We could make two `handler::relayed::Handler` here, one inbound one outbound.
         }
@@ -333,6 +360,9 @@ impl NetworkBehaviour for Behaviour {
             FromSwarm::NewExternalAddrCandidate(NewExternalAddrCandidate { addr }) => {
                 self.address_candidates.add(addr.clone());
             }
+            FromSwarm::ReachabilityChanged(ReachabilityChanged { new, .. }) => {
+                self.reachability = new;
+            }
             _ => {}
         }
     }
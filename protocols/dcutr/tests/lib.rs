@@ -18,6 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use futures::{AsyncRead, AsyncWrite};
 use libp2p_core::multiaddr::{Multiaddr, Protocol};
 use libp2p_core::transport::upgrade::Version;
 use libp2p_core::transport::{MemoryTransport, Transport};
@@ -27,7 +28,10 @@ use libp2p_identity as identity;
 use libp2p_identity::PeerId;
 use libp2p_plaintext as plaintext;
 use libp2p_relay as relay;
-use libp2p_swarm::{Config, NetworkBehaviour, Swarm, SwarmEvent};
+use libp2p_swarm::{
+    Config, FromSwarm, NetworkBehaviour, Reachability, ReachabilityChanged, Swarm, SwarmEvent,
+};
+use libp2p_swarm_test::nat::{NatKind, Network};
 use libp2p_swarm_test::SwarmExt as _;
 use std::time::Duration;
 use tracing_subscriber::EnvFilter;
@@ -98,24 +102,200 @@ async fn connect() {
     assert_eq!(established_conn_id, reported_conn_id);
 }
 
-fn build_relay() -> Swarm<Relay> {
-    Swarm::new_ephemeral(|identity| {
-        let local_peer_id = identity.public().to_peer_id();
-
-        Relay {
-            relay: relay::Behaviour::new(
-                local_peer_id,
-                relay::Config {
-                    reservation_duration: Duration::from_secs(2),
-                    ..Default::default()
-                },
-            ),
-            identify: identify::Behaviour::new(identify::Config::new(
-                "/relay".to_owned(),
-                identity.public(),
-            )),
-        }
+#[async_std::test]
+async fn no_hole_punch_when_publicly_reachable() {
+    no_hole_punch_unless_private(Reachability::Public { confidence: 3 }).await
+}
+
+#[async_std::test]
+async fn no_hole_punch_when_reachability_unknown() {
+    no_hole_punch_unless_private(Reachability::Unknown).await
+}
+
+async fn no_hole_punch_unless_private(reachability: Reachability) {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut relay = build_relay();
+    let mut dst = build_client();
+    let mut src = build_client();
+
+    let (_, relay_tcp_addr) = relay.listen().with_tcp_addr_external().await;
+    dst.listen().await;
+    src.listen().await;
+
+    let relay_peer_id = *relay.local_peer_id();
+    let dst_peer_id = *dst.local_peer_id();
+
+    async_std::task::spawn(relay.loop_on_next());
+
+    // Report the reachability of `dst`, as AutoNAT would.
+    dst.behaviour_mut()
+        .dcutr
+        .on_swarm_event(FromSwarm::ReachabilityChanged(ReachabilityChanged {
+            old: Reachability::Private { confidence: 0 },
+            new: reachability,
+        }));
+
+    let dst_relayed_addr = relay_tcp_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+    dst.listen_on(dst_relayed_addr.clone()).unwrap();
+
+    wait_for_reservation(
+        &mut dst,
+        dst_relayed_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    )
+    .await;
+    async_std::task::spawn(dst.loop_on_next());
+
+    src.dial_and_wait(dst_relayed_addr).await;
+
+    let hole_punch = async_std::future::timeout(
+        Duration::from_secs(2),
+        src.wait(|e| match e {
+            SwarmEvent::Behaviour(ClientEvent::Dcutr(event)) => Some(event),
+            _ => None,
+        }),
+    )
+    .await;
+
+    assert!(hole_punch.is_err(), "Unexpected hole-punch: {hole_punch:?}");
+}
+
+#[async_std::test]
+async fn hole_punch_through_cone_nats() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let result = hole_punch_through_nats(NatKind::Cone).await;
+
+    assert!(result.is_ok(), "{result:?}");
+}
+
+#[async_std::test]
+async fn no_hole_punch_through_symmetric_nats() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let result = hole_punch_through_nats(NatKind::Symmetric).await;
+
+    assert!(result.is_err(), "Unexpected hole-punch: {result:?}");
+}
+
+/// Connects two clients behind NATs of the given kind via a public relay and returns the result
+/// of the hole punch reported by the client listening via the relay, which drives the attempts.
+async fn hole_punch_through_nats(
+    kind: NatKind,
+) -> Result<libp2p_swarm::ConnectionId, dcutr::Error> {
+    let network = Network::new();
+    let mut relay = build_relay_with(network.public_host());
+    let mut dst = build_client_with(network.nat_host(kind));
+    let mut src = build_client_with(network.nat_host(kind));
+
+    let relay_addr = listen_on_nat_network(&mut relay).await;
+    relay.add_external_address(relay_addr.clone());
+    listen_on_nat_network(&mut dst).await;
+    listen_on_nat_network(&mut src).await;
+
+    let relay_peer_id = *relay.local_peer_id();
+    let dst_peer_id = *dst.local_peer_id();
+
+    async_std::task::spawn(relay.loop_on_next());
+
+    let dst_relayed_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+    dst.listen_on(dst_relayed_addr.clone()).unwrap();
+
+    wait_for_reservation(
+        &mut dst,
+        dst_relayed_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    )
+    .await;
+
+    src.dial(dst_relayed_addr).unwrap();
+    async_std::task::spawn(src.loop_on_next());
+
+    dst.wait(|e| match e {
+        SwarmEvent::Behaviour(ClientEvent::Dcutr(dcutr::Event { result, .. })) => Some(result),
+        _ => None,
     })
+    .await
+}
+
+async fn listen_on_nat_network<B>(swarm: &mut Swarm<B>) -> Multiaddr
+where
+    B: NetworkBehaviour + Send,
+    B::ToSwarm: std::fmt::Debug,
+{
+    swarm
+        .listen_on("/ip4/0.0.0.0/tcp/0".parse().unwrap())
+        .unwrap();
+    swarm
+        .wait(|e| match e {
+            SwarmEvent::NewListenAddr { address, .. } => Some(address),
+            _ => None,
+        })
+        .await
+}
+
+fn build_relay() -> Swarm<Relay> {
+    Swarm::new_ephemeral(relay_behaviour)
+}
+
+fn build_relay_with<T>(transport: T) -> Swarm<Relay>
+where
+    T: Transport + Send + Unpin + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Error: Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
+    let local_key = identity::Keypair::generate_ed25519();
+    let local_peer_id = local_key.public().to_peer_id();
+
+    let transport = transport
+        .upgrade(Version::V1)
+        .authenticate(plaintext::Config::new(&local_key))
+        .multiplex(libp2p_yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        relay_behaviour(local_key),
+        local_peer_id,
+        Config::with_async_std_executor(),
+    )
+}
+
+fn relay_behaviour(identity: identity::Keypair) -> Relay {
+    let local_peer_id = identity.public().to_peer_id();
+
+    Relay {
+        relay: relay::Behaviour::new(
+            local_peer_id,
+            relay::Config {
+                reservation_duration: Duration::from_secs(2),
+                ..Default::default()
+            }
+            // The clients are connected via loopback or documentation addresses.
+            .allow_all_destinations(),
+        ),
+        identify: identify::Behaviour::new(identify::Config::new(
+            "/relay".to_owned(),
+            identity.public(),
+        )),
+    }
 }
 
 #[derive(NetworkBehaviour)]
@@ -126,14 +306,26 @@ struct Relay {
 }
 
 fn build_client() -> Swarm<Client> {
+    build_client_with(
+        MemoryTransport::default().or_transport(libp2p_tcp::async_io::Transport::default()),
+    )
+}
+
+fn build_client_with<T>(transport: T) -> Swarm<Client>
+where
+    T: Transport + Send + Unpin + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Error: Send + Sync + 'static,
+    T::Dial: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
+{
     let local_key = identity::Keypair::generate_ed25519();
     let local_peer_id = local_key.public().to_peer_id();
 
     let (relay_transport, behaviour) = relay::client::new(local_peer_id);
 
     let transport = relay_transport
-        .or_transport(MemoryTransport::default())
-        .or_transport(libp2p_tcp::async_io::Transport::default())
+        .or_transport(transport)
         .upgrade(Version::V1)
         .authenticate(plaintext::Config::new(&local_key))
         .multiplex(libp2p_yamux::Config::default())
@@ -143,7 +335,8 @@ fn build_client() -> Swarm<Client> {
         transport,
         Client {
             relay: behaviour,
-            dcutr: dcutr::Behaviour::new(local_peer_id),
+            dcutr: dcutr::Behaviour::new(local_peer_id)
+                .with_reachability(Reachability::Private { confidence: 0 }),
             identify: identify::Behaviour::new(identify::Config::new(
                 "/client".to_owned(),
                 local_key.public(),
@@ -200,7 +393,7 @@ async fn wait_for_reservation(
             }
             SwarmEvent::Behaviour(ClientEvent::Identify(_)) => {}
             SwarmEvent::NewExternalAddrCandidate { .. } => {}
-            SwarmEvent::ExternalAddrConfirmed { address } if !is_renewal => {
+            SwarmEvent::ExternalAddrConfirmed { address, .. } if !is_renewal => {
                 assert_eq!(address, client_addr);
             }
             SwarmEvent::NewExternalAddrOfPeer { .. } => {}
@@ -32,7 +32,8 @@ use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm};
 use libp2p_swarm::{
     dial_opts::DialOpts, CloseConnection, ConnectionDenied, ConnectionId, NetworkBehaviour,
-    NotifyHandler, OneShotHandler, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    NotifyHandler, OneShotHandler, OneShotHandlerError, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
 };
 use smallvec::SmallVec;
 use std::collections::hash_map::{DefaultHasher, HashMap};
@@ -360,7 +361,7 @@ impl NetworkBehaviour for Floodsub {
         let event = match event {
             Ok(InnerMessage::Rx(event)) => event,
             Ok(InnerMessage::Sent) => return,
-            Err(e) => {
+            Err(OneShotHandlerError::Outbound(e)) => {
                 tracing::debug!("Failed to send floodsub message: {e}");
                 self.events.push_back(ToSwarm::CloseConnection {
                     peer_id: propagation_source,
@@ -368,6 +369,10 @@ impl NetworkBehaviour for Floodsub {
                 });
                 return;
             }
+            Err(e) => {
+                tracing::debug!(peer=%propagation_source, "Floodsub handler error: {e}");
+                return;
+            }
         };
 
         // Update connected peers topics
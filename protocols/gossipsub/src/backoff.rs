@@ -22,10 +22,12 @@
 use crate::topic::TopicHash;
 use instant::Instant;
 use libp2p_identity::PeerId;
+use libp2p_swarm::Clock;
 use std::collections::{
     hash_map::{Entry, HashMap},
     HashSet,
 };
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Copy, Clone)]
@@ -44,6 +46,8 @@ pub(crate) struct BackoffStorage {
     heartbeat_interval: Duration,
     /// Backoff slack from the config.
     backoff_slack: u32,
+    /// The clock from the config.
+    clock: Arc<dyn Clock>,
 }
 
 impl BackoffStorage {
@@ -56,6 +60,7 @@ impl BackoffStorage {
         prune_backoff: &Duration,
         heartbeat_interval: Duration,
         backoff_slack: u32,
+        clock: Arc<dyn Clock>,
     ) -> BackoffStorage {
         // We add one additional slot for partial heartbeat
         let max_heartbeats =
@@ -66,13 +71,19 @@ impl BackoffStorage {
             heartbeat_index: HeartbeatIndex(0),
             heartbeat_interval,
             backoff_slack,
+            clock,
         }
     }
 
+    /// Updates the heartbeat interval used to schedule the clean up of backoffs.
+    pub(crate) fn set_heartbeat_interval(&mut self, heartbeat_interval: Duration) {
+        self.heartbeat_interval = heartbeat_interval;
+    }
+
     /// Updates the backoff for a peer (if there is already a more restrictive backoff then this call
     /// doesn't change anything).
     pub(crate) fn update_backoff(&mut self, topic: &TopicHash, peer: &PeerId, time: Duration) {
-        let instant = Instant::now() + time;
+        let instant = self.clock.now() + time;
         let insert_into_backoffs_by_heartbeat =
             |heartbeat_index: HeartbeatIndex,
              backoffs_by_heartbeat: &mut Vec<HashSet<_>>,
@@ -149,7 +160,7 @@ impl BackoffStorage {
         if let Some(s) = self.backoffs_by_heartbeat.get_mut(self.heartbeat_index.0) {
             let backoffs = &mut self.backoffs;
             let slack = self.heartbeat_interval * self.backoff_slack;
-            let now = Instant::now();
+            let now = self.clock.now();
             s.retain(|(topic, peer)| {
                 let keep = match Self::get_backoff_time_from_backoffs(backoffs, topic, peer) {
                     Some(backoff_time) => backoff_time + slack > now,
@@ -30,7 +30,6 @@ use std::{
 };
 
 use futures::StreamExt;
-use futures_ticker::Ticker;
 use prometheus_client::registry::Registry;
 use rand::{seq::SliceRandom, thread_rng};
 
@@ -39,20 +38,26 @@ use libp2p_core::{multiaddr::Protocol::Ip4, multiaddr::Protocol::Ip6, Endpoint,
 use libp2p_identity::Keypair;
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, FromSwarm},
-    dial_opts::DialOpts,
-    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    behaviour::{
+        AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm,
+        NewExternalAddrOfPeer,
+    },
+    dial_opts::{DialOpts, PeerCondition},
+    ConnectionDenied, ConnectionId, Interval, NetworkBehaviour, NotifyHandler, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 
 use crate::backoff::BackoffStorage;
-use crate::config::{Config, ValidationMode};
+use crate::config::{Config, PxVerification, SubscriptionLimitAction, ValidationMode};
 use crate::gossip_promises::GossipPromises;
 use crate::handler::{Handler, HandlerEvent, HandlerIn};
 use crate::mcache::MessageCache;
-use crate::metrics::{Churn, Config as MetricsConfig, Inclusion, Metrics, Penalty};
+use crate::metrics::{
+    Churn, Config as MetricsConfig, GossipControlLimit, Inclusion, Metrics, Penalty, Throttle,
+};
 use crate::peer_score::{PeerScore, PeerScoreParams, PeerScoreThresholds, RejectReason};
 use crate::protocol::SIGNING_PREFIX;
+use crate::quota::{Admission, TopicQuotas};
 use crate::subscription_filter::{AllowAllSubscriptionFilter, TopicSubscriptionFilter};
 use crate::time_cache::DuplicateCache;
 use crate::topic::{Hasher, Topic, TopicHash};
@@ -61,7 +66,9 @@ use crate::types::{
     ControlAction, Message, MessageAcceptance, MessageId, PeerInfo, RawMessage, Subscription,
     SubscriptionAction,
 };
-use crate::types::{PeerConnections, PeerKind, RpcOut};
+use crate::types::{
+    GossipControlStats, MeshStats, PeerConnections, PeerKind, RpcOut, TopicBandwidth,
+};
 use crate::{rpc_proto::proto, TopicScoreParams};
 use crate::{PublishError, SubscriptionError, ValidationError};
 use instant::SystemTime;
@@ -144,6 +151,64 @@ pub enum Event {
     },
     /// A peer that does not support gossipsub has connected.
     GossipsubNotSupported { peer_id: PeerId },
+    /// The protocol negotiated with a peer is known for the first time or changed, i.e. a new
+    /// connection to the peer negotiated a newer version.
+    ///
+    /// See [`Behaviour::peer_kind`].
+    PeerKindChanged {
+        /// The peer whose protocol changed.
+        peer_id: PeerId,
+        /// The previously negotiated protocol, `None` if this is the first negotiation.
+        old: Option<PeerKind>,
+        /// The newly negotiated protocol.
+        new: PeerKind,
+    },
+    /// A message could not be sent to a peer because it exceeds the maximum transmit size on its
+    /// own, see [`Config::max_transmit_size`].
+    ///
+    /// Other RPCs are split to fit into the maximum transmit size.
+    MessageTooLarge {
+        /// The peer the message was to be sent to.
+        peer_id: PeerId,
+        /// The topic of the message.
+        topic: TopicHash,
+        /// The size of the encoded message in bytes.
+        size: usize,
+    },
+    /// We unsubscribed from a topic to make room for a new subscription, see
+    /// [`SubscriptionLimitAction::UnsubscribeLeastRecentlyUsed`].
+    SubscriptionEvicted {
+        /// The least recently used topic we unsubscribed from.
+        topic: TopicHash,
+    },
+    /// The outbound queue of a peer stayed full, see [`Config::slow_peer_detection`].
+    ///
+    /// The peer has been pruned from all meshes and its score has been penalized.
+    SlowPeer {
+        /// The slow peer.
+        peer_id: PeerId,
+        /// The number of RPCs queued for the peer on the connection.
+        queue_len: usize,
+        /// How long the queue has been full.
+        full_for: Duration,
+        /// The topics whose meshes the peer has been pruned from.
+        pruned_topics: Vec<TopicHash>,
+    },
+    /// The receipts for a message we published on a topic with delivery receipts, see
+    /// [`ConfigBuilder::delivery_receipts`](crate::ConfigBuilder::delivery_receipts).
+    ///
+    /// Emitted once all peers the message was sent to confirmed its receipt, or else at the end
+    /// of the receipt window.
+    DeliveryReceipts {
+        /// The id of the published message.
+        message_id: MessageId,
+        /// The topic of the message.
+        topic: TopicHash,
+        /// The number of peers the message was sent to.
+        recipients: usize,
+        /// The number of these peers that confirmed the receipt within the window.
+        confirmations: usize,
+    },
 }
 
 /// A data structure for storing configuration for publishing messages. See [`MessageAuthenticity`]
@@ -254,6 +319,11 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// duplicates from being propagated to the application and on the network.
     duplicate_cache: DuplicateCache<MessageId>,
 
+    /// A time cache of the messages each peer is known to have, because we either sent the
+    /// message to the peer or received it from the peer. Used to avoid sending a message back to
+    /// where it came from and to avoid sending a message more than once to the same peer.
+    known_messages: DuplicateCache<(PeerId, MessageId)>,
+
     /// A set of connected peers, indexed by their [`PeerId`] tracking both the [`PeerKind`] and
     /// the set of [`ConnectionId`]s.
     connected_peers: HashMap<PeerId, PeerConnections>,
@@ -275,12 +345,41 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// Overlay network of connected peers - Maps topics to connected gossipsub peers.
     mesh: HashMap<TopicHash, BTreeSet<PeerId>>,
 
+    /// The topics we are subscribed to in observer mode, see [`Behaviour::observe`]. Their mesh
+    /// is always empty.
+    observed_topics: HashSet<TopicHash>,
+
     /// Map of topics to list of peers that we publish to, but don't subscribe to.
     fanout: HashMap<TopicHash, BTreeSet<PeerId>>,
 
     /// The last publish time for fanout topics.
     fanout_last_pub: HashMap<TopicHash, Instant>,
 
+    /// Counters of mesh membership changes for the topics we are subscribed to.
+    mesh_stats: HashMap<TopicHash, MeshStats>,
+
+    /// The last use of each topic we are subscribed to, as value of [`Self::subscription_uses`],
+    /// see [`SubscriptionLimitAction::UnsubscribeLeastRecentlyUsed`].
+    subscription_last_use: HashMap<TopicHash, u64>,
+
+    /// The number of uses of subscribed topics so far.
+    subscription_uses: u64,
+
+    /// Bytes of the messages published, forwarded and received on the topics we are subscribed
+    /// to, publish to, or have a bandwidth quota for.
+    topic_bandwidth: HashMap<TopicHash, TopicBandwidth>,
+
+    /// The state of the bandwidth quotas of the topics.
+    topic_quotas: TopicQuotas,
+
+    /// The receipts collected for the messages we published on topics with delivery receipts,
+    /// until all recipients confirmed or the receipt window ends.
+    delivery_receipts: HashMap<MessageId, PendingReceipts>,
+
+    /// The receipts we owe each peer, sent in a single ACK control message per peer on the next
+    /// poll of the behaviour.
+    pending_acks: HashMap<PeerId, Vec<MessageId>>,
+
     ///Storage for backoffs
     backoffs: BackoffStorage,
 
@@ -288,7 +387,7 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     mcache: MessageCache,
 
     /// Heartbeat interval stream.
-    heartbeat: Ticker,
+    heartbeat: Interval,
 
     /// Number of heartbeats since the beginning of time; this allows us to amortize some resource
     /// clean up -- eg backoff clean up.
@@ -300,20 +399,44 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// be removed from this list which may result in a true outbound rediscovery.
     px_peers: HashSet<PeerId>,
 
+    /// Peers found through peer exchange which are not yet eligible for the mesh, see
+    /// [`Config::px_verification`].
+    px_unverified: HashMap<PeerId, PxState>,
+
+    /// The unverified peers waiting for a free dial slot, see
+    /// [`Config::max_concurrent_px_dials`].
+    px_dial_queue: VecDeque<PeerId>,
+
     /// Set of connected outbound peers (we only consider true outbound peers found through
     /// discovery and not by PX).
     outbound_peers: HashSet<PeerId>,
 
-    /// Stores optional peer score data together with thresholds, decay interval and gossip
-    /// promises.
-    peer_score: Option<(PeerScore, PeerScoreThresholds, Ticker, GossipPromises)>,
+    /// Stores optional peer score data together with thresholds and decay interval.
+    peer_score: Option<(PeerScore, PeerScoreThresholds, Interval)>,
+
+    /// Tracks the messages we requested via `IWANT` and whether peers deliver them.
+    gossip_promises: GossipPromises,
+
+    /// Counts the number of broken promises of each peer, decreasing by one every heartbeat.
+    broken_promises: HashMap<PeerId, usize>,
 
     /// Counts the number of `IHAVE` received from each peer since the last heartbeat.
     count_received_ihave: HashMap<PeerId, usize>,
 
+    /// Counts the number of message ids advertised via `IHAVE` by each peer since the last
+    /// heartbeat.
+    count_received_ihave_ids: HashMap<PeerId, usize>,
+
     /// Counts the number of `IWANT` that we sent the each peer since the last heartbeat.
     count_sent_iwant: HashMap<PeerId, usize>,
 
+    /// Counts the number of messages we sent to each peer in response to `IWANT` since the last
+    /// heartbeat.
+    count_sent_iwant_responses: HashMap<PeerId, usize>,
+
+    /// Counters of the limited gossip control messages of each connected peer.
+    gossip_control_stats: HashMap<PeerId, GossipControlStats>,
+
     /// Keeps track of IWANT messages that we are awaiting to send.
     /// This is used to prevent sending duplicate IWANT messages for the same message.
     pending_iwant_msgs: HashSet<MessageId>,
@@ -441,33 +564,60 @@ where
             events: VecDeque::new(),
             control_pool: HashMap::new(),
             publish_config: privacy.into(),
-            duplicate_cache: DuplicateCache::new(config.duplicate_cache_time()),
+            duplicate_cache: DuplicateCache::new(
+                config.duplicate_cache_time(),
+                config.clock().clone(),
+            ),
+            known_messages: DuplicateCache::new(
+                config.duplicate_cache_time(),
+                config.clock().clone(),
+            ),
             topic_peers: HashMap::new(),
             peer_topics: HashMap::new(),
             explicit_peers: HashSet::new(),
             blacklisted_peers: HashSet::new(),
             mesh: HashMap::new(),
+            observed_topics: HashSet::new(),
             fanout: HashMap::new(),
             fanout_last_pub: HashMap::new(),
+            mesh_stats: HashMap::new(),
+            subscription_last_use: HashMap::new(),
+            subscription_uses: 0,
+            topic_bandwidth: HashMap::new(),
+            topic_quotas: TopicQuotas::default(),
+            delivery_receipts: HashMap::new(),
+            pending_acks: HashMap::new(),
             backoffs: BackoffStorage::new(
                 &config.prune_backoff(),
                 config.heartbeat_interval(),
                 config.backoff_slack(),
+                config.clock().clone(),
             ),
             mcache: MessageCache::new(config.history_gossip(), config.history_length()),
-            heartbeat: Ticker::new_with_next(
+            heartbeat: Interval::new_with_next(
+                config.clock().clone(),
                 config.heartbeat_interval(),
                 config.heartbeat_initial_delay(),
             ),
             heartbeat_ticks: 0,
             px_peers: HashSet::new(),
+            px_unverified: HashMap::new(),
+            px_dial_queue: VecDeque::new(),
             outbound_peers: HashSet::new(),
             peer_score: None,
+            gossip_promises: GossipPromises::default(),
+            broken_promises: HashMap::new(),
             count_received_ihave: HashMap::new(),
+            count_received_ihave_ids: HashMap::new(),
             count_sent_iwant: HashMap::new(),
+            count_sent_iwant_responses: HashMap::new(),
+            gossip_control_stats: HashMap::new(),
             pending_iwant_msgs: HashSet::new(),
             connected_peers: HashMap::new(),
-            published_message_ids: DuplicateCache::new(config.published_message_ids_cache_time()),
+            published_message_ids: DuplicateCache::new(
+                config.published_message_ids_cache_time(),
+                config.clock().clone(),
+            ),
             config,
             subscription_filter,
             data_transform,
@@ -490,6 +640,7 @@ where
         self.mesh.get(topic_hash).into_iter().flat_map(|x| x.iter())
     }
 
+    /// Lists all mesh peers of all topics, without duplicates.
     pub fn all_mesh_peers(&self) -> impl Iterator<Item = &PeerId> {
         let mut res = BTreeSet::new();
         for peers in self.mesh.values() {
@@ -498,6 +649,43 @@ where
         res.into_iter()
     }
 
+    /// Returns the mesh membership change counters for a topic we are subscribed to.
+    pub fn mesh_stats(&self, topic_hash: &TopicHash) -> Option<MeshStats> {
+        self.mesh
+            .contains_key(topic_hash)
+            .then(|| self.mesh_stats.get(topic_hash).copied().unwrap_or_default())
+    }
+
+    /// Returns the bytes of the messages published, forwarded and received on a topic.
+    ///
+    /// Topics are tracked while we are subscribed to them, publish to them or have a
+    /// [`BandwidthQuota`](crate::BandwidthQuota) for them.
+    pub fn topic_bandwidth(&self, topic_hash: &TopicHash) -> Option<TopicBandwidth> {
+        self.topic_bandwidth.get(topic_hash).copied().or_else(|| {
+            self.is_bandwidth_tracked(topic_hash)
+                .then(TopicBandwidth::default)
+        })
+    }
+
+    /// Returns the counters of the limited gossip control messages of a connected peer.
+    pub fn gossip_control_stats(&self, peer_id: &PeerId) -> Option<GossipControlStats> {
+        self.gossip_control_stats.get(peer_id).copied()
+    }
+
+    /// Lists the hashes of the topics we published to without being subscribed, and thus
+    /// maintain a fanout for.
+    pub fn fanout_topics(&self) -> impl Iterator<Item = &TopicHash> {
+        self.fanout.keys()
+    }
+
+    /// Lists all fanout peers for a certain topic hash.
+    pub fn fanout_peers(&self, topic_hash: &TopicHash) -> impl Iterator<Item = &PeerId> {
+        self.fanout
+            .get(topic_hash)
+            .into_iter()
+            .flat_map(|x| x.iter())
+    }
+
     /// Lists all known peers and their associated subscribed topics.
     pub fn all_peers(&self) -> impl Iterator<Item = (&PeerId, Vec<&TopicHash>)> {
         self.peer_topics
@@ -510,6 +698,17 @@ where
         self.connected_peers.iter().map(|(k, v)| (k, &v.kind))
     }
 
+    /// Returns the protocol negotiated with a connected peer.
+    ///
+    /// Returns `None` if the peer isn't connected or no protocol has been negotiated with it yet,
+    /// in which case [`Behaviour::peer_protocol`] reports it as a [`PeerKind::Floodsub`] peer.
+    pub fn peer_kind(&self, peer_id: &PeerId) -> Option<&PeerKind> {
+        self.connected_peers
+            .get(peer_id)
+            .filter(|connections| connections.kind_negotiated)
+            .map(|connections| &connections.kind)
+    }
+
     /// Returns the gossipsub score for a given peer, if one exists.
     pub fn peer_score(&self, peer_id: &PeerId) -> Option<f64> {
         self.peer_score
@@ -521,16 +720,98 @@ where
     ///
     /// Returns [`Ok(true)`] if the subscription worked. Returns [`Ok(false)`] if we were already
     /// subscribed.
+    ///
+    /// If we are already subscribed to [`Config::max_subscriptions`] topics, the subscription
+    /// either fails or replaces the least recently used one, depending on
+    /// [`Config::subscription_limit_action`].
+    ///
+    /// Subscribing to a topic we observe joins its mesh, see [`Behaviour::observe`].
     pub fn subscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, SubscriptionError> {
         tracing::debug!(%topic, "Subscribing to topic");
-        let topic_hash = topic.hash();
+        self.subscribe_hash(topic.hash(), false)
+    }
+
+    /// Subscribes to a topic in observer mode, receiving its messages without contributing to
+    /// its mesh.
+    ///
+    /// The subscription is announced to our peers as usual, so that they send us gossip and
+    /// flood published messages of the topic. However, we never GRAFT peers into the mesh of
+    /// the topic and answer their GRAFTs with a PRUNE, as any peer with a full mesh would.
+    /// Received messages are not forwarded and messages we publish are sent as if we were not
+    /// subscribed. IWANTs for messages of the topic are only answered, and IHAVEs about them
+    /// only emitted, if [`Config::observer_answers_iwant`] is set.
+    ///
+    /// Returns [`Ok(true)`] if we were not observing the topic before. Observing a topic we are
+    /// subscribed to leaves its mesh, while [`Behaviour::subscribe`] joins the mesh of a topic
+    /// we observe. Either way, [`Behaviour::unsubscribe`] ends the subscription.
+    pub fn observe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, SubscriptionError> {
+        tracing::debug!(%topic, "Observing topic");
+        self.subscribe_hash(topic.hash(), true)
+    }
+
+    /// Returns whether we are subscribed to the topic in observer mode, see
+    /// [`Behaviour::observe`].
+    pub fn is_observing(&self, topic_hash: &TopicHash) -> bool {
+        self.observed_topics.contains(topic_hash)
+    }
+
+    fn subscribe_hash(
+        &mut self,
+        topic_hash: TopicHash,
+        observe: bool,
+    ) -> Result<bool, SubscriptionError> {
         if !self.subscription_filter.can_subscribe(&topic_hash) {
             return Err(SubscriptionError::NotAllowed);
         }
 
         if self.mesh.contains_key(&topic_hash) {
-            tracing::debug!(%topic, "Topic is already in the mesh");
-            return Ok(false);
+            self.use_subscription(&topic_hash);
+            if self.observed_topics.contains(&topic_hash) == observe {
+                tracing::debug!(topic=%topic_hash, "Topic is already in the mesh");
+                return Ok(false);
+            }
+            if observe {
+                self.leave_mesh(&topic_hash);
+            } else {
+                tracing::debug!(topic=%topic_hash, "Joining mesh of observed topic");
+                self.observed_topics.remove(&topic_hash);
+                self.mesh.remove(&topic_hash);
+                if let Some(m) = self.metrics.as_mut() {
+                    m.left(&topic_hash)
+                }
+                self.join(&topic_hash);
+            }
+            return Ok(true);
+        }
+
+        if let Some(max_subscriptions) = self.config.max_subscriptions() {
+            while self.mesh.len() >= max_subscriptions {
+                if self.config.subscription_limit_action() == SubscriptionLimitAction::Reject {
+                    tracing::debug!(
+                        topic=%topic_hash,
+                        "Not subscribing to topic, too many subscriptions"
+                    );
+                    return Err(SubscriptionError::TooManySubscriptions);
+                }
+                let Some(evicted) = self
+                    .subscription_last_use
+                    .iter()
+                    .min_by_key(|(_, last_use)| **last_use)
+                    .map(|(topic_hash, _)| topic_hash.clone())
+                else {
+                    return Err(SubscriptionError::TooManySubscriptions);
+                };
+                tracing::debug!(
+                    topic=%topic_hash,
+                    evicted=%evicted,
+                    "Unsubscribing from least recently used topic to make room for subscription"
+                );
+                self.unsubscribe_hash(&evicted);
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::SubscriptionEvicted {
+                        topic: evicted,
+                    }));
+            }
         }
 
         // send subscription request to all peers
@@ -542,22 +823,63 @@ where
 
         // call JOIN(topic)
         // this will add new peers to the mesh for the topic
+        if observe {
+            self.observed_topics.insert(topic_hash.clone());
+        }
         self.join(&topic_hash);
-        tracing::debug!(%topic, "Subscribed to topic");
+        self.subscription_last_use.insert(topic_hash.clone(), 0);
+        self.use_subscription(&topic_hash);
+        tracing::debug!(topic=%topic_hash, "Subscribed to topic");
         Ok(true)
     }
 
+    /// Turns a topic we are subscribed to into an observed one, pruning all its mesh peers.
+    fn leave_mesh(&mut self, topic_hash: &TopicHash) {
+        tracing::debug!(topic=%topic_hash, "Leaving mesh of observed topic");
+        self.observed_topics.insert(topic_hash.clone());
+        let peers = self
+            .mesh
+            .get_mut(topic_hash)
+            .map(std::mem::take)
+            .unwrap_or_default();
+
+        self.mesh_stats
+            .entry(topic_hash.clone())
+            .or_default()
+            .peers_removed += peers.len() as u64;
+        if let Some(m) = self.metrics.as_mut() {
+            m.peers_removed(topic_hash, Churn::Unsub, peers.len());
+            m.set_mesh_peers(topic_hash, 0);
+        }
+        for peer in peers {
+            tracing::debug!(%peer, "Sending PRUNE to peer of observed topic");
+            let control = self.make_prune(topic_hash, &peer, false, false);
+            Self::control_pool_add(&mut self.control_pool, peer, control);
+            peer_removed_from_mesh(
+                peer,
+                topic_hash,
+                &self.mesh,
+                self.peer_topics.get(&peer),
+                &mut self.events,
+                &self.connected_peers,
+            );
+        }
+    }
+
     /// Unsubscribes from a topic.
     ///
     /// Returns [`Ok(true)`] if we were subscribed to this topic.
     pub fn unsubscribe<H: Hasher>(&mut self, topic: &Topic<H>) -> Result<bool, PublishError> {
         tracing::debug!(%topic, "Unsubscribing from topic");
-        let topic_hash = topic.hash();
+        Ok(self.unsubscribe_hash(&topic.hash()))
+    }
 
-        if !self.mesh.contains_key(&topic_hash) {
+    /// Unsubscribes from a topic, returning whether we were subscribed to it.
+    fn unsubscribe_hash(&mut self, topic_hash: &TopicHash) -> bool {
+        if !self.mesh.contains_key(topic_hash) {
             tracing::debug!(topic=%topic_hash, "Already unsubscribed from topic");
             // we are not subscribed
-            return Ok(false);
+            return false;
         }
 
         // announce to all peers
@@ -569,10 +891,19 @@ where
 
         // call LEAVE(topic)
         // this will remove the topic from the mesh
-        self.leave(&topic_hash);
+        self.leave(topic_hash);
 
         tracing::debug!(topic=%topic_hash, "Unsubscribed from topic");
-        Ok(true)
+        true
+    }
+
+    /// Marks a topic we are subscribed to as used, see
+    /// [`SubscriptionLimitAction::UnsubscribeLeastRecentlyUsed`].
+    fn use_subscription(&mut self, topic_hash: &TopicHash) {
+        if let Some(last_use) = self.subscription_last_use.get_mut(topic_hash) {
+            self.subscription_uses += 1;
+            *last_use = self.subscription_uses;
+        }
     }
 
     /// Publishes a message with multiple topics to the network.
@@ -618,6 +949,7 @@ where
         tracing::trace!(message=%msg_id, "Publishing message");
 
         let topic_hash = raw_message.topic.clone();
+        self.use_subscription(&topic_hash);
 
         let mut recipient_peers = HashSet::new();
         if let Some(set) = self.topic_peers.get(&topic_hash) {
@@ -628,7 +960,11 @@ where
                         || !self.score_below_threshold(p, |ts| ts.publish_threshold).0
                 }));
             } else {
-                match self.mesh.get(&raw_message.topic) {
+                match self
+                    .mesh
+                    .get(&raw_message.topic)
+                    .filter(|_| !self.observed_topics.contains(&topic_hash))
+                {
                     // Mesh peers
                     Some(mesh_peers) => {
                         recipient_peers.extend(mesh_peers);
@@ -669,7 +1005,7 @@ where
                         }
                         // We are publishing to fanout peers - update the time we published
                         self.fanout_last_pub
-                            .insert(topic_hash.clone(), Instant::now());
+                            .insert(topic_hash.clone(), self.config.clock().now());
                     }
                 }
 
@@ -712,10 +1048,23 @@ where
 
         // Send to peers we know are subscribed to the topic.
         for peer_id in recipient_peers.iter() {
+            self.known_messages.insert((*peer_id, msg_id.clone()));
             tracing::trace!(peer=%peer_id, "Sending message to peer");
             self.send_message(*peer_id, RpcOut::Publish(raw_message.clone()));
         }
 
+        if let Some(window) = self.config.delivery_receipt_window(&topic_hash) {
+            self.delivery_receipts.insert(
+                msg_id.clone(),
+                PendingReceipts {
+                    topic: topic_hash.clone(),
+                    deadline: self.config.clock().now() + window,
+                    recipients: recipient_peers.len(),
+                    unconfirmed: recipient_peers,
+                },
+            );
+        }
+
         tracing::debug!(message=%msg_id, "Published message");
 
         if let Some(metrics) = self.metrics.as_mut() {
@@ -866,9 +1215,13 @@ where
             return Err("Peer score set twice".into());
         }
 
-        let interval = Ticker::new(params.decay_interval);
-        let peer_score = PeerScore::new_with_message_delivery_time_callback(params, callback);
-        self.peer_score = Some((peer_score, threshold, interval, GossipPromises::default()));
+        let interval = Interval::new(self.config.clock().clone(), params.decay_interval);
+        let peer_score = PeerScore::new_with_message_delivery_time_callback(
+            params,
+            callback,
+            self.config.clock().clone(),
+        );
+        self.peer_score = Some((peer_score, threshold, interval));
         Ok(())
     }
 
@@ -903,6 +1256,22 @@ where
         }
     }
 
+    /// Updates the interval between heartbeats at runtime.
+    ///
+    /// The next heartbeat fires one new interval from now. Parameters that are expressed in
+    /// heartbeat ticks, e.g. [`Config::history_length`], keep their value and thus scale with
+    /// the new interval.
+    pub fn set_heartbeat_interval(&mut self, interval: Duration) -> Result<(), &'static str> {
+        if interval.is_zero() {
+            return Err("The heartbeat interval must be greater than zero");
+        }
+
+        self.config.set_heartbeat_interval(interval);
+        self.backoffs.set_heartbeat_interval(interval);
+        self.heartbeat = Interval::new(self.config.clock().clone(), interval);
+        Ok(())
+    }
+
     /// Gossipsub JOIN(topic) - adds topic peers to mesh and sends them GRAFT messages.
     fn join(&mut self, topic_hash: &TopicHash) {
         tracing::debug!(topic=%topic_hash, "Running JOIN for topic");
@@ -913,6 +1282,16 @@ where
             return;
         }
 
+        // observers don't take part in the mesh
+        if self.observed_topics.contains(topic_hash) {
+            tracing::debug!(topic=%topic_hash, "JOIN: Observing topic, not adding peers");
+            self.mesh.insert(topic_hash.clone(), BTreeSet::new());
+            if let Some(m) = self.metrics.as_mut() {
+                m.joined(topic_hash)
+            }
+            return;
+        }
+
         let mut added_peers = HashSet::new();
 
         if let Some(m) = self.metrics.as_mut() {
@@ -954,6 +1333,10 @@ where
         }
 
         let fanaout_added = added_peers.len();
+        self.mesh_stats
+            .entry(topic_hash.clone())
+            .or_default()
+            .peers_included += fanaout_added as u64;
         if let Some(m) = self.metrics.as_mut() {
             m.peers_included(topic_hash, Inclusion::Fanout, fanaout_added)
         }
@@ -969,6 +1352,7 @@ where
                 |peer| {
                     !added_peers.contains(peer)
                         && !self.explicit_peers.contains(peer)
+                        && !self.px_unverified.contains_key(peer)
                         && !self.score_below_threshold(peer, |_| 0.0).0
                         && !self.backoffs.is_backoff_with_slack(topic_hash, peer)
                 },
@@ -984,6 +1368,10 @@ where
         }
 
         let random_added = added_peers.len() - fanaout_added;
+        self.mesh_stats
+            .entry(topic_hash.clone())
+            .or_default()
+            .peers_included += random_added as u64;
         if let Some(m) = self.metrics.as_mut() {
             m.peers_included(topic_hash, Inclusion::Random, random_added)
         }
@@ -1089,6 +1477,9 @@ where
 
         // If our mesh contains the topic, send prune to peers and delete it from the mesh
         if let Some((_, peers)) = self.mesh.remove_entry(topic_hash) {
+            self.observed_topics.remove(topic_hash);
+            self.mesh_stats.remove(topic_hash);
+            self.subscription_last_use.remove(topic_hash);
             if let Some(m) = self.metrics.as_mut() {
                 m.left(topic_hash)
             }
@@ -1136,7 +1527,7 @@ where
     }
 
     fn score_below_threshold_from_scores(
-        peer_score: &Option<(PeerScore, PeerScoreThresholds, Ticker, GossipPromises)>,
+        peer_score: &Option<(PeerScore, PeerScoreThresholds, Interval)>,
         peer_id: &PeerId,
         threshold: impl Fn(&PeerScoreThresholds) -> f64,
     ) -> (bool, f64) {
@@ -1164,6 +1555,22 @@ where
             return;
         }
 
+        // Ignore peers which repeatedly advertised messages they did not deliver.
+        if let (Some(max), Some(broken)) = (
+            self.config.max_broken_promises(),
+            self.broken_promises.get(peer_id),
+        ) {
+            if *broken >= max {
+                tracing::debug!(
+                    peer=%peer_id,
+                    "IHAVE: peer has broken too many promises ({}); ignoring",
+                    *broken
+                );
+                self.register_gossip_control_limited(peer_id, GossipControlLimit::IHaveMessages, 1);
+                return;
+            }
+        }
+
         // IHAVE flood protection
         let peer_have = self.count_received_ihave.entry(*peer_id).or_insert(0);
         *peer_have += 1;
@@ -1174,6 +1581,7 @@ where
             interval; ignoring",
                 *peer_have
             );
+            self.register_gossip_control_limited(peer_id, GossipControlLimit::IHaveMessages, 1);
             return;
         }
 
@@ -1201,13 +1609,20 @@ where
                 return false;
             }
 
-            self.peer_score
-                .as_ref()
-                .map(|(_, _, _, promises)| !promises.contains(id))
-                .unwrap_or(true)
+            // When scoring peers, don't request a message again while another peer's promise to
+            // deliver it is pending.
+            self.peer_score.is_none() || !self.gossip_promises.contains(id)
         };
 
-        for (topic, ids) in ihave_msgs {
+        // IHAVE message id flood protection
+        let peer_have_ids = self.count_received_ihave_ids.entry(*peer_id).or_insert(0);
+        let mut ids_budget = self
+            .config
+            .max_ihave_message_ids()
+            .saturating_sub(*peer_have_ids);
+        let mut ids_ignored = 0;
+
+        for (topic, mut ids) in ihave_msgs {
             // only process the message if we are subscribed
             if !self.mesh.contains_key(&topic) {
                 tracing::debug!(
@@ -1217,6 +1632,13 @@ where
                 continue;
             }
 
+            if ids.len() > ids_budget {
+                ids_ignored += ids.len() - ids_budget;
+                ids.truncate(ids_budget);
+            }
+            ids_budget -= ids.len();
+            *peer_have_ids += ids.len();
+
             for id in ids.into_iter().filter(want_message) {
                 // have not seen this message and are not currently requesting it
                 if iwant_ids.insert(id) {
@@ -1228,6 +1650,20 @@ where
             }
         }
 
+        if ids_ignored > 0 {
+            tracing::debug!(
+                peer=%peer_id,
+                "IHAVE: peer has advertised too many message ids within this heartbeat \
+                interval; ignoring {} message ids",
+                ids_ignored
+            );
+            self.register_gossip_control_limited(
+                peer_id,
+                GossipControlLimit::IHaveMessageIds,
+                ids_ignored,
+            );
+        }
+
         if !iwant_ids.is_empty() {
             let iasked = self.count_sent_iwant.entry(*peer_id).or_insert(0);
             let mut iask = iwant_ids.len();
@@ -1256,13 +1692,11 @@ where
                 self.pending_iwant_msgs.insert(message_id.clone());
             }
 
-            if let Some((_, _, _, gossip_promises)) = &mut self.peer_score {
-                gossip_promises.add_promise(
-                    *peer_id,
-                    &iwant_ids_vec,
-                    Instant::now() + self.config.iwant_followup_time(),
-                );
-            }
+            self.gossip_promises.add_promise(
+                *peer_id,
+                &iwant_ids_vec,
+                self.config.clock().now() + self.config.iwant_followup_time(),
+            );
             tracing::trace!(
                 peer=%peer_id,
                 "IHAVE: Asking for the following messages from peer: {:?}",
@@ -1280,6 +1714,58 @@ where
         tracing::trace!(peer=%peer_id, "Completed IHAVE handling for peer");
     }
 
+    /// Handles an ACK control message, counting the receipts for the messages we published.
+    ///
+    /// Receipts for other messages and from peers we did not send the message to are ignored.
+    fn handle_ack(&mut self, peer_id: &PeerId, message_ids: Vec<MessageId>) {
+        for message_id in message_ids {
+            let Some(pending) = self.delivery_receipts.get_mut(&message_id) else {
+                continue;
+            };
+            if !pending.unconfirmed.remove(peer_id) {
+                continue;
+            }
+            tracing::trace!(peer=%peer_id, message=%message_id, "Received delivery receipt");
+
+            if pending.unconfirmed.is_empty() {
+                let pending = self
+                    .delivery_receipts
+                    .remove(&message_id)
+                    .expect("pending receipts to exist");
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(pending.into_event(message_id)));
+            }
+        }
+    }
+
+    /// Sends the pending receipts, batching those owed to a peer into a single ACK control
+    /// message.
+    fn flush_acks(&mut self) {
+        for (peer_id, message_ids) in self.pending_acks.drain().collect::<Vec<_>>() {
+            self.send_message(peer_id, RpcOut::Control(ControlAction::Ack { message_ids }));
+        }
+    }
+
+    /// Reports the receipts of the messages whose receipt window ended.
+    fn expire_delivery_receipts(&mut self) {
+        let now = self.config.clock().now();
+        let expired = self
+            .delivery_receipts
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(message_id, _)| message_id.clone())
+            .collect::<Vec<_>>();
+
+        for message_id in expired {
+            let pending = self
+                .delivery_receipts
+                .remove(&message_id)
+                .expect("pending receipts to exist");
+            self.events
+                .push_back(ToSwarm::GenerateEvent(pending.into_event(message_id)));
+        }
+    }
+
     /// Handles an IWANT control message. Checks our cache of messages. If the message exists it is
     /// forwarded to the requesting peer.
     fn handle_iwant(&mut self, peer_id: &PeerId, iwant_msgs: Vec<MessageId>) {
@@ -1295,7 +1781,16 @@ where
 
         tracing::debug!(peer=%peer_id, "Handling IWANT for peer");
 
+        let mut responses_dropped = 0;
+
         for id in iwant_msgs {
+            // IWANT flood protection
+            let responses = self.count_sent_iwant_responses.entry(*peer_id).or_insert(0);
+            if *responses >= self.config.max_iwant_responses() {
+                responses_dropped += 1;
+                continue;
+            }
+
             // If we have it and the IHAVE count is not above the threshold,
             // forward the message.
             if let Some((msg, count)) = self
@@ -1303,7 +1798,15 @@ where
                 .get_with_iwant_counts(&id, peer_id)
                 .map(|(msg, count)| (msg.clone(), count))
             {
-                if count > self.config.gossip_retransimission() {
+                if self.observed_topics.contains(&msg.topic)
+                    && !self.config.observer_answers_iwant()
+                {
+                    tracing::debug!(
+                        peer=%peer_id,
+                        message=%id,
+                        "IWANT: Not answering for message of observed topic"
+                    );
+                } else if count > self.config.gossip_retransimission() {
                     tracing::debug!(
                         peer=%peer_id,
                         message=%id,
@@ -1311,13 +1814,49 @@ where
                     );
                 } else {
                     tracing::debug!(peer=%peer_id, "IWANT: Sending cached messages to peer");
+                    *responses += 1;
+                    self.known_messages.insert((*peer_id, id));
                     self.send_message(*peer_id, RpcOut::Forward(msg));
                 }
             }
         }
+        if responses_dropped > 0 {
+            tracing::debug!(
+                peer=%peer_id,
+                "IWANT: peer has requested too many messages within this heartbeat interval; \
+                not sending {} messages",
+                responses_dropped
+            );
+            self.register_gossip_control_limited(
+                peer_id,
+                GossipControlLimit::IWantResponses,
+                responses_dropped,
+            );
+        }
         tracing::debug!(peer=%peer_id, "Completed IWANT handling for peer");
     }
 
+    /// Records that `count` gossip control items of the given peer were limited by `limit`.
+    fn register_gossip_control_limited(
+        &mut self,
+        peer_id: &PeerId,
+        limit: GossipControlLimit,
+        count: usize,
+    ) {
+        let count = count as u64;
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.register_gossip_control_limited(limit, count);
+        }
+        if let Some(stats) = self.gossip_control_stats.get_mut(peer_id) {
+            match limit {
+                GossipControlLimit::IHaveMessages => stats.ihave_messages_ignored += count,
+                GossipControlLimit::IHaveMessageIds => stats.ihave_message_ids_ignored += count,
+                GossipControlLimit::IWantResponses => stats.iwant_responses_dropped += count,
+                GossipControlLimit::BrokenPromises => stats.broken_promises += count,
+            }
+        }
+    }
+
     /// Handles GRAFT control messages. If subscribed to the topic, adds the peer to mesh, if not,
     /// responds with PRUNE messages.
     fn handle_graft(&mut self, peer_id: &PeerId, topics: Vec<TopicHash>) {
@@ -1338,6 +1877,12 @@ where
                 .entry(topic.clone())
                 .or_default()
                 .insert(*peer_id);
+            if self.mesh.contains_key(topic) {
+                self.mesh_stats
+                    .entry(topic.clone())
+                    .or_default()
+                    .grafts_received += 1;
+            }
         }
 
         // we don't GRAFT to/from explicit peers; complain loudly if this happens
@@ -1349,9 +1894,21 @@ where
             do_px = false
         } else {
             let (below_zero, score) = self.score_below_threshold(peer_id, |_| 0.0);
-            let now = Instant::now();
+            let now = self.config.clock().now();
             for topic_hash in topics {
                 if let Some(peers) = self.mesh.get_mut(&topic_hash) {
+                    // observers don't take part in the mesh, but PRUNE like a peer with a full mesh
+                    if self.observed_topics.contains(&topic_hash) {
+                        tracing::debug!(
+                            peer=%peer_id,
+                            topic=%topic_hash,
+                            "GRAFT: Observing topic, sending PRUNE"
+                        );
+                        to_prune_topics.insert(topic_hash.clone());
+                        do_px = false;
+                        continue;
+                    }
+
                     // if the peer is already in the mesh ignore the graft
                     if peers.contains(peer_id) {
                         tracing::debug!(
@@ -1429,6 +1986,10 @@ where
                     );
 
                     if peers.insert(*peer_id) {
+                        self.mesh_stats
+                            .entry(topic_hash.clone())
+                            .or_default()
+                            .peers_included += 1;
                         if let Some(m) = self.metrics.as_mut() {
                             m.peers_included(&topic_hash, Inclusion::Subscribed, 1)
                         }
@@ -1497,6 +2058,10 @@ where
                     topic=%topic_hash,
                     "PRUNE: Removing peer from the mesh for topic"
                 );
+                self.mesh_stats
+                    .entry(topic_hash.clone())
+                    .or_default()
+                    .peers_removed += 1;
                 if let Some(m) = self.metrics.as_mut() {
                     m.peers_removed(topic_hash, reason, 1)
                 }
@@ -1539,6 +2104,12 @@ where
         let (below_threshold, score) =
             self.score_below_threshold(peer_id, |pst| pst.accept_px_threshold);
         for (topic_hash, px, backoff) in prune_data {
+            if self.mesh.contains_key(&topic_hash) {
+                self.mesh_stats
+                    .entry(topic_hash.clone())
+                    .or_default()
+                    .prunes_received += 1;
+            }
             self.remove_peer_from_mesh(peer_id, &topic_hash, backoff, true, Churn::Prune);
 
             if self.mesh.contains_key(&topic_hash) {
@@ -1591,12 +2162,84 @@ where
                 // mark as px peer
                 self.px_peers.insert(peer_id);
 
+                if self.config.px_verification() != PxVerification::None {
+                    // Connected peers are known by other means and thus not verified.
+                    if !self.connected_peers.contains_key(&peer_id)
+                        && !self.px_unverified.contains_key(&peer_id)
+                    {
+                        self.px_unverified.insert(peer_id, PxState::Queued);
+                        self.px_dial_queue.push_back(peer_id);
+                    }
+                    continue;
+                }
+
                 // dial peer
                 self.events.push_back(ToSwarm::Dial {
                     opts: DialOpts::peer_id(peer_id).build(),
                 });
             }
         }
+
+        self.dial_px_peers();
+    }
+
+    /// Dials the queued unverified peers found through peer exchange, as far as
+    /// [`Config::max_concurrent_px_dials`] permits.
+    fn dial_px_peers(&mut self) {
+        let mut num_dialing = self
+            .px_unverified
+            .values()
+            .filter(|state| matches!(state, PxState::Dialing(_)))
+            .count();
+
+        while num_dialing < self.config.max_concurrent_px_dials().get() {
+            let Some(peer_id) = self.px_dial_queue.pop_front() else {
+                return;
+            };
+            let Some(state) = self.px_unverified.get_mut(&peer_id) else {
+                // The peer was dropped while waiting.
+                continue;
+            };
+            if *state != PxState::Queued {
+                continue;
+            }
+
+            // Dial regardless of existing connections, as only our own dial proves that the
+            // peer is reachable.
+            let opts = DialOpts::peer_id(peer_id)
+                .condition(PeerCondition::Always)
+                .build();
+            *state = PxState::Dialing(opts.connection_id());
+
+            tracing::debug!(peer=%peer_id, "Dialing PX peer to verify it");
+            num_dialing += 1;
+            self.events.push_back(ToSwarm::Dial { opts });
+        }
+    }
+
+    /// Handles the peers found through peer exchange whose dial failure backoff expired: Peers
+    /// which are still connected are verified again, all others are forgotten.
+    fn expire_px_dial_failures(&mut self) {
+        let now = self.config.clock().now();
+        let expired = self
+            .px_unverified
+            .iter()
+            .filter(|(_, state)| matches!(state, PxState::Failed(until) if *until <= now))
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return;
+        }
+
+        for peer_id in expired {
+            if self.connected_peers.contains_key(&peer_id) {
+                self.px_unverified.insert(peer_id, PxState::Queued);
+                self.px_dial_queue.push_back(peer_id);
+            } else {
+                self.px_unverified.remove(&peer_id);
+            }
+        }
+        self.dial_px_peers();
     }
 
     /// Applies some basic checks to whether this message is valid. Does not apply user validation
@@ -1619,15 +2262,16 @@ where
                 peer=%propagation_source,
                 "Rejecting message from blacklisted peer"
             );
-            if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
+            if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.reject_message(
                     propagation_source,
                     msg_id,
                     &raw_message.topic,
                     RejectReason::BlackListedPeer,
                 );
-                gossip_promises.reject_message(msg_id, &RejectReason::BlackListedPeer);
             }
+            self.gossip_promises
+                .reject_message(msg_id, &RejectReason::BlackListedPeer);
             return false;
         }
 
@@ -1677,6 +2321,21 @@ where
         true
     }
 
+    /// Checks the timestamp embedded into the message, if any, against the configured replay
+    /// window, tolerating the configured clock skew in both directions.
+    fn message_is_stale(&self, message: &Message) -> bool {
+        let Some(timestamp) = self.config.message_timestamp(message) else {
+            return false;
+        };
+        let now = SystemTime::now();
+        let skew = self.config.max_clock_skew();
+
+        match now.duration_since(timestamp) {
+            Ok(age) => age > self.config.message_replay_window() + skew,
+            Err(e) => e.duration() > skew,
+        }
+    }
+
     /// Handles a newly received [`RawMessage`].
     ///
     /// Forwards the message to all peers in the mesh.
@@ -1689,6 +2348,9 @@ where
         if let Some(metrics) = self.metrics.as_mut() {
             metrics.msg_recvd_unfiltered(&raw_message.topic, raw_message.raw_protobuf_len());
         }
+        if let Some(bandwidth) = self.tracked_topic_bandwidth(&raw_message.topic) {
+            bandwidth.received += raw_message.raw_protobuf_len() as u64;
+        }
 
         // Try and perform the data transform to the message. If it fails, consider it invalid.
         let message = match self.data_transform.inbound_transform(raw_message.clone()) {
@@ -1715,6 +2377,37 @@ where
             return;
         }
 
+        if self.message_is_stale(&message) {
+            tracing::debug!(
+                message=%msg_id,
+                source=%propagation_source,
+                "Dropping message with a timestamp outside of the replay window"
+            );
+            self.handle_invalid_message(
+                propagation_source,
+                &raw_message,
+                RejectReason::ValidationIgnored,
+            );
+            return;
+        }
+
+        // The peer has the message, never send it back.
+        self.known_messages
+            .insert((*propagation_source, msg_id.clone()));
+
+        // Confirm the receipt to the peer, also for duplicates, as the peer might be the
+        // publisher waiting for the receipts.
+        if self
+            .config
+            .delivery_receipt_window(&message.topic)
+            .is_some()
+        {
+            self.pending_acks
+                .entry(*propagation_source)
+                .or_default()
+                .push(msg_id.clone());
+        }
+
         if !self.duplicate_cache.insert(msg_id.clone()) {
             tracing::debug!(message=%msg_id, "Message already received, ignoring");
             if let Some((peer_score, ..)) = &mut self.peer_score {
@@ -1735,10 +2428,10 @@ where
 
         // Tells score that message arrived (but is maybe not fully validated yet).
         // Consider the message as delivered for gossip promises.
-        if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
+        if let Some((peer_score, ..)) = &mut self.peer_score {
             peer_score.validate_message(propagation_source, &msg_id, &message.topic);
-            gossip_promises.message_delivered(&msg_id);
         }
+        self.gossip_promises.message_delivered(&msg_id);
 
         // Add the message to our memcache
         self.mcache.put(&msg_id, raw_message.clone());
@@ -1746,6 +2439,7 @@ where
         // Dispatch the message to the user if we are subscribed to any of the topics
         if self.mesh.contains_key(&message.topic) {
             tracing::debug!("Sending received message to user");
+            self.use_subscription(&message.topic);
             self.events
                 .push_back(ToSwarm::GenerateEvent(Event::Message {
                     propagation_source: *propagation_source,
@@ -1784,7 +2478,7 @@ where
         raw_message: &RawMessage,
         reject_reason: RejectReason,
     ) {
-        if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
+        if let Some((peer_score, ..)) = &mut self.peer_score {
             if let Some(metrics) = self.metrics.as_mut() {
                 metrics.register_invalid_message(&raw_message.topic);
             }
@@ -1799,7 +2493,8 @@ where
                     reject_reason,
                 );
 
-                gossip_promises.reject_message(&message_id, &reject_reason);
+                self.gossip_promises
+                    .reject_message(&message_id, &reject_reason);
             } else {
                 // The message is invalid, we reject it ignoring any gossip promises. If a peer is
                 // advertising this message via an IHAVE and it's invalid it will be double
@@ -1888,7 +2583,11 @@ where
                             .backoffs
                             .is_backoff_with_slack(topic_hash, propagation_source)
                     {
-                        if let Some(peers) = self.mesh.get_mut(topic_hash) {
+                        if let Some(peers) = self
+                            .mesh
+                            .get_mut(topic_hash)
+                            .filter(|_| !self.observed_topics.contains(topic_hash))
+                        {
                             if peers.len() < self.config.mesh_n_low()
                                 && peers.insert(*propagation_source)
                             {
@@ -1897,6 +2596,10 @@ where
                                     topic=%topic_hash,
                                     "SUBSCRIPTION: Adding peer to the mesh for topic"
                                 );
+                                self.mesh_stats
+                                    .entry(topic_hash.clone())
+                                    .or_default()
+                                    .peers_included += 1;
                                 if let Some(m) = self.metrics.as_mut() {
                                     m.peers_included(topic_hash, Inclusion::Subscribed, 1)
                                 }
@@ -1983,15 +2686,127 @@ where
         );
     }
 
+    /// Records the protocol negotiated on a connection to the peer.
+    ///
+    /// The most capable protocol negotiated on any connection wins.
+    fn on_peer_kind(&mut self, peer_id: PeerId, kind: PeerKind) {
+        if let PeerKind::NotSupported = kind {
+            tracing::debug!(
+                peer=%peer_id,
+                "Peer does not support gossipsub protocols"
+            );
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::GossipsubNotSupported {
+                    peer_id,
+                }));
+        }
+
+        let Some(conn) = self.connected_peers.get_mut(&peer_id) else {
+            return;
+        };
+        let old = conn.kind_negotiated.then(|| conn.kind.clone());
+        if old.as_ref().is_some_and(|old| old.rank() >= kind.rank()) {
+            return;
+        }
+
+        tracing::debug!(
+            peer=%peer_id,
+            peer_type=%kind,
+            "New peer type found for peer"
+        );
+        conn.kind = kind.clone();
+        conn.kind_negotiated = true;
+
+        if let Some(metrics) = self.metrics.as_mut() {
+            if let Some(old) = old.clone() {
+                metrics.peer_protocol_disconnected(old);
+            }
+            metrics.peer_protocol_connected(kind.clone());
+        }
+
+        self.events
+            .push_back(ToSwarm::GenerateEvent(Event::PeerKindChanged {
+                peer_id,
+                old,
+                new: kind,
+            }));
+    }
+
+    /// Prunes a peer whose outbound queue stayed full from all meshes and penalizes it.
+    fn handle_slow_peer(&mut self, peer_id: PeerId, queue_len: usize, full_for: Duration) {
+        let Some(detection) = self.config.slow_peer_detection() else {
+            return;
+        };
+        tracing::debug!(
+            peer=%peer_id,
+            %queue_len,
+            ?full_for,
+            "Outbound queue of peer stayed full, pruning it from all meshes"
+        );
+
+        let pruned_topics = self
+            .mesh
+            .iter_mut()
+            .filter_map(|(topic_hash, peers)| peers.remove(&peer_id).then(|| topic_hash.clone()))
+            .collect::<Vec<_>>();
+        for topic_hash in &pruned_topics {
+            self.mesh_stats
+                .entry(topic_hash.clone())
+                .or_default()
+                .peers_removed += 1;
+            if let Some(m) = self.metrics.as_mut() {
+                m.peers_removed(topic_hash, Churn::Slow, 1);
+            }
+            let prune = self.make_prune(topic_hash, &peer_id, self.config.do_px(), false);
+            self.send_message(peer_id, RpcOut::Control(prune));
+        }
+        if let Some(topic_hash) = pruned_topics.first() {
+            // The peer is not in any mesh anymore, inform the handler.
+            peer_removed_from_mesh(
+                peer_id,
+                topic_hash,
+                &self.mesh,
+                self.peer_topics.get(&peer_id),
+                &mut self.events,
+                &self.connected_peers,
+            );
+        }
+
+        if let Some((peer_score, ..)) = &mut self.peer_score {
+            peer_score.add_penalty(&peer_id, detection.penalty());
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.register_score_penalty(Penalty::SlowPeer);
+            }
+        }
+
+        self.events
+            .push_back(ToSwarm::GenerateEvent(Event::SlowPeer {
+                peer_id,
+                queue_len,
+                full_for,
+                pruned_topics,
+            }));
+    }
+
     /// Applies penalties to peers that did not respond to our IWANT requests.
     fn apply_iwant_penalties(&mut self) {
-        if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
-            for (peer, count) in gossip_promises.get_broken_promises() {
+        self.broken_promises.retain(|_, count| {
+            *count -= 1;
+            *count > 0
+        });
+
+        for (peer, count) in self
+            .gossip_promises
+            .get_broken_promises(self.config.clock().now())
+        {
+            if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.add_penalty(&peer, count);
                 if let Some(metrics) = self.metrics.as_mut() {
                     metrics.register_score_penalty(Penalty::BrokenPromise);
                 }
             }
+            *self.broken_promises.entry(peer).or_default() += count;
+            self.register_gossip_control_limited(&peer, GossipControlLimit::BrokenPromises, count);
         }
     }
 
@@ -2008,10 +2823,19 @@ where
 
         // clean up expired backoffs
         self.backoffs.heartbeat();
+        self.expire_px_dial_failures();
 
         // clean up ihave counters
         self.count_sent_iwant.clear();
         self.count_received_ihave.clear();
+        self.count_sent_iwant_responses.clear();
+        self.count_received_ihave_ids.clear();
+
+        // release the messages delayed by bandwidth quotas
+        self.release_delayed_messages();
+
+        // report the receipts of messages whose receipt window ended
+        self.expire_delivery_receipts();
 
         // apply iwant penalties
         self.apply_iwant_penalties();
@@ -2035,10 +2859,15 @@ where
 
         // maintain the mesh for each topic
         for (topic_hash, peers) in self.mesh.iter_mut() {
+            if self.observed_topics.contains(topic_hash) {
+                continue;
+            }
+            let topic_start = Instant::now();
             let explicit_peers = &self.explicit_peers;
             let backoffs = &self.backoffs;
             let topic_peers = &self.topic_peers;
             let outbound_peers = &self.outbound_peers;
+            let px_unverified = &self.px_unverified;
 
             // drop all peers with negative score, without PX
             // if there is at some point a stable retain method for BTreeSet the following can be
@@ -2067,6 +2896,10 @@ where
                 }
             }
 
+            self.mesh_stats
+                .entry(topic_hash.clone())
+                .or_default()
+                .peers_removed += to_remove_peers.len() as u64;
             if let Some(m) = self.metrics.as_mut() {
                 m.peers_removed(topic_hash, Churn::BadScore, to_remove_peers.len())
             }
@@ -2093,6 +2926,7 @@ where
                     |peer| {
                         !peers.contains(peer)
                             && !explicit_peers.contains(peer)
+                            && !px_unverified.contains_key(peer)
                             && !backoffs.is_backoff_with_slack(topic_hash, peer)
                             && *scores.get(peer).unwrap_or(&0.0) >= 0.0
                     },
@@ -2103,6 +2937,10 @@ where
                 }
                 // update the mesh
                 tracing::debug!("Updating mesh, new mesh: {:?}", peer_list);
+                self.mesh_stats
+                    .entry(topic_hash.clone())
+                    .or_default()
+                    .peers_included += peer_list.len() as u64;
                 if let Some(m) = self.metrics.as_mut() {
                     m.peers_included(topic_hash, Inclusion::Random, peer_list.len())
                 }
@@ -2165,6 +3003,10 @@ where
                     removed += 1;
                 }
 
+                self.mesh_stats
+                    .entry(topic_hash.clone())
+                    .or_default()
+                    .peers_removed += removed as u64;
                 if let Some(m) = self.metrics.as_mut() {
                     m.peers_removed(topic_hash, Churn::Excess, removed)
                 }
@@ -2186,6 +3028,7 @@ where
                         |peer| {
                             !peers.contains(peer)
                                 && !explicit_peers.contains(peer)
+                                && !px_unverified.contains_key(peer)
                                 && !backoffs.is_backoff_with_slack(topic_hash, peer)
                                 && *scores.get(peer).unwrap_or(&0.0) >= 0.0
                                 && outbound_peers.contains(peer)
@@ -2197,6 +3040,10 @@ where
                     }
                     // update the mesh
                     tracing::debug!("Updating mesh, new mesh: {:?}", peer_list);
+                    self.mesh_stats
+                        .entry(topic_hash.clone())
+                        .or_default()
+                        .peers_included += peer_list.len() as u64;
                     if let Some(m) = self.metrics.as_mut() {
                         m.peers_included(topic_hash, Inclusion::Outbound, peer_list.len())
                     }
@@ -2209,7 +3056,7 @@ where
                 && peers.len() > 1
                 && self.peer_score.is_some()
             {
-                if let Some((_, thresholds, _, _)) = &self.peer_score {
+                if let Some((_, thresholds, _)) = &self.peer_score {
                     // Opportunistic grafting works as follows: we check the median score of peers
                     // in the mesh; if this score is below the opportunisticGraftThreshold, we
                     // select a few peers at random with score over the median.
@@ -2254,6 +3101,7 @@ where
                             |peer_id| {
                                 !peers.contains(peer_id)
                                     && !explicit_peers.contains(peer_id)
+                                    && !px_unverified.contains_key(peer_id)
                                     && !backoffs.is_backoff_with_slack(topic_hash, peer_id)
                                     && *scores.get(peer_id).unwrap_or(&0.0) > median
                             },
@@ -2268,6 +3116,10 @@ where
                             "Opportunistically graft in topic with peers {:?}",
                             peer_list
                         );
+                        self.mesh_stats
+                            .entry(topic_hash.clone())
+                            .or_default()
+                            .peers_included += peer_list.len() as u64;
                         if let Some(m) = self.metrics.as_mut() {
                             m.peers_included(topic_hash, Inclusion::Random, peer_list.len())
                         }
@@ -2279,14 +3131,24 @@ where
             if let Some(m) = self.metrics.as_mut() {
                 m.set_mesh_peers(topic_hash, peers.len())
             }
+
+            let maintenance_time = topic_start.elapsed();
+            self.mesh_stats
+                .entry(topic_hash.clone())
+                .or_default()
+                .heartbeat_time += maintenance_time;
+            if let Some(m) = self.metrics.as_mut() {
+                m.mesh_maintained(topic_hash, maintenance_time)
+            }
         }
 
         // remove expired fanout topics
         {
             let fanout = &mut self.fanout; // help the borrow checker
             let fanout_ttl = self.config.fanout_ttl();
+            let now = self.config.clock().now();
             self.fanout_last_pub.retain(|topic_hash, last_pub_time| {
-                if *last_pub_time + fanout_ttl < Instant::now() {
+                if *last_pub_time + fanout_ttl < now {
                     tracing::debug!(
                         topic=%topic_hash,
                         "HEARTBEAT: Fanout topic removed due to timeout"
@@ -2298,12 +3160,22 @@ where
             });
         }
 
+        // stop tracking the bandwidth of topics we neither subscribe nor publish to anymore
+        {
+            let (mesh, fanout, config) = (&self.mesh, &self.fanout, &self.config);
+            self.topic_bandwidth.retain(|topic_hash, _| {
+                mesh.contains_key(topic_hash)
+                    || fanout.contains_key(topic_hash)
+                    || config.topic_bandwidth_quota(topic_hash).is_some()
+            });
+        }
+
         // maintain fanout
         // check if our peers are still a part of the topic
         for (topic_hash, peers) in self.fanout.iter_mut() {
             let mut to_remove_peers = Vec::new();
             let publish_threshold = match &self.peer_score {
-                Some((_, thresholds, _, _)) => thresholds.publish_threshold,
+                Some((_, thresholds, _)) => thresholds.publish_threshold,
                 _ => 0.0,
             };
             for peer in peers.iter() {
@@ -2382,6 +3254,24 @@ where
 
         self.emit_gossip();
 
+        // account the graft/prunes to the topics they maintain
+        for topic_hash in to_graft.values().flatten() {
+            if let Some(stats) = self.mesh_stats.get_mut(topic_hash) {
+                stats.heartbeat_grafts_sent += 1;
+            }
+            if let Some(m) = self.metrics.as_mut() {
+                m.mesh_maintenance_msg_sent(topic_hash)
+            }
+        }
+        for topic_hash in to_prune.values().flatten() {
+            if let Some(stats) = self.mesh_stats.get_mut(topic_hash) {
+                stats.heartbeat_prunes_sent += 1;
+            }
+            if let Some(m) = self.metrics.as_mut() {
+                m.mesh_maintenance_msg_sent(topic_hash)
+            }
+        }
+
         // send graft/prunes
         if !to_graft.is_empty() | !to_prune.is_empty() {
             self.send_graft_prune(to_graft, to_prune, no_px);
@@ -2405,6 +3295,9 @@ where
     fn emit_gossip(&mut self) {
         let mut rng = thread_rng();
         for (topic_hash, peers) in self.mesh.iter().chain(self.fanout.iter()) {
+            if self.observed_topics.contains(topic_hash) && !self.config.observer_answers_iwant() {
+                continue;
+            }
             let mut message_ids = self.mcache.get_gossip_message_ids(topic_hash);
             if message_ids.is_empty() {
                 continue;
@@ -2445,7 +3338,15 @@ where
             tracing::debug!("Gossiping IHAVE to {} peers", to_msg_peers.len());
 
             for peer in to_msg_peers {
-                let mut peer_message_ids = message_ids.clone();
+                // Don't announce messages the peer is known to have.
+                let mut peer_message_ids = message_ids
+                    .iter()
+                    .filter(|id| !self.known_messages.contains(&(peer, (*id).clone())))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if peer_message_ids.is_empty() {
+                    continue;
+                }
 
                 if peer_message_ids.len() > self.config.max_ihave_length() {
                     // We do this per peer so that we emit a different set for each peer.
@@ -2566,6 +3467,11 @@ where
             }
         }
 
+        if self.observed_topics.contains(&message.topic) {
+            tracing::debug!(message=%msg_id, "Not forwarding message of observed topic");
+            return Ok(false);
+        }
+
         tracing::debug!(message=%msg_id, "Forwarding message");
         let mut recipient_peers = HashSet::new();
 
@@ -2600,6 +3506,36 @@ where
             }
         }
 
+        // Don't send the message to peers that already have it, e.g. because they sent it to us.
+        recipient_peers.retain(|peer| self.known_messages.insert((*peer, msg_id.clone())));
+
+        // Apply the bandwidth quota of the topic, if any.
+        if let Some(quota) = self.config.topic_bandwidth_quota(&message.topic) {
+            let now = self.config.clock().now();
+            let bytes = message.raw_protobuf_len() as u64;
+            recipient_peers.retain(|peer| {
+                let throttle = match self.topic_quotas.admit(quota, *peer, &message, now) {
+                    Admission::Send => return true,
+                    Admission::Delayed => Throttle::Delayed,
+                    Admission::Dropped => Throttle::Dropped,
+                };
+                tracing::debug!(
+                    %peer,
+                    message=%msg_id,
+                    "Bandwidth quota of topic exceeded, message {:?}",
+                    throttle
+                );
+                self.topic_bandwidth
+                    .entry(message.topic.clone())
+                    .or_default()
+                    .throttled += bytes;
+                if let Some(m) = self.metrics.as_mut() {
+                    m.msg_throttled_bytes(&message.topic, throttle, bytes);
+                }
+                false
+            });
+        }
+
         // forward the message to peers
         if !recipient_peers.is_empty() {
             let event = RpcOut::Forward(message.clone());
@@ -2615,6 +3551,37 @@ where
         }
     }
 
+    /// Returns the bandwidth counters of a topic, if the topic is tracked.
+    fn tracked_topic_bandwidth(&mut self, topic: &TopicHash) -> Option<&mut TopicBandwidth> {
+        if self.is_bandwidth_tracked(topic) {
+            return Some(self.topic_bandwidth.entry(topic.clone()).or_default());
+        }
+        self.topic_bandwidth.get_mut(topic)
+    }
+
+    /// Whether we track the bandwidth of the given topic.
+    fn is_bandwidth_tracked(&self, topic: &TopicHash) -> bool {
+        self.mesh.contains_key(topic)
+            || self.fanout.contains_key(topic)
+            || self.config.topic_bandwidth_quota(topic).is_some()
+    }
+
+    /// Sends the forwarded messages delayed by the bandwidth quotas of their topics, as far as
+    /// the new quota intervals allow.
+    fn release_delayed_messages(&mut self) {
+        let config = &self.config;
+        let released = self.topic_quotas.release(
+            |topic| config.topic_bandwidth_quota(topic),
+            config.clock().now(),
+        );
+        for (peer, message) in released {
+            // The peer may have disconnected in the meantime.
+            if self.connected_peers.contains_key(&peer) {
+                self.send_message(peer, RpcOut::Forward(message));
+            }
+        }
+    }
+
     /// Constructs a [`RawMessage`] performing message signing if required.
     pub(crate) fn build_raw_message(
         &mut self,
@@ -2737,12 +3704,58 @@ where
                 m.msg_sent(&message.topic, message.raw_protobuf_len());
             }
         }
+        match rpc {
+            RpcOut::Publish(ref message) => {
+                let bytes = message.raw_protobuf_len();
+                if let Some(m) = self.metrics.as_mut() {
+                    m.msg_published_bytes(&message.topic, bytes);
+                }
+                self.topic_bandwidth
+                    .entry(message.topic.clone())
+                    .or_default()
+                    .published += bytes as u64;
+            }
+            RpcOut::Forward(ref message) => {
+                let bytes = message.raw_protobuf_len();
+                if let Some(m) = self.metrics.as_mut() {
+                    m.msg_forwarded_bytes(&message.topic, bytes);
+                }
+                self.topic_bandwidth
+                    .entry(message.topic.clone())
+                    .or_default()
+                    .forwarded += bytes as u64;
+            }
+            _ => {}
+        }
 
-        self.events.push_back(ToSwarm::NotifyHandler {
-            peer_id,
-            event: HandlerIn::Message(rpc),
-            handler: NotifyHandler::Any,
-        });
+        // RPCs exceeding the maximum transmit size would be rejected by the remote.
+        let rpcs = match rpc.fragment(self.config.max_transmit_size()) {
+            Ok(rpcs) => rpcs,
+            Err(rpc) => {
+                match *rpc {
+                    RpcOut::Publish(message) | RpcOut::Forward(message) => {
+                        let size = message.raw_protobuf_len();
+                        tracing::warn!(peer=%peer_id, topic=%message.topic, %size, "Message too large to send");
+                        self.events
+                            .push_back(ToSwarm::GenerateEvent(Event::MessageTooLarge {
+                                peer_id,
+                                topic: message.topic,
+                                size,
+                            }));
+                    }
+                    rpc => tracing::warn!(peer=%peer_id, ?rpc, "RPC too large to send"),
+                }
+                return;
+            }
+        };
+
+        for rpc in rpcs {
+            self.events.push_back(ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(rpc),
+                handler: NotifyHandler::Any,
+            });
+        }
     }
 
     fn on_connection_established(
@@ -2764,6 +3777,20 @@ where
             self.outbound_peers.insert(peer_id);
         }
 
+        // A successful dial verifies a peer found through peer exchange.
+        if endpoint.is_dialer() {
+            if let Some(state) = self.px_unverified.get_mut(&peer_id) {
+                match self.config.px_verification() {
+                    PxVerification::Identify => *state = PxState::Identifying,
+                    PxVerification::Dial | PxVerification::None => {
+                        tracing::debug!(peer=%peer_id, "Verified PX peer");
+                        self.px_unverified.remove(&peer_id);
+                    }
+                }
+                self.dial_px_peers();
+            }
+        }
+
         // Add the IP to the peer scoring system
         if let Some((peer_score, ..)) = &mut self.peer_score {
             if let Some(ip) = get_ip_addr(endpoint.get_remote_address()) {
@@ -2786,6 +3813,7 @@ where
             .entry(peer_id)
             .or_insert(PeerConnections {
                 kind: PeerKind::Floodsub,
+                kind_negotiated: false,
                 connections: vec![],
             })
             .connections
@@ -2797,6 +3825,8 @@ where
 
         // Insert an empty set of the topics of this peer until known.
         self.peer_topics.insert(peer_id, Default::default());
+        self.gossip_control_stats
+            .insert(peer_id, GossipControlStats::default());
 
         if let Some((peer_score, ..)) = &mut self.peer_score {
             peer_score.add_peer(peer_id);
@@ -2885,6 +3915,10 @@ where
                     if let Some(mesh_peers) = self.mesh.get_mut(topic) {
                         // check if the peer is in the mesh and remove it
                         if mesh_peers.remove(&peer_id) {
+                            self.mesh_stats
+                                .entry(topic.clone())
+                                .or_default()
+                                .peers_removed += 1;
                             if let Some(m) = self.metrics.as_mut() {
                                 m.peers_removed(topic, Churn::Dc, 1);
                                 m.set_mesh_peers(topic, mesh_peers.len());
@@ -2922,6 +3956,14 @@ where
             // Forget px and outbound status for this peer
             self.px_peers.remove(&peer_id);
             self.outbound_peers.remove(&peer_id);
+            // Peers that failed verification stay ineligible until their backoff expired.
+            if !matches!(
+                self.px_unverified.get(&peer_id),
+                None | Some(PxState::Failed(_))
+            ) {
+                self.px_unverified.remove(&peer_id);
+                self.dial_px_peers();
+            }
 
             // Remove peer from peer_topics and connected_peers
             // NOTE: It is possible the peer has already been removed from all mappings if it does not
@@ -2930,15 +3972,18 @@ where
 
             // If metrics are enabled, register the disconnection of a peer based on its protocol.
             if let Some(metrics) = self.metrics.as_mut() {
-                let peer_kind = &self
+                let connections = self
                     .connected_peers
                     .get(&peer_id)
-                    .expect("Connected peer must be registered")
-                    .kind;
-                metrics.peer_protocol_disconnected(peer_kind.clone());
+                    .expect("Connected peer must be registered");
+                if connections.kind_negotiated {
+                    metrics.peer_protocol_disconnected(connections.kind.clone());
+                }
             }
 
             self.connected_peers.remove(&peer_id);
+            self.gossip_control_stats.remove(&peer_id);
+            self.pending_acks.remove(&peer_id);
 
             if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.remove_peer(&peer_id);
@@ -3002,7 +4047,11 @@ where
         _: &Multiaddr,
         _: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(Handler::new(
+            self.config.protocol_config(),
+            self.config.slow_peer_detection(),
+            self.config.clock().clone(),
+        ))
     }
 
     fn handle_established_outbound_connection(
@@ -3012,7 +4061,11 @@ where
         _: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(Handler::new(
+            self.config.protocol_config(),
+            self.config.slow_peer_detection(),
+            self.config.clock().clone(),
+        ))
     }
 
     fn on_connection_handler_event(
@@ -3024,34 +4077,12 @@ where
         match handler_event {
             HandlerEvent::PeerKind(kind) => {
                 // We have identified the protocol this peer is using
-
-                if let Some(metrics) = self.metrics.as_mut() {
-                    metrics.peer_protocol_connected(kind.clone());
-                }
-
-                if let PeerKind::NotSupported = kind {
-                    tracing::debug!(
-                        peer=%propagation_source,
-                        "Peer does not support gossipsub protocols"
-                    );
-                    self.events
-                        .push_back(ToSwarm::GenerateEvent(Event::GossipsubNotSupported {
-                            peer_id: propagation_source,
-                        }));
-                } else if let Some(conn) = self.connected_peers.get_mut(&propagation_source) {
-                    // Only change the value if the old value is Floodsub (the default set in
-                    // `NetworkBehaviour::on_event` with FromSwarm::ConnectionEstablished).
-                    // All other PeerKind changes are ignored.
-                    tracing::debug!(
-                        peer=%propagation_source,
-                        peer_type=%kind,
-                        "New peer type found for peer"
-                    );
-                    if let PeerKind::Floodsub = conn.kind {
-                        conn.kind = kind;
-                    }
-                }
+                self.on_peer_kind(propagation_source, kind);
             }
+            HandlerEvent::SlowPeer {
+                queue_len,
+                full_for,
+            } => self.handle_slow_peer(propagation_source, queue_len, full_for),
             HandlerEvent::Message {
                 rpc,
                 invalid_messages,
@@ -3127,6 +4158,9 @@ where
                             peers,
                             backoff,
                         } => prune_msgs.push((topic_hash, peers, backoff)),
+                        ControlAction::Ack { message_ids } => {
+                            self.handle_ack(&propagation_source, message_ids)
+                        }
                     }
                 }
                 if !ihave_msgs.is_empty() {
@@ -3147,12 +4181,14 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.flush_acks();
+
         if let Some(event) = self.events.pop_front() {
             return Poll::Ready(event);
         }
 
         // update scores
-        if let Some((peer_score, _, interval, _)) = &mut self.peer_score {
+        if let Some((peer_score, _, interval)) = &mut self.peer_score {
             while let Poll::Ready(Some(_)) = interval.poll_next_unpin(cx) {
                 peer_score.refresh_scores();
             }
@@ -3174,11 +4210,69 @@ where
                 self.on_connection_closed(connection_closed)
             }
             FromSwarm::AddressChange(address_change) => self.on_address_change(address_change),
+            FromSwarm::DialFailure(DialFailure {
+                peer_id: Some(peer_id),
+                connection_id,
+                ..
+            }) => {
+                if self.px_unverified.get(&peer_id) == Some(&PxState::Dialing(connection_id)) {
+                    tracing::debug!(peer=%peer_id, "Failed to verify PX peer");
+                    // Remember the failure, such that a later inbound connection of the peer
+                    // doesn't make it eligible without verification.
+                    let until = self.config.clock().now() + self.config.px_dial_failure_backoff();
+                    self.px_unverified.insert(peer_id, PxState::Failed(until));
+                    self.dial_px_peers();
+                }
+            }
+            FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer { peer_id, .. }) => {
+                if self.px_unverified.get(&peer_id) == Some(&PxState::Identifying) {
+                    tracing::debug!(peer=%peer_id, "Verified PX peer");
+                    self.px_unverified.remove(&peer_id);
+                }
+            }
             _ => {}
         }
     }
 }
 
+/// The receipts collected for a message we published, see [`Event::DeliveryReceipts`].
+struct PendingReceipts {
+    /// The topic of the message.
+    topic: TopicHash,
+    /// The end of the receipt window.
+    deadline: Instant,
+    /// The number of peers the message was sent to.
+    recipients: usize,
+    /// The peers the message was sent to that did not confirm the receipt yet.
+    unconfirmed: HashSet<PeerId>,
+}
+
+impl PendingReceipts {
+    fn into_event(self, message_id: MessageId) -> Event {
+        Event::DeliveryReceipts {
+            message_id,
+            topic: self.topic,
+            recipients: self.recipients,
+            confirmations: self.recipients - self.unconfirmed.len(),
+        }
+    }
+}
+
+/// The verification state of a peer found through peer exchange, see
+/// [`Config::px_verification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PxState {
+    /// Waiting for a free dial slot.
+    Queued,
+    /// Being dialed via the given connection.
+    Dialing(ConnectionId),
+    /// Dialed, waiting for an address of the peer to be reported, e.g. by identify.
+    Identifying,
+    /// Dialing the peer failed, it is ineligible until the given instant, see
+    /// [`Config::px_dial_failure_backoff`].
+    Failed(Instant),
+}
+
 /// This is called when peers are added to any mesh. It checks if the peer existed
 /// in any other mesh. If this is the first mesh they have joined, it queues a message to notify
 /// the appropriate connection handler to maintain a connection.
@@ -3356,6 +4450,8 @@ impl<C: DataTransform, F: TopicSubscriptionFilter> fmt::Debug for Behaviour<C, F
             .field("mesh", &self.mesh)
             .field("fanout", &self.fanout)
             .field("fanout_last_pub", &self.fanout_last_pub)
+            .field("mesh_stats", &self.mesh_stats)
+            .field("topic_bandwidth", &self.topic_bandwidth)
             .field("mcache", &self.mcache)
             .field("heartbeat", &self.heartbeat)
             .finish()
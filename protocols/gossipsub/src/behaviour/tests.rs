@@ -22,11 +22,17 @@
 
 use super::*;
 use crate::subscription_filter::WhitelistSubscriptionFilter;
-use crate::{config::ConfigBuilder, types::Rpc, IdentTopic as Topic};
+use crate::{
+    config::ConfigBuilder, protocol::ProtocolConfig, types::Rpc, BandwidthQuota,
+    ConfigBuilderError, IdentTopic as Topic, QuotaAction, SlowPeerDetection,
+};
 use async_std::net::Ipv4Addr;
 use byteorder::{BigEndian, ByteOrder};
 use libp2p_core::ConnectedPoint;
+use libp2p_swarm::{ConnectionHandler, ConnectionHandlerEvent, DialError};
 use rand::Rng;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
 use std::thread::sleep;
 
 #[derive(Default, Debug)]
@@ -220,6 +226,7 @@ where
         endpoint: &endpoint,
         failed_addresses: &[],
         other_established: 0, // first connection
+        negotiated_protocols: &Default::default(),
     }));
     if let Some(kind) = kind {
         gs.on_connection_handler_event(
@@ -351,10 +358,19 @@ fn proto_to_message(rpc: &proto::RPC) -> Rpc {
             });
         }
 
+        let ack_msgs = rpc_control.ack.into_iter().map(|ack| ControlAction::Ack {
+            message_ids: ack
+                .message_ids
+                .into_iter()
+                .map(MessageId::from)
+                .collect::<Vec<_>>(),
+        });
+
         control_msgs.extend(ihave_msgs);
         control_msgs.extend(iwant_msgs);
         control_msgs.extend(graft_msgs);
         control_msgs.extend(prune_msgs);
+        control_msgs.extend(ack_msgs);
     }
 
     Rpc {
@@ -564,6 +580,7 @@ fn test_join() {
             },
             failed_addresses: &[],
             other_established: 0,
+            negotiated_protocols: &Default::default(),
         }));
 
         // add the new peer to the fanout
@@ -675,6 +692,63 @@ fn test_publish_without_flood_publishing() {
     );
 }
 
+/// Test that published data is transformed before being signed and sent, while the message id is
+/// calculated from the untransformed data.
+#[test]
+fn test_publish_applies_data_transform() {
+    #[derive(Default, Clone)]
+    struct ReverseTransform;
+
+    impl DataTransform for ReverseTransform {
+        fn inbound_transform(&self, raw_message: RawMessage) -> Result<Message, std::io::Error> {
+            Ok(Message {
+                source: raw_message.source,
+                data: raw_message.data.into_iter().rev().collect(),
+                sequence_number: raw_message.sequence_number,
+                topic: raw_message.topic,
+            })
+        }
+
+        fn outbound_transform(
+            &self,
+            _topic: &TopicHash,
+            data: Vec<u8>,
+        ) -> Result<Vec<u8>, std::io::Error> {
+            Ok(data.into_iter().rev().collect())
+        }
+    }
+
+    let (mut gs, _, _) = inject_nodes::<ReverseTransform, AllowAllSubscriptionFilter>()
+        .peer_no(20)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+
+    let msg_id = gs.publish(Topic::new("topic1"), b"hello".to_vec()).unwrap();
+
+    let publishes = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                event: HandlerIn::Message(RpcOut::Publish(message)),
+                ..
+            } => Some(message.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(!publishes.is_empty(), "Should publish the message");
+
+    for raw_message in publishes {
+        assert_eq!(raw_message.data, b"olleh", "Should send transformed data");
+        assert!(raw_message.signature.is_some(), "Should sign the message");
+
+        let message = gs.data_transform.inbound_transform(raw_message).unwrap();
+        assert_eq!(message.data, b"hello");
+        assert_eq!(gs.config.message_id(&message), msg_id);
+    }
+}
+
 /// Test local node publish to unsubscribed topic
 #[test]
 fn test_fanout() {
@@ -931,6 +1005,7 @@ fn test_get_random_peers() {
                 *p,
                 PeerConnections {
                     kind: PeerKind::Gossipsubv1_1,
+                    kind_negotiated: true,
                     connections: vec![ConnectionId::new_unchecked(0)],
                 },
             )
@@ -1302,6 +1377,357 @@ fn test_handle_prune_peer_in_mesh() {
     );
 }
 
+#[test]
+// tests that mesh changes are counted and that the fanout is exposed after unsubscribing
+fn test_mesh_diagnostics() {
+    // turn off flood publish to make use of the fanout
+    let config = ConfigBuilder::default()
+        .flood_publish(false)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(20)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let topic = topic_hashes[0].clone();
+
+    gs.handle_prune(&peers[7], vec![(topic.clone(), vec![], None)]);
+    gs.backoffs = BackoffStorage::new(
+        &gs.config.prune_backoff(),
+        gs.config.heartbeat_interval(),
+        gs.config.backoff_slack(),
+        gs.config.clock().clone(),
+    );
+    let before = gs.mesh_stats(&topic).unwrap();
+    assert_eq!(before.prunes_received, 1);
+
+    gs.handle_graft(&peers[7], vec![topic.clone()]);
+    assert!(gs.mesh_peers(&topic).any(|p| p == &peers[7]));
+    gs.handle_prune(&peers[7], vec![(topic.clone(), vec![], None)]);
+
+    let after = gs.mesh_stats(&topic).unwrap();
+    assert_eq!(after.grafts_received, before.grafts_received + 1);
+    assert_eq!(after.prunes_received, before.prunes_received + 1);
+    assert_eq!(after.peers_included, before.peers_included + 1);
+    assert_eq!(after.peers_removed, before.peers_removed + 1);
+
+    assert!(gs.unsubscribe(&Topic::new("topic1")).unwrap());
+    assert_eq!(gs.mesh_stats(&topic), None);
+
+    gs.publish(Topic::new("topic1"), vec![0; 42]).unwrap();
+    assert_eq!(gs.fanout_topics().collect::<Vec<_>>(), vec![&topic]);
+    assert_eq!(gs.fanout_peers(&topic).count(), gs.config.mesh_n());
+}
+
+#[test]
+// tests that an observed topic receives messages without taking part in the mesh
+fn test_observe_topic() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(20)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+    let topic = topic_hashes[0].clone();
+    let mesh_peers = gs.mesh_peers(&topic).count();
+    flush_events(&mut gs);
+
+    // observing a subscribed topic prunes its mesh
+    assert!(gs.observe(&Topic::new("topic1")).unwrap());
+    assert!(gs.is_observing(&topic));
+    assert_eq!(gs.mesh_peers(&topic).count(), 0);
+    assert_eq!(
+        count_control_msgs(&gs, |_, m| matches!(m, ControlAction::Prune { .. })),
+        mesh_peers
+    );
+    assert!(!gs.observe(&Topic::new("topic1")).unwrap());
+    flush_events(&mut gs);
+
+    // GRAFTs are answered with a PRUNE and the mesh is not maintained
+    gs.handle_graft(&peers[19], vec![topic.clone()]);
+    gs.heartbeat();
+    assert_eq!(gs.mesh_peers(&topic).count(), 0);
+    assert_eq!(
+        count_control_msgs(&gs, |p, m| p == &peers[19]
+            && matches!(m, ControlAction::Prune { .. })),
+        1
+    );
+    assert_eq!(
+        count_control_msgs(&gs, |_, m| matches!(m, ControlAction::Graft { .. })),
+        0
+    );
+    flush_events(&mut gs);
+
+    // messages are delivered, but not forwarded
+    gs.handle_received_message(received_message(&topic), &peers[0]);
+    assert!(gs
+        .events
+        .iter()
+        .any(|e| matches!(e, ToSwarm::GenerateEvent(Event::Message { .. }))));
+    assert_eq!(count_forwarded_msgs(&gs), 0);
+
+    // subscribing joins the mesh again
+    assert!(gs.subscribe(&Topic::new("topic1")).unwrap());
+    assert!(!gs.is_observing(&topic));
+    assert_eq!(gs.mesh_peers(&topic).count(), gs.config.mesh_n());
+}
+
+#[test]
+// tests that IWANTs for messages of observed topics are only answered if configured
+fn test_observer_answers_iwant() {
+    for answer in [false, true] {
+        let config = ConfigBuilder::default()
+            .observer_answers_iwant(answer)
+            .build()
+            .unwrap();
+        let (mut gs, peers, _) = inject_nodes1()
+            .peer_no(20)
+            .topics(Vec::new())
+            .to_subscribe(true)
+            .gs_config(config)
+            .create_network();
+        let topic = Topic::new("observed");
+        gs.observe(&topic).unwrap();
+
+        let raw_message = received_message(&topic.hash());
+        let message = gs
+            .data_transform
+            .inbound_transform(raw_message.clone())
+            .unwrap();
+        let msg_id = gs.config.message_id(&message);
+        gs.mcache.put(&msg_id, raw_message);
+        flush_events(&mut gs);
+
+        gs.handle_iwant(&peers[7], vec![msg_id]);
+        assert_eq!(count_forwarded_msgs(&gs), answer as usize);
+    }
+}
+
+fn count_forwarded_msgs<D: DataTransform, F: TopicSubscriptionFilter>(
+    gs: &Behaviour<D, F>,
+) -> usize {
+    gs.events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e,
+                ToSwarm::NotifyHandler {
+                    event: HandlerIn::Message(RpcOut::Forward(_)),
+                    ..
+                }
+            )
+        })
+        .count()
+}
+
+fn received_message(topic: &TopicHash) -> RawMessage {
+    RawMessage {
+        source: Some(PeerId::random()),
+        data: vec![42; 100],
+        sequence_number: Some(0),
+        topic: topic.clone(),
+        signature: None,
+        key: None,
+        validated: true,
+    }
+}
+
+#[test]
+fn test_topic_bandwidth_accounting() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+    let topic = topic_hashes[0].clone();
+    assert_eq!(gs.topic_bandwidth(&topic), Some(TopicBandwidth::default()));
+
+    gs.publish(Topic::new("topic1"), vec![0; 42]).unwrap();
+    let published = gs
+        .events
+        .iter()
+        .map(|e| match e {
+            ToSwarm::NotifyHandler {
+                event: HandlerIn::Message(RpcOut::Publish(m)),
+                ..
+            } => m.raw_protobuf_len() as u64,
+            _ => 0,
+        })
+        .sum::<u64>();
+    assert!(published > 0);
+    flush_events(&mut gs);
+
+    let message = received_message(&topic);
+    let bytes = message.raw_protobuf_len() as u64;
+    gs.handle_received_message(message, &peers[0]);
+    let forwarded = count_forwarded_msgs(&gs) as u64;
+    assert!(forwarded > 0);
+
+    assert_eq!(
+        gs.topic_bandwidth(&topic),
+        Some(TopicBandwidth {
+            published,
+            forwarded: forwarded * bytes,
+            received: bytes,
+            throttled: 0,
+        })
+    );
+
+    // Messages on topics we don't subscribe to aren't tracked.
+    let other = Topic::new("topic2").hash();
+    gs.handle_received_message(received_message(&other), &peers[0]);
+    assert_eq!(gs.topic_bandwidth(&other), None);
+}
+
+#[test]
+fn test_topic_bandwidth_quota_drops_messages() {
+    let topic = Topic::new("topic1").hash();
+    let bytes = received_message(&topic).raw_protobuf_len() as u64;
+    // Allow forwarding the message to a single peer.
+    let config = ConfigBuilder::default()
+        .topic_bandwidth_quota(
+            topic.clone(),
+            BandwidthQuota::new(bytes, Duration::from_secs(3600)),
+        )
+        .build()
+        .unwrap();
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let recipients = gs.mesh_peers(&topic).filter(|p| *p != &peers[0]).count() as u64;
+    assert!(recipients > 1);
+
+    gs.handle_received_message(received_message(&topic), &peers[0]);
+    assert_eq!(count_forwarded_msgs(&gs), 1);
+
+    let bandwidth = gs.topic_bandwidth(&topic).unwrap();
+    assert_eq!(bandwidth.forwarded, bytes);
+    assert_eq!(bandwidth.throttled, (recipients - 1) * bytes);
+
+    // Dropped messages aren't sent later on.
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(count_forwarded_msgs(&gs), 0);
+}
+
+#[test]
+fn test_topic_bandwidth_quota_delays_messages() {
+    let topic = Topic::new("topic1").hash();
+    let bytes = received_message(&topic).raw_protobuf_len() as u64;
+    // Allow forwarding the message to a single peer per interval, delaying one more.
+    let config = ConfigBuilder::default()
+        .topic_bandwidth_quota(
+            topic.clone(),
+            BandwidthQuota::new(bytes, Duration::from_millis(10)).with_action(QuotaAction::Delay),
+        )
+        .build()
+        .unwrap();
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let recipients = gs.mesh_peers(&topic).filter(|p| *p != &peers[0]).count() as u64;
+    assert!(recipients > 2);
+
+    gs.handle_received_message(received_message(&topic), &peers[0]);
+    assert_eq!(count_forwarded_msgs(&gs), 1);
+    assert_eq!(
+        gs.topic_bandwidth(&topic).unwrap().throttled,
+        (recipients - 1) * bytes
+    );
+
+    // The delayed message is sent once the next interval starts.
+    flush_events(&mut gs);
+    gs.heartbeat();
+    assert_eq!(count_forwarded_msgs(&gs), 0);
+
+    sleep(Duration::from_millis(20));
+    gs.heartbeat();
+    assert_eq!(count_forwarded_msgs(&gs), 1);
+    assert_eq!(gs.topic_bandwidth(&topic).unwrap().forwarded, 2 * bytes);
+}
+
+#[test]
+fn test_oversized_control_messages_are_fragmented() {
+    let config = ConfigBuilder::default()
+        .max_transmit_size(200)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    flush_events(&mut gs);
+
+    let message_ids = (0..50u64)
+        .map(|i| MessageId::new(&i.to_be_bytes()))
+        .collect::<Vec<_>>();
+    gs.send_message(
+        peers[0],
+        RpcOut::Control(ControlAction::IHave {
+            topic_hash: topic_hashes[0].clone(),
+            message_ids: message_ids.clone(),
+        }),
+    );
+
+    let mut sent = Vec::new();
+    for event in gs.events.drain(..) {
+        let ToSwarm::NotifyHandler {
+            event: HandlerIn::Message(rpc),
+            ..
+        } = event
+        else {
+            panic!("Unexpected event: {event:?}");
+        };
+        assert!(rpc.clone().into_protobuf().get_size() <= 200);
+        let RpcOut::Control(ControlAction::IHave {
+            topic_hash,
+            message_ids,
+        }) = rpc
+        else {
+            panic!("Unexpected RPC: {rpc:?}");
+        };
+        assert_eq!(topic_hash, topic_hashes[0]);
+        sent.extend(message_ids);
+    }
+    assert_eq!(sent, message_ids);
+}
+
+#[test]
+fn test_oversized_message_is_reported() {
+    let config = ConfigBuilder::default()
+        .max_transmit_size(200)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    flush_events(&mut gs);
+
+    let mut message = received_message(&topic_hashes[0]);
+    message.data = vec![42; 200];
+    let size = message.raw_protobuf_len();
+    gs.send_message(peers[0], RpcOut::Forward(message));
+
+    assert_eq!(count_forwarded_msgs(&gs), 0);
+    assert!(matches!(
+        gs.events.pop_front(),
+        Some(ToSwarm::GenerateEvent(Event::MessageTooLarge { peer_id, topic, size: s }))
+            if peer_id == peers[0] && topic == topic_hashes[0] && s == size
+    ));
+}
+
 fn count_control_msgs<D: DataTransform, F: TopicSubscriptionFilter>(
     gs: &Behaviour<D, F>,
     mut filter: impl FnMut(&PeerId, &ControlAction) -> bool,
@@ -1797,6 +2223,88 @@ fn test_connect_to_px_peers_on_handle_prune() {
     ));
 }
 
+#[test]
+fn test_limit_px_verification_dials() {
+    let config = ConfigBuilder::default()
+        .prune_peers(16)
+        .px_verification(PxVerification::Dial)
+        .max_concurrent_px_dials(NonZeroUsize::new(2).unwrap())
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let px: Vec<_> = (0..5)
+        .map(|_| PeerInfo {
+            peer_id: Some(PeerId::random()),
+        })
+        .collect();
+
+    gs.handle_prune(
+        &peers[0],
+        vec![(
+            topics[0].clone(),
+            px.clone(),
+            Some(config.prune_backoff().as_secs()),
+        )],
+    );
+
+    let dials = |gs: &Behaviour| -> Vec<(PeerId, ConnectionId)> {
+        gs.events
+            .iter()
+            .filter_map(|e| match e {
+                ToSwarm::Dial { opts } => Some((opts.get_peer_id()?, opts.connection_id())),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // Only as many peers as allowed are dialed at once.
+    let first_dials = dials(&gs);
+    assert_eq!(first_dials.len(), 2);
+    assert_eq!(gs.px_unverified.len(), 5);
+    let (failed_peer, failed_dial) = first_dials[0];
+
+    // The failure of a dial we didn't make is ignored.
+    gs.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+        peer_id: Some(failed_peer),
+        error: &DialError::Aborted,
+        connection_id: ConnectionId::new_unchecked(usize::MAX),
+    }));
+    assert_eq!(dials(&gs).len(), 2);
+
+    // A failed dial frees a slot for the next peer.
+    gs.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+        peer_id: Some(failed_peer),
+        error: &DialError::Aborted,
+        connection_id: failed_dial,
+    }));
+    assert_eq!(dials(&gs).len(), 3);
+    assert_eq!(gs.px_unverified.len(), 5);
+
+    // The failed peer stays ineligible for the mesh, even once it connects to us.
+    gs.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: failed_peer,
+        connection_id: ConnectionId::new_unchecked(0),
+        endpoint: &ConnectedPoint::Listener {
+            local_addr: Multiaddr::empty(),
+            send_back_addr: Multiaddr::empty(),
+        },
+        failed_addresses: &[],
+        other_established: 0,
+        negotiated_protocols: &Default::default(),
+    }));
+    assert!(matches!(
+        gs.px_unverified.get(&failed_peer),
+        Some(PxState::Failed(_))
+    ));
+}
+
 #[test]
 fn test_send_px_and_backoff_in_prune() {
     let config: Config = Config::default();
@@ -4054,6 +4562,7 @@ fn test_scoring_p6() {
             },
             failed_addresses: &[],
             other_established: 0,
+            negotiated_protocols: &Default::default(),
         }));
     }
 
@@ -4075,6 +4584,7 @@ fn test_scoring_p6() {
             },
             failed_addresses: &[],
             other_established: 1,
+            negotiated_protocols: &Default::default(),
         }));
     }
 
@@ -4105,6 +4615,7 @@ fn test_scoring_p6() {
         },
         failed_addresses: &[],
         other_established: 2,
+        negotiated_protocols: &Default::default(),
     }));
 
     //nothing changed
@@ -4514,18 +5025,226 @@ fn test_ignore_too_many_messages_in_ihave() {
 }
 
 #[test]
-fn test_limit_number_of_message_ids_inside_ihave() {
+fn test_ignore_too_many_message_ids_in_ihave() {
     let config = ConfigBuilder::default()
-        .max_ihave_messages(10)
-        .max_ihave_length(100)
+        .max_ihave_message_ids(15)
         .build()
         .unwrap();
-    //build gossipsub with full mesh
-    let (mut gs, peers, topics) = inject_nodes1()
+    let (mut gs, _, topics) = inject_nodes1()
         .peer_no(config.mesh_n_high())
         .topics(vec!["test".into()])
         .to_subscribe(false)
-        .gs_config(config)
+        .gs_config(config.clone())
+        .create_network();
+
+    let peer = add_peer(&mut gs, &topics, false, false);
+
+    let mut seq = 0;
+    let message_ids: Vec<_> = (0..20)
+        .map(|_| random_message(&mut seq, &topics))
+        .map(|msg| gs.data_transform.inbound_transform(msg).unwrap())
+        .map(|msg| config.message_id(&msg))
+        .collect();
+
+    gs.handle_ihave(
+        &peer,
+        vec![(topics[0].clone(), message_ids[0..10].to_vec())],
+    );
+    gs.handle_ihave(
+        &peer,
+        vec![(topics[0].clone(), message_ids[10..20].to_vec())],
+    );
+
+    let mut requested = HashSet::new();
+    count_control_msgs(&gs, |p, action| match action {
+        ControlAction::IWant { message_ids } if p == &peer => {
+            requested.extend(message_ids.iter().cloned());
+            true
+        }
+        _ => false,
+    });
+    assert_eq!(
+        requested,
+        message_ids[0..15].iter().cloned().collect(),
+        "only the first 15 advertised message ids should be requested"
+    );
+    assert_eq!(
+        gs.gossip_control_stats(&peer)
+            .unwrap()
+            .ihave_message_ids_ignored,
+        5
+    );
+}
+
+#[test]
+fn test_limit_iwant_responses() {
+    let config = ConfigBuilder::default()
+        .max_iwant_responses(2)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let mut seq = 0;
+    let message_ids: Vec<_> = (0..3)
+        .map(|_| {
+            let raw_message = random_message(&mut seq, &topics);
+            let message = gs
+                .data_transform
+                .inbound_transform(raw_message.clone())
+                .unwrap();
+            let msg_id = config.message_id(&message);
+            gs.mcache.put(&msg_id, raw_message);
+            msg_id
+        })
+        .collect();
+
+    gs.handle_iwant(&peers[0], message_ids.clone());
+
+    let forwarded = gs
+        .events
+        .iter()
+        .filter(|e| {
+            matches!(
+                e,
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerIn::Message(RpcOut::Forward(_)),
+                    ..
+                } if peer_id == &peers[0]
+            )
+        })
+        .count();
+    assert_eq!(forwarded, 2, "only two messages should be sent");
+    assert_eq!(
+        gs.gossip_control_stats(&peers[0])
+            .unwrap()
+            .iwant_responses_dropped,
+        1
+    );
+
+    // The budget is reset on the next heartbeat.
+    flush_events(&mut gs);
+    gs.heartbeat();
+    gs.handle_iwant(&peers[0], message_ids[2..].to_vec());
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::NotifyHandler {
+            event: HandlerIn::Message(RpcOut::Forward(_)),
+            ..
+        }
+    )));
+}
+
+#[test]
+fn test_ignore_ihave_of_peer_breaking_promises() {
+    let config = ConfigBuilder::default()
+        .max_broken_promises(Some(2))
+        .iwant_followup_time(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let (mut gs, _, topics) = inject_nodes1()
+        .peer_no(config.mesh_n_high())
+        .topics(vec!["test".into()])
+        .to_subscribe(false)
+        .gs_config(config.clone())
+        .create_network();
+
+    let peer = add_peer(&mut gs, &topics, false, false);
+
+    let mut seq = 0;
+    let message_ids: Vec<_> = (0..3)
+        .map(|_| random_message(&mut seq, &topics))
+        .map(|msg| gs.data_transform.inbound_transform(msg).unwrap())
+        .map(|msg| config.message_id(&msg))
+        .collect();
+
+    // The peer advertises two messages, which we request but never receive.
+    gs.handle_ihave(&peer, vec![(topics[0].clone(), message_ids[0..2].to_vec())]);
+    sleep(Duration::from_millis(20));
+    gs.heartbeat();
+    flush_events(&mut gs);
+    assert_eq!(gs.gossip_control_stats(&peer).unwrap().broken_promises, 2);
+
+    // Further advertisements of the peer are ignored.
+    gs.handle_ihave(&peer, vec![(topics[0].clone(), message_ids[2..].to_vec())]);
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peer
+            && matches!(action, ControlAction::IWant { .. })),
+        0,
+        "no iwant should be sent to a peer breaking its promises"
+    );
+    assert_eq!(
+        gs.gossip_control_stats(&peer)
+            .unwrap()
+            .ihave_messages_ignored,
+        1
+    );
+}
+
+#[test]
+fn test_ihave_of_peer_breaking_promises_is_not_ignored_by_default() {
+    let config = ConfigBuilder::default()
+        .iwant_followup_time(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let (mut gs, _, topics) = inject_nodes1()
+        .peer_no(config.mesh_n_high())
+        .topics(vec!["test".into()])
+        .to_subscribe(false)
+        .gs_config(config.clone())
+        .create_network();
+
+    let peer = add_peer(&mut gs, &topics, false, false);
+
+    let mut seq = 0;
+    let message_ids: Vec<_> = (0..21)
+        .map(|_| random_message(&mut seq, &topics))
+        .map(|msg| gs.data_transform.inbound_transform(msg).unwrap())
+        .map(|msg| config.message_id(&msg))
+        .collect();
+
+    gs.handle_ihave(
+        &peer,
+        vec![(topics[0].clone(), message_ids[0..20].to_vec())],
+    );
+    sleep(Duration::from_millis(20));
+    gs.heartbeat();
+    flush_events(&mut gs);
+    assert_eq!(gs.gossip_control_stats(&peer).unwrap().broken_promises, 20);
+
+    // The broken promises are tracked, but do not silence the peer.
+    gs.handle_ihave(&peer, vec![(topics[0].clone(), message_ids[20..].to_vec())]);
+    assert_eq!(
+        count_control_msgs(&gs, |p, action| p == &peer
+            && matches!(action, ControlAction::IWant { .. })),
+        1
+    );
+    assert_eq!(
+        gs.gossip_control_stats(&peer)
+            .unwrap()
+            .ihave_messages_ignored,
+        0
+    );
+}
+
+#[test]
+fn test_limit_number_of_message_ids_inside_ihave() {
+    let config = ConfigBuilder::default()
+        .max_ihave_messages(10)
+        .max_ihave_length(100)
+        .build()
+        .unwrap();
+    //build gossipsub with full mesh
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(config.mesh_n_high())
+        .topics(vec!["test".into()])
+        .to_subscribe(false)
+        .gs_config(config)
         .create_network();
 
     //graft to all peers to really fill the mesh with all the peers
@@ -5108,3 +5827,661 @@ fn test_graft_without_subscribe() {
     // We unsubscribe from the topic.
     let _ = gs.unsubscribe(&Topic::new(topic));
 }
+
+#[test]
+fn test_drop_messages_outside_replay_window() {
+    // The first 8 bytes of each message carry its creation time in seconds since the epoch.
+    let config = ConfigBuilder::default()
+        .message_timestamp_fn(|message: &Message| {
+            let secs = BigEndian::read_u64(message.data.get(..8)?);
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        })
+        .message_replay_window(Duration::from_secs(60))
+        .max_clock_skew(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["topic".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut seq = 0;
+    let mut message_at = |secs: u64| {
+        seq += 1;
+        let mut data = vec![0; 8];
+        BigEndian::write_u64(&mut data, secs);
+        RawMessage {
+            source: Some(PeerId::random()),
+            data,
+            sequence_number: Some(seq),
+            topic: topic_hashes[0].clone(),
+            signature: None,
+            key: None,
+            validated: true,
+        }
+    };
+
+    let fresh = message_at(now - 30);
+    let replayed = message_at(now - 300);
+    let from_future = message_at(now + 60);
+    let skewed = message_at(now + 2);
+    let untimestamped = RawMessage {
+        data: vec![1],
+        ..message_at(now)
+    };
+
+    for message in [fresh, replayed, from_future, skewed, untimestamped] {
+        gs.handle_received_message(message, &peers[0]);
+    }
+
+    let received = gs
+        .events
+        .iter()
+        .filter(|e| matches!(e, ToSwarm::GenerateEvent(Event::Message { .. })))
+        .count();
+    assert_eq!(
+        received, 3,
+        "Only messages within the replay window should be delivered"
+    );
+}
+
+#[test]
+fn test_set_heartbeat_interval() {
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+
+    assert!(gs.set_heartbeat_interval(Duration::ZERO).is_err());
+    assert_eq!(gs.config.heartbeat_interval(), Duration::from_secs(1));
+
+    gs.set_heartbeat_interval(Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(gs.config.heartbeat_interval(), Duration::from_millis(100));
+}
+
+#[test]
+fn test_do_not_forward_message_to_peers_that_sent_it() {
+    let config = ConfigBuilder::default()
+        .validate_messages()
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(3)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let message = RawMessage {
+        source: Some(PeerId::random()),
+        data: vec![12],
+        sequence_number: Some(0),
+        topic: topic_hashes[0].clone(),
+        signature: None,
+        key: None,
+        validated: true,
+    };
+    let msg_id = config.message_id(
+        &gs.data_transform
+            .inbound_transform(message.clone())
+            .unwrap(),
+    );
+
+    // The second peer sends us the message while it is still being validated.
+    gs.handle_received_message(message.clone(), &peers[0]);
+    gs.handle_received_message(message.clone(), &peers[1]);
+    gs.report_message_validation_result(&msg_id, &peers[0], MessageAcceptance::Accept)
+        .unwrap();
+
+    let forwarded_to = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Forward(m)),
+                ..
+            } if m.data == message.data => Some(*peer_id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        forwarded_to,
+        vec![peers[2]],
+        "The message should only be forwarded to the peer that doesn't have it"
+    );
+}
+
+#[test]
+fn test_max_subscriptions_rejects_subscription() {
+    assert!(matches!(
+        ConfigBuilder::default()
+            .max_subscriptions(0, SubscriptionLimitAction::Reject)
+            .build(),
+        Err(ConfigBuilderError::MaxSubscriptionsIsZero)
+    ));
+
+    let config = ConfigBuilder::default()
+        .max_subscriptions(2, SubscriptionLimitAction::Reject)
+        .build()
+        .unwrap();
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec!["t1".into(), "t2".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    assert!(matches!(
+        gs.subscribe(&Topic::new("t3")),
+        Err(SubscriptionError::TooManySubscriptions)
+    ));
+    assert!(!gs.subscribe(&Topic::new("t1")).unwrap());
+
+    assert!(gs.unsubscribe(&Topic::new("t2")).unwrap());
+    assert!(gs.subscribe(&Topic::new("t3")).unwrap());
+}
+
+#[test]
+fn test_max_subscriptions_unsubscribes_least_recently_used() {
+    let config = ConfigBuilder::default()
+        .max_subscriptions(2, SubscriptionLimitAction::UnsubscribeLeastRecentlyUsed)
+        .build()
+        .unwrap();
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec!["t1".into(), "t2".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    gs.events.clear();
+
+    // Using t1 leaves t2 as the least recently used topic.
+    assert!(!gs.subscribe(&Topic::new("t1")).unwrap());
+    assert!(gs.subscribe(&Topic::new("t3")).unwrap());
+
+    assert!(gs.mesh.contains_key(&topic_hashes[0]));
+    assert!(!gs.mesh.contains_key(&topic_hashes[1]));
+    assert!(gs.mesh.contains_key(&Topic::new("t3").hash()));
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SubscriptionEvicted { topic }) if topic == &topic_hashes[1]
+    )));
+}
+
+#[test]
+fn test_mesh_stats_count_heartbeat_maintenance() {
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(20)
+        .topics(vec!["t1".into()])
+        .to_subscribe(true)
+        .create_network();
+    let topic = &topic_hashes[0];
+
+    // Emptying the mesh makes the heartbeat graft mesh_n peers.
+    gs.mesh.get_mut(topic).unwrap().clear();
+    gs.heartbeat();
+
+    let stats = gs.mesh_stats(topic).unwrap();
+    assert_eq!(stats.heartbeat_grafts_sent, gs.config.mesh_n() as u64);
+    assert_eq!(stats.heartbeat_prunes_sent, 0);
+    assert!(stats.heartbeat_time > Duration::ZERO);
+}
+
+#[test]
+fn test_heartbeat_and_fanout_expiry_follow_clock() {
+    let clock = libp2p_swarm::VirtualClock::new();
+    let config = ConfigBuilder::default()
+        .flood_publish(false)
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(20)
+        .topics(vec!["fanout".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let topic = Topic::new("fanout");
+    assert!(gs.unsubscribe(&topic).unwrap());
+    gs.publish(topic.clone(), vec![0; 42]).unwrap();
+    assert!(gs.fanout.contains_key(&topic.hash()));
+    gs.events.clear();
+
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    let heartbeat_initial_delay = gs.config.heartbeat_initial_delay();
+    clock.advance(heartbeat_initial_delay - Duration::from_millis(1));
+    while gs.poll(&mut cx).is_ready() {}
+    assert_eq!(gs.heartbeat_ticks, 0);
+
+    clock.advance(Duration::from_millis(1));
+    while gs.poll(&mut cx).is_ready() {}
+    assert_eq!(gs.heartbeat_ticks, 1);
+    assert!(gs.fanout.contains_key(&topic.hash()));
+
+    // Fanout peers are dropped by the first heartbeat after the fanout TTL.
+    clock.advance(gs.config.fanout_ttl());
+    while gs.poll(&mut cx).is_ready() {}
+    assert_eq!(gs.heartbeat_ticks, 2);
+    assert!(!gs.fanout.contains_key(&topic.hash()));
+}
+
+#[test]
+fn test_slow_peer_is_pruned_and_penalized() {
+    let config = ConfigBuilder::default()
+        .slow_peer_detection(SlowPeerDetection::new(16, Duration::from_secs(1)).with_penalty(2))
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec!["t1".into(), "t2".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .scoring(Some((
+            PeerScoreParams::default(),
+            PeerScoreThresholds::default(),
+        )))
+        .create_network();
+    let slow_peer = peers[0];
+    for topic in &topic_hashes {
+        assert!(gs.mesh[topic].contains(&slow_peer));
+    }
+    flush_events(&mut gs);
+
+    gs.on_connection_handler_event(
+        slow_peer,
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::SlowPeer {
+            queue_len: 20,
+            full_for: Duration::from_secs(1),
+        },
+    );
+
+    for topic in &topic_hashes {
+        assert!(!gs.mesh[topic].contains(&slow_peer));
+        assert!(gs.backoffs.is_backoff_with_slack(topic, &slow_peer));
+    }
+    assert_eq!(
+        count_control_msgs(&gs, |peer_id, m| peer_id == &slow_peer
+            && matches!(m, ControlAction::Prune { .. })),
+        2
+    );
+    assert!(gs.peer_score(&slow_peer).unwrap() < 0.0);
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerIn::LeftMesh,
+            ..
+        } if peer_id == &slow_peer
+    )));
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SlowPeer {
+            peer_id,
+            queue_len: 20,
+            pruned_topics,
+            ..
+        }) if peer_id == &slow_peer && pruned_topics.len() == 2
+    )));
+}
+
+#[test]
+fn test_handler_reports_slow_peer_after_window() {
+    let clock = libp2p_swarm::VirtualClock::new();
+    let mut handler = Handler::new(
+        ProtocolConfig::default(),
+        Some(SlowPeerDetection::new(2, Duration::from_secs(10))),
+        Arc::new(clock.clone()),
+    );
+    let graft = || {
+        HandlerIn::Message(RpcOut::Control(ControlAction::Graft {
+            topic_hash: Topic::new("t1").hash(),
+        }))
+    };
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    let mut slow_peer_reported = |handler: &mut Handler| loop {
+        match handler.poll(&mut cx) {
+            Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(HandlerEvent::SlowPeer {
+                queue_len,
+                full_for,
+            })) => break Some((queue_len, full_for)),
+            Poll::Ready(_) => {}
+            Poll::Pending => break None,
+        }
+    };
+
+    handler.on_behaviour_event(graft());
+    handler.on_behaviour_event(graft());
+    assert_eq!(slow_peer_reported(&mut handler), None);
+
+    clock.advance(Duration::from_secs(9));
+    handler.on_behaviour_event(graft());
+    assert_eq!(slow_peer_reported(&mut handler), None);
+
+    clock.advance(Duration::from_secs(1));
+    handler.on_behaviour_event(graft());
+    assert_eq!(
+        slow_peer_reported(&mut handler),
+        Some((4, Duration::from_secs(10)))
+    );
+}
+
+#[test]
+fn test_peer_kind_changes_are_reported() {
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec!["t1".into()])
+        .to_subscribe(true)
+        .create_network();
+    let peer = add_peer_with_addr_and_kind(&mut gs, &[], false, false, Multiaddr::empty(), None);
+    assert_eq!(gs.peer_kind(&peer), None);
+
+    let peer_kind_changes = |gs: &mut Behaviour| {
+        gs.events
+            .drain(..)
+            .filter_map(|e| match e {
+                ToSwarm::GenerateEvent(Event::PeerKindChanged { peer_id, old, new }) => {
+                    assert_eq!(peer_id, peer);
+                    Some((old, new))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    gs.on_connection_handler_event(
+        peer,
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::PeerKind(PeerKind::Gossipsubv1_1),
+    );
+    assert_eq!(gs.peer_kind(&peer), Some(&PeerKind::Gossipsubv1_1));
+    assert_eq!(
+        peer_kind_changes(&mut gs),
+        vec![(None, PeerKind::Gossipsubv1_1)]
+    );
+
+    // A second connection negotiating the same protocol is no change.
+    gs.on_connection_handler_event(
+        peer,
+        ConnectionId::new_unchecked(1),
+        HandlerEvent::PeerKind(PeerKind::Gossipsubv1_1),
+    );
+    assert_eq!(peer_kind_changes(&mut gs), vec![]);
+
+    // A connection negotiating an older protocol doesn't downgrade the peer.
+    gs.on_connection_handler_event(
+        peer,
+        ConnectionId::new_unchecked(2),
+        HandlerEvent::PeerKind(PeerKind::Gossipsub),
+    );
+    assert_eq!(gs.peer_kind(&peer), Some(&PeerKind::Gossipsubv1_1));
+    assert_eq!(peer_kind_changes(&mut gs), vec![]);
+
+    // Neither does a connection not supporting gossipsub.
+    gs.on_connection_handler_event(
+        peer,
+        ConnectionId::new_unchecked(3),
+        HandlerEvent::PeerKind(PeerKind::NotSupported),
+    );
+    assert_eq!(gs.peer_kind(&peer), Some(&PeerKind::Gossipsubv1_1));
+    assert_eq!(peer_kind_changes(&mut gs), vec![]);
+}
+
+#[test]
+fn test_peer_kind_upgrades_are_reported() {
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec!["t1".into()])
+        .to_subscribe(true)
+        .create_network();
+    let peer = add_peer_with_addr_and_kind(&mut gs, &[], false, false, Multiaddr::empty(), None);
+
+    for (id, kind) in [PeerKind::Gossipsub, PeerKind::Gossipsubv1_1]
+        .into_iter()
+        .enumerate()
+    {
+        gs.on_connection_handler_event(
+            peer,
+            ConnectionId::new_unchecked(id),
+            HandlerEvent::PeerKind(kind),
+        );
+    }
+
+    assert_eq!(gs.peer_kind(&peer), Some(&PeerKind::Gossipsubv1_1));
+    let changes = gs
+        .events
+        .drain(..)
+        .filter_map(|e| match e {
+            ToSwarm::GenerateEvent(Event::PeerKindChanged { old, new, .. }) => Some((old, new)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            (None, PeerKind::Gossipsub),
+            (Some(PeerKind::Gossipsub), PeerKind::Gossipsubv1_1)
+        ]
+    );
+}
+
+fn delivery_receipts(gs: &mut Behaviour) -> Vec<(MessageId, usize, usize)> {
+    gs.events
+        .drain(..)
+        .filter_map(|e| match e {
+            ToSwarm::GenerateEvent(Event::DeliveryReceipts {
+                message_id,
+                recipients,
+                confirmations,
+                ..
+            }) => Some((message_id, recipients, confirmations)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_delivery_receipts_are_sent_for_marked_topics() {
+    let topic = Topic::new("receipts").hash();
+    let config = ConfigBuilder::default()
+        .delivery_receipts(topic.clone(), Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec!["receipts".into(), "other".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    flush_events(&mut gs);
+
+    let acks = |gs: &Behaviour| {
+        gs.events
+            .iter()
+            .filter_map(|e| match e {
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerIn::Message(RpcOut::Control(ControlAction::Ack { message_ids })),
+                    ..
+                } => Some((*peer_id, message_ids.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let message = received_message(&topic);
+    let message_id = gs.config.message_id(
+        &gs.data_transform
+            .inbound_transform(message.clone())
+            .unwrap(),
+    );
+    gs.handle_received_message(message.clone(), &peers[0]);
+    gs.flush_acks();
+    assert_eq!(acks(&gs), vec![(peers[0], vec![message_id.clone()])]);
+    flush_events(&mut gs);
+
+    // Duplicates are confirmed as well.
+    gs.handle_received_message(message, &peers[1]);
+    gs.flush_acks();
+    assert_eq!(acks(&gs), vec![(peers[1], vec![message_id])]);
+    flush_events(&mut gs);
+
+    gs.handle_received_message(received_message(&topic_hashes[1]), &peers[0]);
+    gs.flush_acks();
+    assert_eq!(acks(&gs), vec![]);
+}
+
+#[test]
+fn test_delivery_receipts_are_batched_per_peer() {
+    let topic = Topic::new("receipts").hash();
+    let config = ConfigBuilder::default()
+        .delivery_receipts(topic.clone(), Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["receipts".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    flush_events(&mut gs);
+
+    let messages = (0..3).map(|_| received_message(&topic)).collect::<Vec<_>>();
+    let message_ids = messages
+        .iter()
+        .map(|m| {
+            gs.config
+                .message_id(&gs.data_transform.inbound_transform(m.clone()).unwrap())
+        })
+        .collect::<Vec<_>>();
+    for message in messages.clone() {
+        gs.handle_received_message(message, &peers[0]);
+    }
+    gs.handle_received_message(messages[0].clone(), &peers[1]);
+
+    // The receipts are sent on the next poll, in one ACK control message per peer.
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut acks = Vec::new();
+    while let Poll::Ready(event) = gs.poll(&mut cx) {
+        if let ToSwarm::NotifyHandler {
+            peer_id,
+            event: HandlerIn::Message(RpcOut::Control(ControlAction::Ack { message_ids })),
+            ..
+        } = event
+        {
+            acks.push((peer_id, message_ids));
+        }
+    }
+    acks.sort_by_key(|(peer_id, _)| peers.iter().position(|p| p == peer_id));
+    assert_eq!(
+        acks,
+        vec![
+            (peers[0], message_ids.clone()),
+            (peers[1], vec![message_ids[0].clone()])
+        ]
+    );
+    assert!(gs.pending_acks.is_empty());
+}
+
+#[test]
+fn test_delivery_receipts_reported_once_all_recipients_confirmed() {
+    let topic = Topic::new("receipts");
+    let config = ConfigBuilder::default()
+        .delivery_receipts(topic.hash(), Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(3)
+        .topics(vec!["receipts".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let stranger = add_peer(&mut gs, &[], false, false);
+
+    let message_id = gs.publish(topic, vec![0; 42]).unwrap();
+    flush_events(&mut gs);
+
+    // Receipts of peers the message wasn't sent to and of unknown messages are ignored.
+    gs.handle_ack(&stranger, vec![message_id.clone()]);
+    gs.handle_ack(&peers[0], vec![MessageId::new(b"unknown")]);
+    gs.handle_ack(&peers[0], vec![message_id.clone()]);
+    gs.handle_ack(&peers[0], vec![message_id.clone()]);
+    gs.handle_ack(&peers[1], vec![message_id.clone()]);
+    assert_eq!(delivery_receipts(&mut gs), vec![]);
+
+    gs.handle_ack(&peers[2], vec![message_id.clone()]);
+    assert_eq!(delivery_receipts(&mut gs), vec![(message_id, 3, 3)]);
+    assert!(gs.delivery_receipts.is_empty());
+}
+
+#[test]
+fn test_delivery_receipts_reported_at_end_of_window() {
+    let clock = libp2p_swarm::VirtualClock::new();
+    let topic = Topic::new("receipts");
+    let config = ConfigBuilder::default()
+        .delivery_receipts(topic.hash(), Duration::from_secs(5))
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(3)
+        .topics(vec!["receipts".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let message_id = gs.publish(topic, vec![0; 42]).unwrap();
+    flush_events(&mut gs);
+    gs.on_connection_handler_event(
+        peers[0],
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::Message {
+            rpc: Rpc {
+                messages: vec![],
+                subscriptions: vec![],
+                control_msgs: vec![ControlAction::Ack {
+                    message_ids: vec![message_id.clone()],
+                }],
+            },
+            invalid_messages: vec![],
+        },
+    );
+
+    clock.advance(Duration::from_secs(4));
+    gs.heartbeat();
+    assert_eq!(delivery_receipts(&mut gs), vec![]);
+
+    clock.advance(Duration::from_secs(1));
+    gs.heartbeat();
+    assert_eq!(delivery_receipts(&mut gs), vec![(message_id, 3, 1)]);
+}
+
+#[test]
+fn test_ack_control_message_roundtrip() {
+    let rpc = RpcOut::Control(ControlAction::Ack {
+        message_ids: vec![MessageId::new(b"a"), MessageId::new(b"b")],
+    });
+    let proto = rpc.clone().into_protobuf();
+    let mut bytes = Vec::new();
+    proto.write_message(&mut Writer::new(&mut bytes)).unwrap();
+    assert_eq!(bytes.len(), proto.get_size());
+
+    let decoded = proto_to_message(&proto);
+    assert_eq!(
+        decoded.control_msgs,
+        vec![ControlAction::Ack {
+            message_ids: vec![MessageId::new(b"a"), MessageId::new(b"b")],
+        }]
+    );
+}
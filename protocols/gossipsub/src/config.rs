@@ -19,15 +19,19 @@
 // DEALINGS IN THE SOFTWARE.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::error::ConfigBuilderError;
 use crate::protocol::{ProtocolConfig, ProtocolId, FLOODSUB_PROTOCOL};
+use crate::topic::TopicHash;
 use crate::types::{Message, MessageId, PeerKind};
 
+use instant::SystemTime;
 use libp2p_identity::PeerId;
-use libp2p_swarm::StreamProtocol;
+use libp2p_swarm::{Clock, StreamProtocol, SystemClock};
 
 /// The types of message validation that can be employed by gossipsub.
 #[derive(Debug, Clone)]
@@ -52,6 +56,138 @@ pub enum ValidationMode {
     None,
 }
 
+/// What to do with messages to be forwarded on a topic once its [`BandwidthQuota`] is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaAction {
+    /// Don't forward the message. Peers may still request it via IWANT.
+    Drop,
+    /// Forward the message once the next quota interval starts.
+    ///
+    /// Delayed messages are released on the heartbeat. At most the quota's worth of bytes is
+    /// delayed, further messages are dropped.
+    Delay,
+}
+
+/// What to do when subscribing to a topic while already subscribed to
+/// [`Config::max_subscriptions`] topics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionLimitAction {
+    /// Fail the subscription with
+    /// [`SubscriptionError::TooManySubscriptions`](crate::SubscriptionError::TooManySubscriptions).
+    Reject,
+    /// Unsubscribe from the least recently used topic, reported as
+    /// [`Event::SubscriptionEvicted`](crate::Event::SubscriptionEvicted).
+    ///
+    /// A topic is used when subscribing to it, publishing on it or receiving a message on it.
+    UnsubscribeLeastRecentlyUsed,
+}
+
+/// A limit on the bytes forwarded on a topic within an interval,
+/// see [`ConfigBuilder::topic_bandwidth_quota`].
+///
+/// The bytes of a forwarded message count once per peer it is sent to. Messages we publish
+/// ourselves and responses to IWANT requests are not subject to the quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthQuota {
+    max_bytes: u64,
+    interval: Duration,
+    action: QuotaAction,
+}
+
+impl BandwidthQuota {
+    /// Allows forwarding `max_bytes` per `interval`, dropping messages exceeding the quota.
+    pub fn new(max_bytes: u64, interval: Duration) -> Self {
+        Self {
+            max_bytes,
+            interval,
+            action: QuotaAction::Drop,
+        }
+    }
+
+    /// Sets what to do with messages exceeding the quota. The default is [`QuotaAction::Drop`].
+    pub fn with_action(mut self, action: QuotaAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// The bytes that may be forwarded per interval.
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// The interval the quota applies to.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// What to do with messages exceeding the quota.
+    pub fn action(&self) -> QuotaAction {
+        self.action
+    }
+}
+
+/// Detection of peers whose outbound queue stays full, see [`ConfigBuilder::slow_peer_detection`].
+///
+/// A slow peer is pruned from all meshes, its score is penalized and
+/// [`Event::SlowPeer`](crate::Event::SlowPeer) is emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowPeerDetection {
+    max_queue_len: usize,
+    window: Duration,
+    penalty: usize,
+}
+
+impl SlowPeerDetection {
+    /// Considers a peer slow once at least `max_queue_len` RPCs have been queued for it
+    /// throughout `window`.
+    pub fn new(max_queue_len: usize, window: Duration) -> Self {
+        Self {
+            max_queue_len,
+            window,
+            penalty: 1,
+        }
+    }
+
+    /// Sets the behaviour penalty added to the score of a slow peer, see
+    /// [`PeerScoreParams::behaviour_penalty_weight`](crate::PeerScoreParams::behaviour_penalty_weight).
+    /// The default is 1.
+    pub fn with_penalty(mut self, penalty: usize) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    /// The number of queued RPCs from which on the queue of a peer is considered full.
+    pub fn max_queue_len(&self) -> usize {
+        self.max_queue_len
+    }
+
+    /// How long the queue of a peer has to stay full for the peer to be considered slow.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// The behaviour penalty added to the score of a slow peer.
+    pub fn penalty(&self) -> usize {
+        self.penalty
+    }
+}
+
+/// How peers learned via peer exchange are verified before they become eligible for the mesh,
+/// see [`ConfigBuilder::px_verification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PxVerification {
+    /// Peers are eligible as soon as they are connected, including via an inbound connection.
+    #[default]
+    None,
+    /// Peers are only eligible once we successfully dialed them, i.e. once they proved to be
+    /// reachable.
+    Dial,
+    /// Like [`PxVerification::Dial`], but additionally waits for an address of the peer to be
+    /// reported via [`FromSwarm::NewExternalAddrOfPeer`](libp2p_swarm::FromSwarm) after the
+    /// dial succeeded, e.g. by `libp2p-identify` once it exchanged identify information.
+    Identify,
+}
+
 /// Selector for custom Protocol Id
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Version {
@@ -78,9 +214,16 @@ pub struct Config {
     duplicate_cache_time: Duration,
     validate_messages: bool,
     message_id_fn: Arc<dyn Fn(&Message) -> MessageId + Send + Sync + 'static>,
+    message_timestamp_fn:
+        Option<Arc<dyn Fn(&Message) -> Option<SystemTime> + Send + Sync + 'static>>,
+    message_replay_window: Duration,
+    max_clock_skew: Duration,
     allow_self_origin: bool,
     do_px: bool,
     prune_peers: usize,
+    px_verification: PxVerification,
+    max_concurrent_px_dials: NonZeroUsize,
+    px_dial_failure_backoff: Duration,
     prune_backoff: Duration,
     unsubscribe_backoff: Duration,
     backoff_slack: u32,
@@ -93,8 +236,18 @@ pub struct Config {
     max_messages_per_rpc: Option<usize>,
     max_ihave_length: usize,
     max_ihave_messages: usize,
+    max_ihave_message_ids: usize,
+    max_iwant_responses: usize,
+    max_broken_promises: Option<usize>,
     iwant_followup_time: Duration,
     published_message_ids_cache_time: Duration,
+    topic_bandwidth_quotas: HashMap<TopicHash, BandwidthQuota>,
+    delivery_receipt_windows: HashMap<TopicHash, Duration>,
+    max_subscriptions: Option<usize>,
+    subscription_limit_action: SubscriptionLimitAction,
+    observer_answers_iwant: bool,
+    slow_peer_detection: Option<SlowPeerDetection>,
+    clock: Arc<dyn Clock>,
 }
 
 impl Config {
@@ -161,6 +314,10 @@ impl Config {
         self.heartbeat_interval
     }
 
+    pub(crate) fn set_heartbeat_interval(&mut self, heartbeat_interval: Duration) {
+        self.heartbeat_interval = heartbeat_interval;
+    }
+
     /// Time to live for fanout peers (default is 60 seconds).
     pub fn fanout_ttl(&self) -> Duration {
         self.fanout_ttl
@@ -217,6 +374,26 @@ impl Config {
         (self.message_id_fn)(message)
     }
 
+    /// The timestamp the application embedded into the given message, if a
+    /// [`ConfigBuilder::message_timestamp_fn`] is set and the message carries one.
+    pub fn message_timestamp(&self, message: &Message) -> Option<SystemTime> {
+        self.message_timestamp_fn.as_ref().and_then(|f| f(message))
+    }
+
+    /// Received messages with an embedded timestamp older than this window are dropped before
+    /// being validated or forwarded. Only applies if a [`ConfigBuilder::message_timestamp_fn`]
+    /// is set. The default is 2 minutes.
+    pub fn message_replay_window(&self) -> Duration {
+        self.message_replay_window
+    }
+
+    /// The tolerated difference between our clock and the clock of the message author when
+    /// checking message timestamps against the [`Config::message_replay_window`]. Messages
+    /// timestamped further in the future than this are dropped as well. The default is 5 seconds.
+    pub fn max_clock_skew(&self) -> Duration {
+        self.max_clock_skew
+    }
+
     /// By default, gossipsub will reject messages that are sent to us that have the same message
     /// source as we have specified locally. Enabling this, allows these messages and prevents
     /// penalizing the peer that sent us the message. Default is false.
@@ -243,6 +420,24 @@ impl Config {
         self.prune_peers
     }
 
+    /// How peers learned via peer exchange are verified before they become eligible for the
+    /// mesh. The default is [`PxVerification::None`].
+    pub fn px_verification(&self) -> PxVerification {
+        self.px_verification
+    }
+
+    /// The maximum number of concurrent dials verifying peers learned via peer exchange, see
+    /// [`Config::px_verification`]. The default is 8.
+    pub fn max_concurrent_px_dials(&self) -> NonZeroUsize {
+        self.max_concurrent_px_dials
+    }
+
+    /// How long a peer learned via peer exchange stays ineligible for the mesh after dialing it
+    /// failed, see [`Config::px_verification`]. The default is 60 seconds.
+    pub fn px_dial_failure_backoff(&self) -> Duration {
+        self.px_dial_failure_backoff
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of `prune_backoff` so they know
@@ -334,6 +529,27 @@ impl Config {
         self.max_ihave_messages
     }
 
+    /// The maximum number of message ids advertised via IHAVE messages to process from a peer
+    /// within a heartbeat. Further message ids are ignored. The default is 50000.
+    pub fn max_ihave_message_ids(&self) -> usize {
+        self.max_ihave_message_ids
+    }
+
+    /// The maximum number of messages to send to a peer in response to its IWANT messages within a
+    /// heartbeat. Further requested messages are not sent. The default is 5000.
+    pub fn max_iwant_responses(&self) -> usize {
+        self.max_iwant_responses
+    }
+
+    /// The number of broken promises, i.e. messages a peer advertised via IHAVE but did not deliver
+    /// within [`Config::iwant_followup_time`] after we requested them, after which we ignore the
+    /// IHAVE messages of the peer. The count of each peer decreases by one every heartbeat.
+    /// This applies regardless of peer scoring. If this is unset, there is no limit. The default
+    /// is None.
+    pub fn max_broken_promises(&self) -> Option<usize> {
+        self.max_broken_promises
+    }
+
     /// Time to wait for a message requested through IWANT following an IHAVE advertisement.
     /// If the message is not received within this window, a broken promise is declared and
     /// the router may apply behavioural penalties. The default is 3 seconds.
@@ -350,6 +566,53 @@ impl Config {
     pub fn published_message_ids_cache_time(&self) -> Duration {
         self.published_message_ids_cache_time
     }
+
+    /// The bandwidth quota for forwarding messages on the given topic, if any.
+    /// By default, no topic has a quota.
+    pub fn topic_bandwidth_quota(&self, topic: &TopicHash) -> Option<&BandwidthQuota> {
+        self.topic_bandwidth_quotas.get(topic)
+    }
+
+    /// The window to collect delivery receipts in for the messages we publish on the given topic,
+    /// if the topic has delivery receipts, see [`ConfigBuilder::delivery_receipts`].
+    /// By default, no topic has delivery receipts.
+    pub fn delivery_receipt_window(&self, topic: &TopicHash) -> Option<Duration> {
+        self.delivery_receipt_windows.get(topic).copied()
+    }
+
+    /// The maximum number of topics the local node can be subscribed to at once.
+    /// By default, there is no limit.
+    ///
+    /// Every subscribed topic has a mesh to be maintained on each heartbeat, thus the number of
+    /// subscriptions bounds the cost of a heartbeat.
+    pub fn max_subscriptions(&self) -> Option<usize> {
+        self.max_subscriptions
+    }
+
+    /// What to do when subscribing to a topic beyond [`Config::max_subscriptions`].
+    /// The default is [`SubscriptionLimitAction::Reject`].
+    pub fn subscription_limit_action(&self) -> SubscriptionLimitAction {
+        self.subscription_limit_action
+    }
+
+    /// Whether to answer IWANTs for messages of the topics we observe, see
+    /// [`crate::Behaviour::observe`]. If set, IHAVEs are emitted for these messages as well.
+    /// The default is false.
+    pub fn observer_answers_iwant(&self) -> bool {
+        self.observer_answers_iwant
+    }
+
+    /// The detection of slow peers, see [`ConfigBuilder::slow_peer_detection`]. The default is
+    /// `None`, i.e. slow peers are not detected.
+    pub fn slow_peer_detection(&self) -> Option<SlowPeerDetection> {
+        self.slow_peer_detection
+    }
+
+    /// The [`Clock`] driving the heartbeat, the peer score decay and the expiry of fanout peers
+    /// and backoffs. The default is the [`SystemClock`].
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
 }
 
 impl Default for Config {
@@ -400,9 +663,15 @@ impl Default for ConfigBuilder {
                         .push_str(&message.sequence_number.unwrap_or_default().to_string());
                     MessageId::from(source_string)
                 }),
+                message_timestamp_fn: None,
+                message_replay_window: Duration::from_secs(120),
+                max_clock_skew: Duration::from_secs(5),
                 allow_self_origin: false,
                 do_px: false,
                 prune_peers: 0, // NOTE: Increasing this currently has little effect until Signed records are implemented.
+                px_verification: PxVerification::None,
+                max_concurrent_px_dials: NonZeroUsize::new(8).expect("8 > 0"),
+                px_dial_failure_backoff: Duration::from_secs(60),
                 prune_backoff: Duration::from_secs(60),
                 unsubscribe_backoff: Duration::from_secs(10),
                 backoff_slack: 1,
@@ -415,8 +684,18 @@ impl Default for ConfigBuilder {
                 max_messages_per_rpc: None,
                 max_ihave_length: 5000,
                 max_ihave_messages: 10,
+                max_ihave_message_ids: 50000,
+                max_iwant_responses: 5000,
+                max_broken_promises: None,
                 iwant_followup_time: Duration::from_secs(3),
                 published_message_ids_cache_time: Duration::from_secs(10),
+                topic_bandwidth_quotas: HashMap::new(),
+                delivery_receipt_windows: HashMap::new(),
+                max_subscriptions: None,
+                subscription_limit_action: SubscriptionLimitAction::Reject,
+                observer_answers_iwant: false,
+                slow_peer_detection: None,
+                clock: Arc::new(SystemClock),
             },
             invalid_protocol: false,
         }
@@ -618,6 +897,36 @@ impl ConfigBuilder {
         self
     }
 
+    /// A user-defined function extracting the creation time the application embedded into a
+    /// message, e.g. in a header of [`Message::data`]. Returning `None` exempts the message
+    /// from the replay check.
+    ///
+    /// When set, received messages older than [`Self::message_replay_window`] (plus
+    /// [`Self::max_clock_skew`]) or timestamped in the future by more than
+    /// [`Self::max_clock_skew`] are dropped before being validated, delivered or forwarded. This
+    /// bounds the effect of replaying old messages once they have left the duplicate cache.
+    pub fn message_timestamp_fn<F>(&mut self, timestamp_fn: F) -> &mut Self
+    where
+        F: Fn(&Message) -> Option<SystemTime> + Send + Sync + 'static,
+    {
+        self.config.message_timestamp_fn = Some(Arc::new(timestamp_fn));
+        self
+    }
+
+    /// Maximum age of a received message, as reported by [`Self::message_timestamp_fn`]. The
+    /// default is 2 minutes.
+    pub fn message_replay_window(&mut self, message_replay_window: Duration) -> &mut Self {
+        self.config.message_replay_window = message_replay_window;
+        self
+    }
+
+    /// Tolerated clock difference to the message author when checking message timestamps. The
+    /// default is 5 seconds.
+    pub fn max_clock_skew(&mut self, max_clock_skew: Duration) -> &mut Self {
+        self.config.max_clock_skew = max_clock_skew;
+        self
+    }
+
     /// Enables Peer eXchange. This should be enabled in bootstrappers and other well
     /// connected/trusted nodes. The default is false.
     ///
@@ -639,6 +948,36 @@ impl ConfigBuilder {
         self
     }
 
+    /// Controls how peers learned via peer exchange are verified before they become eligible
+    /// for the mesh.
+    ///
+    /// Grafting peers of which we only know the [`PeerId`] from a PRUNE of another peer right
+    /// away is risky, as the pruning peer may advertise peers under its control. With
+    /// verification enabled, such peers are dialed and only considered for the mesh once the
+    /// dial succeeded (and possibly identify information was exchanged), with at most
+    /// [`Self::max_concurrent_px_dials`] verification dials at a time. Peers which are already
+    /// connected are not verified. The default is [`PxVerification::None`].
+    pub fn px_verification(&mut self, verification: PxVerification) -> &mut Self {
+        self.config.px_verification = verification;
+        self
+    }
+
+    /// The maximum number of concurrent dials verifying peers learned via peer exchange, see
+    /// [`Self::px_verification`]. Further peers are queued until a dial finished. The default
+    /// is 8.
+    pub fn max_concurrent_px_dials(&mut self, max_dials: NonZeroUsize) -> &mut Self {
+        self.config.max_concurrent_px_dials = max_dials;
+        self
+    }
+
+    /// How long a peer learned via peer exchange stays ineligible for the mesh after dialing it
+    /// failed, see [`Self::px_verification`]. Once the backoff expired, a peer which is still
+    /// connected is dialed again, otherwise it is forgotten. The default is 60 seconds.
+    pub fn px_dial_failure_backoff(&mut self, backoff: Duration) -> &mut Self {
+        self.config.px_dial_failure_backoff = backoff;
+        self
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of [`Self::prune_backoff`] so they know
@@ -742,6 +1081,30 @@ impl ConfigBuilder {
         self
     }
 
+    /// The maximum number of message ids advertised via IHAVE messages to process from a peer
+    /// within a heartbeat. Further message ids are ignored. The default is 50000.
+    pub fn max_ihave_message_ids(&mut self, max_ihave_message_ids: usize) -> &mut Self {
+        self.config.max_ihave_message_ids = max_ihave_message_ids;
+        self
+    }
+
+    /// The maximum number of messages to send to a peer in response to its IWANT messages within a
+    /// heartbeat. Further requested messages are not sent. The default is 5000.
+    pub fn max_iwant_responses(&mut self, max_iwant_responses: usize) -> &mut Self {
+        self.config.max_iwant_responses = max_iwant_responses;
+        self
+    }
+
+    /// The number of broken promises, i.e. messages a peer advertised via IHAVE but did not deliver
+    /// within [`Config::iwant_followup_time`] after we requested them, after which we ignore the
+    /// IHAVE messages of the peer. The count of each peer decreases by one every heartbeat.
+    /// This applies regardless of peer scoring. If this is unset, there is no limit. The default
+    /// is None.
+    pub fn max_broken_promises(&mut self, max: Option<usize>) -> &mut Self {
+        self.config.max_broken_promises = max;
+        self
+    }
+
     /// By default, gossipsub will reject messages that are sent to us that has the same message
     /// source as we have specified locally. Enabling this, allows these messages and prevents
     /// penalizing the peer that sent us the message. Default is false.
@@ -782,6 +1145,68 @@ impl ConfigBuilder {
         self
     }
 
+    /// Limits the bytes forwarded on the given topic, keeping a single noisy topic from starving
+    /// the others. By default, no topic has a quota.
+    pub fn topic_bandwidth_quota(&mut self, topic: TopicHash, quota: BandwidthQuota) -> &mut Self {
+        self.config.topic_bandwidth_quotas.insert(topic, quota);
+        self
+    }
+
+    /// Enables delivery receipts for the messages of the given topic, an extension to the
+    /// gossipsub protocol.
+    ///
+    /// When receiving a message on the topic, we confirm its receipt to the peer that sent it
+    /// with an ACK control message, batching the receipts of all messages received from the
+    /// peer since the behaviour was last polled. For the messages we publish on the topic, the confirmations
+    /// of the peers we sent the message to are counted within the given window and reported
+    /// via [`Event::DeliveryReceipts`](crate::Event::DeliveryReceipts). The window ends on
+    /// the first heartbeat after it elapsed.
+    ///
+    /// Only peers with delivery receipts enabled for the topic confirm receipts, others ignore
+    /// the ACK control messages. By default, no topic has delivery receipts.
+    pub fn delivery_receipts(&mut self, topic: TopicHash, window: Duration) -> &mut Self {
+        self.config.delivery_receipt_windows.insert(topic, window);
+        self
+    }
+
+    /// Limits the number of topics the local node can be subscribed to at once, taking the given
+    /// action when subscribing to a topic beyond the limit. By default, there is no limit.
+    pub fn max_subscriptions(
+        &mut self,
+        max_subscriptions: usize,
+        action: SubscriptionLimitAction,
+    ) -> &mut Self {
+        self.config.max_subscriptions = Some(max_subscriptions);
+        self.config.subscription_limit_action = action;
+        self
+    }
+
+    /// Whether to answer IWANTs for, and emit IHAVEs about, messages of the topics we observe,
+    /// see [`crate::Behaviour::observe`]. The default is false.
+    pub fn observer_answers_iwant(&mut self, observer_answers_iwant: bool) -> &mut Self {
+        self.config.observer_answers_iwant = observer_answers_iwant;
+        self
+    }
+
+    /// Detect peers whose outbound queue stays full, i.e. which do not read the RPCs we send
+    /// fast enough and thereby delay the propagation of messages in the meshes.
+    ///
+    /// A slow peer is pruned from all meshes, its score is penalized and
+    /// [`Event::SlowPeer`](crate::Event::SlowPeer) is emitted. If the queue of the peer is still
+    /// full after another window, this repeats. By default, slow peers are not detected.
+    pub fn slow_peer_detection(&mut self, detection: SlowPeerDetection) -> &mut Self {
+        self.config.slow_peer_detection = Some(detection);
+        self
+    }
+
+    /// The [`Clock`] driving the heartbeat, the peer score decay and the expiry of fanout peers
+    /// and backoffs, e.g. a [`VirtualClock`](libp2p_swarm::VirtualClock) in tests. The default
+    /// is the [`SystemClock`].
+    pub fn clock(&mut self, clock: impl Clock) -> &mut Self {
+        self.config.clock = Arc::new(clock);
+        self
+    }
+
     /// Constructs a [`Config`] from the given configuration and validates the settings.
     pub fn build(&self) -> Result<Config, ConfigBuilderError> {
         // check all constraints on config
@@ -813,6 +1238,36 @@ impl ConfigBuilder {
             return Err(ConfigBuilderError::InvalidProtocol);
         }
 
+        if let Some(topic) = self
+            .config
+            .topic_bandwidth_quotas
+            .iter()
+            .find_map(|(topic, quota)| quota.interval.is_zero().then_some(topic))
+        {
+            return Err(ConfigBuilderError::BandwidthQuotaIntervalIsZero {
+                topic: topic.clone(),
+            });
+        }
+
+        if let Some(topic) = self
+            .config
+            .delivery_receipt_windows
+            .iter()
+            .find_map(|(topic, window)| window.is_zero().then_some(topic))
+        {
+            return Err(ConfigBuilderError::DeliveryReceiptWindowIsZero {
+                topic: topic.clone(),
+            });
+        }
+
+        if self.config.max_subscriptions == Some(0) {
+            return Err(ConfigBuilderError::MaxSubscriptionsIsZero);
+        }
+
+        if matches!(self.config.slow_peer_detection, Some(d) if d.max_queue_len == 0) {
+            return Err(ConfigBuilderError::SlowPeerMaxQueueLenIsZero);
+        }
+
         Ok(self.config.clone())
     }
 }
@@ -834,9 +1289,14 @@ impl std::fmt::Debug for Config {
         let _ = builder.field("fanout_ttl", &self.fanout_ttl);
         let _ = builder.field("duplicate_cache_time", &self.duplicate_cache_time);
         let _ = builder.field("validate_messages", &self.validate_messages);
+        let _ = builder.field("message_replay_window", &self.message_replay_window);
+        let _ = builder.field("max_clock_skew", &self.max_clock_skew);
         let _ = builder.field("allow_self_origin", &self.allow_self_origin);
         let _ = builder.field("do_px", &self.do_px);
         let _ = builder.field("prune_peers", &self.prune_peers);
+        let _ = builder.field("px_verification", &self.px_verification);
+        let _ = builder.field("max_concurrent_px_dials", &self.max_concurrent_px_dials);
+        let _ = builder.field("px_dial_failure_backoff", &self.px_dial_failure_backoff);
         let _ = builder.field("prune_backoff", &self.prune_backoff);
         let _ = builder.field("backoff_slack", &self.backoff_slack);
         let _ = builder.field("flood_publish", &self.flood_publish);
@@ -847,11 +1307,21 @@ impl std::fmt::Debug for Config {
         let _ = builder.field("max_messages_per_rpc", &self.max_messages_per_rpc);
         let _ = builder.field("max_ihave_length", &self.max_ihave_length);
         let _ = builder.field("max_ihave_messages", &self.max_ihave_messages);
+        let _ = builder.field("max_ihave_message_ids", &self.max_ihave_message_ids);
+        let _ = builder.field("max_iwant_responses", &self.max_iwant_responses);
+        let _ = builder.field("max_broken_promises", &self.max_broken_promises);
         let _ = builder.field("iwant_followup_time", &self.iwant_followup_time);
         let _ = builder.field(
             "published_message_ids_cache_time",
             &self.published_message_ids_cache_time,
         );
+        let _ = builder.field("topic_bandwidth_quotas", &self.topic_bandwidth_quotas);
+        let _ = builder.field("delivery_receipt_windows", &self.delivery_receipt_windows);
+        let _ = builder.field("max_subscriptions", &self.max_subscriptions);
+        let _ = builder.field("subscription_limit_action", &self.subscription_limit_action);
+        let _ = builder.field("observer_answers_iwant", &self.observer_answers_iwant);
+        let _ = builder.field("slow_peer_detection", &self.slow_peer_detection);
+        let _ = builder.field("clock", &self.clock);
         builder.finish()
     }
 }
@@ -956,6 +1426,49 @@ mod test {
         assert_eq!(protocol_ids[0].kind, PeerKind::Gossipsub);
     }
 
+    #[test]
+    fn create_config_with_bandwidth_quota() {
+        let topic = Topic::<IdentityHash>::new("test").hash();
+        let quota =
+            BandwidthQuota::new(1024, Duration::from_secs(1)).with_action(QuotaAction::Delay);
+
+        let config = ConfigBuilder::default()
+            .topic_bandwidth_quota(topic.clone(), quota)
+            .build()
+            .unwrap();
+        assert_eq!(config.topic_bandwidth_quota(&topic), Some(&quota));
+
+        let result = ConfigBuilder::default()
+            .topic_bandwidth_quota(topic.clone(), BandwidthQuota::new(1024, Duration::ZERO))
+            .build();
+        assert!(matches!(
+            result,
+            Err(ConfigBuilderError::BandwidthQuotaIntervalIsZero { topic: t }) if t == topic
+        ));
+    }
+
+    #[test]
+    fn create_config_with_delivery_receipts() {
+        let topic = Topic::<IdentityHash>::new("test").hash();
+
+        let config = ConfigBuilder::default()
+            .delivery_receipts(topic.clone(), Duration::from_secs(5))
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.delivery_receipt_window(&topic),
+            Some(Duration::from_secs(5))
+        );
+
+        let result = ConfigBuilder::default()
+            .delivery_receipts(topic.clone(), Duration::ZERO)
+            .build();
+        assert!(matches!(
+            result,
+            Err(ConfigBuilderError::DeliveryReceiptWindowIsZero { topic: t }) if t == topic
+        ));
+    }
+
     fn get_gossipsub_message() -> Message {
         Message {
             source: None,
@@ -22,6 +22,8 @@
 
 use libp2p_identity::SigningError;
 
+use crate::TopicHash;
+
 /// Error associated with publishing a gossipsub message.
 #[derive(Debug)]
 pub enum PublishError {
@@ -61,6 +63,9 @@ pub enum SubscriptionError {
     PublishError(PublishError),
     /// We are not allowed to subscribe to this topic by the subscription filter
     NotAllowed,
+    /// We are already subscribed to [`Config::max_subscriptions`](crate::Config::max_subscriptions)
+    /// topics.
+    TooManySubscriptions,
 }
 
 impl std::fmt::Display for SubscriptionError {
@@ -136,6 +141,14 @@ pub enum ConfigBuilderError {
     UnsubscribeBackoffIsZero,
     /// Invalid protocol
     InvalidProtocol,
+    /// The interval of the bandwidth quota of a topic is zero.
+    BandwidthQuotaIntervalIsZero { topic: TopicHash },
+    /// The delivery receipt window of a topic is zero.
+    DeliveryReceiptWindowIsZero { topic: TopicHash },
+    /// The maximum number of subscriptions is zero.
+    MaxSubscriptionsIsZero,
+    /// The maximum queue length of the slow peer detection is zero.
+    SlowPeerMaxQueueLenIsZero,
 }
 
 impl std::error::Error for ConfigBuilderError {}
@@ -151,6 +164,60 @@ impl std::fmt::Display for ConfigBuilderError {
             Self::MeshOutboundInvalid => write!(f, "The inequality doesn't hold mesh_outbound_min <= self.config.mesh_n / 2"),
             Self::UnsubscribeBackoffIsZero => write!(f, "unsubscribe_backoff is zero"),
             Self::InvalidProtocol => write!(f, "Invalid protocol"),
+            Self::BandwidthQuotaIntervalIsZero { topic } => {
+                write!(f, "The interval of the bandwidth quota of topic {topic} is zero")
+            }
+            Self::DeliveryReceiptWindowIsZero { topic } => {
+                write!(f, "The delivery receipt window of topic {topic} is zero")
+            }
+            Self::MaxSubscriptionsIsZero => write!(f, "The maximum number of subscriptions is zero"),
+            Self::SlowPeerMaxQueueLenIsZero => {
+                write!(f, "The maximum queue length of the slow peer detection is zero")
+            }
+        }
+    }
+}
+
+/// Error returned by [`PeerScoreParamsBuilder::build`](crate::PeerScoreParamsBuilder::build).
+#[derive(Debug)]
+pub enum PeerScoreParamsError {
+    /// The peer score parameters are invalid.
+    InvalidParams(String),
+    /// The peer score thresholds are invalid.
+    InvalidThresholds(&'static str),
+    /// The mesh message deliveries threshold of a topic is above its cap.
+    MeshDeliveriesThresholdAboveCap { topic: TopicHash },
+    /// The mesh message deliveries window of a topic is not shorter than its activation period.
+    MeshDeliveriesWindowExceedsActivation { topic: TopicHash },
+    /// No peer can reach the opportunistic graft threshold.
+    UnattainableOpportunisticGraftThreshold { threshold: f64, max_score: f64 },
+}
+
+impl std::error::Error for PeerScoreParamsError {}
+
+impl std::fmt::Display for PeerScoreParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InvalidParams(e) => write!(f, "Invalid peer score parameters: {e}"),
+            Self::InvalidThresholds(e) => write!(f, "Invalid peer score thresholds: {e}"),
+            Self::MeshDeliveriesThresholdAboveCap { topic } => write!(
+                f,
+                "mesh_message_deliveries_threshold of topic {topic} is above mesh_message_deliveries_cap, \
+                 every mesh peer would be penalized; lower the threshold or raise the cap"
+            ),
+            Self::MeshDeliveriesWindowExceedsActivation { topic } => write!(
+                f,
+                "mesh_message_deliveries_window of topic {topic} is not shorter than \
+                 mesh_message_deliveries_activation; shorten the window or extend the activation"
+            ),
+            Self::UnattainableOpportunisticGraftThreshold {
+                threshold,
+                max_score,
+            } => write!(
+                f,
+                "opportunistic_graft_threshold {threshold} is above the maximum attainable score {max_score}; \
+                 lower the threshold, raise topic_score_cap or set an app_specific_weight"
+            ),
         }
     }
 }
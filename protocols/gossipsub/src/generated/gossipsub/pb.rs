@@ -154,6 +154,7 @@ pub struct ControlMessage {
     pub iwant: Vec<gossipsub::pb::ControlIWant>,
     pub graft: Vec<gossipsub::pb::ControlGraft>,
     pub prune: Vec<gossipsub::pb::ControlPrune>,
+    pub ack: Vec<gossipsub::pb::ControlAck>,
 }
 
 impl<'a> MessageRead<'a> for ControlMessage {
@@ -165,6 +166,7 @@ impl<'a> MessageRead<'a> for ControlMessage {
                 Ok(18) => msg.iwant.push(r.read_message::<gossipsub::pb::ControlIWant>(bytes)?),
                 Ok(26) => msg.graft.push(r.read_message::<gossipsub::pb::ControlGraft>(bytes)?),
                 Ok(34) => msg.prune.push(r.read_message::<gossipsub::pb::ControlPrune>(bytes)?),
+                Ok(802) => msg.ack.push(r.read_message::<gossipsub::pb::ControlAck>(bytes)?),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -180,6 +182,7 @@ impl MessageWrite for ControlMessage {
         + self.iwant.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
         + self.graft.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
         + self.prune.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+        + self.ack.iter().map(|s| 2 + sizeof_len((s).get_size())).sum::<usize>()
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
@@ -187,6 +190,7 @@ impl MessageWrite for ControlMessage {
         for s in &self.iwant { w.write_with_tag(18, |w| w.write_message(s))?; }
         for s in &self.graft { w.write_with_tag(26, |w| w.write_message(s))?; }
         for s in &self.prune { w.write_with_tag(34, |w| w.write_message(s))?; }
+        for s in &self.ack { w.write_with_tag(802, |w| w.write_message(s))?; }
         Ok(())
     }
 }
@@ -259,6 +263,38 @@ impl MessageWrite for ControlIWant {
     }
 }
 
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ControlAck {
+    pub message_ids: Vec<Vec<u8>>,
+}
+
+impl<'a> MessageRead<'a> for ControlAck {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.message_ids.push(r.read_bytes(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for ControlAck {
+    fn get_size(&self) -> usize {
+        0
+        + self.message_ids.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        for s in &self.message_ids { w.write_with_tag(10, |w| w.write_bytes(&**s))?; }
+        Ok(())
+    }
+}
+
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct ControlGraft {
@@ -76,8 +76,7 @@ impl GossipPromises {
     /// request.
     /// This should be called not too often relative to the expire times, since it iterates over
     /// the whole stored data.
-    pub(crate) fn get_broken_promises(&mut self) -> HashMap<PeerId, usize> {
-        let now = Instant::now();
+    pub(crate) fn get_broken_promises(&mut self, now: Instant) -> HashMap<PeerId, usize> {
         let mut result = HashMap::new();
         self.promises.retain(|msg, peers| {
             peers.retain(|peer_id, expires| {
@@ -18,6 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::config::SlowPeerDetection;
 use crate::protocol::{GossipsubCodec, ProtocolConfig};
 use crate::rpc_proto::proto;
 use crate::types::{PeerKind, RawMessage, Rpc, RpcOut};
@@ -32,11 +33,13 @@ use libp2p_swarm::handler::{
     ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, DialUpgradeError,
     FullyNegotiatedInbound, FullyNegotiatedOutbound, StreamUpgradeError, SubstreamProtocol,
 };
-use libp2p_swarm::Stream;
+use libp2p_swarm::{Clock, Sleep, Stream};
 use smallvec::SmallVec;
 use std::{
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// The event emitted by the Handler. This informs the behaviour of various events created
@@ -55,6 +58,13 @@ pub enum HandlerEvent {
     /// An inbound or outbound substream has been established with the peer and this informs over
     /// which protocol. This message only occurs once per connection.
     PeerKind(PeerKind),
+    /// The send queue stayed full for the window of the [`SlowPeerDetection`].
+    SlowPeer {
+        /// The number of queued RPCs.
+        queue_len: usize,
+        /// How long the send queue has been full.
+        full_for: Duration,
+    },
 }
 
 /// A message sent from the behaviour to the handler.
@@ -120,6 +130,21 @@ pub struct EnabledHandler {
     /// Keeps track of whether this connection is for a peer in the mesh. This is used to make
     /// decisions about the keep alive state for this connection.
     in_mesh: bool,
+
+    /// The detection of slow peers, if enabled.
+    slow_peer_detection: Option<SlowPeerDetection>,
+
+    /// Since when the send queue is full, measured by `clock`.
+    send_queue_full_since: Option<Instant>,
+
+    /// A [`HandlerEvent::SlowPeer`] to report to the behaviour.
+    pending_slow_peer: Option<HandlerEvent>,
+
+    /// Fires once the send queue stayed full for the window of the [`SlowPeerDetection`], so
+    /// that a stalled peer is detected even if we have nothing else to send to it.
+    slow_peer_timer: Option<Sleep>,
+
+    clock: Arc<dyn Clock>,
 }
 
 pub enum DisabledHandler {
@@ -159,7 +184,11 @@ enum OutboundSubstreamState {
 
 impl Handler {
     /// Builds a new [`Handler`].
-    pub fn new(protocol_config: ProtocolConfig) -> Self {
+    pub fn new(
+        protocol_config: ProtocolConfig,
+        slow_peer_detection: Option<SlowPeerDetection>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Handler::Enabled(EnabledHandler {
             listen_protocol: protocol_config,
             inbound_substream: None,
@@ -172,11 +201,46 @@ impl Handler {
             peer_kind_sent: false,
             last_io_activity: Instant::now(),
             in_mesh: false,
+            slow_peer_detection,
+            send_queue_full_since: None,
+            pending_slow_peer: None,
+            slow_peer_timer: None,
+            clock,
         })
     }
 }
 
 impl EnabledHandler {
+    /// Checks whether the send queue stayed full for the window of the [`SlowPeerDetection`].
+    fn check_send_queue(&mut self) {
+        let Some(detection) = self.slow_peer_detection else {
+            return;
+        };
+        if self.send_queue.len() < detection.max_queue_len() {
+            self.send_queue_full_since = None;
+            self.slow_peer_timer = None;
+            return;
+        }
+
+        let now = self.clock.now();
+        let since = *self.send_queue_full_since.get_or_insert(now);
+        let mut full_for = now.duration_since(since);
+        if full_for >= detection.window() {
+            // Report the peer again if the queue is still full after another window.
+            self.send_queue_full_since = Some(now);
+            self.slow_peer_timer = None;
+            self.pending_slow_peer = Some(HandlerEvent::SlowPeer {
+                queue_len: self.send_queue.len(),
+                full_for,
+            });
+            full_for = Duration::ZERO;
+        }
+
+        if self.slow_peer_timer.is_none() {
+            self.slow_peer_timer = Some(self.clock.sleep(detection.window() - full_for));
+        }
+    }
+
     fn on_fully_negotiated_inbound(
         &mut self,
         (substream, peer_kind): (Framed<Stream, GossipsubCodec>, PeerKind),
@@ -231,6 +295,24 @@ impl EnabledHandler {
             }
         }
 
+        while self.pending_slow_peer.is_none() {
+            match self
+                .slow_peer_timer
+                .as_mut()
+                .map(|timer| timer.poll_unpin(cx))
+            {
+                Some(Poll::Ready(())) => {
+                    self.slow_peer_timer = None;
+                    self.check_send_queue();
+                }
+                Some(Poll::Pending) | None => break,
+            }
+        }
+
+        if let Some(event) = self.pending_slow_peer.take() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
         // determine if we need to create the outbound stream
         if !self.send_queue.is_empty()
             && self.outbound_substream.is_none()
@@ -252,6 +334,7 @@ impl EnabledHandler {
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
                     if let Some(message) = self.send_queue.pop() {
                         self.send_queue.shrink_to_fit();
+                        self.check_send_queue();
                         self.outbound_substream =
                             Some(OutboundSubstreamState::PendingSend(substream, message));
                         continue;
@@ -409,7 +492,10 @@ impl ConnectionHandler for Handler {
     fn on_behaviour_event(&mut self, message: HandlerIn) {
         match self {
             Handler::Enabled(handler) => match message {
-                HandlerIn::Message(m) => handler.send_queue.push(m.into_protobuf()),
+                HandlerIn::Message(m) => {
+                    handler.send_queue.push(m.into_protobuf());
+                    handler.check_send_queue();
+                }
                 HandlerIn::JoinedMesh => {
                     handler.in_mesh = true;
                 }
@@ -533,3 +619,56 @@ impl ConnectionHandler for Handler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TopicHash;
+    use libp2p_swarm::VirtualClock;
+
+    #[test]
+    fn idle_slow_peer_is_detected_by_timer() {
+        let clock = VirtualClock::new();
+        let window = Duration::from_secs(1);
+        let mut handler = Handler::new(
+            ProtocolConfig::default(),
+            Some(SlowPeerDetection::new(2, window)),
+            Arc::new(clock.clone()),
+        );
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        for topic in ["a", "b"] {
+            handler.on_behaviour_event(HandlerIn::Message(RpcOut::Subscribe(TopicHash::from_raw(
+                topic,
+            ))));
+        }
+        assert!(matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest { .. })
+        ));
+        assert!(handler.poll(&mut cx).is_pending());
+
+        // Nothing else is sent to the peer, its queue just stays full.
+        clock.advance(window);
+        match handler.poll(&mut cx) {
+            Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(HandlerEvent::SlowPeer {
+                queue_len,
+                full_for,
+            })) => {
+                assert_eq!(queue_len, 2);
+                assert_eq!(full_for, window);
+            }
+            _ => panic!("Expected the peer to be reported as slow"),
+        }
+        assert!(handler.poll(&mut cx).is_pending());
+
+        // The peer is reported again after another window.
+        clock.advance(window);
+        assert!(matches!(
+            handler.poll(&mut cx),
+            Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                HandlerEvent::SlowPeer { .. }
+            ))
+        ));
+    }
+}
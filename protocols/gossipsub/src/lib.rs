@@ -103,6 +103,7 @@ mod mcache;
 mod metrics;
 mod peer_score;
 mod protocol;
+mod quota;
 mod rpc_proto;
 mod subscription_filter;
 mod time_cache;
@@ -111,12 +112,17 @@ mod transform;
 mod types;
 
 pub use self::behaviour::{Behaviour, Event, MessageAuthenticity};
-pub use self::config::{Config, ConfigBuilder, ValidationMode, Version};
-pub use self::error::{ConfigBuilderError, PublishError, SubscriptionError, ValidationError};
+pub use self::config::{
+    BandwidthQuota, Config, ConfigBuilder, PxVerification, QuotaAction, SlowPeerDetection,
+    SubscriptionLimitAction, ValidationMode, Version,
+};
+pub use self::error::{
+    ConfigBuilderError, PeerScoreParamsError, PublishError, SubscriptionError, ValidationError,
+};
 pub use self::metrics::Config as MetricsConfig;
 pub use self::peer_score::{
-    score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreThresholds,
-    TopicScoreParams,
+    score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams,
+    PeerScoreParamsBuilder, PeerScoreThresholds, ScoreProfile, TopicScoreParams,
 };
 pub use self::subscription_filter::{
     AllowAllSubscriptionFilter, CallbackSubscriptionFilter, CombinedSubscriptionFilters,
@@ -125,7 +131,10 @@ pub use self::subscription_filter::{
 };
 pub use self::topic::{Hasher, Topic, TopicHash};
 pub use self::transform::{DataTransform, IdentityTransform};
-pub use self::types::{Message, MessageAcceptance, MessageId, RawMessage};
+pub use self::types::{
+    GossipControlStats, MeshStats, Message, MessageAcceptance, MessageId, RawMessage,
+    TopicBandwidth,
+};
 
 #[deprecated(note = "Will be removed from the public API.")]
 pub type Rpc = self::types::Rpc;
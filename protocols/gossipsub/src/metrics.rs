@@ -22,6 +22,8 @@
 //! protocol.
 
 use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::time::Duration;
 
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
@@ -136,6 +138,10 @@ pub(crate) struct Metrics {
     mesh_peer_inclusion_events: Family<InclusionLabel, Counter>,
     /// Number of times we remove peers in a topic mesh for different reasons.
     mesh_peer_churn_events: Family<ChurnLabel, Counter>,
+    /// Time spent maintaining the mesh of each topic in heartbeats.
+    mesh_maintenance_seconds: Family<TopicHash, Counter<f64, AtomicU64>>,
+    /// Number of GRAFT and PRUNE messages sent by the heartbeat for each topic.
+    mesh_maintenance_msgs: Family<TopicHash, Counter>,
 
     /* Metrics regarding messages sent/received */
     /// Number of gossip messages sent to each topic.
@@ -144,6 +150,13 @@ pub(crate) struct Metrics {
     topic_msg_sent_bytes: Family<TopicHash, Counter>,
     /// Number of gossipsub messages published to each topic.
     topic_msg_published: Family<TopicHash, Counter>,
+    /// Bytes from gossip messages published to each topic.
+    topic_msg_published_bytes: Family<TopicHash, Counter>,
+    /// Bytes from gossip messages forwarded on each topic.
+    topic_msg_forwarded_bytes: Family<TopicHash, Counter>,
+    /// Bytes from gossip messages dropped, respectively delayed, by the bandwidth quota of each
+    /// topic.
+    topic_msg_throttled_bytes: Family<ThrottleLabel, Counter>,
 
     /// Number of gossipsub messages received on each topic (without filtering duplicates).
     topic_msg_recv_counts_unfiltered: Family<TopicHash, Counter>,
@@ -174,6 +187,9 @@ pub(crate) struct Metrics {
     /// The number of times we have decided that an IWANT control message is required for this
     /// topic. A very high metric might indicate an underperforming network.
     topic_iwant_msgs: Family<TopicHash, Counter>,
+    /// The number of IHAVE messages, IHAVE message ids and IWANT responses dropped, respectively
+    /// broken promises, by the limit protecting against gossip control spam.
+    gossip_control_limited: Family<GossipControlLabel, Counter>,
 }
 
 impl Metrics {
@@ -234,6 +250,14 @@ impl Metrics {
             "mesh_peer_churn_events",
             "Number of times a peer gets removed from our mesh for different reasons"
         );
+        let mesh_maintenance_seconds = register_family!(
+            "mesh_maintenance_seconds",
+            "Time spent maintaining the mesh of each topic in heartbeats"
+        );
+        let mesh_maintenance_msgs = register_family!(
+            "mesh_maintenance_msgs",
+            "Number of GRAFT and PRUNE messages sent by the heartbeat for each topic"
+        );
         let topic_msg_sent_counts = register_family!(
             "topic_msg_sent_counts",
             "Number of gossip messages sent to each topic"
@@ -246,6 +270,18 @@ impl Metrics {
             "topic_msg_sent_bytes",
             "Bytes from gossip messages sent to each topic"
         );
+        let topic_msg_published_bytes = register_family!(
+            "topic_msg_published_bytes",
+            "Bytes from gossip messages published to each topic"
+        );
+        let topic_msg_forwarded_bytes = register_family!(
+            "topic_msg_forwarded_bytes",
+            "Bytes from gossip messages forwarded on each topic"
+        );
+        let topic_msg_throttled_bytes = register_family!(
+            "topic_msg_throttled_bytes",
+            "Bytes from gossip messages dropped or delayed by the bandwidth quota of each topic"
+        );
 
         let topic_msg_recv_counts_unfiltered = register_family!(
             "topic_msg_recv_counts_unfiltered",
@@ -292,6 +328,11 @@ impl Metrics {
             "topic_iwant_msgs",
             "Number of times we have decided an IWANT is required for this topic"
         );
+        let gossip_control_limited = register_family!(
+            "gossip_control_limited",
+            "Number of IHAVE messages, IHAVE message ids and IWANT responses dropped, respectively \
+             broken promises, by gossip control limit"
+        );
         let memcache_misses = {
             let metric = Counter::default();
             registry.register(
@@ -315,9 +356,14 @@ impl Metrics {
             mesh_peer_counts,
             mesh_peer_inclusion_events,
             mesh_peer_churn_events,
+            mesh_maintenance_seconds,
+            mesh_maintenance_msgs,
             topic_msg_sent_counts,
             topic_msg_sent_bytes,
             topic_msg_published,
+            topic_msg_published_bytes,
+            topic_msg_forwarded_bytes,
+            topic_msg_throttled_bytes,
             topic_msg_recv_counts_unfiltered,
             topic_msg_recv_counts,
             topic_msg_recv_bytes,
@@ -327,6 +373,7 @@ impl Metrics {
             heartbeat_duration,
             memcache_misses,
             topic_iwant_msgs,
+            gossip_control_limited,
         }
     }
 
@@ -421,6 +468,22 @@ impl Metrics {
         }
     }
 
+    /// Register the time spent maintaining the mesh of a topic in a heartbeat.
+    pub(crate) fn mesh_maintained(&mut self, topic: &TopicHash, duration: Duration) {
+        if self.register_topic(topic).is_ok() {
+            self.mesh_maintenance_seconds
+                .get_or_create(topic)
+                .inc_by(duration.as_secs_f64());
+        }
+    }
+
+    /// Register a GRAFT or PRUNE message sent by the heartbeat for a topic.
+    pub(crate) fn mesh_maintenance_msg_sent(&mut self, topic: &TopicHash) {
+        if self.register_topic(topic).is_ok() {
+            self.mesh_maintenance_msgs.get_or_create(topic).inc();
+        }
+    }
+
     /// Register that an invalid message was received on a specific topic.
     pub(crate) fn register_invalid_message(&mut self, topic: &TopicHash) {
         if self.register_topic(topic).is_ok() {
@@ -452,6 +515,36 @@ impl Metrics {
         }
     }
 
+    /// Register the bytes of a message published on a topic.
+    pub(crate) fn msg_published_bytes(&mut self, topic: &TopicHash, bytes: usize) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_published_bytes
+                .get_or_create(topic)
+                .inc_by(bytes as u64);
+        }
+    }
+
+    /// Register the bytes of a message forwarded on a topic.
+    pub(crate) fn msg_forwarded_bytes(&mut self, topic: &TopicHash, bytes: usize) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_forwarded_bytes
+                .get_or_create(topic)
+                .inc_by(bytes as u64);
+        }
+    }
+
+    /// Register the bytes of a message throttled by the bandwidth quota of a topic.
+    pub(crate) fn msg_throttled_bytes(&mut self, topic: &TopicHash, action: Throttle, bytes: u64) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_throttled_bytes
+                .get_or_create(&ThrottleLabel {
+                    hash: topic.to_string(),
+                    action,
+                })
+                .inc_by(bytes);
+        }
+    }
+
     /// Register that a message was received (and was not a duplicate).
     pub(crate) fn msg_recvd(&mut self, topic: &TopicHash) {
         if self.register_topic(topic).is_ok() {
@@ -497,6 +590,17 @@ impl Metrics {
         }
     }
 
+    /// Register that `count` items were limited by the given gossip control limit.
+    pub(crate) fn register_gossip_control_limited(
+        &mut self,
+        limit: GossipControlLimit,
+        count: u64,
+    ) {
+        self.gossip_control_limited
+            .get_or_create(&GossipControlLabel { limit })
+            .inc_by(count);
+    }
+
     /// Observes a heartbeat duration.
     pub(crate) fn observe_heartbeat_duration(&mut self, millis: u64) {
         self.heartbeat_duration.observe(millis as f64);
@@ -554,6 +658,8 @@ pub(crate) enum Churn {
     Unsub,
     /// Too many peers.
     Excess,
+    /// Peer's outbound queue stayed full.
+    Slow,
 }
 
 /// Kinds of reasons a peer's score has been penalized
@@ -567,6 +673,43 @@ pub(crate) enum Penalty {
     MessageDeficit,
     /// Too many peers under one IP address.
     IPColocation,
+    /// The outbound queue of a peer stayed full.
+    SlowPeer,
+}
+
+/// Limits protecting against gossip control spam.
+#[derive(PartialEq, Eq, Hash, EncodeLabelValue, Clone, Copy, Debug)]
+pub(crate) enum GossipControlLimit {
+    /// IHAVE messages ignored due to `max_ihave_messages` or `max_broken_promises`.
+    IHaveMessages,
+    /// IHAVE message ids ignored due to `max_ihave_message_ids`.
+    IHaveMessageIds,
+    /// IWANT responses not sent due to `max_iwant_responses`.
+    IWantResponses,
+    /// Messages advertised via IHAVE but not delivered after being requested.
+    BrokenPromises,
+}
+
+/// How a message exceeding the bandwidth quota of its topic was throttled.
+#[derive(PartialEq, Eq, Hash, EncodeLabelValue, Clone, Copy, Debug)]
+pub(crate) enum Throttle {
+    /// The message was not forwarded.
+    Dropped,
+    /// The message is forwarded in a later quota interval.
+    Delayed,
+}
+
+/// Label for the bandwidth quota metrics.
+#[derive(PartialEq, Eq, Hash, EncodeLabelSet, Clone, Debug)]
+struct ThrottleLabel {
+    hash: String,
+    action: Throttle,
+}
+
+/// Label for the gossip control limit metrics.
+#[derive(PartialEq, Eq, Hash, EncodeLabelSet, Clone, Debug)]
+struct GossipControlLabel {
+    limit: GossipControlLimit,
 }
 
 /// Label for the mesh inclusion event metrics.
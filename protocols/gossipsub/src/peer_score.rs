@@ -26,16 +26,20 @@ use crate::time_cache::TimeCache;
 use crate::{MessageId, TopicHash};
 use instant::Instant;
 use libp2p_identity::PeerId;
+use libp2p_swarm::{Clock, SystemClock};
 use std::collections::{hash_map, HashMap, HashSet};
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 mod params;
+mod profile;
 use crate::ValidationError;
 pub use params::{
     score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreThresholds,
     TopicScoreParams,
 };
+pub use profile::{PeerScoreParamsBuilder, ScoreProfile};
 
 #[cfg(test)]
 mod tests;
@@ -53,6 +57,8 @@ pub(crate) struct PeerScore {
     deliveries: TimeCache<MessageId, DeliveryRecord>,
     /// callback for monitoring message delivery times
     message_delivery_time_callback: Option<fn(&PeerId, &TopicHash, f64)>,
+    /// The source of time for mesh times, delivery windows and the retention of scores.
+    clock: Arc<dyn Clock>,
 }
 
 /// General statistics for a given gossipsub peer.
@@ -141,10 +147,10 @@ enum MeshStatus {
 }
 
 impl MeshStatus {
-    /// Initialises a new [`MeshStatus::Active`] mesh status.
-    pub(crate) fn new_active() -> Self {
+    /// Initialises a new [`MeshStatus::Active`] mesh status, grafted at the given instant.
+    pub(crate) fn new_active(graft_time: Instant) -> Self {
         MeshStatus::Active {
-            graft_time: Instant::now(),
+            graft_time,
             mesh_time: Duration::from_secs(0),
         }
     }
@@ -182,11 +188,12 @@ enum DeliveryStatus {
     Ignored,
 }
 
-impl Default for DeliveryRecord {
-    fn default() -> Self {
+impl DeliveryRecord {
+    /// Creates a record of a message first seen at the given instant.
+    fn new(first_seen: Instant) -> Self {
         DeliveryRecord {
             status: DeliveryStatus::Unknown,
-            first_seen: Instant::now(),
+            first_seen,
             peers: HashSet::new(),
         }
     }
@@ -196,19 +203,21 @@ impl PeerScore {
     /// Creates a new [`PeerScore`] using a given set of peer scoring parameters.
     #[allow(dead_code)]
     pub(crate) fn new(params: PeerScoreParams) -> Self {
-        Self::new_with_message_delivery_time_callback(params, None)
+        Self::new_with_message_delivery_time_callback(params, None, Arc::new(SystemClock))
     }
 
     pub(crate) fn new_with_message_delivery_time_callback(
         params: PeerScoreParams,
         callback: Option<fn(&PeerId, &TopicHash, f64)>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         PeerScore {
             params,
             peer_stats: HashMap::new(),
             peer_ips: HashMap::new(),
-            deliveries: TimeCache::new(Duration::from_secs(TIME_CACHE_DURATION)),
+            deliveries: TimeCache::new(Duration::from_secs(TIME_CACHE_DURATION), clock.clone()),
             message_delivery_time_callback: callback,
+            clock,
         }
     }
 
@@ -366,7 +375,7 @@ impl PeerScore {
     }
 
     pub(crate) fn refresh_scores(&mut self) {
-        let now = Instant::now();
+        let now = self.clock.now();
         let params_ref = &self.params;
         let peer_ips_ref = &mut self.peer_ips;
         self.peer_stats.retain(|peer_id, peer_stats| {
@@ -516,7 +525,7 @@ impl PeerScore {
             }
 
             peer_stats.status = ConnectionStatus::Disconnected {
-                expire: Instant::now() + self.params.retain_score,
+                expire: self.clock.now() + self.params.retain_score,
             };
         }
     }
@@ -527,7 +536,7 @@ impl PeerScore {
         if let Some(peer_stats) = self.peer_stats.get_mut(peer_id) {
             // if we are scoring the topic, update the mesh status.
             if let Some(topic_stats) = peer_stats.stats_or_default_mut(topic, &self.params) {
-                topic_stats.mesh_status = MeshStatus::new_active();
+                topic_stats.mesh_status = MeshStatus::new_active(self.clock.now());
                 topic_stats.mesh_message_deliveries_active = false;
             }
         }
@@ -565,7 +574,10 @@ impl PeerScore {
         topic_hash: &TopicHash,
     ) {
         // adds an empty record with the message id
-        self.deliveries.entry(msg_id.clone()).or_default();
+        let now = self.clock.now();
+        self.deliveries
+            .entry(msg_id.clone())
+            .or_insert_with(|| DeliveryRecord::new(now));
 
         if let Some(callback) = self.message_delivery_time_callback {
             if self
@@ -588,21 +600,25 @@ impl PeerScore {
     ) {
         self.mark_first_message_delivery(from, topic_hash);
 
-        let record = self.deliveries.entry(msg_id.clone()).or_default();
+        let now = self.clock.now();
+        let record = self
+            .deliveries
+            .entry(msg_id.clone())
+            .or_insert_with(|| DeliveryRecord::new(now));
 
         // this should be the first delivery trace
         if record.status != DeliveryStatus::Unknown {
             tracing::warn!(
                 peer=%from,
                 status=?record.status,
-                first_seen=?record.first_seen.elapsed().as_secs(),
+                first_seen=?now.duration_since(record.first_seen).as_secs(),
                 "Unexpected delivery trace"
             );
             return;
         }
 
         // mark the message as valid and reward mesh peers that have already forwarded it to us
-        record.status = DeliveryStatus::Valid(Instant::now());
+        record.status = DeliveryStatus::Valid(now);
         for peer in record.peers.iter().cloned().collect::<Vec<_>>() {
             // this check is to make sure a peer can't send us a message twice and get a double
             // count if it is a first delivery
@@ -644,7 +660,11 @@ impl PeerScore {
         }
 
         let peers: Vec<_> = {
-            let record = self.deliveries.entry(msg_id.clone()).or_default();
+            let now = self.clock.now();
+            let record = self
+                .deliveries
+                .entry(msg_id.clone())
+                .or_insert_with(|| DeliveryRecord::new(now));
 
             // Multiple peers can now reject the same message as we track which peers send us the
             // message. If we have already updated the status, return.
@@ -678,7 +698,11 @@ impl PeerScore {
         msg_id: &MessageId,
         topic_hash: &TopicHash,
     ) {
-        let record = self.deliveries.entry(msg_id.clone()).or_default();
+        let now = self.clock.now();
+        let record = self
+            .deliveries
+            .entry(msg_id.clone())
+            .or_insert_with(|| DeliveryRecord::new(now));
 
         if record.peers.contains(from) {
             // we have already seen this duplicate!
@@ -687,7 +711,7 @@ impl PeerScore {
 
         if let Some(callback) = self.message_delivery_time_callback {
             let time = if let DeliveryStatus::Valid(validated) = record.status {
-                validated.elapsed().as_secs_f64()
+                now.duration_since(validated).as_secs_f64()
             } else {
                 0.0
             };
@@ -842,7 +866,7 @@ impl PeerScore {
     ) {
         if let Some(peer_stats) = self.peer_stats.get_mut(peer_id) {
             let now = if validated_time.is_some() {
-                Some(Instant::now())
+                Some(self.clock.now())
             } else {
                 None
             };
@@ -0,0 +1,402 @@
+// Copyright 2024 Sigma Prime Pty Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Preset peer score parameters for common use cases.
+
+use super::params::{
+    score_parameter_decay, PeerScoreParams, PeerScoreThresholds, TopicScoreParams,
+};
+use crate::error::PeerScoreParamsError;
+use crate::TopicHash;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// A use case with preset peer score parameters.
+///
+/// The presets are meant as a starting point. They are tuned to the message patterns typical for
+/// the use case, but any production network should still verify them against its own traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreProfile {
+    /// Many small, bursty messages, e.g. chat rooms.
+    ///
+    /// Topics may be silent for long periods, thus mesh peers are not penalized for failing to
+    /// deliver messages.
+    LowLatencyChat,
+    /// Messages published at a regular, known rate that must reach every node quickly,
+    /// e.g. blocks of a blockchain produced every 12 seconds.
+    ///
+    /// Mesh peers are expected to deliver a share of the messages.
+    BlockPropagation,
+    /// Infrequent, large messages, e.g. file or chunk announcements.
+    ///
+    /// Peers delivering messages first are rewarded for long, invalid messages are penalized
+    /// heavily to protect bandwidth.
+    LargeFiles,
+}
+
+impl PeerScoreParams {
+    /// Returns the preset peer score parameters for the given use case, without any topics.
+    ///
+    /// Add topics with [`TopicScoreParams::default_for`], or use a [`PeerScoreParamsBuilder`].
+    pub fn default_for(profile: ScoreProfile) -> Self {
+        match profile {
+            ScoreProfile::LowLatencyChat => PeerScoreParams {
+                topic_score_cap: 100.0,
+                app_specific_weight: 1.0,
+                behaviour_penalty_weight: -10.0,
+                behaviour_penalty_threshold: 6.0,
+                behaviour_penalty_decay: score_parameter_decay(Duration::from_secs(10 * 60)),
+                ..Default::default()
+            },
+            ScoreProfile::BlockPropagation => PeerScoreParams {
+                topic_score_cap: 50.0,
+                app_specific_weight: 1.0,
+                ip_colocation_factor_weight: -35.0,
+                ip_colocation_factor_threshold: 3.0,
+                behaviour_penalty_weight: -16.0,
+                behaviour_penalty_threshold: 6.0,
+                behaviour_penalty_decay: score_parameter_decay(Duration::from_secs(60 * 60)),
+                ..Default::default()
+            },
+            ScoreProfile::LargeFiles => PeerScoreParams {
+                topic_score_cap: 200.0,
+                app_specific_weight: 1.0,
+                behaviour_penalty_weight: -10.0,
+                behaviour_penalty_threshold: 6.0,
+                behaviour_penalty_decay: score_parameter_decay(Duration::from_secs(60 * 60)),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl TopicScoreParams {
+    /// Returns the preset topic score parameters for the given use case.
+    pub fn default_for(profile: ScoreProfile) -> Self {
+        match profile {
+            ScoreProfile::LowLatencyChat => TopicScoreParams {
+                topic_weight: 1.0,
+                time_in_mesh_weight: 0.1,
+                time_in_mesh_quantum: Duration::from_secs(1),
+                time_in_mesh_cap: 100.0,
+                first_message_deliveries_weight: 1.0,
+                first_message_deliveries_decay: score_parameter_decay(Duration::from_secs(10 * 60)),
+                first_message_deliveries_cap: 50.0,
+                mesh_message_deliveries_weight: 0.0,
+                mesh_failure_penalty_weight: 0.0,
+                invalid_message_deliveries_weight: -100.0,
+                invalid_message_deliveries_decay: score_parameter_decay(Duration::from_secs(
+                    60 * 60,
+                )),
+                ..Default::default()
+            },
+            ScoreProfile::BlockPropagation => TopicScoreParams {
+                topic_weight: 0.5,
+                time_in_mesh_weight: 0.0324,
+                time_in_mesh_quantum: Duration::from_secs(12),
+                time_in_mesh_cap: 300.0,
+                first_message_deliveries_weight: 1.0,
+                first_message_deliveries_decay: score_parameter_decay(Duration::from_secs(240)),
+                first_message_deliveries_cap: 23.0,
+                mesh_message_deliveries_weight: -0.72,
+                mesh_message_deliveries_decay: score_parameter_decay(Duration::from_secs(60)),
+                mesh_message_deliveries_cap: 10.0,
+                mesh_message_deliveries_threshold: 1.0,
+                mesh_message_deliveries_window: Duration::from_secs(2),
+                mesh_message_deliveries_activation: Duration::from_secs(60),
+                mesh_failure_penalty_weight: -0.72,
+                mesh_failure_penalty_decay: score_parameter_decay(Duration::from_secs(60)),
+                invalid_message_deliveries_weight: -140.0,
+                invalid_message_deliveries_decay: score_parameter_decay(Duration::from_secs(
+                    60 * 60,
+                )),
+            },
+            ScoreProfile::LargeFiles => TopicScoreParams {
+                topic_weight: 1.0,
+                time_in_mesh_weight: 0.01,
+                time_in_mesh_quantum: Duration::from_secs(60),
+                time_in_mesh_cap: 60.0,
+                first_message_deliveries_weight: 10.0,
+                first_message_deliveries_decay: score_parameter_decay(Duration::from_secs(60 * 60)),
+                first_message_deliveries_cap: 10.0,
+                mesh_message_deliveries_weight: 0.0,
+                mesh_failure_penalty_weight: 0.0,
+                invalid_message_deliveries_weight: -500.0,
+                invalid_message_deliveries_decay: score_parameter_decay(Duration::from_secs(
+                    6 * 60 * 60,
+                )),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl PeerScoreThresholds {
+    /// Returns the preset peer score thresholds for the given use case.
+    pub fn default_for(profile: ScoreProfile) -> Self {
+        match profile {
+            ScoreProfile::LowLatencyChat => PeerScoreThresholds {
+                gossip_threshold: -10.0,
+                publish_threshold: -50.0,
+                graylist_threshold: -80.0,
+                accept_px_threshold: 50.0,
+                opportunistic_graft_threshold: 5.0,
+            },
+            ScoreProfile::BlockPropagation => PeerScoreThresholds {
+                gossip_threshold: -4000.0,
+                publish_threshold: -8000.0,
+                graylist_threshold: -16000.0,
+                accept_px_threshold: 100.0,
+                opportunistic_graft_threshold: 5.0,
+            },
+            ScoreProfile::LargeFiles => PeerScoreThresholds {
+                gossip_threshold: -20.0,
+                publish_threshold: -40.0,
+                graylist_threshold: -100.0,
+                accept_px_threshold: 50.0,
+                opportunistic_graft_threshold: 2.0,
+            },
+        }
+    }
+}
+
+/// Builder for [`PeerScoreParams`] and [`PeerScoreThresholds`] starting from the presets of a
+/// [`ScoreProfile`].
+///
+/// Unlike [`PeerScoreParams::validate`], which checks parameters in isolation,
+/// [`PeerScoreParamsBuilder::build`] also rejects combinations of parameters that are
+/// inconsistent with each other.
+#[derive(Debug, Clone)]
+pub struct PeerScoreParamsBuilder {
+    profile: ScoreProfile,
+    params: PeerScoreParams,
+    thresholds: PeerScoreThresholds,
+}
+
+impl PeerScoreParamsBuilder {
+    pub fn new(profile: ScoreProfile) -> Self {
+        Self {
+            profile,
+            params: PeerScoreParams::default_for(profile),
+            thresholds: PeerScoreThresholds::default_for(profile),
+        }
+    }
+
+    /// Scores the given topic using the preset [`TopicScoreParams`] of the profile.
+    pub fn topic(&mut self, topic: TopicHash) -> &mut Self {
+        self.topic_params(topic, TopicScoreParams::default_for(self.profile))
+    }
+
+    /// Scores the given topic using custom [`TopicScoreParams`].
+    pub fn topic_params(&mut self, topic: TopicHash, params: TopicScoreParams) -> &mut Self {
+        self.params.topics.insert(topic, params);
+        self
+    }
+
+    /// Sets the aggregate topic score cap, see [`PeerScoreParams::topic_score_cap`].
+    pub fn topic_score_cap(&mut self, cap: f64) -> &mut Self {
+        self.params.topic_score_cap = cap;
+        self
+    }
+
+    /// Sets the weight of the application specific score, see
+    /// [`PeerScoreParams::app_specific_weight`].
+    pub fn app_specific_weight(&mut self, weight: f64) -> &mut Self {
+        self.params.app_specific_weight = weight;
+        self
+    }
+
+    /// Sets the IP colocation penalty, see [`PeerScoreParams::ip_colocation_factor_weight`].
+    pub fn ip_colocation_factor(&mut self, weight: f64, threshold: f64) -> &mut Self {
+        self.params.ip_colocation_factor_weight = weight;
+        self.params.ip_colocation_factor_threshold = threshold;
+        self
+    }
+
+    /// Exempts the given IP addresses from the IP colocation penalty.
+    pub fn ip_colocation_factor_whitelist(
+        &mut self,
+        whitelist: impl IntoIterator<Item = std::net::IpAddr>,
+    ) -> &mut Self {
+        self.params.ip_colocation_factor_whitelist = whitelist.into_iter().collect::<HashSet<_>>();
+        self
+    }
+
+    /// Sets the behavioural penalty, see [`PeerScoreParams::behaviour_penalty_weight`].
+    pub fn behaviour_penalty(&mut self, weight: f64, threshold: f64, decay: f64) -> &mut Self {
+        self.params.behaviour_penalty_weight = weight;
+        self.params.behaviour_penalty_threshold = threshold;
+        self.params.behaviour_penalty_decay = decay;
+        self
+    }
+
+    /// Sets the time to remember the score of disconnected peers.
+    pub fn retain_score(&mut self, retain_score: Duration) -> &mut Self {
+        self.params.retain_score = retain_score;
+        self
+    }
+
+    /// Replaces the preset thresholds.
+    pub fn thresholds(&mut self, thresholds: PeerScoreThresholds) -> &mut Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Validates the parameters and returns them, ready to be passed to
+    /// [`Behaviour::with_peer_score`](crate::Behaviour::with_peer_score).
+    pub fn build(&self) -> Result<(PeerScoreParams, PeerScoreThresholds), PeerScoreParamsError> {
+        self.params
+            .validate()
+            .map_err(PeerScoreParamsError::InvalidParams)?;
+        self.thresholds
+            .validate()
+            .map_err(PeerScoreParamsError::InvalidThresholds)?;
+
+        for (topic, params) in &self.params.topics {
+            if params.mesh_message_deliveries_weight == 0.0 {
+                continue;
+            }
+            if params.mesh_message_deliveries_threshold > params.mesh_message_deliveries_cap {
+                return Err(PeerScoreParamsError::MeshDeliveriesThresholdAboveCap {
+                    topic: topic.clone(),
+                });
+            }
+            if params.mesh_message_deliveries_window >= params.mesh_message_deliveries_activation {
+                return Err(
+                    PeerScoreParamsError::MeshDeliveriesWindowExceedsActivation {
+                        topic: topic.clone(),
+                    },
+                );
+            }
+        }
+
+        // Without an application specific score, topic scores are the only source of positive score.
+        if self.params.app_specific_weight == 0.0 && !self.params.topics.is_empty() {
+            let max_score = self.max_topic_score();
+
+            if self.thresholds.opportunistic_graft_threshold > max_score {
+                return Err(
+                    PeerScoreParamsError::UnattainableOpportunisticGraftThreshold {
+                        threshold: self.thresholds.opportunistic_graft_threshold,
+                        max_score,
+                    },
+                );
+            }
+        }
+
+        Ok((self.params.clone(), self.thresholds.clone()))
+    }
+
+    /// The maximum positive score a peer can attain through topic scores.
+    fn max_topic_score(&self) -> f64 {
+        let max_score = self
+            .params
+            .topics
+            .values()
+            .map(|p| {
+                p.topic_weight
+                    * (p.time_in_mesh_weight * p.time_in_mesh_cap
+                        + p.first_message_deliveries_weight * p.first_message_deliveries_cap)
+            })
+            .sum::<f64>();
+
+        if self.params.topic_score_cap > 0.0 {
+            max_score.min(self.params.topic_score_cap)
+        } else {
+            max_score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdentTopic;
+
+    const PROFILES: [ScoreProfile; 3] = [
+        ScoreProfile::LowLatencyChat,
+        ScoreProfile::BlockPropagation,
+        ScoreProfile::LargeFiles,
+    ];
+
+    fn topic() -> TopicHash {
+        IdentTopic::new("test").hash()
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for profile in PROFILES {
+            let (params, thresholds) = PeerScoreParamsBuilder::new(profile)
+                .topic(topic())
+                .build()
+                .unwrap();
+
+            assert!(params.topics.contains_key(&topic()));
+            assert!(thresholds.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_mesh_deliveries_threshold_above_cap() {
+        let params = TopicScoreParams {
+            mesh_message_deliveries_cap: 5.0,
+            mesh_message_deliveries_threshold: 10.0,
+            ..TopicScoreParams::default_for(ScoreProfile::BlockPropagation)
+        };
+
+        let error = PeerScoreParamsBuilder::new(ScoreProfile::BlockPropagation)
+            .topic_params(topic(), params)
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            PeerScoreParamsError::MeshDeliveriesThresholdAboveCap { topic: t } if t == topic()
+        ));
+    }
+
+    #[test]
+    fn rejects_unattainable_opportunistic_graft_threshold() {
+        let error = PeerScoreParamsBuilder::new(ScoreProfile::LowLatencyChat)
+            .topic(topic())
+            .app_specific_weight(0.0)
+            .topic_score_cap(1.0)
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            PeerScoreParamsError::UnattainableOpportunisticGraftThreshold { max_score, .. } if max_score == 1.0
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_thresholds() {
+        let error = PeerScoreParamsBuilder::new(ScoreProfile::LargeFiles)
+            .thresholds(PeerScoreThresholds {
+                gossip_threshold: 1.0,
+                ..PeerScoreThresholds::default_for(ScoreProfile::LargeFiles)
+            })
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, PeerScoreParamsError::InvalidThresholds(_)));
+    }
+}
@@ -469,10 +469,19 @@ impl Decoder for GossipsubCodec {
                 });
             }
 
+            let ack_msgs = rpc_control.ack.into_iter().map(|ack| ControlAction::Ack {
+                message_ids: ack
+                    .message_ids
+                    .into_iter()
+                    .map(MessageId::from)
+                    .collect::<Vec<_>>(),
+            });
+
             control_msgs.extend(ihave_msgs);
             control_msgs.extend(iwant_msgs);
             control_msgs.extend(graft_msgs);
             control_msgs.extend(prune_msgs);
+            control_msgs.extend(ack_msgs);
         }
 
         Ok(Some(HandlerEvent::Message {
@@ -0,0 +1,133 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Enforcement of the per-topic [`BandwidthQuota`]s on forwarded messages.
+
+use crate::config::{BandwidthQuota, QuotaAction};
+use crate::topic::TopicHash;
+use crate::types::RawMessage;
+use instant::Instant;
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+
+/// The outcome of [`TopicQuotas::admit`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// The message can be sent right away.
+    Send,
+    /// The message was queued and is returned by [`TopicQuotas::release`] later on.
+    Delayed,
+    /// The message must not be sent.
+    Dropped,
+}
+
+/// The state of the current quota interval of a topic.
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    bytes: u64,
+    /// Messages waiting for the next interval, with the sum of their sizes.
+    delayed: VecDeque<(PeerId, RawMessage)>,
+    delayed_bytes: u64,
+}
+
+/// Tracks the bytes forwarded on the topics having a [`BandwidthQuota`].
+#[derive(Debug, Default)]
+pub(crate) struct TopicQuotas {
+    windows: HashMap<TopicHash, Window>,
+}
+
+impl TopicQuotas {
+    /// Accounts for sending `message` to `peer` under the given quota.
+    pub(crate) fn admit(
+        &mut self,
+        quota: &BandwidthQuota,
+        peer: PeerId,
+        message: &RawMessage,
+        now: Instant,
+    ) -> Admission {
+        let bytes = message.raw_protobuf_len() as u64;
+        let window = self
+            .windows
+            .entry(message.topic.clone())
+            .or_insert_with(|| Window {
+                start: now,
+                bytes: 0,
+                delayed: VecDeque::new(),
+                delayed_bytes: 0,
+            });
+
+        if now.duration_since(window.start) >= quota.interval() && window.delayed.is_empty() {
+            window.start = now;
+            window.bytes = 0;
+        }
+
+        // Messages must not overtake the ones already waiting.
+        if window.delayed.is_empty() && window.bytes + bytes <= quota.max_bytes() {
+            window.bytes += bytes;
+            return Admission::Send;
+        }
+
+        match quota.action() {
+            QuotaAction::Delay if window.delayed_bytes + bytes <= quota.max_bytes() => {
+                window.delayed.push_back((peer, message.clone()));
+                window.delayed_bytes += bytes;
+                Admission::Delayed
+            }
+            QuotaAction::Delay | QuotaAction::Drop => Admission::Dropped,
+        }
+    }
+
+    /// Starts a new interval for the topics whose interval elapsed, returning the delayed
+    /// messages fitting into it.
+    pub(crate) fn release<'a>(
+        &mut self,
+        quotas: impl Fn(&TopicHash) -> Option<&'a BandwidthQuota>,
+        now: Instant,
+    ) -> Vec<(PeerId, RawMessage)> {
+        let mut released = Vec::new();
+
+        self.windows.retain(|topic, window| {
+            // The quota of a topic can't be removed, but be defensive anyway.
+            let Some(quota) = quotas(topic) else {
+                return false;
+            };
+            if now.duration_since(window.start) < quota.interval() {
+                return true;
+            }
+
+            window.start = now;
+            window.bytes = 0;
+            while let Some((_, message)) = window.delayed.front() {
+                let bytes = message.raw_protobuf_len() as u64;
+                if window.bytes + bytes > quota.max_bytes() {
+                    break;
+                }
+                window.bytes += bytes;
+                window.delayed_bytes -= bytes;
+                released.extend(window.delayed.pop_front());
+            }
+
+            true
+        });
+
+        released
+    }
+}
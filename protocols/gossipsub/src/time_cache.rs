@@ -22,11 +22,13 @@
 
 use fnv::FnvHashMap;
 use instant::Instant;
+use libp2p_swarm::Clock;
 use std::collections::hash_map::{
     self,
     Entry::{Occupied, Vacant},
 };
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::Duration;
 
 struct ExpiringElement<Element> {
@@ -44,6 +46,8 @@ pub(crate) struct TimeCache<Key, Value> {
     list: VecDeque<ExpiringElement<Key>>,
     /// The time elements remain in the cache.
     ttl: Duration,
+    /// The source of time for the expiry of elements.
+    clock: Arc<dyn Clock>,
 }
 
 pub(crate) struct OccupiedEntry<'a, K, V> {
@@ -93,13 +97,10 @@ impl<'a, K: 'a, V: 'a> Entry<'a, K, V>
 where
     K: Eq + std::hash::Hash + Clone,
 {
-    pub(crate) fn or_default(self) -> &'a mut V
-    where
-        V: Default,
-    {
+    pub(crate) fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
         match self {
             Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(entry) => entry.insert(V::default()),
+            Entry::Vacant(entry) => entry.insert(default()),
         }
     }
 }
@@ -108,11 +109,12 @@ impl<Key, Value> TimeCache<Key, Value>
 where
     Key: Eq + std::hash::Hash + Clone,
 {
-    pub(crate) fn new(ttl: Duration) -> Self {
+    pub(crate) fn new(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
         TimeCache {
             map: FnvHashMap::default(),
             list: VecDeque::new(),
             ttl,
+            clock,
         }
     }
 
@@ -131,7 +133,7 @@ where
     }
 
     pub(crate) fn entry(&mut self, key: Key) -> Entry<Key, Value> {
-        let now = Instant::now();
+        let now = self.clock.now();
         self.remove_expired_keys(now);
         match self.map.entry(key) {
             Occupied(entry) => Entry::Occupied(OccupiedEntry { entry }),
@@ -161,8 +163,8 @@ impl<Key> DuplicateCache<Key>
 where
     Key: Eq + std::hash::Hash + Clone,
 {
-    pub(crate) fn new(ttl: Duration) -> Self {
-        Self(TimeCache::new(ttl))
+    pub(crate) fn new(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self(TimeCache::new(ttl, clock))
     }
 
     // Inserts new elements and removes any expired elements.
@@ -186,10 +188,11 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use libp2p_swarm::{SystemClock, VirtualClock};
 
     #[test]
     fn cache_added_entries_exist() {
-        let mut cache = DuplicateCache::new(Duration::from_secs(10));
+        let mut cache = DuplicateCache::new(Duration::from_secs(10), Arc::new(SystemClock));
 
         cache.insert("t");
         cache.insert("e");
@@ -201,15 +204,16 @@ mod test {
 
     #[test]
     fn cache_entries_expire() {
-        let mut cache = DuplicateCache::new(Duration::from_millis(100));
+        let clock = Arc::new(VirtualClock::new());
+        let mut cache = DuplicateCache::new(Duration::from_millis(100), clock.clone());
 
         cache.insert("t");
         assert!(!cache.insert("t"));
         cache.insert("e");
         //assert!(!cache.insert("t"));
         assert!(!cache.insert("e"));
-        // sleep until cache expiry
-        std::thread::sleep(Duration::from_millis(101));
+        // advance until cache expiry
+        clock.advance(Duration::from_millis(101));
         // add another element to clear previous cache
         cache.insert("s");
 
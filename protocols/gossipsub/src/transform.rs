@@ -34,6 +34,10 @@ use crate::{Message, RawMessage, TopicHash};
 /// The inbound/outbound transforms must be inverses. Applying the inbound transform and then the
 /// outbound transform MUST leave the underlying data un-modified.
 ///
+/// The outbound transform is applied before a published message is signed, thus signatures cover
+/// the transformed data, whereas the [`crate::MessageId`] is always calculated from the
+/// untransformed data. A transform is set via [`crate::Behaviour::new_with_transform`].
+///
 /// By default, this is the identity transform for all fields in [`Message`].
 pub trait DataTransform {
     /// Takes a [`RawMessage`] received and converts it to a [`Message`].
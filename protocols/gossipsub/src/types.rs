@@ -26,6 +26,7 @@ use prometheus_client::encoding::EncodeLabelValue;
 use quick_protobuf::MessageWrite;
 use std::fmt;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use crate::rpc_proto::proto;
 #[cfg(feature = "serde")]
@@ -75,6 +76,8 @@ impl std::fmt::Debug for MessageId {
 pub(crate) struct PeerConnections {
     /// The kind of protocol the peer supports.
     pub(crate) kind: PeerKind,
+    /// Whether `kind` has been negotiated on one of the connections, rather than assumed.
+    pub(crate) kind_negotiated: bool,
     /// Its current connections.
     pub(crate) connections: Vec<ConnectionId>,
 }
@@ -194,6 +197,64 @@ pub enum SubscriptionAction {
     Unsubscribe,
 }
 
+/// Counters of the mesh membership changes and maintenance cost of a topic we are subscribed to.
+///
+/// The counters start when subscribing to the topic, see
+/// [`Behaviour::mesh_stats`](crate::Behaviour::mesh_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeshStats {
+    /// Number of peers added to the mesh, either by us or by a GRAFT of the remote.
+    pub peers_included: u64,
+    /// Number of peers removed from the mesh, for whatever reason.
+    pub peers_removed: u64,
+    /// Number of GRAFT control messages received for the topic.
+    pub grafts_received: u64,
+    /// Number of PRUNE control messages received for the topic.
+    pub prunes_received: u64,
+    /// Number of GRAFT control messages sent for the topic by the heartbeat.
+    pub heartbeat_grafts_sent: u64,
+    /// Number of PRUNE control messages sent for the topic by the heartbeat.
+    pub heartbeat_prunes_sent: u64,
+    /// Time spent maintaining the mesh of the topic in heartbeats.
+    pub heartbeat_time: Duration,
+}
+
+/// Counters of the gossip control messages of a peer that were limited to protect against spam.
+///
+/// See [`Behaviour::gossip_control_stats`](crate::Behaviour::gossip_control_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GossipControlStats {
+    /// Number of IHAVE messages ignored, either due to
+    /// [`Config::max_ihave_messages`](crate::Config::max_ihave_messages) or
+    /// [`Config::max_broken_promises`](crate::Config::max_broken_promises).
+    pub ihave_messages_ignored: u64,
+    /// Number of message ids advertised via IHAVE ignored due to
+    /// [`Config::max_ihave_message_ids`](crate::Config::max_ihave_message_ids).
+    pub ihave_message_ids_ignored: u64,
+    /// Number of messages requested via IWANT not sent due to
+    /// [`Config::max_iwant_responses`](crate::Config::max_iwant_responses).
+    pub iwant_responses_dropped: u64,
+    /// Number of messages the peer advertised and we requested, but which the peer did not
+    /// deliver in time.
+    pub broken_promises: u64,
+}
+
+/// Bytes of the messages of a topic, see [`Behaviour::topic_bandwidth`](crate::Behaviour::topic_bandwidth).
+///
+/// The bytes of a message sent to several peers count once per peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TopicBandwidth {
+    /// Bytes of the messages we published.
+    pub published: u64,
+    /// Bytes of the messages we forwarded, including responses to IWANT requests.
+    pub forwarded: u64,
+    /// Bytes of the messages we received, including duplicates.
+    pub received: u64,
+    /// Bytes of the messages not forwarded, respectively delayed, due to the
+    /// [`BandwidthQuota`](crate::BandwidthQuota) of the topic.
+    pub throttled: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PeerInfo {
     pub peer_id: Option<PeerId>,
@@ -231,6 +292,15 @@ pub enum ControlAction {
         /// The backoff time in seconds before we allow to reconnect
         backoff: Option<u64>,
     },
+    /// The node received the messages - Ack control message of the delivery receipts extension,
+    /// see [`ConfigBuilder::delivery_receipts`](crate::ConfigBuilder::delivery_receipts).
+    ///
+    /// Encoded with the field tag 100 of the control message, clear of the tags assigned by the
+    /// specification, so that peers without the extension ignore it as an unknown field.
+    Ack {
+        /// The ids of the received messages.
+        message_ids: Vec<MessageId>,
+    },
 }
 
 /// A Gossipsub RPC message sent.
@@ -254,6 +324,80 @@ impl RpcOut {
     pub fn into_protobuf(self) -> proto::RPC {
         self.into()
     }
+
+    /// Splits the RPC into RPCs whose protobuf encoding does not exceed `max_size` bytes.
+    ///
+    /// Control messages are split across RPCs by their message ids, respectively peers. If a
+    /// single message, message id or peer does not fit, the RPC containing it is returned as error.
+    pub(crate) fn fragment(self, max_size: usize) -> Result<Vec<RpcOut>, Box<RpcOut>> {
+        let size = match &self {
+            // The message is the only field of the RPC.
+            RpcOut::Publish(message) | RpcOut::Forward(message) => {
+                1 + quick_protobuf::sizeofs::sizeof_len(message.raw_protobuf_len())
+            }
+            _ => self.clone().into_protobuf().get_size(),
+        };
+        if size <= max_size {
+            return Ok(vec![self]);
+        }
+
+        let (first, second) = match self {
+            RpcOut::Control(ControlAction::IHave {
+                topic_hash,
+                mut message_ids,
+            }) if message_ids.len() > 1 => {
+                let rest = message_ids.split_off(message_ids.len() / 2);
+                (
+                    ControlAction::IHave {
+                        topic_hash: topic_hash.clone(),
+                        message_ids,
+                    },
+                    ControlAction::IHave {
+                        topic_hash,
+                        message_ids: rest,
+                    },
+                )
+            }
+            RpcOut::Control(ControlAction::IWant { mut message_ids }) if message_ids.len() > 1 => {
+                let rest = message_ids.split_off(message_ids.len() / 2);
+                (
+                    ControlAction::IWant { message_ids },
+                    ControlAction::IWant { message_ids: rest },
+                )
+            }
+            RpcOut::Control(ControlAction::Ack { mut message_ids }) if message_ids.len() > 1 => {
+                let rest = message_ids.split_off(message_ids.len() / 2);
+                (
+                    ControlAction::Ack { message_ids },
+                    ControlAction::Ack { message_ids: rest },
+                )
+            }
+            RpcOut::Control(ControlAction::Prune {
+                topic_hash,
+                mut peers,
+                backoff,
+            }) if peers.len() > 1 => {
+                let rest = peers.split_off(peers.len() / 2);
+                (
+                    ControlAction::Prune {
+                        topic_hash: topic_hash.clone(),
+                        peers,
+                        backoff,
+                    },
+                    ControlAction::Prune {
+                        topic_hash,
+                        peers: rest,
+                        backoff,
+                    },
+                )
+            }
+            rpc => return Err(Box::new(rpc)),
+        };
+
+        let mut fragments = RpcOut::Control(first).fragment(max_size)?;
+        fragments.extend(RpcOut::Control(second).fragment(max_size)?);
+        Ok(fragments)
+    }
 }
 
 impl From<RpcOut> for proto::RPC {
@@ -300,6 +444,7 @@ impl From<RpcOut> for proto::RPC {
                     iwant: vec![],
                     graft: vec![],
                     prune: vec![],
+                    ack: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::IWant { message_ids }) => proto::RPC {
@@ -312,6 +457,7 @@ impl From<RpcOut> for proto::RPC {
                     }],
                     graft: vec![],
                     prune: vec![],
+                    ack: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::Graft { topic_hash }) => proto::RPC {
@@ -324,6 +470,7 @@ impl From<RpcOut> for proto::RPC {
                         topic_id: Some(topic_hash.into_string()),
                     }],
                     prune: vec![],
+                    ack: vec![],
                 }),
             },
             RpcOut::Control(ControlAction::Prune {
@@ -350,9 +497,23 @@ impl From<RpcOut> for proto::RPC {
                                 .collect(),
                             backoff,
                         }],
+                        ack: vec![],
                     }),
                 }
             }
+            RpcOut::Control(ControlAction::Ack { message_ids }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: Vec::new(),
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![],
+                    ack: vec![proto::ControlAck {
+                        message_ids: message_ids.into_iter().map(|msg_id| msg_id.0).collect(),
+                    }],
+                }),
+            },
         }
     }
 }
@@ -411,6 +572,7 @@ impl From<Rpc> for proto::RPC {
             iwant: Vec::new(),
             graft: Vec::new(),
             prune: Vec::new(),
+            ack: Vec::new(),
         };
 
         let empty_control_msg = rpc.control_msgs.is_empty();
@@ -459,6 +621,12 @@ impl From<Rpc> for proto::RPC {
                     };
                     control.prune.push(rpc_prune);
                 }
+                ControlAction::Ack { message_ids } => {
+                    let rpc_ack = proto::ControlAck {
+                        message_ids: message_ids.into_iter().map(|msg_id| msg_id.0).collect(),
+                    };
+                    control.ack.push(rpc_ack);
+                }
             }
         }
 
@@ -499,6 +667,16 @@ impl PeerKind {
             Self::Gossipsubv1_1 => "Gossipsub v1.1",
         }
     }
+
+    /// Ranks the kinds by the features they support, higher being more capable.
+    pub(crate) fn rank(&self) -> u8 {
+        match self {
+            Self::NotSupported => 0,
+            Self::Floodsub => 1,
+            Self::Gossipsub => 2,
+            Self::Gossipsubv1_1 => 3,
+        }
+    }
 }
 
 impl AsRef<str> for PeerKind {
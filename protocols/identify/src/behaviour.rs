@@ -20,18 +20,21 @@
 
 use crate::handler::{self, Handler, InEvent};
 use crate::protocol::{Info, UpgradeError};
+use crate::rate_limiter::RateLimiters;
+use libp2p_allow_block_list::{self as allow_block_list, BlockedPeers};
 use libp2p_core::{multiaddr, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_identity::PublicKey;
 use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
 use libp2p_swarm::{
-    ConnectionDenied, DialError, ExternalAddresses, ListenAddresses, NetworkBehaviour,
-    NotifyHandler, PeerAddresses, StreamUpgradeError, THandlerInEvent, ToSwarm,
+    ConnectionDenied, DialError, ExternalAddrOrigin, ExternalAddresses, ListenAddresses,
+    NetworkBehaviour, NotifyHandler, PeerAddresses, StreamProtocol, StreamUpgradeError,
+    THandlerInEvent, ToSwarm,
 };
 use libp2p_swarm::{ConnectionId, THandler, THandlerOutEvent};
 
 use std::collections::hash_map::Entry;
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     task::Context,
@@ -43,7 +46,8 @@ use std::{
 /// about them, and answers identify queries from other nodes.
 ///
 /// All external addresses of the local node supposedly observed by remotes
-/// are reported via [`ToSwarm::NewExternalAddrCandidate`].
+/// are reported via [`ToSwarm::NewExternalAddrCandidate`], once enough distinct
+/// remotes reported them, see [`Config::observed_addr_min_observers`].
 pub struct Behaviour {
     config: Config,
     /// For each peer we're connected to, the observed address to send back to it.
@@ -51,6 +55,8 @@ pub struct Behaviour {
 
     /// The address a remote observed for us.
     our_observed_addresses: HashMap<ConnectionId, Multiaddr>,
+    /// The remote peer of each connection and the network it connects from.
+    observers: HashMap<ConnectionId, (PeerId, ObserverGroup)>,
 
     /// Pending events to be emitted when polled.
     events: VecDeque<ToSwarm<Event, InEvent>>,
@@ -59,6 +65,18 @@ pub struct Behaviour {
 
     listen_addresses: ListenAddresses,
     external_addresses: ExternalAddresses,
+
+    /// Rate limiters shared with all handlers.
+    rate_limiters: RateLimiters,
+
+    /// Denies and closes the connections to the peers in `quarantined`.
+    blocked: allow_block_list::Behaviour<BlockedPeers>,
+    /// The peers disconnected due to [`AgentVersionAction::Disconnect`], with their agent
+    /// version, least recently quarantined first.
+    ///
+    /// Bounded by [`Config::max_quarantined_peers`], releasing the least recently quarantined
+    /// peer first.
+    quarantined: VecDeque<(PeerId, String)>,
 }
 
 /// Configuration for the [`identify::Behaviour`](Behaviour).
@@ -97,6 +115,106 @@ pub struct Config {
     ///
     /// Disabled by default.
     pub cache_size: usize,
+
+    /// The rate at which identify requests of a single peer are answered,
+    /// across all connections to the peer.
+    ///
+    /// Requests exceeding the limit are dropped and reported via [`Event::RateLimitExceeded`].
+    ///
+    /// Defaults to a burst of 10 requests, replenished by one request every 30 seconds.
+    pub request_rate_limit: Option<RateLimit>,
+
+    /// The rate at which identify pushes of a single peer are accepted,
+    /// across all connections to the peer.
+    ///
+    /// Pushes exceeding the limit are dropped and reported via [`Event::RateLimitExceeded`].
+    ///
+    /// Defaults to a burst of 10 pushes, replenished by one push every 30 seconds.
+    pub push_rate_limit: Option<RateLimit>,
+
+    /// The number of distinct peers that need to report the same observed address before it
+    /// is reported as [`ToSwarm::NewExternalAddrCandidate`].
+    ///
+    /// Only peers connecting from distinct networks are counted, i.e. from distinct `/24`
+    /// subnets for IPv4 and distinct `/48` prefixes for IPv6. This prevents a single remote
+    /// from poisoning the external addresses of the local node and avoids promoting the
+    /// per-connection port mappings of a symmetric NAT.
+    ///
+    /// Defaults to 1, i.e. every observed address is reported.
+    pub observed_addr_min_observers: usize,
+
+    /// Rules applied to the agent version of identified peers, in order.
+    ///
+    /// The action of the first rule whose pattern matches the agent version is taken,
+    /// see [`AgentVersionAction`].
+    ///
+    /// Empty by default.
+    pub agent_version_rules: Vec<(AgentVersionPattern, AgentVersionAction)>,
+
+    /// The maximum number of peers quarantined due to [`AgentVersionAction::Disconnect`].
+    ///
+    /// Once reached, the least recently quarantined peer is released to make room for a new one.
+    ///
+    /// Defaults to 256.
+    pub max_quarantined_peers: NonZeroUsize,
+
+    /// The maximum number of listen addresses sent on relayed connections, i.e. connections
+    /// through a `/p2p-circuit` address.
+    ///
+    /// Relayed connections are usually constrained in the number of bytes they may transfer,
+    /// thus the compact identify information sent on them omits the listen addresses beyond
+    /// the limit, while still containing the protocol and agent version as well as the
+    /// supported protocols. `None` sends all listen addresses.
+    ///
+    /// Defaults to `None`.
+    pub relayed_listen_addrs_limit: Option<usize>,
+}
+
+/// A pattern matching the agent version of a peer, see [`Config::agent_version_rules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentVersionPattern {
+    /// Matches agent versions equal to the given string.
+    Exact(String),
+    /// Matches agent versions starting with the given string, e.g. `rust-libp2p/0.4`.
+    Prefix(String),
+    /// Matches agent versions containing the given string.
+    Contains(String),
+}
+
+impl AgentVersionPattern {
+    /// Whether the given agent version matches the pattern.
+    pub fn matches(&self, agent_version: &str) -> bool {
+        match self {
+            AgentVersionPattern::Exact(s) => agent_version == s,
+            AgentVersionPattern::Prefix(s) => agent_version.starts_with(s.as_str()),
+            AgentVersionPattern::Contains(s) => agent_version.contains(s.as_str()),
+        }
+    }
+}
+
+/// The action taken for a peer whose agent version matches an [`AgentVersionPattern`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentVersionAction {
+    /// Take no action, e.g. to exempt agent versions from a subsequent, broader rule.
+    None,
+    /// Report the peer via [`Event::AgentVersionMatched`].
+    Flag,
+    /// Report the peer via [`Event::AgentVersionMatched`], close all connections to it and deny
+    /// new connections to it until [`Behaviour::release_quarantined`] is called.
+    ///
+    /// The connections are denied with [`libp2p_allow_block_list::Blocked`].
+    Disconnect,
+}
+
+/// A rate limit using the token bucket algorithm.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    /// The maximum number of tokens in the bucket, i.e. the maximum burst.
+    pub limit: NonZeroU32,
+    /// The interval at which a single token is added to the bucket.
+    ///
+    /// A zero interval disables the rate limit.
+    pub interval: Duration,
 }
 
 impl Config {
@@ -110,6 +228,18 @@ impl Config {
             interval: Duration::from_secs(5 * 60),
             push_listen_addr_updates: false,
             cache_size: 100,
+            request_rate_limit: Some(RateLimit {
+                limit: NonZeroU32::new(10).expect("10 > 0"),
+                interval: Duration::from_secs(30),
+            }),
+            push_rate_limit: Some(RateLimit {
+                limit: NonZeroU32::new(10).expect("10 > 0"),
+                interval: Duration::from_secs(30),
+            }),
+            observed_addr_min_observers: 1,
+            agent_version_rules: Vec::new(),
+            max_quarantined_peers: NonZeroUsize::new(256).expect("256 > 0"),
+            relayed_listen_addrs_limit: None,
         }
     }
 
@@ -139,6 +269,62 @@ impl Config {
         self.cache_size = cache_size;
         self
     }
+
+    /// Configures the rate at which identify requests of a single peer are answered.
+    ///
+    /// `None` disables the rate limit.
+    pub fn with_request_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.request_rate_limit = limit;
+        self
+    }
+
+    /// Configures the rate at which identify pushes of a single peer are accepted.
+    ///
+    /// `None` disables the rate limit.
+    pub fn with_push_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.push_rate_limit = limit;
+        self
+    }
+
+    /// Configures the number of peers from distinct networks that need to report the same
+    /// observed address before it becomes an external address candidate.
+    pub fn with_observed_addr_min_observers(mut self, n: usize) -> Self {
+        self.observed_addr_min_observers = n;
+        self
+    }
+
+    /// Adds a rule for the agent version of identified peers, applied after the rules added
+    /// before.
+    pub fn with_agent_version_rule(
+        mut self,
+        pattern: AgentVersionPattern,
+        action: AgentVersionAction,
+    ) -> Self {
+        self.agent_version_rules.push((pattern, action));
+        self
+    }
+
+    /// Configures the maximum number of peers quarantined due to their agent version.
+    pub fn with_max_quarantined_peers(mut self, max: NonZeroUsize) -> Self {
+        self.max_quarantined_peers = max;
+        self
+    }
+
+    /// Configures the maximum number of listen addresses sent on relayed connections.
+    ///
+    /// `None` sends all listen addresses, like on direct connections.
+    pub fn with_relayed_listen_addrs_limit(mut self, limit: Option<usize>) -> Self {
+        self.relayed_listen_addrs_limit = limit;
+        self
+    }
+
+    /// Returns the action of the first rule matching the given agent version, if any.
+    fn agent_version_action(&self, agent_version: &str) -> Option<AgentVersionAction> {
+        self.agent_version_rules
+            .iter()
+            .find(|(pattern, _)| pattern.matches(agent_version))
+            .map(|(_, action)| *action)
+    }
 }
 
 impl Behaviour {
@@ -149,14 +335,74 @@ impl Behaviour {
             Some(size) => PeerCache::enabled(size),
         };
 
+        let rate_limiters = RateLimiters::new(config.request_rate_limit, config.push_rate_limit);
+
         Self {
             config,
+            rate_limiters,
             connected: HashMap::new(),
             our_observed_addresses: Default::default(),
+            observers: Default::default(),
             events: VecDeque::new(),
             discovered_peers,
             listen_addresses: Default::default(),
             external_addresses: Default::default(),
+            blocked: Default::default(),
+            quarantined: VecDeque::new(),
+        }
+    }
+
+    /// Lists the peers disconnected due to their agent version, see
+    /// [`AgentVersionAction::Disconnect`].
+    pub fn quarantined_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.quarantined.iter().map(|(peer, _)| peer)
+    }
+
+    /// Allows connections to a peer previously disconnected due to its agent version.
+    ///
+    /// Returns whether the peer was quarantined.
+    pub fn release_quarantined(&mut self, peer: &PeerId) -> bool {
+        let Some(index) = self.quarantined.iter().position(|(p, _)| p == peer) else {
+            return false;
+        };
+        self.quarantined.remove(index);
+        self.blocked.unblock_peer(*peer);
+        true
+    }
+
+    /// Releases the least recently quarantined peers beyond [`Config::max_quarantined_peers`].
+    fn enforce_max_quarantined_peers(&mut self) {
+        while self.quarantined.len() > self.config.max_quarantined_peers.get() {
+            let Some((released, _)) = self.quarantined.pop_front() else {
+                break;
+            };
+            tracing::debug!(peer=%released, "Quarantine is full, releasing peer");
+            self.blocked.unblock_peer(released);
+        }
+    }
+
+    /// Applies [`Config::agent_version_rules`] to an identified peer.
+    fn apply_agent_version_rules(&mut self, peer_id: PeerId, agent_version: &str) {
+        let action = match self.config.agent_version_action(agent_version) {
+            None | Some(AgentVersionAction::None) => return,
+            Some(action) => action,
+        };
+
+        tracing::debug!(peer=%peer_id, %agent_version, ?action, "Agent version matched rule");
+        self.events
+            .push_back(ToSwarm::GenerateEvent(Event::AgentVersionMatched {
+                peer_id,
+                agent_version: agent_version.to_owned(),
+                action,
+            }));
+
+        if action == AgentVersionAction::Disconnect {
+            self.quarantined.retain(|(peer, _)| *peer != peer_id);
+            self.quarantined
+                .push_back((peer_id, agent_version.to_owned()));
+            // Closes all connections to the peer.
+            self.blocked.block_peer(peer_id);
+            self.enforce_max_quarantined_peers();
         }
     }
 
@@ -179,6 +425,49 @@ impl Behaviour {
         }
     }
 
+    /// Updates the runtime-configurable parameters of the behaviour.
+    ///
+    /// The identify interval, protocol version, agent version, whether listen address updates
+    /// are pushed and the limit of listen addresses on relayed connections are taken from
+    /// `config` and propagated to the handlers of all established connections.
+    ///
+    /// The minimum number of observers of an address and the agent version rules apply to
+    /// subsequently reported addresses and identified peers. If more peers are quarantined than
+    /// the new [`Config::max_quarantined_peers`], the least recently quarantined ones are
+    /// released.
+    ///
+    /// The local public key, the cache size and the rate limits are fixed at construction and
+    /// are left unchanged.
+    pub fn set_config(&mut self, config: Config) {
+        self.config.interval = config.interval;
+        self.config.protocol_version = config.protocol_version;
+        self.config.agent_version = config.agent_version;
+        self.config.push_listen_addr_updates = config.push_listen_addr_updates;
+        self.config.relayed_listen_addrs_limit = config.relayed_listen_addrs_limit;
+        self.config.observed_addr_min_observers = config.observed_addr_min_observers;
+        self.config.agent_version_rules = config.agent_version_rules;
+        self.config.max_quarantined_peers = config.max_quarantined_peers;
+        self.enforce_max_quarantined_peers();
+
+        let change_events = self
+            .connected
+            .iter()
+            .flat_map(|(peer, map)| map.iter().map(|(id, addr)| (*peer, id, addr)))
+            .map(|(peer_id, connection_id, addr)| ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::One(*connection_id),
+                event: InEvent::ConfigChanged {
+                    interval: self.config.interval,
+                    protocol_version: self.config.protocol_version.clone(),
+                    agent_version: self.config.agent_version.clone(),
+                    listen_addrs_limit: self.listen_addrs_limit(addr),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        self.events.extend(change_events)
+    }
+
     fn on_connection_established(
         &mut self,
         ConnectionEstablished {
@@ -194,6 +483,8 @@ impl Behaviour {
             ConnectedPoint::Listener { send_back_addr, .. } => send_back_addr.clone(),
         };
 
+        self.observers
+            .insert(conn, (peer_id, ObserverGroup::new(peer_id, &addr)));
         self.connected
             .entry(peer_id)
             .or_default()
@@ -206,6 +497,40 @@ impl Behaviour {
         }
     }
 
+    /// Returns the number of peers from distinct networks that currently report the given
+    /// address as the observed address of the local node.
+    ///
+    /// See [`Config::observed_addr_min_observers`].
+    pub fn observed_addr_confirmations(&self, address: &Multiaddr) -> usize {
+        let mut peers = HashSet::new();
+        let mut groups = HashSet::new();
+
+        for (connection, _) in self
+            .our_observed_addresses
+            .iter()
+            .filter(|(_, observed)| *observed == address)
+        {
+            let Some((peer, group)) = self.observers.get(connection) else {
+                continue;
+            };
+            // Only count a single connection per peer.
+            if peers.insert(*peer) {
+                groups.insert(*group);
+            }
+        }
+
+        groups.len()
+    }
+
+    /// The maximum number of listen addresses to send on a connection to the given address.
+    fn listen_addrs_limit(&self, remote_addr: &Multiaddr) -> Option<usize> {
+        if is_relayed(remote_addr) {
+            self.config.relayed_listen_addrs_limit
+        } else {
+            None
+        }
+    }
+
     fn all_addresses(&self) -> HashSet<Multiaddr> {
         self.listen_addresses
             .iter()
@@ -221,11 +546,18 @@ impl NetworkBehaviour for Behaviour {
 
     fn handle_established_inbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         peer: PeerId,
-        _: &Multiaddr,
+        local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.blocked.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )?;
+
         Ok(Handler::new(
             self.config.interval,
             peer,
@@ -234,16 +566,25 @@ impl NetworkBehaviour for Behaviour {
             self.config.agent_version.clone(),
             remote_addr.clone(),
             self.all_addresses(),
-        ))
+        )
+        .with_rate_limiters(self.rate_limiters.clone())
+        .with_listen_addrs_limit(self.listen_addrs_limit(remote_addr)))
     }
 
     fn handle_established_outbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         peer: PeerId,
         addr: &Multiaddr,
-        _: Endpoint,
+        role_override: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.blocked.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+        )?;
+
         Ok(Handler::new(
             self.config.interval,
             peer,
@@ -252,7 +593,9 @@ impl NetworkBehaviour for Behaviour {
             self.config.agent_version.clone(),
             addr.clone(), // TODO: This is weird? That is the public address we dialed, shouldn't need to tell the other party?
             self.all_addresses(),
-        ))
+        )
+        .with_rate_limiters(self.rate_limiters.clone())
+        .with_listen_addrs_limit(self.listen_addrs_limit(addr)))
     }
 
     fn on_connection_handler_event(
@@ -273,6 +616,7 @@ impl NetworkBehaviour for Behaviour {
                         peer_id,
                         info: info.clone(),
                     }));
+                self.apply_agent_version_rules(peer_id, &info.agent_version);
 
                 if let Some(ref mut discovered_peers) = self.discovered_peers.0 {
                     for address in &info.listen_addrs {
@@ -288,11 +632,10 @@ impl NetworkBehaviour for Behaviour {
                 match self.our_observed_addresses.entry(id) {
                     Entry::Vacant(not_yet_observed) => {
                         not_yet_observed.insert(observed.clone());
-                        self.events
-                            .push_back(ToSwarm::NewExternalAddrCandidate(observed));
                     }
                     Entry::Occupied(already_observed) if already_observed.get() == &observed => {
                         // No-op, we already observed this address.
+                        return;
                     }
                     Entry::Occupied(mut already_observed) => {
                         tracing::info!(
@@ -302,10 +645,23 @@ impl NetworkBehaviour for Behaviour {
                         );
 
                         *already_observed.get_mut() = observed.clone();
-                        self.events
-                            .push_back(ToSwarm::NewExternalAddrCandidate(observed));
                     }
                 }
+
+                let confirmations = self.observed_addr_confirmations(&observed);
+                if confirmations < self.config.observed_addr_min_observers {
+                    tracing::debug!(
+                        address=%observed,
+                        %confirmations,
+                        "Not reporting observed address as candidate yet, too few observers",
+                    );
+                    return;
+                }
+
+                self.events.push_back(ToSwarm::NewExternalAddrCandidate {
+                    addresses: vec![observed],
+                    origin: ExternalAddrOrigin::Identify,
+                });
             }
             handler::Event::Identification => {
                 self.events
@@ -319,29 +675,53 @@ impl NetworkBehaviour for Behaviour {
                 self.events
                     .push_back(ToSwarm::GenerateEvent(Event::Error { peer_id, error }));
             }
+            handler::Event::RateLimitExceeded(protocol) => {
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::RateLimitExceeded {
+                        peer_id,
+                        protocol,
+                    }));
+            }
         }
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
         if let Some(event) = self.events.pop_front() {
             return Poll::Ready(event);
         }
 
+        if let Poll::Ready(event) = self.blocked.poll(cx) {
+            return Poll::Ready(
+                event
+                    .map_out(|v| void::unreachable(v))
+                    .map_in(|v| void::unreachable(v)),
+            );
+        }
+
         Poll::Pending
     }
 
     fn handle_pending_outbound_connection(
         &mut self,
-        _connection_id: ConnectionId,
+        connection_id: ConnectionId,
         maybe_peer: Option<PeerId>,
-        _addresses: &[Multiaddr],
-        _effective_role: Endpoint,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
     ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
         let peer = match maybe_peer {
             None => return Ok(vec![]),
             Some(peer) => peer,
         };
+        self.blocked.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )?;
 
         Ok(self.discovered_peers.get(&peer))
     }
@@ -394,6 +774,7 @@ impl NetworkBehaviour for Behaviour {
                 }
 
                 self.our_observed_addresses.remove(&connection_id);
+                self.observers.remove(&connection_id);
             }
             FromSwarm::DialFailure(DialFailure { peer_id, error, .. }) => {
                 if let (Some(peer_id), Some(cache), DialError::Transport(errors)) =
@@ -442,6 +823,28 @@ pub enum Event {
         /// The error that occurred.
         error: StreamUpgradeError<UpgradeError>,
     },
+    /// A peer exceeded the configured rate limit and its identify request or push was dropped.
+    ///
+    /// See [`Config::request_rate_limit`] and [`Config::push_rate_limit`].
+    /// Applications may want to penalize the peer, e.g. by disconnecting from it.
+    RateLimitExceeded {
+        /// The peer that exceeded the rate limit.
+        peer_id: PeerId,
+        /// Either [`PROTOCOL_NAME`](crate::PROTOCOL_NAME) or [`PUSH_PROTOCOL_NAME`](crate::PUSH_PROTOCOL_NAME).
+        protocol: StreamProtocol,
+    },
+    /// The agent version of an identified peer matched a rule with [`AgentVersionAction::Flag`]
+    /// or [`AgentVersionAction::Disconnect`].
+    ///
+    /// See [`Config::agent_version_rules`].
+    AgentVersionMatched {
+        /// The identified peer.
+        peer_id: PeerId,
+        /// The agent version the peer reported.
+        agent_version: String,
+        /// The action of the matching rule.
+        action: AgentVersionAction,
+    },
 }
 
 /// If there is a given peer_id in the multiaddr, make sure it is the same as
@@ -454,6 +857,39 @@ fn multiaddr_matches_peer_id(addr: &Multiaddr, peer_id: &PeerId) -> bool {
     true
 }
 
+/// Whether the given address is a relayed address, i.e. contains `/p2p-circuit`.
+fn is_relayed(addr: &Multiaddr) -> bool {
+    addr.iter()
+        .any(|p| matches!(p, multiaddr::Protocol::P2pCircuit))
+}
+
+/// The network a remote connects from, used to count distinct observers of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ObserverGroup {
+    /// The `/24` subnet of an IPv4 address.
+    Ip4([u8; 3]),
+    /// The `/48` prefix of an IPv6 address.
+    Ip6([u16; 3]),
+    /// The peer itself, for addresses without an IP, e.g. DNS addresses.
+    Peer(PeerId),
+}
+
+impl ObserverGroup {
+    fn new(peer: PeerId, addr: &Multiaddr) -> Self {
+        match addr.iter().next() {
+            Some(multiaddr::Protocol::Ip4(ip)) => {
+                let [a, b, c, _] = ip.octets();
+                ObserverGroup::Ip4([a, b, c])
+            }
+            Some(multiaddr::Protocol::Ip6(ip)) => {
+                let [a, b, c, ..] = ip.segments();
+                ObserverGroup::Ip6([a, b, c])
+            }
+            _ => ObserverGroup::Peer(peer),
+        }
+    }
+}
+
 struct PeerCache(Option<PeerAddresses>);
 
 impl PeerCache {
@@ -478,6 +914,77 @@ impl PeerCache {
 mod tests {
     use super::*;
 
+    #[test]
+    fn quarantine_is_bounded() {
+        let config = Config::new(
+            "/test/1.0.0".to_owned(),
+            libp2p_identity::Keypair::generate_ed25519().public(),
+        )
+        .with_agent_version_rule(
+            AgentVersionPattern::Prefix("bad".to_owned()),
+            AgentVersionAction::Disconnect,
+        )
+        .with_max_quarantined_peers(NonZeroUsize::new(1).unwrap());
+        let mut behaviour = Behaviour::new(config);
+        let first = PeerId::random();
+        let second = PeerId::random();
+
+        behaviour.apply_agent_version_rules(first, "bad/1.0.0");
+        behaviour.apply_agent_version_rules(second, "bad/2.0.0");
+
+        assert_eq!(
+            behaviour.quarantined_peers().collect::<Vec<_>>(),
+            vec![&second]
+        );
+        assert!(is_denied(&mut behaviour, second));
+        assert!(!is_denied(&mut behaviour, first));
+    }
+
+    #[test]
+    fn set_config_applies_quarantine_and_observer_settings() {
+        let key = libp2p_identity::Keypair::generate_ed25519().public();
+        let mut behaviour = Behaviour::new(Config::new("/test/1.0.0".to_owned(), key.clone()));
+        let first = PeerId::random();
+        let second = PeerId::random();
+
+        behaviour.apply_agent_version_rules(first, "bad/1.0.0");
+        assert!(behaviour.quarantined_peers().next().is_none());
+
+        let config = Config::new("/test/1.0.0".to_owned(), key.clone())
+            .with_agent_version_rule(
+                AgentVersionPattern::Prefix("bad".to_owned()),
+                AgentVersionAction::Disconnect,
+            )
+            .with_observed_addr_min_observers(3);
+        behaviour.set_config(config);
+        assert_eq!(behaviour.config.observed_addr_min_observers, 3);
+
+        behaviour.apply_agent_version_rules(first, "bad/1.0.0");
+        behaviour.apply_agent_version_rules(second, "bad/2.0.0");
+        assert_eq!(behaviour.quarantined_peers().count(), 2);
+
+        let config = Config::new("/test/1.0.0".to_owned(), key)
+            .with_max_quarantined_peers(NonZeroUsize::new(1).unwrap());
+        behaviour.set_config(config);
+        assert_eq!(
+            behaviour.quarantined_peers().collect::<Vec<_>>(),
+            vec![&second]
+        );
+        assert!(!is_denied(&mut behaviour, first));
+        assert!(is_denied(&mut behaviour, second));
+    }
+
+    fn is_denied(behaviour: &mut Behaviour, peer: PeerId) -> bool {
+        behaviour
+            .handle_pending_outbound_connection(
+                ConnectionId::new_unchecked(0),
+                Some(peer),
+                &[],
+                Endpoint::Dialer,
+            )
+            .is_err()
+    }
+
     #[test]
     fn check_multiaddr_matches_peer_id() {
         let peer_id = PeerId::random();
@@ -499,4 +1006,31 @@ mod tests {
         ));
         assert!(multiaddr_matches_peer_id(&addr_without_peer_id, &peer_id));
     }
+
+    #[test]
+    fn observer_groups() {
+        let peer = PeerId::random();
+        let group = |addr: &str| ObserverGroup::new(peer, &addr.parse().unwrap());
+
+        assert_eq!(
+            group("/ip4/147.75.69.143/tcp/4001"),
+            group("/ip4/147.75.69.1/udp/4001/quic-v1")
+        );
+        assert_ne!(
+            group("/ip4/147.75.69.143/tcp/4001"),
+            group("/ip4/147.75.70.143/tcp/4001")
+        );
+        assert_eq!(
+            group("/ip6/2604:1380:1000:6000::1/tcp/4001"),
+            group("/ip6/2604:1380:1000:ffff::1/tcp/4001")
+        );
+        assert_ne!(
+            group("/ip6/2604:1380:1000:6000::1/tcp/4001"),
+            group("/ip6/2604:1380:1001:6000::1/tcp/4001")
+        );
+        assert_eq!(
+            group("/dns4/example.com/tcp/4001"),
+            ObserverGroup::Peer(peer)
+        );
+    }
 }
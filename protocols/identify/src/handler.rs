@@ -19,13 +19,14 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::protocol::{Info, PushInfo, UpgradeError};
+use crate::rate_limiter::RateLimiters;
 use crate::{protocol, PROTOCOL_NAME, PUSH_PROTOCOL_NAME};
 use either::Either;
 use futures::prelude::*;
 use futures_bounded::Timeout;
 use futures_timer::Delay;
 use libp2p_core::upgrade::{ReadyUpgrade, SelectUpgrade};
-use libp2p_core::Multiaddr;
+use libp2p_core::{multiaddr::Protocol, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_identity::PublicKey;
 use libp2p_swarm::handler::{
@@ -91,6 +92,12 @@ pub struct Handler {
     local_supported_protocols: SupportedProtocols,
     remote_supported_protocols: HashSet<StreamProtocol>,
     external_addresses: HashSet<Multiaddr>,
+
+    /// Per-peer rate limits on inbound requests and pushes.
+    rate_limiters: RateLimiters,
+
+    /// The maximum number of listen addresses sent to the remote, if any.
+    listen_addrs_limit: Option<usize>,
 }
 
 /// An event from `Behaviour` with the information requested by the `Handler`.
@@ -98,6 +105,13 @@ pub struct Handler {
 pub enum InEvent {
     AddressesChanged(HashSet<Multiaddr>),
     Push,
+    /// The runtime-configurable parameters of the [`Config`](crate::Config) changed.
+    ConfigChanged {
+        interval: Duration,
+        protocol_version: String,
+        agent_version: String,
+        listen_addrs_limit: Option<usize>,
+    },
 }
 
 /// Event produced by the `Handler`.
@@ -112,6 +126,8 @@ pub enum Event {
     IdentificationPushed(Info),
     /// Failed to identify the remote, or to reply to an identification request.
     IdentificationError(StreamUpgradeError<UpgradeError>),
+    /// We dropped an inbound stream of the given protocol because the remote exceeded its rate limit.
+    RateLimitExceeded(StreamProtocol),
 }
 
 impl Handler {
@@ -143,9 +159,24 @@ impl Handler {
             remote_supported_protocols: HashSet::default(),
             remote_info: Default::default(),
             external_addresses,
+            rate_limiters: RateLimiters::default(),
+            listen_addrs_limit: None,
         }
     }
 
+    /// Applies the given rate limits to inbound requests and pushes.
+    pub(crate) fn with_rate_limiters(mut self, rate_limiters: RateLimiters) -> Self {
+        self.rate_limiters = rate_limiters;
+        self
+    }
+
+    /// Limits the number of listen addresses sent to the remote, see
+    /// [`Config::relayed_listen_addrs_limit`](crate::Config::relayed_listen_addrs_limit).
+    pub(crate) fn with_listen_addrs_limit(mut self, limit: Option<usize>) -> Self {
+        self.listen_addrs_limit = limit;
+        self
+    }
+
     fn on_fully_negotiated_inbound(
         &mut self,
         FullyNegotiatedInbound {
@@ -157,6 +188,17 @@ impl Handler {
     ) {
         match output {
             future::Either::Left(stream) => {
+                if !self.rate_limiters.try_request(self.remote_peer_id) {
+                    tracing::debug!(
+                        peer=%self.remote_peer_id,
+                        "Dropping inbound identify stream because peer exceeded its rate limit"
+                    );
+                    self.events.push(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::RateLimitExceeded(PROTOCOL_NAME),
+                    ));
+                    return;
+                }
+
                 let info = self.build_info();
 
                 if self
@@ -172,6 +214,17 @@ impl Handler {
                 }
             }
             future::Either::Right(stream) => {
+                if !self.rate_limiters.try_push(self.remote_peer_id) {
+                    tracing::debug!(
+                        peer=%self.remote_peer_id,
+                        "Dropping inbound identify push stream because peer exceeded its rate limit"
+                    );
+                    self.events.push(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::RateLimitExceeded(PUSH_PROTOCOL_NAME),
+                    ));
+                    return;
+                }
+
                 if self
                     .active_streams
                     .try_push(protocol::recv_push(stream).map_ok(Success::ReceivedIdentifyPush))
@@ -227,12 +280,28 @@ impl Handler {
             public_key: self.public_key.clone(),
             protocol_version: self.protocol_version.clone(),
             agent_version: self.agent_version.clone(),
-            listen_addrs: Vec::from_iter(self.external_addresses.iter().cloned()),
+            listen_addrs: self.listen_addrs(),
             protocols: Vec::from_iter(self.local_supported_protocols.iter().cloned()),
             observed_addr: self.observed_addr.clone(),
         }
     }
 
+    /// The listen addresses to send to the remote, limited to `listen_addrs_limit`.
+    ///
+    /// When limited, direct addresses are preferred over relayed ones and the addresses are sorted
+    /// so that the same subset is sent on every identify.
+    fn listen_addrs(&self) -> Vec<Multiaddr> {
+        let mut addrs = Vec::from_iter(self.external_addresses.iter().cloned());
+        if let Some(limit) = self.listen_addrs_limit {
+            addrs.sort_by(|a, b| {
+                let relayed = |addr: &Multiaddr| addr.iter().any(|p| p == Protocol::P2pCircuit);
+                relayed(a).cmp(&relayed(b)).then_with(|| a.cmp(b))
+            });
+            addrs.truncate(limit);
+        }
+        addrs
+    }
+
     fn handle_incoming_info(&mut self, info: &Info) {
         self.remote_info.replace(info.clone());
 
@@ -311,6 +380,20 @@ impl ConnectionHandler for Handler {
                         ),
                     });
             }
+            InEvent::ConfigChanged {
+                interval,
+                protocol_version,
+                agent_version,
+                listen_addrs_limit,
+            } => {
+                if self.exchanged_one_periodic_identify {
+                    self.trigger_next_identify.reset(interval);
+                }
+                self.interval = interval;
+                self.protocol_version = protocol_version;
+                self.agent_version = agent_version;
+                self.listen_addrs_limit = listen_addrs_limit;
+            }
         }
     }
 
@@ -440,3 +523,65 @@ enum Success {
     SentIdentifyPush(Info),
     ReceivedIdentifyPush(PushInfo),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listen_addrs_limit_truncates_sent_addresses() {
+        let addresses = (0..5)
+            .map(|port| format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap())
+            .collect::<HashSet<Multiaddr>>();
+        let new_handler = || {
+            Handler::new(
+                Duration::from_secs(60),
+                PeerId::random(),
+                libp2p_identity::Keypair::generate_ed25519().public(),
+                "test".to_owned(),
+                "test".to_owned(),
+                Multiaddr::empty(),
+                addresses.clone(),
+            )
+        };
+
+        assert_eq!(new_handler().build_info().listen_addrs.len(), 5);
+
+        let info = new_handler().with_listen_addrs_limit(Some(2)).build_info();
+        assert_eq!(info.listen_addrs.len(), 2);
+        assert!(info.listen_addrs.iter().all(|a| addresses.contains(a)));
+        assert_eq!(info.protocol_version, "test");
+
+        let info = new_handler().with_listen_addrs_limit(Some(0)).build_info();
+        assert!(info.listen_addrs.is_empty());
+    }
+
+    #[test]
+    fn listen_addrs_limit_prefers_direct_addresses() {
+        let relayed: Multiaddr = "/ip4/1.1.1.1/tcp/1/p2p/12D3KooWPjceQrSwdWXPyLLeABRXmuqt69Rg3sBYbU1Nft9HyQ6X/p2p-circuit"
+            .parse()
+            .unwrap();
+        let direct: Vec<Multiaddr> = (0..3)
+            .map(|port| format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap())
+            .collect();
+        let addresses = direct
+            .iter()
+            .cloned()
+            .chain([relayed])
+            .collect::<HashSet<Multiaddr>>();
+
+        let info = Handler::new(
+            Duration::from_secs(60),
+            PeerId::random(),
+            libp2p_identity::Keypair::generate_ed25519().public(),
+            "test".to_owned(),
+            "test".to_owned(),
+            Multiaddr::empty(),
+            addresses,
+        )
+        .with_listen_addrs_limit(Some(2))
+        .build_info();
+
+        assert_eq!(info.listen_addrs, direct[..2]);
+    }
+}
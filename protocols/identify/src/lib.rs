@@ -41,12 +41,15 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
-pub use self::behaviour::{Behaviour, Config, Event};
+pub use self::behaviour::{
+    AgentVersionAction, AgentVersionPattern, Behaviour, Config, Event, RateLimit,
+};
 pub use self::protocol::{Info, UpgradeError, PROTOCOL_NAME, PUSH_PROTOCOL_NAME};
 
 mod behaviour;
 mod handler;
 mod protocol;
+mod rate_limiter;
 
 mod proto {
     #![allow(unreachable_pub)]
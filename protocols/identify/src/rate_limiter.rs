@@ -0,0 +1,216 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::behaviour::RateLimit;
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Per-peer rate limiters, shared by all connection handlers of a [`Behaviour`](crate::Behaviour).
+///
+/// Sharing the limiters ensures a peer can't circumvent them by opening multiple connections.
+#[derive(Clone, Default)]
+pub(crate) struct RateLimiters {
+    requests: Option<Arc<Mutex<RateLimiter>>>,
+    pushes: Option<Arc<Mutex<RateLimiter>>>,
+}
+
+impl RateLimiters {
+    /// Creates the rate limiters for the given limits.
+    ///
+    /// A limit with a zero interval refills instantly and is thus treated as no limit.
+    pub(crate) fn new(requests: Option<RateLimit>, pushes: Option<RateLimit>) -> Self {
+        let limiter = |limit: Option<RateLimit>| {
+            limit
+                .filter(|l| !l.interval.is_zero())
+                .map(|l| Arc::new(Mutex::new(RateLimiter::new(l))))
+        };
+
+        Self {
+            requests: limiter(requests),
+            pushes: limiter(pushes),
+        }
+    }
+
+    /// Whether we may answer another identify request of the given peer.
+    pub(crate) fn try_request(&self, peer: PeerId) -> bool {
+        try_next(self.requests.as_ref(), peer)
+    }
+
+    /// Whether we may accept another identify push of the given peer.
+    pub(crate) fn try_push(&self, peer: PeerId) -> bool {
+        try_next(self.pushes.as_ref(), peer)
+    }
+}
+
+fn try_next(limiter: Option<&Arc<Mutex<RateLimiter>>>, peer: PeerId) -> bool {
+    match limiter {
+        Some(limiter) => limiter
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .try_next(peer, Instant::now()),
+        None => true,
+    }
+}
+
+/// Rate limiter using the [Token Bucket] algorithm, with one bucket per peer.
+///
+/// [Token Bucket]: https://en.wikipedia.org/wiki/Token_bucket
+struct RateLimiter {
+    limit: u32,
+    interval: Duration,
+
+    refill_schedule: VecDeque<(Instant, PeerId)>,
+    buckets: HashMap<PeerId, u32>,
+}
+
+impl RateLimiter {
+    fn new(RateLimit { limit, interval }: RateLimit) -> Self {
+        debug_assert!(!interval.is_zero());
+
+        Self {
+            limit: limit.into(),
+            interval,
+            refill_schedule: Default::default(),
+            buckets: Default::default(),
+        }
+    }
+
+    fn try_next(&mut self, peer: PeerId, now: Instant) -> bool {
+        self.refill(now);
+
+        match self.buckets.get_mut(&peer) {
+            Some(balance) => match balance.checked_sub(1) {
+                Some(a) => {
+                    *balance = a;
+                    true
+                }
+                None => false,
+            },
+            // A missing bucket is equivalent to a full bucket.
+            None => {
+                self.buckets.insert(peer, self.limit - 1);
+                self.refill_schedule.push_back((now, peer));
+                true
+            }
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        // Items in `refill_schedule` are sorted, thus, if the first ain't ready, none of them are.
+        while let Some((last_refill, _)) = self.refill_schedule.front() {
+            if now.duration_since(*last_refill) < self.interval {
+                return;
+            }
+
+            let (last_refill, peer) = self
+                .refill_schedule
+                .pop_front()
+                .expect("Queue not to be empty.");
+            let balance = self
+                .buckets
+                .get(&peer)
+                .expect("Entry can only be removed via refill.");
+
+            let new_tokens = now
+                .duration_since(last_refill)
+                .as_micros()
+                .checked_div(self.interval.as_micros())
+                .and_then(|i| i.try_into().ok())
+                .unwrap_or(u32::MAX);
+            let new_balance = balance.saturating_add(new_tokens);
+
+            if new_balance < self.limit {
+                self.buckets.insert(peer, new_balance);
+                self.refill_schedule.push_back((now, peer));
+            } else {
+                self.buckets.remove(&peer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            limit: NonZeroU32::new(3).unwrap(),
+            interval: Duration::from_secs(1),
+        })
+    }
+
+    #[test]
+    fn limits_per_peer() {
+        let now = Instant::now();
+        let mut l = limiter();
+        let peer = PeerId::random();
+
+        for _ in 0..3 {
+            assert!(l.try_next(peer, now));
+        }
+        assert!(!l.try_next(peer, now));
+
+        // Other peers are not affected.
+        assert!(l.try_next(PeerId::random(), now));
+    }
+
+    #[test]
+    fn refills() {
+        let now = Instant::now();
+        let mut l = limiter();
+        let peer = PeerId::random();
+
+        for _ in 0..3 {
+            assert!(l.try_next(peer, now));
+        }
+        assert!(!l.try_next(peer, now));
+
+        let later = now + Duration::from_secs(1);
+        assert!(l.try_next(peer, later));
+        assert!(!l.try_next(peer, later));
+
+        // Buckets are refilled up to the limit at most.
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(l.try_next(peer, much_later));
+        }
+        assert!(!l.try_next(peer, much_later));
+    }
+
+    #[test]
+    fn zero_interval_is_no_limit() {
+        let limit = RateLimit {
+            limit: NonZeroU32::new(1).unwrap(),
+            interval: Duration::ZERO,
+        };
+        let limiters = RateLimiters::new(Some(limit), Some(limit));
+        let peer = PeerId::random();
+
+        for _ in 0..10 {
+            assert!(limiters.try_request(peer));
+            assert!(limiters.try_push(peer));
+        }
+    }
+}
@@ -5,6 +5,7 @@ use libp2p_swarm::{Swarm, SwarmEvent};
 use libp2p_swarm_test::SwarmExt;
 use std::collections::HashSet;
 use std::iter;
+use std::num::NonZeroU32;
 use std::time::{Duration, Instant};
 use tracing_subscriber::EnvFilter;
 
@@ -140,7 +141,7 @@ async fn only_emits_address_candidate_once_per_connection() {
     let external_address_candidates = swarm_events
         .iter()
         .filter_map(|e| match e {
-            SwarmEvent::NewExternalAddrCandidate { address } => Some(address.clone()),
+            SwarmEvent::NewExternalAddrCandidate { address, .. } => Some(address.clone()),
             _ => None,
         })
         .collect::<Vec<_>>();
@@ -156,6 +157,63 @@ async fn only_emits_address_candidate_once_per_connection() {
     );
 }
 
+#[async_std::test]
+async fn requires_distinct_observers_for_address_candidate() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut listener = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_observed_addr_min_observers(2),
+        )
+    });
+    let new_dialer = || {
+        Swarm::new_ephemeral(|identity| {
+            identify::Behaviour::new(identify::Config::new("a".to_string(), identity.public()))
+        })
+    };
+    let mut dialer1 = new_dialer();
+    let mut dialer2 = new_dialer();
+
+    listener.listen().with_memory_addr_external().await;
+
+    dialer1.connect(&mut listener).await;
+    async_std::task::spawn(dialer1.loop_on_next());
+
+    let observed = loop {
+        match listener.next_swarm_event().await {
+            SwarmEvent::Behaviour(identify::Event::Received { info, .. }) => {
+                break info.observed_addr
+            }
+            SwarmEvent::NewExternalAddrCandidate { .. } => {
+                panic!("Observed address should not be a candidate with a single observer")
+            }
+            _ => {}
+        }
+    };
+    assert_eq!(
+        listener.behaviour().observed_addr_confirmations(&observed),
+        1
+    );
+
+    dialer2.connect(&mut listener).await;
+    async_std::task::spawn(dialer2.loop_on_next());
+
+    let candidate = listener
+        .wait(|e| match e {
+            SwarmEvent::NewExternalAddrCandidate { address, .. } => Some(address),
+            _ => None,
+        })
+        .await;
+    assert_eq!(candidate, observed);
+    assert_eq!(
+        listener.behaviour().observed_addr_confirmations(&observed),
+        2
+    );
+}
+
 #[async_std::test]
 async fn emits_unique_listen_addresses() {
     let _ = tracing_subscriber::fmt()
@@ -280,6 +338,107 @@ async fn identify_push() {
     assert!(swarm1_received_info.listen_addrs.is_empty());
 }
 
+#[async_std::test]
+async fn set_config_updates_established_connections() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("a".to_string(), identity.public()))
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_agent_version("b".to_string()),
+        )
+    });
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    // First, let the periodic identify do its thing.
+    let ([_, _], [_, _]): ([identify::Event; 2], [identify::Event; 2]) =
+        libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+    // Second, update the configuration and push it to the established connection.
+    // The local public key is fixed at construction and not affected by the update.
+    let other_key = libp2p_identity::Keypair::generate_ed25519().public();
+    swarm2.behaviour_mut().set_config(
+        identify::Config::new("c".to_string(), other_key).with_agent_version("d".to_string()),
+    );
+    swarm2
+        .behaviour_mut()
+        .push(iter::once(*swarm1.local_peer_id()));
+
+    let swarm1_received_info = match libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await {
+        ([identify::Event::Received { info, .. }], [identify::Event::Pushed { .. }]) => info,
+        other => panic!("Unexpected events: {other:?}"),
+    };
+
+    assert_eq!(
+        swarm1_received_info.public_key.to_peer_id(),
+        *swarm2.local_peer_id()
+    );
+    assert_eq!(swarm1_received_info.protocol_version, "c");
+    assert_eq!(swarm1_received_info.agent_version, "d");
+}
+
+#[async_std::test]
+async fn identify_push_rate_limit() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public()).with_push_rate_limit(Some(
+                identify::RateLimit {
+                    limit: NonZeroU32::new(1).unwrap(),
+                    interval: Duration::from_secs(60),
+                },
+            )),
+        )
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(identify::Config::new("a".to_string(), identity.public()))
+    });
+    let swarm2_peer_id = *swarm2.local_peer_id();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    // First, let the periodic identify do its thing.
+    let _: ([identify::Event; 2], [identify::Event; 2]) =
+        libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+    // Second, push twice, exceeding the rate limit.
+    swarm2
+        .behaviour_mut()
+        .push(iter::once(*swarm1.local_peer_id()));
+    swarm2
+        .behaviour_mut()
+        .push(iter::once(*swarm1.local_peer_id()));
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let mut received = false;
+    let mut rate_limited = false;
+    while !(received && rate_limited) {
+        match swarm1.next_behaviour_event().await {
+            identify::Event::Received { peer_id, .. } if !received => {
+                assert_eq!(peer_id, swarm2_peer_id);
+                received = true;
+            }
+            identify::Event::RateLimitExceeded { peer_id, protocol } if !rate_limited => {
+                assert_eq!(peer_id, swarm2_peer_id);
+                assert_eq!(protocol, identify::PUSH_PROTOCOL_NAME);
+                rate_limited = true;
+            }
+            other => panic!("Unexpected event: {other:?}"),
+        }
+    }
+}
+
 #[async_std::test]
 async fn discover_peer_after_disconnect() {
     let _ = tracing_subscriber::fmt()
@@ -372,3 +531,88 @@ async fn configured_interval_starts_after_first_identify() {
 
     assert!(time_to_first_identify < identify_interval)
 }
+
+#[async_std::test]
+async fn disconnect_peer_matching_agent_version_rule() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_agent_version_rule(
+                    identify::AgentVersionPattern::Exact("bad-client/1.0.1".to_string()),
+                    identify::AgentVersionAction::None,
+                )
+                .with_agent_version_rule(
+                    identify::AgentVersionPattern::Prefix("bad-client/1.0".to_string()),
+                    identify::AgentVersionAction::Disconnect,
+                ),
+        )
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        identify::Behaviour::new(
+            identify::Config::new("a".to_string(), identity.public())
+                .with_agent_version("bad-client/1.0.0".to_string()),
+        )
+    });
+    let swarm2_peer_id = *swarm2.local_peer_id();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let (agent_version, action) = swarm1
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(identify::Event::AgentVersionMatched {
+                peer_id,
+                agent_version,
+                action,
+            }) => {
+                assert_eq!(peer_id, swarm2_peer_id);
+                Some((agent_version, action))
+            }
+            _ => None,
+        })
+        .await;
+    assert_eq!(agent_version, "bad-client/1.0.0");
+    assert_eq!(action, identify::AgentVersionAction::Disconnect);
+
+    swarm1
+        .wait(|event| match event {
+            SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                assert_eq!(peer_id, swarm2_peer_id);
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+    assert_eq!(
+        swarm1.behaviour().quarantined_peers().collect::<Vec<_>>(),
+        vec![&swarm2_peer_id]
+    );
+
+    // New connections to the quarantined peer are denied.
+    match swarm1.dial(swarm2_peer_id) {
+        Err(libp2p_swarm::DialError::Denied { cause }) => {
+            assert!(cause.downcast::<libp2p_allow_block_list::Blocked>().is_ok());
+        }
+        other => panic!("Unexpected dial result: {other:?}"),
+    }
+
+    assert!(swarm1.behaviour_mut().release_quarantined(&swarm2_peer_id));
+    assert_eq!(swarm1.behaviour().quarantined_peers().count(), 0);
+}
+
+#[test]
+fn agent_version_patterns() {
+    use identify::AgentVersionPattern::*;
+
+    assert!(Exact("a/1.0".to_string()).matches("a/1.0"));
+    assert!(!Exact("a/1.0".to_string()).matches("a/1.0.1"));
+    assert!(Prefix("a/1.".to_string()).matches("a/1.0.1"));
+    assert!(!Prefix("a/1.".to_string()).matches("b/a/1.0"));
+    assert!(Contains("/1.".to_string()).matches("b/a/1.0"));
+    assert!(!Contains("/2.".to_string()).matches("b/a/1.0"));
+}
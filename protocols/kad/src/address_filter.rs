@@ -0,0 +1,156 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::{Mode, ProviderRecord};
+use libp2p_core::{multiaddr::Protocol, transport::global_only, Multiaddr};
+use libp2p_identity::PeerId;
+use std::{fmt, sync::Arc};
+
+/// The configurable filtering of the addresses of remote peers.
+///
+/// The filter is applied whenever an address is inserted into the routing table,
+/// i.e. via [`Behaviour::add_address`](crate::Behaviour::add_address) or on established
+/// connections, and to the addresses of the peers returned in responses to `FIND_NODE`
+/// requests. Peers without any remaining address are not inserted, respectively returned.
+///
+/// An address is considered public unless it starts with a private, loopback, link-local or
+/// otherwise non-global IP address. Addresses not starting with an IP address, e.g. DNS
+/// addresses, are considered public.
+#[derive(Clone, Default)]
+pub enum AddressFilter {
+    /// Only public addresses while the local node is in [`Mode::Server`], all addresses
+    /// in [`Mode::Client`].
+    #[default]
+    PublicInServerMode,
+    /// Only public addresses.
+    PublicOnly,
+    /// All addresses.
+    All,
+    /// Only addresses for which the given function returns `true`.
+    Custom(Arc<dyn Fn(&PeerId, &Multiaddr) -> bool + Send + Sync>),
+}
+
+impl AddressFilter {
+    /// Creates an [`AddressFilter::Custom`] from the given function.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&PeerId, &Multiaddr) -> bool + Send + Sync + 'static,
+    {
+        AddressFilter::Custom(Arc::new(f))
+    }
+
+    /// Whether the address of the given peer passes the filter while the local node is in `mode`.
+    pub(crate) fn allows(&self, peer: &PeerId, address: &Multiaddr, mode: Mode) -> bool {
+        match self {
+            AddressFilter::PublicInServerMode => mode == Mode::Client || is_public(address),
+            AddressFilter::PublicOnly => is_public(address),
+            AddressFilter::All => true,
+            AddressFilter::Custom(f) => f(peer, address),
+        }
+    }
+}
+
+impl fmt::Debug for AddressFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressFilter::PublicInServerMode => f.write_str("PublicInServerMode"),
+            AddressFilter::PublicOnly => f.write_str("PublicOnly"),
+            AddressFilter::All => f.write_str("All"),
+            AddressFilter::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// An application-provided filter for inbound provider records,
+/// see [`Config::set_provider_filter`](crate::Config::set_provider_filter).
+#[derive(Clone)]
+pub(crate) struct ProviderFilter(Arc<dyn Fn(&mut ProviderRecord) -> bool + Send + Sync>);
+
+impl ProviderFilter {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&mut ProviderRecord) -> bool + Send + Sync + 'static,
+    {
+        ProviderFilter(Arc::new(f))
+    }
+
+    /// Whether to accept the given record, possibly after modifying its addresses.
+    pub(crate) fn accepts(&self, record: &mut ProviderRecord) -> bool {
+        (self.0)(record)
+    }
+}
+
+impl fmt::Debug for ProviderFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProviderFilter")
+    }
+}
+
+/// Whether the address does not start with an IP address that is private, loopback, link-local
+/// or otherwise reserved, see [`global_only::is_global`].
+fn is_public(address: &Multiaddr) -> bool {
+    match address.iter().next() {
+        Some(Protocol::Ip4(ip)) => global_only::is_global(ip.into()),
+        Some(Protocol::Ip6(ip)) => global_only::is_global(ip.into()),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_addresses() {
+        for (address, public) in [
+            ("/ip4/8.8.8.8/tcp/4001", true),
+            ("/ip4/127.0.0.1/tcp/4001", false),
+            ("/ip4/192.168.1.10/udp/4001/quic-v1", false),
+            ("/ip4/10.0.0.1/tcp/4001", false),
+            ("/ip4/100.64.0.1/tcp/4001", false),
+            ("/ip4/240.0.0.1/tcp/4001", false),
+            ("/ip4/224.0.0.1/udp/4001", false),
+            ("/ip6/2606:4700::1111/tcp/4001", true),
+            ("/ip6/::1/tcp/4001", false),
+            ("/ip6/fe80::1/tcp/4001", false),
+            ("/ip6/fd00::1/tcp/4001", false),
+            ("/ip6/ff02::1/udp/4001", false),
+            ("/ip6/::ffff:192.168.1.1/tcp/4001", false),
+            ("/dns4/example.com/tcp/4001", true),
+            ("/memory/1234", true),
+        ] {
+            let address = address.parse::<Multiaddr>().unwrap();
+            assert_eq!(is_public(&address), public, "{address}");
+        }
+    }
+
+    #[test]
+    fn public_in_server_mode() {
+        let peer = PeerId::random();
+        let private = "/ip4/192.168.1.10/tcp/4001".parse::<Multiaddr>().unwrap();
+        let filter = AddressFilter::default();
+
+        assert!(filter.allows(&peer, &private, Mode::Client));
+        assert!(!filter.allows(&peer, &private, Mode::Server));
+        assert!(AddressFilter::All.allows(&peer, &private, Mode::Server));
+        assert!(!AddressFilter::PublicOnly.allows(&peer, &private, Mode::Client));
+        assert!(!AddressFilter::custom(|_, _| false).allows(&peer, &private, Mode::Client));
+    }
+}
@@ -22,36 +22,40 @@
 
 mod test;
 
+use crate::address_filter::{AddressFilter, ProviderFilter};
 use crate::addresses::Addresses;
 use crate::bootstrap;
+use crate::diversity_filter::{DiversityCounters, DiversityFilter, DiversityGroup};
 use crate::handler::{Handler, HandlerEvent, HandlerIn, RequestId};
 use crate::kbucket::{self, Distance, KBucketsTable, NodeStatus};
 use crate::protocol::{ConnectionType, KadPeer, ProtocolConfig};
 use crate::query::{Query, QueryConfig, QueryId, QueryPool, QueryPoolState};
 use crate::record::{
     self,
-    store::{self, RecordStore},
+    store::{self, ProvideSet, RecordStore},
     ProviderRecord, Record,
 };
 use crate::K_VALUE;
 use crate::{jobs::*, protocol};
 use fnv::{FnvHashMap, FnvHashSet};
+use futures::FutureExt;
 use instant::Instant;
-use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_core::{multiaddr::Protocol, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{
     AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm,
 };
 use libp2p_swarm::{
     dial_opts::{self, DialOpts},
-    ConnectionDenied, ConnectionHandler, ConnectionId, DialError, ExternalAddresses,
-    ListenAddresses, NetworkBehaviour, NotifyHandler, StreamProtocol, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    Clock, ConnectionDenied, ConnectionHandler, ConnectionId, DialError, ExternalAddresses,
+    ListenAddresses, NetworkBehaviour, NotifyHandler, Sleep, StreamProtocol, SystemClock, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use smallvec::SmallVec;
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 use std::time::Duration;
 use std::vec;
@@ -69,12 +73,33 @@ pub struct Behaviour<TStore> {
     /// The k-bucket insertion strategy.
     kbucket_inserts: BucketInserts,
 
+    /// The connected peers supporting the Kademlia protocol that are awaiting
+    /// [`Behaviour::confirm_address`] with [`BucketInserts::OnConfirmed`].
+    unconfirmed_peers: HashSet<PeerId>,
+
+    /// The filter for addresses of remote peers, see [`Config::set_address_filter`].
+    address_filter: AddressFilter,
+
+    /// The number of peers in the routing table per group of the [`DiversityFilter`],
+    /// see [`Config::set_diversity_filter`].
+    diversity: Option<DiversityCounters>,
+
+    /// See [`Config::set_provider_filter`].
+    provider_filter: Option<ProviderFilter>,
+
+    /// The signed peer record advertised in `ADD_PROVIDER` requests,
+    /// see [`Behaviour::set_local_peer_record`].
+    local_peer_record: Option<libp2p_core::PeerRecord>,
+
     /// Configuration of the wire protocol.
     protocol_config: ProtocolConfig,
 
     /// Configuration of [`RecordStore`] filtering.
     record_filtering: StoreInserts,
 
+    /// See [`Config::set_peer_routing_only`].
+    peer_routing_only: bool,
+
     /// The currently active (i.e. in-progress) queries.
     queries: QueryPool<QueryInner>,
 
@@ -83,10 +108,19 @@ pub struct Behaviour<TStore> {
     /// This is a superset of the connected peers currently in the routing table.
     connected_peers: FnvHashSet<PeerId>,
 
+    /// When each peer in the routing table last connected, i.e. when the Kademlia protocol was
+    /// last confirmed on a new connection to it.
+    ///
+    /// Only recorded for peers present in the routing table, not for those pending insertion.
+    last_seen: FnvHashMap<PeerId, Instant>,
+
     /// Periodic job for re-publication of provider records for keys
     /// provided by the local node.
     add_provider_job: Option<AddProviderJob>,
 
+    /// Durable copy of the keys provided by the local node, see [`Behaviour::set_provide_set`].
+    provide_set: Option<Box<dyn ProvideSet + Send>>,
+
     /// Periodic job for (re-)replication and (re-)publishing of
     /// regular (value-)records.
     put_record_job: Option<PutRecordJob>,
@@ -120,6 +154,24 @@ pub struct Behaviour<TStore> {
 
     /// Tracks the status of the current bootstrap.
     bootstrap_status: bootstrap::Status,
+
+    /// See [`Config::set_bootstrap_seeds`].
+    bootstrap_seeds: Vec<Multiaddr>,
+
+    /// See [`Config::set_seed_refresh_interval`].
+    seed_refresh_interval: Option<Duration>,
+
+    /// Fires once the bootstrap seeds are to be dialed (again).
+    next_seed_dial: Option<Sleep>,
+
+    /// Fires at the earliest deadline of the running queries, see [`QueryMut::set_timeout`].
+    query_deadline: Option<(Instant, Sleep)>,
+
+    /// When the bootstrap seeds were last dialed.
+    last_seed_dial: Option<Instant>,
+
+    /// See [`Config::set_clock`].
+    clock: Arc<dyn Clock>,
 }
 
 /// The configurable strategies for the insertion of peers
@@ -144,6 +196,16 @@ pub enum BucketInserts {
     /// > connect and disconnect (i.e. the order of the entries
     /// > as well as the network addresses).
     Manual,
+    /// Connected peers confirmed to support the Kademlia protocol are only
+    /// inserted once one of their addresses has been confirmed via
+    /// [`Behaviour::confirm_address`], e.g. after the address has been
+    /// reported by the identify protocol or verified by a peer store.
+    ///
+    /// Until then, such peers are reported via [`Event::RoutablePeer`] or
+    /// [`Event::UnroutablePeer`]. Contrary to [`BucketInserts::OnConnected`],
+    /// this also allows inserting peers that connected to the local node,
+    /// whose listen addresses are not known from the connection itself.
+    OnConfirmed,
 }
 
 /// The configurable filtering strategies for the acceptance of
@@ -183,10 +245,18 @@ pub struct Config {
     record_filtering: StoreInserts,
     provider_record_ttl: Option<Duration>,
     provider_publication_interval: Option<Duration>,
+    provider_publication_spread: bool,
     kbucket_inserts: BucketInserts,
+    address_filter: AddressFilter,
+    diversity_filter: Option<DiversityFilter>,
+    provider_filter: Option<ProviderFilter>,
     caching: Caching,
     periodic_bootstrap_interval: Option<Duration>,
     automatic_bootstrap_throttle: Option<Duration>,
+    bootstrap_seeds: Vec<Multiaddr>,
+    seed_refresh_interval: Option<Duration>,
+    peer_routing_only: bool,
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for Config {
@@ -225,11 +295,19 @@ impl Config {
             record_publication_interval: Some(Duration::from_secs(22 * 60 * 60)),
             record_filtering: StoreInserts::Unfiltered,
             provider_publication_interval: Some(Duration::from_secs(12 * 60 * 60)),
+            provider_publication_spread: true,
             provider_record_ttl: Some(Duration::from_secs(48 * 60 * 60)),
             kbucket_inserts: BucketInserts::OnConnected,
+            address_filter: AddressFilter::default(),
+            diversity_filter: None,
+            provider_filter: None,
             caching: Caching::Enabled { max_peers: 1 },
             periodic_bootstrap_interval: Some(Duration::from_secs(5 * 60)),
             automatic_bootstrap_throttle: Some(bootstrap::DEFAULT_AUTOMATIC_THROTTLE),
+            bootstrap_seeds: Vec::new(),
+            seed_refresh_interval: Some(Duration::from_secs(60 * 60)),
+            peer_routing_only: false,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -297,7 +375,8 @@ impl Config {
     /// in the presence of potentially adversarial nodes.
     ///
     /// When enabled the number of disjoint paths used equals the configured
-    /// parallelism.
+    /// parallelism. Use [`Behaviour::get_record_disjoint`] to only look up
+    /// specific records on disjoint paths.
     ///
     /// See the S/Kademlia paper for more information on the high level design
     /// as well as its security improvements.
@@ -391,6 +470,18 @@ impl Config {
         self
     }
 
+    /// Sets whether the re-publications of provider records are spread evenly over the
+    /// provider publication interval.
+    ///
+    /// If disabled, the provider records of all keys provided by the local node are re-published
+    /// at once every interval, causing bursts of queries for nodes providing many keys.
+    ///
+    /// The default is `true`.
+    pub fn set_provider_publication_spread(&mut self, spread: bool) -> &mut Self {
+        self.provider_publication_spread = spread;
+        self
+    }
+
     /// Modifies the maximum allowed size of individual Kademlia packets.
     ///
     /// It might be necessary to increase this value if trying to put large
@@ -406,6 +497,43 @@ impl Config {
         self
     }
 
+    /// Sets the filter for addresses of remote peers.
+    ///
+    /// Only addresses passing the filter are inserted into the routing table and
+    /// returned to other peers in responses to `FIND_NODE` requests.
+    ///
+    /// The default is [`AddressFilter::PublicInServerMode`], i.e. private addresses
+    /// are ignored while the local node is in [`Mode::Server`].
+    pub fn set_address_filter(&mut self, filter: AddressFilter) -> &mut Self {
+        self.address_filter = filter;
+        self
+    }
+
+    /// Sets a filter limiting the number of peers in the routing table whose addresses share
+    /// an IP prefix or autonomous system, see [`DiversityFilter`].
+    ///
+    /// By default, the diversity of the routing table is not limited.
+    pub fn set_diversity_filter(&mut self, filter: DiversityFilter) -> &mut Self {
+        self.diversity_filter = Some(filter);
+        self
+    }
+
+    /// Sets a filter for provider records received in `ADD_PROVIDER` requests.
+    ///
+    /// The filter is applied after the addresses of the record have been restricted to the
+    /// ones of a valid signed peer record, if provided by the remote, and to the ones passing
+    /// the [`AddressFilter`]. It may further modify the addresses of the record. Records for
+    /// which the filter returns `false` are dropped.
+    ///
+    /// By default, all provider records are accepted.
+    pub fn set_provider_filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(&mut ProviderRecord) -> bool + Send + Sync + 'static,
+    {
+        self.provider_filter = Some(ProviderFilter::new(filter));
+        self
+    }
+
     /// Sets the [`Caching`] strategy to use for successful lookups.
     ///
     /// The default is [`Caching::Enabled`] with a `max_peers` of 1.
@@ -417,6 +545,23 @@ impl Config {
         self
     }
 
+    /// Restricts the local node to peer routing, i.e. `FIND_NODE`.
+    ///
+    /// Records and provider records of remote peers are neither stored nor served:
+    /// inbound `GET_VALUE` and `GET_PROVIDERS` requests are answered with the closest
+    /// peers only, inbound `PUT_VALUE` requests are rejected by resetting the stream
+    /// and inbound `ADD_PROVIDER` requests are ignored. Periodic (re-)replication and
+    /// (re-)publication of records and provider records is disabled.
+    ///
+    /// This reduces memory usage and attack surface of nodes using the DHT purely for
+    /// peer discovery. Outbound operations like [`Behaviour::get_record`] remain available.
+    ///
+    /// The default is `false`.
+    pub fn set_peer_routing_only(&mut self, enabled: bool) -> &mut Self {
+        self.peer_routing_only = enabled;
+        self
+    }
+
     /// Sets the interval on which [`Behaviour::bootstrap`] is called periodically.
     ///
     /// * Default to `5` minutes.
@@ -426,6 +571,46 @@ impl Config {
         self
     }
 
+    /// Sets the seeds to join the DHT through, e.g. `/dnsaddr/bootstrap.libp2p.io`.
+    ///
+    /// The seeds are dialed as soon as the behaviour is polled for the first time, whenever
+    /// [`Behaviour::bootstrap`] is called with an empty routing table and periodically as
+    /// per [`Config::set_seed_refresh_interval`]. Seeds are assumed to participate in the DHT
+    /// and inserted into the routing table as soon as they are connected, thus triggering an
+    /// automatic bootstrap.
+    ///
+    /// `/dnsaddr` seeds are resolved by the transport of the `Swarm` on every dial, which
+    /// therefore has to be wrapped in the DNS transport, see `libp2p-dns`. Seeds ending in
+    /// `/p2p/<peer-id>` are only dialed if the local node is not connected to that peer
+    /// already. Seeds without `/p2p` are dialed regardless, and a `/dnsaddr` seed without
+    /// `/p2p` results in a connection to only one of the peers it resolves to.
+    ///
+    /// The default is to have no seeds.
+    pub fn set_bootstrap_seeds(&mut self, seeds: Vec<Multiaddr>) -> &mut Self {
+        self.bootstrap_seeds = seeds;
+        self
+    }
+
+    /// Sets the interval on which the bootstrap seeds are dialed again, picking up changes of
+    /// the DNS records of `/dnsaddr` seeds, see [`Config::set_bootstrap_seeds`].
+    ///
+    /// * Default to `1` hour.
+    /// * Set to `None` to only dial the seeds initially and when bootstrapping with an empty
+    ///     routing table.
+    pub fn set_seed_refresh_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.seed_refresh_interval = interval;
+        self
+    }
+
+    /// Sets the [`Clock`] driving query timeouts, record and provider expiry, the periodic
+    /// jobs and bootstrapping.
+    ///
+    /// The default is the [`SystemClock`].
+    pub fn set_clock(&mut self, clock: impl Clock) -> &mut Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Sets the time to wait before calling [`Behaviour::bootstrap`] after a new peer is inserted in the routing table.
     /// This prevent cascading bootstrap requests when multiple peers are inserted into the routing table "at the same time".
     /// This also allows to wait a little bit for other potential peers to be inserted into the routing table before
@@ -463,35 +648,54 @@ where
     /// Creates a new `Kademlia` network behaviour with the given configuration.
     pub fn with_config(id: PeerId, store: TStore, config: Config) -> Self {
         let local_key = kbucket::Key::from(id);
+        let mut protocol_config = config.protocol_config;
+        protocol_config.set_clock(config.clock.clone());
 
         let put_record_job = config
             .record_replication_interval
             .or(config.record_publication_interval)
+            .filter(|_| !config.peer_routing_only)
             .map(|interval| {
                 PutRecordJob::new(
                     id,
                     interval,
                     config.record_publication_interval,
                     config.record_ttl,
+                    config.clock.clone(),
                 )
             });
 
         let add_provider_job = config
             .provider_publication_interval
-            .map(AddProviderJob::new);
+            .filter(|_| !config.peer_routing_only)
+            .map(|interval| {
+                AddProviderJob::new(
+                    interval,
+                    config.provider_publication_spread,
+                    config.clock.clone(),
+                )
+            });
 
         Behaviour {
             store,
             caching: config.caching,
             kbuckets: KBucketsTable::new(local_key, config.kbucket_pending_timeout),
             kbucket_inserts: config.kbucket_inserts,
-            protocol_config: config.protocol_config,
+            unconfirmed_peers: Default::default(),
+            address_filter: config.address_filter,
+            diversity: config.diversity_filter.map(DiversityCounters::new),
+            provider_filter: config.provider_filter,
+            local_peer_record: None,
+            protocol_config,
             record_filtering: config.record_filtering,
+            peer_routing_only: config.peer_routing_only,
             queued_events: VecDeque::with_capacity(config.query_config.replication_factor.get()),
             listen_addresses: Default::default(),
-            queries: QueryPool::new(config.query_config),
+            queries: QueryPool::new(config.query_config, config.clock.clone()),
             connected_peers: Default::default(),
+            last_seen: Default::default(),
             add_provider_job,
+            provide_set: None,
             put_record_job,
             record_ttl: config.record_ttl,
             provider_record_ttl: config.provider_record_ttl,
@@ -504,7 +708,15 @@ where
             bootstrap_status: bootstrap::Status::new(
                 config.periodic_bootstrap_interval,
                 config.automatic_bootstrap_throttle,
+                config.clock.clone(),
             ),
+            next_seed_dial: (!config.bootstrap_seeds.is_empty())
+                .then(|| config.clock.sleep(Duration::ZERO)),
+            query_deadline: None,
+            bootstrap_seeds: config.bootstrap_seeds,
+            seed_refresh_interval: config.seed_refresh_interval,
+            last_seed_dial: None,
+            clock: config.clock,
         }
     }
 
@@ -521,9 +733,10 @@ where
 
     /// Gets an iterator over mutable references to all running queries.
     pub fn iter_queries_mut(&mut self) -> impl Iterator<Item = QueryMut<'_>> {
-        self.queries.iter_mut().filter_map(|query| {
+        let now = self.clock.now();
+        self.queries.iter_mut().filter_map(move |query| {
             if !query.is_finished() {
-                Some(QueryMut { query })
+                Some(QueryMut { query, now })
             } else {
                 None
             }
@@ -543,15 +756,43 @@ where
 
     /// Gets a mutable reference to a running query, if it exists.
     pub fn query_mut<'a>(&'a mut self, id: &QueryId) -> Option<QueryMut<'a>> {
+        let now = self.clock.now();
         self.queries.get_mut(id).and_then(|query| {
             if !query.is_finished() {
-                Some(QueryMut { query })
+                Some(QueryMut { query, now })
             } else {
                 None
             }
         })
     }
 
+    /// Aborts a running query.
+    ///
+    /// The query stops immediately and is reported via a final
+    /// [`Event::OutboundQueryProgressed`] with the respective `Aborted` error,
+    /// carrying the results collected so far.
+    /// An aborted bootstrap does not continue with its remaining steps.
+    ///
+    /// Returns `false` if there is no running query with the given ID.
+    pub fn abort_query(&mut self, id: &QueryId) -> bool {
+        if self.query(id).is_none() {
+            return false;
+        }
+
+        let query = self
+            .queries
+            .abort(id, self.clock.now())
+            .expect("query to be running");
+        if let Some(event) = self.query_timeout(query, true) {
+            self.queued_events.push_back(ToSwarm::GenerateEvent(event));
+            if let Some(waker) = self.no_events_waker.take() {
+                waker.wake();
+            }
+        }
+
+        true
+    }
+
     /// Adds a known listen address of a peer participating in the DHT to the
     /// routing table.
     ///
@@ -569,15 +810,29 @@ where
     ///
     /// If the routing table has been updated as a result of this operation,
     /// a [`Event::RoutingUpdated`] event is emitted.
+    ///
+    /// Addresses rejected by the [`AddressFilter`] are not added,
+    /// see [`Config::set_address_filter`]. Neither are addresses exceeding the limits of
+    /// the [`DiversityFilter`], see [`Config::set_diversity_filter`].
     pub fn add_address(&mut self, peer: &PeerId, address: Multiaddr) -> RoutingUpdate {
         // ensuring address is a fully-qualified /p2p multiaddr
         let Ok(address) = address.with_p2p(*peer) else {
             return RoutingUpdate::Failed;
         };
+        if !self.address_filter.allows(peer, &address, self.mode) {
+            tracing::debug!(%peer, %address, "Address rejected by address filter");
+            return RoutingUpdate::Failed;
+        }
+        if !self.check_diversity(peer, &address) {
+            return RoutingUpdate::Failed;
+        }
         let key = kbucket::Key::from(*peer);
         match self.kbuckets.entry(&key) {
             Some(kbucket::Entry::Present(mut entry, _)) => {
                 if entry.value().insert(address) {
+                    if let Some(diversity) = &mut self.diversity {
+                        diversity.insert(*peer, entry.value().iter());
+                    }
                     self.queued_events
                         .push_back(ToSwarm::GenerateEvent(Event::RoutingUpdated {
                             peer: *peer,
@@ -606,6 +861,9 @@ where
                 };
                 match entry.insert(addresses.clone(), status) {
                     kbucket::InsertResult::Inserted => {
+                        if let Some(diversity) = &mut self.diversity {
+                            diversity.insert(*peer, addresses.iter());
+                        }
                         self.bootstrap_status.on_new_peer_in_routing_table();
                         self.queued_events.push_back(ToSwarm::GenerateEvent(
                             Event::RoutingUpdated {
@@ -638,6 +896,34 @@ where
         }
     }
 
+    /// Confirms an address of a peer, inserting the peer into the routing table
+    /// with [`BucketInserts::OnConfirmed`].
+    ///
+    /// Only addresses of peers that are connected and known to support the Kademlia
+    /// protocol, i.e. that have been reported via [`Event::RoutablePeer`] or
+    /// [`Event::UnroutablePeer`], or of peers already in the routing table are
+    /// accepted. For any other peer, [`RoutingUpdate::Failed`] is returned, see
+    /// [`Behaviour::add_address`] to unconditionally add a peer.
+    ///
+    /// Otherwise this behaves like [`Behaviour::add_address`].
+    pub fn confirm_address(&mut self, peer: &PeerId, address: Multiaddr) -> RoutingUpdate {
+        let key = kbucket::Key::from(*peer);
+        let in_routing_table = matches!(
+            self.kbuckets.entry(&key),
+            Some(kbucket::Entry::Present(..) | kbucket::Entry::Pending(..))
+        );
+        if !in_routing_table && !self.unconfirmed_peers.contains(peer) {
+            tracing::debug!(%peer, %address, "Not confirming address of unknown peer");
+            return RoutingUpdate::Failed;
+        }
+
+        let update = self.add_address(peer, address);
+        if !matches!(update, RoutingUpdate::Failed) {
+            self.unconfirmed_peers.remove(peer);
+        }
+        update
+    }
+
     /// Removes an address of a peer from the routing table.
     ///
     /// If the given address is the last address of the peer in the
@@ -655,11 +941,14 @@ where
     ) -> Option<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> {
         let address = &address.to_owned().with_p2p(*peer).ok()?;
         let key = kbucket::Key::from(*peer);
-        match self.kbuckets.entry(&key)? {
+        let removed = match self.kbuckets.entry(&key)? {
             kbucket::Entry::Present(mut entry, _) => {
                 if entry.value().remove(address).is_err() {
                     Some(entry.remove()) // it is the last address, thus remove the peer.
                 } else {
+                    if let Some(diversity) = &mut self.diversity {
+                        diversity.insert(*peer, entry.value().iter());
+                    }
                     None
                 }
             }
@@ -671,7 +960,14 @@ where
                 }
             }
             kbucket::Entry::Absent(..) => None,
+        };
+        if removed.is_some() {
+            self.last_seen.remove(peer);
+            if let Some(diversity) = &mut self.diversity {
+                diversity.remove(peer);
+            }
         }
+        removed
     }
 
     /// Removes a peer from the routing table.
@@ -683,6 +979,10 @@ where
         peer: &PeerId,
     ) -> Option<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> {
         let key = kbucket::Key::from(*peer);
+        self.last_seen.remove(peer);
+        if let Some(diversity) = &mut self.diversity {
+            diversity.remove(peer);
+        }
         match self.kbuckets.entry(&key)? {
             kbucket::Entry::Present(entry, _) => Some(entry.remove()),
             kbucket::Entry::Pending(entry, _) => Some(entry.remove()),
@@ -690,6 +990,65 @@ where
         }
     }
 
+    /// Returns information about a peer in the routing table, without querying the network.
+    ///
+    /// Returns `None` if the peer is neither in the routing table nor pending insertion.
+    pub fn routing_table_peer(&mut self, peer: &PeerId) -> Option<RoutingTablePeer> {
+        let key = kbucket::Key::from(*peer);
+        let (addresses, status, is_pending) = match self.kbuckets.entry(&key)? {
+            kbucket::Entry::Present(mut entry, status) => (entry.value().clone(), status, false),
+            kbucket::Entry::Pending(mut entry, status) => (entry.value().clone(), status, true),
+            kbucket::Entry::Absent(..) => return None,
+        };
+
+        Some(RoutingTablePeer {
+            peer_id: *peer,
+            addresses,
+            status,
+            is_pending,
+            last_seen: self.last_seen.get(peer).copied(),
+        })
+    }
+
+    /// Returns the peers in the routing table closest to the given key, ordered by increasing
+    /// distance, without querying the network.
+    ///
+    /// Unlike [`Behaviour::get_closest_local_peers`], this includes the information available
+    /// about each peer. Peers pending insertion are not included.
+    pub fn closest_routing_table_peers<'a, K: Clone>(
+        &'a mut self,
+        key: &'a kbucket::Key<K>,
+    ) -> impl Iterator<Item = RoutingTablePeer> + 'a {
+        let last_seen = &self.last_seen;
+        self.kbuckets.closest(key).map(move |entry| {
+            let peer_id = entry.node.key.into_preimage();
+            RoutingTablePeer {
+                peer_id,
+                addresses: entry.node.value,
+                status: entry.status,
+                is_pending: false,
+                last_seen: last_seen.get(&peer_id).copied(),
+            }
+        })
+    }
+
+    /// Returns the occupancy of all buckets of the routing table, ordered by increasing
+    /// distance from the local key.
+    pub fn bucket_occupancy(&mut self) -> Vec<BucketOccupancy> {
+        self.kbuckets
+            .iter()
+            .map(|bucket| BucketOccupancy {
+                range: bucket.range(),
+                num_entries: bucket.num_entries(),
+                num_connected: bucket
+                    .iter()
+                    .filter(|e| e.status == NodeStatus::Connected)
+                    .count(),
+                has_pending: bucket.has_pending(),
+            })
+            .collect()
+    }
+
     /// Returns an iterator over all non-empty buckets in the routing table.
     pub fn kbuckets(
         &mut self,
@@ -742,14 +1101,34 @@ where
     /// The result of this operation is delivered in a
     /// [`Event::OutboundQueryProgressed{QueryResult::GetRecord}`].
     pub fn get_record(&mut self, key: record::Key) -> QueryId {
+        self.get_record_inner(key, None)
+    }
+
+    /// Performs a lookup for a record in the DHT on the given number of disjoint paths,
+    /// regardless of [`Config::disjoint_query_paths`].
+    ///
+    /// Each path only follows the peers discovered on it, i.e. a Sybil on one path can not
+    /// divert the lookup on the others. Every [`GetRecordOk::FoundRecord`] reports in
+    /// [`PeerRecord::confirmed_paths`] on how many paths the same record value was received so
+    /// far. Callers can thus require the agreement of multiple independent paths before
+    /// trusting a value, finishing the query via [`QueryMut::finish`] once satisfied.
+    ///
+    /// The result of this operation is delivered in a
+    /// [`Event::OutboundQueryProgressed{QueryResult::GetRecord}`].
+    pub fn get_record_disjoint(&mut self, key: record::Key, paths: NonZeroUsize) -> QueryId {
+        self.get_record_inner(key, Some(paths))
+    }
+
+    fn get_record_inner(&mut self, key: record::Key, paths: Option<NonZeroUsize>) -> QueryId {
         let record = if let Some(record) = self.store.get(&key) {
-            if record.is_expired(Instant::now()) {
+            if record.is_expired(self.clock.now()) {
                 self.store.remove(&key);
                 None
             } else {
                 Some(PeerRecord {
                     peer: None,
                     record: record.into_owned(),
+                    confirmed_paths: 0,
                 })
             }
         } else {
@@ -765,6 +1144,7 @@ where
                 step: step.next(),
                 found_a_record: true,
                 cache_candidates: BTreeMap::new(),
+                paths_by_value: Vec::new(),
             }
         } else {
             QueryInfo::GetRecord {
@@ -772,11 +1152,18 @@ where
                 step: step.clone(),
                 found_a_record: false,
                 cache_candidates: BTreeMap::new(),
+                paths_by_value: Vec::new(),
             }
         };
         let peers = self.kbuckets.closest_keys(&target);
         let inner = QueryInner::new(info);
-        let id = self.queries.add_iter_closest(target.clone(), peers, inner);
+        let id = match paths {
+            Some(paths) => {
+                self.queries
+                    .add_iter_closest_disjoint(target.clone(), peers, paths, inner)
+            }
+            None => self.queries.add_iter_closest(target.clone(), peers, inner),
+        };
 
         // No queries were actually done for the results yet.
         let stats = QueryStats::empty();
@@ -821,7 +1208,7 @@ where
         self.store.put(record.clone())?;
         record.expires = record
             .expires
-            .or_else(|| self.record_ttl.map(|ttl| Instant::now() + ttl));
+            .or_else(|| self.record_ttl.map(|ttl| self.clock.now() + ttl));
         let quorum = quorum.eval(self.queries.config().replication_factor);
         let target = kbucket::Key::new(record.key.clone());
         let peers = self.kbuckets.closest_keys(&target);
@@ -867,7 +1254,7 @@ where
         };
         record.expires = record
             .expires
-            .or_else(|| self.record_ttl.map(|ttl| Instant::now() + ttl));
+            .or_else(|| self.record_ttl.map(|ttl| self.clock.now() + ttl));
         let context = PutRecordContext::Custom;
         let info = QueryInfo::PutRecord {
             context,
@@ -919,7 +1306,8 @@ where
     /// reported via [`Event::OutboundQueryProgressed{QueryResult::Bootstrap}`] events,
     /// with one such event per bootstrapping query.
     ///
-    /// Returns `Err` if bootstrapping is impossible due an empty routing table.
+    /// Returns `Err` if bootstrapping is impossible due an empty routing table. The
+    /// bootstrap seeds, if any, are dialed in that case, see [`Config::set_bootstrap_seeds`].
     ///
     /// > **Note**: Bootstrapping requires at least one node of the DHT to be known.
     /// > See [`Behaviour::add_address`].
@@ -939,6 +1327,13 @@ where
         };
         let peers = self.kbuckets.closest_keys(&local_key).collect::<Vec<_>>();
         if peers.is_empty() {
+            let now = self.clock.now();
+            let recently_dialed = self.last_seed_dial.map_or(false, |t| {
+                now.duration_since(t) < bootstrap::MIN_SEED_DIAL_INTERVAL
+            });
+            if !recently_dialed {
+                self.dial_bootstrap_seeds();
+            }
             Err(NoKnownPeers())
         } else {
             self.bootstrap_status.on_started();
@@ -947,6 +1342,32 @@ where
         }
     }
 
+    /// Dials the bootstrap seeds and schedules the next refresh.
+    fn dial_bootstrap_seeds(&mut self) {
+        if self.bootstrap_seeds.is_empty() {
+            return;
+        }
+
+        for seed in &self.bootstrap_seeds {
+            tracing::debug!(%seed, "Dialing bootstrap seed");
+            let opts = match seed.iter().last() {
+                Some(Protocol::P2p(peer)) => DialOpts::peer_id(peer)
+                    .addresses(vec![seed.clone()])
+                    .build(),
+                _ => DialOpts::unknown_peer_id().address(seed.clone()).build(),
+            };
+            self.queued_events.push_back(ToSwarm::Dial { opts });
+        }
+        self.next_seed_dial = self
+            .seed_refresh_interval
+            .map(|interval| self.clock.sleep(interval));
+        self.last_seed_dial = Some(self.clock.now());
+
+        if let Some(waker) = self.no_events_waker.take() {
+            waker.wake();
+        }
+    }
+
     /// Establishes the local node as a provider of a value for the given key.
     ///
     /// This operation publishes a provider record with the given key and
@@ -980,6 +1401,11 @@ where
             local_addrs,
         );
         self.store.add_provider(record)?;
+        if let Some(provide_set) = self.provide_set.as_mut() {
+            if let Err(e) = provide_set.insert(&key) {
+                tracing::warn!(?key, "Failed to persist provided key: {e}");
+            }
+        }
         let target = kbucket::Key::new(key.clone());
         let peers = self.kbuckets.closest_keys(&target);
         let context = AddProviderContext::Publish;
@@ -1000,6 +1426,70 @@ where
     pub fn stop_providing(&mut self, key: &record::Key) {
         self.store
             .remove_provider(key, self.kbuckets.local_key().preimage());
+        if let Some(provide_set) = self.provide_set.as_mut() {
+            if let Err(e) = provide_set.remove(key) {
+                tracing::warn!(?key, "Failed to remove provided key from persistence: {e}");
+            }
+        }
+    }
+
+    /// Attaches a [`ProvideSet`] persisting the keys provided by the local node.
+    ///
+    /// The keys of the given set are restored, i.e. the local node becomes a provider for them
+    /// again. Instead of announcing them all at once, they are re-published in the background,
+    /// spread over the provider publication interval (see
+    /// [`Config::set_provider_publication_spread`]), starting right away.
+    /// From then on, [`Behaviour::start_providing`] and [`Behaviour::stop_providing`] update the
+    /// set.
+    ///
+    /// Returns an error if the keys of the set cannot be loaded, in which case the set is not
+    /// attached.
+    pub fn set_provide_set(
+        &mut self,
+        mut provide_set: impl ProvideSet + Send + 'static,
+    ) -> std::io::Result<()> {
+        let keys = provide_set.load()?;
+        let local_peer_id = *self.kbuckets.local_key().preimage();
+        let num_keys = keys.len();
+
+        for key in keys {
+            if let Err(e) =
+                self.store
+                    .add_provider(ProviderRecord::new(key.clone(), local_peer_id, Vec::new()))
+            {
+                tracing::warn!(?key, "Failed to restore provided key: {e}");
+            }
+        }
+
+        if num_keys > 0 {
+            tracing::debug!(%num_keys, "Restored provided keys");
+
+            if let Some(job) = self.add_provider_job.as_mut() {
+                job.asap();
+            }
+        }
+
+        self.provide_set = Some(Box::new(provide_set));
+        Ok(())
+    }
+
+    /// Sets the signed peer record advertised along with the local node in `ADD_PROVIDER`
+    /// requests.
+    ///
+    /// Remote nodes verify the record and only store its addresses with the provider record,
+    /// instead of the unsigned external addresses of the local node. The record needs to be
+    /// updated whenever the external addresses of the local node change.
+    ///
+    /// Records not belonging to the local node are ignored.
+    pub fn set_local_peer_record(&mut self, record: Option<libp2p_core::PeerRecord>) {
+        if let Some(record) = &record {
+            if &record.peer_id() != self.kbuckets.local_key().preimage() {
+                tracing::warn!(peer=%record.peer_id(), "Ignoring peer record of a remote peer");
+                return;
+            }
+        }
+
+        self.local_peer_record = record;
     }
 
     /// Performs a lookup for providers of a value to the given key.
@@ -1011,7 +1501,7 @@ where
             .store
             .providers(&key)
             .into_iter()
-            .filter(|p| !p.is_expired(Instant::now()))
+            .filter(|p| !p.is_expired(self.clock.now()))
             .map(|p| p.provider)
             .collect();
 
@@ -1178,11 +1668,19 @@ where
         target: &kbucket::Key<T>,
         source: &PeerId,
     ) -> Vec<KadPeer> {
+        let address_filter = &self.address_filter;
+        let mode = self.mode;
+
         self.kbuckets
             .closest(target)
             .filter(|e| e.node.key.preimage() != source)
-            .take(self.queries.config().replication_factor.get())
             .map(KadPeer::from)
+            .filter_map(|mut peer| {
+                peer.multiaddrs
+                    .retain(|a| address_filter.allows(&peer.node_id, a, mode));
+                (!peer.multiaddrs.is_empty()).then_some(peer)
+            })
+            .take(self.queries.config().replication_factor.get())
             .collect()
     }
 
@@ -1235,6 +1733,7 @@ where
                         node_id,
                         multiaddrs,
                         connection_ty,
+                        signed_record: None,
                     })
                 } else {
                     None
@@ -1279,14 +1778,32 @@ where
         address: Option<Multiaddr>,
         new_status: NodeStatus,
     ) {
+        let address = address.filter(|a| {
+            let allowed = self.address_filter.allows(&peer, a, self.mode);
+            if !allowed {
+                tracing::debug!(%peer, address=%a, "Address rejected by address filter");
+            }
+            allowed
+        });
+        let diversity_exceeded = address
+            .as_ref()
+            .is_some_and(|a| !self.check_diversity(&peer, a));
+        let address = address.filter(|_| !diversity_exceeded);
         let key = kbucket::Key::from(peer);
+        let now = self.clock.now();
         match self.kbuckets.entry(&key) {
             Some(kbucket::Entry::Present(mut entry, old_status)) => {
                 if old_status != new_status {
                     entry.update(new_status)
                 }
+                if new_status == NodeStatus::Connected {
+                    self.last_seen.insert(peer, now);
+                }
                 if let Some(address) = address {
                     if entry.value().insert(address) {
+                        if let Some(diversity) = &mut self.diversity {
+                            diversity.insert(peer, entry.value().iter());
+                        }
                         self.queued_events.push_back(ToSwarm::GenerateEvent(
                             Event::RoutingUpdated {
                                 peer,
@@ -1314,16 +1831,23 @@ where
             }
 
             Some(kbucket::Entry::Absent(entry)) => {
+                // The peer may have been dropped from the routing table meanwhile.
+                self.last_seen.remove(&peer);
                 // Only connected nodes with a known address are newly inserted.
                 if new_status != NodeStatus::Connected {
                     return;
                 }
+                if self.kbucket_inserts == BucketInserts::OnConfirmed {
+                    self.unconfirmed_peers.insert(peer);
+                }
                 match (address, self.kbucket_inserts) {
+                    // Already reported via `Event::DiversityLimitExceeded`.
+                    (None, _) if diversity_exceeded => {}
                     (None, _) => {
                         self.queued_events
                             .push_back(ToSwarm::GenerateEvent(Event::UnroutablePeer { peer }));
                     }
-                    (Some(a), BucketInserts::Manual) => {
+                    (Some(a), BucketInserts::Manual | BucketInserts::OnConfirmed) => {
                         self.queued_events
                             .push_back(ToSwarm::GenerateEvent(Event::RoutablePeer {
                                 peer,
@@ -1334,6 +1858,10 @@ where
                         let addresses = Addresses::new(a);
                         match entry.insert(addresses.clone(), new_status) {
                             kbucket::InsertResult::Inserted => {
+                                self.last_seen.insert(peer, now);
+                                if let Some(diversity) = &mut self.diversity {
+                                    diversity.insert(peer, addresses.iter());
+                                }
                                 self.bootstrap_status.on_new_peer_in_routing_table();
                                 let event = Event::RoutingUpdated {
                                     peer,
@@ -1385,6 +1913,28 @@ where
         }
     }
 
+    /// Checks whether inserting the address of a peer into the routing table keeps the number of
+    /// peers per group within the limits of the [`DiversityFilter`], reporting
+    /// [`Event::DiversityLimitExceeded`] otherwise.
+    fn check_diversity(&mut self, peer: &PeerId, address: &Multiaddr) -> bool {
+        let Some(group) = self
+            .diversity
+            .as_ref()
+            .and_then(|diversity| diversity.exceeded(peer, address))
+        else {
+            return true;
+        };
+
+        tracing::debug!(%peer, %address, ?group, "Address exceeds diversity limit");
+        self.queued_events
+            .push_back(ToSwarm::GenerateEvent(Event::DiversityLimitExceeded {
+                peer: *peer,
+                address: address.clone(),
+                group,
+            }));
+        false
+    }
+
     /// Handles a finished (i.e. successful) query.
     fn query_finished(&mut self, q: Query<QueryInner>) -> Option<Event> {
         let query_id = q.id();
@@ -1445,8 +1995,13 @@ where
                     };
                     let peers = self.kbuckets.closest_keys(&target);
                     let inner = QueryInner::new(info);
-                    self.queries
-                        .continue_iter_closest(query_id, target, peers, inner);
+                    self.queries.continue_iter_closest(
+                        query_id,
+                        target,
+                        peers,
+                        inner,
+                        result.deadline,
+                    );
                 } else {
                     step.last = true;
                     self.bootstrap_status.on_finish();
@@ -1505,10 +2060,12 @@ where
                     phase: AddProviderPhase::AddProvider {
                         provider_id,
                         external_addresses,
+                        signed_record: self.local_peer_record.clone(),
                         get_closest_peers_stats: result.stats,
                     },
                 });
-                self.queries.continue_fixed(query_id, result.peers, inner);
+                self.queries
+                    .continue_fixed(query_id, result.peers, inner, result.deadline);
                 None
             }
 
@@ -1540,6 +2097,7 @@ where
                 mut step,
                 found_a_record,
                 cache_candidates,
+                ..
             } => {
                 step.last = true;
 
@@ -1575,7 +2133,8 @@ where
                     },
                 };
                 let inner = QueryInner::new(info);
-                self.queries.continue_fixed(query_id, result.peers, inner);
+                self.queries
+                    .continue_fixed(query_id, result.peers, inner, result.deadline);
                 None
             }
 
@@ -1624,10 +2183,16 @@ where
         }
     }
 
-    /// Handles a query that timed out.
-    fn query_timeout(&mut self, query: Query<QueryInner>) -> Option<Event> {
+    /// Handles a query that timed out or was aborted, reporting its partial results.
+    ///
+    /// An aborted bootstrap does not continue with its remaining steps.
+    fn query_timeout(&mut self, query: Query<QueryInner>, aborted: bool) -> Option<Event> {
         let query_id = query.id();
-        tracing::trace!(query=?query_id, "Query timed out");
+        if aborted {
+            tracing::trace!(query=?query_id, "Query aborted");
+        } else {
+            tracing::trace!(query=?query_id, "Query timed out");
+        }
         let result = query.into_result();
         match result.inner.info {
             QueryInfo::Bootstrap {
@@ -1638,8 +2203,10 @@ where
                 let num_remaining = remaining.as_ref().map(|r| r.len().saturating_sub(1) as u32);
 
                 // Continue with the next bootstrap query if `remaining` is not empty.
-                if let Some((target, remaining)) =
-                    remaining.take().and_then(|mut r| Some((r.next()?, r)))
+                if let Some((target, remaining)) = remaining
+                    .take()
+                    .filter(|_| !aborted)
+                    .and_then(|mut r| Some((r.next()?, r)))
                 {
                     let info = QueryInfo::Bootstrap {
                         peer: target.into_preimage(),
@@ -1648,49 +2215,72 @@ where
                     };
                     let peers = self.kbuckets.closest_keys(&target);
                     let inner = QueryInner::new(info);
-                    self.queries
-                        .continue_iter_closest(query_id, target, peers, inner);
+                    self.queries.continue_iter_closest(
+                        query_id,
+                        target,
+                        peers,
+                        inner,
+                        result.deadline,
+                    );
                 } else {
                     step.last = true;
                     self.bootstrap_status.on_finish();
                 }
 
+                let err = if aborted {
+                    BootstrapError::Aborted {
+                        peer,
+                        num_remaining,
+                    }
+                } else {
+                    BootstrapError::Timeout {
+                        peer,
+                        num_remaining,
+                    }
+                };
                 Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
-                    result: QueryResult::Bootstrap(Err(BootstrapError::Timeout {
-                        peer,
-                        num_remaining,
-                    })),
+                    result: QueryResult::Bootstrap(Err(err)),
                     step,
                 })
             }
 
-            QueryInfo::AddProvider { context, key, .. } => Some(match context {
-                AddProviderContext::Publish => Event::OutboundQueryProgressed {
-                    id: query_id,
-                    stats: result.stats,
-                    result: QueryResult::StartProviding(Err(AddProviderError::Timeout { key })),
-                    step: ProgressStep::first_and_last(),
-                },
-                AddProviderContext::Republish => Event::OutboundQueryProgressed {
-                    id: query_id,
-                    stats: result.stats,
-                    result: QueryResult::RepublishProvider(Err(AddProviderError::Timeout { key })),
-                    step: ProgressStep::first_and_last(),
-                },
-            }),
+            QueryInfo::AddProvider { context, key, .. } => {
+                let err = if aborted {
+                    AddProviderError::Aborted { key }
+                } else {
+                    AddProviderError::Timeout { key }
+                };
+                Some(match context {
+                    AddProviderContext::Publish => Event::OutboundQueryProgressed {
+                        id: query_id,
+                        stats: result.stats,
+                        result: QueryResult::StartProviding(Err(err)),
+                        step: ProgressStep::first_and_last(),
+                    },
+                    AddProviderContext::Republish => Event::OutboundQueryProgressed {
+                        id: query_id,
+                        stats: result.stats,
+                        result: QueryResult::RepublishProvider(Err(err)),
+                        step: ProgressStep::first_and_last(),
+                    },
+                })
+            }
 
             QueryInfo::GetClosestPeers { key, mut step } => {
                 step.last = true;
 
+                let peers = result.peers.collect();
+                let err = if aborted {
+                    GetClosestPeersError::Aborted { key, peers }
+                } else {
+                    GetClosestPeersError::Timeout { key, peers }
+                };
                 Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
-                    result: QueryResult::GetClosestPeers(Err(GetClosestPeersError::Timeout {
-                        key,
-                        peers: result.peers.collect(),
-                    })),
+                    result: QueryResult::GetClosestPeers(Err(err)),
                     step,
                 })
             }
@@ -1701,13 +2291,23 @@ where
                 context,
                 phase,
             } => {
-                let err = Err(PutRecordError::Timeout {
-                    key: record.key,
-                    quorum,
-                    success: match phase {
-                        PutRecordPhase::GetClosestPeers => vec![],
-                        PutRecordPhase::PutRecord { ref success, .. } => success.clone(),
-                    },
+                let key = record.key;
+                let success = match phase {
+                    PutRecordPhase::GetClosestPeers => vec![],
+                    PutRecordPhase::PutRecord { ref success, .. } => success.clone(),
+                };
+                let err = Err(if aborted {
+                    PutRecordError::Aborted {
+                        key,
+                        success,
+                        quorum,
+                    }
+                } else {
+                    PutRecordError::Timeout {
+                        key,
+                        success,
+                        quorum,
+                    }
                 });
                 match context {
                     PutRecordContext::Publish | PutRecordContext::Custom => {
@@ -1743,10 +2343,15 @@ where
             QueryInfo::GetRecord { key, mut step, .. } => {
                 step.last = true;
 
+                let err = if aborted {
+                    GetRecordError::Aborted { key }
+                } else {
+                    GetRecordError::Timeout { key }
+                };
                 Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
-                    result: QueryResult::GetRecord(Err(GetRecordError::Timeout { key })),
+                    result: QueryResult::GetRecord(Err(err)),
                     step,
                 })
             }
@@ -1754,13 +2359,16 @@ where
             QueryInfo::GetProviders { key, mut step, .. } => {
                 step.last = true;
 
+                let closest_peers = result.peers.collect();
+                let err = if aborted {
+                    GetProvidersError::Aborted { key, closest_peers }
+                } else {
+                    GetProvidersError::Timeout { key, closest_peers }
+                };
                 Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: result.stats,
-                    result: QueryResult::GetProviders(Err(GetProvidersError::Timeout {
-                        key,
-                        closest_peers: result.peers.collect(),
-                    })),
+                    result: QueryResult::GetProviders(Err(err)),
                     step,
                 })
             }
@@ -1791,7 +2399,7 @@ where
             return;
         }
 
-        let now = Instant::now();
+        let now = self.clock.now();
 
         // Calculate the expiration exponentially inversely proportional to the
         // number of nodes between the local node and the closest node to the key
@@ -1893,12 +2501,31 @@ where
     /// Processes a provider record received from a peer.
     fn provider_received(&mut self, key: record::Key, provider: KadPeer) {
         if &provider.node_id != self.kbuckets.local_key().preimage() {
-            let record = ProviderRecord {
+            let node_id = provider.node_id;
+            // Only trust the addresses of a signed peer record if one is provided.
+            let mut addresses = match provider.signed_record {
+                Some(record) => record
+                    .addresses()
+                    .iter()
+                    .filter_map(|a| a.clone().with_p2p(node_id).ok())
+                    .collect(),
+                None => provider.multiaddrs,
+            };
+            addresses.retain(|a| self.address_filter.allows(&node_id, a, self.mode));
+
+            let mut record = ProviderRecord {
                 key,
-                provider: provider.node_id,
-                expires: self.provider_record_ttl.map(|ttl| Instant::now() + ttl),
-                addresses: provider.multiaddrs,
+                provider: node_id,
+                expires: self.provider_record_ttl.map(|ttl| self.clock.now() + ttl),
+                addresses,
             };
+            if let Some(filter) = &self.provider_filter {
+                if !filter.accepts(&mut record) {
+                    tracing::debug!(provider=%node_id, "Provider record rejected by filter");
+                    return;
+                }
+            }
+
             match self.record_filtering {
                 StoreInserts::Unfiltered => {
                     if let Err(e) = self.store.add_provider(record) {
@@ -1938,6 +2565,9 @@ where
                     %address,
                     "Address removed from peer due to error."
                 );
+                if let Some(diversity) = &mut self.diversity {
+                    diversity.update(peer_id, addrs.iter());
+                }
             } else {
                 // Despite apparently having no reachable address (any longer),
                 // the peer is kept in the routing table with the last address to avoid
@@ -1967,6 +2597,7 @@ where
         &mut self,
         ConnectionEstablished {
             peer_id,
+            endpoint,
             failed_addresses,
             other_established,
             ..
@@ -1980,6 +2611,18 @@ where
         if other_established == 0 {
             self.connected_peers.insert(peer_id);
         }
+
+        // Seeds are known to participate in the DHT, thus don't wait for the protocol to be
+        // confirmed before inserting them into the routing table.
+        if let ConnectedPoint::Dialer { address, .. } = endpoint {
+            let is_seed = self
+                .bootstrap_seeds
+                .iter()
+                .any(|seed| without_p2p(seed) == without_p2p(address));
+            if is_seed {
+                self.add_address(&peer_id, address.clone());
+            }
+        }
     }
 
     fn on_address_change(
@@ -2059,7 +2702,8 @@ where
             | DialError::Aborted
             | DialError::Denied { .. }
             | DialError::Transport(_)
-            | DialError::NoAddresses => {
+            | DialError::NoAddresses
+            | DialError::SelfDial => {
                 if let DialError::Transport(addresses) = error {
                     for (addr, _) in addresses {
                         self.address_failed(peer_id, addr)
@@ -2101,6 +2745,7 @@ where
             }
             self.connection_updated(peer_id, None, NodeStatus::Disconnected);
             self.connected_peers.remove(&peer_id);
+            self.unconfirmed_peers.remove(&peer_id);
         }
     }
 
@@ -2131,6 +2776,15 @@ fn exp_decrease(ttl: Duration, exp: u32) -> Duration {
     Duration::from_secs(ttl.as_secs().checked_shr(exp).unwrap_or(0))
 }
 
+/// Returns the address without a trailing `/p2p` protocol, if any.
+fn without_p2p(address: &Multiaddr) -> Multiaddr {
+    let mut address = address.clone();
+    if let Some(Protocol::P2p(_)) = address.iter().last() {
+        address.pop();
+    }
+    address
+}
+
 impl<TStore> NetworkBehaviour for Behaviour<TStore>
 where
     TStore: RecordStore + Send + 'static,
@@ -2275,7 +2929,11 @@ where
             }
 
             HandlerEvent::GetProvidersReq { key, request_id } => {
-                let provider_peers = self.provider_peers(&key, &source);
+                let provider_peers = if self.peer_routing_only {
+                    Vec::new()
+                } else {
+                    self.provider_peers(&key, &source)
+                };
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
 
                 self.queued_events
@@ -2353,14 +3011,19 @@ where
                     return;
                 }
 
+                if self.peer_routing_only {
+                    tracing::debug!(peer=%source, "Ignoring provider record in peer routing only mode");
+                    return;
+                }
+
                 self.provider_received(key, provider);
             }
 
             HandlerEvent::GetRecord { key, request_id } => {
                 // Lookup the record locally.
-                let record = match self.store.get(&key) {
+                let record = match self.store.get(&key).filter(|_| !self.peer_routing_only) {
                     Some(record) => {
-                        if record.is_expired(Instant::now()) {
+                        if record.is_expired(self.clock.now()) {
                             self.store.remove(&key);
                             None
                         } else {
@@ -2398,18 +3061,34 @@ where
             } => {
                 if let Some(query) = self.queries.get_mut(&query_id) {
                     let stats = query.stats().clone();
+                    let path = query.path_of(&source);
                     if let QueryInfo::GetRecord {
                         key,
                         ref mut step,
                         ref mut found_a_record,
                         cache_candidates,
+                        paths_by_value,
                     } = &mut query.inner.info
                     {
                         if let Some(record) = record {
                             *found_a_record = true;
+                            let confirmed_paths =
+                                match paths_by_value.iter_mut().find(|(v, _)| *v == record.value) {
+                                    Some((_, paths)) => {
+                                        paths.extend(path);
+                                        paths.len()
+                                    }
+                                    None => {
+                                        let paths = HashSet::from_iter(path);
+                                        let confirmed_paths = paths.len();
+                                        paths_by_value.push((record.value.clone(), paths));
+                                        confirmed_paths
+                                    }
+                                };
                             let record = PeerRecord {
                                 peer: Some(source),
                                 record,
+                                confirmed_paths,
                             };
 
                             self.queued_events.push_back(ToSwarm::GenerateEvent(
@@ -2447,6 +3126,16 @@ where
             }
 
             HandlerEvent::PutRecord { record, request_id } => {
+                if self.peer_routing_only {
+                    tracing::debug!(peer=%source, "Rejecting record in peer routing only mode");
+                    self.queued_events.push_back(ToSwarm::NotifyHandler {
+                        peer_id: source,
+                        handler: NotifyHandler::One(connection),
+                        event: HandlerIn::Reset(request_id),
+                    });
+                    return;
+                }
+
                 self.record_received(source, connection, request_id, record);
             }
 
@@ -2487,7 +3176,7 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
-        let now = Instant::now();
+        let now = self.clock.now();
 
         // Calculate the available capacity for queries triggered by background jobs.
         let mut jobs_query_capacity = JOBS_MAX_QUERIES.saturating_sub(self.queries.size());
@@ -2495,14 +3184,23 @@ where
         // Run the periodic provider announcement job.
         if let Some(mut job) = self.add_provider_job.take() {
             let num = usize::min(JOBS_MAX_NEW_QUERIES, jobs_query_capacity);
+            let mut progress = None;
             for i in 0..num {
                 if let Poll::Ready(r) = job.poll(cx, &mut self.store, now) {
+                    progress = job.progress();
                     self.start_add_provider(r.key, AddProviderContext::Republish)
                 } else {
                     jobs_query_capacity -= i;
                     break;
                 }
             }
+            if let Some((republished, total)) = progress {
+                self.queued_events
+                    .push_back(ToSwarm::GenerateEvent(Event::ReprovideProgressed {
+                        republished,
+                        total,
+                    }));
+            }
             self.add_provider_job = Some(job);
         }
 
@@ -2525,6 +3223,11 @@ where
             self.put_record_job = Some(job);
         }
 
+        // Dial the bootstrap seeds initially and whenever they are due for a refresh.
+        if let Some(Poll::Ready(())) = self.next_seed_dial.as_mut().map(|d| d.poll_unpin(cx)) {
+            self.dial_bootstrap_seeds();
+        }
+
         // Poll bootstrap periodically and automatically.
         if let Poll::Ready(()) = self.bootstrap_status.poll_next_bootstrap(cx) {
             if let Err(e) = self.bootstrap() {
@@ -2541,6 +3244,18 @@ where
             // Drain applied pending entries from the routing table.
             if let Some(entry) = self.kbuckets.take_applied_pending() {
                 let kbucket::Node { key, value } = entry.inserted;
+                if let Some(evicted) = &entry.evicted {
+                    self.last_seen.remove(evicted.key.preimage());
+                    if let Some(diversity) = &mut self.diversity {
+                        diversity.remove(evicted.key.preimage());
+                    }
+                }
+                if let Some(diversity) = &mut self.diversity {
+                    diversity.insert(*key.preimage(), value.iter());
+                }
+                if self.connected_peers.contains(key.preimage()) {
+                    self.last_seen.insert(*key.preimage(), self.clock.now());
+                }
                 let event = Event::RoutingUpdated {
                     bucket_range: self
                         .kbuckets
@@ -2564,7 +3279,7 @@ where
                         }
                     }
                     QueryPoolState::Timeout(q) => {
-                        if let Some(event) = self.query_timeout(q) {
+                        if let Some(event) = self.query_timeout(q, false) {
                             return Poll::Ready(ToSwarm::GenerateEvent(event));
                         }
                     }
@@ -2606,6 +3321,23 @@ where
             if self.queued_events.is_empty() {
                 self.no_events_waker = Some(cx.waker().clone());
 
+                // Wake up once the next query deadline passes.
+                match self.queries.next_deadline() {
+                    Some(deadline) => {
+                        let (_, timer) = match &mut self.query_deadline {
+                            Some(d) if d.0 == deadline => d,
+                            d => d.insert((
+                                deadline,
+                                self.clock.sleep(deadline.saturating_duration_since(now)),
+                            )),
+                        };
+                        if timer.poll_unpin(cx).is_ready() {
+                            cx.waker().wake_by_ref();
+                        }
+                    }
+                    None => self.query_deadline = None,
+                }
+
                 return Poll::Pending;
             }
         }
@@ -2664,6 +3396,12 @@ pub struct PeerRecord {
     /// retrieved from local storage.
     pub peer: Option<PeerId>,
     pub record: Record,
+    /// On how many disjoint paths of the query a record with the same value was received so
+    /// far, including this one. `0` if the record was retrieved from local storage.
+    ///
+    /// Unless the query uses disjoint paths, see [`Behaviour::get_record_disjoint`] and
+    /// [`Config::disjoint_query_paths`], all records are received on a single path.
+    pub confirmed_paths: usize,
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -2715,17 +3453,18 @@ pub enum Event {
     /// A peer has connected for whom no listen address is known.
     ///
     /// If the peer is to be added to the routing table, a known
-    /// listen address for the peer must be provided via [`Behaviour::add_address`].
+    /// listen address for the peer must be provided via [`Behaviour::add_address`],
+    /// or [`Behaviour::confirm_address`] with [`BucketInserts::OnConfirmed`].
     UnroutablePeer { peer: PeerId },
 
     /// A connection to a peer has been established for whom a listen address
     /// is known but the peer has not been added to the routing table either
-    /// because [`BucketInserts::Manual`] is configured or because
-    /// the corresponding bucket is full.
+    /// because [`BucketInserts::Manual`] or [`BucketInserts::OnConfirmed`] is
+    /// configured or because the corresponding bucket is full.
     ///
     /// If the peer is to be included in the routing table, it must
-    /// must be explicitly added via [`Behaviour::add_address`], possibly after
-    /// removing another peer.
+    /// must be explicitly added via [`Behaviour::add_address`], respectively
+    /// [`Behaviour::confirm_address`], possibly after removing another peer.
     ///
     /// See [`Behaviour::kbucket`] for insight into the contents of
     /// the k-bucket of `peer`.
@@ -2744,11 +3483,35 @@ pub enum Event {
     /// the k-bucket of `peer`.
     PendingRoutablePeer { peer: PeerId, address: Multiaddr },
 
+    /// An address of a peer has not been inserted into the routing table because the
+    /// routing table already contains the maximum number of peers in one of the groups of
+    /// the address, see [`Config::set_diversity_filter`].
+    DiversityLimitExceeded {
+        peer: PeerId,
+        address: Multiaddr,
+        /// The group whose limit has been reached.
+        group: DiversityGroup,
+    },
+
     /// This peer's mode has been updated automatically.
     ///
     /// This happens in response to an external
     /// address being added or removed.
     ModeChanged { new_mode: Mode },
+
+    /// The periodic re-publication of the provider records of the keys provided by the
+    /// local node has made progress.
+    ///
+    /// The outcome of the individual re-publications is reported via
+    /// [`Event::OutboundQueryProgressed`] with [`QueryResult::RepublishProvider`].
+    ReprovideProgressed {
+        /// The number of keys re-published in the current run so far.
+        republished: usize,
+        /// The total number of keys to re-publish in the current run.
+        ///
+        /// Expired keys are removed instead of re-published and count towards neither number.
+        total: usize,
+    },
 }
 
 /// Information about progress events.
@@ -2880,6 +3643,9 @@ pub enum GetRecordError {
     },
     #[error("the request timed out")]
     Timeout { key: record::Key },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted { key: record::Key },
 }
 
 impl GetRecordError {
@@ -2889,6 +3655,7 @@ impl GetRecordError {
             GetRecordError::QuorumFailed { key, .. } => key,
             GetRecordError::Timeout { key, .. } => key,
             GetRecordError::NotFound { key, .. } => key,
+            GetRecordError::Aborted { key, .. } => key,
         }
     }
 
@@ -2899,6 +3666,7 @@ impl GetRecordError {
             GetRecordError::QuorumFailed { key, .. } => key,
             GetRecordError::Timeout { key, .. } => key,
             GetRecordError::NotFound { key, .. } => key,
+            GetRecordError::Aborted { key, .. } => key,
         }
     }
 }
@@ -2929,6 +3697,14 @@ pub enum PutRecordError {
         success: Vec<PeerId>,
         quorum: NonZeroUsize,
     },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted {
+        key: record::Key,
+        /// [`PeerId`]s of the peers the record was successfully stored on.
+        success: Vec<PeerId>,
+        quorum: NonZeroUsize,
+    },
 }
 
 impl PutRecordError {
@@ -2937,6 +3713,7 @@ impl PutRecordError {
         match self {
             PutRecordError::QuorumFailed { key, .. } => key,
             PutRecordError::Timeout { key, .. } => key,
+            PutRecordError::Aborted { key, .. } => key,
         }
     }
 
@@ -2946,6 +3723,7 @@ impl PutRecordError {
         match self {
             PutRecordError::QuorumFailed { key, .. } => key,
             PutRecordError::Timeout { key, .. } => key,
+            PutRecordError::Aborted { key, .. } => key,
         }
     }
 }
@@ -2968,6 +3746,12 @@ pub enum BootstrapError {
         peer: PeerId,
         num_remaining: Option<u32>,
     },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted {
+        peer: PeerId,
+        num_remaining: Option<u32>,
+    },
 }
 
 /// The result of [`Behaviour::get_closest_peers`].
@@ -2985,6 +3769,9 @@ pub struct GetClosestPeersOk {
 pub enum GetClosestPeersError {
     #[error("the request timed out")]
     Timeout { key: Vec<u8>, peers: Vec<PeerId> },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted { key: Vec<u8>, peers: Vec<PeerId> },
 }
 
 impl GetClosestPeersError {
@@ -2992,6 +3779,7 @@ impl GetClosestPeersError {
     pub fn key(&self) -> &Vec<u8> {
         match self {
             GetClosestPeersError::Timeout { key, .. } => key,
+            GetClosestPeersError::Aborted { key, .. } => key,
         }
     }
 
@@ -3000,6 +3788,7 @@ impl GetClosestPeersError {
     pub fn into_key(self) -> Vec<u8> {
         match self {
             GetClosestPeersError::Timeout { key, .. } => key,
+            GetClosestPeersError::Aborted { key, .. } => key,
         }
     }
 }
@@ -3028,6 +3817,12 @@ pub enum GetProvidersError {
         key: record::Key,
         closest_peers: Vec<PeerId>,
     },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted {
+        key: record::Key,
+        closest_peers: Vec<PeerId>,
+    },
 }
 
 impl GetProvidersError {
@@ -3035,6 +3830,7 @@ impl GetProvidersError {
     pub fn key(&self) -> &record::Key {
         match self {
             GetProvidersError::Timeout { key, .. } => key,
+            GetProvidersError::Aborted { key, .. } => key,
         }
     }
 
@@ -3043,6 +3839,7 @@ impl GetProvidersError {
     pub fn into_key(self) -> record::Key {
         match self {
             GetProvidersError::Timeout { key, .. } => key,
+            GetProvidersError::Aborted { key, .. } => key,
         }
     }
 }
@@ -3061,6 +3858,9 @@ pub struct AddProviderOk {
 pub enum AddProviderError {
     #[error("the request timed out")]
     Timeout { key: record::Key },
+    /// The query was stopped via [`Behaviour::abort_query`].
+    #[error("the query was aborted")]
+    Aborted { key: record::Key },
 }
 
 impl AddProviderError {
@@ -3068,6 +3868,7 @@ impl AddProviderError {
     pub fn key(&self) -> &record::Key {
         match self {
             AddProviderError::Timeout { key, .. } => key,
+            AddProviderError::Aborted { key, .. } => key,
         }
     }
 
@@ -3075,6 +3876,7 @@ impl AddProviderError {
     pub fn into_key(self) -> record::Key {
         match self {
             AddProviderError::Timeout { key, .. } => key,
+            AddProviderError::Aborted { key, .. } => key,
         }
     }
 }
@@ -3088,6 +3890,7 @@ impl From<kbucket::EntryView<kbucket::Key<PeerId>, Addresses>> for KadPeer {
                 NodeStatus::Connected => ConnectionType::Connected,
                 NodeStatus::Disconnected => ConnectionType::NotConnected,
             },
+            signed_record: None,
         }
     }
 }
@@ -3210,6 +4013,9 @@ pub enum QueryInfo {
         /// The peers closest to the `key` that were queried but did not return a record,
         /// i.e. the peers that are candidates for caching the record.
         cache_candidates: BTreeMap<kbucket::Distance, PeerId>,
+        /// The distinct record values received so far, with the indices of the disjoint
+        /// paths on which each of them was received.
+        paths_by_value: Vec<(Vec<u8>, HashSet<usize>)>,
     },
 }
 
@@ -3238,6 +4044,7 @@ impl QueryInfo {
                 AddProviderPhase::AddProvider {
                     provider_id,
                     external_addresses,
+                    signed_record,
                     ..
                 } => HandlerIn::AddProvider {
                     key: key.clone(),
@@ -3245,6 +4052,7 @@ impl QueryInfo {
                         node_id: *provider_id,
                         multiaddrs: external_addresses.clone(),
                         connection_ty: crate::protocol::ConnectionType::Connected,
+                        signed_record: signed_record.clone(),
                     },
                     query_id,
                 },
@@ -3280,6 +4088,9 @@ pub enum AddProviderPhase {
         provider_id: PeerId,
         /// The external addresses of the provider being advertised.
         external_addresses: Vec<Multiaddr>,
+        /// The signed peer record of the provider being advertised,
+        /// see [`Behaviour::set_local_peer_record`].
+        signed_record: Option<libp2p_core::PeerRecord>,
         /// Query statistics from the finished `GetClosestPeers` phase.
         get_closest_peers_stats: QueryStats,
     },
@@ -3303,6 +4114,8 @@ pub enum PutRecordPhase {
 /// A mutable reference to a running query.
 pub struct QueryMut<'a> {
     query: &'a mut Query<QueryInner>,
+    /// The current time as per [`Config::set_clock`].
+    now: Instant,
 }
 
 impl<'a> QueryMut<'a> {
@@ -3328,6 +4141,22 @@ impl<'a> QueryMut<'a> {
     pub fn finish(&mut self) {
         self.query.finish()
     }
+
+    /// Bounds the query to the given timeout, counted from now as per
+    /// [`Config::set_clock`].
+    ///
+    /// Once it elapses, the query terminates like one that exceeded
+    /// [`Config::set_query_timeout`], regardless of whether it is still
+    /// waiting for responses. Unlike the latter, the deadline spans all
+    /// phases of a multi-phase query such as `put_record`. Intended to be
+    /// set right after issuing the query.
+    ///
+    /// Once the behaviour has been polled again, it wakes itself up at the
+    /// deadline, it need not be polled for other reasons for the query to
+    /// time out.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.query.set_deadline(self.now + timeout)
+    }
 }
 
 /// An immutable reference to a running query.
@@ -3366,6 +4195,39 @@ impl fmt::Display for NoKnownPeers {
 
 impl std::error::Error for NoKnownPeers {}
 
+/// Information about a peer in the routing table, see [`Behaviour::routing_table_peer`].
+#[derive(Debug, Clone)]
+pub struct RoutingTablePeer {
+    /// The ID of the peer.
+    pub peer_id: PeerId,
+    /// The known addresses of the peer.
+    pub addresses: Addresses,
+    /// Whether the peer is considered connected.
+    pub status: NodeStatus,
+    /// Whether the peer is pending insertion into a full bucket.
+    pub is_pending: bool,
+    /// When the peer last connected, i.e. when the Kademlia protocol was last confirmed on a new
+    /// connection to it, if ever since it was added to the routing table.
+    ///
+    /// This is not updated by later requests or responses on an existing connection. Peers added
+    /// via [`Behaviour::add_address`] or pending insertion have not been seen until they connect
+    /// while in the routing table.
+    pub last_seen: Option<Instant>,
+}
+
+/// The occupancy of a k-bucket, see [`Behaviour::bucket_occupancy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketOccupancy {
+    /// The minimum inclusive and maximum inclusive distance of peers in the bucket.
+    pub range: (kbucket::Distance, kbucket::Distance),
+    /// The number of peers in the bucket.
+    pub num_entries: usize,
+    /// The number of peers in the bucket that are considered connected.
+    pub num_connected: usize,
+    /// Whether a peer is pending insertion into the bucket.
+    pub has_pending: bool,
+}
+
 /// The possible outcomes of [`Behaviour::add_address`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RoutingUpdate {
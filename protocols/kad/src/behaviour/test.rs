@@ -38,6 +38,7 @@ use libp2p_swarm::{self as swarm, Swarm, SwarmEvent};
 use libp2p_yamux as yamux;
 use quickcheck::*;
 use rand::{random, rngs::StdRng, thread_rng, Rng, SeedableRng};
+use std::sync::{Arc, Mutex};
 
 type TestSwarm = Swarm<Behaviour<MemoryStore>>;
 
@@ -872,6 +873,8 @@ fn add_provider() {
         // Disabling periodic bootstrap and automatic bootstrap to prevent the bootstrap from triggering automatically.
         config.set_periodic_bootstrap_interval(None);
         config.set_automatic_bootstrap_throttle(None);
+        // Republish all keys at once when the provider job is told to run asap.
+        config.set_provider_publication_spread(false);
         if rng.gen() {
             config.disjoint_query_paths(true);
         }
@@ -1043,6 +1046,70 @@ fn add_provider() {
     QuickCheck::new().tests(3).quickcheck(prop as fn(_, _))
 }
 
+#[test]
+fn provide_set_is_restored_and_updated() {
+    #[derive(Clone, Default)]
+    struct SharedProvideSet(Arc<Mutex<HashSet<Key>>>);
+
+    impl ProvideSet for SharedProvideSet {
+        fn load(&mut self) -> std::io::Result<Vec<Key>> {
+            Ok(self.0.lock().unwrap().iter().cloned().collect())
+        }
+
+        fn insert(&mut self, key: &Key) -> std::io::Result<()> {
+            self.0.lock().unwrap().insert(key.clone());
+            Ok(())
+        }
+
+        fn remove(&mut self, key: &Key) -> std::io::Result<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    let provide_set = SharedProvideSet::default();
+    let restored = [
+        Key::new(&random::<[u8; 32]>()),
+        Key::new(&random::<[u8; 32]>()),
+    ];
+    for key in &restored {
+        provide_set.0.lock().unwrap().insert(key.clone());
+    }
+
+    let (_, mut swarm) = build_node();
+    swarm
+        .behaviour_mut()
+        .set_provide_set(provide_set.clone())
+        .unwrap();
+    assert_eq!(swarm.behaviour_mut().store.provided().count(), 2);
+
+    let new_key = Key::new(&random::<[u8; 32]>());
+    swarm
+        .behaviour_mut()
+        .start_providing(new_key.clone())
+        .unwrap();
+    assert!(provide_set.0.lock().unwrap().contains(&new_key));
+
+    swarm.behaviour_mut().stop_providing(&restored[0]);
+    assert!(!provide_set.0.lock().unwrap().contains(&restored[0]));
+
+    // The restored keys are re-published right away, spread over the publication interval.
+    block_on(poll_fn(|ctx| loop {
+        match swarm.poll_next_unpin(ctx) {
+            Poll::Ready(Some(SwarmEvent::Behaviour(Event::ReprovideProgressed {
+                republished,
+                total,
+            }))) => {
+                assert_eq!((republished, total), (1, 2));
+                return Poll::Ready(());
+            }
+            Poll::Ready(Some(_)) => {}
+            Poll::Ready(None) => panic!("Swarm terminated"),
+            Poll::Pending => panic!("Expected provider records to be republished"),
+        }
+    }))
+}
+
 /// User code should be able to start queries beyond the internal
 /// query limit for background jobs. Originally this even produced an
 /// arithmetic overflow, see https://github.com/libp2p/rust-libp2p/issues/1290.
@@ -1237,7 +1304,80 @@ fn disjoint_query_does_not_finish_before_all_paths_did() {
     assert!(records.contains(&PeerRecord {
         peer: Some(*Swarm::local_peer_id(&bob)),
         record: record_bob,
+        confirmed_paths: 1,
+    }));
+}
+
+#[test]
+fn get_record_disjoint_reports_confirmed_paths() {
+    let mut config = Config::new(PROTOCOL_NAME);
+    // Disabling periodic bootstrap and automatic bootstrap to prevent the bootstrap from triggering automatically.
+    config.set_periodic_bootstrap_interval(None);
+    config.set_automatic_bootstrap_throttle(None);
+
+    let mut alice = build_node_with_config(config);
+    let mut bob = build_node();
+    let mut carol = build_node();
+
+    let key = Key::from(
+        Multihash::<64>::wrap(SHA_256_MH, &thread_rng().gen::<[u8; 32]>())
+            .expect("32 array to fit into 64 byte multihash"),
+    );
+    let record = Record::new(key.clone(), b"value".to_vec());
+    bob.1.behaviour_mut().store.put(record.clone()).unwrap();
+    carol.1.behaviour_mut().store.put(record).unwrap();
+
+    alice
+        .1
+        .behaviour_mut()
+        .add_address(bob.1.local_peer_id(), bob.0.clone());
+    alice
+        .1
+        .behaviour_mut()
+        .add_address(carol.1.local_peer_id(), carol.0.clone());
+
+    let (mut alice, mut bob, mut carol) = (alice.1, bob.1, carol.1);
+
+    // `disjoint_query_paths` is disabled, only this query uses disjoint paths.
+    let query_id = alice
+        .behaviour_mut()
+        .get_record_disjoint(key, NonZeroUsize::new(2).unwrap());
+
+    let mut confirmed_paths = Vec::new();
+    block_on(poll_fn(|ctx| {
+        for swarm in [&mut bob, &mut carol] {
+            while let Poll::Ready(Some(_)) = swarm.poll_next_unpin(ctx) {}
+        }
+        loop {
+            match alice.poll_next_unpin(ctx) {
+                Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                    id,
+                    result: QueryResult::GetRecord(result),
+                    step,
+                    ..
+                }))) => {
+                    assert_eq!(id, query_id);
+                    match result {
+                        Ok(GetRecordOk::FoundRecord(record)) => {
+                            confirmed_paths.push(record.confirmed_paths)
+                        }
+                        Ok(GetRecordOk::FinishedWithNoAdditionalRecord { .. }) => {}
+                        Err(e) => panic!("{e:?}"),
+                    }
+                    if step.last {
+                        return Poll::Ready(());
+                    }
+                }
+                // Ignore any other event.
+                Poll::Ready(Some(_)) => (),
+                Poll::Ready(None) => panic!("Expected Kademlia behaviour not to finish."),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
     }));
+
+    confirmed_paths.sort();
+    assert_eq!(confirmed_paths, vec![1, 2]);
 }
 
 /// Tests that peers are not automatically inserted into
@@ -1294,6 +1434,55 @@ fn manual_bucket_inserts() {
     }));
 }
 
+/// Tests that peers are only inserted into the routing table once
+/// their address is confirmed with `BucketInserts::OnConfirmed`.
+#[test]
+fn confirmed_bucket_inserts() {
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_kbucket_inserts(BucketInserts::OnConfirmed);
+    // 1 -> 2 -> 3
+    let mut swarms = build_connected_nodes_with_config(3, 1, cfg);
+    let (address, third) = (swarms[2].0.clone(), *swarms[2].1.local_peer_id());
+
+    // Start an iterative query from the first peer, making it connect to the third.
+    swarms[0]
+        .1
+        .behaviour_mut()
+        .get_closest_peers(PeerId::random());
+    block_on(poll_fn(|ctx| {
+        let mut routable = false;
+        for (i, (_, swarm)) in swarms.iter_mut().enumerate() {
+            while let Poll::Ready(Some(event)) = swarm.poll_next_unpin(ctx) {
+                if let SwarmEvent::Behaviour(Event::RoutablePeer { peer, .. }) = event {
+                    routable |= i == 0 && peer == third;
+                }
+            }
+        }
+        if routable {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }));
+
+    let kad = swarms[0].1.behaviour_mut();
+    let in_bucket = |kad: &mut Behaviour<MemoryStore>| {
+        kad.kbucket(third)
+            .unwrap()
+            .iter()
+            .any(|e| *e.node.key.preimage() == third)
+    };
+    assert!(!in_bucket(kad));
+    assert!(matches!(
+        kad.confirm_address(&PeerId::random(), address.clone()),
+        RoutingUpdate::Failed
+    ));
+    assert!(matches!(
+        kad.confirm_address(&third, address),
+        RoutingUpdate::Success
+    ));
+    assert!(in_bucket(kad));
+}
+
 #[test]
 fn network_behaviour_on_address_change() {
     let local_peer_id = PeerId::random();
@@ -1317,6 +1506,7 @@ fn network_behaviour_on_address_change() {
         endpoint: &endpoint,
         failed_addresses: &[],
         other_established: 0,
+        negotiated_protocols: &Default::default(),
     }));
 
     // At this point the remote is not yet known to support the
@@ -1533,3 +1723,472 @@ fn get_providers_limit_n_2() {
 fn get_providers_limit_n_5() {
     get_providers_limit::<5>();
 }
+
+#[test]
+fn peer_routing_only_neither_serves_nor_stores_records() {
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_peer_routing_only(true);
+    let (server_addr, mut server) = build_node_with_config(cfg);
+    let (_, mut client) = build_node();
+
+    let server_peer_id = *server.local_peer_id();
+    client
+        .behaviour_mut()
+        .add_address(&server_peer_id, server_addr);
+
+    let stored = Record::new(random_multihash(), vec![4, 5, 6]);
+    server.behaviour_mut().store.put(stored.clone()).unwrap();
+    let get_qid = client.behaviour_mut().get_record(stored.key.clone());
+
+    let put = Record::new(random_multihash(), vec![7, 8, 9]);
+    let put_qid = client.behaviour_mut().put_record_to(
+        put.clone(),
+        std::iter::once(server_peer_id),
+        Quorum::One,
+    );
+
+    let mut swarms = [server, client];
+    let mut get_result = None;
+    let mut put_result = None;
+    let (get_result, put_result) = block_on(poll_fn(|ctx| {
+        for swarm in swarms.iter_mut() {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        id,
+                        result: QueryResult::GetRecord(result),
+                        ..
+                    }))) if id == get_qid => get_result = Some(result),
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        id,
+                        result: QueryResult::PutRecord(result),
+                        ..
+                    }))) if id == put_qid => put_result = Some(result),
+                    Poll::Ready(Some(_)) => {}
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+        if get_result.is_some() && put_result.is_some() {
+            return Poll::Ready((get_result.take().unwrap(), put_result.take().unwrap()));
+        }
+        Poll::Pending
+    }));
+
+    assert!(matches!(get_result, Err(GetRecordError::NotFound { .. })));
+    assert!(matches!(
+        put_result,
+        Err(PutRecordError::QuorumFailed { .. })
+    ));
+    assert!(swarms[0].behaviour_mut().store.get(&put.key).is_none());
+}
+
+#[test]
+fn address_filter_applies_to_routing_table_inserts() {
+    let local_id = PeerId::random();
+    let mut kad = Behaviour::new(local_id, MemoryStore::new(local_id));
+    let private: Multiaddr = "/ip4/192.168.1.10/tcp/4001".parse().unwrap();
+    let public: Multiaddr = "/ip4/8.8.8.8/tcp/4001".parse().unwrap();
+
+    kad.set_mode(Some(Mode::Server));
+    assert!(matches!(
+        kad.add_address(&PeerId::random(), private.clone()),
+        RoutingUpdate::Failed
+    ));
+    assert!(matches!(
+        kad.add_address(&PeerId::random(), public.clone()),
+        RoutingUpdate::Success
+    ));
+
+    kad.set_mode(Some(Mode::Client));
+    assert!(matches!(
+        kad.add_address(&PeerId::random(), private.clone()),
+        RoutingUpdate::Success
+    ));
+
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_address_filter(AddressFilter::custom(|_, address| {
+        !address.iter().any(|p| matches!(p, Protocol::Tcp(_)))
+    }));
+    let mut kad = Behaviour::with_config(local_id, MemoryStore::new(local_id), cfg);
+    assert!(matches!(
+        kad.add_address(&PeerId::random(), public),
+        RoutingUpdate::Failed
+    ));
+}
+
+#[test]
+fn diversity_filter_limits_peers_per_prefix() {
+    let local_id = PeerId::random();
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_diversity_filter(DiversityFilter::new(2).with_ipv4_prefix_len(24));
+    let mut kad = Behaviour::with_config(local_id, MemoryStore::new(local_id), cfg);
+    kad.set_mode(Some(Mode::Server));
+
+    let first = PeerId::random();
+    for (peer, address) in [
+        (first, "/ip4/8.8.8.1/tcp/4001"),
+        (PeerId::random(), "/ip4/8.8.8.2/tcp/4001"),
+    ] {
+        assert!(matches!(
+            kad.add_address(&peer, address.parse().unwrap()),
+            RoutingUpdate::Success
+        ));
+    }
+
+    let refused = PeerId::random();
+    let address: Multiaddr = "/ip4/8.8.8.3/tcp/4001".parse().unwrap();
+    assert!(matches!(
+        kad.add_address(&refused, address),
+        RoutingUpdate::Failed
+    ));
+    assert!(kad.queued_events.iter().any(|event| matches!(
+        event,
+        ToSwarm::GenerateEvent(Event::DiversityLimitExceeded {
+            peer,
+            group: DiversityGroup::IpPrefix(prefix, 24),
+            ..
+        }) if *peer == refused && *prefix == std::net::Ipv4Addr::new(8, 8, 8, 0)
+    )));
+
+    // Peers already in the group may add further addresses of it.
+    assert!(matches!(
+        kad.add_address(&first, "/ip4/8.8.8.4/tcp/4001".parse().unwrap()),
+        RoutingUpdate::Success
+    ));
+    // Peers outside of the group are not limited.
+    assert!(matches!(
+        kad.add_address(&refused, "/ip4/8.8.9.1/tcp/4001".parse().unwrap()),
+        RoutingUpdate::Success
+    ));
+
+    // Removing a peer frees its slot in the group.
+    kad.remove_peer(&first);
+    assert!(matches!(
+        kad.add_address(&refused, "/ip4/8.8.8.3/tcp/4001".parse().unwrap()),
+        RoutingUpdate::Success
+    ));
+}
+
+#[test]
+fn routing_table_can_be_inspected_locally() {
+    let local_id = PeerId::random();
+    let mut kad = Behaviour::new(local_id, MemoryStore::new(local_id));
+    kad.set_mode(Some(Mode::Server));
+
+    let peers = (0..5).map(|_| PeerId::random()).collect::<Vec<_>>();
+    for peer in &peers {
+        assert!(matches!(
+            kad.add_address(peer, "/ip4/8.8.8.8/tcp/4001".parse().unwrap()),
+            RoutingUpdate::Success
+        ));
+    }
+    let connected = peers[0];
+    kad.connection_updated(connected, None, NodeStatus::Connected);
+
+    let info = kad.routing_table_peer(&connected).unwrap();
+    assert_eq!(info.status, NodeStatus::Connected);
+    assert!(!info.is_pending);
+    assert!(info.last_seen.is_some());
+    let info = kad.routing_table_peer(&peers[1]).unwrap();
+    assert_eq!(info.status, NodeStatus::Disconnected);
+    assert!(info.last_seen.is_none());
+    assert!(kad.routing_table_peer(&PeerId::random()).is_none());
+
+    let target = kbucket::Key::from(PeerId::random());
+    let closest = kad
+        .closest_routing_table_peers(&target)
+        .map(|p| p.peer_id)
+        .collect::<Vec<_>>();
+    let expected = kad
+        .get_closest_local_peers(&target)
+        .map(|k| k.into_preimage())
+        .collect::<Vec<_>>();
+    assert_eq!(closest, expected);
+    assert_eq!(closest.len(), peers.len());
+
+    let occupancy = kad.bucket_occupancy();
+    assert_eq!(occupancy.len(), 256);
+    assert_eq!(
+        occupancy.iter().map(|b| b.num_entries).sum::<usize>(),
+        peers.len()
+    );
+    assert_eq!(occupancy.iter().map(|b| b.num_connected).sum::<usize>(), 1);
+
+    kad.remove_peer(&connected);
+    assert!(kad.routing_table_peer(&connected).is_none());
+    assert!(!kad.last_seen.contains_key(&connected));
+}
+
+#[test]
+fn last_seen_is_not_recorded_for_pending_peers() {
+    let local_id = PeerId::random();
+    let local_key = kbucket::Key::from(local_id);
+    let mut kad = Behaviour::new(local_id, MemoryStore::new(local_id));
+    kad.set_mode(Some(Mode::Server));
+
+    // Fill the farthest bucket with disconnected peers, plus one more peer to be pending.
+    let mut peers = std::iter::repeat_with(PeerId::random)
+        .filter(|p| local_key.distance(&kbucket::Key::from(*p)).ilog2() == Some(255))
+        .take(K_VALUE.get() + 1)
+        .collect::<Vec<_>>();
+    let pending = peers.pop().unwrap();
+    for (i, peer) in peers.iter().enumerate() {
+        assert!(matches!(
+            kad.add_address(peer, format!("/ip4/100.{i}.8.8/tcp/4001").parse().unwrap()),
+            RoutingUpdate::Success
+        ));
+    }
+
+    kad.connection_updated(
+        pending,
+        Some("/ip4/101.8.8.8/tcp/4001".parse().unwrap()),
+        NodeStatus::Connected,
+    );
+
+    let info = kad.routing_table_peer(&pending).unwrap();
+    assert!(info.is_pending);
+    assert!(info.last_seen.is_none());
+    assert!(!kad.last_seen.contains_key(&pending));
+}
+
+#[test]
+fn provider_records_are_verified_and_filtered() {
+    let local_id = PeerId::random();
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_provider_filter(|record| {
+        record
+            .addresses
+            .retain(|a| !a.iter().any(|p| matches!(p, Protocol::Udp(_))));
+        record.key.as_ref() != b"rejected"
+    });
+    let mut kad = Behaviour::with_config(local_id, MemoryStore::new(local_id), cfg);
+    kad.set_mode(Some(Mode::Server));
+
+    let keypair = identity::Keypair::generate_ed25519();
+    let provider = keypair.public().to_peer_id();
+    let signed: Multiaddr = "/ip4/8.8.8.8/tcp/4001".parse().unwrap();
+    let signed_udp: Multiaddr = "/ip4/8.8.8.8/udp/4001/quic-v1".parse().unwrap();
+    let signed_private: Multiaddr = "/ip4/192.168.1.10/tcp/4001".parse().unwrap();
+    let unsigned: Multiaddr = "/ip4/1.1.1.1/tcp/4001".parse().unwrap();
+    let signed_record =
+        libp2p_core::PeerRecord::new(&keypair, vec![signed.clone(), signed_udp, signed_private])
+            .unwrap();
+
+    let mut add_provider = |key: &[u8], signed_record: Option<libp2p_core::PeerRecord>| {
+        let key = record::Key::new(&key);
+        kad.on_connection_handler_event(
+            provider,
+            ConnectionId::new_unchecked(0),
+            HandlerEvent::AddProvider {
+                key: key.clone(),
+                provider: KadPeer {
+                    node_id: provider,
+                    multiaddrs: vec![unsigned.clone().with_p2p(provider).unwrap()],
+                    connection_ty: ConnectionType::Connected,
+                    signed_record,
+                },
+            },
+        );
+        kad.store
+            .providers(&key)
+            .into_iter()
+            .map(|r| r.addresses)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        add_provider(b"unsigned", None),
+        vec![vec![unsigned.clone().with_p2p(provider).unwrap()]]
+    );
+    // Only the public TCP address of the signed record remains.
+    assert_eq!(
+        add_provider(b"signed", Some(signed_record.clone())),
+        vec![vec![signed.with_p2p(provider).unwrap()]]
+    );
+    assert!(add_provider(b"rejected", Some(signed_record)).is_empty());
+}
+
+#[test]
+fn dials_bootstrap_seeds_and_bootstraps() {
+    let (server_addr, server) = build_node();
+    let server_peer_id = *server.local_peer_id();
+
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_bootstrap_seeds(vec![server_addr]);
+    let (_, client) = build_node_with_config(cfg);
+
+    let mut swarms = [server, client];
+    let mut routing_updated = false;
+    block_on(poll_fn(|ctx| {
+        for (i, swarm) in swarms.iter_mut().enumerate() {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::RoutingUpdated {
+                        peer,
+                        ..
+                    }))) if i == 1 => {
+                        assert_eq!(peer, server_peer_id);
+                        routing_updated = true;
+                    }
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        result: QueryResult::Bootstrap(Ok(_)),
+                        step,
+                        ..
+                    }))) if i == 1 && step.last => {
+                        assert!(routing_updated, "Expect seed to be in the routing table.");
+                        return Poll::Ready(());
+                    }
+                    Poll::Ready(Some(_)) => {}
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+        Poll::Pending
+    }));
+}
+
+#[test]
+fn local_records_expire_according_to_clock() {
+    let clock = swarm::VirtualClock::new();
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_clock(clock.clone());
+    let local_id = PeerId::random();
+    let mut kad = Behaviour::with_config(local_id, MemoryStore::new(local_id), cfg);
+
+    let key = Key::from(random_multihash());
+    let mut record = Record::new(key.clone(), vec![1]);
+    record.expires = Some(clock.now() + Duration::from_secs(60));
+    kad.store_mut().put(record).unwrap();
+
+    clock.advance(Duration::from_secs(59));
+    kad.get_record(key.clone());
+    assert!(kad.store_mut().get(&key).is_some());
+
+    clock.advance(Duration::from_secs(1));
+    kad.get_record(key.clone());
+    assert!(kad.store_mut().get(&key).is_none());
+}
+
+#[test]
+fn aborted_query_reports_partial_result() {
+    let local_id = PeerId::random();
+    let mut kad = Behaviour::with_config(
+        local_id,
+        MemoryStore::new(local_id),
+        Config::new(PROTOCOL_NAME),
+    );
+    for _ in 0..10 {
+        kad.add_address(&PeerId::random(), Protocol::Udp(10u16).into());
+    }
+
+    let search_target = PeerId::random();
+    let qid = kad.get_closest_peers(search_target);
+
+    // Drive the query until it has contacted its first peers.
+    let mut dialed = Vec::new();
+    block_on(poll_fn(|ctx| loop {
+        match kad.poll(ctx) {
+            Poll::Ready(ToSwarm::Dial { opts }) => dialed.extend(opts.get_peer_id()),
+            Poll::Ready(_) => (),
+            Poll::Pending => return Poll::Ready(()),
+        }
+    }));
+    let responded = *dialed.first().expect("the query to contact a peer");
+    kad.on_connection_handler_event(
+        responded,
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::FindNodeRes {
+            closer_peers: vec![],
+            query_id: qid,
+        },
+    );
+
+    assert!(kad.abort_query(&qid));
+    assert!(kad.query(&qid).is_none());
+    assert!(!kad.abort_query(&qid));
+
+    block_on(poll_fn(move |ctx| loop {
+        match kad.poll(ctx) {
+            Poll::Ready(ToSwarm::GenerateEvent(Event::OutboundQueryProgressed {
+                id,
+                result: QueryResult::GetClosestPeers(result),
+                step,
+                stats,
+            })) => {
+                assert_eq!(id, qid);
+                assert!(step.last);
+                assert_eq!(stats.num_successes(), 1);
+                match result {
+                    Err(GetClosestPeersError::Aborted { key, peers }) => {
+                        assert_eq!(&key[..], search_target.to_bytes().as_slice());
+                        assert_eq!(peers, vec![responded]);
+                    }
+                    r => panic!("Expected the query to be aborted, got {r:?}."),
+                }
+                return Poll::Ready(());
+            }
+            Poll::Ready(_) => (),
+            Poll::Pending => panic!("Expected the aborted query to be reported."),
+        }
+    }))
+}
+
+#[test]
+fn query_times_out_at_its_deadline() {
+    let clock = swarm::VirtualClock::new();
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_clock(clock.clone());
+    // No other timer is to wake up the behaviour.
+    cfg.set_periodic_bootstrap_interval(None);
+    cfg.set_automatic_bootstrap_throttle(None);
+    let local_id = PeerId::random();
+    let mut kad = Behaviour::with_config(local_id, MemoryStore::new(local_id), cfg);
+    for _ in 0..10 {
+        kad.add_address(&PeerId::random(), Protocol::Udp(10u16).into());
+    }
+
+    let key = Key::from(random_multihash());
+    let qid = kad.get_record(key.clone());
+    kad.query_mut(&qid)
+        .unwrap()
+        .set_timeout(Duration::from_secs(10));
+
+    // Advance the clock once the behaviour is idle. The query times out even though it has yet
+    // to contact any peer, without the behaviour being polled other than on its own wake-up.
+    let (idle_tx, idle_rx) = std::sync::mpsc::channel();
+    let mut idle_tx = Some(idle_tx);
+    let advance = std::thread::spawn(move || {
+        idle_rx.recv().unwrap();
+        clock.advance(Duration::from_secs(10));
+    });
+
+    block_on(poll_fn(move |ctx| loop {
+        match kad.poll(ctx) {
+            Poll::Ready(ToSwarm::GenerateEvent(Event::OutboundQueryProgressed {
+                id,
+                result: QueryResult::GetRecord(result),
+                step,
+                ..
+            })) => {
+                assert_eq!(id, qid);
+                assert!(step.last);
+                match result {
+                    Err(GetRecordError::Timeout { key: k }) => assert_eq!(k, key),
+                    r => panic!("Expected the query to time out, got {r:?}."),
+                }
+                return Poll::Ready(());
+            }
+            Poll::Ready(_) => (),
+            Poll::Pending => {
+                if let Some(tx) = idle_tx.take() {
+                    tx.send(()).unwrap();
+                }
+                return Poll::Pending;
+            }
+        }
+    }));
+    advance.join().unwrap();
+}
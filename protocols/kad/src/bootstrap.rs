@@ -1,18 +1,23 @@
 use futures::FutureExt;
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 use std::time::Duration;
 
-use futures_timer::Delay;
+use libp2p_swarm::{Clock, Sleep};
 
 /// Default value chosen at `<https://github.com/libp2p/rust-libp2p/pull/4838#discussion_r1490184754>`.
 pub(crate) const DEFAULT_AUTOMATIC_THROTTLE: Duration = Duration::from_millis(500);
 
+/// The minimum time between dialing the bootstrap seeds because of a bootstrap with an empty
+/// routing table, preventing repeated bootstraps from flooding the seeds with dials.
+pub(crate) const MIN_SEED_DIAL_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub(crate) struct Status {
     /// If the user did not disable periodic bootstrap (by providing `None` for `periodic_interval`)
-    /// this is the periodic interval and the delay of the current period. When `Delay` finishes,
-    /// a bootstrap will be triggered and the `Delay` will be reset.
-    interval_and_delay: Option<(Duration, Delay)>,
+    /// this is the periodic interval and the delay of the current period. When the delay finishes,
+    /// a bootstrap will be triggered and the delay will be reset.
+    interval_and_delay: Option<(Duration, Sleep)>,
 
     /// Configured duration to wait before triggering a bootstrap when a new peer
     /// is inserted in the routing table. `None` if automatic bootstrap is disabled.
@@ -28,19 +33,23 @@ pub(crate) struct Status {
     current_bootstrap_requests: usize,
     /// Waker to wake up the `poll` method if progress is ready to be made.
     waker: Option<Waker>,
+    /// The source of time for the periodic and throttle delays.
+    clock: Arc<dyn Clock>,
 }
 
 impl Status {
     pub(crate) fn new(
         periodic_interval: Option<Duration>,
         automatic_throttle: Option<Duration>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         Self {
-            interval_and_delay: periodic_interval.map(|interval| (interval, Delay::new(interval))),
+            interval_and_delay: periodic_interval.map(|interval| (interval, clock.sleep(interval))),
             waker: None,
             automatic_throttle,
             throttle_timer: None,
             current_bootstrap_requests: 0,
+            clock,
         }
     }
 
@@ -50,7 +59,7 @@ impl Status {
         // A `throttle_timer` is useful to not trigger a batch of bootstraps when a
         // batch of peers is inserted into the routing table.
         if let Some(throttle_duration) = self.automatic_throttle {
-            self.throttle_timer = Some(throttle_duration.into());
+            self.throttle_timer = Some(ThrottleTimer::new(throttle_duration, &*self.clock));
         } else {
             // The user disabled bootstrapping on new peer in the routing table.
         }
@@ -72,7 +81,7 @@ impl Status {
 
         // Resetting the `delay` if any since a bootstrap request is being triggered right now.
         if let Some((interval, delay)) = self.interval_and_delay.as_mut() {
-            delay.reset(*interval);
+            *delay = self.clock.sleep(*interval);
         }
     }
 
@@ -144,15 +153,15 @@ impl Status {
 #[derive(Debug)]
 enum ThrottleTimer {
     Immediate,
-    Delay(Delay),
+    Delay(Sleep),
 }
 
-impl From<Duration> for ThrottleTimer {
-    fn from(value: Duration) -> Self {
+impl ThrottleTimer {
+    fn new(value: Duration, clock: &dyn Clock) -> Self {
         if value.is_zero() {
             Self::Immediate
         } else {
-            Self::Delay(Delay::new(value))
+            Self::Delay(clock.sleep(value))
         }
     }
 }
@@ -171,7 +180,9 @@ impl futures::Future for ThrottleTimer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_timer::Delay;
     use instant::Instant;
+    use libp2p_swarm::SystemClock;
 
     const MS_5: Duration = Duration::from_millis(5);
     const MS_100: Duration = Duration::from_millis(100);
@@ -188,7 +199,11 @@ mod tests {
 
     #[async_std::test]
     async fn immediate_automatic_bootstrap_is_triggered_immediately() {
-        let mut status = Status::new(Some(Duration::from_secs(1)), Some(Duration::ZERO));
+        let mut status = Status::new(
+            Some(Duration::from_secs(1)),
+            Some(Duration::ZERO),
+            Arc::new(SystemClock),
+        );
 
         await_and_do_bootstrap(&mut status).await; // Wait for periodic bootstrap
 
@@ -213,7 +228,11 @@ mod tests {
 
     #[async_std::test]
     async fn delayed_automatic_bootstrap_is_triggered_before_periodic_bootstrap() {
-        let mut status = Status::new(Some(Duration::from_secs(1)), Some(MS_5));
+        let mut status = Status::new(
+            Some(Duration::from_secs(1)),
+            Some(MS_5),
+            Arc::new(SystemClock),
+        );
 
         await_and_do_bootstrap(&mut status).await; // Wait for periodic bootstrap
 
@@ -238,7 +257,7 @@ mod tests {
 
     #[test]
     fn given_no_periodic_bootstrap_and_immediate_automatic_bootstrap_try_on_next_connection() {
-        let mut status = Status::new(None, Some(Duration::ZERO));
+        let mut status = Status::new(None, Some(Duration::ZERO), Arc::new(SystemClock));
 
         // User manually triggered a bootstrap
         do_bootstrap(&mut status);
@@ -254,7 +273,7 @@ mod tests {
     #[async_std::test]
     async fn given_periodic_bootstrap_when_routing_table_updated_then_wont_bootstrap_until_next_interval(
     ) {
-        let mut status = Status::new(Some(MS_100), Some(MS_5));
+        let mut status = Status::new(Some(MS_100), Some(MS_5), Arc::new(SystemClock));
 
         status.on_new_peer_in_routing_table();
 
@@ -274,7 +293,7 @@ mod tests {
     #[async_std::test]
     async fn given_no_periodic_bootstrap_and_automatic_bootstrap_when_new_entry_then_will_bootstrap(
     ) {
-        let mut status = Status::new(None, Some(Duration::ZERO));
+        let mut status = Status::new(None, Some(Duration::ZERO), Arc::new(SystemClock));
 
         status.on_new_peer_in_routing_table();
 
@@ -283,7 +302,7 @@ mod tests {
 
     #[async_std::test]
     async fn given_periodic_bootstrap_and_no_automatic_bootstrap_triggers_periodically() {
-        let mut status = Status::new(Some(MS_100), None);
+        let mut status = Status::new(Some(MS_100), None, Arc::new(SystemClock));
 
         let start = Instant::now();
         for i in 1..6 {
@@ -298,7 +317,7 @@ mod tests {
     #[async_std::test]
     async fn given_no_periodic_bootstrap_and_automatic_bootstrap_reset_throttle_when_multiple_peers(
     ) {
-        let mut status = Status::new(None, Some(MS_100));
+        let mut status = Status::new(None, Some(MS_100), Arc::new(SystemClock));
 
         status.on_new_peer_in_routing_table();
         for _ in 0..10 {
@@ -323,7 +342,7 @@ mod tests {
     #[async_std::test]
     async fn given_periodic_bootstrap_and_no_automatic_bootstrap_manually_triggering_prevent_periodic(
     ) {
-        let mut status = Status::new(Some(MS_100), None);
+        let mut status = Status::new(Some(MS_100), None, Arc::new(SystemClock));
 
         status.on_started(); // first manually triggering
         status.on_started(); // second manually triggering
@@ -0,0 +1,340 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::{multiaddr::Protocol, Multiaddr};
+use libp2p_identity::PeerId;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+};
+
+/// Limits the number of peers in the routing table whose addresses share an IP prefix or an
+/// autonomous system, making it harder to eclipse the local node with peers run from a single
+/// network.
+///
+/// A peer counts towards the group of each of its addresses. Addresses not starting with an IP
+/// address, e.g. DNS addresses, are not limited. Addresses exceeding a limit are not inserted
+/// into the routing table and reported via [`Event::DiversityLimitExceeded`](crate::Event).
+///
+/// ```
+/// # use libp2p_kad::DiversityFilter;
+/// // At most 2 peers per /24 IPv4 and /48 IPv6 prefix.
+/// let filter = DiversityFilter::new(2)
+///     .with_ipv4_prefix_len(24)
+///     .with_ipv6_prefix_len(48);
+/// ```
+#[derive(Clone)]
+pub struct DiversityFilter {
+    ipv4_prefix_len: u8,
+    ipv6_prefix_len: u8,
+    max_peers_per_prefix: usize,
+    asn_lookup: Option<AsnLookup>,
+}
+
+#[derive(Clone)]
+struct AsnLookup {
+    max_peers: usize,
+    lookup: Arc<dyn Fn(IpAddr) -> Option<u32> + Send + Sync>,
+}
+
+/// A group of peers limited by a [`DiversityFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiversityGroup {
+    /// The addresses within an IP prefix, given as the masked address and the prefix length.
+    IpPrefix(IpAddr, u8),
+    /// The addresses announced by an autonomous system.
+    Asn(u32),
+}
+
+impl DiversityFilter {
+    /// Creates a filter allowing at most `max_peers_per_prefix` peers per `/16` IPv4 and per
+    /// `/32` IPv6 prefix.
+    pub fn new(max_peers_per_prefix: usize) -> Self {
+        Self {
+            ipv4_prefix_len: 16,
+            ipv6_prefix_len: 32,
+            max_peers_per_prefix,
+            asn_lookup: None,
+        }
+    }
+
+    /// Sets the length of the IPv4 prefixes, capped at 32.
+    pub fn with_ipv4_prefix_len(mut self, len: u8) -> Self {
+        self.ipv4_prefix_len = len.min(32);
+        self
+    }
+
+    /// Sets the length of the IPv6 prefixes, capped at 128.
+    pub fn with_ipv6_prefix_len(mut self, len: u8) -> Self {
+        self.ipv6_prefix_len = len.min(128);
+        self
+    }
+
+    /// Additionally allows at most `max_peers_per_asn` peers per autonomous system, as returned
+    /// by `lookup`, e.g. backed by an IP-to-ASN table.
+    ///
+    /// Addresses for which `lookup` returns `None` are only limited by their prefix.
+    pub fn with_asn_lookup<F>(mut self, max_peers_per_asn: usize, lookup: F) -> Self
+    where
+        F: Fn(IpAddr) -> Option<u32> + Send + Sync + 'static,
+    {
+        self.asn_lookup = Some(AsnLookup {
+            max_peers: max_peers_per_asn,
+            lookup: Arc::new(lookup),
+        });
+        self
+    }
+
+    /// The groups the given address belongs to.
+    pub(crate) fn groups(&self, address: &Multiaddr) -> Vec<DiversityGroup> {
+        let ip = match address.iter().next() {
+            Some(Protocol::Ip4(ip)) => IpAddr::V4(ip),
+            Some(Protocol::Ip6(ip)) => match ip.to_ipv4_mapped() {
+                Some(ip) => IpAddr::V4(ip),
+                None => IpAddr::V6(ip),
+            },
+            _ => return Vec::new(),
+        };
+
+        let prefix = match ip {
+            IpAddr::V4(ip) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.ipv4_prefix_len))
+                    .unwrap_or(0);
+                DiversityGroup::IpPrefix(
+                    Ipv4Addr::from(u32::from(ip) & mask).into(),
+                    self.ipv4_prefix_len,
+                )
+            }
+            IpAddr::V6(ip) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.ipv6_prefix_len))
+                    .unwrap_or(0);
+                DiversityGroup::IpPrefix(
+                    Ipv6Addr::from(u128::from(ip) & mask).into(),
+                    self.ipv6_prefix_len,
+                )
+            }
+        };
+
+        let mut groups = vec![prefix];
+        if let Some(asn) = self
+            .asn_lookup
+            .as_ref()
+            .and_then(|asn_lookup| (asn_lookup.lookup)(ip))
+        {
+            groups.push(DiversityGroup::Asn(asn));
+        }
+        groups
+    }
+
+    /// The maximum number of peers in the given group.
+    pub(crate) fn limit(&self, group: &DiversityGroup) -> usize {
+        match group {
+            DiversityGroup::IpPrefix(..) => self.max_peers_per_prefix,
+            DiversityGroup::Asn(_) => self
+                .asn_lookup
+                .as_ref()
+                .map_or(usize::MAX, |asn_lookup| asn_lookup.max_peers),
+        }
+    }
+}
+
+impl fmt::Debug for DiversityFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiversityFilter")
+            .field("ipv4_prefix_len", &self.ipv4_prefix_len)
+            .field("ipv6_prefix_len", &self.ipv6_prefix_len)
+            .field("max_peers_per_prefix", &self.max_peers_per_prefix)
+            .field(
+                "max_peers_per_asn",
+                &self
+                    .asn_lookup
+                    .as_ref()
+                    .map(|asn_lookup| asn_lookup.max_peers),
+            )
+            .finish()
+    }
+}
+
+/// The number of peers in the routing table per [`DiversityGroup`] of a [`DiversityFilter`].
+///
+/// The counters are updated whenever a peer is inserted into or removed from the routing table
+/// or its addresses change, so that checking an address does not require scanning the table.
+#[derive(Debug)]
+pub(crate) struct DiversityCounters {
+    filter: DiversityFilter,
+    /// The groups of each peer in the routing table.
+    peers: HashMap<PeerId, HashSet<DiversityGroup>>,
+    /// The number of peers in the routing table per group.
+    counts: HashMap<DiversityGroup, usize>,
+}
+
+impl DiversityCounters {
+    pub(crate) fn new(filter: DiversityFilter) -> Self {
+        Self {
+            filter,
+            peers: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Returns the first group of the given address whose limit has already been reached by
+    /// the other peers in the routing table.
+    pub(crate) fn exceeded(&self, peer: &PeerId, address: &Multiaddr) -> Option<DiversityGroup> {
+        let own_groups = self.peers.get(peer);
+        self.filter.groups(address).into_iter().find(|group| {
+            let count = self.counts.get(group).copied().unwrap_or(0);
+            let own = own_groups.is_some_and(|groups| groups.contains(group));
+            count - usize::from(own) >= self.filter.limit(group)
+        })
+    }
+
+    /// Records a peer in the routing table with the given addresses, replacing the groups
+    /// previously recorded for it.
+    pub(crate) fn insert<'a>(
+        &mut self,
+        peer: PeerId,
+        addresses: impl IntoIterator<Item = &'a Multiaddr>,
+    ) {
+        let groups = addresses
+            .into_iter()
+            .flat_map(|address| self.filter.groups(address))
+            .collect::<HashSet<_>>();
+        self.remove(&peer);
+        for group in &groups {
+            *self.counts.entry(*group).or_default() += 1;
+        }
+        self.peers.insert(peer, groups);
+    }
+
+    /// Like [`DiversityCounters::insert`], but only if the peer is already recorded, i.e. not
+    /// merely pending insertion into the routing table.
+    pub(crate) fn update<'a>(
+        &mut self,
+        peer: PeerId,
+        addresses: impl IntoIterator<Item = &'a Multiaddr>,
+    ) {
+        if self.peers.contains_key(&peer) {
+            self.insert(peer, addresses);
+        }
+    }
+
+    /// Removes a peer that is no longer in the routing table.
+    pub(crate) fn remove(&mut self, peer: &PeerId) {
+        let Some(groups) = self.peers.remove(peer) else {
+            return;
+        };
+        for group in groups {
+            if let Entry::Occupied(mut count) = self.counts.entry(group) {
+                *count.get_mut() -= 1;
+                if *count.get() == 0 {
+                    count.remove();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_by_prefix_and_asn() {
+        let filter = DiversityFilter::new(1)
+            .with_ipv4_prefix_len(24)
+            .with_asn_lookup(1, |ip| (ip == Ipv4Addr::new(1, 2, 3, 4)).then_some(64512));
+
+        let groups = |address: &str| filter.groups(&address.parse().unwrap());
+
+        assert_eq!(
+            groups("/ip4/1.2.3.4/tcp/4001"),
+            vec![
+                DiversityGroup::IpPrefix(Ipv4Addr::new(1, 2, 3, 0).into(), 24),
+                DiversityGroup::Asn(64512)
+            ]
+        );
+        assert_eq!(
+            groups("/ip6/::ffff:1.2.3.5/udp/4001/quic-v1"),
+            vec![DiversityGroup::IpPrefix(
+                Ipv4Addr::new(1, 2, 3, 0).into(),
+                24
+            )]
+        );
+        assert_eq!(
+            groups("/ip6/2001:db8:1:2::1/tcp/4001"),
+            vec![DiversityGroup::IpPrefix(
+                "2001:db8::".parse::<Ipv6Addr>().unwrap().into(),
+                32
+            )]
+        );
+        assert!(groups("/dns4/example.com/tcp/4001").is_empty());
+        assert!(groups("/memory/1234").is_empty());
+    }
+
+    #[test]
+    fn zero_prefix_len_groups_all_addresses() {
+        let filter = DiversityFilter::new(1).with_ipv4_prefix_len(0);
+
+        assert_eq!(
+            filter.groups(&"/ip4/1.2.3.4/tcp/4001".parse().unwrap()),
+            vec![DiversityGroup::IpPrefix(Ipv4Addr::UNSPECIFIED.into(), 0)]
+        );
+    }
+
+    #[test]
+    fn counters_track_inserted_and_removed_peers() {
+        let mut counters = DiversityCounters::new(DiversityFilter::new(1).with_ipv4_prefix_len(24));
+        let address = |address: &str| address.parse::<Multiaddr>().unwrap();
+        let group = DiversityGroup::IpPrefix(Ipv4Addr::new(1, 2, 3, 0).into(), 24);
+        let (first, second) = (PeerId::random(), PeerId::random());
+
+        counters.insert(first, &[address("/ip4/1.2.3.4/tcp/4001")]);
+        assert_eq!(
+            counters.exceeded(&second, &address("/ip4/1.2.3.5/tcp/4001")),
+            Some(group)
+        );
+        // A peer does not count against its own addresses.
+        assert_eq!(
+            counters.exceeded(&first, &address("/ip4/1.2.3.5/tcp/4001")),
+            None
+        );
+
+        // Peers pending insertion are not counted.
+        counters.update(second, &[address("/ip4/1.2.4.1/tcp/4001")]);
+        assert_eq!(
+            counters.exceeded(&first, &address("/ip4/1.2.4.2/tcp/4001")),
+            None
+        );
+
+        counters.update(first, &[address("/ip4/1.2.4.1/tcp/4001")]);
+        assert_eq!(
+            counters.exceeded(&second, &address("/ip4/1.2.3.5/tcp/4001")),
+            None
+        );
+
+        counters.remove(&first);
+        assert!(counters.peers.is_empty());
+        assert!(counters.counts.is_empty());
+    }
+}
@@ -63,11 +63,12 @@
 
 use crate::record::{self, store::RecordStore, ProviderRecord, Record};
 use futures::prelude::*;
-use futures_timer::Delay;
 use instant::Instant;
 use libp2p_identity::PeerId;
+use libp2p_swarm::{Clock, Sleep};
 use std::collections::HashSet;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::vec;
@@ -84,6 +85,7 @@ pub(crate) const JOBS_MAX_NEW_QUERIES: usize = 10;
 struct PeriodicJob<T> {
     interval: Duration,
     state: PeriodicJobState<T>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<T> PeriodicJob<T> {
@@ -97,12 +99,15 @@ impl<T> PeriodicJob<T> {
 
     /// Cuts short the remaining delay, if the job is currently waiting
     /// for the delay to expire.
-    #[cfg(test)]
     fn asap(&mut self) {
         if let PeriodicJobState::Waiting(delay, deadline) = &mut self.state {
-            let new_deadline = Instant::now().checked_sub(Duration::from_secs(1)).unwrap();
+            let new_deadline = self
+                .clock
+                .now()
+                .checked_sub(Duration::from_secs(1))
+                .unwrap();
             *deadline = new_deadline;
-            delay.reset(Duration::from_secs(1));
+            *delay = self.clock.sleep(Duration::from_secs(1));
         }
     }
 
@@ -122,7 +127,7 @@ impl<T> PeriodicJob<T> {
 #[derive(Debug)]
 enum PeriodicJobState<T> {
     Running(T),
-    Waiting(Delay, Instant),
+    Waiting(Sleep, Instant),
 }
 
 //////////////////////////////////////////////////////////////////////////////
@@ -146,10 +151,11 @@ impl PutRecordJob {
         replicate_interval: Duration,
         publish_interval: Option<Duration>,
         record_ttl: Option<Duration>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
-        let now = Instant::now();
+        let now = clock.now();
         let deadline = now + replicate_interval;
-        let delay = Delay::new(replicate_interval);
+        let delay = clock.sleep(replicate_interval);
         let next_publish = publish_interval.map(|i| now + i);
         Self {
             local_id,
@@ -160,6 +166,7 @@ impl PutRecordJob {
             inner: PeriodicJob {
                 interval: replicate_interval,
                 state: PeriodicJobState::Waiting(delay, deadline),
+                clock,
             },
         }
     }
@@ -183,7 +190,13 @@ impl PutRecordJob {
     #[cfg(test)]
     pub(crate) fn asap(&mut self, publish: bool) {
         if publish {
-            self.next_publish = Some(Instant::now().checked_sub(Duration::from_secs(1)).unwrap())
+            self.next_publish = Some(
+                self.inner
+                    .clock
+                    .now()
+                    .checked_sub(Duration::from_secs(1))
+                    .unwrap(),
+            )
         }
         self.inner.asap()
     }
@@ -244,7 +257,7 @@ impl PutRecordJob {
 
             // Wait for the next run.
             let deadline = now + self.inner.interval;
-            let delay = Delay::new(self.inner.interval);
+            let delay = self.inner.clock.sleep(self.inner.interval);
             self.inner.state = PeriodicJobState::Waiting(delay, deadline);
             assert!(!self.inner.check_ready(cx, now));
         }
@@ -257,21 +270,45 @@ impl PutRecordJob {
 // AddProviderJob
 
 /// Periodic job for replicating provider records.
+///
+/// If enabled, the re-publications of a run are spread evenly over the publication
+/// interval, instead of starting all of them at once, to avoid bursts of queries.
 pub(crate) struct AddProviderJob {
-    inner: PeriodicJob<vec::IntoIter<ProviderRecord>>,
+    spread: bool,
+    inner: PeriodicJob<ProviderRun>,
+}
+
+/// A running [`AddProviderJob`].
+struct ProviderRun {
+    records: vec::IntoIter<ProviderRecord>,
+    /// The number of records of this run, excluding the expired ones skipped so far.
+    total: usize,
+    /// The number of records re-published by this run so far.
+    republished: usize,
+    /// When this run started.
+    started: Instant,
+    /// The time between the re-publications of two consecutive records.
+    spacing: Duration,
+    /// When the next record is due.
+    next: Instant,
+    /// Resolves once the next record is due, created whenever `next` is moved ahead of the
+    /// current time.
+    delay: Sleep,
 }
 
 impl AddProviderJob {
     /// Creates a new periodic job for provider announcements.
-    pub(crate) fn new(interval: Duration) -> Self {
-        let now = Instant::now();
+    pub(crate) fn new(interval: Duration, spread: bool, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
         Self {
+            spread,
             inner: PeriodicJob {
                 interval,
                 state: {
                     let deadline = now + interval;
-                    PeriodicJobState::Waiting(Delay::new(interval), deadline)
+                    PeriodicJobState::Waiting(clock.sleep(interval), deadline)
                 },
+                clock,
             },
         }
     }
@@ -286,11 +323,21 @@ impl AddProviderJob {
     /// for the delay to expire.
     ///
     /// The job is guaranteed to run on the next invocation of `poll`.
-    #[cfg(test)]
     pub(crate) fn asap(&mut self) {
         self.inner.asap()
     }
 
+    /// Returns the number of records re-published by the current run so far and the
+    /// total number of records of the run, if the job is running.
+    ///
+    /// Expired records are removed instead of re-published and thus count towards neither.
+    pub(crate) fn progress(&self) -> Option<(usize, usize)> {
+        match &self.inner.state {
+            PeriodicJobState::Running(run) => Some((run.republished, run.total)),
+            PeriodicJobState::Waiting(..) => None,
+        }
+    }
+
     /// Polls the job for provider records to replicate.
     ///
     /// Must be called in the context of a task. When `NotReady` is returned,
@@ -306,25 +353,53 @@ impl AddProviderJob {
         T: RecordStore,
     {
         if self.inner.check_ready(cx, now) {
-            let records = store
-                .provided()
-                .map(|r| r.into_owned())
-                .collect::<Vec<_>>()
-                .into_iter();
-            self.inner.state = PeriodicJobState::Running(records);
+            let records = store.provided().map(|r| r.into_owned()).collect::<Vec<_>>();
+            let total = records.len();
+            let spacing = if self.spread {
+                self.inner.interval / u32::try_from(total.max(1)).unwrap_or(u32::MAX)
+            } else {
+                Duration::ZERO
+            };
+            self.inner.state = PeriodicJobState::Running(ProviderRun {
+                records: records.into_iter(),
+                total,
+                republished: 0,
+                started: now,
+                spacing,
+                next: now,
+                delay: self.inner.clock.sleep(Duration::ZERO),
+            });
         }
 
-        if let PeriodicJobState::Running(keys) = &mut self.inner.state {
-            for r in keys {
+        if let PeriodicJobState::Running(run) = &mut self.inner.state {
+            while !run.records.as_slice().is_empty() {
+                if now < run.next && Future::poll(Pin::new(&mut run.delay), cx).is_pending() {
+                    return Poll::Pending;
+                }
+
+                let r = run.records.next().expect("not to be empty");
+
                 if r.is_expired(now) {
-                    store.remove_provider(&r.key, &r.provider)
-                } else {
-                    return Poll::Ready(r);
+                    store.remove_provider(&r.key, &r.provider);
+                    run.total -= 1;
+                    continue;
                 }
+
+                run.republished += 1;
+                run.next += run.spacing;
+                if now < run.next {
+                    run.delay = self.inner.clock.sleep(run.next - now);
+                }
+                return Poll::Ready(r);
             }
 
-            let deadline = now + self.inner.interval;
-            let delay = Delay::new(self.inner.interval);
+            // The next run is due one interval after the start of this one,
+            // regardless of how long it took to spread out the records.
+            let mut deadline = run.started + self.inner.interval;
+            if deadline <= now {
+                deadline = now + self.inner.interval;
+            }
+            let delay = self.inner.clock.sleep(deadline - now);
             self.inner.state = PeriodicJobState::Waiting(delay, deadline);
             assert!(!self.inner.check_ready(cx, now));
         }
@@ -338,6 +413,7 @@ mod tests {
     use super::*;
     use crate::record::store::MemoryStore;
     use futures::{executor::block_on, future::poll_fn};
+    use libp2p_swarm::SystemClock;
     use quickcheck::*;
     use rand::Rng;
 
@@ -347,13 +423,19 @@ mod tests {
         let replicate_interval = Duration::from_secs(rng.gen_range(1..60));
         let publish_interval = Some(replicate_interval * rng.gen_range(1..10));
         let record_ttl = Some(Duration::from_secs(rng.gen_range(1..600)));
-        PutRecordJob::new(id, replicate_interval, publish_interval, record_ttl)
+        PutRecordJob::new(
+            id,
+            replicate_interval,
+            publish_interval,
+            record_ttl,
+            Arc::new(SystemClock),
+        )
     }
 
     fn rand_add_provider_job() -> AddProviderJob {
         let mut rng = rand::thread_rng();
         let interval = Duration::from_secs(rng.gen_range(1..60));
-        AddProviderJob::new(interval)
+        AddProviderJob::new(interval, false, Arc::new(SystemClock))
     }
 
     #[test]
@@ -421,4 +503,61 @@ mod tests {
 
         quickcheck(prop as fn(_))
     }
+
+    #[test]
+    fn add_provider_job_spreads_records() {
+        let interval = Duration::from_secs(60);
+        let mut job = AddProviderJob::new(interval, true, Arc::new(SystemClock));
+        let id = PeerId::random();
+        let mut store = MemoryStore::new(id);
+        for _ in 0..3 {
+            let key = record::Key::new(&rand::thread_rng().gen::<[u8; 32]>());
+            store
+                .add_provider(ProviderRecord::new(key, id, Vec::new()))
+                .unwrap();
+        }
+
+        block_on(poll_fn(|ctx| {
+            let start = Instant::now() + interval;
+
+            // The records are yielded one after the other, a third of the interval apart.
+            for i in 0..3 {
+                let now = start + interval / 3 * i;
+                assert!(job.poll(ctx, &mut store, now).is_ready());
+                assert_eq!(job.progress(), Some(((i + 1) as usize, 3)));
+                assert!(job.poll(ctx, &mut store, now).is_pending());
+            }
+
+            assert!(!job.is_running());
+            assert_eq!(job.progress(), None);
+            Poll::Ready(())
+        }));
+    }
+
+    #[test]
+    fn add_provider_job_progress_excludes_expired_records() {
+        let interval = Duration::from_secs(60);
+        let mut job = AddProviderJob::new(interval, false, Arc::new(SystemClock));
+        let id = PeerId::random();
+        let mut store = MemoryStore::new(id);
+        let now = Instant::now() + interval;
+        for expires in [None, Some(now - Duration::from_secs(1)), None] {
+            let key = record::Key::new(&rand::thread_rng().gen::<[u8; 32]>());
+            let mut record = ProviderRecord::new(key, id, Vec::new());
+            record.expires = expires;
+            store.add_provider(record).unwrap();
+        }
+
+        block_on(poll_fn(|ctx| {
+            // The expired record counts towards neither once it has been removed.
+            for republished in 1..=2 {
+                assert!(job.poll(ctx, &mut store, now).is_ready());
+                let total = if store.provided().count() == 2 { 2 } else { 3 };
+                assert_eq!(job.progress(), Some((republished, total)));
+            }
+            assert!(job.poll(ctx, &mut store, now).is_pending());
+            assert_eq!(store.provided().count(), 2);
+            Poll::Ready(())
+        }));
+    }
 }
@@ -35,9 +35,11 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod address_filter;
 mod addresses;
 mod behaviour;
 mod bootstrap;
+mod diversity_filter;
 mod handler;
 mod jobs;
 mod kbucket;
@@ -54,18 +56,21 @@ mod proto {
     };
 }
 
+pub use address_filter::AddressFilter;
 pub use addresses::Addresses;
 pub use behaviour::{
     AddProviderContext, AddProviderError, AddProviderOk, AddProviderPhase, AddProviderResult,
-    BootstrapError, BootstrapOk, BootstrapResult, GetClosestPeersError, GetClosestPeersOk,
-    GetClosestPeersResult, GetProvidersError, GetProvidersOk, GetProvidersResult, GetRecordError,
-    GetRecordOk, GetRecordResult, InboundRequest, Mode, NoKnownPeers, PeerRecord, PutRecordContext,
-    PutRecordError, PutRecordOk, PutRecordPhase, PutRecordResult, QueryInfo, QueryMut, QueryRef,
-    QueryResult, QueryStats, RoutingUpdate,
+    BootstrapError, BootstrapOk, BootstrapResult, BucketOccupancy, GetClosestPeersError,
+    GetClosestPeersOk, GetClosestPeersResult, GetProvidersError, GetProvidersOk,
+    GetProvidersResult, GetRecordError, GetRecordOk, GetRecordResult, InboundRequest, Mode,
+    NoKnownPeers, PeerRecord, PutRecordContext, PutRecordError, PutRecordOk, PutRecordPhase,
+    PutRecordResult, QueryInfo, QueryMut, QueryRef, QueryResult, QueryStats, RoutingTablePeer,
+    RoutingUpdate,
 };
 pub use behaviour::{
     Behaviour, BucketInserts, Caching, Config, Event, ProgressStep, Quorum, StoreInserts,
 };
+pub use diversity_filter::{DiversityFilter, DiversityGroup};
 pub use kbucket::{
     Distance as KBucketDistance, EntryView, KBucketRef, Key as KBucketKey, NodeStatus,
 };
@@ -73,6 +78,9 @@ pub use protocol::ConnectionType;
 pub use query::QueryId;
 pub use record::{store, Key as RecordKey, ProviderRecord, Record};
 
+#[cfg(feature = "cid")]
+pub use cid;
+
 use libp2p_swarm::StreamProtocol;
 use std::num::NonZeroUsize;
 
@@ -33,10 +33,11 @@ use bytes::BytesMut;
 use futures::prelude::*;
 use instant::Instant;
 use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
-use libp2p_core::Multiaddr;
+use libp2p_core::{Multiaddr, PeerRecord, SignedEnvelope};
 use libp2p_identity::PeerId;
-use libp2p_swarm::StreamProtocol;
+use libp2p_swarm::{Clock, StreamProtocol, SystemClock};
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{io, iter};
 use tracing::debug;
@@ -91,6 +92,11 @@ pub struct KadPeer {
     pub multiaddrs: Vec<Multiaddr>,
     /// How the sender is connected to that remote.
     pub connection_ty: ConnectionType,
+    /// The signed peer record of the peer, if provided by the sender.
+    ///
+    /// The signature of the record is verified when decoding and the record is guaranteed to
+    /// belong to [`KadPeer::node_id`].
+    pub signed_record: Option<PeerRecord>,
 }
 
 // Builds a `KadPeer` from a corresponding protobuf message.
@@ -113,10 +119,24 @@ impl TryFrom<proto::Peer> for KadPeer {
             };
         }
 
+        let signed_record = if peer.signedRecord.is_empty() {
+            None
+        } else {
+            let envelope = SignedEnvelope::from_protobuf_encoding(&peer.signedRecord)
+                .map_err(|_| invalid_data("invalid signed envelope"))?;
+            let record = PeerRecord::from_signed_envelope(envelope)
+                .map_err(|_| invalid_data("invalid signed peer record"))?;
+            if record.peer_id() != node_id {
+                return Err(invalid_data("signed peer record of a different peer"));
+            }
+            Some(record)
+        };
+
         Ok(KadPeer {
             node_id,
             multiaddrs: addrs,
             connection_ty: peer.connection.into(),
+            signed_record,
         })
     }
 }
@@ -127,6 +147,10 @@ impl From<KadPeer> for proto::Peer {
             id: peer.node_id.to_bytes(),
             addrs: peer.multiaddrs.into_iter().map(|a| a.to_vec()).collect(),
             connection: peer.connection_ty.into(),
+            signedRecord: peer
+                .signed_record
+                .map(|record| record.into_signed_envelope().into_protobuf_encoding())
+                .unwrap_or_default(),
         }
     }
 }
@@ -141,6 +165,8 @@ pub struct ProtocolConfig {
     protocol_names: Vec<StreamProtocol>,
     /// Maximum allowed size of a packet.
     max_packet_size: usize,
+    /// The source of time for converting between record expiry instants and TTLs on the wire.
+    clock: Arc<dyn Clock>,
 }
 
 impl ProtocolConfig {
@@ -149,6 +175,7 @@ impl ProtocolConfig {
         ProtocolConfig {
             protocol_names: vec![protocol_name],
             max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            clock: Arc::new(SystemClock),
         }
     }
 
@@ -175,6 +202,11 @@ impl ProtocolConfig {
     pub fn set_max_packet_size(&mut self, size: usize) {
         self.max_packet_size = size;
     }
+
+    /// Sets the clock relative to which the TTLs of records on the wire are computed.
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
 }
 
 impl Default for ProtocolConfig {
@@ -185,6 +217,7 @@ impl Default for ProtocolConfig {
         ProtocolConfig {
             protocol_names: iter::once(DEFAULT_PROTO_NAME).collect(),
             max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            clock: Arc::new(SystemClock),
         }
     }
 }
@@ -201,34 +234,52 @@ impl UpgradeInfo for ProtocolConfig {
 /// Codec for Kademlia inbound and outbound message framing.
 pub struct Codec<A, B> {
     codec: quick_protobuf_codec::Codec<proto::Message>,
+    clock: Arc<dyn Clock>,
     __phantom: PhantomData<(A, B)>,
 }
 impl<A, B> Codec<A, B> {
-    fn new(max_packet_size: usize) -> Self {
+    fn new(max_packet_size: usize, clock: Arc<dyn Clock>) -> Self {
         Codec {
             codec: quick_protobuf_codec::Codec::new(max_packet_size),
+            clock,
             __phantom: PhantomData,
         }
     }
 }
 
-impl<A: Into<proto::Message>, B> Encoder for Codec<A, B> {
+impl<A: IntoProto, B> Encoder for Codec<A, B> {
     type Error = io::Error;
     type Item<'a> = A;
 
     fn encode(&mut self, item: Self::Item<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        Ok(self.codec.encode(item.into(), dst)?)
+        Ok(self.codec.encode(item.into_proto(self.clock.now()), dst)?)
     }
 }
-impl<A, B: TryFrom<proto::Message, Error = io::Error>> Decoder for Codec<A, B> {
+impl<A, B: FromProto> Decoder for Codec<A, B> {
     type Error = io::Error;
     type Item = B;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        self.codec.decode(src)?.map(B::try_from).transpose()
+        let now = self.clock.now();
+        self.codec
+            .decode(src)?
+            .map(|message| B::from_proto(message, now))
+            .transpose()
     }
 }
 
+/// Conversion of a message into its protobuf representation, with record expiry instants
+/// turned into TTLs relative to `now`.
+trait IntoProto {
+    fn into_proto(self, now: Instant) -> proto::Message;
+}
+
+/// Conversion of a protobuf message, with record TTLs turned into expiry instants relative to
+/// `now`.
+trait FromProto: Sized {
+    fn from_proto(message: proto::Message, now: Instant) -> Result<Self, io::Error>;
+}
+
 /// Sink of responses and stream of requests.
 pub(crate) type KadInStreamSink<S> = Framed<S, Codec<KadResponseMsg, KadRequestMsg>>;
 /// Sink of requests and stream of responses.
@@ -243,7 +294,7 @@ where
     type Error = io::Error;
 
     fn upgrade_inbound(self, incoming: C, _: Self::Info) -> Self::Future {
-        let codec = Codec::new(self.max_packet_size);
+        let codec = Codec::new(self.max_packet_size, self.clock);
 
         future::ok(Framed::new(incoming, codec))
     }
@@ -258,7 +309,7 @@ where
     type Error = io::Error;
 
     fn upgrade_outbound(self, incoming: C, _: Self::Info) -> Self::Future {
-        let codec = Codec::new(self.max_packet_size);
+        let codec = Codec::new(self.max_packet_size, self.clock);
 
         future::ok(Framed::new(incoming, codec))
     }
@@ -339,33 +390,29 @@ pub enum KadResponseMsg {
     },
 }
 
-impl From<KadRequestMsg> for proto::Message {
-    fn from(kad_msg: KadRequestMsg) -> Self {
-        req_msg_to_proto(kad_msg)
+impl IntoProto for KadRequestMsg {
+    fn into_proto(self, now: Instant) -> proto::Message {
+        req_msg_to_proto(self, now)
     }
 }
-impl From<KadResponseMsg> for proto::Message {
-    fn from(kad_msg: KadResponseMsg) -> Self {
-        resp_msg_to_proto(kad_msg)
+impl IntoProto for KadResponseMsg {
+    fn into_proto(self, now: Instant) -> proto::Message {
+        resp_msg_to_proto(self, now)
     }
 }
-impl TryFrom<proto::Message> for KadRequestMsg {
-    type Error = io::Error;
-
-    fn try_from(message: proto::Message) -> Result<Self, Self::Error> {
-        proto_to_req_msg(message)
+impl FromProto for KadRequestMsg {
+    fn from_proto(message: proto::Message, now: Instant) -> Result<Self, io::Error> {
+        proto_to_req_msg(message, now)
     }
 }
-impl TryFrom<proto::Message> for KadResponseMsg {
-    type Error = io::Error;
-
-    fn try_from(message: proto::Message) -> Result<Self, Self::Error> {
-        proto_to_resp_msg(message)
+impl FromProto for KadResponseMsg {
+    fn from_proto(message: proto::Message, now: Instant) -> Result<Self, io::Error> {
+        proto_to_resp_msg(message, now)
     }
 }
 
 /// Converts a `KadRequestMsg` into the corresponding protobuf message for sending.
-fn req_msg_to_proto(kad_msg: KadRequestMsg) -> proto::Message {
+fn req_msg_to_proto(kad_msg: KadRequestMsg, now: Instant) -> proto::Message {
     match kad_msg {
         KadRequestMsg::Ping => proto::Message {
             type_pb: proto::MessageType::PING,
@@ -399,14 +446,14 @@ fn req_msg_to_proto(kad_msg: KadRequestMsg) -> proto::Message {
         KadRequestMsg::PutValue { record } => proto::Message {
             type_pb: proto::MessageType::PUT_VALUE,
             key: record.key.to_vec(),
-            record: Some(record_to_proto(record)),
+            record: Some(record_to_proto(record, now)),
             ..proto::Message::default()
         },
     }
 }
 
 /// Converts a `KadResponseMsg` into the corresponding protobuf message for sending.
-fn resp_msg_to_proto(kad_msg: KadResponseMsg) -> proto::Message {
+fn resp_msg_to_proto(kad_msg: KadResponseMsg, now: Instant) -> proto::Message {
     match kad_msg {
         KadResponseMsg::Pong => proto::Message {
             type_pb: proto::MessageType::PING,
@@ -435,7 +482,7 @@ fn resp_msg_to_proto(kad_msg: KadResponseMsg) -> proto::Message {
             type_pb: proto::MessageType::GET_VALUE,
             clusterLevelRaw: 9,
             closerPeers: closer_peers.into_iter().map(KadPeer::into).collect(),
-            record: record.map(record_to_proto),
+            record: record.map(|record| record_to_proto(record, now)),
             ..proto::Message::default()
         },
         KadResponseMsg::PutValue { key, value } => proto::Message {
@@ -454,11 +501,11 @@ fn resp_msg_to_proto(kad_msg: KadResponseMsg) -> proto::Message {
 /// Converts a received protobuf message into a corresponding `KadRequestMsg`.
 ///
 /// Fails if the protobuf message is not a valid and supported Kademlia request message.
-fn proto_to_req_msg(message: proto::Message) -> Result<KadRequestMsg, io::Error> {
+fn proto_to_req_msg(message: proto::Message, now: Instant) -> Result<KadRequestMsg, io::Error> {
     match message.type_pb {
         proto::MessageType::PING => Ok(KadRequestMsg::Ping),
         proto::MessageType::PUT_VALUE => {
-            let record = record_from_proto(message.record.unwrap_or_default())?;
+            let record = record_from_proto(message.record.unwrap_or_default(), now)?;
             Ok(KadRequestMsg::PutValue { record })
         }
         proto::MessageType::GET_VALUE => Ok(KadRequestMsg::GetValue {
@@ -490,12 +537,12 @@ fn proto_to_req_msg(message: proto::Message) -> Result<KadRequestMsg, io::Error>
 /// Converts a received protobuf message into a corresponding `KadResponseMessage`.
 ///
 /// Fails if the protobuf message is not a valid and supported Kademlia response message.
-fn proto_to_resp_msg(message: proto::Message) -> Result<KadResponseMsg, io::Error> {
+fn proto_to_resp_msg(message: proto::Message, now: Instant) -> Result<KadResponseMsg, io::Error> {
     match message.type_pb {
         proto::MessageType::PING => Ok(KadResponseMsg::Pong),
         proto::MessageType::GET_VALUE => {
             let record = if let Some(r) = message.record {
-                Some(record_from_proto(r)?)
+                Some(record_from_proto(r, now)?)
             } else {
                 None
             };
@@ -559,7 +606,7 @@ fn proto_to_resp_msg(message: proto::Message) -> Result<KadResponseMsg, io::Erro
     }
 }
 
-fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
+fn record_from_proto(record: proto::Record, now: Instant) -> Result<Record, io::Error> {
     let key = record::Key::from(record.key);
     let value = record.value;
 
@@ -572,7 +619,7 @@ fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
     };
 
     let expires = if record.ttl > 0 {
-        Some(Instant::now() + Duration::from_secs(record.ttl as u64))
+        Some(now + Duration::from_secs(record.ttl as u64))
     } else {
         None
     };
@@ -585,7 +632,7 @@ fn record_from_proto(record: proto::Record) -> Result<Record, io::Error> {
     })
 }
 
-fn record_to_proto(record: Record) -> proto::Record {
+fn record_to_proto(record: Record, now: Instant) -> proto::Record {
     proto::Record {
         key: record.key.to_vec(),
         value: record.value,
@@ -593,7 +640,6 @@ fn record_to_proto(record: Record) -> proto::Record {
         ttl: record
             .expires
             .map(|t| {
-                let now = Instant::now();
                 if t > now {
                     (t - now).as_secs() as u32
                 } else {
@@ -626,6 +672,7 @@ mod tests {
             id: peer_id.to_bytes(),
             addrs: vec![multiaddr.to_vec()],
             connection: proto::ConnectionType::CAN_CONNECT,
+            ..Default::default()
         };
 
         let peer = KadPeer::try_from(payload).unwrap();
@@ -660,6 +707,7 @@ mod tests {
                 invalid_multiaddr,
             ],
             connection: proto::ConnectionType::CAN_CONNECT,
+            ..Default::default()
         };
 
         let peer = KadPeer::try_from(payload).unwrap();
@@ -667,6 +715,65 @@ mod tests {
         assert_eq!(peer.multiaddrs, vec![valid_multiaddr])
     }
 
+    #[test]
+    fn verify_signed_record() {
+        let keypair = libp2p_identity::Keypair::generate_ed25519();
+        let peer_id = keypair.public().to_peer_id();
+        let multiaddr = "/ip4/147.75.69.143/tcp/4001".parse::<Multiaddr>().unwrap();
+        let record = PeerRecord::new(&keypair, vec![multiaddr]).unwrap();
+
+        let peer = KadPeer {
+            node_id: peer_id,
+            multiaddrs: vec![],
+            connection_ty: ConnectionType::Connected,
+            signed_record: Some(record.clone()),
+        };
+        let decoded = KadPeer::try_from(proto::Peer::from(peer)).unwrap();
+        assert_eq!(decoded.signed_record, Some(record.clone()));
+
+        // A valid record of a different peer is rejected.
+        let payload = proto::Peer {
+            id: PeerId::random().to_bytes(),
+            signedRecord: record.into_signed_envelope().into_protobuf_encoding(),
+            ..Default::default()
+        };
+        assert!(KadPeer::try_from(payload).is_err());
+
+        // A record with an invalid signature is rejected.
+        let mut signed_record = PeerRecord::new(&keypair, vec![])
+            .unwrap()
+            .into_signed_envelope()
+            .into_protobuf_encoding();
+        let last = signed_record.len() - 1;
+        signed_record[last] ^= 1;
+        let payload = proto::Peer {
+            id: peer_id.to_bytes(),
+            signedRecord: signed_record,
+            ..Default::default()
+        };
+        assert!(KadPeer::try_from(payload).is_err());
+    }
+
+    #[test]
+    fn record_ttl_follows_clock() {
+        let clock = libp2p_swarm::VirtualClock::new();
+        let now = clock.now();
+        let record = Record {
+            key: record::Key::new(&"key"),
+            value: vec![1],
+            publisher: None,
+            expires: Some(now + Duration::from_secs(30)),
+        };
+
+        // The TTL on the wire is relative to the time of the clock.
+        clock.advance(Duration::from_secs(10));
+        let proto = record_to_proto(record.clone(), clock.now());
+        assert_eq!(proto.ttl, 20);
+
+        let decoded = record_from_proto(proto, clock.now()).unwrap();
+        assert_eq!(decoded.expires, record.expires);
+    }
+
     /*// TODO: restore
     use self::libp2p_tcp::TcpTransport;
     use self::tokio::runtime::current_thread::Runtime;
@@ -32,6 +32,8 @@ use either::Either;
 use fnv::FnvHashMap;
 use instant::Instant;
 use libp2p_identity::PeerId;
+use libp2p_swarm::{Clock, SystemClock};
+use std::sync::Arc;
 use std::{num::NonZeroUsize, time::Duration};
 
 /// A `QueryPool` provides an aggregate state machine for driving `Query`s to completion.
@@ -43,6 +45,8 @@ pub(crate) struct QueryPool<TInner> {
     next_id: usize,
     config: QueryConfig,
     queries: FnvHashMap<QueryId, Query<TInner>>,
+    /// The source of time for measuring the duration of running queries.
+    clock: Arc<dyn Clock>,
 }
 
 /// The observable states emitted by [`QueryPool::poll`].
@@ -60,11 +64,12 @@ pub(crate) enum QueryPoolState<'a, TInner> {
 
 impl<TInner> QueryPool<TInner> {
     /// Creates a new `QueryPool` with the given configuration.
-    pub(crate) fn new(config: QueryConfig) -> Self {
+    pub(crate) fn new(config: QueryConfig, clock: Arc<dyn Clock>) -> Self {
         QueryPool {
             next_id: 0,
             config,
             queries: Default::default(),
+            clock,
         }
     }
 
@@ -94,21 +99,27 @@ impl<TInner> QueryPool<TInner> {
         I: IntoIterator<Item = PeerId>,
     {
         let id = self.next_query_id();
-        self.continue_fixed(id, peers, inner);
+        self.continue_fixed(id, peers, inner, None);
         id
     }
 
     /// Continues an earlier query with a fixed set of peers, reusing
     /// the given query ID, which must be from a query that finished
-    /// earlier.
-    pub(crate) fn continue_fixed<I>(&mut self, id: QueryId, peers: I, inner: TInner)
-    where
+    /// earlier, as well as its deadline.
+    pub(crate) fn continue_fixed<I>(
+        &mut self,
+        id: QueryId,
+        peers: I,
+        inner: TInner,
+        deadline: Option<Instant>,
+    ) where
         I: IntoIterator<Item = PeerId>,
     {
         assert!(!self.queries.contains_key(&id));
         let parallelism = self.config.replication_factor;
         let peer_iter = QueryPeerIter::Fixed(FixedPeersIter::new(peers, parallelism));
-        let query = Query::new(id, peer_iter, inner);
+        let mut query = Query::new(id, peer_iter, inner);
+        query.deadline = deadline;
         self.queries.insert(id, query);
     }
 
@@ -119,17 +130,20 @@ impl<TInner> QueryPool<TInner> {
         I: IntoIterator<Item = Key<PeerId>>,
     {
         let id = self.next_query_id();
-        self.continue_iter_closest(id, target, peers, inner);
+        self.continue_iter_closest(id, target, peers, inner, None);
         id
     }
 
-    /// Adds a query to the pool that iterates towards the closest peers to the target.
+    /// Continues an earlier query by iterating towards the closest peers to the target,
+    /// reusing the given query ID, which must be from a query that finished earlier,
+    /// as well as its deadline.
     pub(crate) fn continue_iter_closest<T, I>(
         &mut self,
         id: QueryId,
         target: T,
         peers: I,
         inner: TInner,
+        deadline: Option<Instant>,
     ) where
         T: Into<KeyBytes> + Clone,
         I: IntoIterator<Item = Key<PeerId>>,
@@ -148,8 +162,37 @@ impl<TInner> QueryPool<TInner> {
             QueryPeerIter::Closest(ClosestPeersIter::with_config(cfg, target, peers))
         };
 
+        let mut query = Query::new(id, peer_iter, inner);
+        query.deadline = deadline;
+        self.queries.insert(id, query);
+    }
+
+    /// Adds a query to the pool that iterates towards the closest peers to the target on the
+    /// given number of disjoint paths, regardless of [`QueryConfig::disjoint_query_paths`].
+    pub(crate) fn add_iter_closest_disjoint<T, I>(
+        &mut self,
+        target: T,
+        peers: I,
+        paths: NonZeroUsize,
+        inner: TInner,
+    ) -> QueryId
+    where
+        T: Into<KeyBytes> + Clone,
+        I: IntoIterator<Item = Key<PeerId>>,
+    {
+        let cfg = ClosestPeersIterConfig {
+            num_results: self.config.replication_factor,
+            parallelism: paths,
+            ..ClosestPeersIterConfig::default()
+        };
+
+        let id = self.next_query_id();
+        let peer_iter = QueryPeerIter::ClosestDisjoint(ClosestDisjointPeersIter::with_config(
+            cfg, target, peers,
+        ));
         let query = Query::new(id, peer_iter, inner);
         self.queries.insert(id, query);
+        id
     }
 
     fn next_query_id(&mut self) -> QueryId {
@@ -168,6 +211,19 @@ impl<TInner> QueryPool<TInner> {
         self.queries.get_mut(id)
     }
 
+    /// Aborts a query, removing it from the pool before it finished.
+    pub(crate) fn abort(&mut self, id: &QueryId, now: Instant) -> Option<Query<TInner>> {
+        let mut query = self.queries.remove(id)?;
+        query.stats.start(now, &self.clock);
+        query.stats.end = Some(now);
+        Some(query)
+    }
+
+    /// Returns the earliest deadline of the queries in the pool, if any has one.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.queries.values().filter_map(|q| q.deadline).min()
+    }
+
     /// Polls the pool to advance the queries.
     pub(crate) fn poll(&mut self, now: Instant) -> QueryPoolState<'_, TInner> {
         let mut finished = None;
@@ -175,7 +231,11 @@ impl<TInner> QueryPool<TInner> {
         let mut waiting = None;
 
         for (&query_id, query) in self.queries.iter_mut() {
-            query.stats.start = query.stats.start.or(Some(now));
+            query.stats.start(now, &self.clock);
+            if query.deadline.map_or(false, |deadline| now >= deadline) {
+                timeout = Some(query_id);
+                break;
+            }
             match query.next(now) {
                 PeersIterState::Finished => {
                     finished = Some(query_id);
@@ -271,6 +331,8 @@ pub(crate) struct Query<TInner> {
     peer_iter: QueryPeerIter,
     /// Execution statistics of the query.
     stats: QueryStats,
+    /// The instant by which the query times out, regardless of [`QueryConfig::timeout`].
+    deadline: Option<Instant>,
     /// The opaque inner query state.
     pub(crate) inner: TInner,
 }
@@ -290,6 +352,7 @@ impl<TInner> Query<TInner> {
             inner,
             peer_iter,
             stats: QueryStats::empty(),
+            deadline: None,
         }
     }
 
@@ -332,6 +395,16 @@ impl<TInner> Query<TInner> {
         }
     }
 
+    /// Returns the index of the disjoint path on which `peer` was contacted.
+    ///
+    /// Queries not using disjoint paths contact all peers on path `0`.
+    pub(crate) fn path_of(&self, peer: &PeerId) -> Option<usize> {
+        match &self.peer_iter {
+            QueryPeerIter::ClosestDisjoint(iter) => iter.path_of(peer),
+            QueryPeerIter::Closest(_) | QueryPeerIter::Fixed(_) => Some(0),
+        }
+    }
+
     /// Advances the state of the underlying peer iterator.
     fn next(&mut self, now: Instant) -> PeersIterState<'_> {
         let state = match &mut self.peer_iter {
@@ -381,6 +454,14 @@ impl<TInner> Query<TInner> {
         }
     }
 
+    /// Sets the instant by which the query times out.
+    ///
+    /// A query whose deadline passed is reported by [`QueryPool::poll`] via
+    /// [`QueryPoolState::Timeout`], regardless of its state and of [`QueryConfig::timeout`].
+    pub(crate) fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
     /// Finishes the query prematurely.
     ///
     /// A finished query immediately stops yielding new peers to contact and will be
@@ -416,6 +497,7 @@ impl<TInner> Query<TInner> {
             peers,
             inner: self.inner,
             stats: self.stats,
+            deadline: self.deadline,
         }
     }
 }
@@ -428,18 +510,34 @@ pub(crate) struct QueryResult<TInner, TPeers> {
     pub(crate) peers: TPeers,
     /// The collected query statistics.
     pub(crate) stats: QueryStats,
+    /// The deadline of the query, to carry over to subsequent phases.
+    pub(crate) deadline: Option<Instant>,
 }
 
 /// Execution statistics of a query.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct QueryStats {
     requests: u32,
     success: u32,
     failure: u32,
     start: Option<Instant>,
     end: Option<Instant>,
+    /// The clock of the pool that started the query, measuring its duration until it ended.
+    clock: Arc<dyn Clock>,
+}
+
+impl PartialEq for QueryStats {
+    fn eq(&self, other: &Self) -> bool {
+        self.requests == other.requests
+            && self.success == other.success
+            && self.failure == other.failure
+            && self.start == other.start
+            && self.end == other.end
+    }
 }
 
+impl Eq for QueryStats {}
+
 impl QueryStats {
     pub fn empty() -> Self {
         QueryStats {
@@ -448,6 +546,15 @@ impl QueryStats {
             failure: 0,
             start: None,
             end: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Marks the query as started at `now` as per the given clock, unless it already started.
+    fn start(&mut self, now: Instant, clock: &Arc<dyn Clock>) {
+        if self.start.is_none() {
+            self.start = Some(now);
+            self.clock = clock.clone();
         }
     }
 
@@ -477,7 +584,8 @@ impl QueryStats {
     /// Gets the duration of the query.
     ///
     /// If the query has not yet finished, the duration is measured from the
-    /// start of the query to the current instant.
+    /// start of the query to the current instant, as per the clock of the
+    /// [`crate::Behaviour`], see [`crate::Config::set_clock`].
     ///
     /// If the query did not yet start (i.e. yield the first peer to contact),
     /// `None` is returned.
@@ -486,7 +594,7 @@ impl QueryStats {
             if let Some(e) = self.end {
                 Some(e - s)
             } else {
-                Some(Instant::now() - s)
+                Some(self.clock.now() - s)
             }
         } else {
             None
@@ -509,6 +617,11 @@ impl QueryStats {
                 (a, b) => a.or(b),
             },
             end: std::cmp::max(self.end, other.end),
+            clock: if self.start.is_some() {
+                self.clock
+            } else {
+                other.clock
+            },
         }
     }
 }
@@ -279,6 +279,13 @@ impl ClosestDisjointPeersIter {
         state.unwrap_or(PeersIterState::Finished)
     }
 
+    /// Returns the index of the path which contacted the given peer, if any.
+    pub(crate) fn path_of(&self, peer: &PeerId) -> Option<usize> {
+        self.contacted_peers
+            .get(peer)
+            .map(|state| state.initiated_by.0)
+    }
+
     /// Finishes all paths containing one of the given peers.
     ///
     /// See [`crate::query::Query::try_finish`] for details.
@@ -23,8 +23,10 @@
 pub mod store;
 
 use bytes::Bytes;
+#[cfg(feature = "cid")]
+use cid::CidGeneric;
 use instant::Instant;
-use libp2p_core::{multihash::Multihash, Multiaddr};
+use libp2p_core::{multihash, multihash::Multihash, Multiaddr};
 use libp2p_identity::PeerId;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -46,6 +48,31 @@ impl Key {
     pub fn to_vec(&self) -> Vec<u8> {
         Vec::from(&self.0[..])
     }
+
+    /// Interprets the key as a [`Multihash`].
+    ///
+    /// Fails if the key is not exactly one valid multihash, e.g. because it was created from
+    /// arbitrary bytes via [`Key::new`] or from a full CID instead of its multihash.
+    pub fn to_multihash<const S: usize>(&self) -> Result<Multihash<S>, multihash::Error> {
+        Multihash::from_bytes(&self.0)
+    }
+
+    /// Creates a key for content addressed by the given [`CidGeneric`].
+    ///
+    /// Following the convention of IPFS, the key only consists of the [`Multihash`] of the CID.
+    /// Version and codec are discarded, thus all CIDs of the same content map to the same key.
+    #[cfg(feature = "cid")]
+    pub fn from_cid<const S: usize>(cid: &CidGeneric<S>) -> Self {
+        Key::from(*cid.hash())
+    }
+
+    /// Creates a CIDv1 with the given codec from the [`Multihash`] contained in the key.
+    ///
+    /// This is the inverse of [`Key::from_cid`] for CIDv1s with the same codec.
+    #[cfg(feature = "cid")]
+    pub fn to_cid<const S: usize>(&self, codec: u64) -> Result<CidGeneric<S>, multihash::Error> {
+        Ok(CidGeneric::new_v1(codec, self.to_multihash()?))
+    }
 }
 
 impl Borrow<[u8]> for Key {
@@ -72,6 +99,13 @@ impl<const S: usize> From<Multihash<S>> for Key {
     }
 }
 
+#[cfg(feature = "cid")]
+impl<const S: usize> From<CidGeneric<S>> for Key {
+    fn from(cid: CidGeneric<S>) -> Key {
+        Key::from_cid(&cid)
+    }
+}
+
 /// A record stored in the DHT.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Record {
@@ -205,4 +239,32 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn key_to_multihash() {
+        fn prop(key: Key) -> bool {
+            Key::from(key.to_multihash::<64>().unwrap()) == key
+        }
+        quickcheck(prop as fn(_) -> _);
+
+        assert!(Key::new(b"not a multihash").to_multihash::<64>().is_err());
+    }
+
+    #[cfg(feature = "cid")]
+    #[test]
+    fn key_from_cid_ignores_version_and_codec() {
+        use cid::Cid;
+
+        const RAW: u64 = 0x55;
+        const DAG_PB: u64 = 0x70;
+
+        let hash = Multihash::<64>::wrap(SHA_256_MH, &[1; 32]).unwrap();
+        let v0 = Cid::new_v0(hash).unwrap();
+        let v1 = Cid::new_v1(RAW, hash);
+
+        assert_eq!(Key::from(v0), Key::from(hash));
+        assert_eq!(Key::from(v1), Key::from(hash));
+        assert_eq!(Key::from(v1).to_cid(RAW).unwrap(), v1);
+        assert_eq!(Key::from(v0).to_cid(DAG_PB).unwrap(), v0.into_v1().unwrap());
+    }
 }
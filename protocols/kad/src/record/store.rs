@@ -101,3 +101,22 @@ pub trait RecordStore {
     /// Removes a provider record from the store.
     fn remove_provider(&mut self, k: &Key, p: &PeerId);
 }
+
+/// Durable storage of the keys provided by the local node.
+///
+/// A [`RecordStore`] is typically kept in memory, thus the keys provided by the local node are lost
+/// when the process restarts. Attaching a `ProvideSet` via
+/// [`Behaviour::set_provide_set`](crate::Behaviour::set_provide_set) mirrors all calls to
+/// [`Behaviour::start_providing`](crate::Behaviour::start_providing) and
+/// [`Behaviour::stop_providing`](crate::Behaviour::stop_providing) into it, such that the keys can
+/// be restored, and subsequently re-provided, upon the next start.
+pub trait ProvideSet {
+    /// Loads all keys of the set.
+    fn load(&mut self) -> std::io::Result<Vec<Key>>;
+
+    /// Adds a key to the set.
+    fn insert(&mut self, key: &Key) -> std::io::Result<()>;
+
+    /// Removes a key from the set.
+    fn remove(&mut self, key: &Key) -> std::io::Result<()>;
+}
@@ -25,7 +25,7 @@ mod timer;
 use self::iface::InterfaceState;
 use crate::behaviour::{socket::AsyncSocket, timer::Builder};
 use crate::Config;
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
 use futures::{Stream, StreamExt};
 use if_watch::IfEvent;
 use libp2p_core::{Endpoint, Multiaddr};
@@ -37,9 +37,10 @@ use libp2p_swarm::{
 };
 use smallvec::SmallVec;
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::VecDeque;
 use std::future::Future;
 use std::sync::{Arc, RwLock};
-use std::{cmp, fmt, io, net::IpAddr, pin::Pin, task::Context, task::Poll, time::Instant};
+use std::{fmt, io, net::IpAddr, pin::Pin, task::Context, task::Poll, time::Instant};
 
 /// An abstraction to allow for compatibility with various async runtimes.
 pub trait Provider: 'static {
@@ -149,7 +150,9 @@ where
     if_watch: P::Watcher,
 
     /// Handles to tasks running the mDNS queries.
-    if_tasks: HashMap<IpAddr, P::TaskHandle>,
+    ///
+    /// Dropping the [`oneshot::Sender`] asks the task to send its goodbye packets and terminate.
+    if_tasks: HashMap<IpAddr, (P::TaskHandle, oneshot::Sender<()>)>,
 
     query_response_receiver: mpsc::Receiver<(PeerId, Multiaddr, Instant)>,
     query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
@@ -165,6 +168,9 @@ where
     /// `None` if `discovered_nodes` is empty.
     closest_expiration: Option<P::Timer>,
 
+    /// Events that are yet to be returned from [`NetworkBehaviour::poll`].
+    pending_events: VecDeque<Event>,
+
     /// The current set of listen addresses.
     ///
     /// This is shared across all interface tasks using an [`RwLock`].
@@ -190,6 +196,7 @@ where
             query_response_sender: tx,
             discovered_nodes: Default::default(),
             closest_expiration: Default::default(),
+            pending_events: Default::default(),
             listen_addresses: Default::default(),
             local_peer_id,
         })
@@ -287,6 +294,9 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
         // Poll ifwatch.
         while let Poll::Ready(Some(event)) = Pin::new(&mut self.if_watch).poll_next(cx) {
             match event {
@@ -301,15 +311,17 @@ where
                         continue;
                     }
                     if let Entry::Vacant(e) = self.if_tasks.entry(addr) {
+                        let (shutdown_tx, shutdown_rx) = oneshot::channel();
                         match InterfaceState::<P::Socket, P::Timer>::new(
                             addr,
                             self.config.clone(),
                             self.local_peer_id,
                             self.listen_addresses.clone(),
                             self.query_response_sender.clone(),
+                            shutdown_rx,
                         ) {
                             Ok(iface_state) => {
-                                e.insert(P::spawn(iface_state));
+                                e.insert((P::spawn(iface_state), shutdown_tx));
                             }
                             Err(err) => {
                                 tracing::error!("failed to create `InterfaceState`: {}", err)
@@ -318,7 +330,8 @@ where
                     }
                 }
                 Ok(IfEvent::Down(inet)) => {
-                    if let Some(handle) = self.if_tasks.remove(&inet.addr()) {
+                    // The interface is gone, thus there is no point in sending goodbye packets.
+                    if let Some((handle, _)) = self.if_tasks.remove(&inet.addr()) {
                         tracing::info!(instance=%inet.addr(), "dropping instance");
 
                         handle.abort();
@@ -329,27 +342,45 @@ where
         }
         // Emit discovered event.
         let mut discovered = Vec::new();
+        let mut expiry_updated = Vec::new();
 
         while let Poll::Ready(Some((peer, addr, expiration))) =
             self.query_response_receiver.poll_next_unpin(cx)
         {
+            // Records with a TTL of zero are goodbyes, see RFC 6762 section 10.1.
+            let is_goodbye = expiration <= Instant::now();
+
             if let Some((_, _, cur_expires)) = self
                 .discovered_nodes
                 .iter_mut()
                 .find(|(p, a, _)| *p == peer && *a == addr)
             {
-                *cur_expires = cmp::max(*cur_expires, expiration);
-            } else {
+                if is_goodbye {
+                    tracing::debug!(%peer, address=%addr, "received goodbye for address");
+                    *cur_expires = expiration;
+                } else if expiration > *cur_expires {
+                    *cur_expires = expiration;
+                    expiry_updated.push((peer, addr, expiration));
+                }
+            } else if !is_goodbye {
                 tracing::info!(%peer, address=%addr, "discovered peer on address");
                 self.discovered_nodes.push((peer, addr.clone(), expiration));
-                discovered.push((peer, addr));
+                discovered.push((peer, addr.clone()));
+                expiry_updated.push((peer, addr, expiration));
             }
         }
 
+        if !expiry_updated.is_empty() {
+            self.pending_events
+                .push_back(Event::ExpiryUpdated(expiry_updated));
+        }
         if !discovered.is_empty() {
             let event = Event::Discovered(discovered);
             return Poll::Ready(ToSwarm::GenerateEvent(event));
         }
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
         // Emit expired event.
         let now = Instant::now();
         let mut closest_expiration = None;
@@ -387,5 +418,13 @@ pub enum Event {
     ///
     /// Each discovered record has a time-to-live. When this TTL expires and the address hasn't
     /// been refreshed, we remove it from the list and emit it as an `Expired` event.
+    /// Remotes announcing their departure with a goodbye packet are expired right away.
     Expired(Vec<(PeerId, Multiaddr)>),
+
+    /// The given combinations of `PeerId` and `Multiaddr` will expire at the given [`Instant`],
+    /// unless they are refreshed in the meantime.
+    ///
+    /// Emitted for newly discovered addresses, following the [`Event::Discovered`], as well as
+    /// whenever a remote's response extends the lifetime of an address.
+    ExpiryUpdated(Vec<(PeerId, Multiaddr, Instant)>),
 }
@@ -25,8 +25,8 @@ use self::dns::{build_query, build_query_response, build_service_discovery_respo
 use self::query::MdnsPacket;
 use crate::behaviour::{socket::AsyncSocket, timer::Builder};
 use crate::Config;
-use futures::channel::mpsc;
-use futures::{SinkExt, StreamExt};
+use futures::channel::{mpsc, oneshot};
+use futures::{FutureExt, SinkExt, StreamExt};
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use libp2p_swarm::ListenAddresses;
@@ -81,6 +81,14 @@ pub(crate) struct InterfaceState<U, T> {
 
     query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
 
+    /// Resolves once the [`Behaviour`](crate::Behaviour) asks this instance to shut down,
+    /// either explicitly or by dropping the sender.
+    shutdown: oneshot::Receiver<()>,
+    /// Whether we are flushing our remaining packets before terminating.
+    shutting_down: bool,
+    /// Whether to announce our departure with goodbye packets upon shutdown.
+    send_goodbye: bool,
+
     /// Buffer used for receiving data from the main socket.
     /// RFC6762 discourages packets larger than the interface MTU, but allows sizes of up to 9000
     /// bytes, if it can be ensured that all participating devices can handle such large packets.
@@ -116,6 +124,7 @@ where
         local_peer_id: PeerId,
         listen_addresses: Arc<RwLock<ListenAddresses>>,
         query_response_sender: mpsc::Sender<(PeerId, Multiaddr, Instant)>,
+        shutdown: oneshot::Receiver<()>,
     ) -> io::Result<Self> {
         tracing::info!(address=%addr, "creating instance on iface address");
         let recv_socket = match addr {
@@ -171,6 +180,9 @@ where
             send_socket,
             listen_addresses,
             query_response_sender,
+            shutdown,
+            shutting_down: false,
+            send_goodbye: config.send_goodbye,
             recv_buffer: [0; 4096],
             send_buffer: Default::default(),
             discovered: Default::default(),
@@ -192,6 +204,30 @@ where
     fn mdns_socket(&self) -> SocketAddr {
         SocketAddr::new(self.multicast_addr, 5353)
     }
+
+    /// Queues unsolicited responses with a TTL of zero for all our listen addresses, telling
+    /// remotes to expire them right away (see [RFC 6762 section 10.1]).
+    ///
+    /// [RFC 6762 section 10.1]: https://datatracker.ietf.org/doc/html/rfc6762#section-10.1
+    fn queue_goodbye(&mut self) {
+        let listen_addresses = self
+            .listen_addresses
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+
+        if listen_addresses.iter().next().is_none() {
+            return;
+        }
+
+        tracing::debug!(address=%self.addr, "sending goodbye on iface address");
+
+        self.send_buffer.extend(build_query_response(
+            0,
+            self.local_peer_id,
+            listen_addresses.iter(),
+            Duration::ZERO,
+        ));
+    }
 }
 
 impl<U, T> Future for InterfaceState<U, T>
@@ -204,6 +240,30 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
 
+        if !this.shutting_down && this.shutdown.poll_unpin(cx).is_ready() {
+            this.shutting_down = true;
+
+            if this.send_goodbye {
+                this.queue_goodbye();
+            }
+        }
+
+        if this.shutting_down {
+            // Flush the remaining packets, most importantly the goodbye, before terminating.
+            while let Some(packet) = this.send_buffer.front() {
+                match this.send_socket.poll_write(cx, packet, this.mdns_socket()) {
+                    Poll::Ready(Ok(_)) => {}
+                    Poll::Ready(Err(err)) => {
+                        tracing::debug!(address=%this.addr, "error sending packet on iface address during shutdown {}", err);
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+                this.send_buffer.pop_front();
+            }
+
+            return Poll::Ready(());
+        }
+
         loop {
             // 1st priority: Low latency: Create packet ASAP after timeout.
             if this.timeout.poll_next_unpin(cx).is_ready() {
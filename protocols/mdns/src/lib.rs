@@ -63,6 +63,8 @@ pub const IPV6_MDNS_MULTICAST_ADDRESS: Ipv6Addr = Ipv6Addr::new(0xFF02, 0, 0, 0,
 #[derive(Debug, Clone)]
 pub struct Config {
     /// TTL to use for mdns records.
+    ///
+    /// Remotes expire our addresses if they don't hear from us again within this duration.
     pub ttl: Duration,
     /// Interval at which to poll the network for new peers. This isn't
     /// necessary during normal operation but avoids the case that an
@@ -72,6 +74,9 @@ pub struct Config {
     pub query_interval: Duration,
     /// Use IPv6 instead of IPv4.
     pub enable_ipv6: bool,
+    /// Announce our addresses with a TTL of zero when the [`Behaviour`] is dropped, prompting
+    /// remotes to expire them immediately instead of waiting for [`Config::ttl`] to elapse.
+    pub send_goodbye: bool,
 }
 
 impl Default for Config {
@@ -80,6 +85,7 @@ impl Default for Config {
             ttl: Duration::from_secs(6 * 60),
             query_interval: Duration::from_secs(5 * 60),
             enable_ipv6: false,
+            send_goodbye: true,
         }
     }
 }
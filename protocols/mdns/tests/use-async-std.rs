@@ -127,6 +127,49 @@ async fn test_no_expiration_on_close_async_std() {
     .await;
 }
 
+#[async_std::test]
+async fn test_goodbye_async_std() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let config = Config {
+        ttl: Duration::from_secs(120),
+        query_interval: Duration::from_secs(10),
+        ..Default::default()
+    };
+
+    let mut a = create_swarm(config.clone()).await;
+
+    let mut b = create_swarm(config).await;
+    let b_peer_id = *b.local_peer_id();
+
+    loop {
+        if let Either::Left((Event::Discovered(peers), _)) =
+            futures::future::select(a.next_behaviour_event(), b.next_behaviour_event()).await
+        {
+            if peers.into_iter().any(|(p, _)| p == b_peer_id) {
+                break;
+            }
+        }
+    }
+
+    // Dropping `b` makes it announce its departure, well before the TTL elapses.
+    drop(b);
+
+    async_std::future::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Event::Expired(peers) = a.next_behaviour_event().await {
+                if peers.into_iter().any(|(p, _)| p == b_peer_id) {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+    .expect("`b` to be expired upon its goodbye");
+}
+
 async fn run_discovery_test(config: Config) {
     let mut a = create_swarm(config.clone()).await;
     let a_peer_id = *a.local_peer_id();
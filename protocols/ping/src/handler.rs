@@ -21,23 +21,22 @@
 use crate::{protocol, PROTOCOL_NAME};
 use futures::future::{BoxFuture, Either};
 use futures::prelude::*;
-use futures_timer::Delay;
 use libp2p_core::upgrade::ReadyUpgrade;
 use libp2p_swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
 };
 use libp2p_swarm::{
-    ConnectionHandler, ConnectionHandlerEvent, Stream, StreamProtocol, StreamUpgradeError,
-    SubstreamProtocol,
+    Clock, ConnectionHandler, ConnectionHandlerEvent, Sleep, Stream, StreamProtocol,
+    StreamUpgradeError, SubstreamProtocol,
 };
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::{
     error::Error,
     fmt, io,
     task::{Context, Poll},
     time::Duration,
 };
-use void::Void;
 
 /// The configuration for outbound pings.
 #[derive(Debug, Clone)]
@@ -46,6 +45,8 @@ pub struct Config {
     timeout: Duration,
     /// The duration between outbound pings.
     interval: Duration,
+    /// The source of time for the ping interval and timeout.
+    clock: Arc<dyn Clock>,
 }
 
 impl Config {
@@ -63,6 +64,7 @@ impl Config {
         Self {
             timeout: Duration::from_secs(20),
             interval: Duration::from_secs(15),
+            clock: Arc::new(libp2p_swarm::SystemClock),
         }
     }
 
@@ -77,6 +79,14 @@ impl Config {
         self.interval = d;
         self
     }
+
+    /// Sets the [`Clock`] driving the ping interval and timeout.
+    ///
+    /// Defaults to the [`SystemClock`](libp2p_swarm::SystemClock).
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
 }
 
 impl Default for Config {
@@ -125,13 +135,22 @@ impl Error for Failure {
     }
 }
 
+/// Commands sent from the behaviour to the [`Handler`].
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Replace the configuration for outbound pings.
+    UpdateConfig(Config),
+    /// Send an outbound ping immediately and report its result, including the first failure.
+    PingNow,
+}
+
 /// Protocol handler that handles pinging the remote at a regular period
 /// and answering ping queries.
 pub struct Handler {
     /// Configuration options.
     config: Config,
     /// The timer used for the delay to the next ping.
-    interval: Delay,
+    interval: Sleep,
     /// Outbound ping failures that are pending to be processed by `poll()`.
     pending_errors: VecDeque<Failure>,
     /// The number of consecutive ping failures that occurred.
@@ -146,6 +165,8 @@ pub struct Handler {
     inbound: Option<PongFuture>,
     /// Tracks the state of our handler.
     state: State,
+    /// Whether the result of the next outbound ping was requested on demand.
+    ping_now: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -165,13 +186,14 @@ impl Handler {
     /// Builds a new [`Handler`] with the given configuration.
     pub fn new(config: Config) -> Self {
         Handler {
+            interval: config.clock.sleep(Duration::ZERO),
             config,
-            interval: Delay::new(Duration::new(0, 0)),
             pending_errors: VecDeque::with_capacity(2),
             failures: 0,
             outbound: None,
             inbound: None,
             state: State::Active,
+            ping_now: false,
         }
     }
 
@@ -207,7 +229,7 @@ impl Handler {
 }
 
 impl ConnectionHandler for Handler {
-    type FromBehaviour = Void;
+    type FromBehaviour = Command;
     type ToBehaviour = Result<Duration, Failure>;
     type InboundProtocol = ReadyUpgrade<StreamProtocol>;
     type OutboundProtocol = ReadyUpgrade<StreamProtocol>;
@@ -218,7 +240,27 @@ impl ConnectionHandler for Handler {
         SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ())
     }
 
-    fn on_behaviour_event(&mut self, _: Void) {}
+    fn on_behaviour_event(&mut self, command: Command) {
+        match command {
+            Command::UpdateConfig(config) => {
+                // Reschedule the next ping of an idle stream according to the new interval.
+                if matches!(self.outbound, Some(OutboundState::Idle(_))) {
+                    self.interval = config.clock.sleep(config.interval);
+                }
+                self.config = config;
+            }
+            Command::PingNow => {
+                self.ping_now = true;
+
+                match self.state {
+                    // Report the missing support again.
+                    State::Inactive { .. } => self.state = State::Inactive { reported: false },
+                    // An outbound ping in flight or pending on a new stream serves the request.
+                    State::Active => self.interval = self.config.clock.sleep(Duration::ZERO),
+                }
+            }
+        }
+    }
 
     #[tracing::instrument(level = "trace", name = "ConnectionHandler::poll", skip(self, cx))]
     fn poll(
@@ -232,6 +274,7 @@ impl ConnectionHandler for Handler {
             }
             State::Inactive { reported: false } => {
                 self.state = State::Inactive { reported: true };
+                self.ping_now = false;
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Err(
                     Failure::Unsupported,
                 )));
@@ -268,7 +311,8 @@ impl ConnectionHandler for Handler {
                 // for each ping to have successful ping exchanges with peers
                 // that use a single substream, since every successful ping
                 // resets `failures` to `0`.
-                if self.failures > 1 {
+                if self.failures > 1 || self.ping_now {
+                    self.ping_now = false;
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Err(error)));
                 }
             }
@@ -283,12 +327,13 @@ impl ConnectionHandler for Handler {
                     Poll::Ready(Ok((stream, rtt))) => {
                         tracing::debug!(?rtt, "ping succeeded");
                         self.failures = 0;
-                        self.interval.reset(self.config.interval);
+                        self.ping_now = false;
+                        self.interval = self.config.clock.sleep(self.config.interval);
                         self.outbound = Some(OutboundState::Idle(stream));
                         return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(Ok(rtt)));
                     }
                     Poll::Ready(Err(e)) => {
-                        self.interval.reset(self.config.interval);
+                        self.interval = self.config.clock.sleep(self.config.interval);
                         self.pending_errors.push_front(e);
                     }
                 },
@@ -299,7 +344,7 @@ impl ConnectionHandler for Handler {
                     }
                     Poll::Ready(()) => {
                         self.outbound = Some(OutboundState::Ping(
-                            send_ping(stream, self.config.timeout).boxed(),
+                            send_ping(stream, self.config.clock.sleep(self.config.timeout)).boxed(),
                         ));
                     }
                 },
@@ -346,7 +391,7 @@ impl ConnectionHandler for Handler {
             }) => {
                 stream.ignore_for_keep_alive();
                 self.outbound = Some(OutboundState::Ping(
-                    send_ping(stream, self.config.timeout).boxed(),
+                    send_ping(stream, self.config.clock.sleep(self.config.timeout)).boxed(),
                 ));
             }
             ConnectionEvent::DialUpgradeError(dial_upgrade_error) => {
@@ -371,11 +416,11 @@ enum OutboundState {
 }
 
 /// A wrapper around [`protocol::send_ping`] that enforces a time out.
-async fn send_ping(stream: Stream, timeout: Duration) -> Result<(Stream, Duration), Failure> {
+async fn send_ping(stream: Stream, timeout: Sleep) -> Result<(Stream, Duration), Failure> {
     let ping = protocol::send_ping(stream);
     futures::pin_mut!(ping);
 
-    match future::select(ping, Delay::new(timeout)).await {
+    match future::select(ping, timeout).await {
         Either::Left((Ok((stream, rtt)), _)) => Ok((stream, rtt)),
         Either::Left((Err(e), _)) => Err(Failure::other(e)),
         Either::Right(((), _)) => Err(Failure::Timeout),
@@ -50,16 +50,17 @@
 mod handler;
 mod protocol;
 
-use handler::Handler;
+use handler::{Command, Handler};
 use libp2p_core::{Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    behaviour::FromSwarm, ConnectionDenied, ConnectionId, NetworkBehaviour, THandler,
-    THandlerInEvent, THandlerOutEvent, ToSwarm,
+    behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm},
+    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
 };
 use std::time::Duration;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     task::{Context, Poll},
 };
 
@@ -75,6 +76,12 @@ pub struct Behaviour {
     config: Config,
     /// Queue of events to yield to the swarm.
     events: VecDeque<Event>,
+    /// All established connections, used to propagate configuration updates.
+    connections: HashMap<ConnectionId, PeerId>,
+    /// Commands to be sent to the handlers of established connections.
+    pending_commands: VecDeque<(PeerId, ConnectionId, Command)>,
+    /// Round-trip times to be reported to the swarm.
+    pending_rtt_reports: VecDeque<(ConnectionId, Duration)>,
 }
 
 /// Event generated by the `Ping` network behaviour.
@@ -94,8 +101,64 @@ impl Behaviour {
         Self {
             config,
             events: VecDeque::new(),
+            connections: HashMap::new(),
+            pending_commands: VecDeque::new(),
+            pending_rtt_reports: VecDeque::new(),
         }
     }
+
+    /// Replaces the configuration for outbound pings.
+    ///
+    /// The new configuration applies to new connections and is propagated to the handlers of all
+    /// established connections. An outbound ping that is already in flight keeps its timeout and
+    /// the new interval applies from the next ping onwards.
+    pub fn set_config(&mut self, config: Config) {
+        self.pending_commands
+            .retain(|(_, _, command)| !matches!(command, Command::UpdateConfig(_)));
+        self.pending_commands.extend(
+            self.connections.iter().map(|(connection, peer)| {
+                (*peer, *connection, Command::UpdateConfig(config.clone()))
+            }),
+        );
+        self.config = config;
+    }
+
+    /// Sends a ping on all established connections to the given peer as soon as possible, in
+    /// addition to the periodic pings.
+    ///
+    /// The result is reported as the next [`Event`] of each returned connection. Contrary to
+    /// periodic pings, a failure of an on-demand ping is always reported.
+    /// Returns an empty list if the peer is not connected.
+    pub fn ping_now(&mut self, peer: PeerId) -> Vec<ConnectionId> {
+        let connections = self
+            .connections
+            .iter()
+            .filter(|(_, p)| **p == peer)
+            .map(|(connection, _)| *connection)
+            .collect::<Vec<_>>();
+        self.pending_commands.extend(
+            connections
+                .iter()
+                .map(|connection| (peer, *connection, Command::PingNow)),
+        );
+
+        connections
+    }
+
+    /// Sends a ping on the given connection as soon as possible, in addition to the periodic
+    /// pings.
+    ///
+    /// The result is reported as the next [`Event`] of the connection, see
+    /// [`Behaviour::ping_now`]. Returns `false` if the connection is not established.
+    pub fn ping_now_on_connection(&mut self, connection: ConnectionId) -> bool {
+        let Some(peer) = self.connections.get(&connection) else {
+            return false;
+        };
+        self.pending_commands
+            .push_back((*peer, connection, Command::PingNow));
+
+        true
+    }
 }
 
 impl Default for Behaviour {
@@ -134,6 +197,9 @@ impl NetworkBehaviour for Behaviour {
         connection: ConnectionId,
         result: THandlerOutEvent<Self>,
     ) {
+        if let Ok(rtt) = result {
+            self.pending_rtt_reports.push_back((connection, rtt));
+        }
         self.events.push_front(Event {
             peer,
             connection,
@@ -143,12 +209,42 @@ impl NetworkBehaviour for Behaviour {
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
     fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some((connection_id, rtt)) = self.pending_rtt_reports.pop_front() {
+            return Poll::Ready(ToSwarm::ConnectionRtt { connection_id, rtt });
+        }
+
         if let Some(e) = self.events.pop_back() {
-            Poll::Ready(ToSwarm::GenerateEvent(e))
-        } else {
-            Poll::Pending
+            return Poll::Ready(ToSwarm::GenerateEvent(e));
+        }
+
+        if let Some((peer_id, connection, event)) = self.pending_commands.pop_front() {
+            return Poll::Ready(ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::One(connection),
+                event,
+            });
         }
+
+        Poll::Pending
     }
 
-    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                ..
+            }) => {
+                self.connections.insert(connection_id, peer_id);
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                self.connections.remove(&connection_id);
+                self.pending_commands
+                    .retain(|(_, connection, _)| *connection != connection_id);
+                self.pending_rtt_reports
+                    .retain(|(connection, _)| *connection != connection_id);
+            }
+            _ => {}
+        }
+    }
 }
@@ -20,9 +20,10 @@
 
 //! Integration tests for the `Ping` network behaviour.
 
+use libp2p_identity::PeerId;
 use libp2p_ping as ping;
 use libp2p_swarm::dummy;
-use libp2p_swarm::{Swarm, SwarmEvent};
+use libp2p_swarm::{Swarm, SwarmEvent, VirtualClock};
 use libp2p_swarm_test::SwarmExt;
 use quickcheck::*;
 use std::{num::NonZeroU8, time::Duration};
@@ -93,3 +94,114 @@ fn unsupported_doesnt_fail() {
 
     result.expect("node with ping should not fail connection due to unsupported protocol");
 }
+
+#[test]
+fn set_config_updates_established_connections() {
+    let cfg = ping::Config::new().with_interval(Duration::from_secs(60 * 60));
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+
+        // The first ping is sent immediately after the connection is established.
+        let ([e1], [e2]): ([ping::Event; 1], [ping::Event; 1]) =
+            libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+        assert_ping_rtt_less_than_50ms(e1);
+        assert_ping_rtt_less_than_50ms(e2);
+
+        swarm2
+            .behaviour_mut()
+            .set_config(ping::Config::new().with_interval(Duration::from_millis(10)));
+        async_std::task::spawn(swarm1.loop_on_next());
+
+        // Without the update, the next ping would only be sent after an hour.
+        let event = async_std::future::timeout(
+            Duration::from_secs(2),
+            swarm2.wait(|e| match e {
+                SwarmEvent::Behaviour(e) => Some(e),
+                _ => None,
+            }),
+        )
+        .await
+        .expect("ping with updated interval");
+        assert_ping_rtt_less_than_50ms(event);
+    });
+}
+
+#[test]
+fn ping_now_pings_established_connections() {
+    let cfg = ping::Config::new().with_interval(Duration::from_secs(60 * 60));
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+
+        // The first ping is sent immediately after the connection is established.
+        let ([_], [_]): ([ping::Event; 1], [ping::Event; 1]) =
+            libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+        let peer1 = *swarm1.local_peer_id();
+        assert!(swarm2.behaviour_mut().ping_now(PeerId::random()).is_empty());
+        let connections = swarm2.behaviour_mut().ping_now(peer1);
+        assert_eq!(connections.len(), 1);
+        async_std::task::spawn(swarm1.loop_on_next());
+
+        // Without the on-demand ping, the next ping would only be sent after an hour.
+        let event = async_std::future::timeout(
+            Duration::from_secs(2),
+            swarm2.wait(|e| match e {
+                SwarmEvent::Behaviour(e) => Some(e),
+                _ => None,
+            }),
+        )
+        .await
+        .expect("on-demand ping");
+        assert_eq!(event.peer, peer1);
+        assert_eq!(event.connection, connections[0]);
+        assert_ping_rtt_less_than_50ms(event);
+    });
+}
+
+#[test]
+fn virtual_clock_drives_ping_interval() {
+    let clock = VirtualClock::new();
+    let cfg = ping::Config::new()
+        .with_interval(Duration::from_secs(60 * 60))
+        .with_clock(clock.clone());
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+
+        // The first ping is sent immediately after the connection is established.
+        let ([_], [_]): ([ping::Event; 1], [ping::Event; 1]) =
+            libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+        async_std::task::spawn(swarm1.loop_on_next());
+
+        let mut next_ping = Box::pin(swarm2.wait(|e| match e {
+            SwarmEvent::Behaviour(e) => Some(e),
+            _ => None,
+        }));
+        assert!(
+            async_std::future::timeout(Duration::from_millis(100), &mut next_ping)
+                .await
+                .is_err(),
+            "no ping before the interval elapsed"
+        );
+
+        clock.advance(Duration::from_secs(60 * 60));
+        let event = async_std::future::timeout(Duration::from_secs(2), next_ping)
+            .await
+            .expect("ping after the interval elapsed");
+        assert_ping_rtt_less_than_50ms(event);
+    });
+}
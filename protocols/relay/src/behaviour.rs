@@ -20,6 +20,7 @@
 
 //! [`NetworkBehaviour`] to act as a circuit relay v2 **relay**.
 
+pub(crate) mod admission;
 pub(crate) mod handler;
 pub(crate) mod rate_limiter;
 use crate::behaviour::handler::Handler;
@@ -27,7 +28,10 @@ use crate::multiaddr_ext::MultiaddrExt;
 use crate::proto;
 use crate::protocol::{inbound_hop, outbound_stop};
 use either::Either;
+use futures::future::BoxFuture;
+use futures_bounded::FuturesMap;
 use libp2p_core::multiaddr::Protocol;
+use libp2p_core::transport::global_only;
 use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{ConnectionClosed, FromSwarm};
@@ -38,6 +42,8 @@ use libp2p_swarm::{
 use std::collections::{hash_map, HashMap, HashSet, VecDeque};
 use std::num::NonZeroU32;
 use std::ops::Add;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use web_time::Instant;
@@ -58,6 +64,23 @@ pub struct Config {
     pub max_circuit_duration: Duration,
     pub max_circuit_bytes: u64,
     pub circuit_src_rate_limiters: Vec<Box<dyn rate_limiter::RateLimiter>>,
+    /// Decides whether circuits are relayed to a destination connected to the relay via the
+    /// given address. `None` allows all destinations.
+    ///
+    /// Defaults to denying destinations connected via a private, loopback, link-local or
+    /// otherwise reserved IP address, see [`Config::destination_filter`].
+    pub destination_filter: Option<Box<dyn Fn(&Multiaddr) -> bool + Send>>,
+
+    /// Decides whether reservation and circuit requests are accepted, in addition to the limits
+    /// above. `None` accepts all requests within the limits.
+    ///
+    /// Defaults to `None`.
+    pub admission_policy: Option<Box<dyn admission::AdmissionPolicy>>,
+    /// Time to wait for the [`Config::admission_policy`] to decide on a request.
+    pub admission_timeout: Duration,
+    /// Whether to accept requests the [`Config::admission_policy`] did not decide on within
+    /// [`Config::admission_timeout`].
+    pub admit_on_timeout: bool,
 }
 
 impl Config {
@@ -92,6 +115,50 @@ impl Config {
             ));
         self
     }
+
+    /// Only relay circuits to destinations connected to the relay via an address for which
+    /// `filter` returns `true`, denying all others with [`DenialReason::DestinationDenied`].
+    ///
+    /// By default, circuits to destinations connected via a private (e.g. RFC 1918), loopback,
+    /// link-local or otherwise reserved IP address are denied, preventing clients from using
+    /// the relay to reach into its internal network. Addresses not starting with an IP
+    /// address, e.g. DNS addresses, are allowed.
+    pub fn destination_filter(
+        mut self,
+        filter: impl Fn(&Multiaddr) -> bool + Send + 'static,
+    ) -> Self {
+        self.destination_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Relay circuits to all destinations, regardless of the address they are connected via.
+    pub fn allow_all_destinations(mut self) -> Self {
+        self.destination_filter = None;
+        self
+    }
+
+    /// Consult the given [`AdmissionPolicy`](admission::AdmissionPolicy) before accepting
+    /// reservation and circuit requests.
+    pub fn admission_policy(mut self, policy: impl admission::AdmissionPolicy + 'static) -> Self {
+        self.admission_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Wait at most `timeout` for the admission policy to decide on a request, see
+    /// [`Config::admit_on_timeout`] for requests it did not decide on in time.
+    pub fn admission_timeout(mut self, timeout: Duration) -> Self {
+        self.admission_timeout = timeout;
+        self
+    }
+
+    /// Accept requests the admission policy did not decide on within the
+    /// [`Config::admission_timeout`] if `admit` is `true`, deny them otherwise.
+    ///
+    /// Requests are denied by default.
+    pub fn admit_on_timeout(mut self, admit: bool) -> Self {
+        self.admit_on_timeout = admit;
+        self
+    }
 }
 
 impl std::fmt::Debug for Config {
@@ -112,6 +179,16 @@ impl std::fmt::Debug for Config {
                 "circuit_src_rate_limiters",
                 &format!("[{} rate limiters]", self.circuit_src_rate_limiters.len()),
             )
+            .field(
+                "destination_filter",
+                &self.destination_filter.as_ref().map(|_| "<filter>"),
+            )
+            .field(
+                "admission_policy",
+                &self.admission_policy.as_ref().map(|_| "<policy>"),
+            )
+            .field("admission_timeout", &self.admission_timeout)
+            .field("admit_on_timeout", &self.admit_on_timeout)
             .finish()
     }
 }
@@ -155,6 +232,11 @@ impl Default for Config {
             max_circuit_duration: Duration::from_secs(2 * 60),
             max_circuit_bytes: 1 << 17, // 128 kibibyte
             circuit_src_rate_limiters,
+            destination_filter: Some(Box::new(is_public_destination)),
+
+            admission_policy: None,
+            admission_timeout: Duration::from_secs(10),
+            admit_on_timeout: false,
         }
     }
 }
@@ -177,7 +259,10 @@ pub enum Event {
         error: inbound_hop::Error,
     },
     /// An inbound reservation request has been denied.
-    ReservationReqDenied { src_peer_id: PeerId },
+    ReservationReqDenied {
+        src_peer_id: PeerId,
+        reason: DenialReason,
+    },
     /// Denying an inbound reservation request has failed.
     #[deprecated(
         note = "Will be removed in favor of logging them internally, see <https://github.com/libp2p/rust-libp2p/issues/4757> for details."
@@ -188,10 +273,13 @@ pub enum Event {
     },
     /// An inbound reservation has timed out.
     ReservationTimedOut { src_peer_id: PeerId },
+    /// An inbound reservation has been closed because the connection it was made on closed.
+    ReservationClosed { src_peer_id: PeerId },
     /// An inbound circuit request has been denied.
     CircuitReqDenied {
         src_peer_id: PeerId,
         dst_peer_id: PeerId,
+        reason: DenialReason,
     },
     /// Denying an inbound circuit request failed.
     #[deprecated(
@@ -229,10 +317,51 @@ pub enum Event {
     CircuitClosed {
         src_peer_id: PeerId,
         dst_peer_id: PeerId,
+        /// The number of bytes relayed in both directions.
+        bytes_relayed: u64,
         error: Option<std::io::Error>,
     },
 }
 
+/// The reason for denying an inbound reservation or circuit request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DenialReason {
+    /// The peer exceeded [`Config::max_reservations_per_peer`] or
+    /// [`Config::max_circuits_per_peer`].
+    PeerLimitExceeded,
+    /// The relay reached [`Config::max_reservations`] or [`Config::max_circuits`].
+    LimitExceeded,
+    /// The request was denied by one of the rate limiters.
+    RateLimited,
+    /// The destination of the circuit request has no reservation with the relay.
+    NoReservation,
+    /// Connecting to the destination of the circuit request failed.
+    ConnectionFailed,
+    /// The destination of the circuit request is connected via an address denied by the
+    /// [`Config::destination_filter`].
+    DestinationDenied,
+    /// The request was denied by the [`Config::admission_policy`].
+    AdmissionDenied,
+    /// The [`Config::admission_policy`] did not decide on the request within
+    /// [`Config::admission_timeout`] and [`Config::admit_on_timeout`] is `false`.
+    AdmissionTimedOut,
+}
+
+impl DenialReason {
+    fn status(&self) -> proto::Status {
+        match self {
+            DenialReason::PeerLimitExceeded
+            | DenialReason::LimitExceeded
+            | DenialReason::RateLimited => proto::Status::RESOURCE_LIMIT_EXCEEDED,
+            DenialReason::NoReservation => proto::Status::NO_RESERVATION,
+            DenialReason::ConnectionFailed => proto::Status::CONNECTION_FAILED,
+            DenialReason::DestinationDenied
+            | DenialReason::AdmissionDenied
+            | DenialReason::AdmissionTimedOut => proto::Status::PERMISSION_DENIED,
+        }
+    }
+}
+
 /// [`NetworkBehaviour`] implementation of the relay server
 /// functionality of the circuit relay v2 protocol.
 pub struct Behaviour {
@@ -242,21 +371,37 @@ pub struct Behaviour {
 
     reservations: HashMap<PeerId, HashSet<ConnectionId>>,
     circuits: CircuitsTracker,
+    /// The remote address of each non-relayed connection, checked against the
+    /// [`Config::destination_filter`].
+    connection_addrs: HashMap<ConnectionId, Multiaddr>,
 
     /// Queue of actions to return when polled.
     queued_actions: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
 
+    /// Decisions of the [`Config::admission_policy`] still being awaited.
+    admissions: FuturesMap<u64, bool>,
+    /// Requests waiting for the decision of the [`Config::admission_policy`].
+    pending_admissions: HashMap<u64, PendingAdmission>,
+    next_admission_id: u64,
+
     external_addresses: ExternalAddresses,
 }
 
 impl Behaviour {
     pub fn new(local_peer_id: PeerId, config: Config) -> Self {
         Self {
+            admissions: FuturesMap::new(
+                config.admission_timeout,
+                config.max_reservations + config.max_circuits,
+            ),
             config,
             local_peer_id,
             reservations: Default::default(),
             circuits: Default::default(),
+            connection_addrs: Default::default(),
             queued_actions: Default::default(),
+            pending_admissions: Default::default(),
+            next_admission_id: 0,
             external_addresses: Default::default(),
         }
     }
@@ -269,13 +414,29 @@ impl Behaviour {
             ..
         }: ConnectionClosed,
     ) {
+        self.connection_addrs.remove(&connection_id);
+
         if let hash_map::Entry::Occupied(mut peer) = self.reservations.entry(peer_id) {
-            peer.get_mut().remove(&connection_id);
+            if peer.get_mut().remove(&connection_id) {
+                self.queued_actions
+                    .push_back(ToSwarm::GenerateEvent(Event::ReservationClosed {
+                        src_peer_id: peer_id,
+                    }));
+            }
             if peer.get().is_empty() {
                 peer.remove();
             }
         }
 
+        let admissions = &mut self.admissions;
+        self.pending_admissions.retain(|id, pending| {
+            if pending.connection() != connection_id {
+                return true;
+            }
+            admissions.remove(*id);
+            false
+        });
+
         for circuit in self
             .circuits
             .remove_by_connection(peer_id, connection_id)
@@ -287,10 +448,243 @@ impl Behaviour {
                 .push_back(ToSwarm::GenerateEvent(Event::CircuitClosed {
                     src_peer_id: circuit.src_peer_id,
                     dst_peer_id: circuit.dst_peer_id,
+                    bytes_relayed: circuit.bytes_relayed.load(Ordering::Relaxed),
                     error: Some(std::io::ErrorKind::ConnectionAborted.into()),
                 }));
         }
     }
+
+    /// Checks a reservation request of `peer` against the static reservation limits.
+    fn reservation_limit_denial(&self, peer: PeerId, renewed: bool) -> Option<DenialReason> {
+        // Deny if it is a new reservation and exceeds `max_reservations_per_peer`.
+        if !renewed
+            && self.reservations.get(&peer).map(|cs| cs.len()).unwrap_or(0)
+                > self.config.max_reservations_per_peer
+        {
+            return Some(DenialReason::PeerLimitExceeded);
+        }
+        // Deny if it exceeds `max_reservations`.
+        if self.reservations.values().map(|cs| cs.len()).sum::<usize>()
+            >= self.config.max_reservations
+        {
+            return Some(DenialReason::LimitExceeded);
+        }
+
+        None
+    }
+
+    /// Checks a circuit request of `peer` against the static circuit limits.
+    fn circuit_limit_denial(&self, peer: PeerId) -> Option<DenialReason> {
+        if self.circuits.num_circuits_of_peer(peer) > self.config.max_circuits_per_peer {
+            return Some(DenialReason::PeerLimitExceeded);
+        }
+        if self.circuits.len() >= self.config.max_circuits {
+            return Some(DenialReason::LimitExceeded);
+        }
+
+        None
+    }
+
+    /// Whether circuits may be relayed to a destination on the given connection.
+    fn is_allowed_destination(&self, connection: ConnectionId) -> bool {
+        let (Some(filter), Some(addr)) = (
+            self.config.destination_filter.as_ref(),
+            self.connection_addrs.get(&connection),
+        ) else {
+            return true;
+        };
+
+        filter(addr)
+    }
+
+    fn await_admission(&mut self, admission: BoxFuture<'static, bool>, pending: PendingAdmission) {
+        let id = self.next_admission_id;
+        self.next_admission_id += 1;
+
+        // Admission IDs are unique, thus pushing can only fail due to the capacity.
+        if self.admissions.try_push(id, admission).is_err() {
+            tracing::debug!(
+                peer=%pending.peer_id(),
+                "Denying request, too many requests awaiting admission"
+            );
+            self.answer(pending, Some(DenialReason::LimitExceeded));
+            return;
+        }
+
+        self.pending_admissions.insert(id, pending);
+    }
+
+    /// Accepts the given request, or denies it if there is a `denial` reason.
+    fn answer(&mut self, pending: PendingAdmission, denial: Option<DenialReason>) {
+        let action = match (pending, denial) {
+            (
+                PendingAdmission::Reservation {
+                    peer_id,
+                    connection,
+                    inbound_reservation_req,
+                    ..
+                },
+                Some(reason),
+            ) => ToSwarm::NotifyHandler {
+                handler: NotifyHandler::One(connection),
+                peer_id,
+                event: Either::Left(handler::In::DenyReservationReq {
+                    inbound_reservation_req,
+                    status: reason.status(),
+                    reason,
+                }),
+            },
+            (
+                PendingAdmission::Reservation {
+                    peer_id,
+                    connection,
+                    inbound_reservation_req,
+                    ..
+                },
+                None,
+            ) => {
+                // Accept reservation.
+                self.reservations
+                    .entry(peer_id)
+                    .or_default()
+                    .insert(connection);
+
+                ToSwarm::NotifyHandler {
+                    handler: NotifyHandler::One(connection),
+                    peer_id,
+                    event: Either::Left(handler::In::AcceptReservationReq {
+                        inbound_reservation_req,
+                        addrs: self
+                            .external_addresses
+                            .iter()
+                            .cloned()
+                            // Add local peer ID in case it isn't present yet.
+                            .filter_map(|a| match a.iter().last()? {
+                                Protocol::P2p(_) => Some(a),
+                                _ => Some(a.with(Protocol::P2p(self.local_peer_id))),
+                            })
+                            .collect(),
+                    }),
+                }
+            }
+            (
+                PendingAdmission::Circuit {
+                    peer_id,
+                    connection,
+                    inbound_circuit_req,
+                },
+                Some(reason),
+            ) => {
+                // Deny circuit exceeding limits.
+                ToSwarm::NotifyHandler {
+                    handler: NotifyHandler::One(connection),
+                    peer_id,
+                    event: Either::Left(handler::In::DenyCircuitReq {
+                        circuit_id: None,
+                        inbound_circuit_req,
+                        status: reason.status(),
+                        reason,
+                    }),
+                }
+            }
+            (
+                PendingAdmission::Circuit {
+                    peer_id,
+                    connection,
+                    inbound_circuit_req,
+                },
+                None,
+            ) => {
+                let dst_conn = match self.reservations.get(&inbound_circuit_req.dst()) {
+                    // Deny circuit request if no reservation present.
+                    None => Err(DenialReason::NoReservation),
+                    Some(cs) => cs
+                        .iter()
+                        .find(|c| self.is_allowed_destination(**c))
+                        .copied()
+                        .ok_or(DenialReason::DestinationDenied),
+                };
+
+                match dst_conn {
+                    Ok(dst_conn) => {
+                        // Accept circuit request if reservation present.
+                        let circuit_id = self.circuits.insert(Circuit {
+                            status: CircuitStatus::Accepting,
+                            src_peer_id: peer_id,
+                            src_connection_id: connection,
+                            dst_peer_id: inbound_circuit_req.dst(),
+                            dst_connection_id: dst_conn,
+                            bytes_relayed: Default::default(),
+                        });
+
+                        ToSwarm::NotifyHandler {
+                            handler: NotifyHandler::One(dst_conn),
+                            peer_id,
+                            event: Either::Left(handler::In::NegotiateOutboundConnect {
+                                circuit_id,
+                                inbound_circuit_req,
+                                src_peer_id: peer_id,
+                                src_connection_id: connection,
+                            }),
+                        }
+                    }
+                    Err(reason) => {
+                        if reason == DenialReason::DestinationDenied {
+                            tracing::debug!(
+                                src=%peer_id,
+                                dst=%inbound_circuit_req.dst(),
+                                "Denying circuit, destination is connected via a filtered address"
+                            );
+                        }
+
+                        ToSwarm::NotifyHandler {
+                            handler: NotifyHandler::One(connection),
+                            peer_id,
+                            event: Either::Left(handler::In::DenyCircuitReq {
+                                circuit_id: None,
+                                inbound_circuit_req,
+                                status: reason.status(),
+                                reason,
+                            }),
+                        }
+                    }
+                }
+            }
+        };
+
+        self.queued_actions.push_back(action);
+    }
+}
+
+/// A reservation or circuit request waiting for the [`Config::admission_policy`].
+enum PendingAdmission {
+    Reservation {
+        peer_id: PeerId,
+        connection: ConnectionId,
+        inbound_reservation_req: inbound_hop::ReservationReq,
+        renewed: bool,
+    },
+    Circuit {
+        peer_id: PeerId,
+        connection: ConnectionId,
+        inbound_circuit_req: inbound_hop::CircuitReq,
+    },
+}
+
+impl PendingAdmission {
+    fn peer_id(&self) -> PeerId {
+        match self {
+            PendingAdmission::Reservation { peer_id, .. }
+            | PendingAdmission::Circuit { peer_id, .. } => *peer_id,
+        }
+    }
+
+    fn connection(&self) -> ConnectionId {
+        match self {
+            PendingAdmission::Reservation { connection, .. }
+            | PendingAdmission::Circuit { connection, .. } => *connection,
+        }
+    }
 }
 
 impl NetworkBehaviour for Behaviour {
@@ -299,7 +693,7 @@ impl NetworkBehaviour for Behaviour {
 
     fn handle_established_inbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
@@ -309,6 +703,9 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
+        self.connection_addrs
+            .insert(connection_id, remote_addr.clone());
+
         Ok(Either::Left(Handler::new(
             handler::Config {
                 reservation_duration: self.config.reservation_duration,
@@ -324,7 +721,7 @@ impl NetworkBehaviour for Behaviour {
 
     fn handle_established_outbound_connection(
         &mut self,
-        _: ConnectionId,
+        connection_id: ConnectionId,
         _: PeerId,
         addr: &Multiaddr,
         role_override: Endpoint,
@@ -334,6 +731,8 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
+        self.connection_addrs.insert(connection_id, addr.clone());
+
         Ok(Either::Left(Handler::new(
             handler::Config {
                 reservation_duration: self.config.reservation_duration,
@@ -380,65 +779,35 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if
-                // Deny if it is a new reservation and exceeds `max_reservations_per_peer`.
-                (!renewed
-                    && self
-                        .reservations
-                        .get(&event_source)
-                        .map(|cs| cs.len())
-                        .unwrap_or(0)
-                        > self.config.max_reservations_per_peer)
-                    // Deny if it exceeds `max_reservations`.
-                    || self
-                        .reservations
-                        .values()
-                        .map(|cs| cs.len())
-                        .sum::<usize>()
-                        >= self.config.max_reservations
-                    // Deny if it exceeds the allowed rate of reservations.
-                    || !self
-                        .config
-                        .reservation_rate_limiters
-                        .iter_mut()
-                        .all(|limiter| {
-                            limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
-                    ToSwarm::NotifyHandler {
-                        handler: NotifyHandler::One(connection),
-                        peer_id: event_source,
-                        event: Either::Left(handler::In::DenyReservationReq {
-                            inbound_reservation_req,
-                            status: proto::Status::RESOURCE_LIMIT_EXCEEDED,
-                        }),
-                    }
-                } else {
-                    // Accept reservation.
-                    self.reservations
-                        .entry(event_source)
-                        .or_default()
-                        .insert(connection);
-
-                    ToSwarm::NotifyHandler {
-                        handler: NotifyHandler::One(connection),
-                        peer_id: event_source,
-                        event: Either::Left(handler::In::AcceptReservationReq {
-                            inbound_reservation_req,
-                            addrs: self
-                                .external_addresses
-                                .iter()
-                                .cloned()
-                                // Add local peer ID in case it isn't present yet.
-                                .filter_map(|a| match a.iter().last()? {
-                                    Protocol::P2p(_) => Some(a),
-                                    _ => Some(a.with(Protocol::P2p(self.local_peer_id))),
-                                })
-                                .collect(),
-                        }),
-                    }
+                let denial = self
+                    .reservation_limit_denial(event_source, renewed)
+                    .or_else(|| {
+                        // Deny if it exceeds the allowed rate of reservations.
+                        (!self
+                            .config
+                            .reservation_rate_limiters
+                            .iter_mut()
+                            .all(|limiter| {
+                                limiter.try_next(event_source, endpoint.get_remote_address(), now)
+                            }))
+                        .then_some(DenialReason::RateLimited)
+                    });
+
+                let pending = PendingAdmission::Reservation {
+                    peer_id: event_source,
+                    connection,
+                    inbound_reservation_req,
+                    renewed,
                 };
 
-                self.queued_actions.push_back(action);
+                match (denial, self.config.admission_policy.as_mut()) {
+                    (None, Some(policy)) => {
+                        let admission =
+                            policy.admit_reservation(event_source, endpoint.get_remote_address());
+                        self.await_admission(admission, pending);
+                    }
+                    (denial, _) => self.answer(pending, denial),
+                }
             }
             handler::Event::ReservationReqAccepted { renewed } => {
                 // Ensure local eventual consistent reservation state matches handler (source of
@@ -464,10 +833,11 @@ impl NetworkBehaviour for Behaviour {
                     },
                 ));
             }
-            handler::Event::ReservationReqDenied {} => {
+            handler::Event::ReservationReqDenied { reason } => {
                 self.queued_actions.push_back(ToSwarm::GenerateEvent(
                     Event::ReservationReqDenied {
                         src_peer_id: event_source,
+                        reason,
                     },
                 ));
             }
@@ -514,67 +884,40 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if self.circuits.num_circuits_of_peer(event_source)
-                    > self.config.max_circuits_per_peer
-                    || self.circuits.len() >= self.config.max_circuits
-                    || !self
+                let denial = self.circuit_limit_denial(event_source).or_else(|| {
+                    (!self
                         .config
                         .circuit_src_rate_limiters
                         .iter_mut()
                         .all(|limiter| {
                             limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
-                    // Deny circuit exceeding limits.
-                    ToSwarm::NotifyHandler {
-                        handler: NotifyHandler::One(connection),
-                        peer_id: event_source,
-                        event: Either::Left(handler::In::DenyCircuitReq {
-                            circuit_id: None,
-                            inbound_circuit_req,
-                            status: proto::Status::RESOURCE_LIMIT_EXCEEDED,
-                        }),
-                    }
-                } else if let Some(dst_conn) = self
-                    .reservations
-                    .get(&inbound_circuit_req.dst())
-                    .and_then(|cs| cs.iter().next())
-                {
-                    // Accept circuit request if reservation present.
-                    let circuit_id = self.circuits.insert(Circuit {
-                        status: CircuitStatus::Accepting,
-                        src_peer_id: event_source,
-                        src_connection_id: connection,
-                        dst_peer_id: inbound_circuit_req.dst(),
-                        dst_connection_id: *dst_conn,
-                    });
+                        }))
+                    .then_some(DenialReason::RateLimited)
+                });
 
-                    ToSwarm::NotifyHandler {
-                        handler: NotifyHandler::One(*dst_conn),
-                        peer_id: event_source,
-                        event: Either::Left(handler::In::NegotiateOutboundConnect {
-                            circuit_id,
-                            inbound_circuit_req,
-                            src_peer_id: event_source,
-                            src_connection_id: connection,
-                        }),
-                    }
-                } else {
-                    // Deny circuit request if no reservation present.
-                    ToSwarm::NotifyHandler {
-                        handler: NotifyHandler::One(connection),
-                        peer_id: event_source,
-                        event: Either::Left(handler::In::DenyCircuitReq {
-                            circuit_id: None,
-                            inbound_circuit_req,
-                            status: proto::Status::NO_RESERVATION,
-                        }),
-                    }
+                let dst_peer_id = inbound_circuit_req.dst();
+                let pending = PendingAdmission::Circuit {
+                    peer_id: event_source,
+                    connection,
+                    inbound_circuit_req,
                 };
-                self.queued_actions.push_back(action);
+
+                match (denial, self.config.admission_policy.as_mut()) {
+                    (None, Some(policy)) => {
+                        let admission = policy.admit_circuit(
+                            event_source,
+                            endpoint.get_remote_address(),
+                            dst_peer_id,
+                        );
+                        self.await_admission(admission, pending);
+                    }
+                    (denial, _) => self.answer(pending, denial),
+                }
             }
             handler::Event::CircuitReqDenied {
                 circuit_id,
                 dst_peer_id,
+                reason,
             } => {
                 if let Some(circuit_id) = circuit_id {
                     self.circuits.remove(circuit_id);
@@ -584,6 +927,7 @@ impl NetworkBehaviour for Behaviour {
                     .push_back(ToSwarm::GenerateEvent(Event::CircuitReqDenied {
                         src_peer_id: event_source,
                         dst_peer_id,
+                        reason,
                     }));
             }
             handler::Event::CircuitReqDenyFailed {
@@ -612,6 +956,12 @@ impl NetworkBehaviour for Behaviour {
                 dst_stream,
                 dst_pending_data,
             } => {
+                let bytes_relayed = self
+                    .circuits
+                    .get(circuit_id)
+                    .map(|c| c.bytes_relayed.clone())
+                    .unwrap_or_default();
+
                 self.queued_actions.push_back(ToSwarm::NotifyHandler {
                     handler: NotifyHandler::One(src_connection_id),
                     peer_id: src_peer_id,
@@ -621,6 +971,7 @@ impl NetworkBehaviour for Behaviour {
                         inbound_circuit_req,
                         dst_stream,
                         dst_pending_data,
+                        bytes_relayed,
                     }),
                 });
             }
@@ -639,6 +990,7 @@ impl NetworkBehaviour for Behaviour {
                         circuit_id: Some(circuit_id),
                         inbound_circuit_req,
                         status,
+                        reason: DenialReason::ConnectionFailed,
                     }),
                 });
                 #[allow(deprecated)]
@@ -681,12 +1033,16 @@ impl NetworkBehaviour for Behaviour {
                 circuit_id,
                 error,
             } => {
-                self.circuits.remove(circuit_id);
+                // The circuit may have been closed already because one of its connections closed.
+                let Some(circuit) = self.circuits.remove(circuit_id) else {
+                    return;
+                };
 
                 self.queued_actions
                     .push_back(ToSwarm::GenerateEvent(Event::CircuitClosed {
                         src_peer_id: event_source,
                         dst_peer_id,
+                        bytes_relayed: circuit.bytes_relayed.load(Ordering::Relaxed),
                         error,
                     }));
             }
@@ -694,12 +1050,37 @@ impl NetworkBehaviour for Behaviour {
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
-        if let Some(to_swarm) = self.queued_actions.pop_front() {
-            return Poll::Ready(to_swarm);
-        }
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(to_swarm) = self.queued_actions.pop_front() {
+                return Poll::Ready(to_swarm);
+            }
+
+            let Poll::Ready((id, result)) = self.admissions.poll_unpin(cx) else {
+                return Poll::Pending;
+            };
+            let Some(pending) = self.pending_admissions.remove(&id) else {
+                continue;
+            };
 
-        Poll::Pending
+            let denial = match result {
+                Ok(true) => None,
+                Ok(false) => Some(DenialReason::AdmissionDenied),
+                Err(_) if self.config.admit_on_timeout => None,
+                Err(_) => Some(DenialReason::AdmissionTimedOut),
+            };
+            // Limits may have been reached while waiting for the admission policy.
+            let denial = denial.or_else(|| match &pending {
+                PendingAdmission::Reservation {
+                    peer_id, renewed, ..
+                } => self.reservation_limit_denial(*peer_id, *renewed),
+                PendingAdmission::Circuit { peer_id, .. } => self.circuit_limit_denial(*peer_id),
+            });
+            self.answer(pending, denial);
+        }
     }
 }
 
@@ -729,6 +1110,10 @@ impl CircuitsTracker {
         };
     }
 
+    fn get(&self, circuit_id: CircuitId) -> Option<&Circuit> {
+        self.circuits.get(&circuit_id)
+    }
+
     fn remove(&mut self, circuit_id: CircuitId) -> Option<Circuit> {
         self.circuits.remove(&circuit_id)
     }
@@ -774,6 +1159,8 @@ struct Circuit {
     dst_peer_id: PeerId,
     dst_connection_id: ConnectionId,
     status: CircuitStatus,
+    /// Bytes relayed so far, updated by the [`Handler`] driving the circuit.
+    bytes_relayed: Arc<AtomicU64>,
 }
 
 #[derive(Clone)]
@@ -792,3 +1179,53 @@ impl Add<u64> for CircuitId {
         CircuitId(self.0 + rhs)
     }
 }
+
+/// The default [`Config::destination_filter`], denying destinations connected via a private,
+/// loopback, link-local or otherwise reserved IP address, see [`global_only::is_global`].
+fn is_public_destination(addr: &Multiaddr) -> bool {
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => global_only::is_global(ip.into()),
+        Some(Protocol::Ip6(ip)) => global_only::is_global(ip.into()),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_destination_filter() {
+        for denied in [
+            "/ip4/10.0.0.1/tcp/4001",
+            "/ip4/172.16.5.4/tcp/4001",
+            "/ip4/192.168.1.1/udp/4001/quic-v1",
+            "/ip4/127.0.0.1/tcp/4001",
+            "/ip4/169.254.0.1/tcp/4001",
+            "/ip4/100.64.0.1/tcp/4001",
+            "/ip4/0.0.0.0/tcp/4001",
+            "/ip6/::1/tcp/4001",
+            "/ip6/fd00::1/tcp/4001",
+            "/ip6/fe80::1/tcp/4001",
+            "/ip6/::ffff:192.168.1.1/tcp/4001",
+            "/ip4/240.0.0.1/tcp/4001",
+            "/ip4/198.18.0.1/tcp/4001",
+            "/ip4/192.0.0.1/tcp/4001",
+            "/ip6/ff02::1/udp/4001/quic-v1",
+        ] {
+            let addr = denied.parse().unwrap();
+            assert!(!is_public_destination(&addr), "{denied} should be denied");
+        }
+
+        for allowed in [
+            "/ip4/147.75.69.143/tcp/4001",
+            "/ip4/100.128.0.1/tcp/4001",
+            "/ip6/2604:1380:2000:7a00::1/tcp/4001",
+            "/dns4/example.com/tcp/4001",
+            "/memory/1234",
+        ] {
+            let addr = allowed.parse().unwrap();
+            assert!(is_public_destination(&addr), "{allowed} should be allowed");
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use futures::future::{self, BoxFuture, FutureExt};
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+
+/// Asynchronous policy deciding whether to admit an inbound reservation or circuit request,
+/// e.g. by looking up the remote peer in a database or validating a token with a remote
+/// service.
+///
+/// The policy is only consulted for requests that passed the static limits and rate limiters
+/// of the [`Config`](crate::Config). A request is accepted if the returned future resolves to
+/// `true`. Futures not resolving within
+/// [`Config::admission_timeout`](crate::Config::admission_timeout) are answered according to
+/// [`Config::admit_on_timeout`](crate::Config::admit_on_timeout).
+///
+/// Both methods admit all requests by default.
+pub trait AdmissionPolicy: Send {
+    /// Decides whether to admit a reservation request of `peer`, connected via `addr`.
+    fn admit_reservation(&mut self, peer: PeerId, addr: &Multiaddr) -> BoxFuture<'static, bool> {
+        let _ = (peer, addr);
+        future::ready(true).boxed()
+    }
+
+    /// Decides whether to admit a circuit request from `src_peer`, connected via `src_addr`,
+    /// to `dst_peer`.
+    fn admit_circuit(
+        &mut self,
+        src_peer: PeerId,
+        src_addr: &Multiaddr,
+        dst_peer: PeerId,
+    ) -> BoxFuture<'static, bool> {
+        let _ = (src_peer, src_addr, dst_peer);
+        future::ready(true).boxed()
+    }
+}
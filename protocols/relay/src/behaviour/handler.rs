@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::behaviour::CircuitId;
+use crate::behaviour::{CircuitId, DenialReason};
 use crate::copy_future::CopyFuture;
 use crate::protocol::{inbound_hop, outbound_stop};
 use crate::{proto, HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
@@ -39,6 +39,8 @@ use libp2p_swarm::{
     StreamUpgradeError, SubstreamProtocol,
 };
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{fmt, io};
@@ -62,11 +64,13 @@ pub enum In {
     DenyReservationReq {
         inbound_reservation_req: inbound_hop::ReservationReq,
         status: proto::Status,
+        reason: DenialReason,
     },
     DenyCircuitReq {
         circuit_id: Option<CircuitId>,
         inbound_circuit_req: inbound_hop::CircuitReq,
         status: proto::Status,
+        reason: DenialReason,
     },
     NegotiateOutboundConnect {
         circuit_id: CircuitId,
@@ -80,6 +84,7 @@ pub enum In {
         inbound_circuit_req: inbound_hop::CircuitReq,
         dst_stream: Stream,
         dst_pending_data: Bytes,
+        bytes_relayed: Arc<AtomicU64>,
     },
 }
 
@@ -96,18 +101,22 @@ impl fmt::Debug for In {
             In::DenyReservationReq {
                 inbound_reservation_req: _,
                 status,
+                reason,
             } => f
                 .debug_struct("In::DenyReservationReq")
                 .field("status", status)
+                .field("reason", reason)
                 .finish(),
             In::DenyCircuitReq {
                 circuit_id,
                 inbound_circuit_req: _,
                 status,
+                reason,
             } => f
                 .debug_struct("In::DenyCircuitReq")
                 .field("circuit_id", circuit_id)
                 .field("status", status)
+                .field("reason", reason)
                 .finish(),
             In::NegotiateOutboundConnect {
                 circuit_id,
@@ -126,6 +135,7 @@ impl fmt::Debug for In {
                 dst_peer_id,
                 dst_stream: _,
                 dst_pending_data: _,
+                bytes_relayed: _,
             } => f
                 .debug_struct("In::AcceptAndDriveCircuit")
                 .field("circuit_id", circuit_id)
@@ -153,7 +163,7 @@ pub enum Event {
     /// Accepting an inbound reservation request failed.
     ReservationReqAcceptFailed { error: inbound_hop::Error },
     /// An inbound reservation request has been denied.
-    ReservationReqDenied {},
+    ReservationReqDenied { reason: DenialReason },
     /// Denying an inbound reservation request has failed.
     ReservationReqDenyFailed { error: inbound_hop::Error },
     /// An inbound reservation has timed out.
@@ -167,6 +177,7 @@ pub enum Event {
     CircuitReqDenied {
         circuit_id: Option<CircuitId>,
         dst_peer_id: PeerId,
+        reason: DenialReason,
     },
     /// Denying an inbound circuit request failed.
     CircuitReqDenyFailed {
@@ -232,9 +243,10 @@ impl fmt::Debug for Event {
                 .debug_struct("Event::ReservationReqAcceptFailed")
                 .field("error", error)
                 .finish(),
-            Event::ReservationReqDenied {} => {
-                f.debug_struct("Event::ReservationReqDenied").finish()
-            }
+            Event::ReservationReqDenied { reason } => f
+                .debug_struct("Event::ReservationReqDenied")
+                .field("reason", reason)
+                .finish(),
             Event::ReservationReqDenyFailed { error } => f
                 .debug_struct("Event::ReservationReqDenyFailed")
                 .field("error", error)
@@ -250,10 +262,12 @@ impl fmt::Debug for Event {
             Event::CircuitReqDenied {
                 circuit_id,
                 dst_peer_id,
+                reason,
             } => f
                 .debug_struct("Event::CircuitReqDenied")
                 .field("circuit_id", circuit_id)
                 .field("dst_peer_id", dst_peer_id)
+                .field("reason", reason)
                 .finish(),
             Event::CircuitReqDenyFailed {
                 circuit_id,
@@ -353,7 +367,12 @@ pub struct Handler {
     /// Futures accepting an inbound circuit request.
     circuit_accept_futures: Futures<Result<CircuitParts, (CircuitId, PeerId, inbound_hop::Error)>>,
     /// Futures denying an inbound circuit request.
-    circuit_deny_futures: Futures<(Option<CircuitId>, PeerId, Result<(), inbound_hop::Error>)>,
+    circuit_deny_futures: Futures<(
+        Option<CircuitId>,
+        PeerId,
+        DenialReason,
+        Result<(), inbound_hop::Error>,
+    )>,
     /// Futures relaying data for circuit between two peers.
     circuits: Futures<(CircuitId, PeerId, Result<(), std::io::Error>)>,
 
@@ -473,7 +492,10 @@ impl Handler {
 
 enum ReservationRequestFuture {
     Accepting(BoxFuture<'static, Result<(), inbound_hop::Error>>),
-    Denying(BoxFuture<'static, Result<(), inbound_hop::Error>>),
+    Denying(
+        BoxFuture<'static, Result<(), inbound_hop::Error>>,
+        DenialReason,
+    ),
 }
 
 type Futures<T> = FuturesUnordered<BoxFuture<'static, T>>;
@@ -509,11 +531,13 @@ impl ConnectionHandler for Handler {
             In::DenyReservationReq {
                 inbound_reservation_req,
                 status,
+                reason,
             } => {
                 if self
                     .reservation_request_future
                     .replace(ReservationRequestFuture::Denying(
                         inbound_reservation_req.deny(status).err_into().boxed(),
+                        reason,
                     ))
                     .is_some()
                 {
@@ -542,13 +566,14 @@ impl ConnectionHandler for Handler {
                 circuit_id,
                 inbound_circuit_req,
                 status,
+                reason,
             } => {
                 let dst_peer_id = inbound_circuit_req.dst();
                 self.circuit_deny_futures.push(
                     inbound_circuit_req
                         .deny(status)
                         .err_into()
-                        .map(move |result| (circuit_id, dst_peer_id, result))
+                        .map(move |result| (circuit_id, dst_peer_id, reason, result))
                         .boxed(),
                 );
             }
@@ -558,6 +583,7 @@ impl ConnectionHandler for Handler {
                 inbound_circuit_req,
                 dst_stream,
                 dst_pending_data,
+                bytes_relayed,
             } => {
                 self.circuit_accept_futures.push(
                     inbound_circuit_req
@@ -570,6 +596,7 @@ impl ConnectionHandler for Handler {
                             dst_peer_id,
                             dst_stream,
                             dst_pending_data,
+                            bytes_relayed,
                         })
                         .map_err(move |e| (circuit_id, dst_peer_id, e))
                         .boxed(),
@@ -715,7 +742,7 @@ impl ConnectionHandler for Handler {
         }
 
         // Deny new circuits.
-        if let Poll::Ready(Some((circuit_id, dst_peer_id, result))) =
+        if let Poll::Ready(Some((circuit_id, dst_peer_id, reason, result))) =
             self.circuit_deny_futures.poll_next_unpin(cx)
         {
             match result {
@@ -724,6 +751,7 @@ impl ConnectionHandler for Handler {
                         Event::CircuitReqDenied {
                             circuit_id,
                             dst_peer_id,
+                            reason,
                         },
                     ));
                 }
@@ -750,6 +778,7 @@ impl ConnectionHandler for Handler {
                         dst_peer_id,
                         mut dst_stream,
                         dst_pending_data,
+                        bytes_relayed,
                     } = parts;
                     let max_circuit_duration = self.config.max_circuit_duration;
                     let max_circuit_bytes = self.config.max_circuit_bytes;
@@ -768,6 +797,7 @@ impl ConnectionHandler for Handler {
                             dst_stream,
                             max_circuit_duration,
                             max_circuit_bytes,
+                            bytes_relayed,
                         )
                         .await?;
 
@@ -833,14 +863,15 @@ impl ConnectionHandler for Handler {
                     }
                 }
             }
-            Some(ReservationRequestFuture::Denying(fut)) => {
+            Some(ReservationRequestFuture::Denying(fut, reason)) => {
+                let reason = *reason;
                 if let Poll::Ready(result) = fut.poll_unpin(cx) {
                     self.reservation_request_future = None;
 
                     match result {
                         Ok(()) => {
                             return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
-                                Event::ReservationReqDenied {},
+                                Event::ReservationReqDenied { reason },
                             ))
                         }
                         Err(error) => {
@@ -903,6 +934,7 @@ struct CircuitParts {
     dst_peer_id: PeerId,
     dst_stream: Stream,
     dst_pending_data: Bytes,
+    bytes_relayed: Arc<AtomicU64>,
 }
 
 /// Holds everything we know about a to-be-issued `CONNECT` request to a peer.
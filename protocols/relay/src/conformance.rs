@@ -0,0 +1,781 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Conformance checks of the [circuit relay v2] protocol, run against a relay [`Behaviour`].
+//!
+//! The checks talk the raw hop and stop protocols to a relay, using the messages of
+//! [`vectors`], and report deviations from the specification as [`Violation`]s. Every check runs
+//! against a new relay, created by the function given to [`Suite::new`], to which clients connect
+//! over an in-memory transport.
+//!
+//! Checks wait for the configured [`Expectations`] to elapse, e.g. for a reservation to expire,
+//! and open as many circuits and reservations as the limits allow. Run the suite against a relay
+//! configured with short durations and small limits.
+//!
+//! ```no_run
+//! # use libp2p_relay as relay;
+//! # use std::time::Duration;
+//! # async fn run() {
+//! let config = || relay::Config {
+//!     reservation_duration: Duration::from_secs(1),
+//!     max_reservations: 2,
+//!     max_circuits: 2,
+//!     ..Default::default()
+//! };
+//! let suite = relay::conformance::Suite::new(
+//!     relay::conformance::Expectations::from(&config()),
+//!     move |peer_id| relay::Behaviour::new(peer_id, config()),
+//! );
+//!
+//! suite.run().await.expect("relay to conform to the specification");
+//! # }
+//! ```
+//!
+//! [circuit relay v2]: https://github.com/libp2p/specs/blob/master/relay/circuit-v2.md
+
+use std::{fmt, time::Duration};
+
+use asynchronous_codec::{Framed, FramedParts};
+use futures::{future, prelude::*, stream::BoxStream};
+use futures_timer::Delay;
+use libp2p_core::{multiaddr::Protocol, Multiaddr, SignedEnvelope};
+use libp2p_identity::PeerId;
+use libp2p_stream::Control;
+use libp2p_swarm::{Stream, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use quick_protobuf::{BytesReader, MessageRead};
+use web_time::SystemTime;
+
+use crate::protocol::MAX_MESSAGE_SIZE;
+use crate::{proto, Behaviour, Config, HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
+
+/// The signature domain of reservation vouchers.
+const VOUCHER_DOMAIN: &str = "libp2p-relay-rsvp";
+/// The payload type of reservation vouchers.
+const VOUCHER_PAYLOAD_TYPE: &[u8] = &[0x03, 0x02];
+
+/// Time a check may take on top of the durations it waits for.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The limits and durations the relay under test is configured with.
+#[derive(Debug, Clone)]
+pub struct Expectations {
+    /// See [`Config::reservation_duration`].
+    pub reservation_duration: Duration,
+    /// See [`Config::max_reservations`].
+    pub max_reservations: usize,
+    /// See [`Config::max_circuits`].
+    pub max_circuits: usize,
+    /// See [`Config::max_circuit_duration`].
+    pub max_circuit_duration: Duration,
+    /// See [`Config::max_circuit_bytes`]. `0` disables the limit.
+    pub max_circuit_bytes: u64,
+}
+
+impl From<&Config> for Expectations {
+    fn from(config: &Config) -> Self {
+        Self {
+            reservation_duration: config.reservation_duration,
+            max_reservations: config.max_reservations,
+            max_circuits: config.max_circuits,
+            max_circuit_duration: config.max_circuit_duration,
+            max_circuit_bytes: config.max_circuit_bytes,
+        }
+    }
+}
+
+/// A conformance check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// A reservation is accepted with its expiry, the relay addresses and the circuit limits.
+    Reservation,
+    /// A reservation voucher, if any, is signed by the relay and matches the reservation.
+    Voucher,
+    /// Circuits to a peer are denied with `NO_RESERVATION` once its reservation expired.
+    ReservationExpiry,
+    /// Circuits to a peer without a reservation are denied with `NO_RESERVATION`.
+    NoReservation,
+    /// Reservations beyond [`Expectations::max_reservations`] are denied.
+    ReservationLimit,
+    /// Circuits beyond [`Expectations::max_circuits`] are denied with `RESOURCE_LIMIT_EXCEEDED`.
+    CircuitLimit,
+    /// The destination is told the circuit limits and circuits are closed once they relayed
+    /// more than [`Expectations::max_circuit_bytes`].
+    CircuitDataLimit,
+    /// Malformed and unexpected messages are answered with `MALFORMED_MESSAGE`, respectively
+    /// `UNEXPECTED_MESSAGE`, see [`vectors::malformed`].
+    MalformedMessage,
+}
+
+impl Check {
+    /// All checks, in the order [`Suite::run`] runs them.
+    pub const ALL: [Check; 8] = [
+        Check::Reservation,
+        Check::Voucher,
+        Check::ReservationExpiry,
+        Check::NoReservation,
+        Check::ReservationLimit,
+        Check::CircuitLimit,
+        Check::CircuitDataLimit,
+        Check::MalformedMessage,
+    ];
+}
+
+/// A deviation of the relay under test from the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The check that failed.
+    pub check: Check,
+    /// What the relay did wrong.
+    pub reason: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.check, self.reason)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// The status of a hop or stop message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    Ok,
+    ReservationRefused,
+    ResourceLimitExceeded,
+    PermissionDenied,
+    ConnectionFailed,
+    NoReservation,
+    MalformedMessage,
+    UnexpectedMessage,
+}
+
+impl From<proto::Status> for Status {
+    fn from(status: proto::Status) -> Self {
+        match status {
+            proto::Status::OK => Status::Ok,
+            proto::Status::RESERVATION_REFUSED => Status::ReservationRefused,
+            proto::Status::RESOURCE_LIMIT_EXCEEDED => Status::ResourceLimitExceeded,
+            proto::Status::PERMISSION_DENIED => Status::PermissionDenied,
+            proto::Status::CONNECTION_FAILED => Status::ConnectionFailed,
+            proto::Status::NO_RESERVATION => Status::NoReservation,
+            proto::Status::MALFORMED_MESSAGE => Status::MalformedMessage,
+            proto::Status::UNEXPECTED_MESSAGE => Status::UnexpectedMessage,
+        }
+    }
+}
+
+/// Encoded hop protocol messages, i.e. length-prefixed protobufs, to send to a relay.
+pub mod vectors {
+    use super::Status;
+    use crate::proto;
+    use crate::protocol::MAX_MESSAGE_SIZE;
+    use asynchronous_codec::{BytesMut, Encoder};
+    use libp2p_identity::PeerId;
+
+    /// A message together with the status a relay has to answer it with.
+    #[derive(Debug, Clone)]
+    pub struct Vector {
+        pub name: &'static str,
+        pub bytes: Vec<u8>,
+        pub expected: Status,
+    }
+
+    /// A `RESERVE` request.
+    pub fn reserve() -> Vec<u8> {
+        encode(hop(proto::HopMessageType::RESERVE, None))
+    }
+
+    /// A `CONNECT` request to the given destination.
+    pub fn connect(dst: PeerId) -> Vec<u8> {
+        encode(hop(
+            proto::HopMessageType::CONNECT,
+            Some(proto::Peer {
+                id: dst.to_bytes(),
+                addrs: vec![],
+            }),
+        ))
+    }
+
+    /// Messages a relay has to reject.
+    pub fn malformed() -> Vec<Vector> {
+        vec![
+            Vector {
+                name: "connect without peer",
+                bytes: encode(hop(proto::HopMessageType::CONNECT, None)),
+                expected: Status::MalformedMessage,
+            },
+            Vector {
+                name: "connect to invalid peer ID",
+                bytes: encode(hop(
+                    proto::HopMessageType::CONNECT,
+                    Some(proto::Peer {
+                        id: vec![0xde, 0xad, 0xbe, 0xef],
+                        addrs: vec![],
+                    }),
+                )),
+                expected: Status::MalformedMessage,
+            },
+            Vector {
+                name: "undecodable message",
+                // Length prefix of 4, followed by a field with an invalid wire type.
+                bytes: vec![4, 0x0f, 0xff, 0xff, 0xff],
+                expected: Status::MalformedMessage,
+            },
+            Vector {
+                name: "status on hop stream",
+                bytes: encode(proto::HopMessage {
+                    status: Some(proto::Status::OK),
+                    ..hop(proto::HopMessageType::STATUS, None)
+                }),
+                expected: Status::UnexpectedMessage,
+            },
+        ]
+    }
+
+    fn hop(type_pb: proto::HopMessageType, peer: Option<proto::Peer>) -> proto::HopMessage {
+        proto::HopMessage {
+            type_pb,
+            peer,
+            reservation: None,
+            limit: None,
+            status: None,
+        }
+    }
+
+    fn encode(msg: proto::HopMessage) -> Vec<u8> {
+        let mut bytes = BytesMut::new();
+        quick_protobuf_codec::Codec::<proto::HopMessage>::new(MAX_MESSAGE_SIZE)
+            .encode(msg, &mut bytes)
+            .expect("message to be encodable");
+        bytes.to_vec()
+    }
+}
+
+/// A suite of conformance [`Check`]s against relays created by a given function.
+pub struct Suite {
+    expectations: Expectations,
+    new_relay: Box<dyn Fn(PeerId) -> Behaviour + Send + Sync>,
+}
+
+impl Suite {
+    /// Creates a suite checking the relays created by `new_relay` against the given
+    /// [`Expectations`].
+    pub fn new(
+        expectations: Expectations,
+        new_relay: impl Fn(PeerId) -> Behaviour + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            expectations,
+            new_relay: Box::new(new_relay),
+        }
+    }
+
+    /// Runs all [`Check`]s, returning the violations of all failed checks.
+    pub async fn run(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        for check in Check::ALL {
+            if let Err(violation) = self.run_check(check).await {
+                violations.push(violation);
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Runs a single [`Check`] against a new relay.
+    pub async fn run_check(&self, check: Check) -> Result<(), Violation> {
+        let num_clients = match check {
+            Check::Reservation | Check::Voucher | Check::MalformedMessage => 1,
+            Check::ReservationExpiry | Check::NoReservation | Check::CircuitDataLimit => 2,
+            Check::ReservationLimit => self.expectations.max_reservations + 1,
+            Check::CircuitLimit => self.expectations.max_circuits + 2,
+        };
+        let (env, driver) = self.env(num_clients).await;
+        let expectations = &self.expectations;
+
+        let run = async move {
+            match check {
+                Check::Reservation => check_reservation(env, expectations).await,
+                Check::Voucher => check_voucher(env).await,
+                Check::ReservationExpiry => check_reservation_expiry(env, expectations).await,
+                Check::NoReservation => check_no_reservation(env).await,
+                Check::ReservationLimit => check_reservation_limit(env, expectations).await,
+                Check::CircuitLimit => check_circuit_limit(env, expectations).await,
+                Check::CircuitDataLimit => check_circuit_data_limit(env, expectations).await,
+                Check::MalformedMessage => check_malformed_message(env).await,
+            }
+        };
+        let timeout = Delay::new(CHECK_TIMEOUT + expectations.reservation_duration);
+
+        match future::select(future::select(run.boxed(), driver), timeout).await {
+            future::Either::Left((future::Either::Left((result, _)), _)) => {
+                result.map_err(|reason| Violation { check, reason })
+            }
+            future::Either::Left((future::Either::Right(((), _)), _)) => {
+                unreachable!("Swarms are driven forever")
+            }
+            future::Either::Right(((), _)) => Err(Violation {
+                check,
+                reason: "check timed out".to_owned(),
+            }),
+        }
+    }
+
+    /// Creates a new relay with the given number of clients connected to it.
+    ///
+    /// Returns a future driving all swarms, which never completes.
+    async fn env(&self, num_clients: usize) -> (Env, future::BoxFuture<'static, ()>) {
+        let mut relay = Swarm::new_ephemeral(|key| (self.new_relay)(key.public().to_peer_id()));
+        relay.listen().with_memory_addr_external().await;
+
+        let mut clients = Vec::with_capacity(num_clients);
+        let mut drivers = Vec::with_capacity(num_clients + 1);
+        for _ in 0..num_clients {
+            let mut client = Swarm::new_ephemeral(|_| libp2p_stream::Behaviour::new());
+            client.connect(&mut relay).await;
+            clients.push(Client {
+                peer_id: *client.local_peer_id(),
+                control: client.behaviour().new_control(),
+            });
+            drivers.push(client.loop_on_next().boxed());
+        }
+
+        let env = Env {
+            relay: *relay.local_peer_id(),
+            clients,
+        };
+        drivers.push(relay.loop_on_next().boxed());
+
+        (env, future::join_all(drivers).map(|_| ()).boxed())
+    }
+}
+
+impl fmt::Debug for Suite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Suite")
+            .field("expectations", &self.expectations)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A relay and the clients connected to it.
+struct Env {
+    relay: PeerId,
+    clients: Vec<Client>,
+}
+
+struct Client {
+    peer_id: PeerId,
+    control: Control,
+}
+
+type HopStream = Framed<Stream, quick_protobuf_codec::Codec<proto::HopMessage>>;
+type StopStream = Framed<Stream, quick_protobuf_codec::Codec<proto::StopMessage>>;
+
+impl Client {
+    /// Sends the given bytes on a new hop stream and returns the answer, if any.
+    async fn request(
+        &mut self,
+        relay: PeerId,
+        bytes: &[u8],
+    ) -> Result<(HopStream, Option<proto::HopMessage>), String> {
+        let mut stream = self
+            .control
+            .open_stream(relay, HOP_PROTOCOL_NAME)
+            .await
+            .map_err(|e| format!("failed to open hop stream: {e}"))?;
+        stream
+            .write_all(bytes)
+            .await
+            .map_err(|e| format!("failed to send request: {e}"))?;
+        stream
+            .flush()
+            .await
+            .map_err(|e| format!("failed to send request: {e}"))?;
+
+        let mut framed = Framed::new(stream, quick_protobuf_codec::Codec::new(MAX_MESSAGE_SIZE));
+        let answer = match framed.next().await {
+            Some(Ok(msg)) => Some(msg),
+            Some(Err(e)) => return Err(format!("failed to decode answer: {e}")),
+            None => None,
+        };
+
+        Ok((framed, answer))
+    }
+
+    /// Sends the given bytes on a new hop stream and returns the status of the answer.
+    async fn request_status(
+        &mut self,
+        relay: PeerId,
+        bytes: &[u8],
+    ) -> Result<(HopStream, proto::HopMessage, Status), String> {
+        let (stream, answer) = self.request(relay, bytes).await?;
+        let answer = answer.ok_or("relay closed the stream without answering")?;
+        if answer.type_pb != proto::HopMessageType::STATUS {
+            return Err(format!("expected STATUS, got {:?}", answer.type_pb));
+        }
+        let status = answer.status.ok_or("STATUS without status")?.into();
+
+        Ok((stream, answer, status))
+    }
+
+    /// Makes a reservation, returning the stream it was made on to keep the connection alive.
+    async fn reserve(
+        &mut self,
+        relay: PeerId,
+    ) -> Result<(HopStream, proto::Reservation, Option<proto::Limit>), String> {
+        let (stream, answer, status) = self.request_status(relay, &vectors::reserve()).await?;
+        if status != Status::Ok {
+            return Err(format!("reservation denied with {status:?}"));
+        }
+        let reservation = answer
+            .reservation
+            .ok_or("reservation accepted without reservation")?;
+
+        Ok((stream, reservation, answer.limit))
+    }
+
+    /// Accepts all circuits relayed to this client, returning their stop requests and streams.
+    ///
+    /// Circuits are only accepted while the returned stream is polled.
+    fn accept_circuits(&mut self) -> BoxStream<'static, (proto::StopMessage, Stream)> {
+        self.control
+            .accept(STOP_PROTOCOL_NAME)
+            .expect("to accept stop streams once")
+            .filter_map(|(_, stream)| accept_circuit(stream))
+            .boxed()
+    }
+}
+
+/// Answers a stop request with `OK`, returning it together with the relayed stream.
+async fn accept_circuit(stream: Stream) -> Option<(proto::StopMessage, Stream)> {
+    let mut framed: StopStream =
+        Framed::new(stream, quick_protobuf_codec::Codec::new(MAX_MESSAGE_SIZE));
+    let request = framed.next().await?.ok()?;
+    framed
+        .send(proto::StopMessage {
+            type_pb: proto::StopMessageType::STATUS,
+            peer: None,
+            limit: None,
+            status: Some(proto::Status::OK),
+        })
+        .await
+        .ok()?;
+
+    let FramedParts { io, .. } = framed.into_parts();
+
+    Some((request, io))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("now to be after the UNIX epoch")
+        .as_secs()
+}
+
+/// Checks the limits given with a reservation or a stop request.
+fn check_limit(limit: Option<&proto::Limit>, expectations: &Expectations) -> Result<(), String> {
+    let limit = limit.cloned().unwrap_or_default();
+
+    let expected_duration = expectations.max_circuit_duration.as_secs();
+    if limit.duration.map(u64::from) != Some(expected_duration) {
+        return Err(format!(
+            "expected a circuit duration limit of {expected_duration}s, got {:?}",
+            limit.duration
+        ));
+    }
+    let expected_data =
+        (expectations.max_circuit_bytes > 0).then_some(expectations.max_circuit_bytes);
+    if limit.data != expected_data {
+        return Err(format!(
+            "expected a circuit data limit of {expected_data:?}, got {:?}",
+            limit.data
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_reservation(mut env: Env, expectations: &Expectations) -> Result<(), String> {
+    let relay = env.relay;
+    let before = unix_now();
+    let (_stream, reservation, limit) = env.clients[0].reserve(relay).await?;
+    let after = unix_now();
+
+    let duration = expectations.reservation_duration.as_secs();
+    // Allow for the expiry to be rounded to the second in either direction.
+    if reservation.expire + 1 < before + duration || reservation.expire > after + duration + 1 {
+        return Err(format!(
+            "expected the reservation to expire in {duration}s, expires at {} at {before}",
+            reservation.expire
+        ));
+    }
+
+    if reservation.addrs.is_empty() {
+        return Err("reservation without relay addresses".to_owned());
+    }
+    for addr in &reservation.addrs {
+        let addr = Multiaddr::try_from(addr.clone())
+            .map_err(|e| format!("invalid reservation address: {e}"))?;
+        if !addr.iter().any(|p| p == Protocol::P2p(relay)) {
+            return Err(format!(
+                "reservation address {addr} does not contain the relay's peer ID"
+            ));
+        }
+    }
+
+    check_limit(limit.as_ref(), expectations)
+}
+
+async fn check_voucher(mut env: Env) -> Result<(), String> {
+    let relay = env.relay;
+    let client = env.clients[0].peer_id;
+    let (_stream, reservation, _) = env.clients[0].reserve(relay).await?;
+
+    // Vouchers are optional.
+    let Some(voucher) = reservation.voucher else {
+        return Ok(());
+    };
+
+    let envelope = SignedEnvelope::from_protobuf_encoding(&voucher)
+        .map_err(|e| format!("voucher is no signed envelope: {e}"))?;
+    let (payload, signing_key) = envelope
+        .payload_and_signing_key(VOUCHER_DOMAIN.to_owned(), VOUCHER_PAYLOAD_TYPE)
+        .map_err(|e| format!("invalid voucher envelope: {e}"))?;
+    if signing_key.to_peer_id() != relay {
+        return Err("voucher not signed by the relay".to_owned());
+    }
+
+    let voucher =
+        proto::ReservationVoucher::from_reader(&mut BytesReader::from_bytes(payload), payload)
+            .map_err(|e| format!("failed to decode voucher: {e}"))?;
+    if voucher.relay != relay.to_bytes() {
+        return Err("voucher issued by another relay".to_owned());
+    }
+    if voucher.peer != client.to_bytes() {
+        return Err("voucher issued for another peer".to_owned());
+    }
+    if voucher.expiration != reservation.expire {
+        return Err(format!(
+            "voucher expires at {}, reservation at {}",
+            voucher.expiration, reservation.expire
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_reservation_expiry(mut env: Env, expectations: &Expectations) -> Result<(), String> {
+    let relay = env.relay;
+    let dst = env.clients[0].peer_id;
+    let (_dst_stream, _, _) = env.clients[0].reserve(relay).await?;
+    // Reserve as well to keep the connection of the source alive while waiting.
+    let (_src_stream, _, _) = env.clients[1].reserve(relay).await?;
+
+    Delay::new(expectations.reservation_duration + Duration::from_secs(1)).await;
+
+    let (_, _, status) = env.clients[1]
+        .request_status(relay, &vectors::connect(dst))
+        .await?;
+    if status != Status::NoReservation {
+        return Err(format!(
+            "expected NO_RESERVATION for an expired reservation, got {status:?}"
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_no_reservation(mut env: Env) -> Result<(), String> {
+    let relay = env.relay;
+    let dst = env.clients[0].peer_id;
+
+    let (_, _, status) = env.clients[1]
+        .request_status(relay, &vectors::connect(dst))
+        .await?;
+    if status != Status::NoReservation {
+        return Err(format!(
+            "expected NO_RESERVATION for a peer without reservation, got {status:?}"
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_reservation_limit(mut env: Env, expectations: &Expectations) -> Result<(), String> {
+    let relay = env.relay;
+    let (last, clients) = env.clients.split_last_mut().expect("at least one client");
+
+    let mut reservations = Vec::with_capacity(clients.len());
+    for client in clients {
+        reservations.push(client.reserve(relay).await?);
+    }
+
+    let (_, _, status) = last.request_status(relay, &vectors::reserve()).await?;
+    match status {
+        Status::ReservationRefused | Status::ResourceLimitExceeded => Ok(()),
+        status => Err(format!(
+            "expected reservation beyond the limit of {} to be denied, got {status:?}",
+            expectations.max_reservations
+        )),
+    }
+}
+
+async fn check_circuit_limit(mut env: Env, expectations: &Expectations) -> Result<(), String> {
+    let relay = env.relay;
+    let (dst, srcs) = env.clients.split_first_mut().expect("at least one client");
+    let dst_peer_id = dst.peer_id;
+    // Accept and hold all circuits while the sources connect.
+    let circuits = dst.accept_circuits().collect::<Vec<_>>().map(|_| Ok(()));
+    let _reservation = dst.reserve(relay).await?;
+
+    let connect = async move {
+        let (last, srcs) = srcs.split_last_mut().expect("at least one source");
+        let mut circuits = Vec::with_capacity(srcs.len());
+        for src in srcs {
+            let (stream, _, status) = src
+                .request_status(relay, &vectors::connect(dst_peer_id))
+                .await?;
+            if status != Status::Ok {
+                return Err(format!(
+                    "expected circuit within the limit of {} to be accepted, got {status:?}",
+                    expectations.max_circuits
+                ));
+            }
+            circuits.push(stream);
+        }
+
+        let (_, _, status) = last
+            .request_status(relay, &vectors::connect(dst_peer_id))
+            .await?;
+        if status != Status::ResourceLimitExceeded {
+            return Err(format!(
+                "expected circuit beyond the limit of {} to be denied with RESOURCE_LIMIT_EXCEEDED, got {status:?}",
+                expectations.max_circuits
+            ));
+        }
+
+        Ok(())
+    };
+
+    future::select(connect.boxed(), circuits)
+        .await
+        .factor_first()
+        .0
+}
+
+async fn check_circuit_data_limit(mut env: Env, expectations: &Expectations) -> Result<(), String> {
+    let relay = env.relay;
+    let src_peer_id = env.clients[1].peer_id;
+    let dst_peer_id = env.clients[0].peer_id;
+    let mut circuits = env.clients[0].accept_circuits();
+    let _reservation = env.clients[0].reserve(relay).await?;
+
+    let (request, circuit) = future::join(
+        env.clients[1].request_status(relay, &vectors::connect(dst_peer_id)),
+        circuits.next(),
+    )
+    .await;
+    let (src_stream, answer, status) = request?;
+    if status != Status::Ok {
+        return Err(format!("expected circuit to be accepted, got {status:?}"));
+    }
+    // Unlike the stop request, the answer to the source may omit the limits.
+    if answer.limit.is_some() {
+        check_limit(answer.limit.as_ref(), expectations)?;
+    }
+
+    let (stop, dst_stream) = circuit.ok_or("destination did not receive the circuit")?;
+    if stop.type_pb != proto::StopMessageType::CONNECT {
+        return Err(format!("expected stop CONNECT, got {:?}", stop.type_pb));
+    }
+    if stop.peer.map(|p| p.id) != Some(src_peer_id.to_bytes()) {
+        return Err("stop CONNECT does not carry the source of the circuit".to_owned());
+    }
+    check_limit(stop.limit.as_ref(), expectations)?;
+
+    if expectations.max_circuit_bytes == 0 {
+        return Ok(());
+    }
+
+    // Send more than the limit plus the buffers of the relay.
+    let to_send = expectations.max_circuit_bytes as usize + (1 << 20);
+    let FramedParts {
+        io: mut src_stream, ..
+    } = src_stream.into_parts();
+    let send = async move {
+        let chunk = vec![0u8; 1 << 14];
+        let mut sent = 0;
+        while sent < to_send {
+            if src_stream.write_all(&chunk).await.is_err() {
+                break;
+            }
+            sent += chunk.len();
+        }
+        let _ = src_stream.close().await;
+    };
+    let mut dst_stream = dst_stream;
+    let receive = async move {
+        let mut buf = vec![0u8; 1 << 14];
+        let mut received = 0;
+        loop {
+            match dst_stream.read(&mut buf).await {
+                Ok(0) | Err(_) => return received,
+                Ok(n) => received += n,
+            }
+        }
+    };
+    let ((), received) = future::join(send, receive).await;
+
+    if received >= to_send {
+        return Err(format!(
+            "relayed {received} bytes, beyond the limit of {} bytes",
+            expectations.max_circuit_bytes
+        ));
+    }
+
+    Ok(())
+}
+
+async fn check_malformed_message(mut env: Env) -> Result<(), String> {
+    let relay = env.relay;
+
+    for vector in vectors::malformed() {
+        let (_, _, status) = env.clients[0]
+            .request_status(relay, &vector.bytes)
+            .await
+            .map_err(|e| format!("{}: {e}", vector.name))?;
+        if status != vector.expected {
+            return Err(format!(
+                "{}: expected {:?}, got {status:?}",
+                vector.name, vector.expected
+            ));
+        }
+    }
+
+    Ok(())
+}
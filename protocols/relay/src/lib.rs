@@ -24,6 +24,8 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod behaviour;
+#[cfg(feature = "conformance")]
+pub mod conformance;
 mod copy_future;
 mod multiaddr_ext;
 mod priv_client;
@@ -31,15 +33,22 @@ mod protocol;
 
 mod proto {
     #![allow(unreachable_pub)]
+    // `ReservationVoucher` is only decoded by the conformance module.
+    #![cfg_attr(not(feature = "conformance"), allow(dead_code))]
     include!("generated/mod.rs");
     pub(crate) use self::message_v2::pb::mod_HopMessage::Type as HopMessageType;
     pub use self::message_v2::pb::mod_StopMessage::Type as StopMessageType;
+    #[cfg(feature = "conformance")]
+    pub(crate) use self::message_v2::pb::ReservationVoucher;
     pub(crate) use self::message_v2::pb::{
         HopMessage, Limit, Peer, Reservation, Status, StopMessage,
     };
 }
 
-pub use behaviour::{rate_limiter::RateLimiter, Behaviour, CircuitId, Config, Event};
+pub use behaviour::{
+    admission::AdmissionPolicy, rate_limiter::RateLimiter, Behaviour, CircuitId, Config,
+    DenialReason, Event,
+};
 pub use protocol::{HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 
 /// Types related to the relay protocol inbound.
@@ -55,7 +64,10 @@ pub mod inbound {
 /// Types related to the relay protocol outbound.
 pub mod outbound {
     pub mod hop {
-        pub use crate::protocol::outbound_hop::{ConnectError, ProtocolViolation, ReserveError};
+        pub use crate::protocol::outbound_hop::{
+            CircuitFailedReason, ConnectError, ProtocolViolation, ReservationFailedReason,
+            ReserveError,
+        };
     }
     pub mod stop {
         pub use crate::protocol::outbound_stop::{Error, ProtocolViolation};
@@ -64,7 +76,10 @@ pub mod outbound {
 
 /// Everything related to the relay protocol from a client's perspective.
 pub mod client {
-    pub use crate::priv_client::{new, transport::Transport, Behaviour, Connection, Event};
+    pub use crate::priv_client::{
+        new, new_with_config, transport::Transport, Behaviour, Config, Connection, Event,
+        RelayedConnection,
+    };
 
     pub mod transport {
         pub use crate::priv_client::transport::Error;
@@ -25,27 +25,32 @@ pub(crate) mod transport;
 
 use crate::multiaddr_ext::MultiaddrExt;
 use crate::priv_client::handler::Handler;
+use crate::protocol::outbound_hop::{CircuitFailedReason, ReservationFailedReason};
 use crate::protocol::{self, inbound_stop};
 use bytes::Bytes;
 use either::Either;
-use futures::channel::mpsc::Receiver;
+use futures::channel::mpsc::{Receiver, Sender};
 use futures::future::{BoxFuture, FutureExt};
 use futures::io::{AsyncRead, AsyncWrite};
 use futures::ready;
-use futures::stream::StreamExt;
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures_timer::Delay;
 use libp2p_core::multiaddr::Protocol;
-use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_core::transport::ListenerId;
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm};
 use libp2p_swarm::dial_opts::DialOpts;
 use libp2p_swarm::{
-    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, DialFailure, NetworkBehaviour,
-    NotifyHandler, Stream, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, DialFailure,
+    ExternalAddrExpiryReason, ExternalAddrOrigin, NetworkBehaviour, NotifyHandler, Stream,
+    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use std::collections::{hash_map, HashMap, VecDeque};
 use std::io::{Error, ErrorKind, IoSlice};
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use transport::Transport;
 use void::Void;
 
@@ -55,19 +60,136 @@ pub enum Event {
     /// An outbound reservation has been accepted.
     ReservationReqAccepted {
         relay_peer_id: PeerId,
+        /// The listener the reservation was made for.
+        listener_id: ListenerId,
         /// Indicates whether the request replaces an existing reservation.
         renewal: bool,
         limit: Option<protocol::Limit>,
     },
+    /// An outbound reservation has failed.
+    ///
+    /// The full error is reported to the listener of the reservation, i.e. via
+    /// [`SwarmEvent::ListenerClosed`](libp2p_swarm::SwarmEvent::ListenerClosed).
+    ReservationReqFailed {
+        relay_peer_id: PeerId,
+        /// The listener the reservation was made for.
+        listener_id: ListenerId,
+        /// Indicates whether the request replaced an existing reservation.
+        renewal: bool,
+        /// The reason reported by the relay, `None` if the request failed for other reasons,
+        /// e.g. an I/O error.
+        reason: Option<ReservationFailedReason>,
+    },
     OutboundCircuitEstablished {
         relay_peer_id: PeerId,
         limit: Option<protocol::Limit>,
     },
+    /// An outbound circuit request has failed.
+    ///
+    /// The full error is reported to the dialer, i.e. via
+    /// [`SwarmEvent::OutgoingConnectionError`](libp2p_swarm::SwarmEvent::OutgoingConnectionError).
+    OutboundCircuitReqFailed {
+        relay_peer_id: PeerId,
+        /// The reason reported by the relay, `None` if the request failed for other reasons,
+        /// e.g. an I/O error.
+        reason: Option<CircuitFailedReason>,
+    },
     /// An inbound circuit has been established.
     InboundCircuitEstablished {
         src_peer_id: PeerId,
         limit: Option<protocol::Limit>,
     },
+    /// A connection over an inbound circuit has been established, i.e. the remote connected to
+    /// us through a relay.
+    ///
+    /// The `connection_id` matches the one of the corresponding
+    /// [`SwarmEvent::ConnectionEstablished`](libp2p_swarm::SwarmEvent::ConnectionEstablished).
+    /// See also [`Behaviour::relayed_connection`].
+    InboundRelayedConnectionEstablished {
+        connection_id: ConnectionId,
+        src_peer_id: PeerId,
+        relay_peer_id: PeerId,
+        /// The limit the relay imposes on the circuit.
+        limit: Option<protocol::Limit>,
+    },
+    /// A connection previously reported via [`Event::InboundRelayedConnectionEstablished`] has
+    /// been closed.
+    InboundRelayedConnectionClosed {
+        connection_id: ConnectionId,
+        src_peer_id: PeerId,
+        relay_peer_id: PeerId,
+    },
+    /// A confirmed reservation has been lost, e.g. because the connection to the relay closed or
+    /// the relay rejected its renewal.
+    ///
+    /// Only reported with [`Config::recover_reservations`] enabled, the reservation is
+    /// re-established after [`Event::ReservationRecoveryScheduled`].
+    ReservationLost {
+        relay_peer_id: PeerId,
+        /// The listener the reservation was made for.
+        listener_id: ListenerId,
+    },
+    /// An attempt to re-establish a lost reservation has been scheduled.
+    ///
+    /// Success is reported via [`Event::ReservationReqAccepted`] for the same listener.
+    ReservationRecoveryScheduled {
+        relay_peer_id: PeerId,
+        /// The listener the reservation was made for.
+        listener_id: ListenerId,
+        /// The number of the attempt, starting at 1.
+        attempt: u32,
+        /// The time until the attempt is made.
+        backoff: Duration,
+    },
+    /// A lost reservation could not be re-established within [`Config::max_recovery_attempts`],
+    /// the listener of the reservation is closed.
+    ReservationRecoveryFailed {
+        relay_peer_id: PeerId,
+        /// The listener the reservation was made for.
+        listener_id: ListenerId,
+    },
+}
+
+/// Configuration of the client [`Behaviour`] and its [`Transport`], see [`new_with_config`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Whether to re-establish confirmed reservations once they are lost, e.g. after a restart of
+    /// the relay.
+    ///
+    /// The listener of a lost reservation stays open. Its addresses expire and are reported
+    /// again once the reservation is re-established. Defaults to `false`, i.e. closing the
+    /// listener.
+    pub recover_reservations: bool,
+    /// Delay before the first attempt to re-establish a lost reservation, doubled after each
+    /// failed attempt.
+    pub recovery_initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts to re-establish a lost reservation.
+    pub recovery_max_backoff: Duration,
+    /// Number of failed attempts after which a lost reservation is given up and its listener
+    /// closed.
+    pub max_recovery_attempts: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            recover_reservations: false,
+            recovery_initial_backoff: Duration::from_secs(1),
+            recovery_max_backoff: Duration::from_secs(60),
+            max_recovery_attempts: 10,
+        }
+    }
+}
+
+/// An inbound connection established through a relay, see [`Behaviour::relayed_connection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayedConnection {
+    /// The peer that connected to us.
+    pub src_peer_id: PeerId,
+    /// The relay the connection goes through.
+    pub relay_peer_id: PeerId,
+    /// The limit the relay imposes on the circuit.
+    pub limit: Option<protocol::Limit>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -76,52 +198,189 @@ enum ReservationStatus {
     Confirmed,
 }
 
+/// A pending or confirmed reservation on a relay server, made on behalf of a listener.
+#[derive(Debug)]
+struct ReservationInfo {
+    listener_id: ListenerId,
+    relay_peer_id: PeerId,
+    /// The address of the relay server.
+    relay_addr: Multiaddr,
+    /// The `/p2p-circuit` address we reserved on the relay.
+    addr: Multiaddr,
+    status: ReservationStatus,
+    /// Channel to the listener, kept with [`Config::recover_reservations`] to keep the listener
+    /// open while the reservation is lost.
+    to_listener: Option<Sender<transport::ToListenerMsg>>,
+    /// The number of the current attempt to re-establish the reservation, `0` if it was never
+    /// lost.
+    recovery_attempt: u32,
+}
+
 /// [`NetworkBehaviour`] implementation of the relay client
 /// functionality of the circuit relay v2 protocol.
 pub struct Behaviour {
     local_peer_id: PeerId,
+    config: Config,
 
     from_transport: Receiver<transport::TransportToBehaviourMsg>,
     /// Set of directly connected peers, i.e. not connected via a relayed
     /// connection.
     directly_connected_peers: HashMap<PeerId, Vec<ConnectionId>>,
 
-    /// Stores the address of a pending or confirmed reservation.
+    /// Stores the pending or confirmed reservations.
     ///
-    /// This is indexed by the [`ConnectionId`] to a relay server and the address is the `/p2p-circuit` address we reserved on it.
-    reservation_addresses: HashMap<ConnectionId, (Multiaddr, ReservationStatus)>,
+    /// This is indexed by the [`ConnectionId`] to a relay server. Each relay server is listened
+    /// on through its own listener of the [`Transport`].
+    reservations: HashMap<ConnectionId, ReservationInfo>,
 
     /// Queue of actions to return when polled.
     queued_actions: VecDeque<ToSwarm<Event, Either<handler::In, Void>>>,
 
     pending_handler_commands: HashMap<ConnectionId, handler::In>,
+
+    /// The inbound circuits accepted but not yet upgraded to a connection, by source peer,
+    /// with the relay they go through and the limit of the circuit.
+    pending_inbound_circuits: HashMap<PeerId, VecDeque<(PeerId, Option<protocol::Limit>)>>,
+    /// The established connections over inbound circuits.
+    relayed_connections: HashMap<ConnectionId, RelayedConnection>,
+
+    /// Lost reservations waiting for their backoff to elapse before being re-established.
+    pending_recoveries: FuturesUnordered<BoxFuture<'static, ReservationInfo>>,
 }
 
 /// Create a new client relay [`Behaviour`] with it's corresponding [`Transport`].
 pub fn new(local_peer_id: PeerId) -> (Transport, Behaviour) {
-    let (transport, from_transport) = Transport::new();
+    new_with_config(local_peer_id, Config::default())
+}
+
+/// Create a new client relay [`Behaviour`] with it's corresponding [`Transport`], using the
+/// given [`Config`].
+pub fn new_with_config(local_peer_id: PeerId, config: Config) -> (Transport, Behaviour) {
+    let (transport, from_transport) = Transport::new(config.recover_reservations);
     let behaviour = Behaviour {
         local_peer_id,
+        config,
         from_transport,
         directly_connected_peers: Default::default(),
-        reservation_addresses: Default::default(),
+        reservations: Default::default(),
         queued_actions: Default::default(),
         pending_handler_commands: Default::default(),
+        pending_inbound_circuits: Default::default(),
+        relayed_connections: Default::default(),
+        pending_recoveries: Default::default(),
     };
     (transport, behaviour)
 }
 
 impl Behaviour {
+    /// Lists the confirmed reservations as the relay server's [`PeerId`], the [`ListenerId`] the
+    /// reservation was made for and the `/p2p-circuit` address reserved on the relay server.
+    ///
+    /// Listening via multiple relay servers at once is supported, each reservation being reported
+    /// under the [`ListenerId`] of the corresponding [`Transport`] listener.
+    pub fn reservations(&self) -> impl Iterator<Item = (PeerId, ListenerId, &Multiaddr)> {
+        self.reservations
+            .values()
+            .filter(|r| r.status == ReservationStatus::Confirmed)
+            .map(|r| (r.relay_peer_id, r.listener_id, &r.addr))
+    }
+
+    /// Returns the source peer, relay and limit of an inbound connection established through a
+    /// relay, `None` for any other connection.
+    pub fn relayed_connection(&self, connection_id: &ConnectionId) -> Option<&RelayedConnection> {
+        self.relayed_connections.get(connection_id)
+    }
+
+    fn on_connection_established(
+        &mut self,
+        ConnectionEstablished {
+            peer_id,
+            connection_id,
+            endpoint,
+            ..
+        }: ConnectionEstablished,
+    ) {
+        let ConnectedPoint::Listener { local_addr, .. } = endpoint else {
+            return;
+        };
+        if !local_addr.is_relayed() {
+            return;
+        }
+        let hash_map::Entry::Occupied(mut circuits) = self.pending_inbound_circuits.entry(peer_id)
+        else {
+            return;
+        };
+
+        // The listen address of a circuit ends in `/p2p/<relay>/p2p-circuit`.
+        let relay_peer_id = local_addr
+            .iter()
+            .fold(None, |relay, protocol| match protocol {
+                Protocol::P2p(peer) => Some(peer),
+                Protocol::P2pCircuit => relay,
+                _ => None,
+            });
+        let position = relay_peer_id
+            .and_then(|relay| circuits.get().iter().position(|(r, _)| *r == relay))
+            .unwrap_or(0);
+        let Some((relay_peer_id, limit)) = circuits.get_mut().remove(position) else {
+            return;
+        };
+        if circuits.get().is_empty() {
+            circuits.remove();
+        }
+
+        self.relayed_connections.insert(
+            connection_id,
+            RelayedConnection {
+                src_peer_id: peer_id,
+                relay_peer_id,
+                limit,
+            },
+        );
+        self.queued_actions.push_back(ToSwarm::GenerateEvent(
+            Event::InboundRelayedConnectionEstablished {
+                connection_id,
+                src_peer_id: peer_id,
+                relay_peer_id,
+                limit,
+            },
+        ));
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
             peer_id,
             connection_id,
             endpoint,
+            remaining_established,
             ..
         }: ConnectionClosed,
     ) {
+        if let Some(RelayedConnection {
+            src_peer_id,
+            relay_peer_id,
+            ..
+        }) = self.relayed_connections.remove(&connection_id)
+        {
+            self.queued_actions.push_back(ToSwarm::GenerateEvent(
+                Event::InboundRelayedConnectionClosed {
+                    connection_id,
+                    src_peer_id,
+                    relay_peer_id,
+                },
+            ));
+        }
+
         if !endpoint.is_relayed() {
+            // Circuits through a relay we are no longer connected to won't be upgraded anymore.
+            if remaining_established == 0 {
+                self.pending_inbound_circuits.retain(|_, circuits| {
+                    circuits.retain(|(relay, _)| *relay != peer_id);
+                    !circuits.is_empty()
+                });
+            }
+
             match self.directly_connected_peers.entry(peer_id) {
                 hash_map::Entry::Occupied(mut connections) => {
                     let position = connections
@@ -139,14 +398,136 @@ impl Behaviour {
                     unreachable!("`on_connection_closed` for unconnected peer.")
                 }
             };
-            if let Some((addr, ReservationStatus::Confirmed)) =
-                self.reservation_addresses.remove(&connection_id)
-            {
-                self.queued_actions
-                    .push_back(ToSwarm::ExternalAddrExpired(addr));
+            match self.reservations.remove(&connection_id) {
+                Some(reservation) if reservation.status == ReservationStatus::Confirmed => {
+                    self.queued_actions.push_back(ToSwarm::ExternalAddrExpired {
+                        address: reservation.addr.clone(),
+                        reason: ExternalAddrExpiryReason::MappingLost,
+                    });
+                    self.on_reservation_lost(reservation);
+                }
+                Some(reservation) if reservation.recovery_attempt > 0 => {
+                    self.schedule_recovery(reservation);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Requests a reservation on the relay, dialing it if we are not connected yet.
+    fn reserve(
+        &mut self,
+        reservation: ReservationInfo,
+        to_listener: Sender<transport::ToListenerMsg>,
+    ) -> ToSwarm<Event, Either<handler::In, Void>> {
+        let relay_peer_id = reservation.relay_peer_id;
+
+        match self
+            .directly_connected_peers
+            .get(&relay_peer_id)
+            .and_then(|cs| cs.first())
+        {
+            Some(connection_id) => {
+                self.reservations.insert(*connection_id, reservation);
+
+                ToSwarm::NotifyHandler {
+                    peer_id: relay_peer_id,
+                    handler: NotifyHandler::One(*connection_id),
+                    event: Either::Left(handler::In::Reserve { to_listener }),
+                }
+            }
+            None => {
+                let opts = DialOpts::peer_id(relay_peer_id)
+                    .addresses(vec![reservation.relay_addr.clone()])
+                    .extend_addresses_through_behaviour()
+                    .build();
+                let relayed_connection_id = opts.connection_id();
+
+                self.reservations.insert(relayed_connection_id, reservation);
+
+                self.pending_handler_commands
+                    .insert(relayed_connection_id, handler::In::Reserve { to_listener });
+                ToSwarm::Dial { opts }
             }
         }
     }
+
+    /// Expires the addresses of the listener of a lost reservation and schedules re-establishing
+    /// it, if [`Config::recover_reservations`] is enabled.
+    fn on_reservation_lost(&mut self, mut reservation: ReservationInfo) {
+        let Some(to_listener) = reservation.to_listener.as_ref() else {
+            return;
+        };
+        // Each sender has a guaranteed slot in the channel, thus sending via a fresh clone only
+        // fails once the listener is gone, while keeping the order with the messages of the
+        // handler of the re-established reservation.
+        if to_listener
+            .clone()
+            .try_send(transport::ToListenerMsg::ReservationLost)
+            .is_err()
+        {
+            tracing::debug!(
+                relay=%reservation.relay_peer_id,
+                "Failed to report lost reservation to listener"
+            );
+        }
+
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::ReservationLost {
+                relay_peer_id: reservation.relay_peer_id,
+                listener_id: reservation.listener_id,
+            }));
+        reservation.recovery_attempt = 0;
+        self.schedule_recovery(reservation);
+    }
+
+    /// Schedules the next attempt to re-establish a lost reservation, giving up after
+    /// [`Config::max_recovery_attempts`].
+    fn schedule_recovery(&mut self, mut reservation: ReservationInfo) {
+        if reservation
+            .to_listener
+            .as_ref()
+            .map_or(true, |to_listener| to_listener.is_closed())
+        {
+            // The listener has been removed in the meantime.
+            return;
+        }
+
+        if reservation.recovery_attempt >= self.config.max_recovery_attempts {
+            // Dropping the channel closes the listener.
+            self.queued_actions.push_back(ToSwarm::GenerateEvent(
+                Event::ReservationRecoveryFailed {
+                    relay_peer_id: reservation.relay_peer_id,
+                    listener_id: reservation.listener_id,
+                },
+            ));
+            return;
+        }
+
+        let backoff = self
+            .config
+            .recovery_initial_backoff
+            .saturating_mul(2u32.saturating_pow(reservation.recovery_attempt))
+            .min(self.config.recovery_max_backoff);
+        reservation.recovery_attempt += 1;
+        reservation.status = ReservationStatus::Pending;
+
+        self.queued_actions.push_back(ToSwarm::GenerateEvent(
+            Event::ReservationRecoveryScheduled {
+                relay_peer_id: reservation.relay_peer_id,
+                listener_id: reservation.listener_id,
+                attempt: reservation.recovery_attempt,
+                backoff,
+            },
+        ));
+        self.pending_recoveries.push(
+            async move {
+                Delay::new(backoff).await;
+                reservation
+            }
+            .boxed(),
+        );
+    }
 }
 
 impl NetworkBehaviour for Behaviour {
@@ -194,12 +575,16 @@ impl NetworkBehaviour for Behaviour {
 
     fn on_swarm_event(&mut self, event: FromSwarm) {
         match event {
-            FromSwarm::ConnectionEstablished(ConnectionEstablished {
-                peer_id,
-                connection_id,
-                endpoint,
-                ..
-            }) => {
+            FromSwarm::ConnectionEstablished(
+                connection_established @ ConnectionEstablished {
+                    peer_id,
+                    connection_id,
+                    endpoint,
+                    ..
+                },
+            ) => {
+                self.on_connection_established(connection_established);
+
                 if !endpoint.is_relayed() {
                     self.directly_connected_peers
                         .entry(peer_id)
@@ -219,8 +604,12 @@ impl NetworkBehaviour for Behaviour {
                 self.on_connection_closed(connection_closed)
             }
             FromSwarm::DialFailure(DialFailure { connection_id, .. }) => {
-                self.reservation_addresses.remove(&connection_id);
                 self.pending_handler_commands.remove(&connection_id);
+                if let Some(reservation) = self.reservations.remove(&connection_id) {
+                    if reservation.recovery_attempt > 0 {
+                        self.schedule_recovery(reservation);
+                    }
+                }
             }
             _ => {}
         }
@@ -239,23 +628,71 @@ impl NetworkBehaviour for Behaviour {
 
         let event = match handler_event {
             handler::Event::ReservationReqAccepted { renewal, limit } => {
-                let (addr, status) = self
-                    .reservation_addresses
+                let reservation = self
+                    .reservations
                     .get_mut(&connection)
                     .expect("Relay connection exist");
 
-                if !renewal && *status == ReservationStatus::Pending {
-                    *status = ReservationStatus::Confirmed;
-                    self.queued_actions
-                        .push_back(ToSwarm::ExternalAddrConfirmed(addr.clone()));
+                if reservation.status == ReservationStatus::Pending {
+                    reservation.status = ReservationStatus::Confirmed;
+                    reservation.recovery_attempt = 0;
+                    // A renewal replaces a reservation whose address has already been confirmed.
+                    if !renewal {
+                        self.queued_actions
+                            .push_back(ToSwarm::ExternalAddrConfirmed {
+                                address: reservation.addr.clone(),
+                                origin: ExternalAddrOrigin::Relay,
+                            });
+                    }
                 }
 
                 Event::ReservationReqAccepted {
                     relay_peer_id: event_source,
+                    listener_id: reservation.listener_id,
                     renewal,
                     limit,
                 }
             }
+            handler::Event::ReservationReqFailed { renewal, reason } => {
+                let Some(reservation) = self.reservations.get(&connection) else {
+                    return;
+                };
+                let recover = reservation.to_listener.is_some()
+                    && (reservation.status == ReservationStatus::Confirmed
+                        || reservation.recovery_attempt > 0);
+
+                self.queued_actions.push_back(ToSwarm::GenerateEvent(
+                    Event::ReservationReqFailed {
+                        relay_peer_id: event_source,
+                        listener_id: reservation.listener_id,
+                        renewal,
+                        reason,
+                    },
+                ));
+
+                if recover {
+                    let reservation = self
+                        .reservations
+                        .remove(&connection)
+                        .expect("Reservation to exist");
+                    if reservation.status == ReservationStatus::Confirmed {
+                        self.queued_actions.push_back(ToSwarm::ExternalAddrExpired {
+                            address: reservation.addr.clone(),
+                            reason: ExternalAddrExpiryReason::MappingLost,
+                        });
+                        self.on_reservation_lost(reservation);
+                    } else {
+                        self.schedule_recovery(reservation);
+                    }
+                }
+                return;
+            }
+            handler::Event::OutboundCircuitReqFailed { reason } => {
+                Event::OutboundCircuitReqFailed {
+                    relay_peer_id: event_source,
+                    reason,
+                }
+            }
             handler::Event::OutboundCircuitEstablished { limit } => {
                 Event::OutboundCircuitEstablished {
                     relay_peer_id: event_source,
@@ -263,6 +700,11 @@ impl NetworkBehaviour for Behaviour {
                 }
             }
             handler::Event::InboundCircuitEstablished { src_peer_id, limit } => {
+                self.pending_inbound_circuits
+                    .entry(src_peer_id)
+                    .or_default()
+                    .push_back((event_source, limit));
+
                 Event::InboundCircuitEstablished { src_peer_id, limit }
             }
         };
@@ -279,58 +721,42 @@ impl NetworkBehaviour for Behaviour {
             return Poll::Ready(action);
         }
 
+        while let Poll::Ready(Some(mut reservation)) = self.pending_recoveries.poll_next_unpin(cx) {
+            let Some(to_listener) = reservation.to_listener.clone() else {
+                continue;
+            };
+            if to_listener.is_closed() {
+                continue;
+            }
+            reservation.status = ReservationStatus::Pending;
+            return Poll::Ready(self.reserve(reservation, to_listener));
+        }
+
         let action = match ready!(self.from_transport.poll_next_unpin(cx)) {
             Some(transport::TransportToBehaviourMsg::ListenReq {
+                listener_id,
                 relay_peer_id,
                 relay_addr,
                 to_listener,
             }) => {
-                match self
-                    .directly_connected_peers
-                    .get(&relay_peer_id)
-                    .and_then(|cs| cs.first())
-                {
-                    Some(connection_id) => {
-                        self.reservation_addresses.insert(
-                            *connection_id,
-                            (
-                                relay_addr
-                                    .with(Protocol::P2p(relay_peer_id))
-                                    .with(Protocol::P2pCircuit)
-                                    .with(Protocol::P2p(self.local_peer_id)),
-                                ReservationStatus::Pending,
-                            ),
-                        );
-
-                        ToSwarm::NotifyHandler {
-                            peer_id: relay_peer_id,
-                            handler: NotifyHandler::One(*connection_id),
-                            event: Either::Left(handler::In::Reserve { to_listener }),
-                        }
-                    }
-                    None => {
-                        let opts = DialOpts::peer_id(relay_peer_id)
-                            .addresses(vec![relay_addr.clone()])
-                            .extend_addresses_through_behaviour()
-                            .build();
-                        let relayed_connection_id = opts.connection_id();
-
-                        self.reservation_addresses.insert(
-                            relayed_connection_id,
-                            (
-                                relay_addr
-                                    .with(Protocol::P2p(relay_peer_id))
-                                    .with(Protocol::P2pCircuit)
-                                    .with(Protocol::P2p(self.local_peer_id)),
-                                ReservationStatus::Pending,
-                            ),
-                        );
-
-                        self.pending_handler_commands
-                            .insert(relayed_connection_id, handler::In::Reserve { to_listener });
-                        ToSwarm::Dial { opts }
-                    }
-                }
+                let reservation = ReservationInfo {
+                    listener_id,
+                    relay_peer_id,
+                    addr: relay_addr
+                        .clone()
+                        .with(Protocol::P2p(relay_peer_id))
+                        .with(Protocol::P2pCircuit)
+                        .with(Protocol::P2p(self.local_peer_id)),
+                    relay_addr,
+                    status: ReservationStatus::Pending,
+                    to_listener: self
+                        .config
+                        .recover_reservations
+                        .then(|| to_listener.clone()),
+                    recovery_attempt: 0,
+                };
+
+                self.reserve(reservation, to_listener)
             }
             Some(transport::TransportToBehaviourMsg::DialReq {
                 relay_addr,
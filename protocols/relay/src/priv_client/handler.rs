@@ -83,8 +83,18 @@ pub enum Event {
         renewal: bool,
         limit: Option<protocol::Limit>,
     },
+    /// A reservation request failed.
+    ReservationReqFailed {
+        /// Indicates whether the request replaced an existing reservation.
+        renewal: bool,
+        reason: Option<outbound_hop::ReservationFailedReason>,
+    },
     /// An outbound circuit has been established.
     OutboundCircuitEstablished { limit: Option<protocol::Limit> },
+    /// An outbound circuit request failed.
+    OutboundCircuitReqFailed {
+        reason: Option<outbound_hop::CircuitFailedReason>,
+    },
     /// An inbound circuit has been established.
     InboundCircuitEstablished {
         src_peer_id: PeerId,
@@ -286,13 +296,17 @@ impl ConnectionHandler for Handler {
                     ));
                 }
                 Poll::Ready((Ok(Err(error)), mut to_listener)) => {
+                    let reason = error.reason();
                     if let Err(e) =
                         to_listener.try_send(transport::ToListenerMsg::Reservation(Err(error)))
                     {
                         tracing::debug!("Unable to send error to listener: {}", e.into_send_error())
                     }
+                    let renewal = self.reservation.is_some();
                     self.reservation.failed();
-                    continue;
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::ReservationReqFailed { renewal, reason },
+                    ));
                 }
                 Poll::Ready((Err(futures_bounded::Timeout { .. }), mut to_listener)) => {
                     if let Err(e) =
@@ -335,8 +349,11 @@ impl ConnectionHandler for Handler {
                     ));
                 }
                 Poll::Ready((Ok(Err(error)), to_dialer)) => {
+                    let reason = error.reason();
                     let _ = to_dialer.send(Err(error));
-                    continue;
+                    return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                        Event::OutboundCircuitReqFailed { reason },
+                    ));
                 }
                 Poll::Ready((Err(futures_bounded::Timeout { .. }), to_dialer)) => {
                     if to_dialer
@@ -91,19 +91,29 @@ use thiserror::Error;
 ///        .with(Protocol::P2pCircuit); // Signal to listen via remote relay node.
 ///    transport.listen_on(ListenerId::next(), relay_addr).unwrap();
 ///    ```
+///
+///    Listening via multiple relays at once is supported by calling
+///    [`listen_on`](libp2p_core::Transport::listen_on) once per relay. Each relay is served by
+///    its own listener, thus a failing reservation only closes the listener of the affected relay.
 pub struct Transport {
     to_behaviour: mpsc::Sender<TransportToBehaviourMsg>,
     pending_to_behaviour: VecDeque<TransportToBehaviourMsg>,
     listeners: SelectAll<Listener>,
+    /// Whether listeners stay open once their reservation is lost, see
+    /// [`Config::recover_reservations`](crate::client::Config::recover_reservations).
+    recover_reservations: bool,
 }
 
 impl Transport {
-    pub(crate) fn new() -> (Self, mpsc::Receiver<TransportToBehaviourMsg>) {
+    pub(crate) fn new(
+        recover_reservations: bool,
+    ) -> (Self, mpsc::Receiver<TransportToBehaviourMsg>) {
         let (to_behaviour, from_transport) = mpsc::channel(1000);
         let transport = Transport {
             to_behaviour,
             pending_to_behaviour: VecDeque::new(),
             listeners: SelectAll::new(),
+            recover_reservations,
         };
         (transport, from_transport)
     }
@@ -141,6 +151,7 @@ impl libp2p_core::Transport for Transport {
         let (to_listener, from_behaviour) = mpsc::channel(0);
         self.pending_to_behaviour
             .push_back(TransportToBehaviourMsg::ListenReq {
+                listener_id,
                 relay_peer_id,
                 relay_addr,
                 to_listener,
@@ -148,9 +159,12 @@ impl libp2p_core::Transport for Transport {
 
         let listener = Listener {
             listener_id,
+            addrs: Default::default(),
             queued_events: Default::default(),
             from_behaviour,
             is_closed: false,
+            recover: self.recover_reservations,
+            reserved: false,
         };
         self.listeners.push(listener);
         Ok(())
@@ -216,6 +230,10 @@ impl libp2p_core::Transport for Transport {
         None
     }
 
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        addr.is_relayed()
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -306,6 +324,8 @@ fn parse_relayed_multiaddr(addr: Multiaddr) -> Result<RelayedMultiaddr, Transpor
 
 pub(crate) struct Listener {
     listener_id: ListenerId,
+    /// The addresses of the current reservation.
+    addrs: Vec<Multiaddr>,
     /// Queue of events to report when polled.
     queued_events: VecDeque<<Self as Stream>::Item>,
     /// Channel for messages from the behaviour [`Handler`][super::handler::Handler].
@@ -313,6 +333,11 @@ pub(crate) struct Listener {
     /// The listener can be closed either manually with [`Transport::remove_listener`](libp2p_core::Transport) or if
     /// the sender side of the `from_behaviour` channel is dropped.
     is_closed: bool,
+    /// Whether to stay open once a reservation is lost, waiting for the behaviour to
+    /// re-establish it.
+    recover: bool,
+    /// Whether a reservation has been accepted at least once.
+    reserved: bool,
 }
 
 impl Listener {
@@ -329,6 +354,20 @@ impl Listener {
             });
         self.is_closed = true;
     }
+
+    /// Expire the addresses of the current reservation.
+    fn expire_addrs(&mut self) {
+        let listener_id = self.listener_id;
+        self.queued_events
+            .extend(
+                self.addrs
+                    .drain(..)
+                    .map(|listen_addr| TransportEvent::AddressExpired {
+                        listener_id,
+                        listen_addr,
+                    }),
+            );
+    }
 }
 
 impl Stream for Listener {
@@ -357,14 +396,26 @@ impl Stream for Listener {
                         self.queued_events.is_empty(),
                         "Assert empty due to previous `pop_front` attempt."
                     );
-                    // Returned as [`ListenerEvent::NewAddress`] in next iteration of loop.
-                    self.queued_events = addrs
+                    let listener_id = self.listener_id;
+                    self.reserved = true;
+                    let previous_addrs = std::mem::replace(&mut self.addrs, addrs);
+                    // Addresses no longer part of a renewed reservation expire.
+                    let mut events = previous_addrs
                         .into_iter()
-                        .map(|listen_addr| TransportEvent::NewAddress {
-                            listener_id: self.listener_id,
+                        .filter(|addr| !self.addrs.contains(addr))
+                        .map(|listen_addr| TransportEvent::AddressExpired {
+                            listener_id,
                             listen_addr,
                         })
-                        .collect();
+                        .collect::<VecDeque<_>>();
+                    // Returned as [`ListenerEvent::NewAddress`] in next iteration of loop.
+                    events.extend(self.addrs.iter().cloned().map(|listen_addr| {
+                        TransportEvent::NewAddress {
+                            listener_id,
+                            listen_addr,
+                        }
+                    }));
+                    self.queued_events = events;
                 }
                 ToListenerMsg::IncomingRelayedConnection {
                     stream,
@@ -381,7 +432,13 @@ impl Stream for Listener {
                         send_back_addr: Protocol::P2p(src_peer_id).into(),
                     })
                 }
+                ToListenerMsg::Reservation(Err(_)) | ToListenerMsg::ReservationLost
+                    if self.recover && self.reserved =>
+                {
+                    self.expire_addrs()
+                }
                 ToListenerMsg::Reservation(Err(e)) => self.close(Err(Error::Reservation(e))),
+                ToListenerMsg::ReservationLost => {}
             };
         }
     }
@@ -435,6 +492,7 @@ pub(crate) enum TransportToBehaviourMsg {
     },
     /// Listen for incoming relayed connections via relay node.
     ListenReq {
+        listener_id: ListenerId,
         relay_peer_id: PeerId,
         relay_addr: Multiaddr,
         to_listener: mpsc::Sender<ToListenerMsg>,
@@ -450,6 +508,8 @@ pub enum ToListenerMsg {
         relay_peer_id: PeerId,
         relay_addr: Multiaddr,
     },
+    /// The reservation has been lost and is being re-established by the behaviour.
+    ReservationLost,
 }
 
 pub struct Reservation {
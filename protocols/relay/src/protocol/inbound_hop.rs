@@ -175,19 +175,20 @@ pub(crate) async fn handle_inbound_request(
 ) -> Result<Either<ReservationReq, CircuitReq>, Error> {
     let mut substream = Framed::new(io, quick_protobuf_codec::Codec::new(MAX_MESSAGE_SIZE));
 
-    let res = substream.next().await;
-
-    if let None | Some(Err(_)) = res {
-        return Err(Error::StreamClosed);
-    }
-
     let proto::HopMessage {
         type_pb,
         peer,
         reservation: _,
         limit: _,
         status: _,
-    } = res.unwrap().expect("should be ok");
+    } = match substream.next().await {
+        Some(Ok(msg)) => msg,
+        Some(Err(e)) => {
+            reject(&mut substream, proto::Status::MALFORMED_MESSAGE).await;
+            return Err(Error::Codec(e));
+        }
+        None => return Err(Error::StreamClosed),
+    };
 
     let req = match type_pb {
         Type::RESERVE => Either::Left(ReservationReq {
@@ -197,17 +198,45 @@ pub(crate) async fn handle_inbound_request(
             max_circuit_bytes,
         }),
         Type::CONNECT => {
-            let peer_id_res = match peer {
-                Some(r) => PeerId::from_bytes(&r.id),
-                None => return Err(Error::MissingPeer),
+            let dst = match peer.map(|p| PeerId::from_bytes(&p.id)) {
+                Some(Ok(dst)) => dst,
+                Some(Err(_)) => {
+                    reject(&mut substream, proto::Status::MALFORMED_MESSAGE).await;
+                    return Err(Error::ParsePeerId);
+                }
+                None => {
+                    reject(&mut substream, proto::Status::MALFORMED_MESSAGE).await;
+                    return Err(Error::MissingPeer);
+                }
             };
 
-            let dst = peer_id_res.map_err(|_| Error::ParsePeerId)?;
-
             Either::Right(CircuitReq { dst, substream })
         }
-        Type::STATUS => return Err(Error::UnexpectedTypeStatus),
+        Type::STATUS => {
+            reject(&mut substream, proto::Status::UNEXPECTED_MESSAGE).await;
+            return Err(Error::UnexpectedTypeStatus);
+        }
     };
 
     Ok(req)
 }
+
+/// Answers a malformed or unexpected request with the given status.
+///
+/// Failing to do so is ignored, as the request fails regardless.
+async fn reject(
+    substream: &mut Framed<Stream, quick_protobuf_codec::Codec<proto::HopMessage>>,
+    status: proto::Status,
+) {
+    let msg = proto::HopMessage {
+        type_pb: proto::HopMessageType::STATUS,
+        peer: None,
+        reservation: None,
+        limit: None,
+        status: Some(status),
+    };
+
+    if substream.send(msg).await.is_ok() {
+        let _ = substream.close().await;
+    }
+}
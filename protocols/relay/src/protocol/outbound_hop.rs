@@ -45,6 +45,10 @@ pub enum ConnectError {
     NoReservation,
     #[error("Remote denied permission.")]
     PermissionDenied,
+    #[error("Remote reported our message as malformed.")]
+    MalformedMessage,
+    #[error("Remote reported our message as unexpected.")]
+    UnexpectedMessage,
     #[error("Remote does not support the `{HOP_PROTOCOL_NAME}` protocol")]
     Unsupported,
     #[error("IO error")]
@@ -53,12 +57,37 @@ pub enum ConnectError {
     Protocol(#[from] ProtocolViolation),
 }
 
+impl ConnectError {
+    /// The reason the relay reported for denying the circuit, if any.
+    pub fn reason(&self) -> Option<CircuitFailedReason> {
+        let reason = match self {
+            ConnectError::ResourceLimitExceeded => CircuitFailedReason::ResourceLimitExceeded,
+            ConnectError::ConnectionFailed => CircuitFailedReason::ConnectionFailed,
+            ConnectError::NoReservation => CircuitFailedReason::NoReservation,
+            ConnectError::PermissionDenied => CircuitFailedReason::PermissionDenied,
+            ConnectError::MalformedMessage => CircuitFailedReason::MalformedMessage,
+            ConnectError::UnexpectedMessage => CircuitFailedReason::UnexpectedMessage,
+            ConnectError::Unsupported | ConnectError::Io(_) | ConnectError::Protocol(_) => {
+                return None
+            }
+        };
+
+        Some(reason)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ReserveError {
     #[error("Reservation refused.")]
     Refused,
     #[error("Remote reported resource limit exceeded.")]
     ResourceLimitExceeded,
+    #[error("Remote denied permission.")]
+    PermissionDenied,
+    #[error("Remote reported our message as malformed.")]
+    MalformedMessage,
+    #[error("Remote reported our message as unexpected.")]
+    UnexpectedMessage,
     #[error("Remote does not support the `{HOP_PROTOCOL_NAME}` protocol")]
     Unsupported,
     #[error("IO error")]
@@ -67,6 +96,59 @@ pub enum ReserveError {
     Protocol(#[from] ProtocolViolation),
 }
 
+impl ReserveError {
+    /// The reason the relay reported for refusing the reservation, if any.
+    pub fn reason(&self) -> Option<ReservationFailedReason> {
+        let reason = match self {
+            ReserveError::Refused => ReservationFailedReason::Refused,
+            ReserveError::ResourceLimitExceeded => ReservationFailedReason::ResourceLimitExceeded,
+            ReserveError::PermissionDenied => ReservationFailedReason::PermissionDenied,
+            ReserveError::MalformedMessage => ReservationFailedReason::MalformedMessage,
+            ReserveError::UnexpectedMessage => ReservationFailedReason::UnexpectedMessage,
+            ReserveError::Unsupported | ReserveError::Io(_) | ReserveError::Protocol(_) => {
+                return None
+            }
+        };
+
+        Some(reason)
+    }
+}
+
+/// The `STATUS` a relay responded with when refusing a reservation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservationFailedReason {
+    /// The relay refused the reservation, e.g. because it doesn't accept reservations of our
+    /// address.
+    Refused,
+    /// The relay is at capacity. Another relay may accept the reservation.
+    ResourceLimitExceeded,
+    /// The relay doesn't allow us to make reservations. Another relay may accept the
+    /// reservation.
+    PermissionDenied,
+    /// The relay considers our request malformed.
+    MalformedMessage,
+    /// The relay didn't expect our request.
+    UnexpectedMessage,
+}
+
+/// The `STATUS` a relay responded with when refusing to establish a circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitFailedReason {
+    /// The relay is at capacity, or the limits for circuits of the source or destination are
+    /// exhausted. Another relay may succeed.
+    ResourceLimitExceeded,
+    /// The relay failed to connect to the destination, which is likely unreachable.
+    ConnectionFailed,
+    /// The destination has no reservation on the relay. It may be reachable via another relay.
+    NoReservation,
+    /// The relay doesn't allow us to establish circuits. Another relay may succeed.
+    PermissionDenied,
+    /// The relay considers our request malformed.
+    MalformedMessage,
+    /// The relay didn't expect our request.
+    UnexpectedMessage,
+}
+
 #[derive(Debug, Error)]
 pub enum ProtocolViolation {
     #[error(transparent)]
@@ -162,6 +244,15 @@ pub(crate) async fn make_reservation(stream: Stream) -> Result<Reservation, Rese
         proto::Status::RESOURCE_LIMIT_EXCEEDED => {
             return Err(ReserveError::ResourceLimitExceeded);
         }
+        proto::Status::PERMISSION_DENIED => {
+            return Err(ReserveError::PermissionDenied);
+        }
+        proto::Status::MALFORMED_MESSAGE => {
+            return Err(ReserveError::MalformedMessage);
+        }
+        proto::Status::UNEXPECTED_MESSAGE => {
+            return Err(ReserveError::UnexpectedMessage);
+        }
         s => {
             return Err(ReserveError::Protocol(ProtocolViolation::UnexpectedStatus(
                 s,
@@ -267,6 +358,12 @@ pub(crate) async fn open_circuit(
         Some(proto::Status::PERMISSION_DENIED) => {
             return Err(ConnectError::PermissionDenied);
         }
+        Some(proto::Status::MALFORMED_MESSAGE) => {
+            return Err(ConnectError::MalformedMessage);
+        }
+        Some(proto::Status::UNEXPECTED_MESSAGE) => {
+            return Err(ConnectError::UnexpectedMessage);
+        }
         Some(s) => {
             return Err(ConnectError::Protocol(ProtocolViolation::UnexpectedStatus(
                 s,
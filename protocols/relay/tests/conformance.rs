@@ -0,0 +1,70 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_relay as relay;
+use libp2p_relay::conformance::{Check, Expectations, Suite};
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+fn config() -> relay::Config {
+    relay::Config {
+        reservation_duration: Duration::from_secs(1),
+        max_reservations: 2,
+        max_circuits: 2,
+        max_circuit_bytes: 1 << 10,
+        ..Default::default()
+    }
+}
+
+fn suite() -> Suite {
+    Suite::new(Expectations::from(&config()), |peer_id| {
+        relay::Behaviour::new(peer_id, config())
+    })
+}
+
+#[test]
+fn behaviour_conforms_to_specification() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    if let Err(violations) = futures::executor::block_on(suite().run()) {
+        panic!("Relay violates the specification: {violations:#?}");
+    }
+}
+
+#[test]
+fn detects_wrong_expectations() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let suite = Suite::new(
+        Expectations {
+            max_reservations: 3,
+            ..Expectations::from(&config())
+        },
+        |peer_id| relay::Behaviour::new(peer_id, config()),
+    );
+
+    let violation = futures::executor::block_on(suite.run_check(Check::ReservationLimit))
+        .expect_err("relay to deny the third reservation");
+    assert_eq!(violation.check, Check::ReservationLimit);
+}
@@ -18,8 +18,9 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use futures::channel::oneshot;
 use futures::executor::LocalPool;
-use futures::future::FutureExt;
+use futures::future::{self, BoxFuture, FutureExt};
 use futures::io::{AsyncRead, AsyncWrite};
 use futures::stream::StreamExt;
 use futures::task::Spawn;
@@ -160,6 +161,7 @@ fn new_reservation_to_same_relay_replaces_old() {
                 SwarmEvent::NewListenAddr {
                     address,
                     listener_id,
+                    ..
                 } => {
                     assert_eq!(address, client_addr_with_peer_id);
                     assert_eq!(listener_id, new_listener);
@@ -169,7 +171,7 @@ fn new_reservation_to_same_relay_replaces_old() {
                         break;
                     }
                 }
-                SwarmEvent::ExternalAddrConfirmed { address } => {
+                SwarmEvent::ExternalAddrConfirmed { address, .. } => {
                     assert_eq!(
                         address,
                         client_addr.clone().with(Protocol::P2p(client_peer_id))
@@ -226,6 +228,110 @@ fn connect() {
     ));
 }
 
+#[test]
+fn report_inbound_relayed_connection_lifecycle() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+
+    assert!(pool.run_until(wait_for_dial(&mut dst, relay_peer_id)));
+
+    pool.run_until(wait_for_reservation(
+        &mut dst,
+        dst_addr.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+
+    let mut src = build_client();
+    let src_peer_id = *src.local_peer_id();
+
+    src.dial(dst_addr).unwrap();
+
+    let (_, connection_id) = pool.run_until(future::join(
+        connection_established_to(&mut src, relay_peer_id, dst_peer_id),
+        async {
+            let mut established = None;
+            loop {
+                match dst.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished {
+                        peer_id,
+                        connection_id,
+                        ..
+                    } if peer_id == src_peer_id => established = Some(connection_id),
+                    SwarmEvent::Behaviour(ClientEvent::Relay(
+                        relay::client::Event::InboundRelayedConnectionEstablished {
+                            connection_id,
+                            src_peer_id: peer_id,
+                            relay_peer_id: relay,
+                            limit,
+                        },
+                    )) => {
+                        assert_eq!(established, Some(connection_id));
+                        assert_eq!(peer_id, src_peer_id);
+                        assert_eq!(relay, relay_peer_id);
+                        assert!(limit.is_some());
+                        break connection_id;
+                    }
+                    _ => {}
+                }
+            }
+        },
+    ));
+
+    let relayed = dst
+        .behaviour()
+        .relay
+        .relayed_connection(&connection_id)
+        .expect("connection to be relayed");
+    assert_eq!(relayed.src_peer_id, src_peer_id);
+    assert_eq!(relayed.relay_peer_id, relay_peer_id);
+
+    src.disconnect_peer_id(dst_peer_id).unwrap();
+    spawn_swarm_on_pool(&pool, src);
+
+    pool.run_until(async {
+        loop {
+            if let SwarmEvent::Behaviour(ClientEvent::Relay(
+                relay::client::Event::InboundRelayedConnectionClosed {
+                    connection_id: closed,
+                    src_peer_id: peer_id,
+                    relay_peer_id: relay,
+                },
+            )) = dst.select_next_some().await
+            {
+                assert_eq!(closed, connection_id);
+                assert_eq!(peer_id, src_peer_id);
+                assert_eq!(relay, relay_peer_id);
+                break;
+            }
+        }
+    });
+    assert!(dst
+        .behaviour()
+        .relay
+        .relayed_connection(&connection_id)
+        .is_none());
+}
+
 async fn connection_established_to(
     swarm: &mut Swarm<Client>,
     relay_peer_id: PeerId,
@@ -394,6 +500,291 @@ fn propagate_connect_error_to_unknown_peer_to_dialer() {
     ));
 }
 
+#[test]
+fn report_failure_reasons_to_behaviour() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(relay::Config {
+        max_reservations: 0,
+        ..relay::Config::default()
+    });
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let mut client = build_client();
+
+    let reservation_listener = client
+        .listen_on(
+            relay_addr
+                .clone()
+                .with(Protocol::P2p(relay_peer_id))
+                .with(Protocol::P2pCircuit),
+        )
+        .unwrap();
+
+    let (listener_id, reason) = pool.run_until(client.wait(|e| match e {
+        SwarmEvent::Behaviour(ClientEvent::Relay(relay::client::Event::ReservationReqFailed {
+            listener_id,
+            reason,
+            ..
+        })) => Some((listener_id, reason)),
+        _ => None,
+    }));
+    assert_eq!(listener_id, reservation_listener);
+    assert_eq!(
+        reason,
+        Some(relay::outbound::hop::ReservationFailedReason::ResourceLimitExceeded)
+    );
+
+    let mut src = build_client();
+    src.dial(
+        relay_addr
+            .with(Protocol::P2p(relay_peer_id))
+            .with(Protocol::P2pCircuit)
+            .with(Protocol::P2p(PeerId::random())),
+    )
+    .unwrap();
+
+    let reason = pool.run_until(src.wait(|e| match e {
+        SwarmEvent::Behaviour(ClientEvent::Relay(
+            relay::client::Event::OutboundCircuitReqFailed { reason, .. },
+        )) => Some(reason),
+        _ => None,
+    }));
+    assert_eq!(
+        reason,
+        Some(relay::outbound::hop::CircuitFailedReason::NoReservation)
+    );
+}
+
+#[test]
+fn report_denial_reasons_on_relay() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(relay::Config {
+        max_reservations: 0,
+        ..relay::Config::default()
+    });
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+
+    let mut client = build_client();
+    client
+        .listen_on(
+            relay_addr
+                .clone()
+                .with(Protocol::P2p(relay_peer_id))
+                .with(Protocol::P2pCircuit),
+        )
+        .unwrap();
+    spawn_swarm_on_pool(&pool, client);
+
+    let reason = pool.run_until(relay.wait(|e| match e {
+        SwarmEvent::Behaviour(RelayEvent::Relay(relay::Event::ReservationReqDenied {
+            reason,
+            ..
+        })) => Some(reason),
+        _ => None,
+    }));
+    assert_eq!(reason, relay::DenialReason::LimitExceeded);
+
+    let mut src = build_client();
+    src.dial(
+        relay_addr
+            .with(Protocol::P2p(relay_peer_id))
+            .with(Protocol::P2pCircuit)
+            .with(Protocol::P2p(PeerId::random())),
+    )
+    .unwrap();
+    spawn_swarm_on_pool(&pool, src);
+
+    let reason = pool.run_until(relay.wait(|e| match e {
+        SwarmEvent::Behaviour(RelayEvent::Relay(relay::Event::CircuitReqDenied {
+            reason, ..
+        })) => Some(reason),
+        _ => None,
+    }));
+    assert_eq!(reason, relay::DenialReason::NoReservation);
+}
+
+#[test]
+fn deny_circuits_to_filtered_destinations() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(
+        relay::Config::default().destination_filter(|addr| !addr.to_string().contains("memory")),
+    );
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+
+    let mut dst = build_client();
+    let dst_peer_id = *dst.local_peer_id();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+
+    dst.listen_on(dst_addr.clone()).unwrap();
+    spawn_swarm_on_pool(&pool, dst);
+
+    let mut src = build_client();
+    pool.run_until(relay.wait(|e| match e {
+        SwarmEvent::Behaviour(RelayEvent::Relay(relay::Event::ReservationReqAccepted {
+            ..
+        })) => Some(()),
+        _ => None,
+    }));
+
+    src.dial(dst_addr).unwrap();
+    spawn_swarm_on_pool(&pool, src);
+
+    let reason = pool.run_until(relay.wait(|e| match e {
+        SwarmEvent::Behaviour(RelayEvent::Relay(relay::Event::CircuitReqDenied {
+            reason, ..
+        })) => Some(reason),
+        _ => None,
+    }));
+    assert_eq!(reason, relay::DenialReason::DestinationDenied);
+}
+
+#[test]
+fn admission_policy_denies_requests() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(
+        relay::Config::default()
+            .admission_policy(Policy {
+                reservations: Some(false),
+                circuits: None,
+            })
+            .admission_timeout(Duration::from_millis(100)),
+    );
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+
+    let mut client = build_client();
+    client
+        .listen_on(
+            relay_addr
+                .clone()
+                .with(Protocol::P2p(relay_peer_id))
+                .with(Protocol::P2pCircuit),
+        )
+        .unwrap();
+
+    let reason = pool.run_until(async {
+        loop {
+            futures::select! {
+                e = relay.select_next_some() => {
+                    if let SwarmEvent::Behaviour(RelayEvent::Relay(
+                        relay::Event::ReservationReqDenied { reason, .. },
+                    )) = e
+                    {
+                        assert_eq!(reason, relay::DenialReason::AdmissionDenied);
+                    }
+                }
+                e = client.select_next_some() => {
+                    if let SwarmEvent::Behaviour(ClientEvent::Relay(
+                        relay::client::Event::ReservationReqFailed { reason, .. },
+                    )) = e
+                    {
+                        break reason;
+                    }
+                }
+            }
+        }
+    });
+    assert_eq!(
+        reason,
+        Some(relay::outbound::hop::ReservationFailedReason::PermissionDenied)
+    );
+    spawn_swarm_on_pool(&pool, client);
+
+    let mut src = build_client();
+    src.dial(
+        relay_addr
+            .with(Protocol::P2p(relay_peer_id))
+            .with(Protocol::P2pCircuit)
+            .with(Protocol::P2p(PeerId::random())),
+    )
+    .unwrap();
+    spawn_swarm_on_pool(&pool, src);
+
+    // The policy never decides on circuits, thus they are denied after the timeout.
+    let reason = pool.run_until(relay.wait(|e| match e {
+        SwarmEvent::Behaviour(RelayEvent::Relay(relay::Event::CircuitReqDenied {
+            reason, ..
+        })) => Some(reason),
+        _ => None,
+    }));
+    assert_eq!(reason, relay::DenialReason::AdmissionTimedOut);
+}
+
+#[test]
+fn admit_on_admission_timeout() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay_with_config(
+        relay::Config::default()
+            .admission_policy(Policy {
+                reservations: None,
+                circuits: None,
+            })
+            .admission_timeout(Duration::from_millis(100))
+            .admit_on_timeout(true),
+    );
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let client_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit);
+    let mut client = build_client();
+    let client_peer_id = *client.local_peer_id();
+
+    client.listen_on(client_addr.clone()).unwrap();
+
+    assert!(pool.run_until(wait_for_dial(&mut client, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut client,
+        client_addr.with(Protocol::P2p(client_peer_id)),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+}
+
 #[test]
 fn reuse_connection() {
     let _ = tracing_subscriber::fmt()
@@ -433,6 +824,233 @@ fn reuse_connection() {
     ));
 }
 
+#[test]
+fn listen_via_multiple_relays() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let good_relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut good_relay = build_relay();
+    let good_relay_peer_id = *good_relay.local_peer_id();
+    good_relay.listen_on(good_relay_addr.clone()).unwrap();
+    good_relay.add_external_address(good_relay_addr.clone());
+    spawn_swarm_on_pool(&pool, good_relay);
+
+    let full_relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut full_relay = build_relay_with_config(relay::Config {
+        max_reservations: 0, // Will make us fail to make the reservation
+        ..relay::Config::default()
+    });
+    let full_relay_peer_id = *full_relay.local_peer_id();
+    full_relay.listen_on(full_relay_addr.clone()).unwrap();
+    full_relay.add_external_address(full_relay_addr.clone());
+    spawn_swarm_on_pool(&pool, full_relay);
+
+    let mut client = build_client();
+    let client_peer_id = *client.local_peer_id();
+    let good_client_addr = good_relay_addr
+        .with(Protocol::P2p(good_relay_peer_id))
+        .with(Protocol::P2pCircuit);
+    let full_client_addr = full_relay_addr
+        .with(Protocol::P2p(full_relay_peer_id))
+        .with(Protocol::P2pCircuit);
+
+    let good_listener = client.listen_on(good_client_addr.clone()).unwrap();
+    let full_listener = client.listen_on(full_client_addr).unwrap();
+
+    // Wait for the reservation on the good relay to be accepted and the one on the full relay to
+    // close its listener only.
+    pool.run_until(async {
+        let mut reservation_accepted = false;
+        let mut full_listener_closed = false;
+        loop {
+            match client.select_next_some().await {
+                SwarmEvent::Behaviour(ClientEvent::Relay(
+                    relay::client::Event::ReservationReqAccepted {
+                        relay_peer_id,
+                        listener_id,
+                        renewal: false,
+                        ..
+                    },
+                )) => {
+                    assert_eq!(relay_peer_id, good_relay_peer_id);
+                    assert_eq!(listener_id, good_listener);
+                    reservation_accepted = true;
+                }
+                SwarmEvent::ListenerClosed {
+                    listener_id,
+                    reason,
+                    ..
+                } => {
+                    assert_eq!(listener_id, full_listener);
+                    assert!(reason.is_err());
+                    full_listener_closed = true;
+                }
+                SwarmEvent::ListenerError { listener_id, .. } => {
+                    panic!("Unexpected error of listener {listener_id:?}")
+                }
+                _ => {}
+            }
+            if reservation_accepted && full_listener_closed {
+                break;
+            }
+        }
+    });
+
+    assert_eq!(
+        client.behaviour().relay.reservations().collect::<Vec<_>>(),
+        vec![(
+            good_relay_peer_id,
+            good_listener,
+            &good_client_addr.with(Protocol::P2p(client_peer_id))
+        )]
+    );
+}
+
+#[test]
+fn recover_reservation_after_relay_restart() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_key = identity::Keypair::generate_ed25519();
+    let relay_peer_id = relay_key.public().to_peer_id();
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let spawner = pool.spawner();
+    // Runs a relay until the returned sender is dropped, freeing its listen address.
+    let start_relay = || {
+        let mut relay = build_relay_with_key(relay_key.clone(), relay::Config::default());
+        let listener = relay.listen_on(relay_addr.clone()).unwrap();
+        relay.add_external_address(relay_addr.clone());
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        spawner
+            .spawn_obj(
+                async move {
+                    let _ = future::select(stop_rx, relay.by_ref().for_each(|_| future::ready(())))
+                        .await;
+                    relay.remove_listener(listener);
+                }
+                .boxed()
+                .into(),
+            )
+            .unwrap();
+        stop_tx
+    };
+    let stop_relay = start_relay();
+
+    let mut client = build_client_with(
+        Config::with_async_std_executor(),
+        relay::client::Config {
+            recover_reservations: true,
+            recovery_initial_backoff: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+    let client_peer_id = *client.local_peer_id();
+    let client_addr = relay_addr
+        .clone()
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit);
+    let client_addr_with_peer_id = client_addr.clone().with(Protocol::P2p(client_peer_id));
+
+    let listener = client.listen_on(client_addr).unwrap();
+    assert!(pool.run_until(wait_for_dial(&mut client, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut client,
+        client_addr_with_peer_id.clone(),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+
+    drop(stop_relay);
+    pool.run_until(async {
+        let mut lost = false;
+        let mut expired = false;
+        while !(lost && expired) {
+            match client.select_next_some().await {
+                SwarmEvent::Behaviour(ClientEvent::Relay(
+                    relay::client::Event::ReservationLost { listener_id, .. },
+                )) => {
+                    assert_eq!(listener_id, listener);
+                    lost = true;
+                }
+                SwarmEvent::ExpiredListenAddr {
+                    listener_id,
+                    address,
+                    ..
+                } => {
+                    assert_eq!(listener_id, listener);
+                    assert_eq!(address, client_addr_with_peer_id);
+                    expired = true;
+                }
+                SwarmEvent::ListenerClosed { .. } => panic!("Listener should stay open"),
+                _ => {}
+            }
+        }
+    });
+
+    let _stop_relay = start_relay();
+    pool.run_until(async {
+        let mut accepted = false;
+        let mut reported = false;
+        while !(accepted && reported) {
+            match client.select_next_some().await {
+                SwarmEvent::Behaviour(ClientEvent::Relay(
+                    relay::client::Event::ReservationReqAccepted {
+                        listener_id,
+                        renewal: false,
+                        ..
+                    },
+                )) => {
+                    assert_eq!(listener_id, listener);
+                    accepted = true;
+                }
+                SwarmEvent::NewListenAddr {
+                    listener_id,
+                    address,
+                    ..
+                } => {
+                    assert_eq!(listener_id, listener);
+                    assert_eq!(address, client_addr_with_peer_id);
+                    reported = true;
+                }
+                SwarmEvent::Behaviour(ClientEvent::Relay(
+                    relay::client::Event::ReservationRecoveryFailed { .. },
+                ))
+                | SwarmEvent::ListenerClosed { .. } => panic!("Reservation should be recovered"),
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Admission policy answering all reservation, respectively circuit requests with the given
+/// decision, or never deciding on them if `None`.
+struct Policy {
+    reservations: Option<bool>,
+    circuits: Option<bool>,
+}
+
+impl relay::AdmissionPolicy for Policy {
+    fn admit_reservation(&mut self, _: PeerId, _: &Multiaddr) -> BoxFuture<'static, bool> {
+        decide(self.reservations)
+    }
+
+    fn admit_circuit(&mut self, _: PeerId, _: &Multiaddr, _: PeerId) -> BoxFuture<'static, bool> {
+        decide(self.circuits)
+    }
+}
+
+fn decide(decision: Option<bool>) -> BoxFuture<'static, bool> {
+    match decision {
+        Some(decision) => future::ready(decision).boxed(),
+        None => future::pending().boxed(),
+    }
+}
+
 fn build_relay() -> Swarm<Relay> {
     build_relay_with_config(relay::Config {
         reservation_duration: Duration::from_secs(2),
@@ -441,7 +1059,10 @@ fn build_relay() -> Swarm<Relay> {
 }
 
 fn build_relay_with_config(config: relay::Config) -> Swarm<Relay> {
-    let local_key = identity::Keypair::generate_ed25519();
+    build_relay_with_key(identity::Keypair::generate_ed25519(), config)
+}
+
+fn build_relay_with_key(local_key: identity::Keypair, config: relay::Config) -> Swarm<Relay> {
     let local_peer_id = local_key.public().to_peer_id();
 
     let transport = upgrade_transport(MemoryTransport::default().boxed(), &local_key);
@@ -462,10 +1083,14 @@ fn build_client() -> Swarm<Client> {
 }
 
 fn build_client_with_config(config: Config) -> Swarm<Client> {
+    build_client_with(config, relay::client::Config::default())
+}
+
+fn build_client_with(config: Config, relay_config: relay::client::Config) -> Swarm<Client> {
     let local_key = identity::Keypair::generate_ed25519();
     let local_peer_id = local_key.public().to_peer_id();
 
-    let (relay_transport, behaviour) = relay::client::new(local_peer_id);
+    let (relay_transport, behaviour) = relay::client::new_with_config(local_peer_id, relay_config);
     let transport = upgrade_transport(
         OrTransport::new(relay_transport, MemoryTransport::default()).boxed(),
         &local_key,
@@ -527,7 +1152,7 @@ async fn wait_for_reservation(
 
     loop {
         match client.select_next_some().await {
-            SwarmEvent::ExternalAddrConfirmed { address } if !is_renewal => {
+            SwarmEvent::ExternalAddrConfirmed { address, .. } if !is_renewal => {
                 assert_eq!(address, client_addr);
             }
             SwarmEvent::Behaviour(ClientEvent::Relay(
@@ -19,7 +19,9 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::codec::Message::*;
-use crate::codec::{Cookie, ErrorCode, Message, Namespace, NewRegistration, Registration, Ttl};
+use crate::codec::{
+    Cookie, ErrorCode, Message, Namespace, NamespaceToken, NewRegistration, Registration, Ttl,
+};
 use futures::future::BoxFuture;
 use futures::future::FutureExt;
 use futures::stream::FuturesUnordered;
@@ -51,6 +53,9 @@ pub struct Behaviour {
 
     registered_namespaces: HashMap<(PeerId, Namespace), Ttl>,
 
+    /// Tokens attached to registrations in protected namespaces, see [`Behaviour::register_with_token`].
+    namespace_tokens: HashMap<(PeerId, Namespace), NamespaceToken>,
+
     /// Tracks the expiry of registrations that we have discovered and stored in `discovered_peers` otherwise we have a memory leak.
     expiring_registrations: FuturesUnordered<BoxFuture<'static, (PeerId, Namespace)>>,
 
@@ -71,6 +76,7 @@ impl Behaviour {
             waiting_for_discovery: Default::default(),
             discovered_peers: Default::default(),
             registered_namespaces: Default::default(),
+            namespace_tokens: Default::default(),
             expiring_registrations: FuturesUnordered::from_iter(vec![
                 futures::future::pending().boxed()
             ]),
@@ -94,20 +100,46 @@ impl Behaviour {
         }
 
         let peer_record = PeerRecord::new(&self.keypair, external_addresses)?;
-        let req_id = self.inner.send_request(
-            &rendezvous_node,
-            Register(NewRegistration::new(namespace.clone(), peer_record, ttl)),
-        );
+        let mut registration = NewRegistration::new(namespace.clone(), peer_record, ttl);
+        if let Some(token) = self
+            .namespace_tokens
+            .get(&(rendezvous_node, namespace.clone()))
+        {
+            registration = registration.with_token(token.clone());
+        }
+        let req_id = self
+            .inner
+            .send_request(&rendezvous_node, Register(registration));
         self.waiting_for_register
             .insert(req_id, (rendezvous_node, namespace));
 
         Ok(())
     }
 
+    /// Register our external addresses in the given protected namespace with the given rendezvous peer.
+    ///
+    /// The token must be issued by the owner of the namespace for the local peer, see [`NamespaceToken::new`].
+    /// It is attached to all subsequent registrations in this namespace with this rendezvous peer, including
+    /// refreshes upon a change in external addresses, until [`Behaviour::unregister`] is called.
+    pub fn register_with_token(
+        &mut self,
+        namespace: Namespace,
+        rendezvous_node: PeerId,
+        ttl: Option<Ttl>,
+        token: NamespaceToken,
+    ) -> Result<(), RegisterError> {
+        self.namespace_tokens
+            .insert((rendezvous_node, namespace.clone()), token);
+
+        self.register(namespace, rendezvous_node, ttl)
+    }
+
     /// Unregister ourselves from the given namespace with the given rendezvous peer.
     pub fn unregister(&mut self, namespace: Namespace, rendezvous_node: PeerId) {
         self.registered_namespaces
             .retain(|(rz_node, ns), _| rz_node.ne(&rendezvous_node) && ns.ne(&namespace));
+        self.namespace_tokens
+            .remove(&(rendezvous_node, namespace.clone()));
 
         self.inner
             .send_request(&rendezvous_node, Unregister(namespace));
@@ -274,6 +306,9 @@ impl NetworkBehaviour for Behaviour {
                 Poll::Ready(ToSwarm::GenerateEvent(
                     req_res::Event::InboundFailure { .. }
                     | req_res::Event::ResponseSent { .. }
+                    | req_res::Event::InboundRequestDropped { .. }
+                    | req_res::Event::StoredRequest { .. }
+                    | req_res::Event::Subscription { .. }
                     | req_res::Event::Message {
                         message: req_res::Message::Request { .. },
                         ..
@@ -24,6 +24,7 @@ use asynchronous_codec::{BytesMut, Decoder, Encoder};
 use asynchronous_codec::{FramedRead, FramedWrite};
 use futures::{AsyncRead, AsyncWrite, SinkExt, StreamExt};
 use libp2p_core::{peer_record, signed_envelope, PeerRecord, SignedEnvelope};
+use libp2p_identity::{Keypair, PeerId, PublicKey, SigningError};
 use libp2p_swarm::StreamProtocol;
 use quick_protobuf_codec::Codec as ProtobufCodec;
 use rand::RngCore;
@@ -167,11 +168,107 @@ impl Cookie {
 #[error("The cookie was malformed")]
 pub struct InvalidCookie;
 
+const TOKEN_PAYLOAD_TYPE: &str = "/libp2p/rendezvous-namespace-token";
+const TOKEN_DOMAIN_SEP: &str = "libp2p-rendezvous-namespace-token";
+
+/// A token authorizing a peer to register in a protected namespace.
+///
+/// Tokens are issued by the owner of the namespace, i.e. signed with the key configured via
+/// [`server::Config::with_protected_namespace`](crate::server::Config::with_protected_namespace),
+/// and attached to registrations via [`client::Behaviour::register_with_token`](crate::client::Behaviour::register_with_token).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceToken {
+    namespace: Namespace,
+    peer: PeerId,
+    owner: PublicKey,
+    envelope: SignedEnvelope,
+}
+
+impl NamespaceToken {
+    /// Issues a token authorizing `peer` to register in `namespace`, signed with the `owner`'s key.
+    pub fn new(owner: &Keypair, namespace: Namespace, peer: PeerId) -> Result<Self, SigningError> {
+        let peer_bytes = peer.to_bytes();
+        let mut payload = Vec::with_capacity(1 + peer_bytes.len() + namespace.0.len());
+        payload.push(peer_bytes.len() as u8);
+        payload.extend_from_slice(&peer_bytes);
+        payload.extend_from_slice(namespace.0.as_bytes());
+
+        let envelope = SignedEnvelope::new(
+            owner,
+            String::from(TOKEN_DOMAIN_SEP),
+            TOKEN_PAYLOAD_TYPE.as_bytes().to_vec(),
+            payload,
+        )?;
+
+        Ok(Self {
+            namespace,
+            peer,
+            owner: owner.public(),
+            envelope,
+        })
+    }
+
+    /// The namespace this token grants access to.
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    /// The peer that is authorized to register.
+    pub fn peer(&self) -> PeerId {
+        self.peer
+    }
+
+    /// The key of the namespace owner that issued this token.
+    pub fn owner(&self) -> &PublicKey {
+        &self.owner
+    }
+
+    pub fn into_wire_encoding(self) -> Vec<u8> {
+        self.envelope.into_protobuf_encoding()
+    }
+
+    /// Decodes a token, verifying its signature.
+    pub fn from_wire_encoding(bytes: &[u8]) -> Result<Self, InvalidNamespaceToken> {
+        let envelope =
+            SignedEnvelope::from_protobuf_encoding(bytes).map_err(|_| InvalidNamespaceToken)?;
+        let (payload, owner) = envelope
+            .payload_and_signing_key(
+                String::from(TOKEN_DOMAIN_SEP),
+                TOKEN_PAYLOAD_TYPE.as_bytes(),
+            )
+            .map_err(|_| InvalidNamespaceToken)?;
+
+        let (&peer_len, rest) = payload.split_first().ok_or(InvalidNamespaceToken)?;
+        if rest.len() < peer_len as usize {
+            return Err(InvalidNamespaceToken);
+        }
+        let (peer, namespace) = rest.split_at(peer_len as usize);
+        let peer = PeerId::from_bytes(peer).map_err(|_| InvalidNamespaceToken)?;
+        let namespace = String::from_utf8(namespace.to_vec())
+            .ok()
+            .and_then(|ns| Namespace::new(ns).ok())
+            .ok_or(InvalidNamespaceToken)?;
+        let owner = owner.clone();
+
+        Ok(Self {
+            namespace,
+            peer,
+            owner,
+            envelope,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("The namespace token was malformed or its signature invalid")]
+pub struct InvalidNamespaceToken;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct NewRegistration {
     pub namespace: Namespace,
     pub record: PeerRecord,
     pub ttl: Option<u64>,
+    pub token: Option<NamespaceToken>,
 }
 
 impl NewRegistration {
@@ -180,9 +277,15 @@ impl NewRegistration {
             namespace,
             record,
             ttl,
+            token: None,
         }
     }
 
+    pub fn with_token(mut self, token: NamespaceToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
     pub fn effective_ttl(&self) -> Ttl {
         self.ttl.unwrap_or(DEFAULT_TTL)
     }
@@ -327,12 +430,14 @@ impl From<Message> for proto::Message {
                 namespace,
                 record,
                 ttl,
+                token,
             }) => proto::Message {
                 type_pb: Some(proto::MessageType::REGISTER),
                 register: Some(proto::Register {
                     ns: Some(namespace.into()),
                     ttl,
                     signedPeerRecord: Some(record.into_signed_envelope().into_protobuf_encoding()),
+                    authToken: token.map(NamespaceToken::into_wire_encoding),
                 }),
                 registerResponse: None,
                 unregister: None,
@@ -401,6 +506,7 @@ impl From<Message> for proto::Message {
                             signedPeerRecord: Some(
                                 reggo.record.into_signed_envelope().into_protobuf_encoding(),
                             ),
+                            authToken: None,
                         })
                         .collect(),
                     status: Some(proto::ResponseStatus::OK),
@@ -441,6 +547,7 @@ impl TryFrom<proto::Message> for Message {
                         ns,
                         ttl,
                         signedPeerRecord: Some(signed_peer_record),
+                        authToken: token,
                     }),
                 ..
             } => Message::Register(NewRegistration {
@@ -452,6 +559,9 @@ impl TryFrom<proto::Message> for Message {
                 record: PeerRecord::from_signed_envelope(SignedEnvelope::from_protobuf_encoding(
                     &signed_peer_record,
                 )?)?,
+                token: token
+                    .map(|token| NamespaceToken::from_wire_encoding(&token))
+                    .transpose()?,
             }),
             proto::Message {
                 type_pb: Some(proto::MessageType::REGISTER_RESPONSE),
@@ -561,6 +671,8 @@ pub enum ConversionError {
     BadSignedPeerRecord(#[from] peer_record::FromEnvelopeError),
     #[error(transparent)]
     BadCookie(#[from] InvalidCookie),
+    #[error(transparent)]
+    BadNamespaceToken(#[from] InvalidNamespaceToken),
     #[error("The requested PoW difficulty is out of range")]
     PoWDifficultyOutOfRange,
     #[error("The provided PoW hash is not 32 bytes long")]
@@ -575,6 +687,7 @@ impl ConversionError {
             ConversionError::BadSignedEnvelope(_) => ErrorCode::InvalidSignedPeerRecord,
             ConversionError::BadSignedPeerRecord(_) => ErrorCode::InvalidSignedPeerRecord,
             ConversionError::BadCookie(_) => ErrorCode::InvalidCookie,
+            ConversionError::BadNamespaceToken(_) => ErrorCode::NotAuthorized,
             ConversionError::MissingTtl => ErrorCode::InvalidTtl,
             ConversionError::InconsistentWireMessage => ErrorCode::InternalError,
             ConversionError::BadStatusCode => ErrorCode::InternalError,
@@ -652,6 +765,19 @@ mod tests {
         assert_eq!(parsed, cookie);
     }
 
+    #[test]
+    fn namespace_token_wire_encoding_roundtrip() {
+        let owner = Keypair::generate_ed25519();
+        let token =
+            NamespaceToken::new(&owner, Namespace::from_static("foo"), PeerId::random()).unwrap();
+
+        let bytes = token.clone().into_wire_encoding();
+        let parsed = NamespaceToken::from_wire_encoding(&bytes).unwrap();
+
+        assert_eq!(parsed, token);
+        assert_eq!(parsed.owner(), &owner.public());
+    }
+
     #[test]
     fn cookie_wire_encoding_length() {
         let cookie = Cookie::for_namespace(Namespace::from_static("foo"));
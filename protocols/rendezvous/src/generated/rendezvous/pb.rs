@@ -75,6 +75,7 @@ pub struct Register {
     pub ns: Option<String>,
     pub signedPeerRecord: Option<Vec<u8>>,
     pub ttl: Option<u64>,
+    pub authToken: Option<Vec<u8>>,
 }
 
 impl<'a> MessageRead<'a> for Register {
@@ -85,6 +86,7 @@ impl<'a> MessageRead<'a> for Register {
                 Ok(10) => msg.ns = Some(r.read_string(bytes)?.to_owned()),
                 Ok(18) => msg.signedPeerRecord = Some(r.read_bytes(bytes)?.to_owned()),
                 Ok(24) => msg.ttl = Some(r.read_uint64(bytes)?),
+                Ok(34) => msg.authToken = Some(r.read_bytes(bytes)?.to_owned()),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -99,12 +101,14 @@ impl MessageWrite for Register {
         + self.ns.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
         + self.signedPeerRecord.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
         + self.ttl.as_ref().map_or(0, |m| 1 + sizeof_varint(*(m) as u64))
+        + self.authToken.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
         if let Some(ref s) = self.ns { w.write_with_tag(10, |w| w.write_string(&**s))?; }
         if let Some(ref s) = self.signedPeerRecord { w.write_with_tag(18, |w| w.write_bytes(&**s))?; }
         if let Some(ref s) = self.ttl { w.write_with_tag(24, |w| w.write_uint64(*s))?; }
+        if let Some(ref s) = self.authToken { w.write_with_tag(34, |w| w.write_bytes(&**s))?; }
         Ok(())
     }
 }
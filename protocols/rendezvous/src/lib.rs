@@ -22,7 +22,10 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
-pub use self::codec::{Cookie, ErrorCode, Namespace, NamespaceTooLong, Registration, Ttl};
+pub use self::codec::{
+    Cookie, ErrorCode, InvalidNamespaceToken, Namespace, NamespaceToken, NamespaceTooLong,
+    Registration, Ttl,
+};
 use libp2p_swarm::StreamProtocol;
 
 mod codec;
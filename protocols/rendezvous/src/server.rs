@@ -25,7 +25,7 @@ use futures::future::BoxFuture;
 use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
 use libp2p_core::{Endpoint, Multiaddr};
-use libp2p_identity::PeerId;
+use libp2p_identity::{PeerId, PublicKey};
 use libp2p_request_response::ProtocolSupport;
 use libp2p_swarm::behaviour::FromSwarm;
 use libp2p_swarm::{
@@ -46,6 +46,7 @@ pub struct Behaviour {
 pub struct Config {
     min_ttl: Ttl,
     max_ttl: Ttl,
+    protected_namespaces: HashMap<Namespace, PublicKey>,
 }
 
 impl Config {
@@ -58,6 +59,16 @@ impl Config {
         self.max_ttl = max_ttl;
         self
     }
+
+    /// Requires registrations in `namespace` to carry a [`NamespaceToken`](crate::NamespaceToken)
+    /// issued by `owner`.
+    ///
+    /// Registrations without a valid token are declined with [`ErrorCode::NotAuthorized`].
+    /// Discovery of the namespace remains unrestricted.
+    pub fn with_protected_namespace(mut self, namespace: Namespace, owner: PublicKey) -> Self {
+        self.protected_namespaces.insert(namespace, owner);
+        self
+    }
 }
 
 impl Default for Config {
@@ -65,6 +76,7 @@ impl Default for Config {
         Self {
             min_ttl: MIN_TTL,
             max_ttl: MAX_TTL,
+            protected_namespaces: HashMap::new(),
         }
     }
 }
@@ -205,6 +217,15 @@ impl NetworkBehaviour for Behaviour {
                     ToSwarm::GenerateEvent(libp2p_request_response::Event::ResponseSent {
                         ..
                     })
+                    | ToSwarm::GenerateEvent(libp2p_request_response::Event::StoredRequest {
+                        ..
+                    })
+                    | ToSwarm::GenerateEvent(libp2p_request_response::Event::Subscription {
+                        ..
+                    })
+                    | ToSwarm::GenerateEvent(
+                        libp2p_request_response::Event::InboundRequestDropped { .. },
+                    )
                     | ToSwarm::GenerateEvent(libp2p_request_response::Event::Message {
                         peer: _,
                         message: libp2p_request_response::Message::Response { .. },
@@ -239,7 +260,9 @@ fn handle_request(
 ) -> Option<(Event, Option<Message>)> {
     match message {
         Message::Register(registration) => {
-            if registration.record.peer_id() != peer_id {
+            if registration.record.peer_id() != peer_id
+                || !registrations.is_authorized(&registration)
+            {
                 let error = ErrorCode::NotAuthorized;
 
                 let event = Event::PeerNotRegistered {
@@ -342,6 +365,7 @@ pub struct Registrations {
     cookies: HashMap<Cookie, HashSet<RegistrationId>>,
     min_ttl: Ttl,
     max_ttl: Ttl,
+    protected_namespaces: HashMap<Namespace, PublicKey>,
     next_expiry: FuturesUnordered<BoxFuture<'static, RegistrationId>>,
 }
 
@@ -366,11 +390,25 @@ impl Registrations {
             registrations: Default::default(),
             min_ttl: config.min_ttl,
             max_ttl: config.max_ttl,
+            protected_namespaces: config.protected_namespaces,
             cookies: Default::default(),
             next_expiry: FuturesUnordered::from_iter(vec![futures::future::pending().boxed()]),
         }
     }
 
+    /// Checks whether the registration carries a valid token if its namespace is protected.
+    fn is_authorized(&self, registration: &NewRegistration) -> bool {
+        let Some(owner) = self.protected_namespaces.get(&registration.namespace) else {
+            return true;
+        };
+
+        registration.token.as_ref().is_some_and(|token| {
+            token.owner() == owner
+                && token.namespace() == &registration.namespace
+                && token.peer() == registration.record.peer_id()
+        })
+    }
+
     pub fn add(
         &mut self,
         new_registration: NewRegistration,
@@ -635,6 +673,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 0,
             max_ttl: 4,
+            ..Config::default()
         });
 
         let start_time = SystemTime::now();
@@ -671,6 +710,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 1,
             max_ttl: 10,
+            ..Config::default()
         });
         let dummy_registration = new_dummy_registration_with_ttl("foo", 2);
         let namespace = dummy_registration.namespace.clone();
@@ -693,6 +733,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 0,
             max_ttl: 10,
+            ..Config::default()
         });
         let dummy_registration = new_dummy_registration_with_ttl("foo", 1);
 
@@ -710,6 +751,7 @@ mod tests {
         let mut registrations = Registrations::with_config(Config {
             min_ttl: 1,
             max_ttl: 10,
+            ..Config::default()
         });
 
         registrations
@@ -252,6 +252,52 @@ async fn given_invalid_ttl_then_unsuccessful_registration() {
     }
 }
 
+#[tokio::test]
+async fn protected_namespace_requires_token() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let namespace = rendezvous::Namespace::from_static("some-namespace");
+    let owner = identity::Keypair::generate_ed25519();
+    let ([mut alice, mut bob], mut robert) = new_server_with_connected_clients(
+        rendezvous::server::Config::default()
+            .with_protected_namespace(namespace.clone(), owner.public()),
+    )
+    .await;
+
+    bob.behaviour_mut()
+        .register(namespace.clone(), *robert.local_peer_id(), None)
+        .unwrap();
+
+    match libp2p_swarm_test::drive(&mut bob, &mut robert).await {
+        (
+            [rendezvous::client::Event::RegisterFailed { error, .. }],
+            [rendezvous::server::Event::PeerNotRegistered { .. }],
+        ) => {
+            assert_eq!(error, rendezvous::ErrorCode::NotAuthorized);
+        }
+        events => panic!("Unexpected events: {events:?}"),
+    }
+
+    let token =
+        rendezvous::NamespaceToken::new(&owner, namespace.clone(), *alice.local_peer_id()).unwrap();
+    alice
+        .behaviour_mut()
+        .register_with_token(namespace.clone(), *robert.local_peer_id(), None, token)
+        .unwrap();
+
+    match libp2p_swarm_test::drive(&mut alice, &mut robert).await {
+        (
+            [rendezvous::client::Event::Registered { .. }],
+            [rendezvous::server::Event::PeerRegistered { peer, registration }],
+        ) => {
+            assert_eq!(&peer, alice.local_peer_id());
+            assert_eq!(registration.namespace, namespace);
+        }
+        events => panic!("Unexpected events: {events:?}"),
+    }
+}
+
 #[tokio::test]
 async fn discover_allows_for_dial_by_peer_id() {
     let _ = tracing_subscriber::fmt()
@@ -0,0 +1,301 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Accounting of the bytes held by in-flight requests and responses across all connections,
+//! see [`Config::with_memory_budget`](crate::Config::with_memory_budget).
+
+use futures::prelude::*;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// The budget shared by all handlers of a [`Behaviour`](crate::Behaviour).
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    limit: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The bytes currently accounted.
+    used: usize,
+    next_id: u64,
+    /// The inbound requests which are still being read and can thus be shed.
+    reads: HashMap<u64, Read>,
+}
+
+#[derive(Debug)]
+struct Read {
+    priority: u8,
+    bytes: usize,
+    shed: bool,
+    waker: Option<Waker>,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Returns the bytes currently accounted.
+    pub(crate) fn used(&self) -> usize {
+        self.state.lock().unwrap().used
+    }
+
+    /// Starts accounting an inbound request that is about to be read.
+    ///
+    /// Until [`Reservation::settle`] is called, the request is shed if the budget is exceeded
+    /// and it has the lowest priority of all requests being read. Among requests of the same
+    /// priority, the most recent one is shed first.
+    pub(crate) fn request(self: &Arc<Self>, priority: u8) -> Reservation {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.reads.insert(
+            id,
+            Read {
+                priority,
+                bytes: 0,
+                shed: false,
+                waker: None,
+            },
+        );
+
+        Reservation {
+            budget: self.clone(),
+            id,
+            bytes: 0,
+        }
+    }
+
+    /// Starts accounting a response to an outbound request, which is never shed.
+    pub(crate) fn response(self: &Arc<Self>) -> Reservation {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        Reservation {
+            budget: self.clone(),
+            id,
+            bytes: 0,
+        }
+    }
+}
+
+impl State {
+    /// Sheds requests being read until the accounted bytes are within `limit`, or there are no
+    /// more requests to shed.
+    fn enforce(&mut self, limit: usize) {
+        while self.used > limit {
+            let Some((id, read)) = self
+                .reads
+                .iter_mut()
+                .filter(|(_, r)| !r.shed)
+                .min_by_key(|(id, r)| (r.priority, Reverse(**id)))
+            else {
+                return;
+            };
+            tracing::debug!(
+                request=%id,
+                priority=%read.priority,
+                bytes=%read.bytes,
+                "Shedding inbound request, memory budget exceeded"
+            );
+            read.shed = true;
+            self.used -= read.bytes;
+            read.bytes = 0;
+            if let Some(waker) = read.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The bytes accounted for a single request or response, released on drop.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+    budget: Arc<MemoryBudget>,
+    id: u64,
+    /// The bytes accounted once the reservation can no longer be shed.
+    bytes: usize,
+}
+
+impl Reservation {
+    /// Accounts `n` more bytes, shedding requests if the budget is exceeded.
+    ///
+    /// Fails if this reservation has been shed.
+    pub(crate) fn grow(&mut self, n: usize) -> io::Result<()> {
+        let mut state = self.budget.state.lock().unwrap();
+        match state.reads.get_mut(&self.id) {
+            Some(read) if read.shed => return Err(shed_error()),
+            Some(read) => read.bytes += n,
+            None => self.bytes += n,
+        }
+        state.used += n;
+        state.enforce(self.budget.limit);
+
+        match state.reads.get(&self.id) {
+            Some(read) if read.shed => Err(shed_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether this reservation has been shed.
+    pub(crate) fn is_shed(&self) -> bool {
+        let state = self.budget.state.lock().unwrap();
+        state.reads.get(&self.id).map_or(false, |r| r.shed)
+    }
+
+    /// Polls whether this reservation has been shed, waking the task once it is.
+    fn poll_shed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.budget.state.lock().unwrap();
+        match state.reads.get_mut(&self.id) {
+            Some(read) if read.shed => Poll::Ready(()),
+            Some(read) => {
+                read.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    /// Keeps the accounted bytes, but stops the request from being shed, e.g. once it has
+    /// been read and passed on.
+    pub(crate) fn settle(&mut self) {
+        let mut state = self.budget.state.lock().unwrap();
+        if let Some(read) = state.reads.remove(&self.id) {
+            self.bytes += read.bytes;
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut state = self.budget.state.lock().unwrap_or_else(|e| e.into_inner());
+        let read_bytes = state.reads.remove(&self.id).map_or(0, |r| r.bytes);
+        state.used -= read_bytes + self.bytes;
+    }
+}
+
+fn shed_error() -> io::Error {
+    io::Error::new(io::ErrorKind::OutOfMemory, "Memory budget exceeded")
+}
+
+/// Accounts the bytes read from `io` on the given reservation, if any.
+///
+/// Reading fails as soon as the reservation is shed. The bytes read when it is shed are still
+/// returned, so callers keep track of what has been consumed from `io`, and the next read fails.
+pub(crate) struct BudgetedRead<'a, T> {
+    io: &'a mut T,
+    reservation: Option<&'a mut Reservation>,
+}
+
+impl<'a, T> BudgetedRead<'a, T> {
+    pub(crate) fn new(io: &'a mut T, reservation: Option<&'a mut Reservation>) -> Self {
+        Self { io, reservation }
+    }
+}
+
+impl<T> AsyncRead for BudgetedRead<'_, T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(reservation) = this.reservation.as_deref_mut() else {
+            return Pin::new(&mut *this.io).poll_read(cx, buf);
+        };
+        if reservation.poll_shed(cx).is_ready() {
+            return Poll::Ready(Err(shed_error()));
+        }
+
+        let n = futures::ready!(Pin::new(&mut *this.io).poll_read(cx, buf))?;
+        let _ = reservation.grow(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheds_lowest_priority_request_first() {
+        let budget = Arc::new(MemoryBudget::new(100));
+
+        let mut low = budget.request(0);
+        let mut high = budget.request(2);
+        let mut medium = budget.request(1);
+        low.grow(40).unwrap();
+        high.grow(40).unwrap();
+        medium.grow(10).unwrap();
+        assert_eq!(budget.used(), 90);
+
+        medium.grow(20).unwrap();
+        assert!(low.is_shed());
+        assert_eq!(budget.used(), 70);
+        assert!(low.grow(1).is_err());
+
+        // The request exceeding the budget is shed itself if it has the lowest priority.
+        medium.grow(40).unwrap_err();
+        assert!(medium.is_shed());
+        assert!(!high.is_shed());
+        assert_eq!(budget.used(), 40);
+
+        drop((low, medium));
+        assert_eq!(budget.used(), 40);
+    }
+
+    #[test]
+    fn settled_requests_and_responses_are_never_shed() {
+        let budget = Arc::new(MemoryBudget::new(100));
+
+        let mut settled = budget.request(0);
+        settled.grow(60).unwrap();
+        settled.settle();
+        let mut reading = budget.request(1);
+        reading.grow(30).unwrap();
+
+        let mut response = budget.response();
+        response.grow(50).unwrap();
+        assert!(reading.is_shed());
+        assert!(!settled.is_shed());
+
+        // Exceeding the budget is tolerated if there is nothing left to shed.
+        assert_eq!(budget.used(), 110);
+
+        drop(settled);
+        drop(response);
+        assert_eq!(budget.used(), 0);
+    }
+}
@@ -18,16 +18,26 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+mod persistent;
 pub(crate) mod protocol;
+mod subscription;
 
 pub use protocol::ProtocolSupport;
 
+use crate::budget::{BudgetedRead, MemoryBudget, Reservation};
 use crate::codec::Codec;
-use crate::handler::protocol::Protocol;
-use crate::{InboundRequestId, OutboundRequestId, EMPTY_QUEUE_SHRINK_THRESHOLD};
+use crate::handler::persistent::{Direction, InboundContext};
+use crate::handler::protocol::{Protocol, StreamKind};
+use crate::{
+    Config, InboundAdmission, InboundRequestId, OutboundFailure, OutboundRequestId, StreamMode,
+    EMPTY_QUEUE_SHRINK_THRESHOLD,
+};
 
 use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
 use futures::{channel::oneshot, prelude::*};
+use libp2p_identity::PeerId;
 use libp2p_swarm::handler::{
     ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
     ListenUpgradeError,
@@ -38,21 +48,94 @@ use libp2p_swarm::{
 };
 use smallvec::SmallVec;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, HashSet, VecDeque},
     fmt, io,
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     task::{Context, Poll},
     time::Duration,
 };
 
+/// The hook deciding whether an inbound request is admitted, see
+/// [`Behaviour::with_inbound_filter`](super::Behaviour::with_inbound_filter).
+pub(crate) type InboundFilter<TCodec> = Arc<
+    dyn Fn(
+            &PeerId,
+            &<TCodec as Codec>::Protocol,
+            usize,
+        ) -> InboundAdmission<<TCodec as Codec>::Response>
+        + Send
+        + Sync,
+>;
+
+/// The hook assigning a priority to an inbound request, see
+/// [`Behaviour::with_inbound_priority`](super::Behaviour::with_inbound_priority).
+pub(crate) type InboundPriority<TCodec> =
+    Arc<dyn Fn(&PeerId, &<TCodec as Codec>::Protocol) -> u8 + Send + Sync>;
+
+/// The hooks and limits applied to inbound requests, shared by all handlers of a
+/// [`Behaviour`](super::Behaviour).
+pub(crate) struct InboundPolicy<TCodec>
+where
+    TCodec: Codec,
+{
+    pub(crate) filter: Option<InboundFilter<TCodec>>,
+    pub(crate) priority: Option<InboundPriority<TCodec>>,
+    pub(crate) memory_budget: Option<Arc<MemoryBudget>>,
+}
+
+impl<TCodec> InboundPolicy<TCodec>
+where
+    TCodec: Codec,
+{
+    /// Decides whether to admit an inbound request, given the number of pending inbound
+    /// requests of the connection.
+    pub(crate) fn admit(
+        &self,
+        peer: &PeerId,
+        protocol: &TCodec::Protocol,
+        pending: usize,
+    ) -> InboundAdmission<TCodec::Response> {
+        match self.filter.as_ref() {
+            Some(filter) => filter(peer, protocol, pending),
+            None => InboundAdmission::Accept,
+        }
+    }
+
+    /// Starts accounting an inbound request against the memory budget, if any.
+    pub(crate) fn reserve(
+        &self,
+        peer: &PeerId,
+        protocol: &TCodec::Protocol,
+    ) -> Option<Reservation> {
+        let budget = self.memory_budget.as_ref()?;
+        let priority = self.priority.as_ref().map_or(0, |p| p(peer, protocol));
+        Some(budget.request(priority))
+    }
+}
+
+impl<TCodec> Clone for InboundPolicy<TCodec>
+where
+    TCodec: Codec,
+{
+    fn clone(&self) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            priority: self.priority.clone(),
+            memory_budget: self.memory_budget.clone(),
+        }
+    }
+}
+
 /// A connection handler for a request response [`Behaviour`](super::Behaviour) protocol.
 pub struct Handler<TCodec>
 where
     TCodec: Codec,
 {
+    /// The peer this connection is established with.
+    remote_peer_id: PeerId,
     /// The supported inbound protocols.
     inbound_protocols: SmallVec<[TCodec::Protocol; 2]>,
     /// The request/response message codec.
@@ -79,6 +162,58 @@ where
     inbound_request_id: Arc<AtomicU64>,
 
     worker_streams: futures_bounded::FuturesMap<RequestId, Result<Event<TCodec>, io::Error>>,
+    /// The number of inbound requests in `worker_streams` and on persistent streams.
+    pending_inbound_requests: Arc<AtomicUsize>,
+    /// Decides whether inbound requests are admitted and accounts them once they are.
+    inbound_policy: InboundPolicy<TCodec>,
+
+    /// The timeout for inbound and outbound requests.
+    request_timeout: Duration,
+    /// The maximum size of the payload of frames on persistent and subscription streams.
+    max_frame_size: usize,
+    /// Whether persistent streams are supported, see [`StreamMode`](crate::StreamMode).
+    persistent: bool,
+    /// The persistent stream for outbound requests.
+    persistent_outbound: PersistentOutbound<TCodec>,
+    /// The outbound requests sent over the persistent stream, awaiting a response.
+    persistent_requests: HashSet<OutboundRequestId>,
+    /// The tasks serving the persistent streams.
+    persistent_streams: FuturesUnordered<BoxFuture<'static, (Direction, io::Result<()>)>>,
+    /// The events reported by the tasks serving persistent and subscription streams.
+    stream_events: mpsc::Receiver<Event<TCodec>>,
+    /// The [`mpsc::Sender`] for the above receiver. Cloned for each persistent and subscription
+    /// stream.
+    stream_event_sender: mpsc::Sender<Event<TCodec>>,
+
+    /// Whether inbound subscriptions are accepted, see [`Config::with_subscriptions`].
+    accept_subscriptions: bool,
+    /// The upper bound for the number of concurrent subscription streams.
+    max_subscriptions: usize,
+    /// The tasks serving the subscription streams.
+    subscription_streams: FuturesUnordered<BoxFuture<'static, ()>>,
+    /// The acknowledgements for the open outbound subscriptions. Dropping them cancels the
+    /// subscription.
+    outbound_subscriptions: HashMap<OutboundRequestId, subscription::Acknowledgements>,
+    /// The outbound subscriptions cancelled while their stream was being negotiated.
+    cancelled_subscriptions: HashSet<OutboundRequestId>,
+}
+
+/// The state of the persistent stream for outbound requests.
+enum PersistentOutbound<TCodec>
+where
+    TCodec: Codec,
+{
+    /// The remote does not support persistent streams or they are disabled locally.
+    Unsupported,
+    /// No stream is open, the next outbound request opens one.
+    Idle,
+    /// A stream is being negotiated, outbound requests are queued until it is open.
+    Negotiating,
+    /// The stream is open, sending the requests passed to the channel.
+    Open {
+        protocol: TCodec::Protocol,
+        requests: mpsc::Sender<OutboundMessage<TCodec>>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -92,14 +227,18 @@ where
     TCodec: Codec + Send + Clone + 'static,
 {
     pub(super) fn new(
+        remote_peer_id: PeerId,
         inbound_protocols: SmallVec<[TCodec::Protocol; 2]>,
         codec: TCodec,
-        substream_timeout: Duration,
+        config: &Config,
         inbound_request_id: Arc<AtomicU64>,
-        max_concurrent_streams: usize,
+        inbound_policy: InboundPolicy<TCodec>,
     ) -> Self {
+        let persistent = config.stream_mode == StreamMode::Persistent;
         let (inbound_sender, inbound_receiver) = mpsc::channel(0);
+        let (stream_event_sender, stream_events) = mpsc::channel(0);
         Self {
+            remote_peer_id,
             inbound_protocols,
             codec,
             pending_outbound: VecDeque::new(),
@@ -109,9 +248,28 @@ where
             pending_events: VecDeque::new(),
             inbound_request_id,
             worker_streams: futures_bounded::FuturesMap::new(
-                substream_timeout,
-                max_concurrent_streams,
+                config.request_timeout,
+                config.max_concurrent_streams,
             ),
+            pending_inbound_requests: Arc::new(AtomicUsize::new(0)),
+            inbound_policy,
+            request_timeout: config.request_timeout,
+            max_frame_size: config.max_frame_size,
+            persistent,
+            persistent_outbound: if persistent {
+                PersistentOutbound::Idle
+            } else {
+                PersistentOutbound::Unsupported
+            },
+            persistent_requests: HashSet::new(),
+            persistent_streams: FuturesUnordered::new(),
+            stream_events,
+            stream_event_sender,
+            accept_subscriptions: config.subscriptions,
+            max_subscriptions: config.max_concurrent_streams,
+            subscription_streams: FuturesUnordered::new(),
+            outbound_subscriptions: HashMap::new(),
+            cancelled_subscriptions: HashSet::new(),
         }
     }
 
@@ -123,59 +281,199 @@ where
     fn on_fully_negotiated_inbound(
         &mut self,
         FullyNegotiatedInbound {
-            protocol: (mut stream, protocol),
+            protocol: (mut stream, protocol, kind),
             info: (),
         }: FullyNegotiatedInbound<
             <Self as ConnectionHandler>::InboundProtocol,
             <Self as ConnectionHandler>::InboundOpenInfo,
         >,
     ) {
+        if kind == StreamKind::Subscription {
+            self.on_inbound_subscription(stream, protocol);
+            return;
+        }
+        if kind == StreamKind::Persistent {
+            tracing::debug!(peer=%self.remote_peer_id, "Inbound persistent stream opened");
+            // The stream is long-lived, the connection is kept alive by pending requests instead.
+            stream.ignore_for_keep_alive();
+            let cx = InboundContext {
+                remote_peer_id: self.remote_peer_id,
+                codec: self.codec.clone(),
+                inbound_policy: self.inbound_policy.clone(),
+                pending_inbound_requests: self.pending_inbound_requests.clone(),
+                inbound_request_id: self.inbound_request_id.clone(),
+                inbound_sender: self.inbound_sender.clone(),
+                events: self.stream_event_sender.clone(),
+                timeout: self.request_timeout,
+                max_frame_size: self.max_frame_size,
+            };
+            self.persistent_streams.push(
+                persistent::inbound(stream, protocol, cx)
+                    .map(|result| (Direction::Inbound, result))
+                    .boxed(),
+            );
+            return;
+        }
+
         let mut codec = self.codec.clone();
         let request_id = self.next_inbound_request_id();
+
+        let admission = self.inbound_policy.admit(
+            &self.remote_peer_id,
+            &protocol,
+            self.pending_inbound_requests.load(Ordering::Relaxed),
+        );
+        let response = match admission {
+            InboundAdmission::Accept => None,
+            InboundAdmission::Reject => {
+                tracing::debug!(%request_id, "Inbound request rejected before reading it");
+                self.pending_events
+                    .push_back(Event::InboundRejected(request_id));
+                return;
+            }
+            InboundAdmission::Respond(response) => Some(response),
+        };
+
+        if let Some(response) = response {
+            tracing::debug!(%request_id, "Inbound request answered before reading it");
+            let send = async move {
+                let write = codec.write_response(&protocol, &mut stream, response);
+                write.await?;
+                stream.close().await?;
+                Ok(Event::InboundRejected(request_id))
+            };
+            if self
+                .worker_streams
+                .try_push(RequestId::Inbound(request_id), send.boxed())
+                .is_err()
+            {
+                tracing::warn!("Dropping inbound stream because we are at capacity");
+                self.pending_events
+                    .push_back(Event::InboundDropped(request_id));
+            } else {
+                self.pending_inbound_requests
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            return;
+        }
+
         let mut sender = self.inbound_sender.clone();
+        // Held until the response is sent, as the request is kept in memory until then.
+        let mut reservation = self.inbound_policy.reserve(&self.remote_peer_id, &protocol);
 
         let recv = async move {
             // A channel for notifying the inbound upgrade when the
             // response is sent.
             let (rs_send, rs_recv) = oneshot::channel();
 
-            let read = codec.read_request(&protocol, &mut stream);
-            let request = read.await?;
+            let mut io = BudgetedRead::new(&mut stream, reservation.as_mut());
+            let read = codec.read_request(&protocol, &mut io);
+            let request = match read.await {
+                _ if reservation.as_ref().map_or(false, Reservation::is_shed) => {
+                    return Ok(Event::InboundShed(request_id));
+                }
+                Ok(request) => request,
+                Err(e) => return Err(e),
+            };
+            if let Some(reservation) = reservation.as_mut() {
+                reservation.settle();
+            }
             sender
                 .send((request_id, request, rs_send))
                 .await
                 .expect("`ConnectionHandler` owns both ends of the channel");
             drop(sender);
 
-            if let Ok(response) = rs_recv.await {
+            let event = if let Ok(response) = rs_recv.await {
                 let write = codec.write_response(&protocol, &mut stream, response);
                 write.await?;
 
                 stream.close().await?;
-                Ok(Event::ResponseSent(request_id))
+                Event::ResponseSent(request_id)
             } else {
                 stream.close().await?;
-                Ok(Event::ResponseOmission(request_id))
-            }
+                Event::ResponseOmission(request_id)
+            };
+            drop(reservation);
+            Ok(event)
         };
 
-        // Inbound connections are reported to the upper layer from within the above task,
-        // so by failing to schedule it, it means the upper layer will never know about the
-        // inbound request. Because of that we do not report any inbound failure.
+        // Inbound requests are reported to the upper layer from within the above task,
+        // so by failing to schedule it, the upper layer never learns about the request.
+        // Because of that we report it as dropped rather than as an inbound failure.
         if self
             .worker_streams
             .try_push(RequestId::Inbound(request_id), recv.boxed())
             .is_err()
         {
-            tracing::warn!("Dropping inbound stream because we are at capacity")
+            tracing::warn!("Dropping inbound stream because we are at capacity");
+            self.pending_events
+                .push_back(Event::InboundDropped(request_id));
+        } else {
+            self.pending_inbound_requests
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_inbound_subscription(
+        &mut self,
+        stream: libp2p_swarm::Stream,
+        protocol: TCodec::Protocol,
+    ) {
+        let request_id = self.next_inbound_request_id();
+
+        let admission = self.inbound_policy.admit(
+            &self.remote_peer_id,
+            &protocol,
+            self.pending_inbound_requests.load(Ordering::Relaxed),
+        );
+        let response = match admission {
+            InboundAdmission::Accept => None,
+            InboundAdmission::Reject => {
+                tracing::debug!(%request_id, "Inbound subscription rejected before reading it");
+                self.pending_events
+                    .push_back(Event::InboundRejected(request_id));
+                return;
+            }
+            InboundAdmission::Respond(response) => {
+                tracing::debug!(%request_id, "Inbound subscription answered before reading it");
+                Some(response)
+            }
+        };
+
+        if self.subscription_streams.len() >= self.max_subscriptions {
+            tracing::warn!("Dropping inbound subscription because we are at capacity");
+            self.pending_events
+                .push_back(Event::InboundDropped(request_id));
+            return;
         }
+
+        self.pending_inbound_requests
+            .fetch_add(1, Ordering::Relaxed);
+        let pending_inbound_requests = self.pending_inbound_requests.clone();
+        self.subscription_streams.push(
+            subscription::inbound(
+                stream,
+                protocol,
+                self.codec.clone(),
+                request_id,
+                response,
+                self.stream_event_sender.clone(),
+                self.request_timeout,
+                self.max_frame_size,
+            )
+            .map(move |()| {
+                pending_inbound_requests.fetch_sub(1, Ordering::Relaxed);
+            })
+            .boxed(),
+        );
     }
 
     fn on_fully_negotiated_outbound(
         &mut self,
         FullyNegotiatedOutbound {
-            protocol: (mut stream, protocol),
-            info: (),
+            protocol: (mut stream, protocol, kind),
+            info: offered_persistent,
         }: FullyNegotiatedOutbound<
             <Self as ConnectionHandler>::OutboundProtocol,
             <Self as ConnectionHandler>::OutboundOpenInfo,
@@ -186,14 +484,56 @@ where
             .pop_front()
             .expect("negotiated a stream without a pending message");
 
+        if message.subscription {
+            debug_assert_eq!(kind, StreamKind::Subscription);
+            self.on_outbound_subscription(stream, protocol, message);
+            return;
+        }
+        if kind == StreamKind::Persistent {
+            tracing::debug!(peer=%self.remote_peer_id, "Outbound persistent stream opened");
+            // The stream is long-lived, the connection is kept alive by pending requests instead.
+            stream.ignore_for_keep_alive();
+            let (requests, receiver) = mpsc::channel(0);
+            self.persistent_streams.push(
+                persistent::outbound(
+                    stream,
+                    protocol.clone(),
+                    self.codec.clone(),
+                    receiver,
+                    self.stream_event_sender.clone(),
+                    self.request_timeout,
+                    self.max_frame_size,
+                    self.inbound_policy.memory_budget.clone(),
+                )
+                .map(|result| (Direction::Outbound, result))
+                .boxed(),
+            );
+            self.persistent_outbound = PersistentOutbound::Open { protocol, requests };
+            self.send_persistent(message);
+            return;
+        }
+        if offered_persistent {
+            tracing::debug!(
+                peer=%self.remote_peer_id,
+                "Remote does not support persistent streams"
+            );
+            self.persistent_outbound = PersistentOutbound::Unsupported;
+        }
+
         let mut codec = self.codec.clone();
         let request_id = message.request_id;
+        let mut reservation = self
+            .inbound_policy
+            .memory_budget
+            .as_ref()
+            .map(MemoryBudget::response);
 
         let send = async move {
             let write = codec.write_request(&protocol, &mut stream, message.request);
             write.await?;
             stream.close().await?;
-            let read = codec.read_response(&protocol, &mut stream);
+            let mut io = BudgetedRead::new(&mut stream, reservation.as_mut());
+            let read = codec.read_response(&protocol, &mut io);
             let response = read.await?;
 
             Ok(Event::Response {
@@ -214,9 +554,78 @@ where
         }
     }
 
+    fn on_outbound_subscription(
+        &mut self,
+        stream: libp2p_swarm::Stream,
+        protocol: TCodec::Protocol,
+        message: OutboundMessage<TCodec>,
+    ) {
+        let request_id = message.request_id;
+        if self.cancelled_subscriptions.remove(&request_id) {
+            tracing::debug!(%request_id, "Dropping stream of cancelled subscription");
+            return;
+        }
+        if self.subscription_streams.len() >= self.max_subscriptions {
+            self.pending_events
+                .push_back(Event::OutboundSubscriptionFailed {
+                    request_id,
+                    error: OutboundFailure::Io(io::Error::new(
+                        io::ErrorKind::Other,
+                        "max sub-streams reached",
+                    )),
+                });
+            return;
+        }
+
+        let (acks, receiver) = subscription::acknowledgements();
+        self.outbound_subscriptions.insert(request_id, acks);
+        self.subscription_streams.push(
+            subscription::outbound(
+                stream,
+                protocol,
+                self.codec.clone(),
+                request_id,
+                message.request,
+                receiver,
+                self.stream_event_sender.clone(),
+                self.max_frame_size,
+            )
+            .boxed(),
+        );
+    }
+
+    /// Acknowledges the updates of an outbound subscription up to and including `seq`.
+    fn acknowledge_update(&mut self, request_id: OutboundRequestId, seq: u64) {
+        match self.outbound_subscriptions.get_mut(&request_id) {
+            Some(acks) => acks.acknowledge(seq),
+            None => {
+                tracing::debug!(%request_id, "Ignoring acknowledgement for unknown subscription");
+            }
+        }
+    }
+
+    /// Cancels an outbound subscription, whether its stream is open or not.
+    fn cancel_subscription(&mut self, request_id: OutboundRequestId) {
+        // Dropping the acknowledgements makes the task cancel the subscription.
+        if self.outbound_subscriptions.remove(&request_id).is_some() {
+            return;
+        }
+        let pending = self.pending_outbound.len();
+        self.pending_outbound
+            .retain(|message| message.request_id != request_id);
+        if self.pending_outbound.len() == pending
+            && self
+                .requested_outbound
+                .iter()
+                .any(|message| message.request_id == request_id)
+        {
+            self.cancelled_subscriptions.insert(request_id);
+        }
+    }
+
     fn on_dial_upgrade_error(
         &mut self,
-        DialUpgradeError { error, info: () }: DialUpgradeError<
+        DialUpgradeError { error, info }: DialUpgradeError<
             <Self as ConnectionHandler>::OutboundOpenInfo,
             <Self as ConnectionHandler>::OutboundProtocol,
         >,
@@ -226,6 +635,28 @@ where
             .pop_front()
             .expect("negotiated a stream without a pending message");
 
+        if info {
+            self.persistent_outbound = PersistentOutbound::Idle;
+        }
+
+        if message.subscription {
+            if self.cancelled_subscriptions.remove(&message.request_id) {
+                return;
+            }
+            let error = match error {
+                StreamUpgradeError::Timeout => OutboundFailure::Timeout,
+                StreamUpgradeError::NegotiationFailed => OutboundFailure::UnsupportedProtocols,
+                StreamUpgradeError::Apply(e) => void::unreachable(e),
+                StreamUpgradeError::Io(e) => OutboundFailure::Io(e),
+            };
+            self.pending_events
+                .push_back(Event::OutboundSubscriptionFailed {
+                    request_id: message.request_id,
+                    error,
+                });
+            return;
+        }
+
         match error {
             StreamUpgradeError::Timeout => {
                 self.pending_events
@@ -249,6 +680,73 @@ where
             }
         }
     }
+
+    /// Sends an outbound request over the open persistent stream.
+    fn send_persistent(&mut self, message: OutboundMessage<TCodec>) {
+        let PersistentOutbound::Open { requests, .. } = &mut self.persistent_outbound else {
+            unreachable!("persistent stream is open")
+        };
+        let request_id = message.request_id;
+        if requests.try_send(message).is_err() {
+            // The channel has capacity and the task is dropped only after its completion is
+            // handled in `poll`.
+            self.pending_events.push_back(Event::OutboundStreamFailed {
+                request_id,
+                error: io::Error::new(io::ErrorKind::BrokenPipe, "persistent stream closed"),
+            });
+            return;
+        }
+        self.persistent_requests.insert(request_id);
+    }
+
+    fn on_stream_event(&mut self, event: Event<TCodec>) {
+        match &event {
+            Event::Response { request_id, .. } | Event::OutboundStreamFailed { request_id, .. } => {
+                self.persistent_requests.remove(request_id);
+            }
+            Event::OutboundSubscriptionEnded(request_id)
+            | Event::OutboundSubscriptionFailed { request_id, .. } => {
+                self.outbound_subscriptions.remove(request_id);
+            }
+            _ => {}
+        }
+        self.pending_events.push_back(event);
+    }
+
+    fn on_persistent_stream_closed(&mut self, direction: Direction, result: io::Result<()>) {
+        if let Err(e) = &result {
+            tracing::debug!(
+                peer=%self.remote_peer_id,
+                ?direction,
+                "Persistent stream failed: {e}"
+            );
+        }
+        if direction == Direction::Inbound {
+            return;
+        }
+
+        // Report the responses received before the stream closed prior to failing the rest.
+        while let Ok(Some(event)) = self.stream_events.try_next() {
+            self.on_stream_event(event);
+        }
+        let timed_out = matches!(&result, Err(e) if e.kind() == io::ErrorKind::TimedOut);
+        for request_id in self.persistent_requests.drain() {
+            let event = if timed_out {
+                Event::OutboundTimeout(request_id)
+            } else {
+                Event::OutboundStreamFailed {
+                    request_id,
+                    error: io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "persistent stream closed",
+                    ),
+                }
+            };
+            self.pending_events.push_back(event);
+        }
+        self.persistent_outbound = PersistentOutbound::Idle;
+    }
+
     fn on_listen_upgrade_error(
         &mut self,
         ListenUpgradeError { error, .. }: ListenUpgradeError<
@@ -281,6 +779,14 @@ where
     /// A response to an inbound request was omitted as a result
     /// of dropping the response `sender` of an inbound `Request`.
     ResponseOmission(InboundRequestId),
+    /// An inbound request was not admitted by the inbound filter.
+    InboundRejected(InboundRequestId),
+    /// An inbound stream was dropped without reading the request
+    /// because the handler is at capacity.
+    InboundDropped(InboundRequestId),
+    /// An inbound request was shed while being read because the
+    /// memory budget was exceeded.
+    InboundShed(InboundRequestId),
     /// An outbound request timed out while sending the request
     /// or waiting for the response.
     OutboundTimeout(OutboundRequestId),
@@ -297,6 +803,33 @@ where
         request_id: InboundRequestId,
         error: io::Error,
     },
+    /// A subscription has been received.
+    InboundSubscription {
+        request_id: InboundRequestId,
+        request: TCodec::Request,
+        sender: mpsc::Sender<TCodec::Response>,
+    },
+    /// The subscriber acknowledged the updates up to and including `seq`.
+    InboundSubscriptionAcknowledged {
+        request_id: InboundRequestId,
+        seq: u64,
+    },
+    /// The subscriber cancelled an inbound subscription.
+    InboundSubscriptionCancelled(InboundRequestId),
+    /// An inbound subscription was ended by dropping its `sender`.
+    InboundSubscriptionEnded(InboundRequestId),
+    /// An update of an outbound subscription has been received.
+    OutboundSubscriptionUpdate {
+        request_id: OutboundRequestId,
+        seq: u64,
+        update: TCodec::Response,
+    },
+    /// The publisher ended an outbound subscription.
+    OutboundSubscriptionEnded(OutboundRequestId),
+    OutboundSubscriptionFailed {
+        request_id: OutboundRequestId,
+        error: OutboundFailure,
+    },
 }
 
 impl<TCodec: Codec> fmt::Debug for Event<TCodec> {
@@ -325,6 +858,18 @@ impl<TCodec: Codec> fmt::Debug for Event<TCodec> {
                 .debug_tuple("Event::ResponseOmission")
                 .field(request_id)
                 .finish(),
+            Event::InboundRejected(request_id) => f
+                .debug_tuple("Event::InboundRejected")
+                .field(request_id)
+                .finish(),
+            Event::InboundDropped(request_id) => f
+                .debug_tuple("Event::InboundDropped")
+                .field(request_id)
+                .finish(),
+            Event::InboundShed(request_id) => f
+                .debug_tuple("Event::InboundShed")
+                .field(request_id)
+                .finish(),
             Event::OutboundTimeout(request_id) => f
                 .debug_tuple("Event::OutboundTimeout")
                 .field(request_id)
@@ -347,6 +892,45 @@ impl<TCodec: Codec> fmt::Debug for Event<TCodec> {
                 .field("request_id", &request_id)
                 .field("error", &error)
                 .finish(),
+            Event::InboundSubscription {
+                request_id,
+                request: _,
+                sender: _,
+            } => f
+                .debug_struct("Event::InboundSubscription")
+                .field("request_id", request_id)
+                .finish(),
+            Event::InboundSubscriptionAcknowledged { request_id, seq } => f
+                .debug_struct("Event::InboundSubscriptionAcknowledged")
+                .field("request_id", request_id)
+                .field("seq", seq)
+                .finish(),
+            Event::InboundSubscriptionCancelled(request_id) => f
+                .debug_tuple("Event::InboundSubscriptionCancelled")
+                .field(request_id)
+                .finish(),
+            Event::InboundSubscriptionEnded(request_id) => f
+                .debug_tuple("Event::InboundSubscriptionEnded")
+                .field(request_id)
+                .finish(),
+            Event::OutboundSubscriptionUpdate {
+                request_id,
+                seq,
+                update: _,
+            } => f
+                .debug_struct("Event::OutboundSubscriptionUpdate")
+                .field("request_id", request_id)
+                .field("seq", seq)
+                .finish(),
+            Event::OutboundSubscriptionEnded(request_id) => f
+                .debug_tuple("Event::OutboundSubscriptionEnded")
+                .field(request_id)
+                .finish(),
+            Event::OutboundSubscriptionFailed { request_id, error } => f
+                .debug_struct("Event::OutboundSubscriptionFailed")
+                .field("request_id", &request_id)
+                .field("error", &error)
+                .finish(),
         }
     }
 }
@@ -355,6 +939,8 @@ pub struct OutboundMessage<TCodec: Codec> {
     pub(crate) request_id: OutboundRequestId,
     pub(crate) request: TCodec::Request,
     pub(crate) protocols: SmallVec<[TCodec::Protocol; 2]>,
+    /// Whether the request opens a subscription rather than expecting a single response.
+    pub(crate) subscription: bool,
 }
 
 impl<TCodec> fmt::Debug for OutboundMessage<TCodec>
@@ -366,36 +952,98 @@ where
     }
 }
 
+/// The commands passed from the [`Behaviour`](super::Behaviour) to the [`Handler`].
+pub enum HandlerIn<TCodec: Codec> {
+    /// Send a request or open a subscription.
+    Request(OutboundMessage<TCodec>),
+    /// Acknowledge the updates of an outbound subscription up to and including `seq`.
+    AcknowledgeUpdate {
+        request_id: OutboundRequestId,
+        seq: u64,
+    },
+    /// Cancel an outbound subscription.
+    CancelSubscription(OutboundRequestId),
+}
+
+impl<TCodec> fmt::Debug for HandlerIn<TCodec>
+where
+    TCodec: Codec,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerIn::Request(message) => {
+                f.debug_tuple("HandlerIn::Request").field(message).finish()
+            }
+            HandlerIn::AcknowledgeUpdate { request_id, seq } => f
+                .debug_struct("HandlerIn::AcknowledgeUpdate")
+                .field("request_id", request_id)
+                .field("seq", seq)
+                .finish(),
+            HandlerIn::CancelSubscription(request_id) => f
+                .debug_tuple("HandlerIn::CancelSubscription")
+                .field(request_id)
+                .finish(),
+        }
+    }
+}
+
 impl<TCodec> ConnectionHandler for Handler<TCodec>
 where
     TCodec: Codec + Send + Clone + 'static,
 {
-    type FromBehaviour = OutboundMessage<TCodec>;
+    type FromBehaviour = HandlerIn<TCodec>;
     type ToBehaviour = Event<TCodec>;
     type InboundProtocol = Protocol<TCodec::Protocol>;
     type OutboundProtocol = Protocol<TCodec::Protocol>;
-    type OutboundOpenInfo = ();
+    /// Whether the outbound stream was offered as persistent stream.
+    type OutboundOpenInfo = bool;
     type InboundOpenInfo = ();
 
     fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        let mut kinds = SmallVec::new();
+        if self.persistent {
+            kinds.push(StreamKind::Persistent);
+        }
+        kinds.push(StreamKind::PerRequest);
+        if self.accept_subscriptions {
+            kinds.push(StreamKind::Subscription);
+        }
         SubstreamProtocol::new(
             Protocol {
                 protocols: self.inbound_protocols.clone(),
+                kinds,
             },
             (),
         )
     }
 
-    fn on_behaviour_event(&mut self, request: Self::FromBehaviour) {
-        self.pending_outbound.push_back(request);
+    fn connection_keep_alive(&self) -> bool {
+        // Persistent streams don't keep the connection alive by themselves.
+        !self.persistent_requests.is_empty()
+            || self.pending_inbound_requests.load(Ordering::Relaxed) > 0
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        match event {
+            HandlerIn::Request(request) => self.pending_outbound.push_back(request),
+            HandlerIn::AcknowledgeUpdate { request_id, seq } => {
+                self.acknowledge_update(request_id, seq)
+            }
+            HandlerIn::CancelSubscription(request_id) => self.cancel_subscription(request_id),
+        }
     }
 
     #[tracing::instrument(level = "trace", name = "ConnectionHandler::poll", skip(self, cx))]
     fn poll(
         &mut self,
         cx: &mut Context<'_>,
-    ) -> Poll<ConnectionHandlerEvent<Protocol<TCodec::Protocol>, (), Self::ToBehaviour>> {
-        match self.worker_streams.poll_unpin(cx) {
+    ) -> Poll<ConnectionHandlerEvent<Protocol<TCodec::Protocol>, bool, Self::ToBehaviour>> {
+        let worker_stream = self.worker_streams.poll_unpin(cx);
+        if let Poll::Ready((RequestId::Inbound(_), _)) = worker_stream {
+            self.pending_inbound_requests
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+        match worker_stream {
             Poll::Ready((_, Ok(Ok(event)))) => {
                 return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
             }
@@ -428,7 +1076,18 @@ where
             Poll::Pending => {}
         }
 
-        // Drain pending events that were produced by `worker_streams`.
+        while let Poll::Ready(Some((direction, result))) =
+            self.persistent_streams.poll_next_unpin(cx)
+        {
+            self.on_persistent_stream_closed(direction, result);
+        }
+        while let Poll::Ready(Some(())) = self.subscription_streams.poll_next_unpin(cx) {}
+        while let Poll::Ready(Some(event)) = self.stream_events.poll_next_unpin(cx) {
+            self.on_stream_event(event);
+        }
+
+        // Drain pending events that were produced by `worker_streams`, persistent and
+        // subscription streams.
         if let Some(event) = self.pending_events.pop_front() {
             return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
         } else if self.pending_events.capacity() > EMPTY_QUEUE_SHRINK_THRESHOLD {
@@ -447,12 +1106,56 @@ where
         }
 
         // Emit outbound requests.
-        if let Some(request) = self.pending_outbound.pop_front() {
+        while let Some(request) = self.pending_outbound.pop_front() {
+            if request.subscription {
+                let protocols = request.protocols.clone();
+                self.requested_outbound.push_back(request);
+
+                return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                    protocol: SubstreamProtocol::new(
+                        Protocol {
+                            protocols,
+                            kinds: smallvec::smallvec![StreamKind::Subscription],
+                        },
+                        false,
+                    ),
+                });
+            }
+            let persistent = match &mut self.persistent_outbound {
+                PersistentOutbound::Open { protocol, requests }
+                    if request
+                        .protocols
+                        .iter()
+                        .any(|p| p.as_ref() == protocol.as_ref()) =>
+                {
+                    if requests.poll_ready(cx).is_pending() {
+                        self.pending_outbound.push_front(request);
+                        return Poll::Pending;
+                    }
+                    self.send_persistent(request);
+                    continue;
+                }
+                PersistentOutbound::Negotiating => {
+                    self.pending_outbound.push_front(request);
+                    return Poll::Pending;
+                }
+                PersistentOutbound::Idle => {
+                    self.persistent_outbound = PersistentOutbound::Negotiating;
+                    true
+                }
+                PersistentOutbound::Unsupported | PersistentOutbound::Open { .. } => false,
+            };
             let protocols = request.protocols.clone();
             self.requested_outbound.push_back(request);
 
+            let kinds = if persistent {
+                smallvec::smallvec![StreamKind::Persistent, StreamKind::PerRequest]
+            } else {
+                smallvec::smallvec![StreamKind::PerRequest]
+            };
+
             return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
-                protocol: SubstreamProtocol::new(Protocol { protocols }, ()),
+                protocol: SubstreamProtocol::new(Protocol { protocols, kinds }, persistent),
             });
         }
 
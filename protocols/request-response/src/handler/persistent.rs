@@ -0,0 +1,684 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Pipelining of requests and responses over a single long-lived stream,
+//! see [`StreamMode::Persistent`](crate::StreamMode::Persistent).
+//!
+//! Every request and response is sent as a frame, consisting of the 8-byte big-endian ID of the
+//! request, a 1-byte kind and the 4-byte big-endian length of the payload, followed by the
+//! payload as written by the [`Codec`]. The requesting side assigns ascending IDs starting at 0,
+//! the responding side answers the requests as soon as their responses are available, repeating
+//! their ID. Responses may thus arrive in a different order than the requests were sent.
+//! A response the responding side does not provide is sent as a frame of kind [`NO_RESPONSE`]
+//! without a payload.
+
+use crate::budget::{BudgetedRead, MemoryBudget, Reservation};
+use crate::codec::Codec;
+use crate::handler::{Event, InboundPolicy, OutboundMessage};
+use crate::{InboundAdmission, InboundRequestId};
+
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, BoxFuture, Either};
+use futures::prelude::*;
+use futures::stream::{FusedStream, FuturesUnordered};
+use futures_timer::Delay;
+use libp2p_identity::PeerId;
+use libp2p_swarm::Stream;
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// A frame carrying a request or response.
+const MESSAGE: u8 = 0;
+/// A frame signalling that the request is not answered.
+const NO_RESPONSE: u8 = 1;
+/// The length of the header preceding the payload of a frame.
+const HEADER_LEN: usize = 13;
+/// The maximum number of requests sent, respectively served, before the first of them is
+/// answered.
+const MAX_PIPELINED_REQUESTS: usize = 32;
+
+/// The direction of a persistent stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// The state of the handler shared with the task serving an inbound persistent stream.
+pub(crate) struct InboundContext<TCodec>
+where
+    TCodec: Codec,
+{
+    pub(crate) remote_peer_id: PeerId,
+    pub(crate) codec: TCodec,
+    pub(crate) inbound_policy: InboundPolicy<TCodec>,
+    /// The number of pending inbound requests of the connection.
+    pub(crate) pending_inbound_requests: Arc<AtomicUsize>,
+    pub(crate) inbound_request_id: Arc<AtomicU64>,
+    /// The channel for passing the requests to the handler.
+    pub(crate) inbound_sender: mpsc::Sender<(
+        InboundRequestId,
+        TCodec::Request,
+        oneshot::Sender<TCodec::Response>,
+    )>,
+    /// The channel for reporting the outcome of the requests to the handler.
+    pub(crate) events: mpsc::Sender<Event<TCodec>>,
+    /// How long to wait for the response to a request.
+    pub(crate) timeout: Duration,
+    /// The maximum size of the payload of a frame,
+    /// see [`Config::with_max_frame_size`](crate::Config::with_max_frame_size).
+    pub(crate) max_frame_size: usize,
+}
+
+/// Sends the requests received via `requests` over the given stream, reporting the responses
+/// via `events`.
+///
+/// Fails if the stream fails or a request is not answered within `timeout` of being sent,
+/// leaving the requests which did not receive a response unanswered.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn outbound<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    codec: TCodec,
+    mut requests: mpsc::Receiver<OutboundMessage<TCodec>>,
+    mut events: mpsc::Sender<Event<TCodec>>,
+    timeout: Duration,
+    max_frame_size: usize,
+    memory_budget: Option<Arc<MemoryBudget>>,
+) -> io::Result<()>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let (mut reader, mut writer) = stream.split();
+    // Neither channel holds more than the number of unanswered requests.
+    let (mut in_flight_sender, mut in_flight) = mpsc::channel(MAX_PIPELINED_REQUESTS);
+    let (mut answered_sender, mut answered) = mpsc::channel::<()>(MAX_PIPELINED_REQUESTS);
+
+    let mut write_codec = codec.clone();
+    let write_protocol = protocol.clone();
+    let write = async move {
+        let mut next_id = 0u64;
+        let mut unanswered = 0;
+        while let Some(message) = requests.next().await {
+            while let Ok(Some(())) = answered.try_next() {
+                unanswered -= 1;
+            }
+            if unanswered == MAX_PIPELINED_REQUESTS {
+                // The reader only stops on failure, which aborts writing as well.
+                if answered.next().await.is_none() {
+                    break;
+                }
+                unanswered -= 1;
+            }
+
+            let mut payload = Vec::new();
+            write_codec
+                .write_request(&write_protocol, &mut payload, message.request)
+                .await?;
+
+            // Registered before writing, so the reader knows the request once it is answered.
+            let id = next_id;
+            let deadline = Delay::new(timeout).map(move |()| id).boxed();
+            let _ = in_flight_sender
+                .send((id, message.request_id, deadline))
+                .await;
+            write_frame(&mut writer, next_id, Some(&payload)).await?;
+            next_id += 1;
+            unanswered += 1;
+        }
+
+        drop(in_flight_sender);
+        writer.close().await
+    };
+
+    let mut read_codec = codec;
+    let read = async move {
+        let mut pending = HashMap::new();
+        // The deadlines of the requests, including those answered in the meantime.
+        let mut deadlines = FuturesUnordered::new();
+        loop {
+            if pending.is_empty() {
+                let Some((id, request_id, deadline)) = in_flight.next().await else {
+                    return Ok(());
+                };
+                pending.insert(id, request_id);
+                deadlines.push(deadline);
+            }
+
+            let read_frame = async {
+                let (id, kind, len) = read_header(&mut reader, max_frame_size)
+                    .await?
+                    .ok_or(io::ErrorKind::UnexpectedEof)?;
+                match kind {
+                    MESSAGE => {
+                        // Responses are never shed, but may cause inbound requests to be shed.
+                        let mut reservation = memory_budget.as_ref().map(|b| b.response());
+                        let mut io = BudgetedRead::new(&mut reader, reservation.as_mut());
+                        let payload = read_payload(&mut io, len).await?;
+                        Ok((id, Some((payload, reservation))))
+                    }
+                    NO_RESPONSE if len == 0 => Ok((id, None)),
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid frame")),
+                }
+            };
+            let mut read_frame = Box::pin(read_frame);
+            let (id, payload) = loop {
+                while let Ok(Some((id, request_id, deadline))) = in_flight.try_next() {
+                    pending.insert(id, request_id);
+                    deadlines.push(deadline);
+                }
+                match future::select(&mut read_frame, deadlines.select_next_some()).await {
+                    Either::Left((frame, _)) => break frame?,
+                    Either::Right((id, _)) if pending.contains_key(&id) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "Timed out waiting for a response",
+                        ))
+                    }
+                    // The request was answered in time.
+                    Either::Right(_) => {}
+                }
+            };
+
+            while let Ok(Some((id, request_id, deadline))) = in_flight.try_next() {
+                pending.insert(id, request_id);
+                deadlines.push(deadline);
+            }
+            let Some(request_id) = pending.remove(&id) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Response to unknown request {id}"),
+                ));
+            };
+            let _ = answered_sender.send(()).await;
+
+            let event = match payload {
+                Some((payload, _reservation)) => match read_codec
+                    .read_response(&protocol, &mut payload.as_slice())
+                    .await
+                {
+                    Ok(response) => Event::Response {
+                        request_id,
+                        response,
+                    },
+                    Err(error) => Event::OutboundStreamFailed { request_id, error },
+                },
+                None => Event::OutboundStreamFailed {
+                    request_id,
+                    error: io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Remote did not answer the request",
+                    ),
+                },
+            };
+            let _ = events.send(event).await;
+        }
+    };
+
+    future::try_join(write, read).await.map(|_| ())
+}
+
+/// The outcome of an inbound request, written to the stream once it is available.
+struct Reply<TCodec>
+where
+    TCodec: Codec,
+{
+    id: u64,
+    request_id: InboundRequestId,
+    /// The encoded response, `None` if the request is not answered.
+    payload: Option<Vec<u8>>,
+    /// The event reported once the reply is written.
+    event: Event<TCodec>,
+    /// The bytes held by the request, released once the reply is written.
+    reservation: Option<Reservation>,
+    /// Counts the request as pending until the reply is written.
+    pending: Option<PendingRequest>,
+}
+
+impl<TCodec> Reply<TCodec>
+where
+    TCodec: Codec,
+{
+    fn new(
+        id: u64,
+        request_id: InboundRequestId,
+        payload: Option<Vec<u8>>,
+        event: Event<TCodec>,
+    ) -> Self {
+        Self {
+            id,
+            request_id,
+            payload,
+            event,
+            reservation: None,
+            pending: None,
+        }
+    }
+}
+
+/// Counts an inbound request towards the pending requests of the connection until dropped.
+struct PendingRequest(Arc<AtomicUsize>);
+
+impl PendingRequest {
+    fn new(pending_inbound_requests: &Arc<AtomicUsize>) -> Self {
+        pending_inbound_requests.fetch_add(1, Ordering::Relaxed);
+        Self(pending_inbound_requests.clone())
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Serves the requests received over the given stream until the remote closes it.
+///
+/// Up to [`MAX_PIPELINED_REQUESTS`] requests are served concurrently, their responses are
+/// written in the order they become available.
+pub(crate) async fn inbound<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    mut cx: InboundContext<TCodec>,
+) -> io::Result<()>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let (mut reader, mut writer) = stream.split();
+    let (mut reply_sender, mut replies) = mpsc::channel(0);
+    let mut events = cx.events.clone();
+
+    let read = async move {
+        while let Some(reply) = next_request(&mut reader, protocol.clone(), &mut cx).await? {
+            // The writer only stops on failure, which aborts reading as well.
+            let _ = reply_sender.send(reply).await;
+        }
+        Ok(())
+    };
+
+    let write = async move {
+        let mut serving = FuturesUnordered::<BoxFuture<'static, io::Result<Reply<TCodec>>>>::new();
+        loop {
+            let reply = if serving.is_empty() {
+                match replies.next().await {
+                    Some(reply) => {
+                        serving.push(reply);
+                        continue;
+                    }
+                    None => break,
+                }
+            } else if !replies.is_terminated() && serving.len() < MAX_PIPELINED_REQUESTS {
+                let next = match future::select(replies.next(), serving.next()).await {
+                    Either::Left((reply, _)) => Either::Left(reply),
+                    Either::Right((reply, _)) => Either::Right(reply),
+                };
+                match next {
+                    Either::Left(Some(reply)) => {
+                        serving.push(reply);
+                        continue;
+                    }
+                    Either::Left(None) => continue,
+                    Either::Right(reply) => reply.expect("`serving` is not empty"),
+                }
+            } else {
+                serving.next().await.expect("`serving` is not empty")
+            }?;
+
+            let written = write_frame(&mut writer, reply.id, reply.payload.as_deref()).await;
+            if let Err(error) = written {
+                let kind = error.kind();
+                let _ = events
+                    .send(Event::InboundStreamFailed {
+                        request_id: reply.request_id,
+                        error,
+                    })
+                    .await;
+                return Err(kind.into());
+            }
+            drop((reply.reservation, reply.pending));
+            let _ = events.send(reply.event).await;
+        }
+
+        writer.close().await
+    };
+
+    future::try_join(read, write).await.map(|_| ())
+}
+
+/// Reads the next request and starts serving it, returning `None` once the remote closed the
+/// stream.
+///
+/// Admission is decided before the payload is read, which is skipped if the request is not
+/// admitted.
+async fn next_request<T, TCodec>(
+    reader: &mut T,
+    protocol: TCodec::Protocol,
+    cx: &mut InboundContext<TCodec>,
+) -> io::Result<Option<BoxFuture<'static, io::Result<Reply<TCodec>>>>>
+where
+    T: AsyncRead + Unpin,
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let Some((id, kind, len)) = read_header(reader, cx.max_frame_size).await? else {
+        return Ok(None);
+    };
+    match kind {
+        MESSAGE => {}
+        NO_RESPONSE => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request without payload",
+            ))
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid frame")),
+    }
+    let request_id = InboundRequestId(cx.inbound_request_id.fetch_add(1, Ordering::Relaxed));
+
+    let admission = cx.inbound_policy.admit(
+        &cx.remote_peer_id,
+        &protocol,
+        cx.pending_inbound_requests.load(Ordering::Relaxed),
+    );
+    match admission {
+        InboundAdmission::Accept => {}
+        InboundAdmission::Reject => {
+            tracing::debug!(%request_id, "Inbound request rejected before reading it");
+            skip_payload(reader, len).await?;
+            let reply = Reply::new(id, request_id, None, Event::InboundRejected(request_id));
+            return Ok(Some(future::ready(Ok(reply)).boxed()));
+        }
+        InboundAdmission::Respond(response) => {
+            tracing::debug!(%request_id, "Inbound request answered before reading it");
+            skip_payload(reader, len).await?;
+            let mut codec = cx.codec.clone();
+            let reply = async move {
+                let mut payload = Vec::new();
+                codec
+                    .write_response(&protocol, &mut payload, response)
+                    .await?;
+                Ok(Reply::new(
+                    id,
+                    request_id,
+                    Some(payload),
+                    Event::InboundRejected(request_id),
+                ))
+            };
+            return Ok(Some(reply.boxed()));
+        }
+    }
+
+    // Held until the response is written, as the request is kept in memory until then.
+    let mut reservation = cx.inbound_policy.reserve(&cx.remote_peer_id, &protocol);
+    let mut io = BudgetedRead::new(&mut *reader, reservation.as_mut()).take(len as u64);
+    let mut payload = Vec::new();
+    let read = io.read_to_end(&mut payload).await;
+    let remaining = io.limit();
+    match read {
+        _ if reservation.as_ref().map_or(false, Reservation::is_shed) => {
+            skip_payload(reader, remaining as usize).await?;
+            let reply = Reply::new(id, request_id, None, Event::InboundShed(request_id));
+            return Ok(Some(future::ready(Ok(reply)).boxed()));
+        }
+        Ok(_) if remaining > 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+        Ok(_) => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(reservation) = reservation.as_mut() {
+        reservation.settle();
+    }
+
+    let pending = PendingRequest::new(&cx.pending_inbound_requests);
+    let reply = serve(
+        cx.codec.clone(),
+        protocol,
+        cx.inbound_sender.clone(),
+        cx.timeout,
+        id,
+        request_id,
+        payload,
+    )
+    .map_ok(move |reply| Reply {
+        reservation,
+        pending: Some(pending),
+        ..reply
+    });
+    Ok(Some(reply.boxed()))
+}
+
+/// Passes a single request to the handler and awaits the response.
+async fn serve<TCodec>(
+    mut codec: TCodec,
+    protocol: TCodec::Protocol,
+    mut inbound_sender: mpsc::Sender<(
+        InboundRequestId,
+        TCodec::Request,
+        oneshot::Sender<TCodec::Response>,
+    )>,
+    timeout: Duration,
+    id: u64,
+    request_id: InboundRequestId,
+    payload: Vec<u8>,
+) -> io::Result<Reply<TCodec>>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let request = match codec.read_request(&protocol, &mut payload.as_slice()).await {
+        Ok(request) => request,
+        Err(error) => {
+            let event = Event::InboundStreamFailed { request_id, error };
+            return Ok(Reply::new(id, request_id, None, event));
+        }
+    };
+    drop(payload);
+
+    let (sender, receiver) = oneshot::channel();
+    inbound_sender
+        .send((request_id, request, sender))
+        .await
+        .expect("`ConnectionHandler` owns both ends of the channel");
+
+    let (response, event) = match future::select(receiver, Delay::new(timeout)).await {
+        Either::Left((Ok(response), _)) => (Some(response), Event::ResponseSent(request_id)),
+        Either::Left((Err(oneshot::Canceled), _)) => (None, Event::ResponseOmission(request_id)),
+        Either::Right(_) => (None, Event::InboundTimeout(request_id)),
+    };
+
+    let payload = match response {
+        Some(response) => {
+            let mut payload = Vec::new();
+            codec
+                .write_response(&protocol, &mut payload, response)
+                .await?;
+            Some(payload)
+        }
+        None => None,
+    };
+    Ok(Reply::new(id, request_id, payload, event))
+}
+
+async fn write_frame<T>(io: &mut T, id: u64, payload: Option<&[u8]>) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin,
+{
+    match payload {
+        Some(payload) => write_raw_frame(io, id, MESSAGE, payload).await,
+        None => write_raw_frame(io, id, NO_RESPONSE, &[]).await,
+    }
+}
+
+/// Writes a frame of the given kind.
+pub(super) async fn write_raw_frame<T>(
+    io: &mut T,
+    id: u64,
+    kind: u8,
+    payload: &[u8],
+) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin,
+{
+    let len = u32::try_from(payload.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Message exceeds the maximum frame size",
+        )
+    })?;
+
+    let mut header = [0; HEADER_LEN];
+    header[..8].copy_from_slice(&id.to_be_bytes());
+    header[8] = kind;
+    header[9..].copy_from_slice(&len.to_be_bytes());
+
+    io.write_all(&header).await?;
+    io.write_all(payload).await?;
+    io.flush().await
+}
+
+/// Reads the next frame of any kind, returning `None` if the remote closed the stream in between
+/// frames.
+///
+/// Fails if the payload exceeds `max_len` bytes.
+pub(super) async fn read_raw_frame<T>(
+    io: &mut T,
+    max_len: usize,
+) -> io::Result<Option<(u64, u8, Vec<u8>)>>
+where
+    T: AsyncRead + Unpin,
+{
+    let Some((id, kind, len)) = read_header(io, max_len).await? else {
+        return Ok(None);
+    };
+    let payload = read_payload(io, len).await?;
+
+    Ok(Some((id, kind, payload)))
+}
+
+/// Reads the header of the next frame, returning its ID, kind and payload length, or `None` if
+/// the remote closed the stream in between frames.
+///
+/// Fails if the payload exceeds `max_len` bytes.
+async fn read_header<T>(io: &mut T, max_len: usize) -> io::Result<Option<(u64, u8, usize)>>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut header = [0; HEADER_LEN];
+    if io.read(&mut header[..1]).await? == 0 {
+        return Ok(None);
+    }
+    io.read_exact(&mut header[1..]).await?;
+
+    let id = u64::from_be_bytes(header[..8].try_into().expect("slice of 8 bytes"));
+    let len = u32::from_be_bytes(header[9..].try_into().expect("slice of 4 bytes")) as usize;
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {len} bytes exceeds the maximum frame size"),
+        ));
+    }
+
+    Ok(Some((id, header[8], len)))
+}
+
+/// Reads a payload of `len` bytes, growing the buffer as the bytes arrive.
+async fn read_payload<T>(io: &mut T, len: usize) -> io::Result<Vec<u8>>
+where
+    T: AsyncRead + Unpin,
+{
+    let mut payload = Vec::new();
+    io.take(len as u64).read_to_end(&mut payload).await?;
+    if payload.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(payload)
+}
+
+/// Discards a payload of `len` bytes.
+async fn skip_payload<T>(io: &mut T, len: usize) -> io::Result<()>
+where
+    T: AsyncRead + Unpin,
+{
+    let skipped = futures::io::copy(io.take(len as u64), &mut futures::io::sink()).await?;
+    if skipped < len as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_roundtrip() {
+        futures::executor::block_on(async {
+            let mut buf = Vec::new();
+            write_frame(&mut buf, 7, Some(b"hello")).await.unwrap();
+            write_frame(&mut buf, 8, None).await.unwrap();
+
+            let mut io = buf.as_slice();
+            assert_eq!(
+                read_raw_frame(&mut io, 5).await.unwrap(),
+                Some((7, MESSAGE, b"hello".to_vec()))
+            );
+            assert_eq!(
+                read_raw_frame(&mut io, 5).await.unwrap(),
+                Some((8, NO_RESPONSE, Vec::new()))
+            );
+            assert_eq!(read_raw_frame(&mut io, 5).await.unwrap(), None);
+        })
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        futures::executor::block_on(async {
+            let mut buf = Vec::new();
+            write_frame(&mut buf, 0, Some(b"hello")).await.unwrap();
+
+            let mut io = &buf[..buf.len() - 1];
+            assert_eq!(
+                read_raw_frame(&mut io, 5).await.unwrap_err().kind(),
+                io::ErrorKind::UnexpectedEof
+            );
+        })
+    }
+
+    #[test]
+    fn oversized_frame_is_rejected_before_reading_it() {
+        futures::executor::block_on(async {
+            let mut buf = Vec::new();
+            write_frame(&mut buf, 0, Some(b"hello")).await.unwrap();
+
+            let mut io = buf.as_slice();
+            assert_eq!(
+                read_raw_frame(&mut io, 4).await.unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+            assert_eq!(io, b"hello", "The payload is left unread");
+        })
+    }
+}
@@ -57,23 +57,87 @@ impl ProtocolSupport {
     }
 }
 
+/// The suffix of the protocol names negotiating a persistent stream,
+/// see [`StreamMode::Persistent`](crate::StreamMode::Persistent).
+pub(crate) const PERSISTENT_SUFFIX: &str = "/persistent";
+
+/// The suffix of the protocol names negotiating a subscription stream,
+/// see [`Behaviour::subscribe`](crate::Behaviour::subscribe).
+pub(crate) const SUBSCRIPTION_SUFFIX: &str = "/subscribe";
+
+/// How a negotiated stream is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// A single request and response.
+    PerRequest,
+    /// Requests and responses pipelined over a long-lived stream.
+    Persistent,
+    /// A single request followed by a sequence of updates.
+    Subscription,
+}
+
+impl StreamKind {
+    /// The suffix of the protocol names negotiating a stream of this kind.
+    fn suffix(&self) -> Option<&'static str> {
+        match self {
+            StreamKind::PerRequest => None,
+            StreamKind::Persistent => Some(PERSISTENT_SUFFIX),
+            StreamKind::Subscription => Some(SUBSCRIPTION_SUFFIX),
+        }
+    }
+}
+
 /// Response substream upgrade protocol.
 ///
 /// Receives a request and sends a response.
 #[derive(Debug)]
 pub struct Protocol<P> {
     pub(crate) protocols: SmallVec<[P; 2]>,
+    /// The kinds of streams the protocols are offered for, in order of preference.
+    pub(crate) kinds: SmallVec<[StreamKind; 3]>,
+}
+
+/// A protocol name offered during negotiation.
+#[derive(Debug, Clone)]
+pub struct ProtocolName<P> {
+    protocol: P,
+    kind: StreamKind,
+    /// The name of the protocol suffixed according to `kind`, if any.
+    suffixed: Option<String>,
+}
+
+impl<P> AsRef<str> for ProtocolName<P>
+where
+    P: AsRef<str>,
+{
+    fn as_ref(&self) -> &str {
+        self.suffixed
+            .as_deref()
+            .unwrap_or_else(|| self.protocol.as_ref())
+    }
 }
 
 impl<P> UpgradeInfo for Protocol<P>
 where
     P: AsRef<str> + Clone,
 {
-    type Info = P;
-    type InfoIter = smallvec::IntoIter<[Self::Info; 2]>;
+    type Info = ProtocolName<P>;
+    type InfoIter = smallvec::IntoIter<[Self::Info; 4]>;
 
     fn protocol_info(&self) -> Self::InfoIter {
-        self.protocols.clone().into_iter()
+        self.kinds
+            .iter()
+            .flat_map(|kind| {
+                self.protocols.iter().map(|protocol| ProtocolName {
+                    protocol: protocol.clone(),
+                    kind: *kind,
+                    suffixed: kind
+                        .suffix()
+                        .map(|suffix| format!("{}{suffix}", protocol.as_ref())),
+                })
+            })
+            .collect::<SmallVec<_>>()
+            .into_iter()
     }
 }
 
@@ -81,12 +145,13 @@ impl<P> InboundUpgrade<Stream> for Protocol<P>
 where
     P: AsRef<str> + Clone,
 {
-    type Output = (Stream, P);
+    /// The stream, the negotiated protocol and how the stream is used.
+    type Output = (Stream, P, StreamKind);
     type Error = void::Void;
     type Future = Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_inbound(self, io: Stream, protocol: Self::Info) -> Self::Future {
-        ready(Ok((io, protocol)))
+        ready(Ok((io, protocol.protocol, protocol.kind)))
     }
 }
 
@@ -94,11 +159,12 @@ impl<P> OutboundUpgrade<Stream> for Protocol<P>
 where
     P: AsRef<str> + Clone,
 {
-    type Output = (Stream, P);
+    /// The stream, the negotiated protocol and how the stream is used.
+    type Output = (Stream, P, StreamKind);
     type Error = void::Void;
     type Future = Ready<Result<Self::Output, Self::Error>>;
 
     fn upgrade_outbound(self, io: Stream, protocol: Self::Info) -> Self::Future {
-        ready(Ok((io, protocol)))
+        ready(Ok((io, protocol.protocol, protocol.kind)))
     }
 }
@@ -0,0 +1,457 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Server-driven sequences of updates in reply to a single request,
+//! see [`Behaviour::subscribe`](crate::Behaviour::subscribe).
+//!
+//! Frames use the layout of persistent streams. The subscriber sends the request as a frame of
+//! kind [`SUBSCRIBE`], followed by frames of kind [`ACK`] acknowledging all updates up to the
+//! sequence number in their ID, and finally a frame of kind [`CANCEL`] to cancel the
+//! subscription. The publisher sends every update as a frame of kind [`UPDATE`] carrying its
+//! sequence number, starting at 0, and a frame of kind [`END`] once the subscription ended.
+//! At most [`MAX_UNACKED_UPDATES`] updates are sent before they are acknowledged.
+
+use crate::codec::Codec;
+use crate::handler::persistent::{read_raw_frame, write_raw_frame};
+use crate::handler::Event;
+use crate::{InboundRequestId, OutboundFailure, OutboundRequestId};
+
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::prelude::*;
+use futures::stream::BoxStream;
+use futures_timer::Delay;
+use libp2p_swarm::Stream;
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// A frame carrying the request of a subscription.
+const SUBSCRIBE: u8 = 0;
+/// A frame carrying an update.
+const UPDATE: u8 = 1;
+/// A frame acknowledging the updates up to and including the sequence number in its ID.
+const ACK: u8 = 2;
+/// A frame cancelling the subscription.
+const CANCEL: u8 = 3;
+/// A frame signalling that the publisher ended the subscription.
+const END: u8 = 4;
+/// The maximum number of updates sent before they are acknowledged.
+const MAX_UNACKED_UPDATES: u64 = 16;
+/// The number of updates buffered by a [`SubscriptionChannel`](crate::SubscriptionChannel)
+/// while the subscriber is not acknowledging them.
+pub(crate) const MAX_BUFFERED_UPDATES: usize = 16;
+
+/// The acknowledgements of an outbound subscription, passed from the handler to the task
+/// serving it. Dropping them cancels the subscription.
+pub(crate) struct Acknowledgements {
+    /// The number of acknowledged updates.
+    acked: Arc<AtomicU64>,
+    /// Wakes up the task after `acked` was raised.
+    notify: mpsc::Sender<()>,
+}
+
+impl Acknowledgements {
+    /// Acknowledges all updates up to and including `seq`.
+    pub(crate) fn acknowledge(&mut self, seq: u64) {
+        self.acked.fetch_max(seq + 1, Ordering::Relaxed);
+        // A full channel already holds a pending wake-up.
+        let _ = self.notify.try_send(());
+    }
+}
+
+/// The receiving side of [`Acknowledgements`].
+pub(crate) struct AcknowledgementsReceiver {
+    acked: Arc<AtomicU64>,
+    notify: mpsc::Receiver<()>,
+}
+
+/// Creates the [`Acknowledgements`] of a new outbound subscription.
+pub(crate) fn acknowledgements() -> (Acknowledgements, AcknowledgementsReceiver) {
+    let acked = Arc::new(AtomicU64::new(0));
+    let (sender, receiver) = mpsc::channel(0);
+    (
+        Acknowledgements {
+            acked: acked.clone(),
+            notify: sender,
+        },
+        AcknowledgementsReceiver {
+            acked,
+            notify: receiver,
+        },
+    )
+}
+
+/// Sends the request of a subscription over the given stream, reporting the updates via
+/// `events`, until the remote ends the subscription or it is cancelled by dropping the
+/// [`Acknowledgements`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn outbound<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    codec: TCodec,
+    request_id: OutboundRequestId,
+    request: TCodec::Request,
+    acks: AcknowledgementsReceiver,
+    mut events: mpsc::Sender<Event<TCodec>>,
+    max_frame_size: usize,
+) where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let result = subscribe(
+        stream,
+        protocol,
+        codec,
+        request_id,
+        request,
+        acks,
+        events.clone(),
+        max_frame_size,
+    )
+    .await;
+    if let Err(error) = result {
+        let _ = events
+            .send(Event::OutboundSubscriptionFailed {
+                request_id,
+                error: OutboundFailure::Io(error),
+            })
+            .await;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn subscribe<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    mut codec: TCodec,
+    request_id: OutboundRequestId,
+    request: TCodec::Request,
+    mut acks: AcknowledgementsReceiver,
+    mut events: mpsc::Sender<Event<TCodec>>,
+    max_frame_size: usize,
+) -> io::Result<()>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let (reader, mut writer) = stream.split();
+
+    let mut payload = Vec::new();
+    codec
+        .write_request(&protocol, &mut payload, request)
+        .await?;
+    write_raw_frame(&mut writer, 0, SUBSCRIBE, &payload).await?;
+
+    let mut frames = frames(reader, max_frame_size);
+    let mut next_seq = 0;
+    let mut sent_acked = 0;
+    loop {
+        match future::select(frames.next(), acks.notify.next()).await {
+            Either::Left((Some(Ok((seq, UPDATE, payload))), _)) => {
+                if seq != next_seq {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Expected update {next_seq}, got {seq}"),
+                    ));
+                }
+                next_seq += 1;
+                let update = codec
+                    .read_response(&protocol, &mut payload.as_slice())
+                    .await?;
+                let _ = events
+                    .send(Event::OutboundSubscriptionUpdate {
+                        request_id,
+                        seq,
+                        update,
+                    })
+                    .await;
+            }
+            Either::Left((Some(Ok((_, END, _))), _)) => {
+                let _ = events
+                    .send(Event::OutboundSubscriptionEnded(request_id))
+                    .await;
+                return writer.close().await;
+            }
+            Either::Left((Some(Ok(_)), _)) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid frame"));
+            }
+            Either::Left((Some(Err(error)), _)) => return Err(error),
+            Either::Left((None, _)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Remote closed the subscription without ending it",
+                ));
+            }
+            Either::Right((Some(()), _)) => {
+                let acked = acks.acked.load(Ordering::Relaxed);
+                if acked > next_seq {
+                    tracing::debug!(%request_id, "Ignoring acknowledgement of unknown update");
+                }
+                let acked = acked.min(next_seq);
+                if acked > sent_acked {
+                    write_raw_frame(&mut writer, acked - 1, ACK, &[]).await?;
+                    sent_acked = acked;
+                }
+            }
+            Either::Right((None, _)) => {
+                // The subscription is cancelled, the remote is not waited for.
+                let _ = write_raw_frame(&mut writer, next_seq, CANCEL, &[]).await;
+                let _ = writer.close().await;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Serves a subscription received over the given stream, reporting the outcome via `events`.
+///
+/// If `response` is set, it is sent as the only update without reading the request.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn inbound<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    codec: TCodec,
+    request_id: InboundRequestId,
+    response: Option<TCodec::Response>,
+    mut events: mpsc::Sender<Event<TCodec>>,
+    timeout: Duration,
+    max_frame_size: usize,
+) where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let result = match response {
+        Some(response) => respond(stream, protocol, codec, response)
+            .await
+            .map(|()| Event::InboundRejected(request_id)),
+        None => {
+            publish(
+                stream,
+                protocol,
+                codec,
+                request_id,
+                events.clone(),
+                timeout,
+                max_frame_size,
+            )
+            .await
+        }
+    };
+    let event = match result {
+        Ok(event) => event,
+        Err(error) if error.kind() == io::ErrorKind::TimedOut => Event::InboundTimeout(request_id),
+        Err(error) => Event::InboundStreamFailed { request_id, error },
+    };
+    let _ = events.send(event).await;
+}
+
+/// Sends the given response as the only update and ends the subscription.
+async fn respond<TCodec>(
+    mut stream: Stream,
+    protocol: TCodec::Protocol,
+    mut codec: TCodec,
+    response: TCodec::Response,
+) -> io::Result<()>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let mut payload = Vec::new();
+    codec
+        .write_response(&protocol, &mut payload, response)
+        .await?;
+    write_raw_frame(&mut stream, 0, UPDATE, &payload).await?;
+    write_raw_frame(&mut stream, 1, END, &[]).await?;
+    stream.close().await
+}
+
+/// Reads the request, passes it to the handler and sends the updates pushed to the
+/// [`SubscriptionChannel`](crate::SubscriptionChannel) until either side ends the subscription.
+///
+/// Returns the event reporting how the subscription ended.
+async fn publish<TCodec>(
+    stream: Stream,
+    protocol: TCodec::Protocol,
+    mut codec: TCodec,
+    request_id: InboundRequestId,
+    mut events: mpsc::Sender<Event<TCodec>>,
+    timeout: Duration,
+    max_frame_size: usize,
+) -> io::Result<Event<TCodec>>
+where
+    TCodec: Codec + Send + Clone + 'static,
+{
+    let (reader, mut writer) = stream.split();
+    let mut frames = frames(reader, max_frame_size);
+
+    let frame = match future::select(frames.next(), Delay::new(timeout)).await {
+        Either::Left((frame, _)) => frame,
+        Either::Right(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Timed out waiting for the request",
+            ))
+        }
+    };
+    let payload = match frame.transpose()? {
+        Some((_, SUBSCRIBE, payload)) => payload,
+        Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid frame")),
+        None => return Err(io::ErrorKind::UnexpectedEof.into()),
+    };
+    let request = codec
+        .read_request(&protocol, &mut payload.as_slice())
+        .await?;
+
+    let (sender, mut updates) = mpsc::channel(MAX_BUFFERED_UPDATES);
+    let _ = events
+        .send(Event::InboundSubscription {
+            request_id,
+            request,
+            sender,
+        })
+        .await;
+
+    let mut next_seq = 0;
+    let mut acked = 0;
+    loop {
+        let input = if next_seq - acked < MAX_UNACKED_UPDATES {
+            match future::select(frames.next(), updates.next()).await {
+                Either::Left((frame, _)) => Either::Left(frame),
+                Either::Right((update, _)) => Either::Right(update),
+            }
+        } else {
+            Either::Left(frames.next().await)
+        };
+
+        match input {
+            Either::Left(Some(Ok((seq, ACK, _)))) => {
+                if seq >= next_seq {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Acknowledgement of unsent update {seq}"),
+                    ));
+                }
+                if seq >= acked {
+                    acked = seq + 1;
+                    let _ = events
+                        .send(Event::InboundSubscriptionAcknowledged { request_id, seq })
+                        .await;
+                }
+            }
+            Either::Left(Some(Ok((_, CANCEL, _))) | None) => {
+                let _ = writer.close().await;
+                return Ok(Event::InboundSubscriptionCancelled(request_id));
+            }
+            Either::Left(Some(Ok(_))) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid frame"));
+            }
+            Either::Left(Some(Err(error))) => return Err(error),
+            Either::Right(Some(update)) => {
+                let mut payload = Vec::new();
+                codec
+                    .write_response(&protocol, &mut payload, update)
+                    .await?;
+                write_raw_frame(&mut writer, next_seq, UPDATE, &payload).await?;
+                next_seq += 1;
+            }
+            Either::Right(None) => {
+                write_raw_frame(&mut writer, next_seq, END, &[]).await?;
+                writer.close().await?;
+                return Ok(Event::InboundSubscriptionEnded(request_id));
+            }
+        }
+    }
+}
+
+/// Returns the frames read from `reader`, ending once the remote closed the stream in between
+/// frames or after the first error.
+fn frames<T>(reader: T, max_frame_size: usize) -> BoxStream<'static, io::Result<(u64, u8, Vec<u8>)>>
+where
+    T: AsyncRead + Unpin + Send + 'static,
+{
+    stream::unfold(Some(reader), move |reader| async move {
+        let mut reader = reader?;
+        match read_raw_frame(&mut reader, max_frame_size).await {
+            Ok(Some(frame)) => Some((Ok(frame), Some(reader))),
+            Ok(None) => None,
+            Err(error) => Some((Err(error), None)),
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a frame of the given kind and reads it back via [`frames`].
+    fn roundtrip(id: u64, kind: u8, payload: &[u8]) -> (u64, u8, Vec<u8>) {
+        futures::executor::block_on(async {
+            let mut buf = Vec::new();
+            write_raw_frame(&mut buf, id, kind, payload).await.unwrap();
+
+            let mut frames = frames(futures::io::Cursor::new(buf), payload.len());
+            let frame = frames.next().await.unwrap().unwrap();
+            assert!(frames.next().await.is_none());
+            frame
+        })
+    }
+
+    #[test]
+    fn subscribe_frame_roundtrip() {
+        assert_eq!(
+            roundtrip(0, SUBSCRIBE, b"request"),
+            (0, SUBSCRIBE, b"request".to_vec())
+        );
+    }
+
+    #[test]
+    fn update_frame_roundtrip() {
+        assert_eq!(
+            roundtrip(3, UPDATE, b"update"),
+            (3, UPDATE, b"update".to_vec())
+        );
+    }
+
+    #[test]
+    fn ack_frame_roundtrip() {
+        assert_eq!(roundtrip(5, ACK, &[]), (5, ACK, Vec::new()));
+    }
+
+    #[test]
+    fn cancel_frame_roundtrip() {
+        assert_eq!(roundtrip(6, CANCEL, &[]), (6, CANCEL, Vec::new()));
+    }
+
+    #[test]
+    fn end_frame_roundtrip() {
+        assert_eq!(roundtrip(7, END, &[]), (7, END, Vec::new()));
+    }
+
+    #[test]
+    fn frame_kinds_are_distinct() {
+        let kinds = [SUBSCRIBE, UPDATE, ACK, CANCEL, END];
+        for (i, a) in kinds.iter().enumerate() {
+            assert!(!kinds[i + 1..].contains(a), "Frame kind {a} is used twice");
+        }
+    }
+}
@@ -63,26 +63,68 @@
 //! family can be configured in this way. Such protocols will not be
 //! advertised during inbound respectively outbound protocol negotiation
 //! on the substreams.
+//!
+//! ## At-least-once Delivery
+//!
+//! By default, requests that can not be delivered fail with an
+//! [`Event::OutboundFailure`]. Attaching a [`RequestStore`] via
+//! [`Behaviour::with_request_store`] instead keeps requests until a response
+//! is received or their deadline passes, retrying them whenever a new
+//! connection to the peer is established. State changes of stored requests
+//! are reported via [`Event::StoredRequest`].
+//!
+//! ## Admission Control
+//!
+//! An inbound filter attached via [`Behaviour::with_inbound_filter`] is
+//! consulted for every inbound stream as soon as the protocol has been
+//! negotiated, i.e. before the request is read. It can reject the request,
+//! e.g. because the remote exceeded a rate limit, or answer it right away
+//! with a response of its choosing, e.g. an error with a hint when to retry.
+//!
+//! ## Memory Budget
+//!
+//! [`Config::with_memory_budget`] bounds the bytes held by in-flight requests and
+//! responses across all connections. Once the budget is exceeded, inbound requests which
+//! are still being read are shed, starting with the lowest priority as assigned via
+//! [`Behaviour::with_inbound_priority`].
+//!
+//! ## Subscriptions
+//!
+//! A request sent via [`Behaviour::subscribe`] opens a subscription, over which the remote
+//! pushes a sequence of updates until either side ends it. Updates are of the response type
+//! and reported as [`SubscriptionEvent::Update`], to be acknowledged via
+//! [`Behaviour::acknowledge_update`]. The remote only accepts subscriptions if enabled via
+//! [`Config::with_subscriptions`] and pushes updates through the [`SubscriptionChannel`] of
+//! [`SubscriptionEvent::Subscribed`].
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod budget;
 #[cfg(feature = "cbor")]
 pub mod cbor;
 mod codec;
 mod handler;
 #[cfg(feature = "json")]
 pub mod json;
+mod store;
 
 pub use codec::Codec;
 pub use handler::ProtocolSupport;
+pub use store::{MemoryStore, PendingRequest, RequestStore};
 
-use crate::handler::OutboundMessage;
-use futures::channel::oneshot;
+use crate::budget::MemoryBudget;
+use crate::handler::{HandlerIn, InboundPolicy, OutboundMessage};
+use futures::{
+    channel::{mpsc, oneshot},
+    FutureExt,
+};
+use futures_timer::Delay;
 use handler::Handler;
+use instant::SystemTime;
 use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    behaviour::{AddressChange, ConnectionClosed, DialFailure, FromSwarm},
+    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm},
     dial_opts::DialOpts,
     ConnectionDenied, ConnectionHandler, ConnectionId, NetworkBehaviour, NotifyHandler,
     PeerAddresses, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
@@ -161,6 +203,103 @@ pub enum Event<TRequest, TResponse, TChannelResponse = TResponse> {
         /// The ID of the inbound request whose response was sent.
         request_id: InboundRequestId,
     },
+    /// An inbound request was discarded before it could be handed to the application
+    /// as [`Message::Request`].
+    ///
+    /// Together with [`Event::ResponseSent`] and [`Event::InboundFailure`], this
+    /// accounts for the terminal state of every inbound request of a connection
+    /// that is closed while the `Behaviour` is still being polled.
+    InboundRequestDropped {
+        /// The peer from whom the request was received.
+        peer: PeerId,
+        /// The ID of the dropped inbound request.
+        request_id: InboundRequestId,
+        /// Why the request was dropped.
+        reason: InboundDropReason,
+    },
+    /// An event of a subscription, see [`Behaviour::subscribe`].
+    Subscription {
+        /// The remote peer of the subscription.
+        peer: PeerId,
+        /// The event of the subscription.
+        event: SubscriptionEvent<TRequest, TResponse, TChannelResponse>,
+    },
+    /// An outbound request held by the [`RequestStore`] changed its state.
+    ///
+    /// Only emitted if a store was attached via [`Behaviour::with_request_store`].
+    StoredRequest {
+        /// The peer to whom the request is to be sent.
+        peer: PeerId,
+        /// The (local) ID of the stored request.
+        request_id: OutboundRequestId,
+        /// The new state of the request.
+        state: StoredRequestState,
+    },
+}
+
+/// An event of a subscription, see [`Event::Subscription`].
+#[derive(Debug)]
+pub enum SubscriptionEvent<TRequest, TResponse, TChannelResponse = TResponse> {
+    /// A remote peer subscribed.
+    Subscribed {
+        /// The ID of the inbound subscription.
+        request_id: InboundRequestId,
+        /// The request of the subscription.
+        request: TRequest,
+        /// The channel for pushing updates to the subscriber.
+        ///
+        /// The subscription is ended once the channel is dropped.
+        channel: SubscriptionChannel<TChannelResponse>,
+    },
+    /// The subscriber acknowledged the updates of an inbound subscription up to and including
+    /// `seq`.
+    Acknowledged {
+        /// The ID of the inbound subscription.
+        request_id: InboundRequestId,
+        /// The sequence number of the last acknowledged update.
+        seq: u64,
+    },
+    /// The subscriber cancelled an inbound subscription.
+    Cancelled {
+        /// The ID of the inbound subscription.
+        request_id: InboundRequestId,
+    },
+    /// An update of an outbound subscription has been received.
+    Update {
+        /// The ID of the outbound subscription, see [`Behaviour::subscribe`].
+        request_id: OutboundRequestId,
+        /// The sequence number of the update, starting at 0.
+        seq: u64,
+        /// The update.
+        update: TResponse,
+    },
+    /// The remote ended an outbound subscription.
+    Ended {
+        /// The ID of the outbound subscription, see [`Behaviour::subscribe`].
+        request_id: OutboundRequestId,
+    },
+}
+
+/// The state of an outbound request held by a [`RequestStore`].
+///
+/// See [`Event::StoredRequest`].
+#[derive(Debug)]
+pub enum StoredRequestState {
+    /// The request has been added to the store.
+    ///
+    /// It is kept until a response is received, in which case
+    /// [`Event::Message`] is emitted, or until `deadline` has passed.
+    Stored { deadline: SystemTime },
+    /// An attempt to deliver the request failed.
+    ///
+    /// The request is retried once a new connection to the peer is established.
+    Requeued { error: OutboundFailure },
+    /// The request is being re-sent on a newly established connection.
+    Retrying,
+    /// The deadline passed before the request could be delivered.
+    ///
+    /// The request has been removed from the store.
+    Expired,
 }
 
 /// Possible failures occurring in the context of sending
@@ -221,6 +360,9 @@ pub enum InboundFailure {
     /// due to the [`ResponseChannel`] being dropped instead of
     /// being passed to [`Behaviour::send_response`].
     ResponseOmission,
+    /// The request was not admitted by the inbound filter,
+    /// see [`Behaviour::with_inbound_filter`].
+    Rejected,
     /// An IO failure happened on an inbound stream.
     Io(io::Error),
 }
@@ -242,6 +384,9 @@ impl fmt::Display for InboundFailure {
                 f,
                 "The response channel was dropped without sending a response to the remote"
             ),
+            InboundFailure::Rejected => {
+                write!(f, "The inbound request was rejected by the inbound filter")
+            }
             InboundFailure::Io(e) => write!(f, "IO error on inbound stream: {e}"),
         }
     }
@@ -249,6 +394,60 @@ impl fmt::Display for InboundFailure {
 
 impl std::error::Error for InboundFailure {}
 
+/// The reason an inbound request was dropped before reaching the application,
+/// see [`Event::InboundRequestDropped`].
+#[derive(Debug)]
+pub enum InboundDropReason {
+    /// The connection was at its limit of concurrent streams,
+    /// see [`Config::with_max_concurrent_streams`].
+    AtCapacity,
+    /// The request was not read within the request timeout.
+    Timeout,
+    /// An IO failure happened while reading the request.
+    Io(io::Error),
+    /// The connection closed before the request could be handed to the application.
+    ConnectionClosed,
+    /// The request was shed while being read because the memory budget was exceeded,
+    /// see [`Config::with_memory_budget`].
+    MemoryBudget,
+}
+
+impl fmt::Display for InboundDropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InboundDropReason::AtCapacity => {
+                write!(f, "The connection is at its limit of concurrent streams")
+            }
+            InboundDropReason::Timeout => write!(f, "Timeout while reading the request"),
+            InboundDropReason::Io(e) => write!(f, "IO error while reading the request: {e}"),
+            InboundDropReason::ConnectionClosed => {
+                write!(
+                    f,
+                    "Connection was closed before the request could be delivered"
+                )
+            }
+            InboundDropReason::MemoryBudget => {
+                write!(f, "The memory budget for in-flight requests was exceeded")
+            }
+        }
+    }
+}
+
+/// The decision of an inbound filter on an inbound request,
+/// see [`Behaviour::with_inbound_filter`].
+#[derive(Debug)]
+pub enum InboundAdmission<TResponse> {
+    /// Read the request and emit it as [`Message::Request`].
+    Accept,
+    /// Drop the stream without reading the request.
+    ///
+    /// The remote observes an [`OutboundFailure::Io`].
+    Reject,
+    /// Send the given response without reading the request, e.g. a
+    /// protocol-level error or a hint when to retry.
+    Respond(TResponse),
+}
+
 /// A channel for sending a response to an inbound request.
 ///
 /// See [`Behaviour::send_response`].
@@ -270,6 +469,36 @@ impl<TResponse> ResponseChannel<TResponse> {
     }
 }
 
+/// A channel for pushing the updates of an inbound subscription, see
+/// [`SubscriptionEvent::Subscribed`].
+///
+/// Dropping the channel ends the subscription once the updates pushed before are sent.
+#[derive(Debug)]
+pub struct SubscriptionChannel<TResponse> {
+    sender: mpsc::Sender<TResponse>,
+}
+
+impl<TResponse> SubscriptionChannel<TResponse> {
+    /// Pushes an update to the subscriber.
+    ///
+    /// Only a bounded number of updates is sent before the subscriber acknowledges them, see
+    /// [`SubscriptionEvent::Acknowledged`], and only a bounded number of further updates is
+    /// buffered. The update is returned as an `Err` if the buffer is full or the subscription
+    /// is no longer open.
+    pub fn send(&mut self, update: TResponse) -> Result<(), TResponse> {
+        self.sender.try_send(update).map_err(|e| e.into_inner())
+    }
+
+    /// Checks whether the subscription is still open, i.e. it has not been cancelled by the
+    /// subscriber and the connection is not closed.
+    pub fn is_open(&self) -> bool {
+        !self.sender.is_closed()
+    }
+
+    /// Ends the subscription once the updates pushed before are sent.
+    pub fn end(self) {}
+}
+
 /// The ID of an inbound request.
 ///
 /// Note: [`InboundRequestId`]'s uniqueness is only guaranteed between
@@ -296,11 +525,30 @@ impl fmt::Display for OutboundRequestId {
     }
 }
 
+/// Allows a persistent [`RequestStore`] to restore request IDs.
+impl From<u64> for OutboundRequestId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+/// Allows a persistent [`RequestStore`] to persist request IDs.
+impl From<OutboundRequestId> for u64 {
+    fn from(id: OutboundRequestId) -> Self {
+        id.0
+    }
+}
+
 /// The configuration for a `Behaviour` protocol.
 #[derive(Debug, Clone)]
 pub struct Config {
     request_timeout: Duration,
     max_concurrent_streams: usize,
+    stored_request_ttl: Duration,
+    stream_mode: StreamMode,
+    memory_budget: Option<usize>,
+    subscriptions: bool,
+    max_frame_size: usize,
 }
 
 impl Default for Config {
@@ -308,6 +556,11 @@ impl Default for Config {
         Self {
             request_timeout: Duration::from_secs(10),
             max_concurrent_streams: 100,
+            stored_request_ttl: Duration::from_secs(60 * 60),
+            stream_mode: StreamMode::default(),
+            memory_budget: None,
+            subscriptions: false,
+            max_frame_size: 1024 * 1024,
         }
     }
 }
@@ -331,6 +584,77 @@ impl Config {
         self.max_concurrent_streams = num_streams;
         self
     }
+
+    /// Sets how long requests are kept in the [`RequestStore`] before they expire.
+    ///
+    /// Only applies if a store was attached via [`Behaviour::with_request_store`].
+    /// Defaults to one hour.
+    pub fn with_stored_request_ttl(mut self, ttl: Duration) -> Self {
+        self.stored_request_ttl = ttl;
+        self
+    }
+
+    /// Sets how requests and responses are mapped onto streams.
+    ///
+    /// Defaults to [`StreamMode::PerRequest`].
+    pub fn with_stream_mode(mut self, mode: StreamMode) -> Self {
+        self.stream_mode = mode;
+        self
+    }
+
+    /// Sets the number of bytes in-flight requests and responses may hold across all connections.
+    ///
+    /// Requests are accounted with their encoded size from the moment they are read until
+    /// the response is sent, responses while they are read. Once the budget is exceeded,
+    /// inbound requests which are still being read are shed, lowest priority first, see
+    /// [`Behaviour::with_inbound_priority`]. Shed requests are reported via
+    /// [`Event::InboundRequestDropped`] with [`InboundDropReason::MemoryBudget`].
+    ///
+    /// Unlimited by default.
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Sets whether inbound subscriptions are accepted, see [`Behaviour::subscribe`].
+    ///
+    /// Subscription streams are negotiated with the protocol name suffixed by `/subscribe`,
+    /// e.g. `/my-protocol/1.0.0/subscribe`, and count towards the limit set via
+    /// [`Config::with_max_concurrent_streams`]. Outbound subscriptions do not require this
+    /// setting. Disabled by default.
+    pub fn with_subscriptions(mut self, enabled: bool) -> Self {
+        self.subscriptions = enabled;
+        self
+    }
+
+    /// Sets the maximum size in bytes of a single request, response or update sent over a
+    /// persistent or subscription stream, see [`StreamMode::Persistent`] and
+    /// [`Behaviour::subscribe`].
+    ///
+    /// The size is checked before the message is read, a larger message fails the stream.
+    /// The [`Codec`] may enforce lower limits. Defaults to 1 MiB.
+    pub fn with_max_frame_size(mut self, bytes: usize) -> Self {
+        self.max_frame_size = bytes;
+        self
+    }
+}
+
+/// How requests and responses are mapped onto streams, see [`Config::with_stream_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamMode {
+    /// Every request is sent on a new stream, which is closed after the response.
+    #[default]
+    PerRequest,
+    /// Requests are pipelined over a single long-lived stream per connection and direction,
+    /// saving the round-trips for opening a stream and negotiating the protocol per request.
+    ///
+    /// The stream is negotiated with the protocol name suffixed by `/persistent`, e.g.
+    /// `/my-protocol/1.0.0/persistent`. Requests are served concurrently, thus responses may
+    /// arrive in a different order than the requests were sent. Messages are limited to the
+    /// size set via [`Config::with_max_frame_size`].
+    /// Peers not supporting persistent streams are sent one stream per request instead.
+    /// Inbound persistent streams are only accepted in this mode.
+    Persistent,
 }
 
 /// A request/response protocol for some message codec.
@@ -351,8 +675,7 @@ where
     /// The protocol codec for reading and writing requests and responses.
     codec: TCodec,
     /// Pending events to return from `poll`.
-    pending_events:
-        VecDeque<ToSwarm<Event<TCodec::Request, TCodec::Response>, OutboundMessage<TCodec>>>,
+    pending_events: VecDeque<ToSwarm<Event<TCodec::Request, TCodec::Response>, HandlerIn<TCodec>>>,
     /// The currently connected peers, their pending outbound and inbound responses and their known,
     /// reachable addresses, if any.
     connected: HashMap<PeerId, SmallVec<[Connection; 2]>>,
@@ -361,6 +684,15 @@ where
     /// Requests that have not yet been sent and are waiting for a connection
     /// to be established.
     pending_outbound_requests: HashMap<PeerId, SmallVec<[OutboundMessage<TCodec>; 10]>>,
+    /// Storage for requests awaiting delivery, see [`Behaviour::with_request_store`].
+    request_store: Option<Box<dyn RequestStore<TCodec::Request>>>,
+    /// The requests held by the `request_store`.
+    stored_requests: HashMap<OutboundRequestId, StoredRequest>,
+    /// Fires once the earliest deadline of the requeued `stored_requests` has passed.
+    next_expiry: Option<Delay>,
+    /// The hooks and limits applied to inbound requests, see [`Behaviour::with_inbound_filter`],
+    /// [`Behaviour::with_inbound_priority`] and [`Config::with_memory_budget`].
+    inbound_policy: InboundPolicy<TCodec>,
 }
 
 impl<TCodec> Behaviour<TCodec>
@@ -396,6 +728,9 @@ where
                 outbound_protocols.push(p.clone());
             }
         }
+        let memory_budget = cfg
+            .memory_budget
+            .map(|limit| Arc::new(MemoryBudget::new(limit)));
         Behaviour {
             inbound_protocols,
             outbound_protocols,
@@ -407,9 +742,101 @@ where
             connected: HashMap::new(),
             pending_outbound_requests: HashMap::new(),
             addresses: PeerAddresses::default(),
+            request_store: None,
+            stored_requests: HashMap::new(),
+            next_expiry: None,
+            inbound_policy: InboundPolicy {
+                filter: None,
+                priority: None,
+                memory_budget,
+            },
         }
     }
 
+    /// Attaches a filter deciding whether inbound requests are admitted.
+    ///
+    /// The filter is called as soon as an inbound stream for one of the inbound protocols has
+    /// been negotiated, before the request is read. It is passed the remote peer, the negotiated
+    /// protocol and the number of inbound requests of the same connection that are still being
+    /// read or awaiting a response.
+    ///
+    /// Requests that are not accepted are reported as [`Event::InboundFailure`] with
+    /// [`InboundFailure::Rejected`], see [`InboundAdmission`].
+    pub fn with_inbound_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&PeerId, &TCodec::Protocol, usize) -> InboundAdmission<TCodec::Response>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.inbound_policy.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Attaches a hook assigning a priority to inbound requests, where higher is more important.
+    ///
+    /// The hook is called for every admitted inbound request before it is read and passed the
+    /// remote peer and the negotiated protocol. If the memory budget is exceeded, requests of
+    /// lower priority are shed first, see [`Config::with_memory_budget`]. Without a hook, all
+    /// requests have priority 0 and the most recent ones are shed first.
+    pub fn with_inbound_priority<F>(mut self, priority: F) -> Self
+    where
+        F: Fn(&PeerId, &TCodec::Protocol) -> u8 + Send + Sync + 'static,
+    {
+        self.inbound_policy.priority = Some(Arc::new(priority));
+        self
+    }
+
+    /// Returns the bytes currently held by in-flight requests and responses.
+    ///
+    /// Only tracked if a budget is configured via [`Config::with_memory_budget`].
+    pub fn memory_usage(&self) -> Option<usize> {
+        self.inbound_policy
+            .memory_budget
+            .as_ref()
+            .map(|budget| budget.used())
+    }
+
+    /// Attaches a [`RequestStore`], enabling at-least-once delivery of outbound requests.
+    ///
+    /// Requests already held by the store, e.g. from a previous run, are
+    /// restored and their peers dialed.
+    ///
+    /// See [`RequestStore`] for details.
+    pub fn with_request_store<S>(mut self, mut store: S) -> Self
+    where
+        S: RequestStore<TCodec::Request>,
+    {
+        let mut peers = HashSet::new();
+        for PendingRequest {
+            peer,
+            request_id,
+            deadline,
+        } in store.pending()
+        {
+            self.next_outbound_request_id.0 = self.next_outbound_request_id.0.max(request_id.0 + 1);
+            self.stored_requests.insert(
+                request_id,
+                StoredRequest {
+                    peer,
+                    deadline,
+                    in_flight: false,
+                },
+            );
+            peers.insert(peer);
+        }
+        for peer in peers {
+            self.pending_events.push_back(ToSwarm::Dial {
+                opts: DialOpts::peer_id(peer).build(),
+            });
+        }
+
+        self.request_store = Some(Box::new(store));
+        self.schedule_expiry();
+
+        self
+    }
+
     /// Initiates sending a request.
     ///
     /// If the targeted peer is currently not connected, a dialing
@@ -424,25 +851,97 @@ where
     /// > [`Behaviour::remove_address`].
     pub fn send_request(&mut self, peer: &PeerId, request: TCodec::Request) -> OutboundRequestId {
         let request_id = self.next_outbound_request_id();
+        if let Some(store) = self.request_store.as_mut() {
+            let deadline = SystemTime::now() + self.config.stored_request_ttl;
+            store.insert(*peer, request_id, &request, deadline);
+            self.stored_requests.insert(
+                request_id,
+                StoredRequest {
+                    peer: *peer,
+                    deadline,
+                    in_flight: true,
+                },
+            );
+            self.pending_events
+                .push_back(ToSwarm::GenerateEvent(Event::StoredRequest {
+                    peer: *peer,
+                    request_id,
+                    state: StoredRequestState::Stored { deadline },
+                }));
+        }
+
         let request = OutboundMessage {
             request_id,
             request,
             protocols: self.outbound_protocols.clone(),
+            subscription: false,
         };
+        self.send_or_dial(peer, request);
 
-        if let Some(request) = self.try_send_request(peer, request) {
-            self.pending_events.push_back(ToSwarm::Dial {
-                opts: DialOpts::peer_id(*peer).build(),
-            });
-            self.pending_outbound_requests
-                .entry(*peer)
-                .or_default()
-                .push(request);
-        }
+        request_id
+    }
+
+    /// Initiates a subscription, i.e. a request answered by a sequence of updates.
+    ///
+    /// Updates are reported as [`SubscriptionEvent::Update`] until the remote ends the
+    /// subscription, reported as [`SubscriptionEvent::Ended`], or it fails with an
+    /// [`Event::OutboundFailure`]. The remote only sends a bounded number of updates before they
+    /// are acknowledged via [`Behaviour::acknowledge_update`].
+    ///
+    /// Like for [`Behaviour::send_request`], the peer is dialed if not connected. Subscriptions
+    /// are not held by the [`RequestStore`].
+    pub fn subscribe(&mut self, peer: &PeerId, request: TCodec::Request) -> OutboundRequestId {
+        let request_id = self.next_outbound_request_id();
+        let request = OutboundMessage {
+            request_id,
+            request,
+            protocols: self.outbound_protocols.clone(),
+            subscription: true,
+        };
+        self.send_or_dial(peer, request);
 
         request_id
     }
 
+    /// Acknowledges the updates of an outbound subscription up to and including `seq`.
+    ///
+    /// Returns `false` if the subscription is not open.
+    pub fn acknowledge_update(
+        &mut self,
+        peer: &PeerId,
+        request_id: OutboundRequestId,
+        seq: u64,
+    ) -> bool {
+        self.notify_subscription(
+            peer,
+            request_id,
+            HandlerIn::AcknowledgeUpdate { request_id, seq },
+        )
+    }
+
+    /// Cancels an outbound subscription.
+    ///
+    /// No further events are reported for the subscription. Returns `false` if the subscription
+    /// is not pending.
+    pub fn cancel_subscription(&mut self, peer: &PeerId, request_id: OutboundRequestId) -> bool {
+        if let Some(pending) = self.pending_outbound_requests.get_mut(peer) {
+            if let Some(ix) = pending.iter().position(|r| r.request_id == request_id) {
+                pending.remove(ix);
+                return true;
+            }
+        }
+
+        let notified =
+            self.notify_subscription(peer, request_id, HandlerIn::CancelSubscription(request_id));
+        if let Some(connections) = self.connected.get_mut(peer) {
+            for connection in connections.iter_mut() {
+                connection.pending_outbound_responses.remove(&request_id);
+            }
+        }
+
+        notified
+    }
+
     /// Initiates sending a response to an inbound request.
     ///
     /// If the [`ResponseChannel`] is already closed due to a timeout or the
@@ -509,8 +1008,10 @@ where
             .get(peer)
             .map(|rps| rps.iter().any(|rp| rp.request_id == *request_id))
             .unwrap_or(false);
+        // Check if request is held by the request store.
+        let stored = self.stored_requests.contains_key(request_id);
 
-        est_conn || pen_conn
+        est_conn || pen_conn || stored
     }
 
     /// Checks whether an inbound request from the peer with the provided
@@ -533,6 +1034,44 @@ where
         request_id
     }
 
+    /// Sends a request if the peer is connected, otherwise queues it and dials the peer.
+    fn send_or_dial(&mut self, peer: &PeerId, request: OutboundMessage<TCodec>) {
+        if let Some(request) = self.try_send_request(peer, request) {
+            self.pending_events.push_back(ToSwarm::Dial {
+                opts: DialOpts::peer_id(*peer).build(),
+            });
+            self.pending_outbound_requests
+                .entry(*peer)
+                .or_default()
+                .push(request);
+        }
+    }
+
+    /// Passes the given event to the connection of an outbound subscription.
+    ///
+    /// Returns `false` if the subscription is not pending on any connection.
+    fn notify_subscription(
+        &mut self,
+        peer: &PeerId,
+        request_id: OutboundRequestId,
+        event: HandlerIn<TCodec>,
+    ) -> bool {
+        let Some(connection) = self.connected.get(peer).and_then(|connections| {
+            connections
+                .iter()
+                .find(|c| c.pending_outbound_responses.contains(&request_id))
+        }) else {
+            return false;
+        };
+        self.pending_events.push_back(ToSwarm::NotifyHandler {
+            peer_id: *peer,
+            handler: NotifyHandler::One(connection.id),
+            event,
+        });
+
+        true
+    }
+
     /// Tries to send a request by queueing an appropriate event to be
     /// emitted to the `Swarm`. If the peer is not currently connected,
     /// the given request is return unchanged.
@@ -551,7 +1090,7 @@ where
             self.pending_events.push_back(ToSwarm::NotifyHandler {
                 peer_id: *peer,
                 handler: NotifyHandler::One(conn.id),
-                event: request,
+                event: HandlerIn::Request(request),
             });
             None
         } else {
@@ -591,6 +1130,130 @@ where
             .unwrap_or(false)
     }
 
+    /// Reports a failed outbound request.
+    ///
+    /// Stored requests are requeued instead, unless the failure is permanent.
+    fn on_outbound_failure(
+        &mut self,
+        peer: PeerId,
+        request_id: OutboundRequestId,
+        error: OutboundFailure,
+    ) {
+        if let Some(stored) = self.stored_requests.get_mut(&request_id) {
+            if !matches!(error, OutboundFailure::UnsupportedProtocols) {
+                stored.in_flight = false;
+                self.pending_events
+                    .push_back(ToSwarm::GenerateEvent(Event::StoredRequest {
+                        peer,
+                        request_id,
+                        state: StoredRequestState::Requeued { error },
+                    }));
+                self.schedule_expiry();
+                return;
+            }
+
+            self.remove_stored_request(request_id);
+        }
+
+        self.pending_events
+            .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            }));
+    }
+
+    /// Removes a request from the request store, e.g. after a response was received.
+    fn remove_stored_request(&mut self, request_id: OutboundRequestId) {
+        if self.stored_requests.remove(&request_id).is_some() {
+            if let Some(store) = self.request_store.as_mut() {
+                store.remove(request_id);
+            }
+        }
+    }
+
+    /// Re-sends all requeued requests to a newly connected peer.
+    fn retry_stored_requests(&mut self, peer: PeerId) {
+        let request_ids = self
+            .stored_requests
+            .iter()
+            .filter(|(_, r)| r.peer == peer && !r.in_flight)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for request_id in request_ids {
+            let Some(request) = self
+                .request_store
+                .as_mut()
+                .and_then(|store| store.get(request_id))
+            else {
+                tracing::debug!("Request ({request_id}) is no longer held by the request store");
+                self.stored_requests.remove(&request_id);
+                continue;
+            };
+
+            let request = OutboundMessage {
+                request_id,
+                request,
+                protocols: self.outbound_protocols.clone(),
+                subscription: false,
+            };
+            if self.try_send_request(&peer, request).is_some() {
+                // The connection is already gone again, keep the request
+                // requeued for the next connection to the peer.
+                self.on_outbound_failure(peer, request_id, OutboundFailure::DialFailure);
+                continue;
+            }
+
+            if let Some(stored) = self.stored_requests.get_mut(&request_id) {
+                stored.in_flight = true;
+            }
+            self.pending_events
+                .push_back(ToSwarm::GenerateEvent(Event::StoredRequest {
+                    peer,
+                    request_id,
+                    state: StoredRequestState::Retrying,
+                }));
+        }
+    }
+
+    /// Removes all requeued requests whose deadline has passed.
+    fn expire_stored_requests(&mut self) {
+        let now = SystemTime::now();
+        let expired = self
+            .stored_requests
+            .iter()
+            .filter(|(_, r)| !r.in_flight && r.deadline <= now)
+            .map(|(id, r)| (*id, r.peer))
+            .collect::<Vec<_>>();
+
+        for (request_id, peer) in expired {
+            self.remove_stored_request(request_id);
+            self.pending_events
+                .push_back(ToSwarm::GenerateEvent(Event::StoredRequest {
+                    peer,
+                    request_id,
+                    state: StoredRequestState::Expired,
+                }));
+        }
+
+        self.schedule_expiry();
+    }
+
+    /// Sets `next_expiry` to the earliest deadline of all requeued requests.
+    ///
+    /// In-flight requests are only expired once they are requeued.
+    fn schedule_expiry(&mut self) {
+        let now = SystemTime::now();
+        self.next_expiry = self
+            .stored_requests
+            .values()
+            .filter(|r| !r.in_flight)
+            .map(|r| r.deadline)
+            .min()
+            .map(|deadline| Delay::new(deadline.duration_since(now).unwrap_or_default()));
+    }
+
     /// Returns a mutable reference to the connection in `self.connected`
     /// corresponding to the given [`PeerId`] and [`ConnectionId`].
     fn get_connection_mut(
@@ -663,12 +1326,7 @@ where
         }
 
         for request_id in connection.pending_outbound_responses {
-            self.pending_events
-                .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                    peer: peer_id,
-                    request_id,
-                    error: OutboundFailure::ConnectionClosed,
-                }));
+            self.on_outbound_failure(peer_id, request_id, OutboundFailure::ConnectionClosed);
         }
     }
 
@@ -682,12 +1340,11 @@ where
             // another, concurrent dialing attempt ongoing.
             if let Some(pending) = self.pending_outbound_requests.remove(&peer) {
                 for request in pending {
-                    self.pending_events
-                        .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                            peer,
-                            request_id: request.request_id,
-                            error: OutboundFailure::DialFailure,
-                        }));
+                    self.on_outbound_failure(
+                        peer,
+                        request.request_id,
+                        OutboundFailure::DialFailure,
+                    );
                 }
             }
         }
@@ -708,7 +1365,7 @@ where
                 connection
                     .pending_outbound_responses
                     .insert(request.request_id);
-                handler.on_behaviour_event(request);
+                handler.on_behaviour_event(HandlerIn::Request(request));
             }
         }
 
@@ -731,11 +1388,12 @@ where
         _: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         let mut handler = Handler::new(
+            peer,
             self.inbound_protocols.clone(),
             self.codec.clone(),
-            self.config.request_timeout,
+            &self.config,
             self.next_inbound_request_id.clone(),
-            self.config.max_concurrent_streams,
+            self.inbound_policy.clone(),
         );
 
         self.preload_new_handler(&mut handler, peer, connection_id, None);
@@ -774,11 +1432,12 @@ where
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         let mut handler = Handler::new(
+            peer,
             self.inbound_protocols.clone(),
             self.codec.clone(),
-            self.config.request_timeout,
+            &self.config,
             self.next_inbound_request_id.clone(),
-            self.config.max_concurrent_streams,
+            self.inbound_policy.clone(),
         );
 
         self.preload_new_handler(
@@ -794,7 +1453,9 @@ where
     fn on_swarm_event(&mut self, event: FromSwarm) {
         self.addresses.on_swarm_event(&event);
         match event {
-            FromSwarm::ConnectionEstablished(_) => {}
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { peer_id, .. }) => {
+                self.retry_stored_requests(peer_id)
+            }
             FromSwarm::ConnectionClosed(connection_closed) => {
                 self.on_connection_closed(connection_closed)
             }
@@ -820,6 +1481,7 @@ where
                     removed,
                     "Expect request_id to be pending before receiving response.",
                 );
+                self.remove_stored_request(request_id);
 
                 let message = Message::Response {
                     request_id,
@@ -848,6 +1510,13 @@ where
                 }
                 None => {
                     tracing::debug!("Connection ({connection}) closed after `Event::Request` ({request_id}) has been emitted.");
+                    self.pending_events.push_back(ToSwarm::GenerateEvent(
+                        Event::InboundRequestDropped {
+                            peer,
+                            request_id,
+                            reason: InboundDropReason::ConnectionClosed,
+                        },
+                    ));
                 }
             },
             handler::Event::ResponseSent(request_id) => {
@@ -877,6 +1546,32 @@ where
                         error: InboundFailure::ResponseOmission,
                     }));
             }
+            handler::Event::InboundRejected(request_id) => {
+                self.pending_events
+                    .push_back(ToSwarm::GenerateEvent(Event::InboundFailure {
+                        peer,
+                        request_id,
+                        error: InboundFailure::Rejected,
+                    }));
+            }
+            handler::Event::InboundDropped(request_id) => {
+                self.pending_events.push_back(ToSwarm::GenerateEvent(
+                    Event::InboundRequestDropped {
+                        peer,
+                        request_id,
+                        reason: InboundDropReason::AtCapacity,
+                    },
+                ));
+            }
+            handler::Event::InboundShed(request_id) => {
+                self.pending_events.push_back(ToSwarm::GenerateEvent(
+                    Event::InboundRequestDropped {
+                        peer,
+                        request_id,
+                        reason: InboundDropReason::MemoryBudget,
+                    },
+                ));
+            }
             handler::Event::OutboundTimeout(request_id) => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
                 debug_assert!(
@@ -884,12 +1579,7 @@ where
                     "Expect request_id to be pending before request times out."
                 );
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::Timeout,
-                    }));
+                self.on_outbound_failure(peer, request_id, OutboundFailure::Timeout);
             }
             handler::Event::OutboundUnsupportedProtocols(request_id) => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
@@ -898,23 +1588,13 @@ where
                     "Expect request_id to be pending before failing to connect.",
                 );
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::UnsupportedProtocols,
-                    }));
+                self.on_outbound_failure(peer, request_id, OutboundFailure::UnsupportedProtocols);
             }
             handler::Event::OutboundStreamFailed { request_id, error } => {
                 let removed = self.remove_pending_outbound_response(&peer, connection, request_id);
                 debug_assert!(removed, "Expect request_id to be pending upon failure");
 
-                self.pending_events
-                    .push_back(ToSwarm::GenerateEvent(Event::OutboundFailure {
-                        peer,
-                        request_id,
-                        error: OutboundFailure::Io(error),
-                    }))
+                self.on_outbound_failure(peer, request_id, OutboundFailure::Io(error));
             }
             handler::Event::InboundTimeout(request_id) => {
                 let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
@@ -931,6 +1611,95 @@ where
                     tracing::debug!(
                         "Inbound request timeout for an unknown request_id ({request_id})"
                     );
+                    self.pending_events.push_back(ToSwarm::GenerateEvent(
+                        Event::InboundRequestDropped {
+                            peer,
+                            request_id,
+                            reason: InboundDropReason::Timeout,
+                        },
+                    ));
+                }
+            }
+            handler::Event::InboundSubscription {
+                request_id,
+                request,
+                sender,
+            } => match self.get_connection_mut(&peer, connection) {
+                Some(connection) => {
+                    let inserted = connection.pending_inbound_responses.insert(request_id);
+                    debug_assert!(inserted, "Expect id of new subscription to be unknown.");
+
+                    let event = SubscriptionEvent::Subscribed {
+                        request_id,
+                        request,
+                        channel: SubscriptionChannel { sender },
+                    };
+                    self.pending_events
+                        .push_back(ToSwarm::GenerateEvent(Event::Subscription { peer, event }));
+                }
+                None => {
+                    tracing::debug!("Connection ({connection}) closed after `Event::InboundSubscription` ({request_id}) has been emitted.");
+                    self.pending_events.push_back(ToSwarm::GenerateEvent(
+                        Event::InboundRequestDropped {
+                            peer,
+                            request_id,
+                            reason: InboundDropReason::ConnectionClosed,
+                        },
+                    ));
+                }
+            },
+            handler::Event::InboundSubscriptionAcknowledged { request_id, seq } => {
+                let event = SubscriptionEvent::Acknowledged { request_id, seq };
+                self.pending_events
+                    .push_back(ToSwarm::GenerateEvent(Event::Subscription { peer, event }));
+            }
+            handler::Event::InboundSubscriptionCancelled(request_id) => {
+                let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
+                debug_assert!(
+                    removed,
+                    "Expect subscription to be pending before it is cancelled."
+                );
+
+                let event = SubscriptionEvent::Cancelled { request_id };
+                self.pending_events
+                    .push_back(ToSwarm::GenerateEvent(Event::Subscription { peer, event }));
+            }
+            handler::Event::InboundSubscriptionEnded(request_id) => {
+                let removed = self.remove_pending_inbound_response(&peer, connection, request_id);
+                debug_assert!(removed, "Expect subscription to be pending before it ends.");
+            }
+            // Events of outbound subscriptions may still be reported after they are cancelled,
+            // in which case they are no longer pending and are discarded.
+            handler::Event::OutboundSubscriptionUpdate {
+                request_id,
+                seq,
+                update,
+            } => {
+                let pending = self
+                    .get_connection_mut(&peer, connection)
+                    .map_or(false, |c| {
+                        c.pending_outbound_responses.contains(&request_id)
+                    });
+                if pending {
+                    let event = SubscriptionEvent::Update {
+                        request_id,
+                        seq,
+                        update,
+                    };
+                    self.pending_events
+                        .push_back(ToSwarm::GenerateEvent(Event::Subscription { peer, event }));
+                }
+            }
+            handler::Event::OutboundSubscriptionEnded(request_id) => {
+                if self.remove_pending_outbound_response(&peer, connection, request_id) {
+                    let event = SubscriptionEvent::Ended { request_id };
+                    self.pending_events
+                        .push_back(ToSwarm::GenerateEvent(Event::Subscription { peer, event }));
+                }
+            }
+            handler::Event::OutboundSubscriptionFailed { request_id, error } => {
+                if self.remove_pending_outbound_response(&peer, connection, request_id) {
+                    self.on_outbound_failure(peer, request_id, error);
                 }
             }
             handler::Event::InboundStreamFailed { request_id, error } => {
@@ -946,13 +1715,27 @@ where
                 } else {
                     // This happens when `read_request` fails.
                     tracing::debug!("Inbound failure is reported for an unknown request_id ({request_id}): {error}");
+                    self.pending_events.push_back(ToSwarm::GenerateEvent(
+                        Event::InboundRequestDropped {
+                            peer,
+                            request_id,
+                            reason: InboundDropReason::Io(error),
+                        },
+                    ));
                 }
             }
         }
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
-    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while let Some(Poll::Ready(())) = self.next_expiry.as_mut().map(|d| d.poll_unpin(cx)) {
+            self.expire_stored_requests();
+        }
+
         if let Some(ev) = self.pending_events.pop_front() {
             return Poll::Ready(ev);
         } else if self.pending_events.capacity() > EMPTY_QUEUE_SHRINK_THRESHOLD {
@@ -982,6 +1765,15 @@ struct Connection {
     pending_inbound_responses: HashSet<InboundRequestId>,
 }
 
+/// Internal information tracked for a request held by the [`RequestStore`].
+struct StoredRequest {
+    peer: PeerId,
+    deadline: SystemTime,
+    /// Whether the request has been handed to a connection (or is waiting for
+    /// a dial to complete). Only requeued requests are retried and expired.
+    in_flight: bool,
+}
+
 impl Connection {
     fn new(id: ConnectionId, remote_address: Option<Multiaddr>) -> Self {
         Self {
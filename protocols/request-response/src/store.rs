@@ -0,0 +1,132 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::OutboundRequestId;
+use instant::SystemTime;
+use libp2p_identity::PeerId;
+use std::collections::HashMap;
+
+/// Storage for outbound requests awaiting delivery.
+///
+/// Once a store is attached via [`Behaviour::with_request_store`](crate::Behaviour::with_request_store),
+/// every request sent via [`Behaviour::send_request`](crate::Behaviour::send_request) is
+/// inserted into the store and only removed after a response has been received or its deadline
+/// has passed. Requests that fail due to dial failures, closed connections, timeouts or IO errors
+/// are retried the next time a connection to the peer is established.
+///
+/// Implementations backed by persistent storage allow requests to survive restarts, see
+/// [`RequestStore::pending`].
+pub trait RequestStore<TRequest>: Send + 'static {
+    /// Stores a request which is to be delivered to `peer` before `deadline`.
+    fn insert(
+        &mut self,
+        peer: PeerId,
+        request_id: OutboundRequestId,
+        request: &TRequest,
+        deadline: SystemTime,
+    );
+
+    /// Returns a copy of the request stored under `request_id`, if any.
+    ///
+    /// Called every time the request is (re-)sent.
+    fn get(&mut self, request_id: OutboundRequestId) -> Option<TRequest>;
+
+    /// Removes the request stored under `request_id`.
+    fn remove(&mut self, request_id: OutboundRequestId);
+
+    /// Returns all stored requests.
+    ///
+    /// Called once when the store is attached to a [`Behaviour`](crate::Behaviour). The returned
+    /// requests are retried as soon as a connection to their peer is established.
+    fn pending(&mut self) -> Vec<PendingRequest>;
+}
+
+/// A request held by a [`RequestStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingRequest {
+    pub peer: PeerId,
+    pub request_id: OutboundRequestId,
+    pub deadline: SystemTime,
+}
+
+/// A [`RequestStore`] keeping requests in memory.
+///
+/// Requests are retried across reconnects but don't survive a restart.
+#[derive(Debug)]
+pub struct MemoryStore<TRequest> {
+    requests: HashMap<OutboundRequestId, (PendingRequest, TRequest)>,
+}
+
+impl<TRequest> Default for MemoryStore<TRequest> {
+    fn default() -> Self {
+        Self {
+            requests: HashMap::new(),
+        }
+    }
+}
+
+impl<TRequest> MemoryStore<TRequest> {
+    /// Returns the number of stored requests.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns `true` if no requests are stored.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
+
+impl<TRequest> RequestStore<TRequest> for MemoryStore<TRequest>
+where
+    TRequest: Clone + Send + 'static,
+{
+    fn insert(
+        &mut self,
+        peer: PeerId,
+        request_id: OutboundRequestId,
+        request: &TRequest,
+        deadline: SystemTime,
+    ) {
+        let pending = PendingRequest {
+            peer,
+            request_id,
+            deadline,
+        };
+        self.requests.insert(request_id, (pending, request.clone()));
+    }
+
+    fn get(&mut self, request_id: OutboundRequestId) -> Option<TRequest> {
+        self.requests
+            .get(&request_id)
+            .map(|(_, request)| request.clone())
+    }
+
+    fn remove(&mut self, request_id: OutboundRequestId) {
+        self.requests.remove(&request_id);
+    }
+
+    fn pending(&mut self) -> Vec<PendingRequest> {
+        self.requests
+            .values()
+            .map(|(pending, _)| *pending)
+            .collect()
+    }
+}
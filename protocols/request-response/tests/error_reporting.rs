@@ -8,7 +8,8 @@ use libp2p_request_response::ProtocolSupport;
 use libp2p_swarm::{StreamProtocol, Swarm};
 use libp2p_swarm_test::SwarmExt;
 use request_response::{
-    Codec, InboundFailure, InboundRequestId, OutboundFailure, OutboundRequestId, ResponseChannel,
+    Codec, InboundAdmission, InboundDropReason, InboundFailure, InboundRequestId, OutboundFailure,
+    OutboundRequestId, ResponseChannel,
 };
 use std::pin::pin;
 use std::time::Duration;
@@ -83,9 +84,21 @@ async fn report_outbound_failure_on_write_request() {
     swarm1.listen().with_memory_addr_external().await;
     swarm2.connect(&mut swarm1).await;
 
-    // Expects no events because `Event::Request` is produced after `read_request`.
+    // Expects no `Event::Request` because it is produced after `read_request`, at most the
+    // request being dropped, depending on whether the stream reached `swarm1`.
     // Keep the connection alive, otherwise swarm2 may receive `ConnectionClosed` instead.
-    let server_task = wait_no_events(&mut swarm1);
+    let server_task = async move {
+        loop {
+            match swarm1.select_next_some().await.try_into_behaviour_event() {
+                Ok(request_response::Event::InboundRequestDropped {
+                    reason: InboundDropReason::Io(_),
+                    ..
+                })
+                | Err(_) => {}
+                Ok(ev) => panic!("Unexpected event: {ev:?}"),
+            }
+        }
+    };
 
     // Expects OutboundFailure::Io failure with `FailOnWriteRequest` error.
     let client_task = async move {
@@ -213,6 +226,69 @@ async fn report_outbound_failure_on_max_streams() {
     futures::future::select(swarm1_task, swarm2_task).await;
 }
 
+#[async_std::test]
+async fn report_dropped_inbound_request_on_max_streams() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    // `swarm1` will be able to handle only 1 stream per time.
+    let swarm1_config = request_response::Config::default()
+        .with_request_timeout(Duration::from_secs(10))
+        .with_max_concurrent_streams(1);
+
+    let (peer1_id, mut swarm1) = new_swarm_with_config(swarm1_config);
+    let (peer2_id, mut swarm2) = new_swarm_with_timeout(Duration::from_secs(10));
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    // The stream of the first request is still pending, thus the second one is dropped,
+    // possibly before the first one has been read.
+    let server_task = async move {
+        let mut channel = None;
+        let mut dropped = false;
+
+        while channel.is_none() || !dropped {
+            match swarm1.select_next_some().await.try_into_behaviour_event() {
+                Ok(request_response::Event::Message {
+                    peer,
+                    message: request_response::Message::Request { channel: ch, .. },
+                }) => {
+                    assert_eq!(peer, peer2_id);
+                    assert!(channel.replace(ch).is_none());
+                }
+                Ok(request_response::Event::InboundRequestDropped { peer, reason, .. }) => {
+                    assert_eq!(peer, peer2_id);
+                    assert!(matches!(reason, InboundDropReason::AtCapacity));
+                    assert!(!dropped);
+                    dropped = true;
+                }
+                Ok(ev) => panic!("Unexpected event: {ev:?}"),
+                Err(..) => {}
+            }
+        }
+    };
+
+    // Keep the connection alive, otherwise swarm1 may receive `ConnectionClosed` instead.
+    let client_task = async move {
+        swarm2
+            .behaviour_mut()
+            .send_request(&peer1_id, Action::FailOnMaxStreams);
+        swarm2
+            .behaviour_mut()
+            .send_request(&peer1_id, Action::FailOnMaxStreams);
+
+        loop {
+            swarm2.select_next_some().await;
+        }
+    };
+
+    let server_task = pin!(server_task);
+    let client_task = pin!(client_task);
+    futures::future::select(server_task, client_task).await;
+}
+
 #[async_std::test]
 async fn report_inbound_failure_on_read_request() {
     let _ = tracing_subscriber::fmt()
@@ -220,14 +296,20 @@ async fn report_inbound_failure_on_read_request() {
         .try_init();
 
     let (peer1_id, mut swarm1) = new_swarm();
-    let (_peer2_id, mut swarm2) = new_swarm();
+    let (peer2_id, mut swarm2) = new_swarm();
 
     swarm1.listen().with_memory_addr_external().await;
     swarm2.connect(&mut swarm1).await;
 
-    // Expects no events because `Event::Request` is produced after `read_request`.
+    // Expects the request to be dropped because `Event::Request` is produced after `read_request`.
     // Keep the connection alive, otherwise swarm2 may receive `ConnectionClosed` instead.
-    let server_task = wait_no_events(&mut swarm1);
+    let server_task = async move {
+        let (peer, _req_id, reason) = wait_inbound_dropped(&mut swarm1).await.unwrap();
+        assert_eq!(peer, peer2_id);
+        assert!(matches!(reason, InboundDropReason::Io(_)));
+
+        wait_no_events(&mut swarm1).await;
+    };
 
     // Expects io::ErrorKind::UnexpectedEof
     let client_task = async move {
@@ -373,6 +455,86 @@ async fn report_inbound_timeout_on_write_response() {
     futures::future::select(server_task, client_task).await;
 }
 
+#[async_std::test]
+async fn report_inbound_failure_on_rejected_request() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (peer1_id, mut swarm1) = new_swarm_with_inbound_filter(|_, _, _| InboundAdmission::Reject);
+    let (peer2_id, mut swarm2) = new_swarm();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    let server_task = async move {
+        let (peer, _, error) = wait_inbound_failure(&mut swarm1).await.unwrap();
+        assert_eq!(peer, peer2_id);
+        assert!(matches!(error, InboundFailure::Rejected));
+
+        // Keep the connection alive, otherwise swarm2 may receive `ConnectionClosed` instead.
+        wait_no_events(&mut swarm1).await;
+    };
+
+    // Expects an IO error since the stream is dropped without a response.
+    let client_task = async move {
+        let req_id = swarm2
+            .behaviour_mut()
+            .send_request(&peer1_id, Action::FailOnReadRequest);
+
+        let (peer, req_id_done, error) = wait_outbound_failure(&mut swarm2).await.unwrap();
+        assert_eq!(peer, peer1_id);
+        assert_eq!(req_id_done, req_id);
+        assert!(matches!(error, OutboundFailure::Io(_)));
+    };
+
+    let server_task = pin!(server_task);
+    let client_task = pin!(client_task);
+    futures::future::select(server_task, client_task).await;
+}
+
+#[async_std::test]
+async fn respond_to_rejected_request_without_reading_it() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    // `FailOnReadRequest` would fail on the server if the request was read.
+    let (peer1_id, mut swarm1) = new_swarm_with_inbound_filter(|_, protocol, pending| {
+        assert_eq!(protocol.as_ref(), "/test/1");
+        assert_eq!(pending, 0);
+        InboundAdmission::Respond(Action::Rejected)
+    });
+    let (peer2_id, mut swarm2) = new_swarm();
+
+    swarm1.listen().with_memory_addr_external().await;
+    swarm2.connect(&mut swarm1).await;
+
+    let server_task = async move {
+        let (peer, _, error) = wait_inbound_failure(&mut swarm1).await.unwrap();
+        assert_eq!(peer, peer2_id);
+        assert!(matches!(error, InboundFailure::Rejected));
+
+        // Keep the connection alive, otherwise swarm2 may receive `ConnectionClosed` instead.
+        wait_no_events(&mut swarm1).await;
+    };
+
+    let client_task = async move {
+        let req_id = swarm2
+            .behaviour_mut()
+            .send_request(&peer1_id, Action::FailOnReadRequest);
+
+        let (peer, req_id_done, response) = wait_response(&mut swarm2).await.unwrap();
+        assert_eq!(peer, peer1_id);
+        assert_eq!(req_id_done, req_id);
+        assert_eq!(response, Action::Rejected);
+    };
+
+    let server_task = pin!(server_task);
+    let client_task = pin!(client_task);
+    futures::future::select(server_task, client_task).await;
+}
+
 #[derive(Clone, Default)]
 struct TestCodec;
 
@@ -385,6 +547,7 @@ enum Action {
     FailOnWriteResponse,
     TimeoutOnWriteResponse,
     FailOnMaxStreams,
+    Rejected,
 }
 
 impl From<Action> for u8 {
@@ -397,6 +560,7 @@ impl From<Action> for u8 {
             Action::FailOnWriteResponse => 4,
             Action::TimeoutOnWriteResponse => 5,
             Action::FailOnMaxStreams => 6,
+            Action::Rejected => 7,
         }
     }
 }
@@ -413,6 +577,7 @@ impl TryFrom<u8> for Action {
             4 => Ok(Action::FailOnWriteResponse),
             5 => Ok(Action::TimeoutOnWriteResponse),
             6 => Ok(Action::FailOnMaxStreams),
+            7 => Ok(Action::Rejected),
             _ => Err(io::Error::new(io::ErrorKind::Other, "invalid action")),
         }
     }
@@ -535,6 +700,20 @@ fn new_swarm_with_config(
     (peed_id, swarm)
 }
 
+fn new_swarm_with_inbound_filter(
+    filter: impl Fn(&PeerId, &StreamProtocol, usize) -> InboundAdmission<Action> + Send + Sync + 'static,
+) -> (PeerId, Swarm<request_response::Behaviour<TestCodec>>) {
+    let protocols = iter::once((StreamProtocol::new("/test/1"), ProtocolSupport::Full));
+    let cfg = request_response::Config::default().with_request_timeout(Duration::from_millis(100));
+
+    let swarm = Swarm::new_ephemeral(|_| {
+        request_response::Behaviour::<TestCodec>::new(protocols, cfg).with_inbound_filter(filter)
+    });
+    let peed_id = *swarm.local_peer_id();
+
+    (peed_id, swarm)
+}
+
 fn new_swarm_with_timeout(
     timeout: Duration,
 ) -> (PeerId, Swarm<request_response::Behaviour<TestCodec>>) {
@@ -576,6 +755,27 @@ async fn wait_request(
     }
 }
 
+async fn wait_response(
+    swarm: &mut Swarm<request_response::Behaviour<TestCodec>>,
+) -> Result<(PeerId, OutboundRequestId, Action)> {
+    loop {
+        match swarm.select_next_some().await.try_into_behaviour_event() {
+            Ok(request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+            }) => {
+                return Ok((peer, request_id, response));
+            }
+            Ok(ev) => bail!("Unexpected event: {ev:?}"),
+            Err(..) => {}
+        }
+    }
+}
+
 async fn wait_response_sent(
     swarm: &mut Swarm<request_response::Behaviour<TestCodec>>,
 ) -> Result<(PeerId, InboundRequestId)> {
@@ -610,6 +810,24 @@ async fn wait_inbound_failure(
     }
 }
 
+async fn wait_inbound_dropped(
+    swarm: &mut Swarm<request_response::Behaviour<TestCodec>>,
+) -> Result<(PeerId, InboundRequestId, InboundDropReason)> {
+    loop {
+        match swarm.select_next_some().await.try_into_behaviour_event() {
+            Ok(request_response::Event::InboundRequestDropped {
+                peer,
+                request_id,
+                reason,
+            }) => {
+                return Ok((peer, request_id, reason));
+            }
+            Ok(ev) => bail!("Unexpected event: {ev:?}"),
+            Err(..) => {}
+        }
+    }
+}
+
 async fn wait_outbound_failure(
     swarm: &mut Swarm<request_response::Behaviour<TestCodec>>,
 ) -> Result<(PeerId, OutboundRequestId, OutboundFailure)> {
@@ -0,0 +1,137 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for [`Config::with_memory_budget`](request_response::Config::with_memory_budget).
+
+#![cfg(feature = "cbor")]
+
+use futures::{channel::mpsc, prelude::*};
+use libp2p_request_response as request_response;
+use libp2p_request_response::{InboundDropReason, ProtocolSupport, StreamMode};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use std::iter;
+use tracing_subscriber::EnvFilter;
+
+const BUDGET: usize = 1024;
+
+#[async_std::test]
+async fn sheds_requests_exceeding_memory_budget() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    for mode in [StreamMode::PerRequest, StreamMode::Persistent] {
+        let mut server = new_swarm(mode);
+        let mut client = new_swarm(mode);
+        let server_id = *server.local_peer_id();
+
+        server.listen().with_memory_addr_external().await;
+        client.connect(&mut server).await;
+
+        let (tx, mut server_events) = mpsc::channel(8);
+        async_std::task::spawn(serve(server, tx));
+
+        let request_id = client
+            .behaviour_mut()
+            .send_request(&server_id, vec![0; 4 * BUDGET]);
+        match client.next_behaviour_event().await {
+            request_response::Event::OutboundFailure { request_id: id, .. } => {
+                assert_eq!(id, request_id)
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+        assert!(matches!(
+            server_events.next().await.unwrap(),
+            ServerEvent::Dropped(InboundDropReason::MemoryBudget)
+        ));
+
+        // Requests within the budget are served, releasing their bytes once answered.
+        let request_id = client
+            .behaviour_mut()
+            .send_request(&server_id, vec![0; BUDGET / 2]);
+        match client.next_behaviour_event().await {
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id: id,
+                        response,
+                    },
+                ..
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(response, vec![1]);
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+        match server_events.next().await.unwrap() {
+            ServerEvent::ResponseSent { memory_usage } => assert_eq!(memory_usage, Some(0)),
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ServerEvent {
+    Dropped(InboundDropReason),
+    ResponseSent { memory_usage: Option<usize> },
+}
+
+fn new_swarm(mode: StreamMode) -> Swarm<request_response::cbor::Behaviour<Vec<u8>, Vec<u8>>> {
+    Swarm::new_ephemeral(|_| {
+        request_response::cbor::Behaviour::new(
+            iter::once((StreamProtocol::new("/budget/1"), ProtocolSupport::Full)),
+            request_response::Config::default()
+                .with_stream_mode(mode)
+                .with_memory_budget(BUDGET),
+        )
+    })
+}
+
+/// Answers all requests, reporting dropped requests and sent responses via `events`.
+async fn serve(
+    mut swarm: Swarm<request_response::cbor::Behaviour<Vec<u8>, Vec<u8>>>,
+    mut events: mpsc::Sender<ServerEvent>,
+) {
+    loop {
+        let event = match swarm.next_swarm_event().await.try_into_behaviour_event() {
+            Ok(request_response::Event::Message {
+                message: request_response::Message::Request { channel, .. },
+                ..
+            }) => {
+                swarm
+                    .behaviour_mut()
+                    .send_response(channel, vec![1])
+                    .unwrap();
+                continue;
+            }
+            Ok(request_response::Event::InboundRequestDropped { reason, .. }) => {
+                ServerEvent::Dropped(reason)
+            }
+            Ok(request_response::Event::ResponseSent { .. }) => ServerEvent::ResponseSent {
+                memory_usage: swarm.behaviour().memory_usage(),
+            },
+            _ => continue,
+        };
+        if events.send(event).await.is_err() {
+            return;
+        }
+    }
+}
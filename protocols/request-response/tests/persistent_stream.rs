@@ -0,0 +1,334 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for [`StreamMode::Persistent`](request_response::StreamMode::Persistent).
+
+#![cfg(feature = "cbor")]
+
+use futures::future;
+use futures::FutureExt;
+use libp2p_request_response as request_response;
+use libp2p_request_response::{ProtocolSupport, StreamMode};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::SwarmExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::iter;
+use std::time::{Duration, Instant};
+use tracing_subscriber::EnvFilter;
+
+#[async_std::test]
+async fn pipelines_requests_over_persistent_stream() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(StreamMode::Persistent);
+    let mut client = new_swarm(StreamMode::Persistent);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    // Requests 3 and 7 are not answered by the server.
+    async_std::task::spawn(serve(server, |n| n % 4 != 3));
+
+    let mut pending = (0..10u8)
+        .map(|n| (client.behaviour_mut().send_request(&server_id, Ping(n)), n))
+        .collect::<HashMap<_, _>>();
+
+    while !pending.is_empty() {
+        match client.next_behaviour_event().await {
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => {
+                let n = pending.remove(&request_id).unwrap();
+                assert_eq!(response, Pong(n));
+                assert_ne!(n % 4, 3);
+            }
+            request_response::Event::OutboundFailure {
+                request_id, error, ..
+            } => {
+                let n = pending.remove(&request_id).unwrap();
+                assert_eq!(n % 4, 3, "Unexpected failure: {error:?}");
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+
+    // The connection stays usable after the omitted responses.
+    let request_id = client.behaviour_mut().send_request(&server_id, Ping(42));
+    match client.next_behaviour_event().await {
+        request_response::Event::Message {
+            message:
+                request_response::Message::Response {
+                    request_id: id,
+                    response,
+                },
+            ..
+        } => {
+            assert_eq!(id, request_id);
+            assert_eq!(response, Pong(42));
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    }
+}
+
+#[async_std::test]
+async fn responses_are_not_held_back_by_earlier_requests() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(StreamMode::Persistent);
+    let mut client = new_swarm(StreamMode::Persistent);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    let first = client.behaviour_mut().send_request(&server_id, Ping(0));
+    let second = client.behaviour_mut().send_request(&server_id, Ping(1));
+
+    // The server answers the second request while still holding on to the first.
+    let mut held = None;
+    let pending = async {
+        loop {
+            if let Ok(request_response::Event::Message {
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            }) = server.next_swarm_event().await.try_into_behaviour_event()
+            {
+                if request == Ping(0) {
+                    held = Some(channel);
+                } else {
+                    server
+                        .behaviour_mut()
+                        .send_response(channel, Pong(request.0))
+                        .unwrap();
+                }
+            }
+        }
+    };
+    let response = async {
+        loop {
+            if let request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } = client.next_behaviour_event().await
+            {
+                return (request_id, response);
+            }
+        }
+    };
+    let (request_id, response) = match future::select(pending.boxed(), response.boxed()).await {
+        future::Either::Left(_) => unreachable!(),
+        future::Either::Right((response, _)) => response,
+    };
+    assert_eq!(request_id, second);
+    assert_eq!(response, Pong(1));
+
+    server
+        .behaviour_mut()
+        .send_response(held.take().unwrap(), Pong(0))
+        .unwrap();
+    async_std::task::spawn(serve(server, |_| true));
+    match client.next_behaviour_event().await {
+        request_response::Event::Message {
+            message:
+                request_response::Message::Response {
+                    request_id,
+                    response,
+                },
+            ..
+        } => {
+            assert_eq!(request_id, first);
+            assert_eq!(response, Pong(0));
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    }
+}
+
+#[async_std::test]
+async fn falls_back_to_stream_per_request() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(StreamMode::PerRequest);
+    let mut client = new_swarm(StreamMode::Persistent);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    async_std::task::spawn(serve(server, |_| true));
+
+    for n in 0..3u8 {
+        let request_id = client.behaviour_mut().send_request(&server_id, Ping(n));
+        match client.next_behaviour_event().await {
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id: id,
+                        response,
+                    },
+                ..
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(response, Pong(n));
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+}
+
+#[async_std::test]
+async fn request_times_out_while_other_requests_are_answered() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let timeout = Duration::from_millis(500);
+    let mut server = new_swarm(StreamMode::Persistent);
+    let mut client = new_swarm_with_config(
+        request_response::Config::default()
+            .with_stream_mode(StreamMode::Persistent)
+            .with_request_timeout(timeout),
+    );
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    // The server holds on to the first request, answering all others.
+    async_std::task::spawn(async move {
+        let mut held = Vec::new();
+        loop {
+            if let Ok(request_response::Event::Message {
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            }) = server.next_swarm_event().await.try_into_behaviour_event()
+            {
+                if request == Ping(0) {
+                    held.push(channel);
+                } else {
+                    server
+                        .behaviour_mut()
+                        .send_response(channel, Pong(request.0))
+                        .unwrap();
+                }
+            }
+        }
+    });
+
+    let started = Instant::now();
+    let first = client.behaviour_mut().send_request(&server_id, Ping(0));
+    client.behaviour_mut().send_request(&server_id, Ping(1));
+
+    // Keep responses arriving on the stream well past the timeout of the first request.
+    loop {
+        match client.next_behaviour_event().await {
+            request_response::Event::Message {
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                assert!(
+                    started.elapsed() < timeout * 4,
+                    "First request never timed out"
+                );
+                async_std::task::sleep(timeout / 5).await;
+                client
+                    .behaviour_mut()
+                    .send_request(&server_id, Ping(response.0.wrapping_add(1).max(1)));
+            }
+            request_response::Event::OutboundFailure {
+                request_id,
+                error: request_response::OutboundFailure::Timeout,
+                ..
+            } => {
+                // Requests pending on the same stream time out along with it.
+                if request_id == first {
+                    break;
+                }
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+}
+
+fn new_swarm(mode: StreamMode) -> Swarm<request_response::cbor::Behaviour<Ping, Pong>> {
+    new_swarm_with_config(request_response::Config::default().with_stream_mode(mode))
+}
+
+fn new_swarm_with_config(
+    config: request_response::Config,
+) -> Swarm<request_response::cbor::Behaviour<Ping, Pong>> {
+    Swarm::new_ephemeral(|_| {
+        request_response::cbor::Behaviour::new(
+            iter::once((StreamProtocol::new("/ping/1"), ProtocolSupport::Full)),
+            config,
+        )
+    })
+}
+
+/// Answers the requests for which `answer` returns `true`, dropping the others.
+async fn serve(
+    mut swarm: Swarm<request_response::cbor::Behaviour<Ping, Pong>>,
+    answer: impl Fn(u8) -> bool,
+) {
+    loop {
+        if let Ok(request_response::Event::Message {
+            message:
+                request_response::Message::Request {
+                    request, channel, ..
+                },
+            ..
+        }) = swarm.next_swarm_event().await.try_into_behaviour_event()
+        {
+            if answer(request.0) {
+                swarm
+                    .behaviour_mut()
+                    .send_response(channel, Pong(request.0))
+                    .unwrap();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Ping(u8);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Pong(u8);
@@ -0,0 +1,194 @@
+//! Integration tests for at-least-once delivery via a `RequestStore`.
+
+use futures::prelude::*;
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr, NegotiatedProtocols};
+use libp2p_identity::PeerId;
+use libp2p_request_response as request_response;
+use libp2p_request_response::{MemoryStore, ProtocolSupport, StoredRequestState};
+use libp2p_swarm::{
+    behaviour::ConnectionEstablished, ConnectionId, FromSwarm, NetworkBehaviour, StreamProtocol,
+    Swarm, SwarmEvent,
+};
+use libp2p_swarm_test::SwarmExt;
+use serde::{Deserialize, Serialize};
+use std::{iter, time::Duration};
+use tracing_subscriber::EnvFilter;
+
+#[async_std::test]
+#[cfg(feature = "cbor")]
+async fn stored_request_is_retried_on_connection_established() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| {
+        behaviour(request_response::Config::default()).with_request_store(MemoryStore::default())
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|_| behaviour(request_response::Config::default()));
+    let peer2_id = *swarm2.local_peer_id();
+
+    let request_id = swarm1
+        .behaviour_mut()
+        .send_request(&peer2_id, Ping(b"ping".to_vec()));
+
+    // No addresses are known for `peer2_id`, thus dialing fails and the request is requeued.
+    assert!(matches!(
+        swarm1.next_behaviour_event().await,
+        request_response::Event::StoredRequest {
+            state: StoredRequestState::Stored { .. },
+            ..
+        }
+    ));
+    assert!(matches!(
+        swarm1.next_behaviour_event().await,
+        request_response::Event::StoredRequest {
+            state: StoredRequestState::Requeued {
+                error: request_response::OutboundFailure::DialFailure
+            },
+            ..
+        }
+    ));
+    assert!(swarm1
+        .behaviour()
+        .is_pending_outbound(&peer2_id, &request_id));
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+
+    async_std::task::spawn(async move {
+        loop {
+            if let SwarmEvent::Behaviour(request_response::Event::Message {
+                message: request_response::Message::Request { channel, .. },
+                ..
+            }) = swarm2.select_next_some().await
+            {
+                swarm2
+                    .behaviour_mut()
+                    .send_response(channel, Pong(b"pong".to_vec()))
+                    .unwrap();
+            }
+        }
+    });
+
+    let response = swarm1
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Response {
+                        request_id: id,
+                        response,
+                    },
+            }) if peer == peer2_id && id == request_id => Some(response),
+            _ => None,
+        })
+        .await;
+
+    assert_eq!(response, Pong(b"pong".to_vec()));
+    assert!(!swarm1
+        .behaviour()
+        .is_pending_outbound(&peer2_id, &request_id));
+}
+
+#[async_std::test]
+#[cfg(feature = "cbor")]
+async fn stored_request_stays_requeued_if_connection_is_gone() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm = Swarm::new_ephemeral(|_| {
+        behaviour(request_response::Config::default()).with_request_store(MemoryStore::default())
+    });
+    let offline_peer = PeerId::random();
+
+    let request_id = swarm
+        .behaviour_mut()
+        .send_request(&offline_peer, Ping(b"ping".to_vec()));
+    swarm
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(request_response::Event::StoredRequest {
+                state: StoredRequestState::Requeued { .. },
+                ..
+            }) => Some(()),
+            _ => None,
+        })
+        .await;
+
+    // Report a connection for which no handler was ever created, e.g. because it
+    // was closed again right away.
+    let endpoint = ConnectedPoint::Dialer {
+        address: Multiaddr::empty(),
+        role_override: Endpoint::Dialer,
+    };
+    swarm
+        .behaviour_mut()
+        .on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+            peer_id: offline_peer,
+            connection_id: ConnectionId::new_unchecked(0),
+            endpoint: &endpoint,
+            failed_addresses: &[],
+            other_established: 0,
+            negotiated_protocols: &NegotiatedProtocols::default(),
+        }));
+
+    assert!(matches!(
+        swarm.next_behaviour_event().await,
+        request_response::Event::StoredRequest {
+            request_id: id,
+            state: StoredRequestState::Requeued {
+                error: request_response::OutboundFailure::DialFailure
+            },
+            ..
+        } if id == request_id
+    ));
+    assert!(swarm
+        .behaviour()
+        .is_pending_outbound(&offline_peer, &request_id));
+}
+
+#[async_std::test]
+#[cfg(feature = "cbor")]
+async fn stored_request_expires_after_ttl() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let cfg =
+        request_response::Config::default().with_stored_request_ttl(Duration::from_millis(100));
+    let mut swarm =
+        Swarm::new_ephemeral(|_| behaviour(cfg).with_request_store(MemoryStore::default()));
+    let offline_peer = PeerId::random();
+
+    let request_id = swarm
+        .behaviour_mut()
+        .send_request(&offline_peer, Ping(b"ping".to_vec()));
+
+    let expired = swarm
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(request_response::Event::StoredRequest {
+                request_id,
+                state: StoredRequestState::Expired,
+                ..
+            }) => Some(request_id),
+            _ => None,
+        })
+        .await;
+
+    assert_eq!(expired, request_id);
+    assert!(!swarm
+        .behaviour()
+        .is_pending_outbound(&offline_peer, &request_id));
+}
+
+fn behaviour(cfg: request_response::Config) -> request_response::cbor::Behaviour<Ping, Pong> {
+    request_response::cbor::Behaviour::new(
+        iter::once((StreamProtocol::new("/ping/1"), ProtocolSupport::Full)),
+        cfg,
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Ping(Vec<u8>);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Pong(Vec<u8>);
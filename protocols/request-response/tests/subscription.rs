@@ -0,0 +1,211 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Integration tests for [`Behaviour::subscribe`](request_response::Behaviour::subscribe).
+
+#![cfg(feature = "cbor")]
+
+use libp2p_request_response as request_response;
+use libp2p_request_response::{OutboundFailure, ProtocolSupport, SubscriptionEvent};
+use libp2p_swarm::{StreamProtocol, Swarm};
+use libp2p_swarm_test::{drive, SwarmExt};
+use serde::{Deserialize, Serialize};
+use std::iter;
+use tracing_subscriber::EnvFilter;
+
+#[async_std::test]
+async fn updates_are_pushed_until_subscription_ends() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(true);
+    let mut client = new_swarm(false);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    // More updates than are sent or buffered without being acknowledged.
+    async_std::task::spawn(publish(server, 50));
+
+    let request_id = client.behaviour_mut().subscribe(&server_id, Subscribe(50));
+    let mut expected = 0;
+    loop {
+        match client.next_behaviour_event().await {
+            request_response::Event::Subscription {
+                event:
+                    SubscriptionEvent::Update {
+                        request_id: id,
+                        seq,
+                        update,
+                    },
+                ..
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!((seq, update), (expected, Update(expected)));
+                assert!(client
+                    .behaviour_mut()
+                    .acknowledge_update(&server_id, request_id, seq));
+                expected += 1;
+            }
+            request_response::Event::Subscription {
+                event: SubscriptionEvent::Ended { request_id: id },
+                ..
+            } => {
+                assert_eq!(id, request_id);
+                break;
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+    assert_eq!(expected, 50);
+    assert!(!client
+        .behaviour()
+        .is_pending_outbound(&server_id, &request_id));
+}
+
+#[async_std::test]
+async fn cancelled_subscription_is_reported_to_publisher() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(true);
+    let mut client = new_swarm(false);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+
+    let request_id = client.behaviour_mut().subscribe(&server_id, Subscribe(1));
+    let ([subscribed], []): ([Event; 1], [Event; 0]) = drive(&mut server, &mut client).await;
+    let (inbound_id, mut channel) = match subscribed {
+        request_response::Event::Subscription {
+            event:
+                SubscriptionEvent::Subscribed {
+                    request_id,
+                    request,
+                    channel,
+                },
+            ..
+        } => {
+            assert_eq!(request, Subscribe(1));
+            (request_id, channel)
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    };
+    channel.send(Update(0)).unwrap();
+
+    let ([], [update]): ([Event; 0], [Event; 1]) = drive(&mut server, &mut client).await;
+    match update {
+        request_response::Event::Subscription {
+            event: SubscriptionEvent::Update { seq: 0, .. },
+            ..
+        } => {}
+        e => panic!("Unexpected event: {e:?}"),
+    }
+    assert!(client
+        .behaviour_mut()
+        .cancel_subscription(&server_id, request_id));
+    assert!(!client
+        .behaviour()
+        .is_pending_outbound(&server_id, &request_id));
+    async_std::task::spawn(client.loop_on_next());
+
+    match server.next_behaviour_event().await {
+        request_response::Event::Subscription {
+            event: SubscriptionEvent::Cancelled { request_id },
+            ..
+        } => assert_eq!(request_id, inbound_id),
+        e => panic!("Unexpected event: {e:?}"),
+    }
+    assert!(!channel.is_open());
+}
+
+#[async_std::test]
+async fn subscriptions_are_not_accepted_unless_enabled() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let mut server = new_swarm(false);
+    let mut client = new_swarm(false);
+    let server_id = *server.local_peer_id();
+
+    server.listen().with_memory_addr_external().await;
+    client.connect(&mut server).await;
+    async_std::task::spawn(server.loop_on_next());
+
+    let request_id = client.behaviour_mut().subscribe(&server_id, Subscribe(1));
+    match client.next_behaviour_event().await {
+        request_response::Event::OutboundFailure {
+            request_id: id,
+            error: OutboundFailure::UnsupportedProtocols,
+            ..
+        } => assert_eq!(id, request_id),
+        e => panic!("Unexpected event: {e:?}"),
+    }
+}
+
+fn new_swarm(subscriptions: bool) -> Swarm<request_response::cbor::Behaviour<Subscribe, Update>> {
+    Swarm::new_ephemeral(|_| {
+        request_response::cbor::Behaviour::new(
+            iter::once((StreamProtocol::new("/updates/1"), ProtocolSupport::Full)),
+            request_response::Config::default().with_subscriptions(subscriptions),
+        )
+    })
+}
+
+/// Pushes `n` updates to the subscriber, resuming whenever it acknowledges updates, and ends
+/// the subscription afterwards.
+async fn publish(mut swarm: Swarm<request_response::cbor::Behaviour<Subscribe, Update>>, n: u64) {
+    let mut channel = None;
+    let mut next = 0;
+    loop {
+        match swarm.next_swarm_event().await.try_into_behaviour_event() {
+            Ok(request_response::Event::Subscription {
+                event: SubscriptionEvent::Subscribed { channel: c, .. },
+                ..
+            }) => channel = Some(c),
+            Ok(request_response::Event::Subscription {
+                event: SubscriptionEvent::Acknowledged { .. },
+                ..
+            }) => {}
+            _ => continue,
+        }
+        let Some(c) = channel.as_mut() else {
+            continue;
+        };
+        while next < n && c.send(Update(next)).is_ok() {
+            next += 1;
+        }
+        if next == n {
+            channel.take().unwrap().end();
+        }
+    }
+}
+
+type Event = request_response::Event<Subscribe, Update>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Subscribe(u64);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Update(u64);
@@ -106,6 +106,7 @@ impl NetworkBehaviour for Behaviour {
                     error @ (DialError::Transport(_)
                     | DialError::Denied { .. }
                     | DialError::NoAddresses
+                    | DialError::SelfDial
                     | DialError::WrongPeerId { .. }),
                 ..
             }) => {
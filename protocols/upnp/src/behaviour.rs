@@ -38,8 +38,9 @@ use futures_timer::Delay;
 use igd_next::PortMappingProtocol;
 use libp2p_core::{multiaddr, transport::ListenerId, Endpoint, Multiaddr};
 use libp2p_swarm::{
-    derive_prelude::PeerId, dummy, ConnectionDenied, ConnectionId, ExpiredListenAddr, FromSwarm,
-    NetworkBehaviour, NewListenAddr, ToSwarm,
+    derive_prelude::PeerId, dummy, ConnectionDenied, ConnectionId, ExpiredListenAddr,
+    ExternalAddrExpiryReason, ExternalAddrOrigin, FromSwarm, NetworkBehaviour, NewListenAddr,
+    ToSwarm,
 };
 
 /// The duration in seconds of a port mapping on the gateway.
@@ -215,6 +216,9 @@ pub struct Behaviour {
 
     /// Pending behaviour events to be emitted.
     pending_events: VecDeque<Event>,
+
+    /// Pending address reports to the [`Swarm`](libp2p_swarm::Swarm).
+    pending_actions: VecDeque<ToSwarm<Event, void::Void>>,
 }
 
 impl Default for Behaviour {
@@ -223,6 +227,7 @@ impl Default for Behaviour {
             state: GatewayState::Searching(crate::tokio::search_gateway()),
             mappings: Default::default(),
             pending_events: VecDeque::new(),
+            pending_actions: VecDeque::new(),
         }
     }
 }
@@ -257,6 +262,7 @@ impl NetworkBehaviour for Behaviour {
             FromSwarm::NewListenAddr(NewListenAddr {
                 listener_id,
                 addr: multiaddr,
+                ..
             }) => {
                 let (addr, protocol) = match multiaddr_to_socketaddr_protocol(multiaddr.clone()) {
                     Ok(addr_port) => addr_port,
@@ -334,6 +340,7 @@ impl NetworkBehaviour for Behaviour {
             FromSwarm::ExpiredListenAddr(ExpiredListenAddr {
                 listener_id,
                 addr: _addr,
+                ..
             }) => {
                 if let GatewayState::Available(ref mut gateway) = &mut self.state {
                     if let Some((mapping, _state)) = self.mappings.remove_entry(&listener_id) {
@@ -370,6 +377,9 @@ impl NetworkBehaviour for Behaviour {
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, libp2p_swarm::THandlerInEvent<Self>>> {
         // If there are pending addresses to be emitted we emit them.
+        if let Some(action) = self.pending_actions.pop_front() {
+            return Poll::Ready(action);
+        }
         if let Some(event) = self.pending_events.pop_front() {
             return Poll::Ready(ToSwarm::GenerateEvent(event));
         }
@@ -405,8 +415,10 @@ impl NetworkBehaviour for Behaviour {
                     Poll::Pending => return Poll::Pending,
                 },
                 GatewayState::Available(ref mut gateway) => {
-                    // Poll pending mapping requests.
-                    if let Poll::Ready(Some(result)) = gateway.receiver.poll_next_unpin(cx) {
+                    // Poll all pending mapping requests, so that the addresses mapped at once are
+                    // reported as candidates together.
+                    let mut mapped = Vec::new();
+                    while let Poll::Ready(Some(result)) = gateway.receiver.poll_next_unpin(cx) {
                         match result {
                             GatewayEvent::Mapped(mapping) => {
                                 let new_state = MappingState::Active(Delay::new(
@@ -429,9 +441,13 @@ impl NetworkBehaviour for Behaviour {
                                             protocol=%mapping.protocol,
                                             "successfully mapped UPnP for protocol"
                                         );
-                                        return Poll::Ready(ToSwarm::ExternalAddrConfirmed(
-                                            external_multiaddr,
-                                        ));
+                                        mapped.push(external_multiaddr.clone());
+                                        self.pending_actions.push_back(
+                                            ToSwarm::ExternalAddrConfirmed {
+                                                address: external_multiaddr,
+                                                origin: ExternalAddrOrigin::Upnp,
+                                            },
+                                        );
                                     }
                                     MappingState::Active(_) => {
                                         tracing::debug!(
@@ -460,9 +476,12 @@ impl NetworkBehaviour for Behaviour {
                                         self.pending_events.push_back(Event::ExpiredExternalAddr(
                                             external_multiaddr.clone(),
                                         ));
-                                        return Poll::Ready(ToSwarm::ExternalAddrExpired(
-                                            external_multiaddr,
-                                        ));
+                                        self.pending_actions.push_back(
+                                            ToSwarm::ExternalAddrExpired {
+                                                address: external_multiaddr,
+                                                reason: ExternalAddrExpiryReason::MappingLost,
+                                            },
+                                        );
                                     }
                                     MappingState::Pending => {
                                         tracing::debug!(
@@ -508,6 +527,16 @@ impl NetworkBehaviour for Behaviour {
 
                     // Renew expired and request inactive mappings.
                     self.mappings.renew(gateway, cx);
+
+                    if !mapped.is_empty() {
+                        return Poll::Ready(ToSwarm::NewExternalAddrCandidate {
+                            addresses: mapped,
+                            origin: ExternalAddrOrigin::Upnp,
+                        });
+                    }
+                    if let Some(action) = self.pending_actions.pop_front() {
+                        return Poll::Ready(action);
+                    }
                     return Poll::Pending;
                 }
                 _ => return Poll::Pending,
@@ -0,0 +1,204 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use toml::Table;
+
+/// The crate providing the `derive_prelude` the generated code refers to.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum CratePath {
+    /// The `libp2p` facade crate, under the given (possibly renamed) name.
+    Facade(String),
+    /// The `libp2p-swarm` crate, under the given (possibly renamed) name.
+    Swarm(String),
+}
+
+impl CratePath {
+    /// The path of the `derive_prelude` module within the crate.
+    pub(crate) fn prelude(&self) -> syn::Path {
+        let path = match self {
+            CratePath::Facade(name) => format!("::{name}::swarm::derive_prelude"),
+            CratePath::Swarm(name) => format!("::{name}::derive_prelude"),
+        };
+
+        syn::parse_str(&path).expect("crate names to be valid identifiers")
+    }
+}
+
+/// Detects whether the crate invoking the macro depends on the `libp2p` facade or directly on
+/// `libp2p-swarm` by looking at the dependencies declared in its manifest.
+///
+/// Falls back to the facade if the manifest cannot be read or neither is a direct dependency,
+/// e.g. when `libp2p` is only available through a re-export, in which case
+/// `#[behaviour(crate = "...")]` needs to be used.
+pub(crate) fn detect() -> CratePath {
+    if env::var("CARGO_PKG_NAME").as_deref() == Ok("libp2p-swarm") {
+        // Integration tests, examples and doc tests of `libp2p-swarm` itself.
+        return CratePath::Swarm("libp2p_swarm".to_owned());
+    }
+
+    let Some(dir) = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from) else {
+        return CratePath::Facade("libp2p".to_owned());
+    };
+    let Some(manifest) = read_manifest(&dir) else {
+        return CratePath::Facade("libp2p".to_owned());
+    };
+    let workspace = if manifest.contains_key("workspace") {
+        None
+    } else {
+        dir.ancestors()
+            .skip(1)
+            .filter_map(read_manifest)
+            .find(|manifest| manifest.contains_key("workspace"))
+    };
+
+    resolve(&manifest, workspace.as_ref().unwrap_or(&manifest))
+}
+
+/// Resolves the [`CratePath`] from the dependencies declared in the given manifest.
+///
+/// Regular dependencies take precedence over dev-dependencies, as only the former are available
+/// to the library itself. Among each, the facade is preferred if both are present. Dependencies
+/// inherited via `workspace = true` are looked up in the given workspace manifest, renames via
+/// `package = "..."` are honoured.
+fn resolve(manifest: &Table, workspace: &Table) -> CratePath {
+    let workspace_dependencies = workspace
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table());
+
+    for kind in ["dependencies", "dev-dependencies"] {
+        let dependencies = dependency_tables(manifest, kind)
+            .flat_map(|table| table.iter())
+            .map(|(name, value)| {
+                let inherited = value
+                    .get("workspace")
+                    .and_then(|w| w.as_bool())
+                    .unwrap_or(false);
+                let declaration = if inherited {
+                    workspace_dependencies
+                        .and_then(|d| d.get(name))
+                        .unwrap_or(value)
+                } else {
+                    value
+                };
+                let package = declaration
+                    .get("package")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or(name);
+
+                (name.replace('-', "_"), package)
+            })
+            .collect::<Vec<_>>();
+        let find = |package: &str| {
+            dependencies
+                .iter()
+                .find(|(_, p)| *p == package)
+                .map(|(name, _)| name.clone())
+        };
+
+        if let Some(name) = find("libp2p") {
+            return CratePath::Facade(name);
+        }
+        if let Some(name) = find("libp2p-swarm") {
+            return CratePath::Swarm(name);
+        }
+    }
+
+    CratePath::Facade("libp2p".to_owned())
+}
+
+/// Returns the `[<kind>]` table and all `[target.'...'.<kind>]` tables of the given manifest.
+fn dependency_tables<'a>(manifest: &'a Table, kind: &'a str) -> impl Iterator<Item = &'a Table> {
+    let targets = manifest
+        .get("target")
+        .and_then(|t| t.as_table())
+        .into_iter()
+        .flat_map(|targets| targets.values())
+        .filter_map(move |target| target.get(kind));
+
+    manifest
+        .get(kind)
+        .into_iter()
+        .chain(targets)
+        .filter_map(|table| table.as_table())
+}
+
+fn read_manifest(dir: &Path) -> Option<Table> {
+    fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_str(manifest: &str, workspace: &str) -> CratePath {
+        resolve(&manifest.parse().unwrap(), &workspace.parse().unwrap())
+    }
+
+    #[test]
+    fn prefers_regular_dependencies_over_dev_dependencies() {
+        let manifest = r#"
+            [dependencies]
+            libp2p-swarm = "0.45"
+
+            [dev-dependencies]
+            libp2p = "0.54"
+        "#;
+
+        assert_eq!(
+            resolve_str(manifest, ""),
+            CratePath::Swarm("libp2p_swarm".to_owned())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_dev_dependencies() {
+        let manifest = r#"
+            [dev-dependencies]
+            libp2p = { version = "0.54", features = ["macros"] }
+        "#;
+
+        assert_eq!(
+            resolve_str(manifest, ""),
+            CratePath::Facade("libp2p".to_owned())
+        );
+    }
+
+    #[test]
+    fn honours_package_renames() {
+        let manifest = r#"
+            [dependencies]
+            p2p = { package = "libp2p", version = "0.54" }
+
+            [target.'cfg(unix)'.dependencies.swarm]
+            package = "libp2p-swarm"
+            version = "0.45"
+        "#;
+
+        assert_eq!(
+            resolve_str(manifest, ""),
+            CratePath::Facade("p2p".to_owned())
+        );
+    }
+
+    #[test]
+    fn honours_workspace_renames() {
+        let manifest = r#"
+            [dependencies]
+            my-swarm = { workspace = true }
+        "#;
+        let workspace = r#"
+            [workspace.dependencies]
+            my-swarm = { package = "libp2p-swarm", version = "0.45" }
+        "#;
+
+        assert_eq!(
+            resolve_str(manifest, workspace),
+            CratePath::Swarm("my_swarm".to_owned())
+        );
+    }
+}
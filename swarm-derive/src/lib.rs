@@ -21,6 +21,7 @@
 #![recursion_limit = "256"]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod crate_path;
 mod syn_ext;
 
 use crate::syn_ext::RequireStrLit;
@@ -28,10 +29,10 @@ use heck::ToUpperCamelCase;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::punctuated::Punctuated;
-use syn::{parse_macro_input, Data, DataStruct, DeriveInput, Meta, Token};
+use syn::{parse_macro_input, Data, DataEnum, DataStruct, DeriveInput, Fields, Meta, Token};
 
-/// Generates a delegating `NetworkBehaviour` implementation for the struct this is used for. See
-/// the trait documentation for better description.
+/// Generates a delegating `NetworkBehaviour` implementation for the struct or enum this is used
+/// for. See the trait documentation for better description.
 #[proc_macro_derive(NetworkBehaviour, attributes(behaviour))]
 pub fn hello_macro_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -42,10 +43,7 @@ pub fn hello_macro_derive(input: TokenStream) -> TokenStream {
 fn build(ast: &DeriveInput) -> syn::Result<TokenStream> {
     match ast.data {
         Data::Struct(ref s) => build_struct(ast, s),
-        Data::Enum(_) => Err(syn::Error::new_spanned(
-            ast,
-            "Cannot derive `NetworkBehaviour` on enums",
-        )),
+        Data::Enum(ref e) => build_enum(ast, e),
         Data::Union(_) => Err(syn::Error::new_spanned(
             ast,
             "Cannot derive `NetworkBehaviour` on union",
@@ -57,10 +55,12 @@ fn build(ast: &DeriveInput) -> syn::Result<TokenStream> {
 fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<TokenStream> {
     let name = &ast.ident;
     let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let attributes = parse_attributes(ast)?;
     let BehaviourAttributes {
-        prelude_path,
-        user_specified_out_event,
-    } = parse_attributes(ast)?;
+        ref prelude_path,
+        trace_swarm_events,
+        ..
+    } = attributes;
 
     let multiaddr = quote! { #prelude_path::Multiaddr };
     let trait_to_impl = quote! { #prelude_path::NetworkBehaviour };
@@ -77,113 +77,35 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     let endpoint = quote! { #prelude_path::Endpoint };
     let connection_denied = quote! { #prelude_path::ConnectionDenied };
 
-    // Build the generics.
-    let impl_generics = {
-        let tp = ast.generics.type_params();
-        let lf = ast.generics.lifetimes();
-        let cst = ast.generics.const_params();
-        quote! {<#(#lf,)* #(#tp,)* #(#cst,)*>}
-    };
+    // The field marked with `#[behaviour(poll_observer)]`, if any, isn't a member.
+    let (poll_observer, data_struct) = split_poll_observer(data_struct)?;
+    let data_struct = &data_struct;
 
-    let (out_event_name, out_event_definition, out_event_from_clauses) = {
-        // If we find a `#[behaviour(to_swarm = "Foo")]` attribute on the
-        // struct, we set `Foo` as the out event. If not, the `ToSwarm` is
-        // generated.
-        match user_specified_out_event {
-            // User provided `ToSwarm`.
-            Some(name) => {
-                let definition = None;
-                let from_clauses = data_struct
-                    .fields
-                    .iter()
-                    .map(|field| {
-                        let ty = &field.ty;
-                        quote! {#name: From< <#ty as #trait_to_impl>::ToSwarm >}
-                    })
-                    .collect::<Vec<_>>();
-                (name, definition, from_clauses)
-            }
-            // User did not provide `ToSwarm`. Generate it.
-            None => {
-                let enum_name_str = ast.ident.to_string() + "Event";
-                let enum_name: syn::Type =
-                    syn::parse_str(&enum_name_str).expect("ident + `Event` is a valid type");
-                let definition = {
-                    let fields = data_struct.fields.iter().map(|field| {
-                        let variant: syn::Variant = syn::parse_str(
-                            &field
-                                .ident
-                                .clone()
-                                .expect("Fields of NetworkBehaviour implementation to be named.")
-                                .to_string()
-                                .to_upper_camel_case(),
-                        )
-                        .expect("uppercased field name to be a valid enum variant");
-                        let ty = &field.ty;
-                        (variant, ty)
-                    });
-
-                    let enum_variants = fields
-                        .clone()
-                        .map(|(variant, ty)| quote! {#variant(<#ty as #trait_to_impl>::ToSwarm)});
-
-                    let visibility = &ast.vis;
-
-                    let additional = fields
-                        .clone()
-                        .map(|(_variant, tp)| quote! { #tp : #trait_to_impl })
-                        .collect::<Vec<_>>();
-
-                    let additional_debug = fields
-                        .clone()
-                        .map(|(_variant, ty)| quote! { <#ty as #trait_to_impl>::ToSwarm : ::core::fmt::Debug })
-                        .collect::<Vec<_>>();
-
-                    let where_clause = {
-                        if let Some(where_clause) = where_clause {
-                            if where_clause.predicates.trailing_punct() {
-                                Some(quote! {#where_clause #(#additional),* })
-                            } else {
-                                Some(quote! {#where_clause, #(#additional),*})
-                            }
-                        } else if additional.is_empty() {
-                            None
-                        } else {
-                            Some(quote! {where #(#additional),*})
-                        }
-                    };
-
-                    let where_clause_debug = where_clause
-                        .as_ref()
-                        .map(|where_clause| quote! {#where_clause, #(#additional_debug),*});
-
-                    let match_variants = fields.map(|(variant, _ty)| variant);
-                    let msg = format!("`NetworkBehaviour::ToSwarm` produced by {name}.");
-
-                    Some(quote! {
-                        #[doc = #msg]
-                        #visibility enum #enum_name #impl_generics
-                            #where_clause
-                        {
-                            #(#enum_variants),*
-                        }
+    // Build the generics.
+    let impl_generics = impl_generics(ast);
 
-                        impl #impl_generics ::core::fmt::Debug for #enum_name #ty_generics #where_clause_debug {
-                            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-                                match &self {
-                                    #(#enum_name::#match_variants(event) => {
-                                        write!(f, "{}: {:?}", #enum_name_str, event)
-                                    }),*
-                                }
-                            }
-                        }
-                    })
-                };
-                let from_clauses = vec![];
-                (enum_name, definition, from_clauses)
-            }
-        }
-    };
+    let members = data_struct
+        .fields
+        .iter()
+        .map(|field| {
+            let variant = syn::Ident::new(
+                &field
+                    .ident
+                    .as_ref()
+                    .expect("Fields of NetworkBehaviour implementation to be named.")
+                    .to_string()
+                    .to_upper_camel_case(),
+                proc_macro2::Span::call_site(),
+            );
+            (variant, field)
+        })
+        .collect::<Vec<_>>();
+    let OutEvent {
+        name: out_event_name,
+        definition: out_event_definition,
+        from_clauses: out_event_from_clauses,
+        mappings: out_event_mappings,
+    } = build_out_event(ast, &attributes, &members)?;
 
     // Build the `where ...` clause of the trait implementation.
     let where_clause = {
@@ -194,18 +116,14 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 let ty = &field.ty;
                 quote! {#ty: #trait_to_impl}
             })
+            .chain(poll_observer.as_ref().map(|field| {
+                let ty = &field.ty;
+                quote! {#ty: #prelude_path::PollObserver}
+            }))
             .chain(out_event_from_clauses)
             .collect::<Vec<_>>();
 
-        if let Some(where_clause) = where_clause {
-            if where_clause.predicates.trailing_punct() {
-                Some(quote! {#where_clause #(#additional),* })
-            } else {
-                Some(quote! {#where_clause, #(#additional),*})
-            }
-        } else {
-            Some(quote! {where #(#additional),*})
-        }
+        extend_where_clause(where_clause, &additional)
     };
 
     // Build the list of statements to put in the body of `on_swarm_event()`.
@@ -214,13 +132,35 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
             .fields
             .iter()
             .enumerate()
-            .map(|(field_n, field)| match field.ident {
-                Some(ref i) => quote! {
-                    self.#i.on_swarm_event(event);
-                },
-                None => quote! {
-                    self.#field_n.on_swarm_event(event);
-                },
+            .map(|(field_n, field)| {
+                let (field_name, field_str) = match field.ident {
+                    Some(ref i) => (quote! { self.#i }, i.to_string()),
+                    None => {
+                        let n = syn::Index::from(field_n);
+                        (quote! { self.#n }, field_n.to_string())
+                    }
+                };
+
+                if !trace_swarm_events {
+                    return quote! {
+                        #field_name.on_swarm_event(event);
+                    };
+                }
+
+                let name_str = name.to_string();
+                quote! {
+                    {
+                        let _span = #prelude_path::tracing::trace_span!(
+                            "NetworkBehaviour::on_swarm_event",
+                            behaviour = #name_str,
+                            field = #field_str,
+                            event = event.kind(),
+                        )
+                        .entered();
+                        #prelude_path::tracing::trace!("Dispatching event to sub-behaviour");
+                        #field_name.on_swarm_event(event);
+                    }
+                }
             })
     };
 
@@ -327,11 +267,11 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 });
 
         quote! {
-            let mut combined_addresses = vec![];
+            let mut combined_addresses = ::std::vec::Vec::new();
 
             #(#extend_stmts)*
 
-            Ok(combined_addresses)
+            ::core::result::Result::Ok(combined_addresses)
         }
     };
 
@@ -383,22 +323,28 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
             // If the `NetworkBehaviour`'s `ToSwarm` is generated by the derive macro, wrap the sub
             // `NetworkBehaviour` `ToSwarm` in the variant of the generated `ToSwarm`. If the
             // `NetworkBehaviour`'s `ToSwarm` is provided by the user, use the corresponding `From`
-            // implementation.
-            let map_out_event = if out_event_definition.is_some() {
-                let event_variant: syn::Variant =
-                    syn::parse_str(&field.to_string().to_upper_camel_case())
-                        .expect("uppercased field name to be a valid enum variant name");
-                quote! { #out_event_name::#event_variant }
-            } else {
-                quote! { |e| e.into() }
-            };
+            // implementation, after applying the `map_out_event` function if given.
+            let map_out_event = &out_event_mappings[field_n];
 
             let map_in_event = quote! { |event| #wrapped_event };
 
+            let poll = match poll_observer.as_ref().and_then(|observer| observer.ident.as_ref()) {
+                Some(observer) => {
+                    let field_str = field.to_string();
+                    quote! {
+                        {
+                            let member = &mut self.#field;
+                            #prelude_path::PollObserver::observe(&mut self.#observer, #field_str, || #trait_to_impl::poll(member, cx))
+                        }
+                    }
+                }
+                None => quote! { #trait_to_impl::poll(&mut self.#field, cx) },
+            };
+
             quote! {
-                match #trait_to_impl::poll(&mut self.#field, cx) {
-                    std::task::Poll::Ready(e) => return std::task::Poll::Ready(e.map_out(#map_out_event).map_in(#map_in_event)),
-                    std::task::Poll::Pending => {},
+                match #poll {
+                    ::core::task::Poll::Ready(e) => return ::core::task::Poll::Ready(e.map_out(#map_out_event).map_in(#map_in_event)),
+                    ::core::task::Poll::Pending => {},
                 }
             }
         });
@@ -409,10 +355,50 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
         quote! { #out_event_name }
     };
 
+    // `AsRef` and `AsMut` implementations for the fields marked with `#[behaviour(as_ref)]`.
+    let as_ref_impls = data_struct
+        .fields
+        .iter()
+        .enumerate()
+        .filter_map(|(field_n, field)| match parse_field_attributes(field) {
+            Ok(FieldAttributes { as_ref: false, .. }) => None,
+            Ok(FieldAttributes { as_ref: true, .. }) => {
+                let ty = &field.ty;
+                let field = match field.ident {
+                    Some(ref i) => quote! { #i },
+                    None => {
+                        let field_n = syn::Index::from(field_n);
+                        quote! { #field_n }
+                    }
+                };
+                Some(Ok(quote! {
+                    impl #impl_generics ::core::convert::AsRef<#ty> for #name #ty_generics
+                    #where_clause
+                    {
+                        fn as_ref(&self) -> &#ty {
+                            &self.#field
+                        }
+                    }
+
+                    impl #impl_generics ::core::convert::AsMut<#ty> for #name #ty_generics
+                    #where_clause
+                    {
+                        fn as_mut(&mut self) -> &mut #ty {
+                            &mut self.#field
+                        }
+                    }
+                }))
+            }
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
     // Now the magic happens.
     let final_quote = quote! {
         #out_event_definition
 
+        #(#as_ref_impls)*
+
         impl #impl_generics #trait_to_impl for #name #ty_generics
         #where_clause
         {
@@ -425,10 +411,10 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 connection_id: #connection_id,
                 local_addr: &#multiaddr,
                 remote_addr: &#multiaddr,
-            ) -> Result<(), #connection_denied> {
+            ) -> ::core::result::Result<(), #connection_denied> {
                 #(#handle_pending_inbound_connection_stmts)*
 
-                Ok(())
+                ::core::result::Result::Ok(())
             }
 
             #[allow(clippy::needless_question_mark)]
@@ -438,18 +424,18 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 peer: #peer_id,
                 local_addr: &#multiaddr,
                 remote_addr: &#multiaddr,
-            ) -> Result<#t_handler<Self>, #connection_denied> {
-                Ok(#handle_established_inbound_connection)
+            ) -> ::core::result::Result<#t_handler<Self>, #connection_denied> {
+                ::core::result::Result::Ok(#handle_established_inbound_connection)
             }
 
             #[allow(clippy::needless_question_mark)]
             fn handle_pending_outbound_connection(
                 &mut self,
                 connection_id: #connection_id,
-                maybe_peer: Option<#peer_id>,
+                maybe_peer: ::core::option::Option<#peer_id>,
                 addresses: &[#multiaddr],
                 effective_role: #endpoint,
-            ) -> Result<::std::vec::Vec<#multiaddr>, #connection_denied> {
+            ) -> ::core::result::Result<::std::vec::Vec<#multiaddr>, #connection_denied> {
                 #handle_pending_outbound_connection
             }
 
@@ -460,8 +446,8 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 peer: #peer_id,
                 addr: &#multiaddr,
                 role_override: #endpoint,
-            ) -> Result<#t_handler<Self>, #connection_denied> {
-                Ok(#handle_established_outbound_connection)
+            ) -> ::core::result::Result<#t_handler<Self>, #connection_denied> {
+                ::core::result::Result::Ok(#handle_established_outbound_connection)
             }
 
             fn on_connection_handler_event(
@@ -475,9 +461,9 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
                 }
             }
 
-            fn poll(&mut self, cx: &mut std::task::Context) -> std::task::Poll<#network_behaviour_action<Self::ToSwarm, #t_handler_in_event<Self>>> {
+            fn poll(&mut self, cx: &mut ::core::task::Context) -> ::core::task::Poll<#network_behaviour_action<Self::ToSwarm, #t_handler_in_event<Self>>> {
                 #(#poll_stmts)*
-                std::task::Poll::Pending
+                ::core::task::Poll::Pending
             }
 
             fn on_swarm_event(&mut self, event: #from_swarm) {
@@ -489,17 +475,609 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     Ok(final_quote.into())
 }
 
+/// The version for enums, with each variant wrapping a single behaviour.
+///
+/// Every method of the trait is delegated to the behaviour of the active variant. The connection
+/// handler is a construction of nested `Either`s of the handlers of the variants.
+fn build_enum(ast: &DeriveInput, data_enum: &DataEnum) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let attributes = parse_attributes(ast)?;
+    let BehaviourAttributes {
+        ref prelude_path,
+        trace_swarm_events,
+        ..
+    } = attributes;
+
+    let multiaddr = quote! { #prelude_path::Multiaddr };
+    let trait_to_impl = quote! { #prelude_path::NetworkBehaviour };
+    let either_ident = quote! { #prelude_path::Either };
+    let lenient_either = quote! { #prelude_path::LenientEither };
+    let network_behaviour_action = quote! { #prelude_path::ToSwarm };
+    let peer_id = quote! { #prelude_path::PeerId };
+    let connection_id = quote! { #prelude_path::ConnectionId };
+    let from_swarm = quote! { #prelude_path::FromSwarm };
+    let t_handler = quote! { #prelude_path::THandler };
+    let t_handler_in_event = quote! { #prelude_path::THandlerInEvent };
+    let t_handler_out_event = quote! { #prelude_path::THandlerOutEvent };
+    let endpoint = quote! { #prelude_path::Endpoint };
+    let connection_denied = quote! { #prelude_path::ConnectionDenied };
+
+    let impl_generics = impl_generics(ast);
+
+    if data_enum.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            ast,
+            "Cannot derive `NetworkBehaviour` on enums without variants",
+        ));
+    }
+
+    let members = data_enum
+        .variants
+        .iter()
+        .map(|variant| {
+            let field = match &variant.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0],
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "variants have to wrap exactly one behaviour, e.g. `Client(Behaviour)`",
+                    ))
+                }
+            };
+            let attributes = parse_field_attributes(field)?;
+            if attributes.as_ref {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`as_ref` only applies to struct members",
+                ));
+            }
+            if attributes.poll_observer {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`poll_observer` only applies to struct fields",
+                ));
+            }
+            Ok((variant.ident.clone(), field))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let OutEvent {
+        name: out_event_name,
+        definition: out_event_definition,
+        from_clauses: out_event_from_clauses,
+        mappings: out_event_mappings,
+    } = build_out_event(ast, &attributes, &members)?;
+
+    // Build the `where ...` clause of the trait implementation.
+    let where_clause = {
+        let additional = members
+            .iter()
+            .map(|(_, field)| {
+                let ty = &field.ty;
+                quote! {#ty: #trait_to_impl}
+            })
+            .chain(out_event_from_clauses)
+            .collect::<Vec<_>>();
+
+        extend_where_clause(where_clause, &additional)
+    };
+
+    // Wraps an event of the handler of the variant at `n` into the nested `#either_ident`s of the
+    // events of the handler of the enum.
+    let wrap = |n: usize, inner: proc_macro2::TokenStream| {
+        let mut wrapped = if n != 0 {
+            quote! { #either_ident::Right(#inner) }
+        } else {
+            inner
+        };
+        for _ in 0..members.len() - 1 - n {
+            wrapped = quote! { #either_ident::Left(#wrapped) };
+        }
+        wrapped
+    };
+
+    // Wraps the handler of the variant at `n` into the nested `LenientEither`s of the handler of
+    // the enum.
+    //
+    // The active variant can change while handlers created by the previous one remain on their
+    // connections. Unlike `Either`, `LenientEither` drops events of the behaviour addressed to
+    // the handler of another variant instead of panicking, and does not accept them, see
+    // `ConnectionHandler::accepts_behaviour_event`. The handler thus tells which variant owns a
+    // connection.
+    let wrap_handler = |n: usize, inner: proc_macro2::TokenStream| {
+        let mut wrapped = if n != 0 {
+            quote! { #lenient_either(#either_ident::Right(#inner)) }
+        } else {
+            inner
+        };
+        for _ in 0..members.len() - 1 - n {
+            wrapped = quote! { #lenient_either(#either_ident::Left(#wrapped)) };
+        }
+        wrapped
+    };
+
+    // The [`ConnectionHandler`] associated type.
+    let connection_handler_ty = members
+        .iter()
+        .map(|(_, field)| {
+            let ty = &field.ty;
+            quote! { #t_handler<#ty> }
+        })
+        .reduce(|left, right| quote! { #lenient_either<#left, #right> })
+        .expect("enum to have variants");
+
+    // The arms of `on_swarm_event()`.
+    let on_swarm_event_arms = members.iter().map(|(variant, _)| {
+        if !trace_swarm_events {
+            return quote! {
+                #name::#variant(behaviour) => #trait_to_impl::on_swarm_event(behaviour, event),
+            };
+        }
+
+        let name_str = name.to_string();
+        let variant_str = variant.to_string();
+        quote! {
+            #name::#variant(behaviour) => {
+                let _span = #prelude_path::tracing::trace_span!(
+                    "NetworkBehaviour::on_swarm_event",
+                    behaviour = #name_str,
+                    variant = #variant_str,
+                    event = event.kind(),
+                )
+                .entered();
+                #prelude_path::tracing::trace!("Dispatching event to sub-behaviour");
+                #trait_to_impl::on_swarm_event(behaviour, event);
+            }
+        }
+    });
+
+    // The arms of `on_connection_handler_event()`.
+    //
+    // Events of handlers created before the behaviour was replaced by another variant can not be
+    // dispatched, thus they are dropped.
+    let on_node_event_arms = members.iter().enumerate().map(|(n, (variant, _))| {
+        let event = wrap(n, quote! { event });
+        quote! {
+            (#name::#variant(behaviour), #event) => {
+                #trait_to_impl::on_connection_handler_event(behaviour, peer_id, connection_id, event)
+            }
+        }
+    });
+    let on_node_event_fallback = (members.len() > 1).then(|| quote! { _ => {} });
+
+    // The arms of `handle_pending_inbound_connection()`.
+    let handle_pending_inbound_connection_arms = members.iter().map(|(variant, _)| {
+        quote! {
+            #name::#variant(behaviour) => {
+                #trait_to_impl::handle_pending_inbound_connection(behaviour, connection_id, local_addr, remote_addr)?;
+            }
+        }
+    });
+
+    // The arms of `handle_established_inbound_connection()`.
+    let handle_established_inbound_connection_arms =
+        members.iter().enumerate().map(|(n, (variant, _))| {
+            let handler = wrap_handler(
+                n,
+                quote! {
+                    #trait_to_impl::handle_established_inbound_connection(behaviour, connection_id, peer, local_addr, remote_addr)?
+                },
+            );
+            quote! {
+                #name::#variant(behaviour) => #handler,
+            }
+        });
+
+    // The arms of `handle_pending_outbound_connection()`.
+    let handle_pending_outbound_connection_arms = members.iter().map(|(variant, _)| {
+        quote! {
+            #name::#variant(behaviour) => {
+                #trait_to_impl::handle_pending_outbound_connection(behaviour, connection_id, maybe_peer, addresses, effective_role)?
+            }
+        }
+    });
+
+    // The arms of `handle_established_outbound_connection()`.
+    let handle_established_outbound_connection_arms =
+        members.iter().enumerate().map(|(n, (variant, _))| {
+            let handler = wrap_handler(
+                n,
+                quote! {
+                    #trait_to_impl::handle_established_outbound_connection(behaviour, connection_id, peer, addr, role_override)?
+                },
+            );
+            quote! {
+                #name::#variant(behaviour) => #handler,
+            }
+        });
+
+    // The arms of `poll()`.
+    //
+    // If the `NetworkBehaviour`'s `ToSwarm` is generated by the derive macro, wrap the sub
+    // `NetworkBehaviour` `ToSwarm` in the variant of the generated `ToSwarm`. If the
+    // `NetworkBehaviour`'s `ToSwarm` is provided by the user, use the corresponding `From`
+    // implementation, after applying the `map_out_event` function if given.
+    let poll_arms = members.iter().enumerate().map(|(n, (variant, _))| {
+        let map_out_event = &out_event_mappings[n];
+        let wrapped_event = wrap(n, quote! { event });
+
+        quote! {
+            #name::#variant(behaviour) => match #trait_to_impl::poll(behaviour, cx) {
+                ::core::task::Poll::Ready(e) => ::core::task::Poll::Ready(e.map_out(#map_out_event).map_in(|event| #wrapped_event)),
+                ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+            },
+        }
+    });
+
+    let out_event_reference = if out_event_definition.is_some() {
+        quote! { #out_event_name #ty_generics }
+    } else {
+        quote! { #out_event_name }
+    };
+
+    let final_quote = quote! {
+        #out_event_definition
+
+        const _: () = {
+            impl #impl_generics #trait_to_impl for #name #ty_generics
+            #where_clause
+            {
+                type ConnectionHandler = #connection_handler_ty;
+                type ToSwarm = #out_event_reference;
+
+                fn handle_pending_inbound_connection(
+                    &mut self,
+                    connection_id: #connection_id,
+                    local_addr: &#multiaddr,
+                    remote_addr: &#multiaddr,
+                ) -> ::core::result::Result<(), #connection_denied> {
+                    match self {
+                        #(#handle_pending_inbound_connection_arms)*
+                    }
+                    ::core::result::Result::Ok(())
+                }
+
+                fn handle_established_inbound_connection(
+                    &mut self,
+                    connection_id: #connection_id,
+                    peer: #peer_id,
+                    local_addr: &#multiaddr,
+                    remote_addr: &#multiaddr,
+                ) -> ::core::result::Result<#t_handler<Self>, #connection_denied> {
+                    ::core::result::Result::Ok(match self {
+                        #(#handle_established_inbound_connection_arms)*
+                    })
+                }
+
+                fn handle_pending_outbound_connection(
+                    &mut self,
+                    connection_id: #connection_id,
+                    maybe_peer: ::core::option::Option<#peer_id>,
+                    addresses: &[#multiaddr],
+                    effective_role: #endpoint,
+                ) -> ::core::result::Result<::std::vec::Vec<#multiaddr>, #connection_denied> {
+                    ::core::result::Result::Ok(match self {
+                        #(#handle_pending_outbound_connection_arms)*
+                    })
+                }
+
+                fn handle_established_outbound_connection(
+                    &mut self,
+                    connection_id: #connection_id,
+                    peer: #peer_id,
+                    addr: &#multiaddr,
+                    role_override: #endpoint,
+                ) -> ::core::result::Result<#t_handler<Self>, #connection_denied> {
+                    ::core::result::Result::Ok(match self {
+                        #(#handle_established_outbound_connection_arms)*
+                    })
+                }
+
+                fn on_connection_handler_event(
+                    &mut self,
+                    peer_id: #peer_id,
+                    connection_id: #connection_id,
+                    event: #t_handler_out_event<Self>
+                ) {
+                    match (self, event) {
+                        #(#on_node_event_arms)*
+                        #on_node_event_fallback
+                    }
+                }
+
+                fn poll(&mut self, cx: &mut ::core::task::Context) -> ::core::task::Poll<#network_behaviour_action<Self::ToSwarm, #t_handler_in_event<Self>>> {
+                    match self {
+                        #(#poll_arms)*
+                    }
+                }
+
+                fn on_swarm_event(&mut self, event: #from_swarm) {
+                    match self {
+                        #(#on_swarm_event_arms)*
+                    }
+                }
+            }
+        };
+    };
+
+    Ok(final_quote.into())
+}
+
+/// The `ToSwarm` of a derived behaviour.
+struct OutEvent {
+    /// The name of the event, either given via `#[behaviour(to_swarm = "...")]` or generated.
+    name: syn::Type,
+    /// The definition of the generated event, `None` if the event is given by the user.
+    definition: Option<proc_macro2::TokenStream>,
+    /// Bounds requiring the event given by the user to be convertible from the member events.
+    from_clauses: Vec<proc_macro2::TokenStream>,
+    /// The functions mapping the event of each member into the event, in order of the members.
+    mappings: Vec<proc_macro2::TokenStream>,
+}
+
+/// Builds the `ToSwarm` of a behaviour from its members, each given along with the name of the
+/// variant wrapping its events in the generated event.
+fn build_out_event(
+    ast: &DeriveInput,
+    attributes: &BehaviourAttributes,
+    members: &[(syn::Ident, &syn::Field)],
+) -> syn::Result<OutEvent> {
+    let name = &ast.ident;
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let impl_generics = impl_generics(ast);
+    let BehaviourAttributes {
+        prelude_path,
+        user_specified_out_event,
+        out_event_derives,
+        out_event_non_exhaustive,
+        ..
+    } = attributes;
+    let trait_to_impl = quote! { #prelude_path::NetworkBehaviour };
+
+    // If we find a `#[behaviour(to_swarm = "Foo")]` attribute on the
+    // behaviour, we set `Foo` as the out event. If not, the `ToSwarm` is
+    // generated.
+    match user_specified_out_event {
+        // User provided `ToSwarm`.
+        Some(name) => {
+            if let Some(from) = members
+                .iter()
+                .map(|(_, field)| parse_field_attributes(field))
+                .find_map(|attributes| attributes.map(|a| a.from).transpose())
+            {
+                return Err(syn::Error::new_spanned(
+                    from?.1,
+                    "`from` only applies to the generated event",
+                ));
+            }
+
+            // Events of members marked with `#[behaviour(map_out_event = "...")]` are passed
+            // through the given function, whose result has to be convertible into the event.
+            let mut from_clauses = Vec::new();
+            let mut mappings = Vec::new();
+            for (_, field) in members {
+                match parse_field_attributes(field)?.map_out_event {
+                    Some((map_out_event, _)) => mappings
+                        .push(quote! { |e| ::core::convert::Into::into((#map_out_event)(e)) }),
+                    None => {
+                        let ty = &field.ty;
+                        from_clauses.push(quote! {#name: From< <#ty as #trait_to_impl>::ToSwarm >});
+                        mappings.push(quote! { |e| e.into() });
+                    }
+                }
+            }
+            Ok(OutEvent {
+                name: name.clone(),
+                definition: None,
+                from_clauses,
+                mappings,
+            })
+        }
+        // User did not provide `ToSwarm`. Generate it.
+        None => {
+            if let Some(map_out_event) = members
+                .iter()
+                .map(|(_, field)| parse_field_attributes(field))
+                .find_map(|attributes| attributes.map(|a| a.map_out_event).transpose())
+            {
+                return Err(syn::Error::new_spanned(
+                    map_out_event?.1,
+                    "`map_out_event` only applies to an event given via `to_swarm`",
+                ));
+            }
+
+            let enum_name_str = ast.ident.to_string() + "Event";
+            let enum_name: syn::Type =
+                syn::parse_str(&enum_name_str).expect("ident + `Event` is a valid type");
+
+            let enum_variants = members
+                .iter()
+                .map(|(variant, field)| {
+                    let ty = &field.ty;
+                    quote! {#variant(<#ty as #trait_to_impl>::ToSwarm)}
+                })
+                .collect::<Vec<_>>();
+
+            let visibility = &ast.vis;
+
+            let additional = members
+                .iter()
+                .map(|(_variant, field)| {
+                    let ty = &field.ty;
+                    quote! { #ty : #trait_to_impl }
+                })
+                .collect::<Vec<_>>();
+
+            let additional_debug = members
+                .iter()
+                .map(|(_variant, field)| {
+                    let ty = &field.ty;
+                    quote! { <#ty as #trait_to_impl>::ToSwarm : ::core::fmt::Debug }
+                })
+                .collect::<Vec<_>>();
+
+            let where_clause = extend_where_clause(where_clause, &additional);
+
+            let where_clause_debug = where_clause
+                .as_ref()
+                .map(|where_clause| quote! {#where_clause, #(#additional_debug),*});
+
+            let match_variants = members.iter().map(|(variant, _field)| variant);
+            let msg = format!("`NetworkBehaviour::ToSwarm` produced by {name}.");
+            let derives = (!out_event_derives.is_empty())
+                .then(|| quote! { #[derive(#(#out_event_derives),*)] });
+            let non_exhaustive = out_event_non_exhaustive.then(|| quote! { #[non_exhaustive] });
+
+            // `From` implementations for the events of the members marked with
+            // `#[behaviour(from = "...")]`. The event type has to be named explicitly, as
+            // `<Member as NetworkBehaviour>::ToSwarm` of a foreign member is opaque to the
+            // compiler when checking for overlap with `impl<T> From<T> for T`.
+            let mut from_impls = Vec::new();
+            let mut from_types = Vec::<String>::new();
+            for (variant, field) in members {
+                let Some((from, meta)) = parse_field_attributes(field)?.from else {
+                    continue;
+                };
+
+                let from_str = quote! { #from }.to_string();
+                if from_types.contains(&from_str) {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "`from` type is used for multiple members",
+                    ));
+                }
+                from_types.push(from_str);
+
+                from_impls.push(quote! {
+                    impl #impl_generics ::core::convert::From<#from> for #enum_name #ty_generics
+                        #where_clause
+                    {
+                        fn from(event: #from) -> Self {
+                            #enum_name::#variant(event)
+                        }
+                    }
+                });
+            }
+
+            let definition = quote! {
+                #[doc = #msg]
+                #derives
+                #non_exhaustive
+                #visibility enum #enum_name #impl_generics
+                    #where_clause
+                {
+                    #(#enum_variants),*
+                }
+
+                impl #impl_generics ::core::fmt::Debug for #enum_name #ty_generics #where_clause_debug {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        match &self {
+                            #(#enum_name::#match_variants(event) => {
+                                ::core::write!(f, "{}: {:?}", #enum_name_str, event)
+                            }),*
+                        }
+                    }
+                }
+
+                #(#from_impls)*
+            };
+            let mappings = members
+                .iter()
+                .map(|(variant, _)| quote! { #enum_name::#variant })
+                .collect();
+            Ok(OutEvent {
+                name: enum_name,
+                definition: Some(definition),
+                from_clauses: vec![],
+                mappings,
+            })
+        }
+    }
+}
+
+/// The generic parameters of the implementations for the type the macro is used for.
+fn impl_generics(ast: &DeriveInput) -> proc_macro2::TokenStream {
+    let tp = ast.generics.type_params();
+    let lf = ast.generics.lifetimes();
+    let cst = ast.generics.const_params();
+    quote! {<#(#lf,)* #(#tp,)* #(#cst,)*>}
+}
+
+/// Extends the `where` clause of the type the macro is used for by the `additional` predicates.
+fn extend_where_clause(
+    where_clause: Option<&syn::WhereClause>,
+    additional: &[proc_macro2::TokenStream],
+) -> Option<proc_macro2::TokenStream> {
+    if let Some(where_clause) = where_clause {
+        if where_clause.predicates.trailing_punct() {
+            Some(quote! {#where_clause #(#additional),* })
+        } else {
+            Some(quote! {#where_clause, #(#additional),*})
+        }
+    } else if additional.is_empty() {
+        None
+    } else {
+        Some(quote! {where #(#additional),*})
+    }
+}
+
 struct BehaviourAttributes {
     prelude_path: syn::Path,
     user_specified_out_event: Option<syn::Type>,
+    out_event_derives: Vec<syn::Path>,
+    out_event_non_exhaustive: bool,
+    trace_swarm_events: bool,
+}
+
+/// Splits off the field marked with `#[behaviour(poll_observer)]`, returning it along with the
+/// members of the struct.
+fn split_poll_observer(data_struct: &DataStruct) -> syn::Result<(Option<syn::Field>, DataStruct)> {
+    let mut poll_observer = None;
+    let mut data_struct = data_struct.clone();
+    let fields = match &mut data_struct.fields {
+        Fields::Named(fields) => &mut fields.named,
+        Fields::Unnamed(fields) => &mut fields.unnamed,
+        Fields::Unit => return Ok((None, data_struct)),
+    };
+
+    let mut members = Punctuated::new();
+    for field in std::mem::take(fields) {
+        if !parse_field_attributes(&field)?.poll_observer {
+            members.push(field);
+            continue;
+        }
+        if poll_observer.is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only one field can be marked with `poll_observer`",
+            ));
+        }
+        if field.ident.is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`poll_observer` requires named fields",
+            ));
+        }
+        poll_observer = Some(field);
+    }
+    *fields = members;
+
+    Ok((poll_observer, data_struct))
 }
 
 /// Parses the `value` of a key=value pair in the `#[behaviour]` attribute into the requested type.
+///
+/// The path of the `derive_prelude` is taken from `prelude = "..."` if given, derived from the
+/// path to the `libp2p` crate given via `crate = "..."` or otherwise detected from the
+/// dependencies of the crate invoking the macro.
 fn parse_attributes(ast: &DeriveInput) -> syn::Result<BehaviourAttributes> {
-    let mut attributes = BehaviourAttributes {
-        prelude_path: syn::parse_quote! { ::libp2p::swarm::derive_prelude },
-        user_specified_out_event: None,
-    };
+    let mut prelude_path = None;
+    let mut crate_path = None::<(syn::Path, Meta)>;
+    let mut user_specified_out_event = None;
+    let mut out_event_derives = Vec::new();
+    let mut out_event_non_exhaustive = None::<Meta>;
+    let mut trace_swarm_events = false;
 
     for attr in ast
         .attrs
@@ -512,7 +1090,15 @@ fn parse_attributes(ast: &DeriveInput) -> syn::Result<BehaviourAttributes> {
             if meta.path().is_ident("prelude") {
                 let value = meta.require_name_value()?.value.require_str_lit()?;
 
-                attributes.prelude_path = syn::parse_str(&value)?;
+                prelude_path = Some(syn::parse_str(&value)?);
+
+                continue;
+            }
+
+            if meta.path().is_ident("crate") {
+                let value = meta.require_name_value()?.value.require_str_lit()?;
+
+                crate_path = Some((syn::parse_str(&value)?, meta));
 
                 continue;
             }
@@ -520,12 +1106,158 @@ fn parse_attributes(ast: &DeriveInput) -> syn::Result<BehaviourAttributes> {
             if meta.path().is_ident("to_swarm") || meta.path().is_ident("out_event") {
                 let value = meta.require_name_value()?.value.require_str_lit()?;
 
-                attributes.user_specified_out_event = Some(syn::parse_str(&value)?);
+                user_specified_out_event = Some(syn::parse_str(&value)?);
 
                 continue;
             }
+
+            if meta.path().is_ident("to_swarm_derive") || meta.path().is_ident("out_event_derive") {
+                let derives = meta
+                    .require_list()?
+                    .parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)?;
+
+                if let Some(debug) = derives.iter().find(|path| path.is_ident("Debug")) {
+                    return Err(syn::Error::new_spanned(
+                        debug,
+                        "`Debug` is always implemented for the generated event",
+                    ));
+                }
+
+                out_event_derives.extend(derives);
+
+                continue;
+            }
+
+            if meta.path().is_ident("to_swarm_non_exhaustive")
+                || meta.path().is_ident("out_event_non_exhaustive")
+            {
+                meta.require_path_only()?;
+
+                out_event_non_exhaustive = Some(meta);
+
+                continue;
+            }
+
+            if meta.path().is_ident("trace_swarm_events") {
+                meta.require_path_only()?;
+
+                trace_swarm_events = true;
+
+                continue;
+            }
+        }
+    }
+
+    let prelude_path = match (prelude_path, crate_path) {
+        (Some(_), Some((_, meta))) => {
+            return Err(syn::Error::new_spanned(
+                meta,
+                "`crate` and `prelude` are mutually exclusive",
+            ))
+        }
+        (Some(prelude_path), None) => prelude_path,
+        (None, Some((crate_path, _))) => syn::parse_quote! { #crate_path::swarm::derive_prelude },
+        (None, None) => crate_path::detect().prelude(),
+    };
+
+    if user_specified_out_event.is_some() {
+        if let Some(path) = out_event_derives.first() {
+            return Err(syn::Error::new_spanned(
+                path,
+                "`to_swarm_derive` only applies to the generated event",
+            ));
+        }
+        if let Some(meta) = out_event_non_exhaustive {
+            return Err(syn::Error::new_spanned(
+                meta,
+                "`to_swarm_non_exhaustive` only applies to the generated event",
+            ));
+        }
+    }
+
+    Ok(BehaviourAttributes {
+        prelude_path,
+        user_specified_out_event,
+        out_event_derives,
+        out_event_non_exhaustive: out_event_non_exhaustive.is_some(),
+        trace_swarm_events,
+    })
+}
+
+struct FieldAttributes {
+    as_ref: bool,
+    from: Option<(syn::Type, Meta)>,
+    map_out_event: Option<(syn::Expr, Meta)>,
+    poll_observer: bool,
+}
+
+/// Parses the `#[behaviour]` attributes of a struct member.
+fn parse_field_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+    let mut as_ref = false;
+    let mut from = None;
+    let mut map_out_event = None;
+    let mut poll_observer = None::<Meta>;
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("behaviour"))
+    {
+        let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+        for meta in nested {
+            if meta.path().is_ident("as_ref") {
+                meta.require_path_only()?;
+
+                as_ref = true;
+
+                continue;
+            }
+
+            if meta.path().is_ident("from") {
+                let value = meta.require_name_value()?.value.require_str_lit()?;
+
+                from = Some((syn::parse_str(&value)?, meta));
+
+                continue;
+            }
+
+            if meta.path().is_ident("map_out_event") {
+                let value = meta.require_name_value()?.value.require_str_lit()?;
+
+                map_out_event = Some((syn::parse_str(&value)?, meta));
+
+                continue;
+            }
+
+            if meta.path().is_ident("poll_observer") {
+                meta.require_path_only()?;
+
+                poll_observer = Some(meta);
+
+                continue;
+            }
+
+            return Err(syn::Error::new_spanned(
+                meta,
+                "unknown attribute, expected `as_ref`, `from`, `map_out_event` or `poll_observer`",
+            ));
+        }
+    }
+
+    if let Some(meta) = &poll_observer {
+        if as_ref || from.is_some() || map_out_event.is_some() {
+            return Err(syn::Error::new_spanned(
+                meta,
+                "`poll_observer` cannot be combined with other attributes",
+            ));
         }
     }
 
-    Ok(attributes)
+    Ok(FieldAttributes {
+        as_ref,
+        from,
+        map_out_event,
+        poll_observer: poll_observer.is_some(),
+    })
 }
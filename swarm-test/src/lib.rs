@@ -18,6 +18,8 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+pub mod nat;
+
 use async_trait::async_trait;
 use futures::future::{BoxFuture, Either};
 use futures::{FutureExt, StreamExt};
@@ -387,6 +389,7 @@ where
                     SwarmEvent::NewListenAddr {
                         address,
                         listener_id,
+                        ..
                     } => (listener_id == memory_addr_listener_id).then_some(address),
                     other => {
                         panic!("Unexpected event while waiting for `NewListenAddr`: {other:?}")
@@ -403,6 +406,7 @@ where
                     SwarmEvent::NewListenAddr {
                         address,
                         listener_id,
+                        ..
                     } => (listener_id == tcp_addr_listener_id).then_some(address),
                     other => {
                         panic!("Unexpected event while waiting for `NewListenAddr`: {other:?}")
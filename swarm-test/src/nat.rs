@@ -0,0 +1,516 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Emulation of hosts behind NATs, for deterministic integration tests of NAT traversal, e.g.
+//! via AutoNAT, circuit relays and hole punching with DCUtR.
+//!
+//! A [`Network`] connects its hosts via in-memory connections, addressed by
+//! `/ip4/<ip>/tcp/<port>` multiaddresses. Public hosts are reachable by everyone. A host behind a
+//! NAT is only reachable via the address a remote observed for it, and only by the remotes it
+//! previously sent a connection attempt to. Like `libp2p-tcp` with port reuse, a host dials from
+//! its listening port, if any.
+//!
+//! Connection attempts filtered by a NAT are dropped and time out after [`DIAL_TIMEOUT`], unless
+//! the remote sends a connection attempt the other way in the meantime. Like a TCP simultaneous
+//! open, both attempts then succeed with the same connection.
+//!
+//! Hosts are assigned addresses from the documentation ranges, thus relays used in these tests
+//! need to be configured with `relay::Config::allow_all_destinations`.
+//!
+//! ```
+//! # use futures::executor::block_on;
+//! # use libp2p_core::{transport::ListenerId, Transport};
+//! # use libp2p_swarm_test::nat::{NatKind, Network};
+//! let network = Network::new();
+//! let mut public = network.public_host();
+//! let mut natted = network.nat_host(NatKind::Cone);
+//! public.listen_on(ListenerId::next(), "/ip4/0.0.0.0/tcp/4001".parse()?)?;
+//! natted.listen_on(ListenerId::next(), "/ip4/0.0.0.0/tcp/4001".parse()?)?;
+//!
+//! // The public host observes the host behind the NAT under the public address of its NAT ...
+//! assert!(block_on(public.dial("/ip4/203.0.113.2/tcp/4001".parse()?)?).is_err());
+//! // ... and can only reach it there once it was dialed by the host behind the NAT.
+//! assert!(block_on(natted.dial("/ip4/198.51.100.1/tcp/4001".parse()?)?).is_ok());
+//! assert!(block_on(public.dial("/ip4/203.0.113.2/tcp/4001".parse()?)?).is_ok());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use futures::channel::{mpsc, oneshot};
+use futures::future::{BoxFuture, Either};
+use futures::{future, FutureExt, Sink, Stream, StreamExt};
+use futures_timer::Delay;
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+use libp2p_core::transport::{ListenerId, TransportError, TransportEvent};
+use rw_stream_sink::RwStreamSink;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use std::{fmt, io};
+
+/// How long a connection attempt dropped by a NAT waits for a simultaneous open.
+pub const DIAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The first port assigned to connections not dialed from a listening port.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// The kind of NAT a host is behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatKind {
+    /// Endpoint-independent mapping with address- and port-dependent filtering, also known as
+    /// port-restricted cone NAT.
+    ///
+    /// All remotes observe the same address for the host, which thus can be hole punched.
+    Cone,
+    /// Endpoint-dependent mapping with address- and port-dependent filtering.
+    ///
+    /// Each remote observes a different port for the host, which thus cannot be hole punched.
+    Symmetric,
+}
+
+/// A network of public hosts and hosts behind NATs, see the [module documentation](self).
+///
+/// Clones refer to the same network.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    state: Arc<Mutex<State>>,
+}
+
+impl Network {
+    /// Creates an empty network.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a publicly reachable host to the network, returning its [`Transport`].
+    pub fn public_host(&self) -> Transport {
+        self.add_host(None)
+    }
+
+    /// Adds a host behind its own NAT of the given kind to the network, returning its
+    /// [`Transport`].
+    pub fn nat_host(&self, kind: NatKind) -> Transport {
+        self.add_host(Some(kind))
+    }
+
+    fn add_host(&self, nat: Option<NatKind>) -> Transport {
+        let mut state = self.state.lock().unwrap();
+        let host = state.hosts.len();
+        let n = u8::try_from(host + 1).expect("less than 256 hosts");
+        state.hosts.push(match nat {
+            None => Host {
+                ip: Ipv4Addr::new(198, 51, 100, n),
+                nat: None,
+            },
+            Some(kind) => Host {
+                ip: Ipv4Addr::new(192, 168, n, 2),
+                nat: Some(Nat {
+                    kind,
+                    public_ip: Ipv4Addr::new(203, 0, 113, n),
+                    mappings: HashMap::new(),
+                    ports: HashMap::new(),
+                    next_port: FIRST_EPHEMERAL_PORT,
+                }),
+            },
+        });
+
+        Transport {
+            network: self.clone(),
+            host,
+            listeners: Vec::new(),
+            pending_events: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    hosts: Vec<Host>,
+    /// The incoming connections of each listening endpoint.
+    listeners: HashMap<SocketAddrV4, Incoming>,
+    /// The connection attempts dropped by a NAT, by observed and remote endpoint.
+    dropped: Vec<(SocketAddrV4, SocketAddrV4, oneshot::Sender<Connection>)>,
+    next_ephemeral_port: u16,
+}
+
+#[derive(Debug)]
+struct Host {
+    /// The address of the host, private if it is behind a NAT.
+    ip: Ipv4Addr,
+    nat: Option<Nat>,
+}
+
+#[derive(Debug)]
+struct Nat {
+    kind: NatKind,
+    public_ip: Ipv4Addr,
+    /// The public port of each local endpoint and, for symmetric NATs, remote endpoint.
+    mappings: HashMap<(SocketAddrV4, Option<SocketAddrV4>), u16>,
+    /// The local endpoint of each public port and the remote endpoints allowed to reach it.
+    ports: HashMap<u16, (SocketAddrV4, HashSet<SocketAddrV4>)>,
+    next_port: u16,
+}
+
+impl Nat {
+    /// Maps a connection attempt from `local` to `remote`, returning the endpoint the remote
+    /// observes and allowing the remote to reach `local` via it.
+    fn outbound(&mut self, local: SocketAddrV4, remote: SocketAddrV4) -> SocketAddrV4 {
+        let key = match self.kind {
+            NatKind::Cone => (local, None),
+            NatKind::Symmetric => (local, Some(remote)),
+        };
+        let port = match self.mappings.get(&key) {
+            Some(port) => *port,
+            None => {
+                let port = match self.kind {
+                    NatKind::Cone => local.port(),
+                    NatKind::Symmetric => {
+                        self.next_port += 1;
+                        self.next_port
+                    }
+                };
+                self.mappings.insert(key, port);
+                port
+            }
+        };
+        self.ports
+            .entry(port)
+            .or_insert_with(|| (local, HashSet::new()))
+            .1
+            .insert(remote);
+
+        SocketAddrV4::new(self.public_ip, port)
+    }
+
+    /// Returns the local endpoint a connection attempt from `remote` to the public `port` is
+    /// forwarded to, if any.
+    fn inbound(&self, remote: SocketAddrV4, port: u16) -> Option<SocketAddrV4> {
+        let (local, allowed) = self.ports.get(&port)?;
+
+        allowed.contains(&remote).then_some(*local)
+    }
+}
+
+#[derive(Default)]
+struct Incoming {
+    connections: VecDeque<(Connection, SocketAddrV4)>,
+    waker: Option<Waker>,
+}
+
+impl fmt::Debug for Incoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Incoming")
+            .field("connections", &self.connections.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl State {
+    fn ephemeral_port(&mut self) -> u16 {
+        self.next_ephemeral_port = self.next_ephemeral_port.max(FIRST_EPHEMERAL_PORT) + 1;
+        self.next_ephemeral_port
+    }
+
+    /// Sends a connection attempt from `host` to `remote`.
+    fn connect(
+        &mut self,
+        host: usize,
+        remote: SocketAddrV4,
+    ) -> BoxFuture<'static, io::Result<Connection>> {
+        let ip = self.hosts[host].ip;
+        let local_port = match self.listeners.keys().find(|e| *e.ip() == ip) {
+            Some(listener) => listener.port(),
+            None => self.ephemeral_port(),
+        };
+        let local = SocketAddrV4::new(ip, local_port);
+        let observed = match &mut self.hosts[host].nat {
+            Some(nat) => nat.outbound(local, remote),
+            None => local,
+        };
+
+        self.dropped.retain(|(_, _, sender)| !sender.is_canceled());
+        if let Some(index) = self
+            .dropped
+            .iter()
+            .position(|(from, to, _)| *from == remote && *to == observed)
+        {
+            let (_, _, sender) = self.dropped.swap_remove(index);
+            let (local, remote) = connection();
+            let _ = sender.send(remote);
+
+            return future::ok(local).boxed();
+        }
+
+        let route = self.hosts.iter().find_map(|h| match &h.nat {
+            None if h.ip == *remote.ip() => Some(Some(remote)),
+            Some(nat) if nat.public_ip == *remote.ip() => {
+                Some(nat.inbound(observed, remote.port()))
+            }
+            _ => None,
+        });
+        let incoming = match route {
+            Some(Some(destination)) => self.listeners.get_mut(&destination),
+            Some(None) => {
+                let (sender, receiver) = oneshot::channel();
+                self.dropped.push((observed, remote, sender));
+
+                return async move {
+                    match future::select(receiver, Delay::new(DIAL_TIMEOUT)).await {
+                        Either::Left((Ok(connection), _)) => Ok(connection),
+                        _ => Err(io::ErrorKind::TimedOut.into()),
+                    }
+                }
+                .boxed();
+            }
+            None => None,
+        };
+        let Some(incoming) = incoming else {
+            return future::err(io::ErrorKind::ConnectionRefused.into()).boxed();
+        };
+
+        let (local, remote) = connection();
+        incoming.connections.push_back((remote, observed));
+        if let Some(waker) = incoming.waker.take() {
+            waker.wake();
+        }
+
+        future::ok(local).boxed()
+    }
+}
+
+/// Creates both ends of a [`Connection`].
+fn connection() -> (Connection, Connection) {
+    let (a_tx, a_rx) = mpsc::channel(4096);
+    let (b_tx, b_rx) = mpsc::channel(4096);
+
+    (
+        RwStreamSink::new(Chan {
+            incoming: a_rx,
+            outgoing: b_tx,
+        }),
+        RwStreamSink::new(Chan {
+            incoming: b_rx,
+            outgoing: a_tx,
+        }),
+    )
+}
+
+/// A connection between two hosts of a [`Network`].
+pub type Connection = RwStreamSink<Chan>;
+
+/// The [`libp2p_core::Transport`] of a host in a [`Network`].
+pub struct Transport {
+    network: Network,
+    host: usize,
+    listeners: Vec<(ListenerId, SocketAddrV4)>,
+    pending_events:
+        VecDeque<TransportEvent<<Self as libp2p_core::Transport>::ListenerUpgrade, io::Error>>,
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport")
+            .field("host", &self.host)
+            .field("listeners", &self.listeners)
+            .finish_non_exhaustive()
+    }
+}
+
+impl libp2p_core::Transport for Transport {
+    type Output = Connection;
+    type Error = io::Error;
+    type ListenerUpgrade = future::Ready<Result<Self::Output, Self::Error>>;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        let Some(requested) = multiaddr_to_socketaddr(&addr) else {
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        };
+
+        let mut state = self.network.state.lock().unwrap();
+        let ip = state.hosts[self.host].ip;
+        if !requested.ip().is_unspecified() && *requested.ip() != ip {
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        }
+        let port = match requested.port() {
+            0 => state.ephemeral_port(),
+            port => port,
+        };
+        let endpoint = SocketAddrV4::new(ip, port);
+        if state.listeners.contains_key(&endpoint) {
+            return Err(TransportError::Other(io::ErrorKind::AddrInUse.into()));
+        }
+        state.listeners.insert(endpoint, Incoming::default());
+
+        self.listeners.push((id, endpoint));
+        self.pending_events.push_back(TransportEvent::NewAddress {
+            listener_id: id,
+            listen_addr: socketaddr_to_multiaddr(endpoint),
+        });
+
+        Ok(())
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        let Some(index) = self.listeners.iter().position(|(l, _)| *l == id) else {
+            return false;
+        };
+        let (_, endpoint) = self.listeners.remove(index);
+        self.network
+            .state
+            .lock()
+            .unwrap()
+            .listeners
+            .remove(&endpoint);
+        self.pending_events
+            .push_back(TransportEvent::ListenerClosed {
+                listener_id: id,
+                reason: Ok(()),
+            });
+
+        true
+    }
+
+    fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let Some(remote) = multiaddr_to_socketaddr(&addr) else {
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        };
+
+        Ok(self
+            .network
+            .state
+            .lock()
+            .unwrap()
+            .connect(self.host, remote))
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        self.dial(addr)
+    }
+
+    fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+        None
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        multiaddr_to_socketaddr(addr).is_some()
+    }
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        let mut state = self.network.state.lock().unwrap();
+        for (listener_id, endpoint) in &self.listeners {
+            let Some(incoming) = state.listeners.get_mut(endpoint) else {
+                continue;
+            };
+            match incoming.connections.pop_front() {
+                Some((connection, remote)) => {
+                    return Poll::Ready(TransportEvent::Incoming {
+                        listener_id: *listener_id,
+                        upgrade: future::ready(Ok(connection)),
+                        local_addr: socketaddr_to_multiaddr(*endpoint),
+                        send_back_addr: socketaddr_to_multiaddr(remote),
+                    })
+                }
+                None => incoming.waker = Some(cx.waker().clone()),
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// One side of a [`Connection`].
+pub struct Chan {
+    incoming: mpsc::Receiver<Vec<u8>>,
+    outgoing: mpsc::Sender<Vec<u8>>,
+}
+
+impl fmt::Debug for Chan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chan").finish_non_exhaustive()
+    }
+}
+
+impl Stream for Chan {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.incoming.poll_next_unpin(cx).map(|item| item.map(Ok))
+    }
+}
+
+impl Sink<Vec<u8>> for Chan {
+    type Error = io::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outgoing
+            .poll_ready(cx)
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Vec<u8>) -> Result<(), Self::Error> {
+        self.outgoing
+            .start_send(item)
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.outgoing.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Parses `/ip4/<ip>/tcp/<port>`, optionally followed by `/p2p/<peer>`.
+fn multiaddr_to_socketaddr(addr: &Multiaddr) -> Option<SocketAddrV4> {
+    let mut iter = addr.iter();
+    let (Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port))) = (iter.next(), iter.next()) else {
+        return None;
+    };
+    match iter.next() {
+        None | Some(Protocol::P2p(_)) if iter.next().is_none() => Some(SocketAddrV4::new(ip, port)),
+        _ => None,
+    }
+}
+
+fn socketaddr_to_multiaddr(addr: SocketAddrV4) -> Multiaddr {
+    Multiaddr::empty()
+        .with(Protocol::Ip4(*addr.ip()))
+        .with(Protocol::Tcp(addr.port()))
+}
@@ -18,10 +18,15 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+pub mod degrade;
+pub mod dial_back;
 mod either;
 mod external_addresses;
+pub mod intercept;
 mod listen_addresses;
+pub mod map;
 mod peer_addresses;
+pub mod switchable;
 pub mod toggle;
 
 pub use external_addresses::ExternalAddresses;
@@ -35,9 +40,13 @@ use crate::{
     ConnectionDenied, ConnectionHandler, DialError, ListenError, THandler, THandlerInEvent,
     THandlerOutEvent,
 };
-use libp2p_core::{transport::ListenerId, ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_core::{
+    muxing::CloseReason, transport::ListenerId, ConnectedPoint, Endpoint, Multiaddr,
+    NegotiatedProtocols,
+};
 use libp2p_identity::PeerId;
-use std::{task::Context, task::Poll};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fmt, task::Context, task::Poll, time::Duration};
 
 /// A [`NetworkBehaviour`] defines the behaviour of the local node on the network.
 ///
@@ -89,6 +98,64 @@ use std::{task::Context, task::Poll};
 /// The aforementioned conversion of each of the event types generated by the struct members to the
 /// custom `to_swarm` is handled by [`From`] implementations which the user needs to define in
 /// addition to the event `enum` itself.
+/// Alternatively, a struct member can be marked with
+/// `#[behaviour(map_out_event = "path::to::fn")]`, passing its events through the given function
+/// or closure first, e.g. to rename or restructure them. The result is then converted via
+/// [`From`], thus the function can also return the custom `to_swarm` itself.
+///
+/// The generated event always implements [`Debug`](std::fmt::Debug). Further traits can be
+/// derived via `#[behaviour(to_swarm_derive(Clone, PartialEq))]`, given that the events of all
+/// struct members implement them. With `#[behaviour(to_swarm_non_exhaustive)]`, the generated
+/// event is marked `#[non_exhaustive]`, allowing to add struct members without a breaking change
+/// for users matching on the event.
+///
+/// The generated code refers to either the `libp2p` or the `libp2p-swarm` crate, depending on
+/// which of the two the crate using the macro depends on. If `libp2p` is only available through a
+/// re-export, its path can be provided via `#[behaviour(crate = "my_reexport::libp2p")]`.
+/// Crates that neither depend on nor re-export the `libp2p` facade can instead point the macro to
+/// a re-export of `libp2p_swarm::derive_prelude` via
+/// `#[behaviour(prelude = "my_crate::libp2p_reexport")]`. Items of the standard library are
+/// always referred to by their absolute `::core` and `::std` paths.
+///
+/// With `#[behaviour(trace_swarm_events)]`, the dispatch of each [`FromSwarm`] event to the
+/// struct members is wrapped in a `NetworkBehaviour::on_swarm_event` trace span, recording the
+/// name of the struct, the name of the member and the [`FromSwarm::kind`] of the event. The
+/// span fields allow filtering by sub-behaviour, e.g. via
+/// `RUST_LOG="[NetworkBehaviour::on_swarm_event{field=kademlia}]=trace"`.
+///
+/// Marking a struct member with `#[behaviour(as_ref)]` generates [`AsRef`] and [`AsMut`]
+/// implementations for its type, allowing generic code to access the member of any composed
+/// behaviour containing it, e.g. via `B: NetworkBehaviour + AsMut<kad::Behaviour<MemoryStore>>`.
+/// Each type can be marked at most once per struct.
+///
+/// Marking a struct member with `#[behaviour(from = "ping::Event")]` generates a [`From`]
+/// implementation converting the member's event into the generated event, e.g. to construct the
+/// generated event via `.into()` in tests. The event type needs to be spelled out, as the compiler
+/// does not resolve `<ping::Behaviour as NetworkBehaviour>::ToSwarm` of a member defined in
+/// another crate when checking the implementation against `impl<T> From<T> for T`, even though
+/// the member's type is concrete. Thus the macro can not generate these implementations on its
+/// own.
+///
+/// A field marked with `#[behaviour(poll_observer)]` is not a member. Instead, the `poll` of each
+/// member is called through the field's [`PollObserver`] implementation, e.g. to record metrics
+/// per member via `libp2p-metrics`.
+///
+/// The macro can also be derived for an `enum` whose variants each wrap a single
+/// [`NetworkBehaviour`], e.g. to run a node either in client or in server mode. Each trait method
+/// is delegated to the behaviour of the active variant only. The generated event has a variant of
+/// the same name for each `enum` variant, `to_swarm` and the `from` and `map_out_event` attributes
+/// on the variants' fields apply as they do for `struct`s. The active variant can be changed
+/// while connections are established, their handlers stay the ones of the variant active when the
+/// connection was established: events of such a handler are dropped once another variant is
+/// active, and events the active variant sends via [`ToSwarm::NotifyHandler`] only reach
+/// handlers it created, others are dropped and reported via
+/// [`FromSwarm::HandlerNotificationDropped`] if sent with an acknowledgement. [`FromSwarm`]
+/// events are always passed to the active variant, including e.g.
+/// [`FromSwarm::ConnectionClosed`] of connections established under another variant. Unless the
+/// behaviours tolerate events of connections they never saw, replace the active variant only
+/// while no connections are established. Replacing the active variant by a new instance of the
+/// same variant hands it the connections of the previous instance. With `trace_swarm_events`,
+/// the span records the name of the active variant as `variant`.
 ///
 /// ``` rust
 /// # use libp2p_identify as identify;
@@ -215,6 +282,17 @@ pub trait NetworkBehaviour: 'static {
         -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>>;
 }
 
+/// Observes the calls to [`NetworkBehaviour::poll`] of the members of a
+/// `#[derive(NetworkBehaviour)]` struct, e.g. to measure how long each of them takes.
+///
+/// Mark a field of a type implementing this trait with `#[behaviour(poll_observer)]`. The field
+/// is not a member itself, the `poll` of every member is then called through
+/// [`PollObserver::observe`].
+pub trait PollObserver {
+    /// Calls `poll` of the member with the given name and returns its result.
+    fn observe<T>(&mut self, member: &'static str, poll: impl FnOnce() -> Poll<T>) -> Poll<T>;
+}
+
 /// A command issued from a [`NetworkBehaviour`] for the [`Swarm`].
 ///
 /// [`Swarm`]: super::Swarm
@@ -264,7 +342,27 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
         event: TInEvent,
     },
 
-    /// Reports a **new** candidate for an external address to the [`Swarm`](crate::Swarm).
+    /// Like [`ToSwarm::NotifyHandler`], additionally reporting whether the event reached a
+    /// [`ConnectionHandler`].
+    ///
+    /// Once the event was passed to [`ConnectionHandler::on_behaviour_event`],
+    /// [`FromSwarm::HandlerNotified`] is reported with the given ID. If the event is dropped
+    /// instead, e.g. because the connection closed before the handler could be notified or the
+    /// handler does not accept it, see [`ConnectionHandler::accepts_behaviour_event`],
+    /// [`FromSwarm::HandlerNotificationDropped`] is reported, allowing the behaviour to
+    /// retry on another connection.
+    NotifyHandlerWithAck {
+        /// The peer for whom a [`ConnectionHandler`] should be notified.
+        peer_id: PeerId,
+        /// The options w.r.t. which connection handler to notify of the event.
+        handler: NotifyHandler,
+        /// The event to send.
+        event: TInEvent,
+        /// The ID echoed back in the acknowledgement, see [`NotificationId::next`].
+        id: NotificationId,
+    },
+
+    /// Reports **new** candidates for external addresses to the [`Swarm`](crate::Swarm).
     ///
     /// The emphasis on a **new** candidate is important.
     /// Protocols MUST take care to only emit a candidate once per "source".
@@ -272,25 +370,42 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
     /// Thus, only one candidate should be emitted per connection.    
     ///
     /// This makes the report frequency of an address a meaningful data-point for consumers of this event.
-    /// This address will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::NewExternalAddrCandidate`].
+    /// Each address will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::NewExternalAddrCandidate`],
+    /// followed by a single [`FromSwarm::NewExternalAddrCandidates`] event for all of them.
+    /// Report addresses learned together, e.g. from a single remote, in one action.
     ///
-    /// This address could come from a variety of sources:
+    /// These addresses could come from a variety of sources:
     /// - A protocol such as identify obtained it from a remote.
     /// - The user provided it based on configuration.
     /// - We made an educated guess based on one of our listen addresses.
-    NewExternalAddrCandidate(Multiaddr),
+    NewExternalAddrCandidate {
+        addresses: Vec<Multiaddr>,
+        /// The subsystem that proposed the candidates.
+        origin: ExternalAddrOrigin,
+    },
 
     /// Indicates to the [`Swarm`](crate::Swarm) that the provided address is confirmed to be externally reachable.
     ///
     /// This is intended to be issued in response to a [`FromSwarm::NewExternalAddrCandidate`] if we are indeed externally reachable on this address.
     /// This address will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrConfirmed`].
-    ExternalAddrConfirmed(Multiaddr),
+    ExternalAddrConfirmed {
+        address: Multiaddr,
+        /// The subsystem that confirmed the address.
+        ///
+        /// If the address was reported as a candidate with a known origin before, that origin
+        /// takes precedence, i.e. the address is attributed to the subsystem that proposed it.
+        origin: ExternalAddrOrigin,
+    },
 
     /// Indicates to the [`Swarm`](crate::Swarm) that we are no longer externally reachable under the provided address.
     ///
     /// This expires an address that was earlier confirmed via [`ToSwarm::ExternalAddrConfirmed`].
     /// This address will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrExpired`].
-    ExternalAddrExpired(Multiaddr),
+    ExternalAddrExpired {
+        address: Multiaddr,
+        /// Why the address expired.
+        reason: ExternalAddrExpiryReason,
+    },
 
     /// Instructs the `Swarm` to initiate a graceful close of one or all connections with the given peer.
     ///
@@ -306,8 +421,35 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
         connection: CloseConnection,
     },
 
+    /// Like [`ToSwarm::CloseConnection`], additionally signalling the given [`CloseReason`] to
+    /// the remote if supported by the transport, e.g. QUIC.
+    CloseConnectionWithReason {
+        /// The peer to disconnect.
+        peer_id: PeerId,
+        /// Whether to close a specific or all connections to the given peer.
+        connection: CloseConnection,
+        /// The reason signalled to the remote.
+        reason: CloseReason,
+    },
+
     /// Reports external address of a remote peer to the [`Swarm`](crate::Swarm) and through that to other [`NetworkBehaviour`]s.
     NewExternalAddrOfPeer { peer_id: PeerId, address: Multiaddr },
+
+    /// Reports the [`Reachability`] of the local node to the [`Swarm`](crate::Swarm).
+    ///
+    /// This is intended to be issued by protocols probing our reachability, e.g. AutoNAT.
+    /// If the reachability differs from the one currently known to the [`Swarm`](crate::Swarm),
+    /// it will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::ReachabilityChanged`].
+    ReachabilityChanged(Reachability),
+
+    /// Reports the round-trip time measured on a connection to the [`Swarm`](crate::Swarm).
+    ///
+    /// This is intended to be issued by protocols measuring the latency of connections, e.g. ping.
+    /// The last reported round-trip time is used by [`ConnectionSelection::LowestRtt`].
+    ConnectionRtt {
+        connection_id: ConnectionId,
+        rtt: Duration,
+    },
 }
 
 impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
@@ -330,6 +472,17 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
                 handler,
                 event: f(event),
             },
+            ToSwarm::NotifyHandlerWithAck {
+                peer_id,
+                handler,
+                event,
+                id,
+            } => ToSwarm::NotifyHandlerWithAck {
+                peer_id,
+                handler,
+                event: f(event),
+                id,
+            },
             ToSwarm::CloseConnection {
                 peer_id,
                 connection,
@@ -337,9 +490,24 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
                 peer_id,
                 connection,
             },
-            ToSwarm::NewExternalAddrCandidate(addr) => ToSwarm::NewExternalAddrCandidate(addr),
-            ToSwarm::ExternalAddrConfirmed(addr) => ToSwarm::ExternalAddrConfirmed(addr),
-            ToSwarm::ExternalAddrExpired(addr) => ToSwarm::ExternalAddrExpired(addr),
+            ToSwarm::CloseConnectionWithReason {
+                peer_id,
+                connection,
+                reason,
+            } => ToSwarm::CloseConnectionWithReason {
+                peer_id,
+                connection,
+                reason,
+            },
+            ToSwarm::NewExternalAddrCandidate { addresses, origin } => {
+                ToSwarm::NewExternalAddrCandidate { addresses, origin }
+            }
+            ToSwarm::ExternalAddrConfirmed { address, origin } => {
+                ToSwarm::ExternalAddrConfirmed { address, origin }
+            }
+            ToSwarm::ExternalAddrExpired { address, reason } => {
+                ToSwarm::ExternalAddrExpired { address, reason }
+            }
             ToSwarm::NewExternalAddrOfPeer {
                 address: addr,
                 peer_id,
@@ -347,6 +515,10 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::ReachabilityChanged(r) => ToSwarm::ReachabilityChanged(r),
+            ToSwarm::ConnectionRtt { connection_id, rtt } => {
+                ToSwarm::ConnectionRtt { connection_id, rtt }
+            }
         }
     }
 }
@@ -368,9 +540,26 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
                 handler,
                 event,
             },
-            ToSwarm::NewExternalAddrCandidate(addr) => ToSwarm::NewExternalAddrCandidate(addr),
-            ToSwarm::ExternalAddrConfirmed(addr) => ToSwarm::ExternalAddrConfirmed(addr),
-            ToSwarm::ExternalAddrExpired(addr) => ToSwarm::ExternalAddrExpired(addr),
+            ToSwarm::NotifyHandlerWithAck {
+                peer_id,
+                handler,
+                event,
+                id,
+            } => ToSwarm::NotifyHandlerWithAck {
+                peer_id,
+                handler,
+                event,
+                id,
+            },
+            ToSwarm::NewExternalAddrCandidate { addresses, origin } => {
+                ToSwarm::NewExternalAddrCandidate { addresses, origin }
+            }
+            ToSwarm::ExternalAddrConfirmed { address, origin } => {
+                ToSwarm::ExternalAddrConfirmed { address, origin }
+            }
+            ToSwarm::ExternalAddrExpired { address, reason } => {
+                ToSwarm::ExternalAddrExpired { address, reason }
+            }
             ToSwarm::CloseConnection {
                 peer_id,
                 connection,
@@ -378,6 +567,15 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
                 peer_id,
                 connection,
             },
+            ToSwarm::CloseConnectionWithReason {
+                peer_id,
+                connection,
+                reason,
+            } => ToSwarm::CloseConnectionWithReason {
+                peer_id,
+                connection,
+                reason,
+            },
             ToSwarm::NewExternalAddrOfPeer {
                 address: addr,
                 peer_id,
@@ -385,6 +583,10 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::ReachabilityChanged(r) => ToSwarm::ReachabilityChanged(r),
+            ToSwarm::ConnectionRtt { connection_id, rtt } => {
+                ToSwarm::ConnectionRtt { connection_id, rtt }
+            }
         }
     }
 }
@@ -394,8 +596,54 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
 pub enum NotifyHandler {
     /// Notify a particular connection handler.
     One(ConnectionId),
-    /// Notify an arbitrary connection handler.
+    /// Notify an arbitrary connection handler, selected by the [`ConnectionSelection`]
+    /// configured via [`Config::with_connection_selection`](crate::Config::with_connection_selection).
+    Any,
+    /// Notify a connection handler selected by the given [`ConnectionSelection`].
+    Select(ConnectionSelection),
+}
+
+static NEXT_NOTIFICATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifies an event sent via [`ToSwarm::NotifyHandlerWithAck`] in its acknowledgement.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NotificationId(u64);
+
+impl NotificationId {
+    /// Returns the next available [`NotificationId`].
+    ///
+    /// IDs are unique across all behaviours, thus a behaviour can tell its own
+    /// acknowledgements apart from those of other behaviours of the same [`Swarm`](crate::Swarm).
+    pub fn next() -> Self {
+        Self(NEXT_NOTIFICATION_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl fmt::Display for NotificationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The policy for selecting which of the connections to a peer to notify of an event.
+///
+/// Connections are tried in the order of preference. If the preferred connection is busy,
+/// the event is delivered to the next connection ready to receive it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConnectionSelection {
+    /// No preference for any connection.
+    #[default]
     Any,
+    /// Prefer the connection with the lowest round-trip time, as last reported via
+    /// [`ToSwarm::ConnectionRtt`]. Connections without a reported round-trip time come last.
+    LowestRtt,
+    /// Prefer connections whose remote address contains the given protocol, e.g. `"quic-v1"`.
+    ///
+    /// See [`Protocol::tag`](libp2p_core::multiaddr::Protocol::tag) for the protocol names.
+    Transport(&'static str),
+    /// Prefer the connection that most recently received an event from or delivered an event
+    /// to its [`ConnectionHandler`].
+    MostRecentlyActive,
 }
 
 /// The options which connections to close.
@@ -434,7 +682,7 @@ pub enum FromSwarm<'a> {
     /// connection unexpectedly closed.
     ListenFailure(ListenFailure<'a>),
     /// Informs the behaviour that a new listener was created.
-    NewListener(NewListener),
+    NewListener(NewListener<'a>),
     /// Informs the behaviour that we have started listening on a new multiaddr.
     NewListenAddr(NewListenAddr<'a>),
     /// Informs the behaviour that a multiaddr
@@ -447,12 +695,54 @@ pub enum FromSwarm<'a> {
     ListenerClosed(ListenerClosed<'a>),
     /// Informs the behaviour that we have discovered a new candidate for an external address for us.
     NewExternalAddrCandidate(NewExternalAddrCandidate<'a>),
+    /// Informs the behaviour about all candidates for external addresses reported at once.
+    ///
+    /// Emitted after the [`FromSwarm::NewExternalAddrCandidate`] events of these addresses.
+    /// Every candidate is part of exactly one such event, thus behaviours re-evaluating their
+    /// state on new candidates can do so once per event instead of once per address.
+    NewExternalAddrCandidates(NewExternalAddrCandidates<'a>),
     /// Informs the behaviour that an external address of the local node was confirmed.
     ExternalAddrConfirmed(ExternalAddrConfirmed<'a>),
     /// Informs the behaviour that an external address of the local node expired, i.e. is no-longer confirmed.
     ExternalAddrExpired(ExternalAddrExpired<'a>),
     /// Informs the behaviour that we have discovered a new external address for a remote peer.
     NewExternalAddrOfPeer(NewExternalAddrOfPeer<'a>),
+    /// Informs the behaviour that the [`Reachability`] of the local node changed.
+    ReachabilityChanged(ReachabilityChanged),
+    /// Informs the behaviour that an event sent via [`ToSwarm::NotifyHandlerWithAck`] was
+    /// passed to a [`ConnectionHandler`].
+    HandlerNotified(HandlerNotified),
+    /// Informs the behaviour that an event sent via [`ToSwarm::NotifyHandlerWithAck`] was
+    /// dropped without reaching a [`ConnectionHandler`].
+    HandlerNotificationDropped(HandlerNotificationDropped),
+}
+
+impl FromSwarm<'_> {
+    /// The name of the variant, e.g. `"ConnectionEstablished"`.
+    ///
+    /// Useful to identify the event in logs without printing its fields.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FromSwarm::ConnectionEstablished(_) => "ConnectionEstablished",
+            FromSwarm::ConnectionClosed(_) => "ConnectionClosed",
+            FromSwarm::AddressChange(_) => "AddressChange",
+            FromSwarm::DialFailure(_) => "DialFailure",
+            FromSwarm::ListenFailure(_) => "ListenFailure",
+            FromSwarm::NewListener(_) => "NewListener",
+            FromSwarm::NewListenAddr(_) => "NewListenAddr",
+            FromSwarm::ExpiredListenAddr(_) => "ExpiredListenAddr",
+            FromSwarm::ListenerError(_) => "ListenerError",
+            FromSwarm::ListenerClosed(_) => "ListenerClosed",
+            FromSwarm::NewExternalAddrCandidate(_) => "NewExternalAddrCandidate",
+            FromSwarm::NewExternalAddrCandidates(_) => "NewExternalAddrCandidates",
+            FromSwarm::ExternalAddrConfirmed(_) => "ExternalAddrConfirmed",
+            FromSwarm::ExternalAddrExpired(_) => "ExternalAddrExpired",
+            FromSwarm::NewExternalAddrOfPeer(_) => "NewExternalAddrOfPeer",
+            FromSwarm::ReachabilityChanged(_) => "ReachabilityChanged",
+            FromSwarm::HandlerNotified(_) => "HandlerNotified",
+            FromSwarm::HandlerNotificationDropped(_) => "HandlerNotificationDropped",
+        }
+    }
 }
 
 /// [`FromSwarm`] variant that informs the behaviour about a newly established connection to a peer.
@@ -463,6 +753,11 @@ pub struct ConnectionEstablished<'a> {
     pub endpoint: &'a ConnectedPoint,
     pub failed_addresses: &'a [Multiaddr],
     pub other_established: usize,
+    /// The security and multiplexing protocols negotiated on the connection.
+    ///
+    /// Empty if the transport did not record them, see
+    /// [`StreamMuxer::negotiated_protocols`](libp2p_core::StreamMuxer::negotiated_protocols).
+    pub negotiated_protocols: &'a NegotiatedProtocols,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour about a closed connection to a peer.
@@ -512,8 +807,10 @@ pub struct ListenFailure<'a> {
 
 /// [`FromSwarm`] variant that informs the behaviour that a new listener was created.
 #[derive(Debug, Clone, Copy)]
-pub struct NewListener {
+pub struct NewListener<'a> {
     pub listener_id: ListenerId,
+    /// The metadata attached via [`ListenOpts::with_metadata`].
+    pub metadata: Option<&'a str>,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour
@@ -522,6 +819,8 @@ pub struct NewListener {
 pub struct NewListenAddr<'a> {
     pub listener_id: ListenerId,
     pub addr: &'a Multiaddr,
+    /// The metadata attached via [`ListenOpts::with_metadata`].
+    pub metadata: Option<&'a str>,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour that a multiaddr
@@ -531,6 +830,8 @@ pub struct NewListenAddr<'a> {
 pub struct ExpiredListenAddr<'a> {
     pub listener_id: ListenerId,
     pub addr: &'a Multiaddr,
+    /// The metadata attached via [`ListenOpts::with_metadata`].
+    pub metadata: Option<&'a str>,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour that a listener experienced an error.
@@ -538,6 +839,8 @@ pub struct ExpiredListenAddr<'a> {
 pub struct ListenerError<'a> {
     pub listener_id: ListenerId,
     pub err: &'a (dyn std::error::Error + 'static),
+    /// The metadata attached via [`ListenOpts::with_metadata`].
+    pub metadata: Option<&'a str>,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour that a listener closed.
@@ -545,6 +848,8 @@ pub struct ListenerError<'a> {
 pub struct ListenerClosed<'a> {
     pub listener_id: ListenerId,
     pub reason: Result<(), &'a std::io::Error>,
+    /// The metadata attached via [`ListenOpts::with_metadata`].
+    pub metadata: Option<&'a str>,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour about a new candidate for an external address for us.
@@ -553,6 +858,13 @@ pub struct NewExternalAddrCandidate<'a> {
     pub addr: &'a Multiaddr,
 }
 
+/// [`FromSwarm`] variant that informs the behaviour about several new candidates for external
+/// addresses for us, reported at once.
+#[derive(Debug, Clone, Copy)]
+pub struct NewExternalAddrCandidates<'a> {
+    pub addrs: &'a [Multiaddr],
+}
+
 /// [`FromSwarm`] variant that informs the behaviour that an external address was confirmed.
 #[derive(Debug, Clone, Copy)]
 pub struct ExternalAddrConfirmed<'a> {
@@ -571,3 +883,121 @@ pub struct NewExternalAddrOfPeer<'a> {
     pub peer_id: PeerId,
     pub addr: &'a Multiaddr,
 }
+
+/// [`FromSwarm`] variant that informs the behaviour that the [`Reachability`] of the local node changed.
+#[derive(Clone, Copy, Debug)]
+pub struct ReachabilityChanged {
+    pub old: Reachability,
+    pub new: Reachability,
+}
+
+/// [`FromSwarm`] variant that informs the behaviour that an event sent via
+/// [`ToSwarm::NotifyHandlerWithAck`] was passed to a [`ConnectionHandler`].
+#[derive(Clone, Copy, Debug)]
+pub struct HandlerNotified {
+    pub id: NotificationId,
+    pub peer_id: PeerId,
+    /// The connection whose handler was notified.
+    pub connection_id: ConnectionId,
+}
+
+/// [`FromSwarm`] variant that informs the behaviour that an event sent via
+/// [`ToSwarm::NotifyHandlerWithAck`] was dropped without reaching a [`ConnectionHandler`].
+#[derive(Clone, Copy, Debug)]
+pub struct HandlerNotificationDropped {
+    pub id: NotificationId,
+    pub peer_id: PeerId,
+}
+
+/// The subsystem that proposed or confirmed an external address of the local node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExternalAddrOrigin {
+    /// The address was added via [`Swarm::add_external_address`](crate::Swarm::add_external_address).
+    Manual,
+    /// The address was observed by a remote and reported via the identify protocol.
+    Identify,
+    /// The address was confirmed by AutoNAT probes.
+    Autonat,
+    /// The address is backed by a port mapping created via UPnP.
+    Upnp,
+    /// The address is backed by a reservation on a relay.
+    Relay,
+    /// The address was reported by a subsystem not listed above.
+    Unknown,
+}
+
+impl fmt::Display for ExternalAddrOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalAddrOrigin::Manual => write!(f, "manual"),
+            ExternalAddrOrigin::Identify => write!(f, "identify"),
+            ExternalAddrOrigin::Autonat => write!(f, "autonat"),
+            ExternalAddrOrigin::Upnp => write!(f, "upnp"),
+            ExternalAddrOrigin::Relay => write!(f, "relay"),
+            ExternalAddrOrigin::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Why an external address of the local node expired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExternalAddrExpiryReason {
+    /// The mapping the address relied on was lost, e.g. a UPnP port mapping or a relay reservation.
+    MappingLost,
+    /// Probes stopped confirming the address, e.g. AutoNAT.
+    ConfidenceDecayed,
+    /// The address was removed via [`Swarm::remove_external_address`](crate::Swarm::remove_external_address).
+    Manual,
+    /// The address was expired for a reason not listed above.
+    Unknown,
+}
+
+impl fmt::Display for ExternalAddrExpiryReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalAddrExpiryReason::MappingLost => write!(f, "mapping lost"),
+            ExternalAddrExpiryReason::ConfidenceDecayed => write!(f, "confidence decayed"),
+            ExternalAddrExpiryReason::Manual => write!(f, "manual"),
+            ExternalAddrExpiryReason::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Whether the local node is reachable from the public internet.
+///
+/// The reachability is reported by protocols probing it, e.g. AutoNAT, via
+/// [`ToSwarm::ReachabilityChanged`] and shared with all [`NetworkBehaviour`]s.
+/// The confidence is the number of consecutive probes that confirmed the status.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Reachability {
+    /// No protocol determined the reachability (yet).
+    #[default]
+    Unknown,
+    /// We are reachable from the public internet.
+    Public { confidence: usize },
+    /// We are behind a NAT or firewall and not reachable from the public internet.
+    Private { confidence: usize },
+}
+
+impl Reachability {
+    /// Whether we are known to be publicly reachable.
+    pub fn is_public(&self) -> bool {
+        matches!(self, Reachability::Public { .. })
+    }
+
+    /// Whether we are known to be behind a NAT or firewall.
+    pub fn is_private(&self) -> bool {
+        matches!(self, Reachability::Private { .. })
+    }
+
+    /// The confidence in the reachability, `0` for [`Reachability::Unknown`].
+    pub fn confidence(&self) -> usize {
+        match self {
+            Reachability::Unknown => 0,
+            Reachability::Public { confidence } | Reachability::Private { confidence } => {
+                *confidence
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] degrading connections instead of denying them.
+//!
+//! A [`NetworkBehaviour`] unable to create a [`ConnectionHandler`](crate::ConnectionHandler)
+//! for an established connection, e.g. because a key is missing or a resource limit is reached,
+//! returns a [`ConnectionDenied`] from
+//! [`NetworkBehaviour::handle_established_inbound_connection`], respectively
+//! [`NetworkBehaviour::handle_established_outbound_connection`], carrying a typed cause which
+//! can be recovered via [`ConnectionDenied::downcast`]. This closes the connection, including
+//! for all other behaviours composed with it.
+//!
+//! Wrapping the behaviour in [`Degrade`] instead keeps the connection open with a disabled
+//! handler for the wrapped behaviour, which does not learn about the connection at all.
+//! Events the wrapped behaviour sends to the handler of a degraded connection, e.g. via
+//! [`NotifyHandler::Any`](crate::NotifyHandler::Any), are dropped.
+
+use crate::behaviour::toggle::ToggleConnectionHandler;
+use crate::behaviour::{
+    ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm, ListenFailure,
+};
+use crate::connection::ConnectionId;
+use crate::{
+    ConnectionDenied, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::collections::HashSet;
+use std::fmt;
+use std::task::{Context, Poll};
+
+/// Implementation of [`NetworkBehaviour`] degrading the connections the wrapped behaviour
+/// denies, see the [module-level documentation](self).
+pub struct Degrade<TBehaviour> {
+    inner: TBehaviour,
+    /// Decides whether to degrade a connection denied with the given cause, degrading all
+    /// connections if `None`.
+    filter: Option<Box<dyn Fn(&ConnectionDenied) -> bool + Send + 'static>>,
+    /// The connections with a disabled handler.
+    degraded: HashSet<ConnectionId>,
+}
+
+impl<TBehaviour> Degrade<TBehaviour> {
+    /// Wraps the given behaviour, degrading all connections it denies.
+    pub fn new(inner: TBehaviour) -> Self {
+        Self {
+            inner,
+            filter: None,
+            degraded: HashSet::new(),
+        }
+    }
+
+    /// Only degrades the connections for which `filter` returns `true`, denying all others.
+    ///
+    /// The cause can be inspected via [`ConnectionDenied::downcast_ref`].
+    pub fn degrade_if<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&ConnectionDenied) -> bool + Send + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Whether the wrapped behaviour has a disabled handler on the given connection.
+    pub fn is_degraded(&self, connection_id: &ConnectionId) -> bool {
+        self.degraded.contains(connection_id)
+    }
+
+    /// Returns a reference to the wrapped behaviour.
+    pub fn get_ref(&self) -> &TBehaviour {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped behaviour.
+    pub fn get_mut(&mut self) -> &mut TBehaviour {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped behaviour.
+    pub fn into_inner(self) -> TBehaviour {
+        self.inner
+    }
+
+    fn degrade<THandler>(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        handler: Result<THandler, ConnectionDenied>,
+    ) -> Result<ToggleConnectionHandler<THandler>, ConnectionDenied> {
+        match handler {
+            Ok(handler) => Ok(ToggleConnectionHandler::enabled(handler)),
+            Err(cause) if self.filter.as_ref().map_or(true, |filter| filter(&cause)) => {
+                tracing::debug!(%peer, connection=%connection_id, ?cause, "Degrading connection");
+                self.degraded.insert(connection_id);
+                Ok(ToggleConnectionHandler::disabled())
+            }
+            Err(cause) => Err(cause),
+        }
+    }
+}
+
+impl<TBehaviour> fmt::Debug for Degrade<TBehaviour>
+where
+    TBehaviour: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Degrade")
+            .field("inner", &self.inner)
+            .field("degraded", &self.degraded)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TBehaviour> NetworkBehaviour for Degrade<TBehaviour>
+where
+    TBehaviour: NetworkBehaviour,
+{
+    type ConnectionHandler = ToggleConnectionHandler<THandler<TBehaviour>>;
+    type ToSwarm = TBehaviour::ToSwarm;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handler = self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        );
+
+        self.degrade(connection_id, peer, handler)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handler = self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+        );
+
+        self.degrade(connection_id, peer, handler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        let connection_id = match &event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. }) => {
+                *connection_id
+            }
+            FromSwarm::AddressChange(address_change) => address_change.connection_id,
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                if self.degraded.remove(connection_id) {
+                    return;
+                }
+                *connection_id
+            }
+            // Another behaviour denied a connection we degraded, thus it is never established.
+            FromSwarm::DialFailure(DialFailure { connection_id, .. })
+            | FromSwarm::ListenFailure(ListenFailure { connection_id, .. }) => {
+                self.degraded.remove(connection_id);
+                return self.inner.on_swarm_event(event);
+            }
+            _ => return self.inner.on_swarm_event(event),
+        };
+
+        if !self.degraded.contains(&connection_id) {
+            self.inner.on_swarm_event(event);
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.inner.poll(cx)
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] verifying the reachability of the local node by dialing itself.
+//!
+//! [`Behaviour::probe`] dials the given address without expecting any particular peer. If the
+//! dial ends up at the local node, the [`Swarm`](crate::Swarm) aborts it with
+//! [`DialError::LocalPeerId`], which proves that the address routes back to us and is thus
+//! reachable. This works without any cooperating infrastructure, e.g. when no AutoNAT servers
+//! are available, as long as the network path allows it:
+//!
+//! - Probing the public address of a node behind a NAT requires the NAT to support hairpinning.
+//! - Probing a relayed address, i.e. `/<relay-addr>/p2p/<relay-id>/p2p-circuit/p2p/<local-id>`,
+//!   has the relay dial back to the local node through its reservation.
+//!
+//! Addresses the local node is listening on are never dialed by the `Swarm` and are reported
+//! as [`ProbeError::ListenAddress`].
+
+use crate::behaviour::{DialFailure, FromSwarm};
+use crate::connection::ConnectionId;
+use crate::dial_opts::DialOpts;
+use crate::{
+    dummy, ConnectionDenied, DialError, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p_core::transport::TransportError;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::task::{Context, Poll};
+use std::{error, fmt, io};
+
+/// The ID of a probe started via [`Behaviour::probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProbeId(u64);
+
+impl fmt::Display for ProbeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The outcome of a probe.
+#[derive(Debug)]
+pub enum Event {
+    /// Dialing the address reached the local node, i.e. the address is reachable.
+    Reachable {
+        probe_id: ProbeId,
+        address: Multiaddr,
+    },
+    /// The address could not be verified to be reachable.
+    Unreachable {
+        probe_id: ProbeId,
+        address: Multiaddr,
+        error: ProbeError,
+    },
+}
+
+/// Why a probed address could not be verified to be reachable.
+#[derive(Debug)]
+pub enum ProbeError {
+    /// The address is one of our listen addresses and thus not dialed by the `Swarm`.
+    ListenAddress,
+    /// The address belongs to another peer.
+    ForeignPeer(PeerId),
+    /// Dialing the address failed.
+    Dial(io::Error),
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeError::ListenAddress => write!(f, "The address is a local listen address"),
+            ProbeError::ForeignPeer(peer) => write!(f, "The address belongs to peer {peer}"),
+            ProbeError::Dial(e) => write!(f, "Failed to dial the address: {e}"),
+        }
+    }
+}
+
+impl error::Error for ProbeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ProbeError::Dial(e) => Some(e),
+            ProbeError::ListenAddress | ProbeError::ForeignPeer(_) => None,
+        }
+    }
+}
+
+/// An owned copy of an error and its sources, as the [`DialError`] of a [`DialFailure`] is only
+/// borrowed.
+#[derive(Debug)]
+struct ErrorChain {
+    message: String,
+    source: Option<Box<ErrorChain>>,
+}
+
+impl ErrorChain {
+    fn capture(error: &dyn error::Error) -> Self {
+        Self {
+            message: error.to_string(),
+            source: error.source().map(|source| Box::new(Self::capture(source))),
+        }
+    }
+}
+
+impl fmt::Display for ErrorChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for ErrorChain {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.source.as_deref().map(|source| source as _)
+    }
+}
+
+/// A [`NetworkBehaviour`] probing the reachability of addresses by dialing the local node,
+/// see the [module-level documentation](self).
+#[derive(Debug, Default)]
+pub struct Behaviour {
+    next_probe_id: u64,
+    /// The ongoing probes, by the connection dialing the address.
+    probes: HashMap<ConnectionId, (ProbeId, Multiaddr)>,
+    /// Pending events to return from `poll`.
+    pending_events: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
+}
+
+impl Behaviour {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Dials the given address to verify that it reaches the local node.
+    ///
+    /// The outcome is reported via [`Event`].
+    pub fn probe(&mut self, address: Multiaddr) -> ProbeId {
+        let probe_id = ProbeId(self.next_probe_id);
+        self.next_probe_id += 1;
+
+        let opts = DialOpts::unknown_peer_id().address(address.clone()).build();
+        self.probes
+            .insert(opts.connection_id(), (probe_id, address));
+        self.pending_events.push_back(ToSwarm::Dial { opts });
+
+        probe_id
+    }
+
+    /// Whether the given probe is still ongoing.
+    pub fn is_pending(&self, probe_id: ProbeId) -> bool {
+        self.probes.values().any(|(id, _)| *id == probe_id)
+    }
+
+    fn on_dial_failure(
+        &mut self,
+        DialFailure {
+            connection_id,
+            error,
+            ..
+        }: DialFailure,
+    ) {
+        let Some((probe_id, address)) = self.probes.remove(&connection_id) else {
+            return;
+        };
+
+        let error = match error {
+            DialError::LocalPeerId { .. } => {
+                tracing::debug!(probe=%probe_id, %address, "Address is reachable");
+                self.pending_events
+                    .push_back(ToSwarm::GenerateEvent(Event::Reachable {
+                        probe_id,
+                        address,
+                    }));
+                return;
+            }
+            DialError::NoAddresses => ProbeError::ListenAddress,
+            DialError::WrongPeerId { obtained, .. } => ProbeError::ForeignPeer(*obtained),
+            DialError::Transport(errors) => {
+                let kind = match errors.last() {
+                    Some((_, TransportError::Other(e))) => e.kind(),
+                    Some((_, TransportError::MultiaddrNotSupported(_))) => {
+                        io::ErrorKind::Unsupported
+                    }
+                    None => io::ErrorKind::Other,
+                };
+                // `DialError::Transport` has no source, the last of its errors is used instead.
+                let mut chain = ErrorChain::capture(error);
+                chain.source = errors
+                    .last()
+                    .map(|(_, error)| Box::new(ErrorChain::capture(error)));
+                ProbeError::Dial(io::Error::new(kind, chain))
+            }
+            error => ProbeError::Dial(io::Error::new(
+                io::ErrorKind::Other,
+                ErrorChain::capture(error),
+            )),
+        };
+
+        tracing::debug!(probe=%probe_id, %address, "Address is unreachable: {error}");
+        self.pending_events
+            .push_back(ToSwarm::GenerateEvent(Event::Unreachable {
+                probe_id,
+                address,
+                error,
+            }));
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let Some((probe_id, address)) = self.probes.remove(&connection_id) else {
+            return Ok(dummy::ConnectionHandler);
+        };
+
+        // Another peer answered on the address, there is no point in keeping the connection.
+        tracing::debug!(probe=%probe_id, %address, %peer, "Address belongs to another peer");
+        self.pending_events
+            .push_back(ToSwarm::GenerateEvent(Event::Unreachable {
+                probe_id,
+                address,
+                error: ProbeError::ForeignPeer(peer),
+            }));
+
+        Err(ConnectionDenied::new(ProbeError::ForeignPeer(peer)))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::DialFailure(dial_failure) = event {
+            self.on_dial_failure(dial_failure)
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
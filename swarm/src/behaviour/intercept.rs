@@ -0,0 +1,208 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] passing the events and actions of another behaviour through an
+//! [`Interceptor`].
+//!
+//! Wrapping a behaviour in [`Intercepted`] allows observing, modifying or withholding the
+//! [`FromSwarm`] events reported to it and the [`ToSwarm`] actions it emits, e.g. to record
+//! them in tests or to mute [`ToSwarm::Dial`] during maintenance, without forking the
+//! behaviour. The [`ConnectionHandler`](crate::ConnectionHandler) of the wrapped behaviour is
+//! used as is.
+
+use crate::behaviour::FromSwarm;
+use crate::connection::ConnectionId;
+use crate::{
+    ConnectionDenied, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::fmt;
+use std::task::{Context, Poll};
+
+/// Observes and modifies the events and actions of a [`NetworkBehaviour`] wrapped in
+/// [`Intercepted`].
+///
+/// All methods default to passing the event or action on unchanged.
+pub trait Interceptor<TBehaviour>
+where
+    TBehaviour: NetworkBehaviour,
+{
+    /// Called with every [`FromSwarm`] event before it is passed to the wrapped behaviour.
+    ///
+    /// Returning `None` withholds the event. Note that behaviours commonly rely on seeing
+    /// e.g. [`FromSwarm::ConnectionClosed`] for every [`FromSwarm::ConnectionEstablished`].
+    fn intercept_swarm_event<'a>(&mut self, event: FromSwarm<'a>) -> Option<FromSwarm<'a>> {
+        Some(event)
+    }
+
+    /// Called with every [`ToSwarm`] action of the wrapped behaviour before it is returned to
+    /// the [`Swarm`](crate::Swarm).
+    ///
+    /// Returning `None` drops the action. The wrapped behaviour is not informed about dropped
+    /// actions, e.g. there is no [`FromSwarm::DialFailure`] for a dropped [`ToSwarm::Dial`].
+    fn intercept_action(
+        &mut self,
+        action: ToSwarm<TBehaviour::ToSwarm, THandlerInEvent<TBehaviour>>,
+    ) -> Option<ToSwarm<TBehaviour::ToSwarm, THandlerInEvent<TBehaviour>>> {
+        Some(action)
+    }
+}
+
+/// Implementation of [`NetworkBehaviour`] passing the events and actions of the wrapped
+/// behaviour through an [`Interceptor`], see the [module-level documentation](self).
+pub struct Intercepted<TBehaviour, TInterceptor> {
+    inner: TBehaviour,
+    interceptor: TInterceptor,
+}
+
+impl<TBehaviour, TInterceptor> Intercepted<TBehaviour, TInterceptor> {
+    /// Wraps the given behaviour, passing its events and actions through `interceptor`.
+    pub fn new(inner: TBehaviour, interceptor: TInterceptor) -> Self {
+        Self { inner, interceptor }
+    }
+
+    /// Returns a reference to the wrapped behaviour.
+    pub fn get_ref(&self) -> &TBehaviour {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped behaviour.
+    pub fn get_mut(&mut self) -> &mut TBehaviour {
+        &mut self.inner
+    }
+
+    /// Returns a reference to the interceptor.
+    pub fn interceptor(&self) -> &TInterceptor {
+        &self.interceptor
+    }
+
+    /// Returns a mutable reference to the interceptor.
+    pub fn interceptor_mut(&mut self) -> &mut TInterceptor {
+        &mut self.interceptor
+    }
+
+    /// Returns the wrapped behaviour and the interceptor.
+    pub fn into_inner(self) -> (TBehaviour, TInterceptor) {
+        (self.inner, self.interceptor)
+    }
+}
+
+impl<TBehaviour, TInterceptor> fmt::Debug for Intercepted<TBehaviour, TInterceptor>
+where
+    TBehaviour: fmt::Debug,
+    TInterceptor: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Intercepted")
+            .field("inner", &self.inner)
+            .field("interceptor", &self.interceptor)
+            .finish()
+    }
+}
+
+impl<TBehaviour, TInterceptor> NetworkBehaviour for Intercepted<TBehaviour, TInterceptor>
+where
+    TBehaviour: NetworkBehaviour,
+    TInterceptor: Interceptor<TBehaviour> + 'static,
+{
+    type ConnectionHandler = THandler<TBehaviour>;
+    type ToSwarm = TBehaviour::ToSwarm;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let Some(event) = self.interceptor.intercept_swarm_event(event) {
+            self.inner.on_swarm_event(event);
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            let action = futures::ready!(self.inner.poll(cx));
+            if let Some(action) = self.interceptor.intercept_action(action) {
+                return Poll::Ready(action);
+            }
+            tracing::trace!("Interceptor dropped action");
+        }
+    }
+}
@@ -63,6 +63,7 @@ mod tests {
         FromSwarm::NewListenAddr(NewListenAddr {
             listener_id: ListenerId::next(),
             addr: &MEMORY_ADDR,
+            metadata: None,
         })
     }
 
@@ -70,6 +71,7 @@ mod tests {
         FromSwarm::ExpiredListenAddr(ExpiredListenAddr {
             listener_id: ListenerId::next(),
             addr: &MEMORY_ADDR,
+            metadata: None,
         })
     }
 
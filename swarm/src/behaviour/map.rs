@@ -0,0 +1,627 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] over a runtime-sized collection of behaviours of the same type.
+//!
+//! Unlike the `NetworkBehaviour` derive macro, which composes a fixed set of behaviours,
+//! [`BehaviourMap`] allows inserting and removing behaviours at runtime, e.g. one
+//! request-response behaviour per tenant. Behaviours are identified by a key, which tags the
+//! events they emit. Their connection handlers are combined into a [`MultiHandler`]. As
+//! behaviours of the same type share their protocols, each handler's protocols are namespaced
+//! by its key, i.e. `/ipfs/ping/1.0.0` of the behaviour under key `tenant` is negotiated as
+//! `/tenant/ipfs/ping/1.0.0`. Thus a behaviour only talks to the behaviour under the same key
+//! on the remote.
+//!
+//! Each connection only carries handlers for the behaviours present when it was established.
+//! A behaviour inserted later does not learn about existing connections and a removed
+//! behaviour keeps its handler on existing connections until they close, with all events
+//! from that handler being dropped.
+
+use crate::behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, FromSwarm};
+use crate::connection::ConnectionId;
+use crate::handler::multi::MultiHandler;
+use crate::handler::{
+    ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, DialUpgradeError,
+    FullyNegotiatedInbound, FullyNegotiatedOutbound, ListenUpgradeError, ProtocolsChange,
+    SubstreamProtocol,
+};
+use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend, UpgradeInfoSend};
+use crate::{
+    ConnectionDenied, NetworkBehaviour, Stream, StreamProtocol, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+/// Implementation of [`NetworkBehaviour`] over a collection of behaviours of the same type,
+/// see the [module-level documentation](self).
+///
+/// Events emitted by the behaviours are tagged with their key.
+pub struct BehaviourMap<K, TBehaviour> {
+    behaviours: HashMap<K, TBehaviour>,
+    /// The keys of the behaviours with a handler on each established connection.
+    connections: HashMap<ConnectionId, HashSet<K>>,
+    /// The index of the behaviour to poll first, rotating to poll the behaviours fairly.
+    next_poll: usize,
+    /// The waker of the last call to [`NetworkBehaviour::poll`], woken once a behaviour is
+    /// inserted.
+    waker: Option<Waker>,
+}
+
+impl<K, TBehaviour> BehaviourMap<K, TBehaviour>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty [`BehaviourMap`].
+    pub fn new() -> Self {
+        Self {
+            behaviours: HashMap::new(),
+            connections: HashMap::new(),
+            next_poll: 0,
+            waker: None,
+        }
+    }
+
+    /// Inserts a behaviour under the given key, returning the behaviour previously inserted
+    /// under that key, if any.
+    ///
+    /// The behaviour only learns about connections established from now on.
+    pub fn insert(&mut self, key: K, behaviour: TBehaviour) -> Option<TBehaviour> {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+        self.behaviours.insert(key, behaviour)
+    }
+
+    /// Removes the behaviour inserted under the given key.
+    pub fn remove(&mut self, key: &K) -> Option<TBehaviour> {
+        for keys in self.connections.values_mut() {
+            keys.remove(key);
+        }
+        self.behaviours.remove(key)
+    }
+
+    /// Returns a reference to the behaviour inserted under the given key.
+    pub fn get(&self, key: &K) -> Option<&TBehaviour> {
+        self.behaviours.get(key)
+    }
+
+    /// Returns a mutable reference to the behaviour inserted under the given key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut TBehaviour> {
+        self.behaviours.get_mut(key)
+    }
+
+    /// Whether a behaviour is inserted under the given key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.behaviours.contains_key(key)
+    }
+
+    /// Returns an iterator over the keys of all behaviours.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.behaviours.keys()
+    }
+
+    /// Returns an iterator over all behaviours and their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &TBehaviour)> {
+        self.behaviours.iter()
+    }
+
+    /// Returns an iterator over mutable references to all behaviours and their keys.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut TBehaviour)> {
+        self.behaviours.iter_mut()
+    }
+
+    /// Returns the number of behaviours.
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    /// Whether there are no behaviours.
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+}
+
+impl<K, TBehaviour> Default for BehaviourMap<K, TBehaviour>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, TBehaviour> FromIterator<(K, TBehaviour)> for BehaviourMap<K, TBehaviour>
+where
+    K: Eq + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = (K, TBehaviour)>>(iter: I) -> Self {
+        Self {
+            behaviours: HashMap::from_iter(iter),
+            ..Self::new()
+        }
+    }
+}
+
+impl<K, TBehaviour> fmt::Debug for BehaviourMap<K, TBehaviour>
+where
+    K: fmt::Debug,
+    TBehaviour: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BehaviourMap")
+            .field("behaviours", &self.behaviours)
+            .field("connections", &self.connections)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, TBehaviour> BehaviourMap<K, TBehaviour>
+where
+    K: Clone + fmt::Debug + fmt::Display + Hash + Eq + Send + 'static,
+    TBehaviour: NetworkBehaviour,
+{
+    /// Combines the handlers created by the behaviours into a [`MultiHandler`], namespacing
+    /// their protocols by key and remembering which behaviours have a handler on the connection.
+    fn multi_handler(
+        &mut self,
+        connection_id: ConnectionId,
+        handlers: Vec<(K, THandler<TBehaviour>)>,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let keys = handlers.iter().map(|(key, _)| key.clone()).collect();
+        let handlers = handlers.into_iter().map(|(key, handler)| {
+            let namespace = format!("/{key}");
+            (key, NamespacedHandler::new(namespace, handler))
+        });
+        let handler = MultiHandler::try_from_iter(handlers).map_err(ConnectionDenied::new)?;
+        self.connections.insert(connection_id, keys);
+
+        Ok(handler)
+    }
+
+    /// Passes the event to the behaviours with a handler on the given connection.
+    fn on_connection_event(&mut self, connection_id: ConnectionId, event: FromSwarm) {
+        let Some(keys) = self.connections.get(&connection_id) else {
+            return;
+        };
+        for key in keys {
+            if let Some(behaviour) = self.behaviours.get_mut(key) {
+                behaviour.on_swarm_event(event);
+            }
+        }
+    }
+}
+
+impl<K, TBehaviour> NetworkBehaviour for BehaviourMap<K, TBehaviour>
+where
+    K: Clone + fmt::Debug + fmt::Display + Hash + Eq + Send + 'static,
+    TBehaviour: NetworkBehaviour,
+{
+    type ConnectionHandler = MultiHandler<K, NamespacedHandler<THandler<TBehaviour>>>;
+    type ToSwarm = (K, TBehaviour::ToSwarm);
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        for behaviour in self.behaviours.values_mut() {
+            behaviour.handle_pending_inbound_connection(connection_id, local_addr, remote_addr)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .map(|(key, behaviour)| {
+                let handler = behaviour.handle_established_inbound_connection(
+                    connection_id,
+                    peer,
+                    local_addr,
+                    remote_addr,
+                )?;
+                Ok((key.clone(), handler))
+            })
+            .collect::<Result<Vec<_>, ConnectionDenied>>()?;
+
+        self.multi_handler(connection_id, handlers)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let mut combined_addresses = Vec::new();
+        for behaviour in self.behaviours.values_mut() {
+            combined_addresses.extend(behaviour.handle_pending_outbound_connection(
+                connection_id,
+                maybe_peer,
+                addresses,
+                effective_role,
+            )?);
+        }
+
+        Ok(combined_addresses)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .map(|(key, behaviour)| {
+                let handler = behaviour.handle_established_outbound_connection(
+                    connection_id,
+                    peer,
+                    addr,
+                    role_override,
+                )?;
+                Ok((key.clone(), handler))
+            })
+            .collect::<Result<Vec<_>, ConnectionDenied>>()?;
+
+        self.multi_handler(connection_id, handlers)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. })
+            | FromSwarm::AddressChange(AddressChange { connection_id, .. }) => {
+                self.on_connection_event(connection_id, event)
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                self.on_connection_event(connection_id, event);
+                self.connections.remove(&connection_id);
+            }
+            _ => {
+                for behaviour in self.behaviours.values_mut() {
+                    behaviour.on_swarm_event(event);
+                }
+            }
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        (key, event): THandlerOutEvent<Self>,
+    ) {
+        let has_handler = self
+            .connections
+            .get(&connection_id)
+            .is_some_and(|keys| keys.contains(&key));
+        match self.behaviours.get_mut(&key).filter(|_| has_handler) {
+            Some(behaviour) => behaviour.on_connection_handler_event(peer_id, connection_id, event),
+            None => tracing::trace!(
+                ?key,
+                connection=%connection_id,
+                "Dropping handler event of removed behaviour"
+            ),
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let len = self.behaviours.len();
+        let start = if len == 0 { 0 } else { self.next_poll % len };
+        self.next_poll = start + 1;
+
+        for (key, behaviour) in self.behaviours.iter_mut().skip(start) {
+            if let Poll::Ready(action) = behaviour.poll(cx) {
+                return Poll::Ready(tag(key, action));
+            }
+        }
+        for (key, behaviour) in self.behaviours.iter_mut().take(start) {
+            if let Poll::Ready(action) = behaviour.poll(cx) {
+                return Poll::Ready(tag(key, action));
+            }
+        }
+
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Tags the events of an action with the key of the behaviour that emitted it.
+fn tag<K, TOut, TInEvent>(
+    key: &K,
+    action: ToSwarm<TOut, TInEvent>,
+) -> ToSwarm<(K, TOut), (K, TInEvent)>
+where
+    K: Clone,
+{
+    action
+        .map_out(|event| (key.clone(), event))
+        .map_in(|event| (key.clone(), event))
+}
+
+/// A [`ConnectionHandler`] prefixing all protocols of the wrapped handler with a namespace.
+///
+/// Protocol changes are reported to the wrapped handler with the namespace stripped, omitting
+/// the protocols outside of the namespace.
+pub struct NamespacedHandler<THandler> {
+    namespace: Arc<str>,
+    inner: THandler,
+    /// The local protocols within the namespace, with the namespace stripped.
+    local_protocols: HashSet<StreamProtocol>,
+    /// The remote protocols within the namespace, with the namespace stripped.
+    remote_protocols: HashSet<StreamProtocol>,
+}
+
+impl<THandler> NamespacedHandler<THandler> {
+    fn new(namespace: String, inner: THandler) -> Self {
+        Self {
+            namespace: namespace.into(),
+            inner,
+            local_protocols: HashSet::new(),
+            remote_protocols: HashSet::new(),
+        }
+    }
+
+    fn namespaced<TUpgrade>(&self, upgrade: TUpgrade) -> Namespaced<TUpgrade> {
+        Namespaced {
+            namespace: self.namespace.clone(),
+            inner: upgrade,
+        }
+    }
+}
+
+impl<THandler> fmt::Debug for NamespacedHandler<THandler>
+where
+    THandler: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamespacedHandler")
+            .field("namespace", &self.namespace)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<THandler> NamespacedHandler<THandler>
+where
+    THandler: ConnectionHandler,
+{
+    /// Reports the protocols of the change within the namespace to the wrapped handler.
+    fn on_protocols_change(&mut self, change: ProtocolsChange, local: bool) {
+        let existing = if local {
+            &mut self.local_protocols
+        } else {
+            &mut self.remote_protocols
+        };
+        let old = std::mem::take(existing);
+        let mut new = old.clone();
+        match change {
+            ProtocolsChange::Added(added) => {
+                new.extend(added.filter_map(|p| strip_namespace(&self.namespace, p)))
+            }
+            ProtocolsChange::Removed(removed) => {
+                for protocol in removed.filter_map(|p| strip_namespace(&self.namespace, p)) {
+                    new.remove(&protocol);
+                }
+            }
+        }
+
+        for change in ProtocolsChange::from_full_sets(&old, &new) {
+            let event = if local {
+                ConnectionEvent::LocalProtocolsChange(change)
+            } else {
+                ConnectionEvent::RemoteProtocolsChange(change)
+            };
+            self.inner.on_connection_event(event);
+        }
+
+        if local {
+            self.local_protocols = new;
+        } else {
+            self.remote_protocols = new;
+        }
+    }
+}
+
+impl<THandler> ConnectionHandler for NamespacedHandler<THandler>
+where
+    THandler: ConnectionHandler,
+{
+    type FromBehaviour = THandler::FromBehaviour;
+    type ToBehaviour = THandler::ToBehaviour;
+    type InboundProtocol = Namespaced<THandler::InboundProtocol>;
+    type OutboundProtocol = Namespaced<THandler::OutboundProtocol>;
+    type InboundOpenInfo = THandler::InboundOpenInfo;
+    type OutboundOpenInfo = THandler::OutboundOpenInfo;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        self.inner
+            .listen_protocol()
+            .map_upgrade(|upgrade| self.namespaced(upgrade))
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        self.inner.on_behaviour_event(event)
+    }
+
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        self.inner.accepts_behaviour_event(event)
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.inner.connection_keep_alive()
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        let event = std::task::ready!(self.inner.poll(cx));
+        Poll::Ready(event.map_protocol(|upgrade| self.namespaced(upgrade)))
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::ToBehaviour>> {
+        self.inner.poll_close(cx)
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound { protocol, info }) => {
+                self.inner
+                    .on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
+                        FullyNegotiatedInbound { protocol, info },
+                    ))
+            }
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol,
+                info,
+            }) => self
+                .inner
+                .on_connection_event(ConnectionEvent::FullyNegotiatedOutbound(
+                    FullyNegotiatedOutbound { protocol, info },
+                )),
+            ConnectionEvent::AddressChange(address_change) => self
+                .inner
+                .on_connection_event(ConnectionEvent::AddressChange(address_change)),
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error }) => self
+                .inner
+                .on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
+                    info,
+                    error,
+                })),
+            ConnectionEvent::ListenUpgradeError(ListenUpgradeError { info, error }) => self
+                .inner
+                .on_connection_event(ConnectionEvent::ListenUpgradeError(ListenUpgradeError {
+                    info,
+                    error,
+                })),
+            ConnectionEvent::LocalProtocolsChange(change) => self.on_protocols_change(change, true),
+            ConnectionEvent::RemoteProtocolsChange(change) => {
+                self.on_protocols_change(change, false)
+            }
+        }
+    }
+}
+
+/// Strips the namespace from a protocol, returning `None` if it is outside of the namespace.
+fn strip_namespace(namespace: &str, protocol: &StreamProtocol) -> Option<StreamProtocol> {
+    let stripped = protocol.as_ref().strip_prefix(namespace)?;
+    if !stripped.starts_with('/') {
+        return None;
+    }
+    StreamProtocol::try_from_owned(stripped.to_owned()).ok()
+}
+
+/// An upgrade negotiating the protocols of the wrapped upgrade prefixed with a namespace.
+#[derive(Debug, Clone)]
+pub struct Namespaced<TUpgrade> {
+    namespace: Arc<str>,
+    inner: TUpgrade,
+}
+
+/// A protocol of the upgrade wrapped by [`Namespaced`], prefixed with the namespace.
+#[derive(Debug, Clone)]
+pub struct NamespacedProtocol<TInfo> {
+    name: String,
+    inner: TInfo,
+}
+
+impl<TInfo> AsRef<str> for NamespacedProtocol<TInfo> {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<TUpgrade> UpgradeInfo for Namespaced<TUpgrade>
+where
+    TUpgrade: UpgradeInfoSend,
+{
+    type Info = NamespacedProtocol<TUpgrade::Info>;
+    type InfoIter = Vec<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.inner
+            .protocol_info()
+            .map(|inner| NamespacedProtocol {
+                name: format!("{}{}", self.namespace, inner.as_ref()),
+                inner,
+            })
+            .collect()
+    }
+}
+
+impl<TUpgrade> InboundUpgrade<Stream> for Namespaced<TUpgrade>
+where
+    TUpgrade: InboundUpgradeSend,
+{
+    type Output = TUpgrade::Output;
+    type Error = TUpgrade::Error;
+    type Future = TUpgrade::Future;
+
+    fn upgrade_inbound(self, stream: Stream, info: Self::Info) -> Self::Future {
+        self.inner.upgrade_inbound(stream, info.inner)
+    }
+}
+
+impl<TUpgrade> OutboundUpgrade<Stream> for Namespaced<TUpgrade>
+where
+    TUpgrade: OutboundUpgradeSend,
+{
+    type Output = TUpgrade::Output;
+    type Error = TUpgrade::Error;
+    type Future = TUpgrade::Future;
+
+    fn upgrade_outbound(self, stream: Stream, info: Self::Info) -> Self::Future {
+        self.inner.upgrade_outbound(stream, info.inner)
+    }
+}
@@ -0,0 +1,592 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] that can be enabled and disabled at runtime.
+//!
+//! Unlike [`Toggle`](crate::behaviour::toggle::Toggle), whose state is chosen at construction,
+//! [`SwitchableBehaviour`] allows to e.g. disable mDNS once enough peers are known and to
+//! enable it again later on.
+//!
+//! While disabled, the wrapped behaviour is not polled and new connections get a disabled
+//! handler, denying all inbound substreams. Disabling the behaviour closes its handlers on the
+//! existing connections via [`ConnectionHandler::poll_close`], after which the wrapped behaviour
+//! is informed via [`FromSwarm::ConnectionClosed`], even though the connection itself stays
+//! open. Once enabled again, the wrapped behaviour creates handlers for new connections only.
+//!
+//! Events not concerning a specific connection, e.g. [`FromSwarm::NewListenAddr`], are passed
+//! to the wrapped behaviour regardless of its state, keeping it up to date for when it is
+//! enabled again.
+
+use crate::behaviour::{
+    ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm, ListenFailure,
+};
+use crate::connection::ConnectionId;
+use crate::handler::{
+    AddressChange as HandlerAddressChange, ConnectionEvent, ConnectionHandler,
+    ConnectionHandlerEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+    ListenUpgradeError, SubstreamProtocol,
+};
+use crate::upgrade::SendWrapper;
+use crate::{
+    ConnectionDenied, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent, THandlerOutEvent,
+    ToSwarm,
+};
+use either::Either;
+use futures::future;
+use libp2p_core::{upgrade::DeniedUpgrade, ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::task::{Context, Poll, Waker};
+
+/// Implementation of [`NetworkBehaviour`] that can be enabled and disabled at runtime, see the
+/// [module-level documentation](self).
+pub struct SwitchableBehaviour<TBehaviour> {
+    inner: TBehaviour,
+    enabled: bool,
+    /// The connections with an enabled handler, including the ones whose handler is closing.
+    connections: HashMap<ConnectionId, Connection>,
+    /// The connections whose handler is to be notified to close.
+    pending_disable: VecDeque<(PeerId, ConnectionId)>,
+    /// The waker of the last call to [`NetworkBehaviour::poll`], woken once the state changes.
+    waker: Option<Waker>,
+}
+
+/// A connection the wrapped behaviour knows about.
+#[derive(Debug)]
+struct Connection {
+    peer_id: PeerId,
+    endpoint: ConnectedPoint,
+}
+
+impl<TBehaviour> SwitchableBehaviour<TBehaviour> {
+    /// Wraps the given behaviour in the enabled state.
+    pub fn enabled(inner: TBehaviour) -> Self {
+        Self {
+            inner,
+            enabled: true,
+            connections: HashMap::new(),
+            pending_disable: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// Wraps the given behaviour in the disabled state.
+    pub fn disabled(inner: TBehaviour) -> Self {
+        Self {
+            enabled: false,
+            ..Self::enabled(inner)
+        }
+    }
+
+    /// Returns `true` if the wrapped behaviour is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables the wrapped behaviour.
+    ///
+    /// The wrapped behaviour is polled again and creates handlers for connections established
+    /// from now on.
+    pub fn enable(&mut self) {
+        if self.enabled {
+            return;
+        }
+        self.enabled = true;
+        self.pending_disable.clear();
+        self.wake();
+    }
+
+    /// Disables the wrapped behaviour, closing its handlers on all existing connections.
+    pub fn disable(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.enabled = false;
+        self.pending_disable.extend(
+            self.connections
+                .iter()
+                .map(|(connection_id, connection)| (connection.peer_id, *connection_id)),
+        );
+        self.wake();
+    }
+
+    /// Returns a reference to the wrapped behaviour.
+    pub fn get_ref(&self) -> &TBehaviour {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped behaviour.
+    pub fn get_mut(&mut self) -> &mut TBehaviour {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped behaviour.
+    pub fn into_inner(self) -> TBehaviour {
+        self.inner
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// The number of connections to the given peer the wrapped behaviour knows about, besides
+    /// the given one.
+    fn other_connections(&self, peer_id: PeerId, connection_id: ConnectionId) -> usize {
+        self.connections
+            .iter()
+            .filter(|(id, connection)| **id != connection_id && connection.peer_id == peer_id)
+            .count()
+    }
+}
+
+impl<TBehaviour> fmt::Debug for SwitchableBehaviour<TBehaviour>
+where
+    TBehaviour: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwitchableBehaviour")
+            .field("inner", &self.inner)
+            .field("enabled", &self.enabled)
+            .field("connections", &self.connections)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TBehaviour> SwitchableBehaviour<TBehaviour>
+where
+    TBehaviour: NetworkBehaviour,
+{
+    /// Informs the wrapped behaviour about the closed handler of a connection that stays open.
+    fn on_handler_closed(&mut self, connection_id: ConnectionId) {
+        let Some(connection) = self.connections.remove(&connection_id) else {
+            return;
+        };
+        let remaining_established = self.other_connections(connection.peer_id, connection_id);
+
+        self.inner
+            .on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id: connection.peer_id,
+                connection_id,
+                endpoint: &connection.endpoint,
+                remaining_established,
+            }));
+    }
+}
+
+impl<TBehaviour> NetworkBehaviour for SwitchableBehaviour<TBehaviour>
+where
+    TBehaviour: NetworkBehaviour,
+{
+    type ConnectionHandler = SwitchableConnectionHandler<THandler<TBehaviour>>;
+    type ToSwarm = TBehaviour::ToSwarm;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(SwitchableConnectionHandler::disabled());
+        }
+
+        let handler = self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )?;
+        self.connections.insert(
+            connection_id,
+            Connection {
+                peer_id: peer,
+                endpoint: ConnectedPoint::Listener {
+                    local_addr: local_addr.clone(),
+                    send_back_addr: remote_addr.clone(),
+                },
+            },
+        );
+
+        Ok(SwitchableConnectionHandler::enabled(handler))
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(vec![]);
+        }
+
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        if !self.enabled {
+            return Ok(SwitchableConnectionHandler::disabled());
+        }
+
+        let handler = self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+        )?;
+        self.connections.insert(
+            connection_id,
+            Connection {
+                peer_id: peer,
+                endpoint: ConnectedPoint::Dialer {
+                    address: addr.clone(),
+                    role_override,
+                },
+            },
+        );
+
+        Ok(SwitchableConnectionHandler::enabled(handler))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        // Connections without an enabled handler are hidden from the wrapped behaviour and the
+        // connection counts are adjusted accordingly.
+        match event {
+            FromSwarm::ConnectionEstablished(established) => {
+                if !self.connections.contains_key(&established.connection_id) {
+                    return;
+                }
+                let other_established =
+                    self.other_connections(established.peer_id, established.connection_id);
+
+                self.inner.on_swarm_event(FromSwarm::ConnectionEstablished(
+                    ConnectionEstablished {
+                        other_established,
+                        ..established
+                    },
+                ));
+            }
+            FromSwarm::AddressChange(address_change) => {
+                let Some(connection) = self.connections.get_mut(&address_change.connection_id)
+                else {
+                    return;
+                };
+                connection.endpoint = address_change.new.clone();
+
+                self.inner
+                    .on_swarm_event(FromSwarm::AddressChange(address_change));
+            }
+            FromSwarm::ConnectionClosed(closed) => {
+                if self.connections.remove(&closed.connection_id).is_none() {
+                    return;
+                }
+                let remaining_established =
+                    self.other_connections(closed.peer_id, closed.connection_id);
+
+                self.inner
+                    .on_swarm_event(FromSwarm::ConnectionClosed(ConnectionClosed {
+                        remaining_established,
+                        ..closed
+                    }));
+            }
+            // Another behaviour denied a connection we created a handler for, thus it is never
+            // established.
+            FromSwarm::DialFailure(DialFailure { connection_id, .. })
+            | FromSwarm::ListenFailure(ListenFailure { connection_id, .. }) => {
+                if self.connections.remove(&connection_id).is_some() {
+                    self.pending_disable.retain(|(_, id)| *id != connection_id);
+                }
+                self.inner.on_swarm_event(event);
+            }
+            event => self.inner.on_swarm_event(event),
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {
+            HandlerOut::Inner(event) => {
+                self.inner
+                    .on_connection_handler_event(peer_id, connection_id, event)
+            }
+            HandlerOut::Closed => self.on_handler_closed(connection_id),
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some((peer_id, connection_id)) = self.pending_disable.pop_front() {
+            return Poll::Ready(ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::One(connection_id),
+                event: HandlerIn::Disable,
+            });
+        }
+
+        if self.enabled {
+            if let Poll::Ready(action) = self.inner.poll(cx) {
+                return Poll::Ready(action.map_in(HandlerIn::Inner));
+            }
+        }
+
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Event sent from a [`SwitchableBehaviour`] to its [`SwitchableConnectionHandler`].
+#[derive(Debug)]
+pub enum HandlerIn<TInner> {
+    /// An event for the wrapped handler.
+    Inner(TInner),
+    /// Close the wrapped handler.
+    Disable,
+}
+
+/// Event sent from a [`SwitchableConnectionHandler`] to its [`SwitchableBehaviour`].
+#[derive(Debug)]
+pub enum HandlerOut<TInner> {
+    /// An event of the wrapped handler.
+    Inner(TInner),
+    /// The wrapped handler is closed after being disabled.
+    Closed,
+}
+
+/// Implementation of [`ConnectionHandler`] that can be disabled at runtime.
+pub struct SwitchableConnectionHandler<TInner> {
+    inner: Option<TInner>,
+    /// Whether the wrapped handler is being closed, after which it is dropped.
+    closing: bool,
+}
+
+impl<TInner> SwitchableConnectionHandler<TInner> {
+    fn enabled(inner: TInner) -> Self {
+        Self {
+            inner: Some(inner),
+            closing: false,
+        }
+    }
+
+    fn disabled() -> Self {
+        Self {
+            inner: None,
+            closing: false,
+        }
+    }
+
+    /// Returns the wrapped handler, unless it is disabled or being closed.
+    fn active(&mut self) -> Option<&mut TInner> {
+        self.inner.as_mut().filter(|_| !self.closing)
+    }
+}
+
+impl<TInner> ConnectionHandler for SwitchableConnectionHandler<TInner>
+where
+    TInner: ConnectionHandler,
+{
+    type FromBehaviour = HandlerIn<TInner::FromBehaviour>;
+    type ToBehaviour = HandlerOut<TInner::ToBehaviour>;
+    type InboundProtocol = Either<SendWrapper<TInner::InboundProtocol>, SendWrapper<DeniedUpgrade>>;
+    type OutboundProtocol = TInner::OutboundProtocol;
+    type OutboundOpenInfo = TInner::OutboundOpenInfo;
+    type InboundOpenInfo = Either<TInner::InboundOpenInfo, ()>;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        match self.inner.as_ref().filter(|_| !self.closing) {
+            Some(inner) => inner
+                .listen_protocol()
+                .map_upgrade(|u| Either::Left(SendWrapper(u)))
+                .map_info(Either::Left),
+            None => {
+                SubstreamProtocol::new(Either::Right(SendWrapper(DeniedUpgrade)), Either::Right(()))
+            }
+        }
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        match event {
+            HandlerIn::Inner(event) => match self.active() {
+                Some(inner) => inner.on_behaviour_event(event),
+                None => tracing::debug!("Dropping event for disabled handler"),
+            },
+            HandlerIn::Disable => {
+                if self.inner.is_some() {
+                    self.closing = true;
+                }
+            }
+        }
+    }
+
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        match event {
+            HandlerIn::Inner(event) => self
+                .inner
+                .as_ref()
+                .filter(|_| !self.closing)
+                .is_some_and(|h| h.accepts_behaviour_event(event)),
+            HandlerIn::Disable => true,
+        }
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.inner
+            .as_ref()
+            .filter(|_| !self.closing)
+            .is_some_and(|h| h.connection_keep_alive())
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Pending;
+        };
+
+        if !self.closing {
+            return inner.poll(cx).map(|e| e.map_custom(HandlerOut::Inner));
+        }
+
+        match inner.poll_close(cx) {
+            Poll::Ready(Some(event)) => Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                HandlerOut::Inner(event),
+            )),
+            Poll::Ready(None) => {
+                self.inner = None;
+                self.closing = false;
+                Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(HandlerOut::Closed))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        // Substreams negotiated after the wrapped handler was closed are dropped.
+        let Some(inner) = self.inner.as_mut() else {
+            tracing::debug!("Dropping connection event for disabled handler");
+            return;
+        };
+
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound { protocol, info }) => {
+                let protocol = match protocol {
+                    future::Either::Left(protocol) => protocol,
+                    future::Either::Right(v) => void::unreachable(v),
+                };
+                // A handler enabled on creation never listens with the denied upgrade.
+                let Either::Left(info) = info else {
+                    return;
+                };
+                inner.on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
+                    FullyNegotiatedInbound { protocol, info },
+                ))
+            }
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol,
+                info,
+            }) => inner.on_connection_event(ConnectionEvent::FullyNegotiatedOutbound(
+                FullyNegotiatedOutbound { protocol, info },
+            )),
+            ConnectionEvent::AddressChange(address_change) => {
+                inner.on_connection_event(ConnectionEvent::AddressChange(HandlerAddressChange {
+                    new_address: address_change.new_address,
+                }))
+            }
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error }) => inner
+                .on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
+                    info,
+                    error,
+                })),
+            ConnectionEvent::ListenUpgradeError(ListenUpgradeError { info, error }) => {
+                let Either::Left(info) = info else {
+                    return;
+                };
+                let error = match error {
+                    Either::Left(e) => e,
+                    Either::Right(v) => void::unreachable(v),
+                };
+                inner.on_connection_event(ConnectionEvent::ListenUpgradeError(ListenUpgradeError {
+                    info,
+                    error,
+                }))
+            }
+            ConnectionEvent::LocalProtocolsChange(change) => {
+                inner.on_connection_event(ConnectionEvent::LocalProtocolsChange(change))
+            }
+            ConnectionEvent::RemoteProtocolsChange(change) => {
+                inner.on_connection_event(ConnectionEvent::RemoteProtocolsChange(change))
+            }
+        }
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::ToBehaviour>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        inner.poll_close(cx).map(|e| e.map(HandlerOut::Inner))
+    }
+}
@@ -36,7 +36,9 @@ use std::{task::Context, task::Poll};
 
 /// Implementation of `NetworkBehaviour` that can be either in the disabled or enabled state.
 ///
-/// The state can only be chosen at initialization.
+/// The state can only be chosen at initialization, see
+/// [`SwitchableBehaviour`](crate::behaviour::switchable::SwitchableBehaviour) to change it at
+/// runtime.
 pub struct Toggle<TBehaviour> {
     inner: Option<TBehaviour>,
 }
@@ -191,6 +193,16 @@ pub struct ToggleConnectionHandler<TInner> {
     inner: Option<TInner>,
 }
 
+impl<TInner> ToggleConnectionHandler<TInner> {
+    pub(crate) fn enabled(inner: TInner) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    pub(crate) fn disabled() -> Self {
+        Self { inner: None }
+    }
+}
+
 impl<TInner> ToggleConnectionHandler<TInner>
 where
     TInner: ConnectionHandler,
@@ -281,10 +293,17 @@ where
     }
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        match self.inner.as_mut() {
+            Some(inner) => inner.on_behaviour_event(event),
+            // Only reachable through a `Degrade`d behaviour notifying any handler of a peer.
+            None => tracing::debug!("Dropping event for disabled handler"),
+        }
+    }
+
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
         self.inner
-            .as_mut()
-            .expect("Can't receive events if disabled; QED")
-            .on_behaviour_event(event)
+            .as_ref()
+            .is_some_and(|h| h.accepts_behaviour_event(event))
     }
 
     fn connection_keep_alive(&self) -> bool {
@@ -0,0 +1,268 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Provides the source of time for timeouts, backoffs and periodic tasks.
+//!
+//! By default, the [`Swarm`](crate::Swarm) and the behaviours use the [`SystemClock`]. Tests can
+//! plug in a [`VirtualClock`] instead and advance time manually, which makes timeout and backoff
+//! logic deterministic and avoids waiting for wall-clock time to pass.
+
+use futures::{Future, Stream};
+use instant::Instant;
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// A source of time.
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// Returns the current point in time.
+    fn now(&self) -> Instant;
+
+    /// Returns a future that resolves once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// Future returned by [`Clock::sleep`].
+pub struct Sleep(Pin<Box<dyn Future<Output = ()> + Send>>);
+
+impl Sleep {
+    /// Wraps the given future, which is expected to resolve once the sleep has elapsed.
+    pub fn new(future: impl Future<Output = ()> + Send + 'static) -> Self {
+        Self(Box::pin(future))
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sleep").finish_non_exhaustive()
+    }
+}
+
+/// The [`Clock`] following the system time, used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Sleep::new(futures_timer::Delay::new(duration))
+    }
+}
+
+/// A [`Clock`] that only moves forward when [`VirtualClock::advance`] is called.
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    inner: Arc<Mutex<VirtualClockState>>,
+}
+
+#[derive(Debug)]
+struct VirtualClockState {
+    start: Instant,
+    elapsed: Duration,
+    next_sleep_id: u64,
+    /// Deadline and waker of the pending [`Sleep`]s.
+    sleepers: HashMap<u64, (Duration, Option<Waker>)>,
+}
+
+impl VirtualClock {
+    /// Creates a new clock starting at the current system time.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(VirtualClockState {
+                start: Instant::now(),
+                elapsed: Duration::ZERO,
+                next_sleep_id: 0,
+                sleepers: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Moves the clock forward by `duration`, waking all [`Sleep`]s that elapsed.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.inner.lock().unwrap();
+        state.elapsed += duration;
+        let elapsed = state.elapsed;
+        for (deadline, waker) in state.sleepers.values_mut() {
+            if *deadline <= elapsed {
+                if let Some(waker) = waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    /// Returns how far the clock has been advanced since its creation.
+    pub fn elapsed(&self) -> Duration {
+        self.inner.lock().unwrap().elapsed
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        let state = self.inner.lock().unwrap();
+        state.start + state.elapsed
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut state = self.inner.lock().unwrap();
+        let id = state.next_sleep_id;
+        state.next_sleep_id += 1;
+        let deadline = state.elapsed + duration;
+        state.sleepers.insert(id, (deadline, None));
+
+        Sleep::new(VirtualSleep {
+            clock: self.inner.clone(),
+            id,
+            deadline,
+        })
+    }
+}
+
+struct VirtualSleep {
+    clock: Arc<Mutex<VirtualClockState>>,
+    id: u64,
+    deadline: Duration,
+}
+
+impl Future for VirtualSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.clock.lock().unwrap();
+        if state.elapsed >= self.deadline {
+            state.sleepers.remove(&self.id);
+            return Poll::Ready(());
+        }
+        if let Some((_, waker)) = state.sleepers.get_mut(&self.id) {
+            *waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for VirtualSleep {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.clock.lock() {
+            state.sleepers.remove(&self.id);
+        }
+    }
+}
+
+/// A [`Stream`] yielding at a fixed period, driven by a [`Clock`].
+pub struct Interval {
+    clock: Arc<dyn Clock>,
+    period: Duration,
+    sleep: Sleep,
+}
+
+impl Interval {
+    /// Creates an interval first yielding after `period`.
+    pub fn new(clock: Arc<dyn Clock>, period: Duration) -> Self {
+        Self::new_with_next(clock, period, period)
+    }
+
+    /// Creates an interval first yielding after `next` and every `period` thereafter.
+    pub fn new_with_next(clock: Arc<dyn Clock>, period: Duration, next: Duration) -> Self {
+        let sleep = clock.sleep(next);
+        Self {
+            clock,
+            period,
+            sleep,
+        }
+    }
+}
+
+impl Stream for Interval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        futures::ready!(Pin::new(&mut self.sleep).poll(cx));
+        let period = self.period;
+        self.sleep = self.clock.sleep(period);
+        Poll::Ready(Some(()))
+    }
+}
+
+impl fmt::Debug for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interval")
+            .field("clock", &self.clock)
+            .field("period", &self.period)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{FutureExt, StreamExt};
+
+    #[test]
+    fn virtual_sleep_resolves_once_clock_is_advanced() {
+        let clock = VirtualClock::new();
+        let start = clock.now();
+        let mut sleep = clock.sleep(Duration::from_secs(10));
+
+        assert!((&mut sleep).now_or_never().is_none());
+        clock.advance(Duration::from_secs(9));
+        assert!((&mut sleep).now_or_never().is_none());
+        clock.advance(Duration::from_secs(1));
+        assert!(sleep.now_or_never().is_some());
+        assert_eq!(clock.now() - start, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn interval_yields_every_period() {
+        let clock = VirtualClock::new();
+        let mut interval = Interval::new_with_next(
+            Arc::new(clock.clone()),
+            Duration::from_secs(5),
+            Duration::ZERO,
+        );
+
+        assert_eq!(interval.next().now_or_never(), Some(Some(())));
+        assert!(interval.next().now_or_never().is_none());
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(interval.next().now_or_never(), Some(Some(())));
+        assert!(interval.next().now_or_never().is_none());
+    }
+}
@@ -29,6 +29,7 @@ pub(crate) use error::{
 };
 pub use supported_protocols::SupportedProtocols;
 
+use crate::clock::{Clock, Sleep};
 use crate::handler::{
     AddressChange, ConnectionEvent, ConnectionHandler, DialUpgradeError, FullyNegotiatedInbound,
     FullyNegotiatedOutbound, ListenUpgradeError, ProtocolSupport, ProtocolsAdded, ProtocolsChange,
@@ -43,19 +44,21 @@ use futures::future::BoxFuture;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use futures::{stream, FutureExt};
-use futures_timer::Delay;
 use instant::Instant;
 use libp2p_core::connection::ConnectedPoint;
 use libp2p_core::multiaddr::Multiaddr;
-use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox};
+use libp2p_core::muxing::{
+    CloseReason, StreamMuxer, StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox,
+};
 use libp2p_core::upgrade;
-use libp2p_core::upgrade::{NegotiationError, ProtocolError};
+use libp2p_core::upgrade::{NegotiationError, NegotiationObserver, ProtocolError};
 use libp2p_core::Endpoint;
 use libp2p_identity::PeerId;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::Waker;
 use std::time::Duration;
 use std::{fmt, io, mem, pin::Pin, task::Context, task::Poll};
@@ -157,6 +160,10 @@ where
     remote_supported_protocols: HashSet<StreamProtocol>,
     idle_timeout: Duration,
     stream_counter: ActiveStreamCounter,
+    /// The source of time for the idle and upgrade timeouts.
+    clock: Arc<dyn Clock>,
+    /// The observer of the protocol negotiations on the substreams, if any.
+    negotiation_observer: Option<Arc<dyn NegotiationObserver>>,
 }
 
 impl<THandler> fmt::Debug for Connection<THandler>
@@ -185,6 +192,7 @@ where
         substream_upgrade_protocol_override: Option<upgrade::Version>,
         max_negotiating_inbound_streams: usize,
         idle_timeout: Duration,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         let initial_protocols = gather_supported_protocols(&handler);
         if !initial_protocols.is_empty() {
@@ -205,27 +213,51 @@ where
             remote_supported_protocols: Default::default(),
             idle_timeout,
             stream_counter: ActiveStreamCounter::default(),
+            clock,
+            negotiation_observer: None,
         }
     }
 
+    /// Reports the protocol negotiations on the substreams to the given observer.
+    pub(crate) fn with_negotiation_observer(
+        mut self,
+        observer: Option<Arc<dyn NegotiationObserver>>,
+    ) -> Self {
+        self.negotiation_observer = observer;
+        self
+    }
+
     /// Notifies the connection handler of an event.
     pub(crate) fn on_behaviour_event(&mut self, event: THandler::FromBehaviour) {
         self.handler.on_behaviour_event(event);
     }
 
+    /// Whether the connection handler acts upon the event, see
+    /// [`ConnectionHandler::accepts_behaviour_event`].
+    pub(crate) fn accepts_behaviour_event(&self, event: &THandler::FromBehaviour) -> bool {
+        self.handler.accepts_behaviour_event(event)
+    }
+
     /// Begins an orderly shutdown of the connection, returning a stream of final events and a `Future` that resolves when connection shutdown is complete.
+    ///
+    /// The given [`CloseReason`] is signalled to the remote if supported by the muxer.
     pub(crate) fn close(
         self,
+        reason: Option<CloseReason>,
     ) -> (
         impl futures::Stream<Item = THandler::ToBehaviour>,
         impl Future<Output = io::Result<()>>,
     ) {
         let Connection {
             mut handler,
-            muxing,
+            mut muxing,
             ..
         } = self;
 
+        if let Some(reason) = reason {
+            Pin::new(&mut muxing).set_close_reason(reason);
+        }
+
         (
             stream::poll_fn(move |cx| handler.poll_close(cx)),
             muxing.close(),
@@ -252,6 +284,8 @@ where
             remote_supported_protocols,
             idle_timeout,
             stream_counter,
+            clock,
+            negotiation_observer,
             ..
         } = self.get_mut();
 
@@ -277,7 +311,11 @@ where
                     let timeout = *protocol.timeout();
                     let (upgrade, user_data) = protocol.into_upgrade();
 
-                    requested_substreams.push(SubstreamRequested::new(user_data, timeout, upgrade));
+                    requested_substreams.push(SubstreamRequested::new(
+                        user_data,
+                        clock.sleep(timeout),
+                        upgrade,
+                    ));
                     continue; // Poll handler until exhausted.
                 }
                 Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event)) => {
@@ -364,9 +402,12 @@ where
                 && requested_substreams.is_empty()
                 && stream_counter.has_no_active_streams()
             {
-                if let Some(new_timeout) =
-                    compute_new_shutdown(handler.connection_keep_alive(), shutdown, *idle_timeout)
-                {
+                if let Some(new_timeout) = compute_new_shutdown(
+                    handler.connection_keep_alive(),
+                    shutdown,
+                    *idle_timeout,
+                    clock.as_ref(),
+                ) {
                     *shutdown = new_timeout;
                 }
 
@@ -407,6 +448,7 @@ where
                             upgrade,
                             *substream_upgrade_protocol_override,
                             stream_counter.clone(),
+                            negotiation_observer.clone(),
                         ));
 
                         continue; // Go back to the top, handler can potentially make progress again.
@@ -424,6 +466,8 @@ where
                             substream,
                             protocol,
                             stream_counter.clone(),
+                            clock.as_ref(),
+                            negotiation_observer.clone(),
                         ));
 
                         continue; // Go back to the top, handler can potentially make progress again.
@@ -467,15 +511,16 @@ fn compute_new_shutdown(
     handler_keep_alive: bool,
     current_shutdown: &Shutdown,
     idle_timeout: Duration,
+    clock: &dyn Clock,
 ) -> Option<Shutdown> {
     match (current_shutdown, handler_keep_alive) {
         (_, false) if idle_timeout == Duration::ZERO => Some(Shutdown::Asap),
         (Shutdown::Later(_), false) => None, // Do nothing, i.e. let the shutdown timer continue to tick.
         (_, false) => {
-            let now = Instant::now();
+            let now = clock.now();
             let safe_keep_alive = checked_add_fraction(now, idle_timeout);
 
-            Some(Shutdown::Later(Delay::new(safe_keep_alive)))
+            Some(Shutdown::Later(clock.sleep(safe_keep_alive)))
         }
         (_, true) => Some(Shutdown::None),
     }
@@ -516,7 +561,7 @@ impl<'a> IncomingInfo<'a> {
 
 struct StreamUpgrade<UserData, TOk, TErr> {
     user_data: Option<UserData>,
-    timeout: Delay,
+    timeout: Sleep,
     upgrade: BoxFuture<'static, Result<TOk, StreamUpgradeError<TErr>>>,
 }
 
@@ -524,10 +569,11 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
     fn new_outbound<Upgrade>(
         substream: SubstreamBox,
         user_data: UserData,
-        timeout: Delay,
+        timeout: Sleep,
         upgrade: Upgrade,
         version_override: Option<upgrade::Version>,
         counter: ActiveStreamCounter,
+        observer: Option<Arc<dyn NegotiationObserver>>,
     ) -> Self
     where
         Upgrade: OutboundUpgradeSend<Output = TOk, Error = TErr>,
@@ -555,6 +601,7 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
                     protocols,
                     effective_version,
                 )
+                .with_observer(observer)
                 .await
                 .map_err(to_stream_upgrade_error)?;
 
@@ -574,6 +621,8 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
         substream: SubstreamBox,
         protocol: SubstreamProtocol<Upgrade, UserData>,
         counter: ActiveStreamCounter,
+        clock: &dyn Clock,
+        observer: Option<Arc<dyn NegotiationObserver>>,
     ) -> Self
     where
         Upgrade: InboundUpgradeSend<Output = TOk, Error = TErr>,
@@ -584,10 +633,11 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
 
         Self {
             user_data: Some(open_info),
-            timeout: Delay::new(timeout),
+            timeout: clock.sleep(timeout),
             upgrade: Box::pin(async move {
                 let (info, stream) =
                     multistream_select::listener_select_proto(substream, protocols)
+                        .with_observer(observer)
                         .await
                         .map_err(to_stream_upgrade_error)?;
 
@@ -644,7 +694,7 @@ impl<UserData, TOk, TErr> Future for StreamUpgrade<UserData, TOk, TErr> {
 enum SubstreamRequested<UserData, Upgrade> {
     Waiting {
         user_data: UserData,
-        timeout: Delay,
+        timeout: Sleep,
         upgrade: Upgrade,
         /// A waker to notify our [`FuturesUnordered`] that we have extracted the data.
         ///
@@ -656,16 +706,16 @@ enum SubstreamRequested<UserData, Upgrade> {
 }
 
 impl<UserData, Upgrade> SubstreamRequested<UserData, Upgrade> {
-    fn new(user_data: UserData, timeout: Duration, upgrade: Upgrade) -> Self {
+    fn new(user_data: UserData, timeout: Sleep, upgrade: Upgrade) -> Self {
         Self::Waiting {
             user_data,
-            timeout: Delay::new(timeout),
+            timeout,
             upgrade,
             extracted_waker: None,
         }
     }
 
-    fn extract(&mut self) -> (UserData, Delay, Upgrade) {
+    fn extract(&mut self) -> (UserData, Sleep, Upgrade) {
         match mem::replace(self, Self::Done) {
             SubstreamRequested::Waiting {
                 user_data,
@@ -730,13 +780,14 @@ enum Shutdown {
     None,
     /// A shut down is planned as soon as possible.
     Asap,
-    /// A shut down is planned for when a `Delay` has elapsed.
-    Later(Delay),
+    /// A shut down is planned for when a [`Sleep`] has elapsed.
+    Later(Sleep),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{SystemClock, VirtualClock};
     use crate::dummy;
     use futures::future;
     use futures::AsyncRead;
@@ -767,6 +818,7 @@ mod tests {
                 None,
                 max_negotiating_inbound_streams,
                 Duration::ZERO,
+                Arc::new(SystemClock),
             );
 
             let result = connection.poll_noop_waker();
@@ -785,18 +837,20 @@ mod tests {
     #[test]
     fn outbound_stream_timeout_starts_on_request() {
         let upgrade_timeout = Duration::from_secs(1);
+        let clock = VirtualClock::new();
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
             MockConnectionHandler::new(upgrade_timeout),
             None,
             2,
             Duration::ZERO,
+            Arc::new(clock.clone()),
         );
 
         connection.handler.open_new_outbound();
         let _ = connection.poll_noop_waker();
 
-        std::thread::sleep(upgrade_timeout + Duration::from_secs(1));
+        clock.advance(upgrade_timeout);
 
         let _ = connection.poll_noop_waker();
 
@@ -814,6 +868,7 @@ mod tests {
             None,
             0,
             Duration::ZERO,
+            Arc::new(SystemClock),
         );
 
         // First, start listening on a single protocol.
@@ -853,6 +908,7 @@ mod tests {
             None,
             0,
             Duration::ZERO,
+            Arc::new(SystemClock),
         );
 
         // First, remote supports a single protocol.
@@ -896,9 +952,10 @@ mod tests {
         assert_eq!(connection.handler.remote_removed, vec![vec!["/bar"]]);
     }
 
-    #[tokio::test]
-    async fn idle_timeout_with_keep_alive_no() {
-        let idle_timeout = Duration::from_millis(100);
+    #[test]
+    fn idle_timeout_with_keep_alive_no() {
+        let idle_timeout = Duration::from_secs(10);
+        let clock = VirtualClock::new();
 
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
@@ -906,11 +963,15 @@ mod tests {
             None,
             0,
             idle_timeout,
+            Arc::new(clock.clone()),
         );
 
         assert!(connection.poll_noop_waker().is_pending());
 
-        tokio::time::sleep(idle_timeout).await;
+        clock.advance(idle_timeout / 2);
+        assert!(connection.poll_noop_waker().is_pending());
+
+        clock.advance(idle_timeout / 2);
 
         assert!(matches!(
             connection.poll_noop_waker(),
@@ -945,9 +1006,9 @@ mod tests {
                     Shutdown::None => Shutdown::None,
                     Shutdown::Asap => Shutdown::Asap,
                     Shutdown::Later(_) => Shutdown::Later(
-                        // compute_new_shutdown does not touch the delay. Delay does not
-                        // implement Clone. Thus use a placeholder delay.
-                        Delay::new(Duration::from_secs(1)),
+                        // compute_new_shutdown does not touch the sleep. Sleep does not
+                        // implement Clone. Thus use a placeholder sleep.
+                        SystemClock.sleep(Duration::from_secs(1)),
                     ),
                 };
 
@@ -960,7 +1021,9 @@ mod tests {
                 let shutdown = match g.gen_range(1u8..4) {
                     1 => Shutdown::None,
                     2 => Shutdown::Asap,
-                    3 => Shutdown::Later(Delay::new(Duration::from_secs(u32::arbitrary(g) as u64))),
+                    3 => Shutdown::Later(
+                        SystemClock.sleep(Duration::from_secs(u32::arbitrary(g) as u64)),
+                    ),
                     _ => unreachable!(),
                 };
 
@@ -973,7 +1036,12 @@ mod tests {
             current_shutdown: ArbitraryShutdown,
             idle_timeout: Duration,
         ) {
-            compute_new_shutdown(handler_keep_alive, &current_shutdown.0, idle_timeout);
+            compute_new_shutdown(
+                handler_keep_alive,
+                &current_shutdown.0,
+                idle_timeout,
+                &SystemClock,
+            );
         }
 
         QuickCheck::new().quickcheck(prop as fn(_, _, _));
@@ -21,6 +21,7 @@
 use crate::transport::TransportError;
 use crate::Multiaddr;
 use crate::{ConnectedPoint, PeerId};
+use libp2p_core::muxing::CloseReason;
 use std::{fmt, io};
 
 /// Errors that can occur in the context of an established `Connection`.
@@ -34,6 +35,16 @@ pub enum ConnectionError {
     KeepAliveTimeout,
 }
 
+impl ConnectionError {
+    /// The [`CloseReason`] signalled by the remote when closing the connection, if any.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        match self {
+            ConnectionError::IO(err) => CloseReason::from_error(err),
+            ConnectionError::KeepAliveTimeout => None,
+        }
+    }
+}
+
 impl fmt::Display for ConnectionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
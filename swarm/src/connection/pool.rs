@@ -18,16 +18,19 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
+use crate::clock::{Clock, SystemClock};
 use crate::connection::{Connection, ConnectionId, PendingPoint};
+use crate::dial_opts::RelayedAddresses;
 use crate::{
     connection::{
         Connected, ConnectionError, IncomingInfo, PendingConnectionError,
         PendingInboundConnectionError, PendingOutboundConnectionError,
     },
     transport::TransportError,
-    ConnectedPoint, ConnectionHandler, Executor, Multiaddr, PeerId,
+    ConnectedPoint, ConnectionExecutor, ConnectionHandler, ConnectionSelection, Executor,
+    Multiaddr, NotificationId, PeerId,
 };
-use concurrent_dial::ConcurrentDial;
+use concurrent_dial::{ConcurrentDial, Dial};
 use fnv::FnvHashMap;
 use futures::prelude::*;
 use futures::stream::SelectAll;
@@ -38,14 +41,20 @@ use futures::{
     stream::FuturesUnordered,
 };
 use instant::{Duration, Instant};
-use libp2p_core::connection::Endpoint;
-use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerExt};
+use libp2p_core::connection::{Endpoint, NegotiatedProtocols};
+use libp2p_core::muxing::{CloseReason, StreamMuxer, StreamMuxerBox, StreamMuxerExt};
+use libp2p_core::upgrade::NegotiationObserver;
+use smallvec::SmallVec;
 use std::task::Waker;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt,
     num::{NonZeroU8, NonZeroUsize},
     pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     task::Context,
     task::Poll,
 };
@@ -105,9 +114,15 @@ where
     /// Number of addresses concurrently dialed for a single outbound connection attempt.
     dial_concurrency_factor: NonZeroU8,
 
+    /// When to dial relayed addresses relative to direct ones.
+    relayed_addresses: RelayedAddresses,
+
     /// The configured override for substream protocol upgrades, if any.
     substream_upgrade_protocol_override: Option<libp2p_core::upgrade::Version>,
 
+    /// The observer of the protocol negotiations on substreams, if any.
+    negotiation_observer: Option<Arc<dyn NegotiationObserver>>,
+
     /// The maximum number of inbound streams concurrently negotiating on a connection.
     ///
     /// See [`Connection::max_negotiating_inbound_streams`].
@@ -120,6 +135,12 @@ where
     /// or a local queue.
     executor: ExecSwitch,
 
+    /// Selects the executor for the tasks of established connections, if any.
+    connection_executor: Option<Box<dyn ConnectionExecutor + Send>>,
+
+    /// Whether to record the [`ConnectionTaskStats`] of established connections.
+    record_task_stats: bool,
+
     /// Sender distributed to pending tasks for reporting events back
     /// to the pool.
     pending_connection_events_tx: mpsc::Sender<task::PendingConnectionEvent>,
@@ -139,6 +160,27 @@ where
 
     /// How long a connection should be kept alive once it starts idling.
     idle_connection_timeout: Duration,
+
+    /// The source of time for the timeouts of established connections.
+    clock: Arc<dyn Clock>,
+
+    /// The policy for selecting connections for [`NotifyHandler::Any`](crate::NotifyHandler::Any).
+    connection_selection: ConnectionSelection,
+
+    /// The maximum number of inbound connections concurrently upgrading, if limited.
+    max_concurrent_inbound_upgrades: Option<NonZeroUsize>,
+
+    /// How long an inbound connection waits for an upgrade slot before it is dropped.
+    inbound_upgrade_queue_timeout: Duration,
+
+    /// The number of inbound connections currently upgrading.
+    num_upgrading_incoming: usize,
+
+    /// The tasks of the inbound connections waiting for an upgrade slot, in order of arrival.
+    queued_incoming: VecDeque<(ConnectionId, BoxFuture<'static, ()>)>,
+
+    /// Resolves with the ID of a queued inbound connection once it timed out.
+    queued_incoming_timeouts: FuturesUnordered<BoxFuture<'static, ConnectionId>>,
 }
 
 #[derive(Debug)]
@@ -146,6 +188,12 @@ pub(crate) struct EstablishedConnection<TInEvent> {
     endpoint: ConnectedPoint,
     /// Channel endpoint to send commands to the task.
     sender: mpsc::Sender<task::Command<TInEvent>>,
+    /// The last round-trip time reported for this connection.
+    rtt: Option<Duration>,
+    /// The last time an event was exchanged with the handler.
+    last_active: Instant,
+    /// The statistics of the task running the connection, if recorded.
+    task_stats: Option<Arc<TaskStats>>,
 }
 
 impl<TInEvent> EstablishedConnection<TInEvent> {
@@ -159,12 +207,25 @@ impl<TInEvent> EstablishedConnection<TInEvent> {
     /// `poll_ready_notify_handler` without another intervening execution
     /// of `notify_handler`, it only fails if the connection is now about
     /// to close.
-    pub(crate) fn notify_handler(&mut self, event: TInEvent) -> Result<(), TInEvent> {
-        let cmd = task::Command::NotifyHandler(event);
-        self.sender.try_send(cmd).map_err(|e| match e.into_inner() {
-            task::Command::NotifyHandler(event) => event,
-            _ => unreachable!("Expect failed send to return initial event."),
-        })
+    ///
+    /// If `ack` is given, the handler being notified is reported via
+    /// [`PoolEvent::HandlerNotified`], the event being dropped instead via
+    /// [`PoolEvent::HandlerNotificationDropped`].
+    pub(crate) fn notify_handler(
+        &mut self,
+        event: TInEvent,
+        ack: Option<NotificationId>,
+    ) -> Result<(), TInEvent> {
+        let cmd = task::Command::NotifyHandler(event, ack);
+        self.sender
+            .try_send(cmd)
+            .map_err(|e| match e.into_inner() {
+                task::Command::NotifyHandler(event, _) => event,
+                _ => unreachable!("Expect failed send to return initial event."),
+            })?;
+        self.last_active = Instant::now();
+
+        Ok(())
     }
 
     /// Checks if `notify_handler` is ready to accept an event.
@@ -180,13 +241,14 @@ impl<TInEvent> EstablishedConnection<TInEvent> {
         self.sender.poll_ready(cx).map_err(|_| ())
     }
 
-    /// Initiates a graceful close of the connection.
+    /// Initiates a graceful close of the connection, signalling the given reason to the remote
+    /// if supported by the muxer.
     ///
     /// Has no effect if the connection is already closing.
-    pub(crate) fn start_close(&mut self) {
+    pub(crate) fn start_close(&mut self, reason: Option<CloseReason>) {
         // Clone the sender so that we are guaranteed to have
         // capacity for the close command (every sender gets a slot).
-        match self.sender.clone().try_send(task::Command::Close) {
+        match self.sender.clone().try_send(task::Command::Close(reason)) {
             Ok(()) => {}
             Err(e) => assert!(e.is_disconnected(), "No capacity for close command."),
         };
@@ -233,6 +295,8 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         peer_id: PeerId,
         endpoint: ConnectedPoint,
         connection: NewConnection,
+        /// The security and multiplexing protocols negotiated on the connection.
+        negotiated_protocols: NegotiatedProtocols,
         /// [`Some`] when the new connection is an outgoing connection.
         /// Addresses are dialed in parallel. Contains the addresses and errors
         /// of dial attempts that failed before the one successful dial.
@@ -285,6 +349,16 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         error: PendingInboundConnectionError,
     },
 
+    /// An inbound connection waited longer than the configured timeout for an upgrade slot.
+    InboundUpgradeQueueTimeout {
+        /// The ID of the dropped connection.
+        id: ConnectionId,
+        /// Address used to send back data to the remote.
+        send_back_addr: Multiaddr,
+        /// Local connection address.
+        local_addr: Multiaddr,
+    },
+
     /// A node has produced an event.
     ConnectionEvent {
         id: ConnectionId,
@@ -293,6 +367,19 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         event: ToBehaviour,
     },
 
+    /// A connection handler has been notified of an event sent with an acknowledgement.
+    HandlerNotified {
+        id: ConnectionId,
+        peer_id: PeerId,
+        notification: NotificationId,
+    },
+
+    /// An event sent with an acknowledgement has been dropped by a closing connection.
+    HandlerNotificationDropped {
+        peer_id: PeerId,
+        notification: NotificationId,
+    },
+
     /// The connection to a node has changed its address.
     AddressChange {
         id: ConnectionId,
@@ -322,11 +409,22 @@ where
             pending: Default::default(),
             task_command_buffer_size: config.task_command_buffer_size,
             dial_concurrency_factor: config.dial_concurrency_factor,
+            relayed_addresses: config.relayed_addresses,
             substream_upgrade_protocol_override: config.substream_upgrade_protocol_override,
+            negotiation_observer: config.negotiation_observer,
             max_negotiating_inbound_streams: config.max_negotiating_inbound_streams,
             per_connection_event_buffer_size: config.per_connection_event_buffer_size,
             idle_connection_timeout: config.idle_connection_timeout,
+            clock: config.clock,
+            connection_selection: config.connection_selection,
+            max_concurrent_inbound_upgrades: config.max_concurrent_inbound_upgrades,
+            inbound_upgrade_queue_timeout: config.inbound_upgrade_queue_timeout,
+            num_upgrading_incoming: 0,
+            queued_incoming: Default::default(),
+            queued_incoming_timeouts: Default::default(),
             executor,
+            connection_executor: config.connection_executor,
+            record_task_stats: config.record_task_stats,
             pending_connection_events_tx,
             pending_connection_events_rx,
             no_established_connections_waker: None,
@@ -368,10 +466,10 @@ where
     /// All connections to the peer, whether pending or established are
     /// closed asap and no more events from these connections are emitted
     /// by the pool effective immediately.
-    pub(crate) fn disconnect(&mut self, peer: PeerId) {
+    pub(crate) fn disconnect(&mut self, peer: PeerId, reason: Option<CloseReason>) {
         if let Some(conns) = self.established.get_mut(&peer) {
             for (_, conn) in conns.iter_mut() {
-                conn.start_close();
+                conn.start_close(reason.clone());
             }
         }
 
@@ -395,6 +493,71 @@ where
         }
     }
 
+    /// Returns the established connections of `peer` in the order of preference of the given
+    /// [`ConnectionSelection`], or of the configured one if `None`.
+    pub(crate) fn select_established_connections_of_peer(
+        &self,
+        peer: &PeerId,
+        selection: Option<&ConnectionSelection>,
+    ) -> SmallVec<[ConnectionId; 10]> {
+        let Some(conns) = self.established.get(peer) else {
+            return SmallVec::new();
+        };
+        let mut conns = conns.iter().collect::<SmallVec<[_; 10]>>();
+
+        match selection.unwrap_or(&self.connection_selection) {
+            ConnectionSelection::Any => {}
+            ConnectionSelection::LowestRtt => {
+                conns.sort_by_key(|(_, c)| (c.rtt.is_none(), c.rtt));
+            }
+            ConnectionSelection::Transport(transport) => {
+                conns.sort_by_key(|(_, c)| {
+                    !c.endpoint
+                        .get_remote_address()
+                        .iter()
+                        .any(|p| p.tag() == *transport)
+                });
+            }
+            ConnectionSelection::MostRecentlyActive => {
+                conns.sort_by_key(|(_, c)| std::cmp::Reverse(c.last_active));
+            }
+        }
+
+        conns.into_iter().map(|(id, _)| *id).collect()
+    }
+
+    /// Records the round-trip time of an established connection.
+    pub(crate) fn set_rtt(&mut self, id: ConnectionId, rtt: Duration) {
+        if let Some(conn) = self
+            .established
+            .values_mut()
+            .find_map(|conns| conns.get_mut(&id))
+        {
+            conn.rtt = Some(rtt);
+        }
+    }
+
+    /// Overrides the remote address of an established connection.
+    #[cfg(test)]
+    pub(crate) fn set_remote_address(&mut self, id: ConnectionId, address: Multiaddr) {
+        if let Some(conn) = self
+            .established
+            .values_mut()
+            .find_map(|conns| conns.get_mut(&id))
+        {
+            conn.endpoint.set_remote_address(address);
+        }
+    }
+
+    /// Returns the statistics of the task running an established connection.
+    pub(crate) fn task_stats(&self, id: ConnectionId) -> Option<ConnectionTaskStats> {
+        self.established
+            .values()
+            .find_map(|conns| conns.get(&id))
+            .and_then(|conn| conn.task_stats.as_ref())
+            .map(|stats| stats.snapshot())
+    }
+
     /// Checks whether we are currently dialing the given peer.
     pub(crate) fn is_dialing(&self, peer: PeerId) -> bool {
         self.pending.iter().any(|(_, info)| {
@@ -408,19 +571,19 @@ where
         self.established.keys()
     }
 
+    /// When to dial relayed addresses relative to direct ones.
+    pub(crate) fn relayed_addresses(&self) -> RelayedAddresses {
+        self.relayed_addresses
+    }
+
     /// Adds a pending outgoing connection to the pool in the form of a `Future`
     /// that establishes and negotiates the connection.
+    ///
+    /// The `fallback_dials` are only started once all `dials` failed.
     pub(crate) fn add_outgoing(
         &mut self,
-        dials: Vec<
-            BoxFuture<
-                'static,
-                (
-                    Multiaddr,
-                    Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
-                ),
-            >,
-        >,
+        dials: Vec<Dial>,
+        fallback_dials: Vec<Dial>,
         peer: Option<PeerId>,
         role_override: Endpoint,
         dial_concurrency_factor_override: Option<NonZeroU8>,
@@ -428,7 +591,7 @@ where
     ) {
         let concurrency_factor =
             dial_concurrency_factor_override.unwrap_or(self.dial_concurrency_factor);
-        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.len(), id = %connection_id);
+        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.len(), num_fallback_dials=%fallback_dials.len(), id = %connection_id);
         span.follows_from(tracing::Span::current());
 
         let (abort_notifier, abort_receiver) = oneshot::channel();
@@ -436,7 +599,7 @@ where
         self.executor.spawn(
             task::new_for_pending_outgoing_connection(
                 connection_id,
-                ConcurrentDial::new(dials, concurrency_factor),
+                ConcurrentDial::new(dials, fallback_dials, concurrency_factor),
                 abort_receiver,
                 self.pending_connection_events_tx.clone(),
             )
@@ -459,12 +622,16 @@ where
 
     /// Adds a pending incoming connection to the pool in the form of a
     /// `Future` that establishes and negotiates the connection.
+    ///
+    /// Returns `true` if the connection has been queued, as the maximum number of concurrently
+    /// upgrading inbound connections is reached.
     pub(crate) fn add_incoming<TFut>(
         &mut self,
         future: TFut,
         info: IncomingInfo<'_>,
         connection_id: ConnectionId,
-    ) where
+    ) -> bool
+    where
         TFut: Future<Output = Result<(PeerId, StreamMuxerBox), std::io::Error>> + Send + 'static,
     {
         let endpoint = info.create_connected_point();
@@ -474,15 +641,29 @@ where
         let span = tracing::debug_span!(parent: tracing::Span::none(), "new_incoming_connection", remote_addr = %info.send_back_addr, id = %connection_id);
         span.follows_from(tracing::Span::current());
 
-        self.executor.spawn(
-            task::new_for_pending_incoming_connection(
-                connection_id,
-                future,
-                abort_receiver,
-                self.pending_connection_events_tx.clone(),
-            )
-            .instrument(span),
-        );
+        let task = task::new_for_pending_incoming_connection(
+            connection_id,
+            future,
+            abort_receiver,
+            self.pending_connection_events_tx.clone(),
+        )
+        .instrument(span)
+        .boxed();
+
+        let queued = !self.has_inbound_upgrade_slot();
+        if queued {
+            tracing::debug!(
+                connection=%connection_id,
+                "Maximum number of concurrently upgrading inbound connections reached, queueing connection"
+            );
+            self.queued_incoming.push_back((connection_id, task));
+            let timeout = self.clock.sleep(self.inbound_upgrade_queue_timeout);
+            self.queued_incoming_timeouts
+                .push(timeout.map(move |()| connection_id).boxed());
+        } else {
+            self.num_upgrading_incoming += 1;
+            self.executor.spawn(task);
+        }
 
         self.counters.inc_pending_incoming();
         self.pending.insert(
@@ -494,6 +675,29 @@ where
                 accepted_at: Instant::now(),
             },
         );
+
+        queued
+    }
+
+    /// Whether another inbound connection can start upgrading without exceeding the maximum
+    /// number of concurrently upgrading inbound connections.
+    fn has_inbound_upgrade_slot(&self) -> bool {
+        self.max_concurrent_inbound_upgrades
+            .map_or(true, |max| self.num_upgrading_incoming < max.get())
+    }
+
+    /// Releases the upgrade slot of an inbound connection, starting the upgrades of queued inbound
+    /// connections for the free slots.
+    fn release_inbound_upgrade_slot(&mut self) {
+        self.num_upgrading_incoming -= 1;
+
+        while self.has_inbound_upgrade_slot() {
+            let Some((_, task)) = self.queued_incoming.pop_front() else {
+                break;
+            };
+            self.num_upgrading_incoming += 1;
+            self.executor.spawn(task);
+        }
     }
 
     pub(crate) fn spawn_connection(
@@ -510,12 +714,18 @@ where
 
         let (command_sender, command_receiver) = mpsc::channel(self.task_command_buffer_size);
         let (event_sender, event_receiver) = mpsc::channel(self.per_connection_event_buffer_size);
+        let task_stats = self
+            .record_task_stats
+            .then(|| Arc::new(TaskStats::default()));
 
         conns.insert(
             id,
             EstablishedConnection {
                 endpoint: endpoint.clone(),
                 sender: command_sender,
+                rtt: None,
+                last_active: Instant::now(),
+                task_stats: task_stats.clone(),
             },
         );
         self.established_connection_events.push(event_receiver);
@@ -529,21 +739,34 @@ where
             self.substream_upgrade_protocol_override,
             self.max_negotiating_inbound_streams,
             self.idle_connection_timeout,
-        );
+            self.clock.clone(),
+        )
+        .with_negotiation_observer(self.negotiation_observer.clone());
 
         let span = tracing::debug_span!(parent: tracing::Span::none(), "new_established_connection", remote_addr = %endpoint.get_remote_address(), %id, peer = %obtained_peer_id);
         span.follows_from(tracing::Span::current());
 
-        self.executor.spawn(
-            task::new_for_established_connection(
-                id,
-                obtained_peer_id,
-                connection,
-                command_receiver,
-                event_sender,
-            )
-            .instrument(span),
+        let task = task::new_for_established_connection(
+            id,
+            obtained_peer_id,
+            connection,
+            command_receiver,
+            event_sender,
         )
+        .instrument(span);
+        let task = match task_stats {
+            Some(stats) => stats.record(task).boxed(),
+            None => task.boxed(),
+        };
+
+        match self
+            .connection_executor
+            .as_ref()
+            .and_then(|executor| executor.select(&obtained_peer_id, endpoint))
+        {
+            Some(executor) => executor.exec(task),
+            None => self.executor.spawn(task),
+        }
     }
 
     /// Polls the connection pool for events.
@@ -564,8 +787,35 @@ where
             }
 
             Poll::Ready(Some(task::EstablishedConnectionEvent::Notify { id, peer_id, event })) => {
+                if let Some(conn) = self
+                    .established
+                    .get_mut(&peer_id)
+                    .and_then(|conns| conns.get_mut(&id))
+                {
+                    conn.last_active = Instant::now();
+                }
                 return Poll::Ready(PoolEvent::ConnectionEvent { peer_id, id, event });
             }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::HandlerNotified {
+                id,
+                peer_id,
+                notification,
+            })) => {
+                return Poll::Ready(PoolEvent::HandlerNotified {
+                    id,
+                    peer_id,
+                    notification,
+                });
+            }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::HandlerNotificationDropped {
+                peer_id,
+                notification,
+            })) => {
+                return Poll::Ready(PoolEvent::HandlerNotificationDropped {
+                    peer_id,
+                    notification,
+                });
+            }
             Poll::Ready(Some(task::EstablishedConnectionEvent::AddressChange {
                 id,
                 peer_id,
@@ -624,6 +874,35 @@ where
                 continue;
             }
 
+            if let Poll::Ready(Some(id)) = self.queued_incoming_timeouts.poll_next_unpin(cx) {
+                // The connection might have started upgrading in the meantime.
+                let Some(position) = self.queued_incoming.iter().position(|(c, _)| *c == id) else {
+                    continue;
+                };
+                // Dropping the task drops the connection.
+                self.queued_incoming.remove(position);
+
+                let PendingConnection { endpoint, .. } = self
+                    .pending
+                    .remove(&id)
+                    .expect("Entry in `self.pending` for queued connection.");
+                self.counters.dec_pending(&endpoint);
+
+                let PendingPoint::Listener {
+                    local_addr,
+                    send_back_addr,
+                } = endpoint
+                else {
+                    unreachable!("Only incoming connections are queued.")
+                };
+
+                return Poll::Ready(PoolEvent::InboundUpgradeQueueTimeout {
+                    id,
+                    send_back_addr,
+                    local_addr,
+                });
+            }
+
             let event = match self.pending_connection_events_rx.poll_next_unpin(cx) {
                 Poll::Ready(Some(event)) => event,
                 Poll::Pending => break,
@@ -647,6 +926,9 @@ where
                         .expect("Entry in `self.pending` for previously pending connection.");
 
                     self.counters.dec_pending(&endpoint);
+                    if let PendingPoint::Listener { .. } = endpoint {
+                        self.release_inbound_upgrade_slot();
+                    }
 
                     let (endpoint, concurrent_dial_errors) = match (endpoint, outgoing) {
                         (PendingPoint::Dialer { role_override }, Some((address, errors))) => (
@@ -733,6 +1015,8 @@ where
                     }
 
                     let established_in = accepted_at.elapsed();
+                    let negotiated_protocols =
+                        muxer.negotiated_protocols().cloned().unwrap_or_default();
 
                     let (connection, drop_listener) = NewConnection::new(muxer);
                     self.new_connection_dropped_listeners.push(drop_listener);
@@ -742,6 +1026,7 @@ where
                         endpoint,
                         id,
                         connection,
+                        negotiated_protocols,
                         concurrent_dial_errors,
                         established_in,
                     });
@@ -755,6 +1040,9 @@ where
                     }) = self.pending.remove(&id)
                     {
                         self.counters.dec_pending(&endpoint);
+                        if let PendingPoint::Listener { .. } = endpoint {
+                            self.release_inbound_upgrade_slot();
+                        }
 
                         match (endpoint, error) {
                             (PendingPoint::Dialer { .. }, Either::Left(error)) => {
@@ -945,6 +1233,60 @@ impl ConnectionCounters {
     }
 }
 
+/// Statistics of the task running an established connection,
+/// see [`Swarm::connection_task_stats`](crate::Swarm::connection_task_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionTaskStats {
+    num_polls: u64,
+    busy: Duration,
+}
+
+impl ConnectionTaskStats {
+    /// The number of times the task was polled.
+    pub fn num_polls(&self) -> u64 {
+        self.num_polls
+    }
+
+    /// The total time spent polling the task.
+    pub fn busy_duration(&self) -> Duration {
+        self.busy
+    }
+}
+
+/// The [`ConnectionTaskStats`] shared with the task.
+#[derive(Debug, Default)]
+struct TaskStats {
+    num_polls: AtomicU64,
+    busy_nanos: AtomicU64,
+}
+
+impl TaskStats {
+    /// Wraps the given task, recording each of its polls.
+    fn record(
+        self: &Arc<Self>,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let stats = self.clone();
+        let mut task = task.boxed();
+
+        poll_fn(move |cx| {
+            let start = Instant::now();
+            let poll = task.poll_unpin(cx);
+            let busy = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            stats.num_polls.fetch_add(1, Ordering::Relaxed);
+            stats.busy_nanos.fetch_add(busy, Ordering::Relaxed);
+            poll
+        })
+    }
+
+    fn snapshot(&self) -> ConnectionTaskStats {
+        ConnectionTaskStats {
+            num_polls: self.num_polls.load(Ordering::Relaxed),
+            busy: Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
 /// Configuration options when creating a [`Pool`].
 ///
 /// The default configuration specifies no dedicated task executor, a
@@ -952,6 +1294,10 @@ impl ConnectionCounters {
 pub(crate) struct PoolConfig {
     /// Executor to use to spawn tasks.
     pub(crate) executor: Option<Box<dyn Executor + Send>>,
+    /// Selects the executor for the tasks of established connections.
+    pub(crate) connection_executor: Option<Box<dyn ConnectionExecutor + Send>>,
+    /// Whether to record the statistics of the tasks of established connections.
+    pub(crate) record_task_stats: bool,
     /// Size of the task command buffer (per task).
     pub(crate) task_command_buffer_size: usize,
     /// Size of the pending connection task event buffer and the established connection task event
@@ -959,27 +1305,51 @@ pub(crate) struct PoolConfig {
     pub(crate) per_connection_event_buffer_size: usize,
     /// Number of addresses concurrently dialed for a single outbound connection attempt.
     pub(crate) dial_concurrency_factor: NonZeroU8,
+    /// When to dial relayed addresses relative to direct ones.
+    pub(crate) relayed_addresses: RelayedAddresses,
     /// How long a connection should be kept alive once it is idling.
     pub(crate) idle_connection_timeout: Duration,
+    /// The source of time for the timeouts of established connections.
+    pub(crate) clock: Arc<dyn Clock>,
     /// The configured override for substream protocol upgrades, if any.
     substream_upgrade_protocol_override: Option<libp2p_core::upgrade::Version>,
 
+    /// The observer of the protocol negotiations on substreams, if any.
+    negotiation_observer: Option<Arc<dyn NegotiationObserver>>,
+
     /// The maximum number of inbound streams concurrently negotiating on a connection.
     ///
     /// See [`Connection::max_negotiating_inbound_streams`].
     max_negotiating_inbound_streams: usize,
+
+    /// The policy for selecting connections for [`NotifyHandler::Any`](crate::NotifyHandler::Any).
+    pub(crate) connection_selection: ConnectionSelection,
+
+    /// The maximum number of inbound connections concurrently upgrading, if limited.
+    pub(crate) max_concurrent_inbound_upgrades: Option<NonZeroUsize>,
+
+    /// How long an inbound connection waits for an upgrade slot before it is dropped.
+    pub(crate) inbound_upgrade_queue_timeout: Duration,
 }
 
 impl PoolConfig {
     pub(crate) fn new(executor: Option<Box<dyn Executor + Send>>) -> Self {
         Self {
             executor,
+            connection_executor: None,
+            record_task_stats: false,
             task_command_buffer_size: 32,
             per_connection_event_buffer_size: 7,
             dial_concurrency_factor: NonZeroU8::new(8).expect("8 > 0"),
+            relayed_addresses: RelayedAddresses::default(),
             idle_connection_timeout: Duration::ZERO,
+            clock: Arc::new(SystemClock),
             substream_upgrade_protocol_override: None,
+            negotiation_observer: None,
             max_negotiating_inbound_streams: 128,
+            connection_selection: ConnectionSelection::Any,
+            max_concurrent_inbound_upgrades: None,
+            inbound_upgrade_queue_timeout: Duration::from_secs(10),
         }
     }
 
@@ -1021,6 +1391,15 @@ impl PoolConfig {
         self
     }
 
+    /// Reports the protocol negotiations on substreams to the given observer.
+    pub(crate) fn with_negotiation_observer(
+        mut self,
+        observer: Arc<dyn NegotiationObserver>,
+    ) -> Self {
+        self.negotiation_observer = Some(observer);
+        self
+    }
+
     /// The maximum number of inbound streams concurrently negotiating on a connection.
     ///
     /// See [`Connection::max_negotiating_inbound_streams`].
@@ -32,7 +32,7 @@ use std::{
     task::{Context, Poll},
 };
 
-type Dial = BoxFuture<
+pub(crate) type Dial = BoxFuture<
     'static,
     (
         Multiaddr,
@@ -43,28 +43,44 @@ type Dial = BoxFuture<
 pub(crate) struct ConcurrentDial {
     dials: FuturesUnordered<Dial>,
     pending_dials: Box<dyn Iterator<Item = Dial> + Send>,
+    /// Dials started only once all other dials failed.
+    fallback_dials: Vec<Dial>,
+    concurrency_factor: NonZeroU8,
     errors: Vec<(Multiaddr, TransportError<std::io::Error>)>,
 }
 
 impl Unpin for ConcurrentDial {}
 
 impl ConcurrentDial {
-    pub(crate) fn new(pending_dials: Vec<Dial>, concurrency_factor: NonZeroU8) -> Self {
+    pub(crate) fn new(
+        pending_dials: Vec<Dial>,
+        fallback_dials: Vec<Dial>,
+        concurrency_factor: NonZeroU8,
+    ) -> Self {
+        let mut dial = Self {
+            dials: FuturesUnordered::new(),
+            pending_dials: Box::new(std::iter::empty()),
+            fallback_dials,
+            concurrency_factor,
+            errors: Default::default(),
+        };
+        dial.start(pending_dials);
+        dial
+    }
+
+    /// Starts the given dials, as many as the concurrency factor allows, the remaining ones once
+    /// others fail.
+    fn start(&mut self, pending_dials: Vec<Dial>) {
         let mut pending_dials = pending_dials.into_iter();
 
-        let dials = FuturesUnordered::new();
         for dial in pending_dials.by_ref() {
-            dials.push(dial);
-            if dials.len() == concurrency_factor.get() as usize {
+            self.dials.push(dial);
+            if self.dials.len() == self.concurrency_factor.get() as usize {
                 break;
             }
         }
 
-        Self {
-            dials,
-            errors: Default::default(),
-            pending_dials: Box::new(pending_dials),
-        }
+        self.pending_dials = Box::new(pending_dials);
     }
 }
 
@@ -94,6 +110,10 @@ impl Future for ConcurrentDial {
                         self.dials.push(dial)
                     }
                 }
+                None if !self.fallback_dials.is_empty() => {
+                    let fallback_dials = std::mem::take(&mut self.fallback_dials);
+                    self.start(fallback_dials);
+                }
                 None => {
                     return Poll::Ready(Err(std::mem::take(&mut self.errors)));
                 }
@@ -28,25 +28,25 @@ use crate::{
         PendingOutboundConnectionError,
     },
     transport::TransportError,
-    ConnectionHandler, Multiaddr, PeerId,
+    ConnectionHandler, Multiaddr, NotificationId, PeerId,
 };
 use futures::{
     channel::{mpsc, oneshot},
     future::{poll_fn, Either, Future},
     SinkExt, StreamExt,
 };
-use libp2p_core::muxing::StreamMuxerBox;
+use libp2p_core::muxing::{CloseReason, StreamMuxerBox};
 use std::pin::Pin;
 use void::Void;
 
 /// Commands that can be sent to a task driving an established connection.
 #[derive(Debug)]
 pub(crate) enum Command<T> {
-    /// Notify the connection handler of an event.
-    NotifyHandler(T),
+    /// Notify the connection handler of an event, acknowledging it with the given ID, if any.
+    NotifyHandler(T, Option<NotificationId>),
     /// Gracefully close the connection (active close) before
-    /// terminating the task.
-    Close,
+    /// terminating the task, signalling the reason to the remote if given.
+    Close(Option<CloseReason>),
 }
 
 pub(crate) enum PendingConnectionEvent {
@@ -73,6 +73,18 @@ pub(crate) enum EstablishedConnectionEvent<ToBehaviour> {
         peer_id: PeerId,
         new_address: Multiaddr,
     },
+    /// The connection handler has been notified of an event sent with an acknowledgement.
+    HandlerNotified {
+        id: ConnectionId,
+        peer_id: PeerId,
+        notification: NotificationId,
+    },
+    /// An event sent with an acknowledgement has been dropped, either as the connection is closing
+    /// or as the connection handler does not accept it.
+    HandlerNotificationDropped {
+        peer_id: PeerId,
+        notification: NotificationId,
+    },
     /// Notify the manager of an event from the connection.
     Notify {
         id: ConnectionId,
@@ -183,10 +195,28 @@ pub(crate) async fn new_for_established_connection<THandler>(
         .await
         {
             Either::Left((Some(command), _)) => match command {
-                Command::NotifyHandler(event) => connection.on_behaviour_event(event),
-                Command::Close => {
+                Command::NotifyHandler(event, ack) => {
+                    let accepted = connection.accepts_behaviour_event(&event);
+                    connection.on_behaviour_event(event);
+                    if let Some(notification) = ack {
+                        let event = if accepted {
+                            EstablishedConnectionEvent::HandlerNotified {
+                                id: connection_id,
+                                peer_id,
+                                notification,
+                            }
+                        } else {
+                            EstablishedConnectionEvent::HandlerNotificationDropped {
+                                peer_id,
+                                notification,
+                            }
+                        };
+                        let _ = events.send(event).await;
+                    }
+                }
+                Command::Close(reason) => {
                     command_receiver.close();
-                    let (remaining_events, closing_muxer) = connection.close();
+                    let (remaining_events, closing_muxer) = connection.close(reason);
 
                     let _ = events
                         .send_all(&mut remaining_events.map(|event| {
@@ -197,6 +227,7 @@ pub(crate) async fn new_for_established_connection<THandler>(
                             })
                         }))
                         .await;
+                    drop_pending_commands(peer_id, &mut command_receiver, &mut events).await;
 
                     let error = closing_muxer.await.err().map(ConnectionError::IO);
 
@@ -236,7 +267,7 @@ pub(crate) async fn new_for_established_connection<THandler>(
                     }
                     Err(error) => {
                         command_receiver.close();
-                        let (remaining_events, _closing_muxer) = connection.close();
+                        let (remaining_events, _closing_muxer) = connection.close(None);
 
                         let _ = events
                             .send_all(&mut remaining_events.map(|event| {
@@ -247,6 +278,7 @@ pub(crate) async fn new_for_established_connection<THandler>(
                                 })
                             }))
                             .await;
+                        drop_pending_commands(peer_id, &mut command_receiver, &mut events).await;
 
                         // Terminate the task with the error, dropping the connection.
                         let _ = events
@@ -263,3 +295,22 @@ pub(crate) async fn new_for_established_connection<THandler>(
         }
     }
 }
+
+/// Drains the commands still queued for a closing connection, reporting the events sent with
+/// an acknowledgement as dropped.
+async fn drop_pending_commands<TInEvent, TOutEvent>(
+    peer_id: PeerId,
+    command_receiver: &mut mpsc::Receiver<Command<TInEvent>>,
+    events: &mut mpsc::Sender<EstablishedConnectionEvent<TOutEvent>>,
+) {
+    while let Ok(Some(command)) = command_receiver.try_next() {
+        if let Command::NotifyHandler(_, Some(notification)) = command {
+            let _ = events
+                .send(EstablishedConnectionEvent::HandlerNotificationDropped {
+                    peer_id,
+                    notification,
+                })
+                .await;
+        }
+    }
+}
@@ -25,6 +25,7 @@ use libp2p_core::multiaddr::Protocol;
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use std::num::NonZeroU8;
+use std::time::Duration;
 
 /// Options to configure a dial to a known or unknown peer.
 ///
@@ -44,6 +45,7 @@ pub struct DialOpts {
     extend_addresses_through_behaviour: bool,
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
+    dial_timeout_override: Option<Duration>,
     connection_id: ConnectionId,
 }
 
@@ -65,6 +67,7 @@ impl DialOpts {
             condition: Default::default(),
             role_override: Endpoint::Dialer,
             dial_concurrency_factor_override: Default::default(),
+            dial_timeout_override: Default::default(),
         }
     }
 
@@ -105,6 +108,11 @@ impl DialOpts {
         self.connection_id
     }
 
+    /// The [`PeerId`] given via [`DialOpts::peer_id`], ignoring the addresses.
+    pub(crate) fn explicit_peer_id(&self) -> Option<PeerId> {
+        self.peer_id
+    }
+
     pub(crate) fn get_addresses(&self) -> Vec<Multiaddr> {
         self.addresses.clone()
     }
@@ -124,6 +132,10 @@ impl DialOpts {
     pub(crate) fn role_override(&self) -> Endpoint {
         self.role_override
     }
+
+    pub(crate) fn dial_timeout_override(&self) -> Option<Duration> {
+        self.dial_timeout_override
+    }
 }
 
 impl From<Multiaddr> for DialOpts {
@@ -144,6 +156,7 @@ pub struct WithPeerId {
     condition: PeerCondition,
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
+    dial_timeout_override: Option<Duration>,
 }
 
 impl WithPeerId {
@@ -160,6 +173,15 @@ impl WithPeerId {
         self
     }
 
+    /// Override the timeout of dialing a single address.
+    ///
+    /// Takes precedence over the default dial timeout of the transport, see
+    /// [`Transport::dial_timeout`](libp2p_core::Transport::dial_timeout).
+    pub fn override_dial_timeout(mut self, timeout: Duration) -> Self {
+        self.dial_timeout_override = Some(timeout);
+        self
+    }
+
     /// Specify a set of addresses to be used to dial the known peer.
     pub fn addresses(self, addresses: Vec<Multiaddr>) -> WithPeerIdWithAddresses {
         WithPeerIdWithAddresses {
@@ -169,6 +191,7 @@ impl WithPeerId {
             extend_addresses_through_behaviour: false,
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
+            dial_timeout_override: self.dial_timeout_override,
         }
     }
 
@@ -192,6 +215,7 @@ impl WithPeerId {
             extend_addresses_through_behaviour: true,
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
+            dial_timeout_override: self.dial_timeout_override,
             connection_id: ConnectionId::next(),
         }
     }
@@ -205,6 +229,7 @@ pub struct WithPeerIdWithAddresses {
     extend_addresses_through_behaviour: bool,
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
+    dial_timeout_override: Option<Duration>,
 }
 
 impl WithPeerIdWithAddresses {
@@ -239,6 +264,15 @@ impl WithPeerIdWithAddresses {
         self
     }
 
+    /// Override the timeout of dialing a single address.
+    ///
+    /// Takes precedence over the default dial timeout of the transport, see
+    /// [`Transport::dial_timeout`](libp2p_core::Transport::dial_timeout).
+    pub fn override_dial_timeout(mut self, timeout: Duration) -> Self {
+        self.dial_timeout_override = Some(timeout);
+        self
+    }
+
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
         DialOpts {
@@ -248,6 +282,7 @@ impl WithPeerIdWithAddresses {
             extend_addresses_through_behaviour: self.extend_addresses_through_behaviour,
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
+            dial_timeout_override: self.dial_timeout_override,
             connection_id: ConnectionId::next(),
         }
     }
@@ -262,6 +297,7 @@ impl WithoutPeerId {
         WithoutPeerIdWithAddress {
             address,
             role_override: Endpoint::Dialer,
+            dial_timeout_override: None,
         }
     }
 }
@@ -270,6 +306,7 @@ impl WithoutPeerId {
 pub struct WithoutPeerIdWithAddress {
     address: Multiaddr,
     role_override: Endpoint,
+    dial_timeout_override: Option<Duration>,
 }
 
 impl WithoutPeerIdWithAddress {
@@ -283,6 +320,16 @@ impl WithoutPeerIdWithAddress {
         self.role_override = Endpoint::Listener;
         self
     }
+
+    /// Override the timeout of dialing a single address.
+    ///
+    /// Takes precedence over the default dial timeout of the transport, see
+    /// [`Transport::dial_timeout`](libp2p_core::Transport::dial_timeout).
+    pub fn override_dial_timeout(mut self, timeout: Duration) -> Self {
+        self.dial_timeout_override = Some(timeout);
+        self
+    }
+
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
         DialOpts {
@@ -292,6 +339,7 @@ impl WithoutPeerIdWithAddress {
             extend_addresses_through_behaviour: false,
             role_override: self.role_override,
             dial_concurrency_factor_override: None,
+            dial_timeout_override: self.dial_timeout_override,
             connection_id: ConnectionId::next(),
         }
     }
@@ -327,3 +375,33 @@ pub enum PeerCondition {
     /// configured connection limits.
     Always,
 }
+
+/// When to dial the relayed addresses of a peer, i.e. the addresses containing `/p2p-circuit`,
+/// relative to its direct addresses.
+///
+/// Configured via [`Config::with_relayed_addresses`](crate::Config::with_relayed_addresses).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum RelayedAddresses {
+    /// Dial the relayed addresses only once the dials of all direct addresses failed.
+    #[default]
+    DialLast,
+    /// Dial the direct addresses only once the dials of all relayed addresses failed.
+    DialFirst,
+    /// Dial relayed and direct addresses alike, ordered by their
+    /// [score](libp2p_core::Transport::address_score) only.
+    Unordered,
+}
+
+impl RelayedAddresses {
+    /// Splits the addresses into the ones to dial first and the ones to fall back to, keeping
+    /// their order.
+    pub(crate) fn split(self, addresses: Vec<Multiaddr>) -> (Vec<Multiaddr>, Vec<Multiaddr>) {
+        let is_relayed = |addr: &Multiaddr| addr.iter().any(|p| p == Protocol::P2pCircuit);
+
+        match self {
+            RelayedAddresses::DialLast => addresses.into_iter().partition(|a| !is_relayed(a)),
+            RelayedAddresses::DialFirst => addresses.into_iter().partition(is_relayed),
+            RelayedAddresses::Unordered => (addresses, Vec::new()),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 //! Provides executors for spawning background tasks.
 use futures::executor::ThreadPool;
+use libp2p_core::ConnectedPoint;
+use libp2p_identity::PeerId;
 use std::{future::Future, pin::Pin};
 
 /// Implemented on objects that can run a `Future` in the background.
@@ -26,6 +28,17 @@ impl Executor for ThreadPool {
     }
 }
 
+/// Selects the [`Executor`] running the task of an established connection,
+/// see [`Config::with_connection_executor`](crate::Config::with_connection_executor).
+///
+/// This allows to run connections with a high load, e.g. relayed connections of a relay server,
+/// on dedicated threads or runtimes.
+pub trait ConnectionExecutor {
+    /// Returns the executor for the task of the given connection, or `None` to run it on the
+    /// executor of the [`Swarm`](crate::Swarm).
+    fn select(&self, peer: &PeerId, endpoint: &ConnectedPoint) -> Option<&dyn Executor>;
+}
+
 #[cfg(all(
     feature = "tokio",
     not(any(target_os = "emscripten", target_os = "wasi", target_os = "unknown"))
@@ -49,7 +49,7 @@ mod select;
 pub use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend, SendWrapper, UpgradeInfoSend};
 pub use map_in::MapInEvent;
 pub use map_out::MapOutEvent;
-pub use one_shot::{OneShotHandler, OneShotHandlerConfig};
+pub use one_shot::{OneShotHandler, OneShotHandlerConfig, OneShotHandlerError};
 pub use pending::PendingConnectionHandler;
 pub use select::ConnectionHandlerSelect;
 
@@ -197,6 +197,17 @@ pub trait ConnectionHandler: Send + 'static {
     /// Informs the handler about an event from the [`NetworkBehaviour`](super::NetworkBehaviour).
     fn on_behaviour_event(&mut self, _event: Self::FromBehaviour);
 
+    /// Whether the handler acts upon `event` when passed to
+    /// [`ConnectionHandler::on_behaviour_event`] instead of dropping it.
+    ///
+    /// An event sent via [`ToSwarm::NotifyHandlerWithAck`](crate::ToSwarm::NotifyHandlerWithAck)
+    /// that is not accepted is reported via
+    /// [`FromSwarm::HandlerNotificationDropped`](crate::FromSwarm::HandlerNotificationDropped).
+    /// Handlers wrapping other handlers are to delegate this method.
+    fn accepts_behaviour_event(&self, _event: &Self::FromBehaviour) -> bool {
+        true
+    }
+
     fn on_connection_event(
         &mut self,
         event: ConnectionEvent<
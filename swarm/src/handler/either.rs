@@ -107,6 +107,16 @@ where
         }
     }
 
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        match (self, event) {
+            (Either::Left(handler), Either::Left(event)) => handler.accepts_behaviour_event(event),
+            (Either::Right(handler), Either::Right(event)) => {
+                handler.accepts_behaviour_event(event)
+            }
+            _ => false,
+        }
+    }
+
     fn connection_keep_alive(&self) -> bool {
         match self {
             Either::Left(handler) => handler.connection_keep_alive(),
@@ -228,3 +238,70 @@ where
         }
     }
 }
+
+/// A [`ConnectionHandler`] that is either of two [`ConnectionHandler`]s, like the implementation
+/// on [`Either`], but drops events from the behaviour addressed to the other handler instead of
+/// panicking.
+///
+/// Used by `#[derive(NetworkBehaviour)]` on enums: the active variant can change while the
+/// handlers created by the previous variant remain on their connections.
+#[derive(Debug)]
+pub struct LenientEither<L, R>(pub Either<L, R>);
+
+impl<L, R> ConnectionHandler for LenientEither<L, R>
+where
+    L: ConnectionHandler,
+    R: ConnectionHandler,
+{
+    type FromBehaviour = <Either<L, R> as ConnectionHandler>::FromBehaviour;
+    type ToBehaviour = <Either<L, R> as ConnectionHandler>::ToBehaviour;
+    type InboundProtocol = <Either<L, R> as ConnectionHandler>::InboundProtocol;
+    type OutboundProtocol = <Either<L, R> as ConnectionHandler>::OutboundProtocol;
+    type InboundOpenInfo = <Either<L, R> as ConnectionHandler>::InboundOpenInfo;
+    type OutboundOpenInfo = <Either<L, R> as ConnectionHandler>::OutboundOpenInfo;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        self.0.listen_protocol()
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        match (&mut self.0, event) {
+            (Either::Left(handler), Either::Left(event)) => handler.on_behaviour_event(event),
+            (Either::Right(handler), Either::Right(event)) => handler.on_behaviour_event(event),
+            _ => tracing::debug!("Dropping event addressed to the handler of another variant"),
+        }
+    }
+
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        self.0.accepts_behaviour_event(event)
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.0.connection_keep_alive()
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        self.0.poll(cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::ToBehaviour>> {
+        self.0.poll_close(cx)
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        self.0.on_connection_event(event)
+    }
+}
@@ -61,6 +61,10 @@ where
         self.inner.on_behaviour_event(event)
     }
 
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        self.inner.accepts_behaviour_event(event)
+    }
+
     fn connection_keep_alive(&self) -> bool {
         self.inner.connection_keep_alive()
     }
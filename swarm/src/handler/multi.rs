@@ -228,6 +228,12 @@ where
         }
     }
 
+    fn accepts_behaviour_event(&self, (key, event): &Self::FromBehaviour) -> bool {
+        self.handlers
+            .get(key)
+            .is_some_and(|h| h.accepts_behaviour_event(event))
+    }
+
     fn connection_keep_alive(&self) -> bool {
         self.handlers
             .values()
@@ -20,25 +20,32 @@
 
 use crate::handler::{
     ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, DialUpgradeError,
-    FullyNegotiatedInbound, FullyNegotiatedOutbound, SubstreamProtocol,
+    FullyNegotiatedInbound, FullyNegotiatedOutbound, ListenUpgradeError, SubstreamProtocol,
 };
 use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend};
 use crate::StreamUpgradeError;
-use smallvec::SmallVec;
-use std::{error, fmt::Debug, task::Context, task::Poll, time::Duration};
+use std::collections::VecDeque;
+use std::{error, fmt, fmt::Debug, task::Context, task::Poll, time::Duration};
 
 /// A [`ConnectionHandler`] that opens a new substream for each request.
+///
+/// Requests are queued until a substream can be opened for them, up to
+/// [`OneShotHandlerConfig::max_pending_requests`]. Requests exceeding the limit are dropped
+/// and reported as [`OneShotHandlerError::QueueFull`].
+///
+/// The connection is kept alive while requests are pending.
 // TODO: Debug
 pub struct OneShotHandler<TInbound, TOutbound, TEvent>
 where
+    TInbound: InboundUpgradeSend,
     TOutbound: OutboundUpgradeSend,
 {
     /// The upgrade for inbound substreams.
     listen_protocol: SubstreamProtocol<TInbound, ()>,
     /// Queue of events to produce in `poll()`.
-    events_out: SmallVec<[Result<TEvent, StreamUpgradeError<TOutbound::Error>>; 4]>,
+    events_out: VecDeque<Result<TEvent, OneShotHandlerError<TInbound::Error, TOutbound::Error>>>,
     /// Queue of outbound substreams to open.
-    dial_queue: SmallVec<[TOutbound; 4]>,
+    dial_queue: VecDeque<TOutbound>,
     /// Current number of concurrent outbound substreams being opened.
     dial_negotiated: u32,
     /// The configuration container for the handler
@@ -47,6 +54,7 @@ where
 
 impl<TInbound, TOutbound, TEvent> OneShotHandler<TInbound, TOutbound, TEvent>
 where
+    TInbound: InboundUpgradeSend,
     TOutbound: OutboundUpgradeSend,
 {
     /// Creates a `OneShotHandler`.
@@ -56,8 +64,8 @@ where
     ) -> Self {
         OneShotHandler {
             listen_protocol,
-            events_out: SmallVec::new(),
-            dial_queue: SmallVec::new(),
+            events_out: VecDeque::new(),
+            dial_queue: VecDeque::new(),
             dial_negotiated: 0,
             config,
         }
@@ -85,8 +93,18 @@ where
     }
 
     /// Opens an outbound substream with `upgrade`.
+    ///
+    /// If [`OneShotHandlerConfig::max_pending_requests`] is reached, the request is dropped
+    /// and [`OneShotHandlerError::QueueFull`] is reported instead.
     pub fn send_request(&mut self, upgrade: TOutbound) {
-        self.dial_queue.push(upgrade);
+        if self.dial_queue.len() >= self.config.max_pending_requests {
+            tracing::debug!("Dropping outbound request because the queue is full");
+            self.events_out
+                .push_back(Err(OneShotHandlerError::QueueFull));
+            return;
+        }
+
+        self.dial_queue.push_back(upgrade);
     }
 }
 
@@ -109,12 +127,13 @@ where
     TOutbound: Debug + OutboundUpgradeSend,
     TInbound::Output: Into<TEvent>,
     TOutbound::Output: Into<TEvent>,
+    TInbound::Error: error::Error + Send + 'static,
     TOutbound::Error: error::Error + Send + 'static,
     SubstreamProtocol<TInbound, ()>: Clone,
     TEvent: Debug + Send + 'static,
 {
     type FromBehaviour = TOutbound;
-    type ToBehaviour = Result<TEvent, StreamUpgradeError<TOutbound::Error>>;
+    type ToBehaviour = Result<TEvent, OneShotHandlerError<TInbound::Error, TOutbound::Error>>;
     type InboundProtocol = TInbound;
     type OutboundProtocol = TOutbound;
     type OutboundOpenInfo = ();
@@ -128,30 +147,32 @@ where
         self.send_request(event);
     }
 
+    fn connection_keep_alive(&self) -> bool {
+        self.pending_requests() > 0
+    }
+
     fn poll(
         &mut self,
         _: &mut Context<'_>,
     ) -> Poll<
         ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
     > {
-        if !self.events_out.is_empty() {
-            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
-                self.events_out.remove(0),
-            ));
+        if let Some(event) = self.events_out.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
         } else {
             self.events_out.shrink_to_fit();
         }
 
-        if !self.dial_queue.is_empty() {
-            if self.dial_negotiated < self.config.max_dial_negotiated {
+        if self.dial_negotiated < self.config.max_dial_negotiated {
+            if let Some(upgrade) = self.dial_queue.pop_front() {
                 self.dial_negotiated += 1;
-                let upgrade = self.dial_queue.remove(0);
                 return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
                     protocol: SubstreamProtocol::new(upgrade, ())
                         .with_timeout(self.config.outbound_substream_timeout),
                 });
             }
-        } else {
+        }
+        if self.dial_queue.is_empty() {
             self.dial_queue.shrink_to_fit();
         }
 
@@ -172,26 +193,76 @@ where
                 protocol: out,
                 ..
             }) => {
-                self.events_out.push(Ok(out.into()));
+                self.events_out.push_back(Ok(out.into()));
             }
             ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
                 protocol: out,
                 ..
             }) => {
                 self.dial_negotiated -= 1;
-                self.events_out.push(Ok(out.into()));
+                self.events_out.push_back(Ok(out.into()));
             }
             ConnectionEvent::DialUpgradeError(DialUpgradeError { error, .. }) => {
-                self.events_out.push(Err(error));
+                self.dial_negotiated -= 1;
+                self.events_out
+                    .push_back(Err(OneShotHandlerError::Outbound(error)));
+            }
+            ConnectionEvent::ListenUpgradeError(ListenUpgradeError { error, .. }) => {
+                self.events_out
+                    .push_back(Err(OneShotHandlerError::Inbound(error)));
             }
             ConnectionEvent::AddressChange(_)
-            | ConnectionEvent::ListenUpgradeError(_)
             | ConnectionEvent::LocalProtocolsChange(_)
             | ConnectionEvent::RemoteProtocolsChange(_) => {}
         }
     }
 }
 
+/// Error reported by a [`OneShotHandler`] to its behaviour.
+#[derive(Debug)]
+pub enum OneShotHandlerError<TInboundError, TOutboundError> {
+    /// Opening or upgrading an outbound substream failed.
+    Outbound(StreamUpgradeError<TOutboundError>),
+    /// Upgrading an inbound substream failed.
+    Inbound(TInboundError),
+    /// An outbound request was dropped because
+    /// [`OneShotHandlerConfig::max_pending_requests`] was reached.
+    QueueFull,
+}
+
+impl<TInboundError, TOutboundError> fmt::Display
+    for OneShotHandlerError<TInboundError, TOutboundError>
+where
+    TInboundError: error::Error + 'static,
+    TOutboundError: error::Error + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OneShotHandlerError::Outbound(e) => write!(f, "Outbound substream failed: {e}"),
+            OneShotHandlerError::Inbound(e) => {
+                write!(f, "Inbound substream failed: ")?;
+                crate::print_error_chain(f, e)
+            }
+            OneShotHandlerError::QueueFull => write!(f, "Too many pending outbound requests"),
+        }
+    }
+}
+
+impl<TInboundError, TOutboundError> error::Error
+    for OneShotHandlerError<TInboundError, TOutboundError>
+where
+    TInboundError: error::Error + 'static,
+    TOutboundError: error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            OneShotHandlerError::Outbound(e) => Some(e),
+            OneShotHandlerError::Inbound(e) => Some(e),
+            OneShotHandlerError::QueueFull => None,
+        }
+    }
+}
+
 /// Configuration parameters for the `OneShotHandler`
 #[derive(Debug)]
 pub struct OneShotHandlerConfig {
@@ -199,6 +270,8 @@ pub struct OneShotHandlerConfig {
     pub outbound_substream_timeout: Duration,
     /// Maximum number of concurrent outbound substreams being opened.
     pub max_dial_negotiated: u32,
+    /// Maximum number of outbound requests waiting for a substream to be opened.
+    pub max_pending_requests: usize,
 }
 
 impl Default for OneShotHandlerConfig {
@@ -206,6 +279,7 @@ impl Default for OneShotHandlerConfig {
         OneShotHandlerConfig {
             outbound_substream_timeout: Duration::from_secs(10),
             max_dial_negotiated: 8,
+            max_pending_requests: 1024,
         }
     }
 }
@@ -234,4 +308,58 @@ mod tests {
 
         assert!(!handler.connection_keep_alive());
     }
+
+    #[test]
+    fn keep_connection_alive_while_requests_are_pending() {
+        let mut handler: OneShotHandler<_, DeniedUpgrade, Void> = OneShotHandler::new(
+            SubstreamProtocol::new(DeniedUpgrade {}, ()),
+            Default::default(),
+        );
+
+        handler.send_request(DeniedUpgrade);
+        assert!(handler.connection_keep_alive());
+
+        let event = block_on(poll_fn(|cx| handler.poll(cx)));
+        assert!(matches!(
+            event,
+            ConnectionHandlerEvent::OutboundSubstreamRequest { .. }
+        ));
+        assert!(handler.connection_keep_alive());
+
+        handler.on_connection_event(ConnectionEvent::DialUpgradeError(DialUpgradeError {
+            info: (),
+            error: StreamUpgradeError::NegotiationFailed,
+        }));
+
+        let event = block_on(poll_fn(|cx| handler.poll(cx)));
+        assert!(matches!(
+            event,
+            ConnectionHandlerEvent::NotifyBehaviour(Err(OneShotHandlerError::Outbound(
+                StreamUpgradeError::NegotiationFailed
+            )))
+        ));
+        assert!(!handler.connection_keep_alive());
+    }
+
+    #[test]
+    fn report_dropped_requests() {
+        let mut handler: OneShotHandler<_, DeniedUpgrade, Void> = OneShotHandler::new(
+            SubstreamProtocol::new(DeniedUpgrade {}, ()),
+            OneShotHandlerConfig {
+                max_dial_negotiated: 0,
+                max_pending_requests: 1,
+                ..Default::default()
+            },
+        );
+
+        handler.send_request(DeniedUpgrade);
+        handler.send_request(DeniedUpgrade);
+        assert_eq!(handler.pending_requests(), 1);
+
+        let event = block_on(poll_fn(|cx| handler.poll(cx)));
+        assert!(matches!(
+            event,
+            ConnectionHandlerEvent::NotifyBehaviour(Err(OneShotHandlerError::QueueFull))
+        ));
+    }
 }
@@ -207,6 +207,13 @@ where
         }
     }
 
+    fn accepts_behaviour_event(&self, event: &Self::FromBehaviour) -> bool {
+        match event {
+            Either::Left(event) => self.proto1.accepts_behaviour_event(event),
+            Either::Right(event) => self.proto2.accepts_behaviour_event(event),
+        }
+    }
+
     fn connection_keep_alive(&self) -> bool {
         cmp::max(
             self.proto1.connection_keep_alive(),
@@ -55,6 +55,7 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod clock;
 mod connection;
 mod executor;
 mod stream;
@@ -80,19 +81,24 @@ pub mod derive_prelude {
     pub use crate::behaviour::ExternalAddrConfirmed;
     pub use crate::behaviour::ExternalAddrExpired;
     pub use crate::behaviour::FromSwarm;
+    pub use crate::behaviour::HandlerNotificationDropped;
     pub use crate::behaviour::ListenFailure;
     pub use crate::behaviour::ListenerClosed;
     pub use crate::behaviour::ListenerError;
     pub use crate::behaviour::NewExternalAddrCandidate;
+    pub use crate::behaviour::NewExternalAddrCandidates;
     pub use crate::behaviour::NewExternalAddrOfPeer;
     pub use crate::behaviour::NewListenAddr;
     pub use crate::behaviour::NewListener;
+    pub use crate::behaviour::ReachabilityChanged;
     pub use crate::connection::ConnectionId;
+    pub use crate::handler::either::LenientEither;
     pub use crate::ConnectionDenied;
     pub use crate::ConnectionHandler;
     pub use crate::ConnectionHandlerSelect;
     pub use crate::DialError;
     pub use crate::NetworkBehaviour;
+    pub use crate::PollObserver;
     pub use crate::THandler;
     pub use crate::THandlerInEvent;
     pub use crate::THandlerOutEvent;
@@ -104,20 +110,24 @@ pub mod derive_prelude {
     pub use libp2p_core::Endpoint;
     pub use libp2p_core::Multiaddr;
     pub use libp2p_identity::PeerId;
+    pub use tracing;
 }
 
 pub use behaviour::{
-    AddressChange, CloseConnection, ConnectionClosed, DialFailure, ExpiredListenAddr,
-    ExternalAddrExpired, ExternalAddresses, FromSwarm, ListenAddresses, ListenFailure,
-    ListenerClosed, ListenerError, NetworkBehaviour, NewExternalAddrCandidate,
-    NewExternalAddrOfPeer, NewListenAddr, NotifyHandler, PeerAddresses, ToSwarm,
+    AddressChange, CloseConnection, ConnectionClosed, ConnectionSelection, DialFailure,
+    ExpiredListenAddr, ExternalAddrExpired, ExternalAddrExpiryReason, ExternalAddrOrigin,
+    ExternalAddresses, FromSwarm, HandlerNotificationDropped, HandlerNotified, ListenAddresses,
+    ListenFailure, ListenerClosed, ListenerError, NetworkBehaviour, NewExternalAddrCandidate,
+    NewExternalAddrCandidates, NewExternalAddrOfPeer, NewListenAddr, NotificationId, NotifyHandler,
+    PeerAddresses, PollObserver, Reachability, ReachabilityChanged, ToSwarm,
 };
-pub use connection::pool::ConnectionCounters;
+pub use clock::{Clock, Interval, Sleep, SystemClock, VirtualClock};
+pub use connection::pool::{ConnectionCounters, ConnectionTaskStats};
 pub use connection::{ConnectionError, ConnectionId, SupportedProtocols};
-pub use executor::Executor;
+pub use executor::{ConnectionExecutor, Executor};
 pub use handler::{
     ConnectionHandler, ConnectionHandlerEvent, ConnectionHandlerSelect, OneShotHandler,
-    OneShotHandlerConfig, StreamUpgradeError, SubstreamProtocol,
+    OneShotHandlerConfig, OneShotHandlerError, StreamUpgradeError, SubstreamProtocol,
 };
 #[cfg(feature = "macros")]
 pub use libp2p_swarm_derive::NetworkBehaviour;
@@ -132,11 +142,11 @@ use connection::IncomingInfo;
 use connection::{
     PendingConnectionError, PendingInboundConnectionError, PendingOutboundConnectionError,
 };
-use dial_opts::{DialOpts, PeerCondition};
+use dial_opts::{DialOpts, PeerCondition, RelayedAddresses};
 use futures::{prelude::*, stream::FusedStream};
 use libp2p_core::{
-    connection::ConnectedPoint,
-    muxing::StreamMuxerBox,
+    connection::{ConnectedPoint, NegotiatedProtocols},
+    muxing::{CloseReason, StreamMuxerBox},
     transport::{self, ListenerId, TransportError, TransportEvent},
     Endpoint, Multiaddr, Transport,
 };
@@ -144,14 +154,18 @@ use libp2p_identity::PeerId;
 use smallvec::SmallVec;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+use std::sync::Arc;
 use std::time::Duration;
 use std::{
-    error, fmt, io,
+    cmp, error, fmt, io,
     pin::Pin,
     task::{Context, Poll},
 };
 use tracing::Instrument;
 
+/// The number of external address candidates whose origin is remembered.
+const MAX_CANDIDATE_ORIGINS: usize = 32;
+
 /// Event generated by the [`NetworkBehaviour`] that the swarm will report back.
 type TBehaviourOutEvent<TBehaviour> = <TBehaviour as NetworkBehaviour>::ToSwarm;
 
@@ -180,6 +194,8 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         connection_id: ConnectionId,
         /// Endpoint of the connection that has been opened.
         endpoint: ConnectedPoint,
+        /// The security and multiplexing protocols negotiated on the connection.
+        negotiated_protocols: NegotiatedProtocols,
         /// Number of established connections to this peer, including the one that has just been
         /// opened.
         num_established: NonZeroU32,
@@ -236,6 +252,21 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         /// The error that happened.
         error: ListenError,
     },
+    /// A new connection arrived on a listener while the maximum number of concurrently upgrading
+    /// inbound connections is reached, see [`Config::with_max_concurrent_inbound_upgrades`].
+    ///
+    /// The connection starts upgrading once the upgrade of another inbound connection completes.
+    /// If that doesn't happen within the configured timeout, an
+    /// [`IncomingConnectionError`](SwarmEvent::IncomingConnectionError) with
+    /// [`ListenError::UpgradeQueueTimeout`] is generated for it.
+    IncomingConnectionQueued {
+        /// Identifier of the connection.
+        connection_id: ConnectionId,
+        /// Local connection address.
+        local_addr: Multiaddr,
+        /// Address used to send back data to the remote.
+        send_back_addr: Multiaddr,
+    },
     /// An error happened on an outbound connection.
     OutgoingConnectionError {
         /// Identifier of the connection.
@@ -251,6 +282,8 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         listener_id: ListenerId,
         /// The new address that is being listened on.
         address: Multiaddr,
+        /// The metadata attached to the listener via [`ListenOpts::with_metadata`].
+        metadata: Option<String>,
     },
     /// One of our listeners has reported the expiration of a listening address.
     ExpiredListenAddr {
@@ -258,6 +291,8 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         listener_id: ListenerId,
         /// The expired address.
         address: Multiaddr,
+        /// The metadata attached to the listener via [`ListenOpts::with_metadata`].
+        metadata: Option<String>,
     },
     /// One of the listeners gracefully closed.
     ListenerClosed {
@@ -270,6 +305,8 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         /// Reason for the closure. Contains `Ok(())` if the stream produced `None`, or `Err`
         /// if the stream produced an error.
         reason: Result<(), io::Error>,
+        /// The metadata attached to the listener via [`ListenOpts::with_metadata`].
+        metadata: Option<String>,
     },
     /// One of the listeners reported a non-fatal error.
     ListenerError {
@@ -277,6 +314,8 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         listener_id: ListenerId,
         /// The listener error.
         error: io::Error,
+        /// The metadata attached to the listener via [`ListenOpts::with_metadata`].
+        metadata: Option<String>,
     },
     /// A new dialing attempt has been initiated by the [`NetworkBehaviour`]
     /// implementation.
@@ -293,13 +332,33 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         connection_id: ConnectionId,
     },
     /// We have discovered a new candidate for an external address for us.
-    NewExternalAddrCandidate { address: Multiaddr },
+    NewExternalAddrCandidate {
+        address: Multiaddr,
+        /// The subsystem that proposed the candidate.
+        origin: ExternalAddrOrigin,
+    },
     /// An external address of the local node was confirmed.
-    ExternalAddrConfirmed { address: Multiaddr },
+    ExternalAddrConfirmed {
+        address: Multiaddr,
+        /// The subsystem that proposed the address, or confirmed it if it was never proposed
+        /// as a candidate.
+        origin: ExternalAddrOrigin,
+    },
     /// An external address of the local node expired, i.e. is no-longer confirmed.
-    ExternalAddrExpired { address: Multiaddr },
+    ExternalAddrExpired {
+        address: Multiaddr,
+        /// The origin the address was confirmed with.
+        origin: ExternalAddrOrigin,
+        /// Why the address expired.
+        reason: ExternalAddrExpiryReason,
+    },
     /// We have discovered a new address of a peer.
     NewExternalAddrOfPeer { peer_id: PeerId, address: Multiaddr },
+    /// The reachability of the local node changed.
+    ReachabilityChanged {
+        old: Reachability,
+        new: Reachability,
+    },
 }
 
 impl<TBehaviourOutEvent> SwarmEvent<TBehaviourOutEvent> {
@@ -337,15 +396,31 @@ where
     /// List of protocols that the behaviour says it supports.
     supported_protocols: SmallVec<[Vec<u8>; 16]>,
 
-    confirmed_external_addr: HashSet<Multiaddr>,
+    /// The confirmed external addresses and the subsystem each one originates from.
+    confirmed_external_addr: HashMap<Multiaddr, ExternalAddrOrigin>,
+
+    /// The origins of the most recent external address candidates, used to attribute
+    /// confirmed addresses to the subsystem that proposed them.
+    candidate_origins: VecDeque<(Multiaddr, ExternalAddrOrigin)>,
 
     /// Multiaddresses that our listeners are listening on,
     listened_addrs: HashMap<ListenerId, SmallVec<[Multiaddr; 1]>>,
 
+    /// Metadata attached to our listeners via [`ListenOpts::with_metadata`].
+    listener_metadata: HashMap<ListenerId, String>,
+
+    /// The reachability of the local node, as last reported via [`ToSwarm::ReachabilityChanged`].
+    reachability: Reachability,
+
     /// Pending event to be delivered to connection handlers
     /// (or dropped if the peer disconnected) before the `behaviour`
-    /// can be polled again.
-    pending_handler_event: Option<(PeerId, PendingNotifyHandler, THandlerInEvent<TBehaviour>)>,
+    /// can be polled again, together with the ID to acknowledge it with, if any.
+    pending_handler_event: Option<(
+        PeerId,
+        PendingNotifyHandler,
+        THandlerInEvent<TBehaviour>,
+        Option<NotificationId>,
+    )>,
 
     pending_swarm_events: VecDeque<SwarmEvent<TBehaviour::ToSwarm>>,
 }
@@ -373,7 +448,10 @@ where
             behaviour,
             supported_protocols: Default::default(),
             confirmed_external_addr: Default::default(),
+            candidate_origins: VecDeque::default(),
             listened_addrs: HashMap::new(),
+            listener_metadata: HashMap::new(),
+            reachability: Reachability::Unknown,
             pending_handler_event: None,
             pending_swarm_events: VecDeque::default(),
         }
@@ -395,7 +473,18 @@ where
     /// Listeners report their new listening addresses as [`SwarmEvent::NewListenAddr`].
     /// Depending on the underlying transport, one listener may have multiple listening addresses.
     pub fn listen_on(&mut self, addr: Multiaddr) -> Result<ListenerId, TransportError<io::Error>> {
-        let opts = ListenOpts::new(addr);
+        self.listen_on_with_opts(ListenOpts::new(addr))
+    }
+
+    /// Starts listening as described by the given [`ListenOpts`].
+    /// Returns an error if the address is not supported.
+    ///
+    /// Like [`Swarm::listen_on`] but allows attaching metadata to the listener via
+    /// [`ListenOpts::with_metadata`], which is echoed back in all events concerning the listener.
+    pub fn listen_on_with_opts(
+        &mut self,
+        opts: ListenOpts,
+    ) -> Result<ListenerId, TransportError<io::Error>> {
         let id = opts.listener_id();
         self.add_listener(opts)?;
         Ok(id)
@@ -442,6 +531,9 @@ where
         let peer_id = dial_opts.get_peer_id();
         let condition = dial_opts.peer_condition();
         let connection_id = dial_opts.connection_id();
+        // Dials of an unknown peer may target the local node on purpose, e.g. to verify the
+        // reachability of an address, see `behaviour::dial_back`.
+        let explicit_peer_id = dial_opts.explicit_peer_id();
 
         let should_dial = match (condition, peer_id) {
             (_, None) => true,
@@ -453,6 +545,19 @@ where
             }
         };
 
+        if explicit_peer_id == Some(self.local_peer_id) {
+            let error = DialError::SelfDial;
+
+            self.behaviour
+                .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                    peer_id,
+                    error: &error,
+                    connection_id,
+                }));
+
+            return Err(error);
+        }
+
         if !should_dial {
             let e = DialError::DialPeerConditionFalse(condition);
 
@@ -505,13 +610,22 @@ where
             }
 
             let mut unique_addresses = HashSet::new();
+            let mut num_self_addresses = 0;
             addresses_from_opts.retain(|addr| {
+                if explicit_peer_id.is_some() && self.confirmed_external_addr.contains_key(addr) {
+                    num_self_addresses += 1;
+                    return false;
+                }
                 !self.listened_addrs.values().flatten().any(|a| a == addr)
                     && unique_addresses.insert(addr.clone())
             });
 
             if addresses_from_opts.is_empty() {
-                let error = DialError::NoAddresses;
+                let error = if num_self_addresses > 0 {
+                    DialError::SelfDial
+                } else {
+                    DialError::NoAddresses
+                };
                 self.behaviour
                     .on_swarm_event(FromSwarm::DialFailure(DialFailure {
                         peer_id,
@@ -521,43 +635,54 @@ where
                 return Err(error);
             };
 
+            // Dial the addresses with the highest score first.
+            addresses_from_opts.sort_by_cached_key(|addr| {
+                cmp::Reverse(self.transport.address_score(addr).unwrap_or_default())
+            });
+
             addresses_from_opts
         };
 
-        let dials = addresses
-            .into_iter()
-            .map(|a| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
-                Ok(address) => {
-                    let (dial, span) = match dial_opts.role_override() {
-                        Endpoint::Dialer => (
-                            self.transport.dial(address.clone()),
-                            tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address),
-                        ),
-                        Endpoint::Listener => (
-                            self.transport.dial_as_listener(address.clone()),
-                            tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial_as_listener", %address),
-                        ),
-                    };
-                    span.follows_from(tracing::Span::current());
-
-                    match dial {
-                        Ok(fut) => fut
-                            .map(|r| (address, r.map_err(TransportError::Other)))
-                            .instrument(span)
-                            .boxed(),
-                        Err(err) => futures::future::ready((address, Err(err))).boxed(),
-                    }
+        // Fall back to relayed addresses only once all direct ones failed, or vice versa.
+        let (addresses, fallback_addresses) = self.pool.relayed_addresses().split(addresses);
+
+        let mut dial = |a: Multiaddr| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
+            Ok(address) => {
+                let dial_timeout = dial_opts
+                    .dial_timeout_override()
+                    .or_else(|| self.transport.dial_timeout(&address));
+                let (dial, span) = match dial_opts.role_override() {
+                    Endpoint::Dialer => (
+                        self.transport.dial(address.clone()),
+                        tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address),
+                    ),
+                    Endpoint::Listener => (
+                        self.transport.dial_as_listener(address.clone()),
+                        tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial_as_listener", %address),
+                    ),
+                };
+                span.follows_from(tracing::Span::current());
+
+                match dial {
+                    Ok(fut) => with_dial_timeout(fut, dial_timeout)
+                        .map(|r| (address, r.map_err(TransportError::Other)))
+                        .instrument(span)
+                        .boxed(),
+                    Err(err) => futures::future::ready((address, Err(err))).boxed(),
                 }
-                Err(address) => futures::future::ready((
-                    address.clone(),
-                    Err(TransportError::MultiaddrNotSupported(address)),
-                ))
-                .boxed(),
-            })
-            .collect();
+            }
+            Err(address) => futures::future::ready((
+                address.clone(),
+                Err(TransportError::MultiaddrNotSupported(address)),
+            ))
+            .boxed(),
+        };
+        let dials = addresses.into_iter().map(&mut dial).collect();
+        let fallback_dials = fallback_addresses.into_iter().map(dial).collect();
 
         self.pool.add_outgoing(
             dials,
+            fallback_dials,
             peer_id,
             dial_opts.role_override(),
             dial_opts.dial_concurrency_override(),
@@ -579,7 +704,17 @@ where
 
     /// List all **confirmed** external address for the local node.
     pub fn external_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
-        self.confirmed_external_addr.iter()
+        self.confirmed_external_addr.keys()
+    }
+
+    /// Returns the subsystem a **confirmed** external address of the local node originates from.
+    pub fn external_address_origin(&self, addr: &Multiaddr) -> Option<ExternalAddrOrigin> {
+        self.confirmed_external_addr.get(addr).copied()
+    }
+
+    /// Returns the reachability of the local node, as last reported by a [`NetworkBehaviour`].
+    pub fn reachability(&self) -> Reachability {
+        self.reachability
     }
 
     fn add_listener(&mut self, opts: ListenOpts) -> Result<(), TransportError<io::Error>> {
@@ -591,6 +726,7 @@ where
                 .on_swarm_event(FromSwarm::ListenerError(behaviour::ListenerError {
                     listener_id,
                     err: &e,
+                    metadata: opts.metadata(),
                 }));
 
             return Err(e);
@@ -599,7 +735,12 @@ where
         self.behaviour
             .on_swarm_event(FromSwarm::NewListener(behaviour::NewListener {
                 listener_id,
+                metadata: opts.metadata(),
             }));
+        if let Some(metadata) = opts.metadata() {
+            self.listener_metadata
+                .insert(listener_id, metadata.to_owned());
+        }
 
         Ok(())
     }
@@ -609,11 +750,15 @@ where
     /// This function should only be called with addresses that are guaranteed to be reachable.
     /// The address is broadcast to all [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrConfirmed`].
     pub fn add_external_address(&mut self, a: Multiaddr) {
+        self.confirm_external_address(a, ExternalAddrOrigin::Manual);
+    }
+
+    fn confirm_external_address(&mut self, a: Multiaddr, origin: ExternalAddrOrigin) {
         self.behaviour
             .on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
                 addr: &a,
             }));
-        self.confirmed_external_addr.insert(a);
+        self.confirmed_external_addr.insert(a, origin);
     }
 
     /// Remove an external address for the local node.
@@ -625,6 +770,22 @@ where
         self.confirmed_external_addr.remove(addr);
     }
 
+    /// Expires a confirmed external address, reporting it as [`SwarmEvent::ExternalAddrExpired`].
+    fn expire_external_address(&mut self, addr: Multiaddr, reason: ExternalAddrExpiryReason) {
+        let origin = self
+            .confirmed_external_addr
+            .get(&addr)
+            .copied()
+            .unwrap_or(ExternalAddrOrigin::Unknown);
+        self.remove_external_address(&addr);
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ExternalAddrExpired {
+                address: addr,
+                origin,
+                reason,
+            });
+    }
+
     /// Add a new external address of a remote peer.
     ///
     /// The address is broadcast to all [`NetworkBehaviour`]s via [`FromSwarm::NewExternalAddrOfPeer`].
@@ -645,7 +806,7 @@ where
     #[allow(clippy::result_unit_err)]
     pub fn disconnect_peer_id(&mut self, peer_id: PeerId) -> Result<(), ()> {
         let was_connected = self.pool.is_connected(peer_id);
-        self.pool.disconnect(peer_id);
+        self.pool.disconnect(peer_id, None);
 
         if was_connected {
             Ok(())
@@ -665,7 +826,25 @@ where
     /// - `false` if the connection was not found or is no longer established.
     pub fn close_connection(&mut self, connection_id: ConnectionId) -> bool {
         if let Some(established) = self.pool.get_established(connection_id) {
-            established.start_close();
+            established.start_close(None);
+            return true;
+        }
+
+        false
+    }
+
+    /// Attempt to gracefully close a connection, signalling the given [`CloseReason`] to the
+    /// remote if supported by the transport, e.g. as the application error code of a QUIC
+    /// `CONNECTION_CLOSE` frame.
+    ///
+    /// See [`Swarm::close_connection`].
+    pub fn close_connection_with_reason(
+        &mut self,
+        connection_id: ConnectionId,
+        reason: CloseReason,
+    ) -> bool {
+        if let Some(established) = self.pool.get_established(connection_id) {
+            established.start_close(Some(reason));
             return true;
         }
 
@@ -682,6 +861,17 @@ where
         self.pool.iter_connected()
     }
 
+    /// Returns the statistics of the task running the given established connection.
+    ///
+    /// Returns `None` if the connection is not established or the statistics are not recorded,
+    /// see [`Config::with_connection_task_stats`].
+    pub fn connection_task_stats(
+        &self,
+        connection_id: ConnectionId,
+    ) -> Option<ConnectionTaskStats> {
+        self.pool.task_stats(connection_id)
+    }
+
     /// Returns a reference to the provided [`NetworkBehaviour`].
     pub fn behaviour(&self) -> &TBehaviour {
         &self.behaviour
@@ -699,6 +889,7 @@ where
                 id,
                 endpoint,
                 connection,
+                negotiated_protocols,
                 concurrent_dial_errors,
                 established_in,
             } => {
@@ -812,6 +1003,7 @@ where
                             endpoint: &endpoint,
                             failed_addresses: &failed_addresses,
                             other_established: other_established_connection_ids.len(),
+                            negotiated_protocols: &negotiated_protocols,
                         },
                     ));
                 self.supported_protocols = supported_protocols;
@@ -821,6 +1013,7 @@ where
                         connection_id: id,
                         num_established,
                         endpoint,
+                        negotiated_protocols,
                         concurrent_dial_errors,
                         established_in,
                     });
@@ -876,6 +1069,29 @@ where
                         error,
                     });
             }
+            PoolEvent::InboundUpgradeQueueTimeout {
+                id,
+                send_back_addr,
+                local_addr,
+            } => {
+                let error = ListenError::UpgradeQueueTimeout;
+
+                tracing::debug!(connection=%id, "Incoming connection timed out in upgrade queue");
+                self.behaviour
+                    .on_swarm_event(FromSwarm::ListenFailure(ListenFailure {
+                        local_addr: &local_addr,
+                        send_back_addr: &send_back_addr,
+                        error: &error,
+                        connection_id: id,
+                    }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::IncomingConnectionError {
+                        connection_id: id,
+                        local_addr,
+                        send_back_addr,
+                        error,
+                    });
+            }
             PoolEvent::ConnectionClosed {
                 id,
                 connected,
@@ -936,6 +1152,22 @@ where
                         new: &new_endpoint,
                     }));
             }
+            PoolEvent::HandlerNotified {
+                id,
+                peer_id,
+                notification,
+            } => {
+                self.behaviour
+                    .on_swarm_event(FromSwarm::HandlerNotified(HandlerNotified {
+                        id: notification,
+                        peer_id,
+                        connection_id: id,
+                    }));
+            }
+            PoolEvent::HandlerNotificationDropped {
+                peer_id,
+                notification,
+            } => self.handler_notification_dropped(peer_id, Some(notification)),
         }
     }
 
@@ -983,7 +1215,7 @@ where
                     }
                 }
 
-                self.pool.add_incoming(
+                let queued = self.pool.add_incoming(
                     upgrade,
                     IncomingInfo {
                         local_addr: &local_addr,
@@ -995,9 +1227,17 @@ where
                 self.pending_swarm_events
                     .push_back(SwarmEvent::IncomingConnection {
                         connection_id,
-                        local_addr,
-                        send_back_addr,
-                    })
+                        local_addr: local_addr.clone(),
+                        send_back_addr: send_back_addr.clone(),
+                    });
+                if queued {
+                    self.pending_swarm_events
+                        .push_back(SwarmEvent::IncomingConnectionQueued {
+                            connection_id,
+                            local_addr,
+                            send_back_addr,
+                        });
+                }
             }
             TransportEvent::NewAddress {
                 listener_id,
@@ -1012,15 +1252,18 @@ where
                 if !addrs.contains(&listen_addr) {
                     addrs.push(listen_addr.clone())
                 }
+                let metadata = self.listener_metadata.get(&listener_id);
                 self.behaviour
                     .on_swarm_event(FromSwarm::NewListenAddr(NewListenAddr {
                         listener_id,
                         addr: &listen_addr,
+                        metadata: metadata.map(String::as_str),
                     }));
                 self.pending_swarm_events
                     .push_back(SwarmEvent::NewListenAddr {
                         listener_id,
                         address: listen_addr,
+                        metadata: metadata.cloned(),
                     })
             }
             TransportEvent::AddressExpired {
@@ -1035,15 +1278,18 @@ where
                 if let Some(addrs) = self.listened_addrs.get_mut(&listener_id) {
                     addrs.retain(|a| a != &listen_addr);
                 }
+                let metadata = self.listener_metadata.get(&listener_id);
                 self.behaviour
                     .on_swarm_event(FromSwarm::ExpiredListenAddr(ExpiredListenAddr {
                         listener_id,
                         addr: &listen_addr,
+                        metadata: metadata.map(String::as_str),
                     }));
                 self.pending_swarm_events
                     .push_back(SwarmEvent::ExpiredListenAddr {
                         listener_id,
                         address: listen_addr,
+                        metadata: metadata.cloned(),
                     })
             }
             TransportEvent::ListenerClosed {
@@ -1056,35 +1302,122 @@ where
                     "Listener closed"
                 );
                 let addrs = self.listened_addrs.remove(&listener_id).unwrap_or_default();
+                let metadata = self.listener_metadata.remove(&listener_id);
                 for addr in addrs.iter() {
                     self.behaviour.on_swarm_event(FromSwarm::ExpiredListenAddr(
-                        ExpiredListenAddr { listener_id, addr },
+                        ExpiredListenAddr {
+                            listener_id,
+                            addr,
+                            metadata: metadata.as_deref(),
+                        },
                     ));
                 }
                 self.behaviour
                     .on_swarm_event(FromSwarm::ListenerClosed(ListenerClosed {
                         listener_id,
                         reason: reason.as_ref().copied(),
+                        metadata: metadata.as_deref(),
                     }));
                 self.pending_swarm_events
                     .push_back(SwarmEvent::ListenerClosed {
                         listener_id,
                         addresses: addrs.to_vec(),
                         reason,
+                        metadata,
                     })
             }
             TransportEvent::ListenerError { listener_id, error } => {
+                let metadata = self.listener_metadata.get(&listener_id);
                 self.behaviour
                     .on_swarm_event(FromSwarm::ListenerError(ListenerError {
                         listener_id,
                         err: &error,
+                        metadata: metadata.map(String::as_str),
                     }));
                 self.pending_swarm_events
-                    .push_back(SwarmEvent::ListenerError { listener_id, error })
+                    .push_back(SwarmEvent::ListenerError {
+                        listener_id,
+                        error,
+                        metadata: metadata.cloned(),
+                    })
             }
         }
     }
 
+    fn new_external_address_candidates(
+        &mut self,
+        candidates: Vec<Multiaddr>,
+        origin: ExternalAddrOrigin,
+    ) {
+        let mut addrs = Vec::new();
+        for addr in candidates {
+            // Apply address translation to the candidate address.
+            // For TCP without port-reuse, the observed address contains an ephemeral port which needs to be replaced by the port of a listen address.
+            let translated: Vec<_> = self
+                .listened_addrs
+                .values()
+                .flatten()
+                .filter_map(|server| self.transport.address_translation(server, &addr))
+                .collect();
+
+            // If address translation yielded nothing, broadcast the original candidate address.
+            if translated.is_empty() {
+                addrs.push(addr);
+            } else {
+                addrs.extend(translated);
+            }
+        }
+
+        // remove duplicates
+        addrs.sort_unstable();
+        addrs.dedup();
+
+        if addrs.is_empty() {
+            return;
+        }
+
+        for addr in &addrs {
+            self.behaviour
+                .on_swarm_event(FromSwarm::NewExternalAddrCandidate(
+                    NewExternalAddrCandidate { addr },
+                ));
+            if origin != ExternalAddrOrigin::Unknown {
+                self.candidate_origins.retain(|(a, _)| a != addr);
+                if self.candidate_origins.len() == MAX_CANDIDATE_ORIGINS {
+                    self.candidate_origins.pop_front();
+                }
+                self.candidate_origins.push_back((addr.clone(), origin));
+            }
+        }
+        self.behaviour
+            .on_swarm_event(FromSwarm::NewExternalAddrCandidates(
+                NewExternalAddrCandidates { addrs: &addrs },
+            ));
+
+        for addr in addrs {
+            self.pending_swarm_events
+                .push_back(SwarmEvent::NewExternalAddrCandidate {
+                    address: addr,
+                    origin,
+                });
+        }
+    }
+
+    fn external_address_confirmed(&mut self, addr: Multiaddr, origin: ExternalAddrOrigin) {
+        // Attribute the address to the subsystem that proposed it, if known.
+        let origin = self
+            .candidate_origins
+            .iter()
+            .find(|(a, _)| a == &addr)
+            .map_or(origin, |(_, o)| *o);
+        self.confirm_external_address(addr.clone(), origin);
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ExternalAddrConfirmed {
+                address: addr,
+                origin,
+            });
+    }
+
     fn handle_behaviour_event(
         &mut self,
         event: ToSwarm<TBehaviour::ToSwarm, THandlerInEvent<TBehaviour>>,
@@ -1115,80 +1448,31 @@ where
                 peer_id,
                 handler,
                 event,
-            } => {
-                assert!(self.pending_handler_event.is_none());
-                let handler = match handler {
-                    NotifyHandler::One(connection) => PendingNotifyHandler::One(connection),
-                    NotifyHandler::Any => {
-                        let ids = self
-                            .pool
-                            .iter_established_connections_of_peer(&peer_id)
-                            .collect();
-                        PendingNotifyHandler::Any(ids)
-                    }
-                };
-
-                self.pending_handler_event = Some((peer_id, handler, event));
-            }
-            ToSwarm::NewExternalAddrCandidate(addr) => {
-                // Apply address translation to the candidate address.
-                // For TCP without port-reuse, the observed address contains an ephemeral port which needs to be replaced by the port of a listen address.
-                let translated_addresses = {
-                    let mut addrs: Vec<_> = self
-                        .listened_addrs
-                        .values()
-                        .flatten()
-                        .filter_map(|server| self.transport.address_translation(server, &addr))
-                        .collect();
-
-                    // remove duplicates
-                    addrs.sort_unstable();
-                    addrs.dedup();
-                    addrs
-                };
-
-                // If address translation yielded nothing, broadcast the original candidate address.
-                if translated_addresses.is_empty() {
-                    self.behaviour
-                        .on_swarm_event(FromSwarm::NewExternalAddrCandidate(
-                            NewExternalAddrCandidate { addr: &addr },
-                        ));
-                    self.pending_swarm_events
-                        .push_back(SwarmEvent::NewExternalAddrCandidate { address: addr });
-                } else {
-                    for addr in translated_addresses {
-                        self.behaviour
-                            .on_swarm_event(FromSwarm::NewExternalAddrCandidate(
-                                NewExternalAddrCandidate { addr: &addr },
-                            ));
-                        self.pending_swarm_events
-                            .push_back(SwarmEvent::NewExternalAddrCandidate { address: addr });
-                    }
-                }
+            } => self.notify_handler(peer_id, handler, event, None),
+            ToSwarm::NotifyHandlerWithAck {
+                peer_id,
+                handler,
+                event,
+                id,
+            } => self.notify_handler(peer_id, handler, event, Some(id)),
+            ToSwarm::NewExternalAddrCandidate { addresses, origin } => {
+                self.new_external_address_candidates(addresses, origin)
             }
-            ToSwarm::ExternalAddrConfirmed(addr) => {
-                self.add_external_address(addr.clone());
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ExternalAddrConfirmed { address: addr });
+            ToSwarm::ExternalAddrConfirmed { address, origin } => {
+                self.external_address_confirmed(address, origin)
             }
-            ToSwarm::ExternalAddrExpired(addr) => {
-                self.remove_external_address(&addr);
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ExternalAddrExpired { address: addr });
+            ToSwarm::ExternalAddrExpired { address, reason } => {
+                self.expire_external_address(address, reason)
             }
             ToSwarm::CloseConnection {
                 peer_id,
                 connection,
-            } => match connection {
-                CloseConnection::One(connection_id) => {
-                    if let Some(conn) = self.pool.get_established(connection_id) {
-                        conn.start_close();
-                    }
-                }
-                CloseConnection::All => {
-                    self.pool.disconnect(peer_id);
-                }
-            },
+            } => self.close_connections(peer_id, connection, None),
+            ToSwarm::CloseConnectionWithReason {
+                peer_id,
+                connection,
+                reason,
+            } => self.close_connections(peer_id, connection, Some(reason)),
             ToSwarm::NewExternalAddrOfPeer { peer_id, address } => {
                 self.behaviour
                     .on_swarm_event(FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer {
@@ -1198,6 +1482,74 @@ where
                 self.pending_swarm_events
                     .push_back(SwarmEvent::NewExternalAddrOfPeer { peer_id, address });
             }
+            ToSwarm::ReachabilityChanged(new) => {
+                if new == self.reachability {
+                    return;
+                }
+                let old = std::mem::replace(&mut self.reachability, new);
+
+                self.behaviour
+                    .on_swarm_event(FromSwarm::ReachabilityChanged(ReachabilityChanged {
+                        old,
+                        new,
+                    }));
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ReachabilityChanged { old, new });
+            }
+            ToSwarm::ConnectionRtt { connection_id, rtt } => {
+                self.pool.set_rtt(connection_id, rtt);
+            }
+        }
+    }
+
+    fn notify_handler(
+        &mut self,
+        peer_id: PeerId,
+        handler: NotifyHandler,
+        event: THandlerInEvent<TBehaviour>,
+        ack: Option<NotificationId>,
+    ) {
+        assert!(self.pending_handler_event.is_none());
+        let handler = match handler {
+            NotifyHandler::One(connection) => PendingNotifyHandler::One(connection),
+            NotifyHandler::Any => PendingNotifyHandler::Any(
+                self.pool
+                    .select_established_connections_of_peer(&peer_id, None),
+            ),
+            NotifyHandler::Select(selection) => PendingNotifyHandler::Any(
+                self.pool
+                    .select_established_connections_of_peer(&peer_id, Some(&selection)),
+            ),
+        };
+
+        self.pending_handler_event = Some((peer_id, handler, event, ack));
+    }
+
+    /// Reports an event sent via [`ToSwarm::NotifyHandlerWithAck`] as dropped.
+    fn handler_notification_dropped(&mut self, peer_id: PeerId, ack: Option<NotificationId>) {
+        if let Some(id) = ack {
+            self.behaviour
+                .on_swarm_event(FromSwarm::HandlerNotificationDropped(
+                    HandlerNotificationDropped { id, peer_id },
+                ));
+        }
+    }
+
+    fn close_connections(
+        &mut self,
+        peer_id: PeerId,
+        connection: CloseConnection,
+        reason: Option<CloseReason>,
+    ) {
+        match connection {
+            CloseConnection::One(connection_id) => {
+                if let Some(conn) = self.pool.get_established(connection_id) {
+                    conn.start_close(reason);
+                }
+            }
+            CloseConnection::All => {
+                self.pool.disconnect(peer_id, reason);
+            }
         }
     }
 
@@ -1230,24 +1582,36 @@ where
             match this.pending_handler_event.take() {
                 // Try to deliver the pending event emitted by the [`NetworkBehaviour`] in the previous
                 // iteration to the connection handler(s).
-                Some((peer_id, handler, event)) => match handler {
+                Some((peer_id, handler, event, ack)) => match handler {
                     PendingNotifyHandler::One(conn_id) => {
                         match this.pool.get_established(conn_id) {
-                            Some(conn) => match notify_one(conn, event, cx) {
-                                None => continue,
-                                Some(event) => {
-                                    this.pending_handler_event = Some((peer_id, handler, event));
+                            Some(conn) => match notify_one(conn, event, ack, cx) {
+                                Notify::Sent => continue,
+                                Notify::Dropped => {
+                                    this.handler_notification_dropped(peer_id, ack);
+                                    continue;
+                                }
+                                Notify::Pending(event) => {
+                                    this.pending_handler_event =
+                                        Some((peer_id, handler, event, ack));
                                 }
                             },
-                            None => continue,
+                            None => {
+                                this.handler_notification_dropped(peer_id, ack);
+                                continue;
+                            }
                         }
                     }
                     PendingNotifyHandler::Any(ids) => {
-                        match notify_any::<_, TBehaviour>(ids, &mut this.pool, event, cx) {
-                            None => continue,
-                            Some((event, ids)) => {
+                        match notify_any::<_, TBehaviour>(ids, &mut this.pool, event, ack, cx) {
+                            Notify::Sent => continue,
+                            Notify::Dropped => {
+                                this.handler_notification_dropped(peer_id, ack);
+                                continue;
+                            }
+                            Notify::Pending((event, ids)) => {
                                 let handler = PendingNotifyHandler::Any(ids);
-                                this.pending_handler_event = Some((peer_id, handler, event));
+                                this.pending_handler_event = Some((peer_id, handler, event, ack));
                             }
                         }
                     }
@@ -1286,6 +1650,34 @@ where
     }
 }
 
+/// Fails the given dial with [`io::ErrorKind::TimedOut`] if it doesn't complete within `timeout`.
+///
+/// The timer only starts once the dial is polled for the first time, i.e. when it is scheduled
+/// according to the dial concurrency factor.
+fn with_dial_timeout<F, T>(
+    dial: F,
+    timeout: Option<Duration>,
+) -> future::BoxFuture<'static, io::Result<T>>
+where
+    F: Future<Output = io::Result<T>> + Send + Unpin + 'static,
+    T: Send + 'static,
+{
+    let Some(timeout) = timeout else {
+        return dial.boxed();
+    };
+
+    async move {
+        match future::select(dial, futures_timer::Delay::new(timeout)).await {
+            future::Either::Left((result, _)) => result,
+            future::Either::Right(((), _)) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("dial timed out after {timeout:?}"),
+            )),
+        }
+    }
+    .boxed()
+}
+
 /// Connection to notify of a pending event.
 ///
 /// The connection IDs out of which to notify one of an event are captured at
@@ -1297,46 +1689,52 @@ enum PendingNotifyHandler {
     Any(SmallVec<[ConnectionId; 10]>),
 }
 
+/// The outcome of [`notify_one`] and [`notify_any`].
+enum Notify<T> {
+    /// The event has been sent to a connection.
+    Sent,
+    /// The event has been dropped, as the connection(s) are closing.
+    Dropped,
+    /// The event is still to be sent, the current task is scheduled to be woken up.
+    Pending(T),
+}
+
 /// Notify a single connection of an event.
 ///
-/// Returns `Some` with the given event if the connection is not currently
-/// ready to receive another event, in which case the current task is
-/// scheduled to be woken up.
-///
-/// Returns `None` if the connection is closing or the event has been
-/// successfully sent, in either case the event is consumed.
+/// Returns [`Notify::Pending`] with the given event if the connection is not
+/// currently ready to receive another event.
 fn notify_one<THandlerInEvent>(
     conn: &mut EstablishedConnection<THandlerInEvent>,
     event: THandlerInEvent,
+    ack: Option<NotificationId>,
     cx: &mut Context<'_>,
-) -> Option<THandlerInEvent> {
+) -> Notify<THandlerInEvent> {
     match conn.poll_ready_notify_handler(cx) {
-        Poll::Pending => Some(event),
-        Poll::Ready(Err(())) => None, // connection is closing
-        Poll::Ready(Ok(())) => {
+        Poll::Pending => Notify::Pending(event),
+        Poll::Ready(Err(())) => Notify::Dropped, // connection is closing
+        Poll::Ready(Ok(())) => match conn.notify_handler(event, ack) {
+            Ok(()) => Notify::Sent,
             // Can now only fail if connection is closing.
-            let _ = conn.notify_handler(event);
-            None
-        }
+            Err(_) => Notify::Dropped,
+        },
     }
 }
 
 /// Notify any one of a given list of connections of a peer of an event.
 ///
-/// Returns `Some` with the given event and a new list of connections if
+/// Returns [`Notify::Pending`] with the given event and a new list of connections if
 /// none of the given connections was able to receive the event but at
-/// least one of them is not closing, in which case the current task
-/// is scheduled to be woken up. The returned connections are those which
+/// least one of them is not closing. The returned connections are those which
 /// may still become ready to receive another event.
 ///
-/// Returns `None` if either all connections are closing or the event
-/// was successfully sent to a handler, in either case the event is consumed.
+/// Returns [`Notify::Dropped`] if all connections are closing.
 fn notify_any<THandler, TBehaviour>(
     ids: SmallVec<[ConnectionId; 10]>,
     pool: &mut Pool<THandler>,
     event: THandlerInEvent<TBehaviour>,
+    ack: Option<NotificationId>,
     cx: &mut Context<'_>,
-) -> Option<(THandlerInEvent<TBehaviour>, SmallVec<[ConnectionId; 10]>)>
+) -> Notify<(THandlerInEvent<TBehaviour>, SmallVec<[ConnectionId; 10]>)>
 where
     TBehaviour: NetworkBehaviour,
     THandler: ConnectionHandler<
@@ -1353,7 +1751,7 @@ where
                 Poll::Ready(Err(())) => {} // connection is closing
                 Poll::Ready(Ok(())) => {
                     let e = event.take().expect("by (1),(2)");
-                    if let Err(e) = conn.notify_handler(e) {
+                    if let Err(e) = conn.notify_handler(e, ack) {
                         event = Some(e) // (2)
                     } else {
                         break;
@@ -1363,13 +1761,11 @@ where
         }
     }
 
-    event.and_then(|e| {
-        if !pending.is_empty() {
-            Some((e, pending))
-        } else {
-            None
-        }
-    })
+    match event {
+        None => Notify::Sent,
+        Some(e) if !pending.is_empty() => Notify::Pending((e, pending)),
+        Some(_) => Notify::Dropped,
+    }
 }
 
 /// Stream of events returned by [`Swarm`].
@@ -1481,6 +1877,15 @@ impl Config {
         self
     }
 
+    /// When to dial the relayed addresses of a peer relative to its direct addresses.
+    ///
+    /// Defaults to [`RelayedAddresses::DialLast`], only dialing relayed addresses once the dials
+    /// of all direct addresses failed.
+    pub fn with_relayed_addresses(mut self, relayed_addresses: RelayedAddresses) -> Self {
+        self.pool_config.relayed_addresses = relayed_addresses;
+        self
+    }
+
     /// Configures an override for the substream upgrade protocol to use.
     ///
     /// The subtream upgrade protocol is the multistream-select protocol
@@ -1499,6 +1904,19 @@ impl Config {
         self
     }
 
+    /// Reports every protocol negotiation on the substreams of the connections of the [`Swarm`]
+    /// to the given observer, e.g. to collect protocol usage statistics.
+    ///
+    /// The negotiations of the security and multiplexing protocols of the connections are
+    /// observed via [`libp2p_core::transport::upgrade::Builder::with_negotiation_observer`].
+    pub fn with_negotiation_observer(
+        mut self,
+        observer: Arc<dyn libp2p_core::upgrade::NegotiationObserver>,
+    ) -> Self {
+        self.pool_config = self.pool_config.with_negotiation_observer(observer);
+        self
+    }
+
     /// The maximum number of inbound streams concurrently negotiating on a
     /// connection. New inbound streams exceeding the limit are dropped and thus
     /// reset.
@@ -1520,6 +1938,66 @@ impl Config {
         self.pool_config.idle_connection_timeout = timeout;
         self
     }
+
+    /// The [`Clock`] driving the idle and stream upgrade timeouts of connections.
+    ///
+    /// Defaults to [`SystemClock`]. Use a [`VirtualClock`] to control time in tests. Note that
+    /// behaviours take their clock from their own configuration.
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.pool_config.clock = Arc::new(clock);
+        self
+    }
+
+    /// The policy for selecting which connection to notify for [`NotifyHandler::Any`] when
+    /// multiple connections to a peer exist.
+    ///
+    /// Defaults to [`ConnectionSelection::Any`].
+    pub fn with_connection_selection(mut self, selection: ConnectionSelection) -> Self {
+        self.pool_config.connection_selection = selection;
+        self
+    }
+
+    /// Limits the number of inbound connections concurrently upgrading, i.e. performing the
+    /// security and multiplexer handshakes, to protect against bursts of inbound connections
+    /// spiking the CPU.
+    ///
+    /// Further inbound connections are queued, see [`SwarmEvent::IncomingConnectionQueued`], and
+    /// start upgrading in order of arrival once the upgrade of another inbound connection
+    /// completes. Connections waiting longer than `queue_timeout` are dropped with
+    /// [`ListenError::UpgradeQueueTimeout`].
+    ///
+    /// Queued connections count as pending connections, e.g. towards the limits of
+    /// `libp2p-connection-limits`, which are thus independent of this limit. Unlimited by default.
+    pub fn with_max_concurrent_inbound_upgrades(
+        mut self,
+        limit: NonZeroUsize,
+        queue_timeout: Duration,
+    ) -> Self {
+        self.pool_config.max_concurrent_inbound_upgrades = Some(limit);
+        self.pool_config.inbound_upgrade_queue_timeout = queue_timeout;
+        self
+    }
+
+    /// Runs the tasks of established connections on the executors selected by the given
+    /// [`ConnectionExecutor`], falling back to the executor of the [`Swarm`].
+    ///
+    /// Pending connections always run on the executor of the [`Swarm`].
+    pub fn with_connection_executor(
+        mut self,
+        executor: impl ConnectionExecutor + Send + 'static,
+    ) -> Self {
+        self.pool_config.connection_executor = Some(Box::new(executor));
+        self
+    }
+
+    /// Records the number of polls and the time spent polling the task of each established
+    /// connection, reported via [`Swarm::connection_task_stats`].
+    ///
+    /// Disabled by default, as it measures the time of every poll of a connection.
+    pub fn with_connection_task_stats(mut self, enabled: bool) -> Self {
+        self.pool_config.record_task_stats = enabled;
+        self
+    }
 }
 
 /// Possible errors when trying to establish or upgrade an outbound connection.
@@ -1529,6 +2007,9 @@ pub enum DialError {
     LocalPeerId {
         endpoint: ConnectedPoint,
     },
+    /// The dial targeted the local peer, i.e. the local [`PeerId`] or only confirmed external
+    /// addresses of the local node, and was thus not attempted.
+    SelfDial,
     /// No addresses have been provided by [`NetworkBehaviour::handle_pending_outbound_connection`] and [`DialOpts`].
     NoAddresses,
     /// The provided [`dial_opts::PeerCondition`] evaluated to false and thus
@@ -1565,6 +2046,7 @@ impl fmt::Display for DialError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             DialError::NoAddresses => write!(f, "Dial error: no addresses for peer."),
+            DialError::SelfDial => write!(f, "Dial error: tried to dial the local node."),
             DialError::LocalPeerId { endpoint } => write!(
                 f,
                 "Dial error: tried to dial local peer id at {endpoint:?}."
@@ -1615,6 +2097,7 @@ impl error::Error for DialError {
         match self {
             DialError::LocalPeerId { .. } => None,
             DialError::NoAddresses => None,
+            DialError::SelfDial => None,
             DialError::DialPeerConditionFalse(_) => None,
             DialError::Aborted => None,
             DialError::WrongPeerId { .. } => None,
@@ -1643,6 +2126,9 @@ pub enum ListenError {
     },
     /// An error occurred while negotiating the transport protocol(s) on a connection.
     Transport(TransportError<io::Error>),
+    /// The connection waited too long for the upgrade of other inbound connections to complete,
+    /// see [`Config::with_max_concurrent_inbound_upgrades`].
+    UpgradeQueueTimeout,
 }
 
 impl From<PendingInboundConnectionError> for ListenError {
@@ -1680,6 +2166,10 @@ impl fmt::Display for ListenError {
             ListenError::LocalPeerId { endpoint } => {
                 write!(f, "Listen error: Local peer ID at {endpoint:?}.")
             }
+            ListenError::UpgradeQueueTimeout => write!(
+                f,
+                "Listen error: Timed out waiting for other inbound connections to upgrade."
+            ),
         }
     }
 }
@@ -1692,6 +2182,7 @@ impl error::Error for ListenError {
             ListenError::Aborted => None,
             ListenError::Denied { cause } => Some(cause),
             ListenError::LocalPeerId { .. } => None,
+            ListenError::UpgradeQueueTimeout => None,
         }
     }
 }
@@ -1771,6 +2262,8 @@ impl NetworkInfo {
 mod tests {
     use super::*;
     use crate::test::{CallTraceBehaviour, MockBehaviour};
+    use futures::channel::oneshot;
+    use futures::future::BoxFuture;
     use libp2p_core::multiaddr::multiaddr;
     use libp2p_core::transport::memory::MemoryTransportError;
     use libp2p_core::{multiaddr, upgrade};
@@ -1778,6 +2271,7 @@ mod tests {
     use libp2p_plaintext as plaintext;
     use libp2p_yamux as yamux;
     use quickcheck::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     // Test execution state.
     // Connection => Disconnecting => Connecting.
@@ -2051,6 +2545,125 @@ mod tests {
         .await
     }
 
+    #[tokio::test]
+    async fn connection_selection_orders_connections_of_peer() {
+        let mut swarm1 = new_test_swarm(Config::with_tokio_executor());
+        let mut swarm2 = new_test_swarm(Config::with_tokio_executor());
+
+        let addr2: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        swarm2.listen_on(addr2.clone()).unwrap();
+        let swarm2_id = *swarm2.local_peer_id();
+
+        let num_connections = 3;
+        for _ in 0..num_connections {
+            swarm1.dial(addr2.clone()).unwrap();
+        }
+
+        future::poll_fn(|cx| loop {
+            let poll1 = Swarm::poll_next_event(Pin::new(&mut swarm1), cx);
+            let poll2 = Swarm::poll_next_event(Pin::new(&mut swarm2), cx);
+            if swarms_connected(&swarm1, &swarm2, num_connections) {
+                return Poll::Ready(());
+            }
+            if poll1.is_pending() && poll2.is_pending() {
+                return Poll::Pending;
+            }
+        })
+        .await;
+
+        let conns = swarm1
+            .behaviour
+            .on_connection_established
+            .iter()
+            .map(|(_, id, ..)| *id)
+            .collect::<Vec<_>>();
+        swarm1.pool.set_rtt(conns[0], Duration::from_millis(30));
+        swarm1.pool.set_rtt(conns[2], Duration::from_millis(10));
+
+        assert_eq!(
+            swarm1
+                .pool
+                .select_established_connections_of_peer(
+                    &swarm2_id,
+                    Some(&ConnectionSelection::LowestRtt)
+                )
+                .into_vec(),
+            vec![conns[2], conns[0], conns[1]]
+        );
+
+        swarm1
+            .pool
+            .set_remote_address(conns[1], "/ip4/127.0.0.1/tcp/4001".parse().unwrap());
+        let tcp_first = swarm1.pool.select_established_connections_of_peer(
+            &swarm2_id,
+            Some(&ConnectionSelection::Transport("tcp")),
+        );
+        assert_eq!(tcp_first.len(), num_connections);
+        assert_eq!(tcp_first[0], conns[1]);
+    }
+
+    #[tokio::test]
+    async fn connection_executor_runs_selected_connections() {
+        struct DialerExecutor(Box<dyn Executor + Send>);
+
+        impl ConnectionExecutor for DialerExecutor {
+            fn select(&self, _: &PeerId, endpoint: &ConnectedPoint) -> Option<&dyn Executor> {
+                endpoint.is_dialer().then_some(&*self.0 as &dyn Executor)
+            }
+        }
+
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let executor = {
+            let spawned = spawned.clone();
+            move |task| {
+                spawned.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(task);
+            }
+        };
+        let mut swarm1 = new_test_swarm(
+            Config::with_tokio_executor()
+                .with_connection_executor(DialerExecutor(Box::new(executor)))
+                .with_connection_task_stats(true),
+        );
+        let mut swarm2 = new_test_swarm(Config::with_tokio_executor());
+
+        let addr2: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        swarm2.listen_on(addr2.clone()).unwrap();
+        swarm1.dial(addr2).unwrap();
+
+        future::poll_fn(|cx| loop {
+            let poll1 = Swarm::poll_next_event(Pin::new(&mut swarm1), cx);
+            let poll2 = Swarm::poll_next_event(Pin::new(&mut swarm2), cx);
+            if swarms_connected(&swarm1, &swarm2, 1) {
+                return Poll::Ready(());
+            }
+            if poll1.is_pending() && poll2.is_pending() {
+                return Poll::Pending;
+            }
+        })
+        .await;
+
+        // Only the established outbound connection of `swarm1` ran on the selected executor.
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+
+        let (_, connection_id, ..) = swarm1.behaviour.on_connection_established[0];
+        while swarm1
+            .connection_task_stats(connection_id)
+            .unwrap()
+            .num_polls()
+            == 0
+        {
+            tokio::task::yield_now().await;
+        }
+        assert!(swarm1
+            .connection_task_stats(ConnectionId::new_unchecked(usize::MAX))
+            .is_none());
+
+        // `swarm2` does not record the statistics of its connections.
+        let (_, connection_id, ..) = swarm2.behaviour.on_connection_established[0];
+        assert!(swarm2.connection_task_stats(connection_id).is_none());
+    }
+
     #[test]
     fn concurrent_dialing() {
         #[derive(Clone, Debug)]
@@ -2175,6 +2788,47 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn connection_established_reports_negotiated_protocols() {
+        let mut swarm1 = new_test_swarm(Config::with_tokio_executor());
+        let mut swarm2 = new_test_swarm(Config::with_tokio_executor());
+
+        swarm1.listen_on("/memory/0".parse().unwrap()).unwrap();
+
+        let address = future::poll_fn(|cx| match swarm1.poll_next_unpin(cx) {
+            Poll::Ready(Some(SwarmEvent::NewListenAddr { address, .. })) => Poll::Ready(address),
+            Poll::Pending => Poll::Pending,
+            _ => panic!("Was expecting the listen address to be reported"),
+        })
+        .await;
+
+        swarm2.dial(address).unwrap();
+
+        let negotiated_protocols = future::poll_fn(|cx| {
+            if let Poll::Ready(Some(SwarmEvent::IncomingConnection { .. })) =
+                swarm1.poll_next_unpin(cx)
+            {}
+
+            match swarm2.poll_next_unpin(cx) {
+                Poll::Ready(Some(SwarmEvent::ConnectionEstablished {
+                    negotiated_protocols,
+                    ..
+                })) => Poll::Ready(negotiated_protocols),
+                Poll::Ready(x) => panic!("unexpected {x:?}"),
+                Poll::Pending => Poll::Pending,
+            }
+        })
+        .await;
+
+        assert_eq!(
+            negotiated_protocols,
+            NegotiatedProtocols {
+                security: Some("/plaintext/2.0.0".to_owned()),
+                muxer: Some("/yamux/1.0.0".to_owned()),
+            }
+        );
+    }
+
     #[tokio::test]
     async fn dial_self() {
         // Check whether dialing ourselves correctly fails.
@@ -2242,6 +2896,35 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test]
+    async fn dial_self_fails_early() {
+        let mut swarm = new_test_swarm(Config::with_tokio_executor());
+        let local_peer_id = *swarm.local_peer_id();
+        let external_address: Multiaddr = "/memory/1234".parse().unwrap();
+        swarm.add_external_address(external_address.clone());
+
+        let error = swarm
+            .dial(
+                DialOpts::peer_id(local_peer_id)
+                    .addresses(vec!["/memory/4321".parse().unwrap()])
+                    .build(),
+            )
+            .unwrap_err();
+        assert!(matches!(error, DialError::SelfDial));
+
+        let error = swarm
+            .dial(
+                DialOpts::peer_id(PeerId::random())
+                    .addresses(vec![external_address.clone()])
+                    .build(),
+            )
+            .unwrap_err();
+        assert!(matches!(error, DialError::SelfDial));
+
+        // Dials of an unknown peer may target our own addresses on purpose.
+        swarm.dial(external_address).unwrap();
+    }
+
     #[tokio::test]
     async fn dial_self_by_id() {
         // Trying to dial self by passing the same `PeerId` shouldn't even be possible in the first
@@ -2336,6 +3019,448 @@ mod tests {
         }
     }
 
+    /// A transport whose dials never complete.
+    struct PendingTransport {
+        dial_timeout: Option<Duration>,
+    }
+
+    impl Transport for PendingTransport {
+        type Output = (PeerId, StreamMuxerBox);
+        type Error = io::Error;
+        type ListenerUpgrade = future::Pending<io::Result<Self::Output>>;
+        type Dial = future::Pending<io::Result<Self::Output>>;
+
+        fn listen_on(
+            &mut self,
+            _: ListenerId,
+            addr: Multiaddr,
+        ) -> Result<(), TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn remove_listener(&mut self, _: ListenerId) -> bool {
+            false
+        }
+
+        fn dial(&mut self, _: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Ok(future::pending())
+        }
+
+        fn dial_as_listener(
+            &mut self,
+            _: Multiaddr,
+        ) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Ok(future::pending())
+        }
+
+        fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+            None
+        }
+
+        fn dial_timeout(&self, _: &Multiaddr) -> Option<Duration> {
+            self.dial_timeout
+        }
+
+        fn poll(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+            Poll::Pending
+        }
+    }
+
+    async fn expect_dial_timeout(transport_timeout: Option<Duration>, opts: DialOpts) {
+        let mut swarm = Swarm::new(
+            PendingTransport {
+                dial_timeout: transport_timeout,
+            }
+            .boxed(),
+            dummy::Behaviour,
+            PeerId::random(),
+            Config::with_tokio_executor(),
+        );
+        swarm.dial(opts).unwrap();
+
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Transport(errors),
+                ..
+            } => {
+                let (_, TransportError::Other(e)) = &errors[0] else {
+                    panic!("Unexpected transport error {errors:?}");
+                };
+                assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+            }
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+    }
+
+    #[tokio::test]
+    async fn dial_times_out_after_transport_default() {
+        expect_dial_timeout(
+            Some(Duration::from_millis(10)),
+            DialOpts::unknown_peer_id()
+                .address(multiaddr![Memory(1u64)])
+                .build(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn dial_timeout_can_be_overridden() {
+        expect_dial_timeout(
+            Some(Duration::from_secs(3600)),
+            DialOpts::unknown_peer_id()
+                .address(multiaddr![Memory(1u64)])
+                .override_dial_timeout(Duration::from_millis(10))
+                .build(),
+        )
+        .await;
+    }
+
+    /// A transport reporting the given incoming connections, whose upgrades fail once the
+    /// respective sender is dropped.
+    struct IncomingTransport {
+        incoming: VecDeque<oneshot::Receiver<()>>,
+    }
+
+    impl Transport for IncomingTransport {
+        type Output = (PeerId, StreamMuxerBox);
+        type Error = io::Error;
+        type ListenerUpgrade = BoxFuture<'static, io::Result<Self::Output>>;
+        type Dial = future::Pending<io::Result<Self::Output>>;
+
+        fn listen_on(
+            &mut self,
+            _: ListenerId,
+            addr: Multiaddr,
+        ) -> Result<(), TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn remove_listener(&mut self, _: ListenerId) -> bool {
+            false
+        }
+
+        fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn dial_as_listener(
+            &mut self,
+            addr: Multiaddr,
+        ) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn address_translation(&self, _: &Multiaddr, _: &Multiaddr) -> Option<Multiaddr> {
+            None
+        }
+
+        fn poll(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+            let Some(upgrade) = self.incoming.pop_front() else {
+                return Poll::Pending;
+            };
+
+            Poll::Ready(TransportEvent::Incoming {
+                listener_id: ListenerId::next(),
+                upgrade: upgrade
+                    .map(|_| Err(io::Error::new(io::ErrorKind::Other, "upgrade failed")))
+                    .boxed(),
+                local_addr: multiaddr![Memory(1u64)],
+                send_back_addr: multiaddr![Memory(2u64)],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn inbound_upgrades_beyond_limit_are_queued() {
+        let clock = VirtualClock::new();
+        let (upgrades, incoming): (Vec<_>, _) = (0..3).map(|_| oneshot::channel()).unzip();
+        let mut upgrades = upgrades.into_iter().map(Some).collect::<Vec<_>>();
+        let mut swarm = Swarm::new(
+            IncomingTransport { incoming }.boxed(),
+            dummy::Behaviour,
+            PeerId::random(),
+            Config::with_tokio_executor()
+                .with_clock(clock.clone())
+                .with_max_concurrent_inbound_upgrades(
+                    NonZeroUsize::new(1).unwrap(),
+                    Duration::from_secs(10),
+                ),
+        );
+
+        let mut ids = Vec::new();
+        let mut queued = Vec::new();
+        while queued.len() < 2 {
+            match swarm.next().await.unwrap() {
+                SwarmEvent::IncomingConnection { connection_id, .. } => ids.push(connection_id),
+                SwarmEvent::IncomingConnectionQueued { connection_id, .. } => {
+                    queued.push(connection_id)
+                }
+                e => panic!("Unexpected swarm event {e:?}."),
+            }
+        }
+        assert_eq!(queued, ids[1..]);
+
+        // Completing the first upgrade starts the upgrade of the second connection.
+        upgrades[0] = None;
+        match swarm.next().await.unwrap() {
+            SwarmEvent::IncomingConnectionError {
+                connection_id,
+                error: ListenError::Transport(_),
+                ..
+            } => assert_eq!(connection_id, ids[0]),
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+
+        // Only the third connection is still queued and thus times out.
+        clock.advance(Duration::from_secs(10));
+        match swarm.next().await.unwrap() {
+            SwarmEvent::IncomingConnectionError {
+                connection_id,
+                error: ListenError::UpgradeQueueTimeout,
+                ..
+            } => assert_eq!(connection_id, ids[2]),
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+
+        upgrades[1] = None;
+        match swarm.next().await.unwrap() {
+            SwarmEvent::IncomingConnectionError {
+                connection_id,
+                error: ListenError::Transport(_),
+                ..
+            } => assert_eq!(connection_id, ids[1]),
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+        assert_eq!(swarm.network_info().connection_counters().num_pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn dials_addresses_with_higher_score_first() {
+        let pending = || PendingTransport {
+            dial_timeout: Some(Duration::from_millis(10)),
+        };
+        let transport = pending()
+            .or_transport(pending())
+            .with_address_score(|addr| (addr == &multiaddr![Memory(2u64)]).then_some(1))
+            .map(|output, _| output.into_inner())
+            .boxed();
+        let mut swarm = Swarm::new(
+            transport,
+            dummy::Behaviour,
+            PeerId::random(),
+            Config::with_tokio_executor(),
+        );
+        let peer = PeerId::random();
+        swarm
+            .dial(
+                DialOpts::peer_id(peer)
+                    .addresses(vec![multiaddr![Memory(1u64)], multiaddr![Memory(2u64)]])
+                    .override_dial_concurrency_factor(NonZeroU8::new(1).unwrap())
+                    .build(),
+            )
+            .unwrap();
+
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Transport(errors),
+                ..
+            } => {
+                let addresses = errors.into_iter().map(|(a, _)| a).collect::<Vec<_>>();
+                assert_eq!(
+                    addresses,
+                    [
+                        multiaddr![Memory(2u64), P2p(peer)],
+                        multiaddr![Memory(1u64), P2p(peer)]
+                    ]
+                );
+            }
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+    }
+
+    /// Dials a peer via two relayed and two direct addresses, all timing out, returning the
+    /// addresses in the order their dials failed.
+    async fn dialed_addresses(config: Config) -> Vec<Multiaddr> {
+        let transport = PendingTransport {
+            dial_timeout: Some(Duration::from_millis(10)),
+        }
+        .boxed();
+        let mut swarm = Swarm::new(transport, dummy::Behaviour, PeerId::random(), config);
+        let relay = PeerId::random();
+        swarm
+            .dial(
+                DialOpts::peer_id(PeerId::random())
+                    .addresses(vec![
+                        multiaddr![Memory(1u64), P2p(relay), P2pCircuit],
+                        multiaddr![Memory(2u64)],
+                        multiaddr![Memory(3u64), P2p(relay), P2pCircuit],
+                        multiaddr![Memory(4u64)],
+                    ])
+                    .build(),
+            )
+            .unwrap();
+
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Transport(errors),
+                ..
+            } => errors
+                .into_iter()
+                .map(|(mut address, _)| {
+                    address.pop();
+                    address
+                })
+                .collect(),
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+    }
+
+    #[tokio::test]
+    async fn relayed_addresses_are_dialed_last() {
+        let addresses = dialed_addresses(Config::with_tokio_executor()).await;
+
+        assert!(addresses[..2].iter().all(|a| !is_relayed(a)));
+        assert!(addresses[2..].iter().all(is_relayed));
+    }
+
+    #[tokio::test]
+    async fn relayed_addresses_are_dialed_first() {
+        let addresses = dialed_addresses(
+            Config::with_tokio_executor().with_relayed_addresses(RelayedAddresses::DialFirst),
+        )
+        .await;
+
+        assert!(addresses[..2].iter().all(is_relayed));
+        assert!(addresses[2..].iter().all(|a| !is_relayed(a)));
+    }
+
+    #[tokio::test]
+    async fn relayed_addresses_are_dialed_unordered() {
+        let addresses = dialed_addresses(
+            Config::with_tokio_executor()
+                .with_relayed_addresses(RelayedAddresses::Unordered)
+                .with_dial_concurrency_factor(NonZeroU8::new(1).unwrap()),
+        )
+        .await;
+
+        assert!(is_relayed(&addresses[0]));
+        assert!(!is_relayed(&addresses[1]));
+        assert!(is_relayed(&addresses[2]));
+        assert!(!is_relayed(&addresses[3]));
+    }
+
+    fn is_relayed(addr: &Multiaddr) -> bool {
+        addr.iter()
+            .any(|p| p == libp2p_core::multiaddr::Protocol::P2pCircuit)
+    }
+
+    #[tokio::test]
+    async fn external_address_candidates_are_reported_in_batches() {
+        let mut swarm = new_test_swarm(Config::with_tokio_executor());
+        let upnp = ExternalAddrOrigin::Upnp;
+        let candidates = vec![
+            multiaddr![Ip4([1, 2, 3, 4]), Tcp(1234u16)],
+            multiaddr![Ip4([1, 2, 3, 4]), Udp(1234u16), QuicV1],
+            multiaddr![Ip4([1, 2, 3, 4]), Tcp(1234u16)],
+        ];
+
+        swarm.handle_behaviour_event(ToSwarm::NewExternalAddrCandidate {
+            addresses: candidates.clone(),
+            origin: upnp,
+        });
+        swarm.handle_behaviour_event(ToSwarm::NewExternalAddrCandidate {
+            addresses: vec![candidates[0].clone()],
+            origin: ExternalAddrOrigin::Unknown,
+        });
+
+        let mut deduplicated = candidates[..2].to_vec();
+        deduplicated.sort_unstable();
+        assert_eq!(
+            swarm.behaviour().on_new_external_addr,
+            [deduplicated.clone(), vec![candidates[0].clone()]].concat()
+        );
+        assert_eq!(
+            swarm.behaviour().on_new_external_addrs,
+            vec![deduplicated.clone(), vec![candidates[0].clone()]]
+        );
+        for address in deduplicated {
+            assert!(matches!(
+                swarm.next().await.unwrap(),
+                SwarmEvent::NewExternalAddrCandidate { address: a, origin }
+                    if a == address && origin == upnp
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn external_address_events_report_origin_and_reason() {
+        let mut swarm = new_test_swarm(Config::with_tokio_executor());
+        let identify = ExternalAddrOrigin::Identify;
+        let autonat = ExternalAddrOrigin::Autonat;
+        let candidate = multiaddr![Ip4([1, 2, 3, 4]), Tcp(1234u16)];
+
+        swarm.handle_behaviour_event(ToSwarm::NewExternalAddrCandidate {
+            addresses: vec![candidate.clone()],
+            origin: identify,
+        });
+        swarm.handle_behaviour_event(ToSwarm::ExternalAddrConfirmed {
+            address: candidate.clone(),
+            origin: autonat,
+        });
+        swarm.handle_behaviour_event(ToSwarm::ExternalAddrExpired {
+            address: candidate.clone(),
+            reason: ExternalAddrExpiryReason::ConfidenceDecayed,
+        });
+        assert!(matches!(
+            swarm.next().await.unwrap(),
+            SwarmEvent::NewExternalAddrCandidate { address, origin }
+                if address == candidate && origin == identify
+        ));
+        // The confirmed address is attributed to the subsystem that proposed it.
+        assert!(matches!(
+            swarm.next().await.unwrap(),
+            SwarmEvent::ExternalAddrConfirmed { address, origin }
+                if address == candidate && origin == identify
+        ));
+        assert!(matches!(
+            swarm.next().await.unwrap(),
+            SwarmEvent::ExternalAddrExpired { address, origin, reason }
+                if address == candidate
+                    && origin == identify
+                    && reason == ExternalAddrExpiryReason::ConfidenceDecayed
+        ));
+
+        // Manually added addresses are kept once the listener of an equal address closes.
+        let listener = swarm.listen_on(multiaddr![Memory(0u64)]).unwrap();
+        let listen_addr = match swarm.next().await.unwrap() {
+            SwarmEvent::NewListenAddr { address, .. } => address,
+            e => panic!("Unexpected swarm event {e:?}."),
+        };
+        swarm.add_external_address(listen_addr.clone());
+        assert_eq!(
+            swarm.external_address_origin(&listen_addr),
+            Some(ExternalAddrOrigin::Manual)
+        );
+        assert!(swarm.remove_listener(listener));
+        loop {
+            match swarm.next().await.unwrap() {
+                SwarmEvent::ListenerClosed { .. } => break,
+                SwarmEvent::ExpiredListenAddr { .. } => {}
+                e => panic!("Unexpected swarm event {e:?}."),
+            }
+        }
+        assert_eq!(
+            swarm.external_addresses().collect::<Vec<_>>(),
+            vec![&listen_addr]
+        );
+    }
+
     #[test]
     fn dial_error_prints_sources() {
         // This constitutes a fairly typical error for chained transports.
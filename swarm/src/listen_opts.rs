@@ -5,6 +5,7 @@ use libp2p_core::Multiaddr;
 pub struct ListenOpts {
     id: ListenerId,
     address: Multiaddr,
+    metadata: Option<String>,
 }
 
 impl ListenOpts {
@@ -12,9 +13,19 @@ impl ListenOpts {
         ListenOpts {
             id: ListenerId::next(),
             address,
+            metadata: None,
         }
     }
 
+    /// Attach opaque metadata to the listener, e.g. describing its purpose.
+    ///
+    /// The metadata is echoed back in all [`SwarmEvent`](crate::SwarmEvent)s and
+    /// [`FromSwarm`](crate::FromSwarm) events concerning the listener.
+    pub fn with_metadata(mut self, metadata: impl Into<String>) -> Self {
+        self.metadata = Some(metadata.into());
+        self
+    }
+
     /// Get the [`ListenerId`] of this listen attempt
     pub fn listener_id(&self) -> ListenerId {
         self.id
@@ -24,6 +35,11 @@ impl ListenOpts {
     pub fn address(&self) -> &Multiaddr {
         &self.address
     }
+
+    /// Get the metadata attached to the listener, if any.
+    pub fn metadata(&self) -> Option<&str> {
+        self.metadata.as_deref()
+    }
 }
 
 impl From<Multiaddr> for ListenOpts {
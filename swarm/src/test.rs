@@ -20,7 +20,8 @@
 
 use crate::behaviour::{
     ConnectionClosed, ConnectionEstablished, DialFailure, ExpiredListenAddr, ExternalAddrExpired,
-    FromSwarm, ListenerClosed, ListenerError, NewExternalAddrCandidate, NewListenAddr, NewListener,
+    FromSwarm, ListenerClosed, ListenerError, NewExternalAddrCandidate, NewExternalAddrCandidates,
+    NewListenAddr, NewListener,
 };
 use crate::{
     ConnectionDenied, ConnectionHandler, ConnectionId, NetworkBehaviour, THandler, THandlerInEvent,
@@ -148,6 +149,7 @@ where
     pub(crate) on_new_listener: Vec<ListenerId>,
     pub(crate) on_new_listen_addr: Vec<(ListenerId, Multiaddr)>,
     pub(crate) on_new_external_addr: Vec<Multiaddr>,
+    pub(crate) on_new_external_addrs: Vec<Vec<Multiaddr>>,
     pub(crate) on_expired_listen_addr: Vec<(ListenerId, Multiaddr)>,
     pub(crate) on_expired_external_addr: Vec<Multiaddr>,
     pub(crate) on_listener_error: Vec<ListenerId>,
@@ -174,6 +176,7 @@ where
             on_new_listener: Vec::new(),
             on_new_listen_addr: Vec::new(),
             on_new_external_addr: Vec::new(),
+            on_new_external_addrs: Vec::new(),
             on_expired_listen_addr: Vec::new(),
             on_expired_external_addr: Vec::new(),
             on_listener_error: Vec::new(),
@@ -194,6 +197,7 @@ where
         self.on_dial_failure = Vec::new();
         self.on_new_listen_addr = Vec::new();
         self.on_new_external_addr = Vec::new();
+        self.on_new_external_addrs = Vec::new();
         self.on_expired_listen_addr = Vec::new();
         self.on_listener_error = Vec::new();
         self.on_listener_closed = Vec::new();
@@ -249,6 +253,7 @@ where
             endpoint,
             failed_addresses,
             other_established,
+            negotiated_protocols,
         }: ConnectionEstablished,
     ) {
         let mut other_peer_connections = self
@@ -291,6 +296,7 @@ where
                 endpoint,
                 failed_addresses,
                 other_established,
+                negotiated_protocols,
             }));
     }
 
@@ -449,19 +455,26 @@ where
             FromSwarm::DialFailure(DialFailure { peer_id, .. }) => {
                 self.on_dial_failure.push(peer_id);
             }
-            FromSwarm::NewListener(NewListener { listener_id }) => {
+            FromSwarm::NewListener(NewListener { listener_id, .. }) => {
                 self.on_new_listener.push(listener_id);
             }
-            FromSwarm::NewListenAddr(NewListenAddr { listener_id, addr }) => {
+            FromSwarm::NewListenAddr(NewListenAddr {
+                listener_id, addr, ..
+            }) => {
                 self.on_new_listen_addr.push((listener_id, addr.clone()));
             }
-            FromSwarm::ExpiredListenAddr(ExpiredListenAddr { listener_id, addr }) => {
+            FromSwarm::ExpiredListenAddr(ExpiredListenAddr {
+                listener_id, addr, ..
+            }) => {
                 self.on_expired_listen_addr
                     .push((listener_id, addr.clone()));
             }
             FromSwarm::NewExternalAddrCandidate(NewExternalAddrCandidate { addr }) => {
                 self.on_new_external_addr.push(addr.clone());
             }
+            FromSwarm::NewExternalAddrCandidates(NewExternalAddrCandidates { addrs }) => {
+                self.on_new_external_addrs.push(addrs.to_vec());
+            }
             FromSwarm::ExternalAddrExpired(ExternalAddrExpired { addr }) => {
                 self.on_expired_external_addr.push(addr.clone());
             }
@@ -471,6 +484,7 @@ where
             FromSwarm::ListenerClosed(ListenerClosed {
                 listener_id,
                 reason,
+                ..
             }) => {
                 self.on_listener_closed.push((listener_id, reason.is_ok()));
             }
@@ -0,0 +1,143 @@
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_ping as ping;
+use libp2p_swarm::behaviour::map::BehaviourMap;
+use libp2p_swarm::{
+    dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, Swarm, SwarmEvent,
+    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+use std::collections::{HashSet, VecDeque};
+use std::task::{Context, Poll};
+
+#[async_std::test]
+async fn events_are_tagged_with_key() {
+    let mut swarm =
+        Swarm::new_ephemeral(|_| BehaviourMap::from_iter([("tenant", ping::Behaviour::default())]));
+    let mut other =
+        Swarm::new_ephemeral(|_| BehaviourMap::from_iter([("tenant", ping::Behaviour::default())]));
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+    async_std::task::spawn(other.loop_on_next());
+
+    let (key, event) = swarm.next_behaviour_event().await;
+    assert_eq!(key, "tenant");
+    assert!(event.result.is_ok());
+}
+
+#[async_std::test]
+async fn behaviours_sharing_a_protocol_are_namespaced_by_key() {
+    let new_map = || {
+        BehaviourMap::from_iter([
+            ("a", ping::Behaviour::default()),
+            ("b", ping::Behaviour::default()),
+        ])
+    };
+    let mut swarm = Swarm::new_ephemeral(|_| new_map());
+    let mut other = Swarm::new_ephemeral(|_| new_map());
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+    async_std::task::spawn(other.loop_on_next());
+
+    let mut pinged = HashSet::new();
+    while pinged.len() < 2 {
+        let (key, event) = swarm.next_behaviour_event().await;
+        assert!(event.result.is_ok());
+        pinged.insert(key);
+    }
+    assert_eq!(pinged, HashSet::from(["a", "b"]));
+}
+
+#[async_std::test]
+async fn inserted_behaviour_only_learns_about_new_connections() {
+    let mut swarm = Swarm::new_ephemeral(|_| BehaviourMap::from_iter([("a", Counter::default())]));
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    other.listen().with_memory_addr_external().await;
+    let other_id = *other.local_peer_id();
+
+    swarm.connect(&mut other).await;
+    swarm.behaviour_mut().insert("b", Counter::default());
+    swarm.disconnect_peer_id(other_id).unwrap();
+
+    let key = swarm
+        .wait(|event| match event {
+            SwarmEvent::Behaviour((key, Closed)) => Some(key),
+            _ => None,
+        })
+        .await;
+    assert_eq!(key, "a");
+
+    let a = swarm.behaviour().get(&"a").unwrap();
+    assert_eq!((a.established, a.closed), (1, 1));
+    let b = swarm.behaviour().get(&"b").unwrap();
+    assert_eq!((b.established, b.closed), (0, 0));
+
+    swarm.connect(&mut other).await;
+    assert_eq!(swarm.behaviour().get(&"a").unwrap().established, 2);
+    assert_eq!(swarm.behaviour().get(&"b").unwrap().established, 1);
+}
+
+#[derive(Debug)]
+struct Closed;
+
+/// A [`NetworkBehaviour`] counting established and closed connections.
+#[derive(Default)]
+struct Counter {
+    established: usize,
+    closed: usize,
+    events: VecDeque<Closed>,
+}
+
+impl NetworkBehaviour for Counter {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Closed;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(_) => self.established += 1,
+            FromSwarm::ConnectionClosed(_) => {
+                self.closed += 1;
+                self.events.push_back(Closed);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(ToSwarm::GenerateEvent(event)),
+            None => Poll::Pending,
+        }
+    }
+}
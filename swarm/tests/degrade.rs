@@ -0,0 +1,153 @@
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::behaviour::degrade::Degrade;
+use libp2p_swarm::behaviour::{DialFailure, ListenFailure};
+use libp2p_swarm::{
+    dummy, ConnectionDenied, ConnectionId, DialError, FromSwarm, ListenError, NetworkBehaviour,
+    Swarm, SwarmEvent, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+use std::task::{Context, Poll};
+use std::{error, fmt};
+
+#[async_std::test]
+async fn degraded_connection_stays_open() {
+    let mut swarm = Swarm::new_ephemeral(|_| Degrade::new(Refusing::default()));
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+
+    let refusing = swarm.behaviour().get_ref();
+    assert_eq!(refusing.denied.len(), 1);
+    assert!(swarm.behaviour().is_degraded(&refusing.denied[0]));
+    assert_eq!(
+        refusing.established, 0,
+        "Degraded connections are not reported"
+    );
+    assert!(swarm.is_connected(other.local_peer_id()));
+}
+
+#[async_std::test]
+async fn filtered_connection_is_denied() {
+    let mut swarm = Swarm::new_ephemeral(|_| {
+        Degrade::new(Refusing::default())
+            .degrade_if(|cause| cause.downcast_ref::<MissingKey>().is_none())
+    });
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let (other_addr, _) = other.listen().await;
+    async_std::task::spawn(other.loop_on_next());
+
+    swarm.dial(other_addr).unwrap();
+
+    let cause = swarm
+        .wait(|event| match event {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Denied { cause },
+                ..
+            } => Some(cause),
+            _ => None,
+        })
+        .await;
+    assert!(cause.downcast::<MissingKey>().is_ok());
+    assert!(!swarm
+        .behaviour()
+        .is_degraded(&swarm.behaviour().get_ref().denied[0]));
+}
+
+#[test]
+fn connection_denied_by_other_behaviour_is_forgotten() {
+    let mut behaviour = Degrade::new(Refusing::default());
+    let addr: Multiaddr = "/memory/1234".parse().unwrap();
+    let inbound = ConnectionId::new_unchecked(0);
+    let outbound = ConnectionId::new_unchecked(1);
+
+    assert!(behaviour
+        .handle_established_inbound_connection(inbound, PeerId::random(), &addr, &addr)
+        .is_ok());
+    assert!(behaviour
+        .handle_established_outbound_connection(outbound, PeerId::random(), &addr, Endpoint::Dialer)
+        .is_ok());
+    assert!(behaviour.is_degraded(&inbound));
+    assert!(behaviour.is_degraded(&outbound));
+
+    // A behaviour composed with `Degrade` denied both connections.
+    behaviour.on_swarm_event(FromSwarm::ListenFailure(ListenFailure {
+        local_addr: &addr,
+        send_back_addr: &addr,
+        error: &ListenError::Aborted,
+        connection_id: inbound,
+    }));
+    behaviour.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+        peer_id: None,
+        error: &DialError::Aborted,
+        connection_id: outbound,
+    }));
+
+    assert!(!behaviour.is_degraded(&inbound));
+    assert!(!behaviour.is_degraded(&outbound));
+}
+
+/// A [`NetworkBehaviour`] denying all established connections.
+#[derive(Default)]
+struct Refusing {
+    denied: Vec<ConnectionId>,
+    established: usize,
+}
+
+#[derive(Debug)]
+struct MissingKey;
+
+impl fmt::Display for MissingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing key")
+    }
+}
+
+impl error::Error for MissingKey {}
+
+impl NetworkBehaviour for Refusing {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = void::Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.denied.push(connection_id);
+        Err(ConnectionDenied::new(MissingKey))
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.denied.push(connection_id);
+        Err(ConnectionDenied::new(MissingKey))
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionEstablished(_) = event {
+            self.established += 1;
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
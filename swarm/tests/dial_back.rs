@@ -0,0 +1,74 @@
+use libp2p_core::multiaddr::Protocol;
+use libp2p_swarm::behaviour::dial_back::{Behaviour, Event, ProbeError};
+use libp2p_swarm::Swarm;
+use libp2p_swarm_test::SwarmExt;
+use std::error::Error;
+
+#[async_std::test]
+async fn probe_reports_reachable_address() {
+    let mut swarm = Swarm::new_ephemeral(|_| Behaviour::new());
+    let (memory_addr, _) = swarm.listen().await;
+
+    // Listen addresses are not dialed by the `Swarm`, thus probe the address including our peer ID.
+    let address = memory_addr.with(Protocol::P2p(*swarm.local_peer_id()));
+    let id = swarm.behaviour_mut().probe(address.clone());
+    assert!(swarm.behaviour().is_pending(id));
+
+    match swarm.next_behaviour_event().await {
+        Event::Reachable {
+            probe_id,
+            address: a,
+        } => {
+            assert_eq!(probe_id, id);
+            assert_eq!(a, address);
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    }
+    assert!(!swarm.behaviour().is_pending(id));
+}
+
+#[async_std::test]
+async fn probe_reports_unreachable_addresses() {
+    let mut swarm = Swarm::new_ephemeral(|_| Behaviour::new());
+    let (memory_addr, _) = swarm.listen().await;
+
+    let mut other = Swarm::new_ephemeral(|_| Behaviour::new());
+    let other_peer_id = *other.local_peer_id();
+    let (other_addr, _) = other.listen().await;
+    async_std::task::spawn(other.loop_on_next());
+
+    let listen_addr = swarm.behaviour_mut().probe(memory_addr);
+    let foreign_addr = swarm.behaviour_mut().probe(other_addr);
+    let no_listener = swarm
+        .behaviour_mut()
+        .probe(Protocol::Memory(u64::MAX).into());
+
+    for _ in 0..3 {
+        match swarm.next_behaviour_event().await {
+            Event::Unreachable {
+                probe_id,
+                error: ProbeError::ListenAddress,
+                ..
+            } => assert_eq!(probe_id, listen_addr),
+            Event::Unreachable {
+                probe_id,
+                error: ProbeError::ForeignPeer(peer),
+                ..
+            } => {
+                assert_eq!(probe_id, foreign_addr);
+                assert_eq!(peer, other_peer_id);
+            }
+            Event::Unreachable {
+                probe_id,
+                error: ProbeError::Dial(error),
+                ..
+            } => {
+                assert_eq!(probe_id, no_listener);
+                // The transport errors of the dial are kept as the source of the error.
+                let dial_error = error.get_ref().unwrap();
+                assert!(dial_error.source().is_some(), "{dial_error:?}");
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+    }
+}
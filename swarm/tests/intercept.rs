@@ -0,0 +1,161 @@
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::behaviour::intercept::{Intercepted, Interceptor};
+use libp2p_swarm::{
+    dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, Swarm, SwarmEvent,
+    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[async_std::test]
+async fn muted_dials_are_dropped() {
+    let mut swarm =
+        Swarm::new_ephemeral(|_| Intercepted::new(Dialer::default(), Maintenance::default()));
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    let (other_addr, _) = other.listen().await;
+    let other_id = *other.local_peer_id();
+    async_std::task::spawn(other.loop_on_next());
+
+    swarm.behaviour_mut().interceptor_mut().muted = true;
+    swarm.behaviour_mut().get_mut().dial(other_addr.clone());
+    assert!(
+        async_std::future::timeout(Duration::from_millis(100), swarm.next_swarm_event())
+            .await
+            .is_err(),
+        "Muted dial should not produce any event"
+    );
+    assert_eq!(swarm.behaviour().interceptor().dropped_dials, 1);
+    assert!(!swarm.is_connected(&other_id));
+
+    swarm.behaviour_mut().interceptor_mut().muted = false;
+    swarm.behaviour_mut().get_mut().dial(other_addr);
+    swarm
+        .wait(|event| match event {
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                (peer_id == other_id).then_some(())
+            }
+            _ => None,
+        })
+        .await;
+    assert_eq!(swarm.behaviour().interceptor().established, 1);
+    assert_eq!(swarm.behaviour().get_ref().established, 1);
+}
+
+#[async_std::test]
+async fn withheld_swarm_events_are_not_reported() {
+    let mut swarm = Swarm::new_ephemeral(|_| {
+        Intercepted::new(
+            Dialer::default(),
+            Maintenance {
+                withhold_established: true,
+                ..Default::default()
+            },
+        )
+    });
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+
+    assert_eq!(swarm.behaviour().interceptor().established, 1);
+    assert_eq!(swarm.behaviour().get_ref().established, 0);
+}
+
+/// An [`Interceptor`] recording established connections and dropping dials while muted.
+#[derive(Default)]
+struct Maintenance {
+    muted: bool,
+    withhold_established: bool,
+    dropped_dials: usize,
+    established: usize,
+}
+
+impl Interceptor<Dialer> for Maintenance {
+    fn intercept_swarm_event<'a>(&mut self, event: FromSwarm<'a>) -> Option<FromSwarm<'a>> {
+        if let FromSwarm::ConnectionEstablished(_) = event {
+            self.established += 1;
+            if self.withhold_established {
+                return None;
+            }
+        }
+        Some(event)
+    }
+
+    fn intercept_action(
+        &mut self,
+        action: ToSwarm<void::Void, void::Void>,
+    ) -> Option<ToSwarm<void::Void, void::Void>> {
+        if let ToSwarm::Dial { .. } = action {
+            if self.muted {
+                self.dropped_dials += 1;
+                return None;
+            }
+        }
+        Some(action)
+    }
+}
+
+/// A [`NetworkBehaviour`] dialing the addresses passed to [`Dialer::dial`].
+#[derive(Default)]
+struct Dialer {
+    pending: VecDeque<Multiaddr>,
+    established: usize,
+}
+
+impl Dialer {
+    fn dial(&mut self, address: Multiaddr) {
+        self.pending.push_back(address);
+    }
+}
+
+impl NetworkBehaviour for Dialer {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = void::Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionEstablished(_) = event {
+            self.established += 1;
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.pending.pop_front() {
+            Some(address) => Poll::Ready(ToSwarm::Dial {
+                opts: address.into(),
+            }),
+            None => Poll::Pending,
+        }
+    }
+}
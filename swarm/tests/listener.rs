@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     task::{Context, Poll},
 };
 
@@ -24,6 +24,7 @@ async fn behaviour_listener() {
             SwarmEvent::NewListenAddr {
                 listener_id,
                 address,
+                ..
             } => {
                 assert_eq!(listener_id, id);
                 Some(address)
@@ -40,6 +41,7 @@ async fn behaviour_listener() {
                 listener_id,
                 addresses,
                 reason,
+                ..
             } => {
                 assert_eq!(listener_id, id);
                 assert!(addresses.contains(&address));
@@ -51,10 +53,56 @@ async fn behaviour_listener() {
         .await;
 }
 
+#[async_std::test]
+async fn listener_metadata_is_echoed() {
+    let mut swarm = Swarm::new_ephemeral(|_| Behaviour::default());
+    let opts = ListenOpts::new(Protocol::Memory(0).into()).with_metadata("memory");
+    swarm.behaviour_mut().listeners.insert(opts.listener_id());
+    let id = swarm.listen_on_with_opts(opts).unwrap();
+
+    assert_eq!(
+        swarm.behaviour().metadata.get(&id).map(String::as_str),
+        Some("memory")
+    );
+
+    swarm
+        .wait(|e| match e {
+            SwarmEvent::NewListenAddr {
+                listener_id,
+                metadata,
+                ..
+            } => {
+                assert_eq!(listener_id, id);
+                assert_eq!(metadata.as_deref(), Some("memory"));
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+
+    assert!(swarm.remove_listener(id));
+
+    swarm
+        .wait(|e| match e {
+            SwarmEvent::ListenerClosed {
+                listener_id,
+                metadata,
+                ..
+            } => {
+                assert_eq!(listener_id, id);
+                assert_eq!(metadata.as_deref(), Some("memory"));
+                Some(())
+            }
+            _ => None,
+        })
+        .await;
+}
+
 #[derive(Default)]
 struct Behaviour {
     events: VecDeque<ToSwarm<<Self as NetworkBehaviour>::ToSwarm, THandlerInEvent<Self>>>,
     listeners: HashSet<ListenerId>,
+    metadata: HashMap<ListenerId, String>,
 }
 
 impl Behaviour {
@@ -107,18 +155,27 @@ impl NetworkBehaviour for Behaviour {
 
     fn on_swarm_event(&mut self, event: FromSwarm) {
         match event {
-            FromSwarm::NewListener(NewListener { listener_id }) => {
+            FromSwarm::NewListener(NewListener {
+                listener_id,
+                metadata,
+            }) => {
                 assert!(self.listeners.contains(&listener_id));
+                if let Some(metadata) = metadata {
+                    self.metadata.insert(listener_id, metadata.to_owned());
+                }
             }
             FromSwarm::NewListenAddr(NewListenAddr { listener_id, .. }) => {
                 assert!(self.listeners.contains(&listener_id));
             }
-            FromSwarm::ListenerError(ListenerError { listener_id, err }) => {
+            FromSwarm::ListenerError(ListenerError {
+                listener_id, err, ..
+            }) => {
                 panic!("Error for listener {listener_id:?}: {err}");
             }
             FromSwarm::ListenerClosed(ListenerClosed {
                 listener_id,
                 reason,
+                ..
             }) => {
                 assert!(self.listeners.contains(&listener_id));
                 assert!(reason.is_ok());
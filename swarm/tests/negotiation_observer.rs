@@ -0,0 +1,76 @@
+use futures::StreamExt;
+use libp2p_core::transport::MemoryTransport;
+use libp2p_core::upgrade::{Negotiation, NegotiationObserver, NegotiationRole, Version};
+use libp2p_core::Transport;
+use libp2p_identity::Keypair;
+use libp2p_swarm::{Config, Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Observed = Arc<Mutex<Vec<(NegotiationRole, String)>>>;
+
+#[async_std::test]
+async fn observes_connection_and_stream_negotiations() {
+    let observed = Observed::default();
+    let observed_clone = observed.clone();
+    let observer: Arc<dyn NegotiationObserver> = Arc::new(move |n: &Negotiation<'_>| {
+        if let Ok(protocol) = n.outcome {
+            observed_clone
+                .lock()
+                .unwrap()
+                .push((n.role, protocol.to_owned()));
+        }
+    });
+
+    let mut listener = new_swarm(observer.clone());
+    let mut dialer = new_swarm(observer);
+
+    listener.listen_on("/memory/0".parse().unwrap()).unwrap();
+    let address = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+            break address;
+        }
+    };
+    async_std::task::spawn(listener.loop_on_next());
+
+    dialer.dial(address).unwrap();
+    loop {
+        if let SwarmEvent::Behaviour(libp2p_ping::Event { result: Ok(_), .. }) =
+            dialer.select_next_some().await
+        {
+            break;
+        }
+    }
+
+    let observed = observed.lock().unwrap();
+    for role in [NegotiationRole::Dialer, NegotiationRole::Listener] {
+        for protocol in ["/plaintext/2.0.0", "/yamux/1.0.0", "/ipfs/ping/1.0.0"] {
+            assert!(
+                observed.contains(&(role, protocol.to_owned())),
+                "{protocol} not observed as {role:?}: {observed:?}"
+            );
+        }
+    }
+}
+
+fn new_swarm(observer: Arc<dyn NegotiationObserver>) -> Swarm<libp2p_ping::Behaviour> {
+    let identity = Keypair::generate_ed25519();
+    let peer_id = identity.public().to_peer_id();
+
+    let transport = MemoryTransport::default()
+        .upgrade(Version::V1)
+        .with_negotiation_observer(observer.clone())
+        .authenticate(libp2p_plaintext::Config::new(&identity))
+        .multiplex(libp2p_yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        libp2p_ping::Behaviour::default(),
+        peer_id,
+        Config::with_async_std_executor()
+            .with_idle_connection_timeout(Duration::from_secs(5))
+            .with_negotiation_observer(observer),
+    )
+}
@@ -0,0 +1,260 @@
+use futures::future::poll_fn;
+use futures::StreamExt;
+use libp2p_core::upgrade::DeniedUpgrade;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::handler::ConnectionEvent;
+use libp2p_swarm::{
+    CloseConnection, ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+    FromSwarm, HandlerNotificationDropped, HandlerNotified, NetworkBehaviour, NotificationId,
+    NotifyHandler, SubstreamProtocol, Swarm, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::task::{Context, Poll};
+use void::Void;
+
+#[async_std::test]
+async fn acknowledges_notified_handler() {
+    let mut swarm1 = Swarm::new_ephemeral(|_| Behaviour::default());
+    let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::default());
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer_id = *swarm2.local_peer_id();
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let id = NotificationId::next();
+    swarm1
+        .behaviour_mut()
+        .actions
+        .push_back(ToSwarm::NotifyHandlerWithAck {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: (),
+            id,
+        });
+
+    match wait_for_ack(&mut swarm1).await {
+        Ack::Notified(notified) => {
+            assert_eq!(notified.id, id);
+            assert_eq!(notified.peer_id, peer_id);
+            assert_eq!(
+                Some(notified.connection_id),
+                swarm1.behaviour().connections.first().copied()
+            );
+        }
+        ack => panic!("Unexpected acknowledgement: {ack:?}"),
+    }
+}
+
+#[async_std::test]
+async fn reports_dropped_notification() {
+    let mut swarm1 = Swarm::new_ephemeral(|_| Behaviour::default());
+    let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::default());
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer_id = *swarm2.local_peer_id();
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    let id = NotificationId::next();
+    swarm1
+        .behaviour_mut()
+        .actions
+        .push_back(ToSwarm::NotifyHandlerWithAck {
+            peer_id,
+            handler: NotifyHandler::One(ConnectionId::new_unchecked(0)),
+            event: (),
+            id,
+        });
+
+    match wait_for_ack(&mut swarm1).await {
+        Ack::Dropped(dropped) => {
+            assert_eq!(dropped.id, id);
+            assert_eq!(dropped.peer_id, peer_id);
+        }
+        ack => panic!("Unexpected acknowledgement: {ack:?}"),
+    }
+}
+
+#[async_std::test]
+async fn reports_notification_dropped_by_closing_connection() {
+    let (unblock_tx, unblock_rx) = mpsc::channel();
+    let mut swarm1 = Swarm::new_ephemeral(|_| Behaviour {
+        block_handler: Some(unblock_rx),
+        ..Default::default()
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::default());
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer_id = *swarm2.local_peer_id();
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    // Stall the connection task in the handler, so that the close command and the
+    // notification are both queued by the time it reads its next command.
+    let id = NotificationId::next();
+    swarm1.behaviour_mut().actions.extend([
+        ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: (),
+        },
+        ToSwarm::CloseConnection {
+            peer_id,
+            connection: CloseConnection::All,
+        },
+        ToSwarm::NotifyHandlerWithAck {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: (),
+            id,
+        },
+    ]);
+    swarm1.behaviour_mut().unblock_handler = Some(unblock_tx);
+
+    match wait_for_ack(&mut swarm1).await {
+        Ack::Dropped(dropped) => {
+            assert_eq!(dropped.id, id);
+            assert_eq!(dropped.peer_id, peer_id);
+        }
+        ack => panic!("Unexpected acknowledgement: {ack:?}"),
+    }
+}
+
+async fn wait_for_ack(swarm: &mut Swarm<Behaviour>) -> Ack {
+    poll_fn(|cx| {
+        while swarm.poll_next_unpin(cx).is_ready() {}
+
+        match swarm.behaviour_mut().acks.pop_front() {
+            Some(ack) => Poll::Ready(ack),
+            None => Poll::Pending,
+        }
+    })
+    .await
+}
+
+#[derive(Debug)]
+enum Ack {
+    Notified(HandlerNotified),
+    Dropped(HandlerNotificationDropped),
+}
+
+#[derive(Default)]
+struct Behaviour {
+    actions: VecDeque<ToSwarm<Void, ()>>,
+    acks: VecDeque<Ack>,
+    connections: Vec<ConnectionId>,
+    /// Makes the next handler block on its first event until signalled.
+    block_handler: Option<mpsc::Receiver<()>>,
+    /// Signals the blocked handler once all `actions` have been handed to the swarm.
+    unblock_handler: Option<mpsc::Sender<()>>,
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = Handler;
+    type ToSwarm = Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler {
+            blocked_on: self.block_handler.take(),
+        })
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(Handler {
+            blocked_on: self.block_handler.take(),
+        })
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(e) => self.connections.push(e.connection_id),
+            FromSwarm::HandlerNotified(e) => self.acks.push_back(Ack::Notified(e)),
+            FromSwarm::HandlerNotificationDropped(e) => self.acks.push_back(Ack::Dropped(e)),
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.actions.pop_front() {
+            Some(action) => Poll::Ready(action),
+            None => {
+                if let Some(tx) = self.unblock_handler.take() {
+                    let _ = tx.send(());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct Handler {
+    blocked_on: Option<mpsc::Receiver<()>>,
+}
+
+impl ConnectionHandler for Handler {
+    type FromBehaviour = ();
+    type ToBehaviour = Void;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = ();
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        true
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        Poll::Pending
+    }
+
+    fn on_behaviour_event(&mut self, _: Self::FromBehaviour) {
+        if let Some(rx) = self.blocked_on.take() {
+            let _ = rx.recv();
+        }
+    }
+
+    fn on_connection_event(
+        &mut self,
+        _: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+    }
+}
@@ -513,6 +513,62 @@ fn generated_out_event_derive_debug() {
     require_debug::<Foo>();
 }
 
+#[test]
+fn generated_out_event_extra_derives() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(
+        prelude = "libp2p_swarm::derive_prelude",
+        to_swarm_derive(Clone, PartialEq),
+        to_swarm_non_exhaustive
+    )]
+    struct Foo {
+        a: libp2p_swarm::dummy::Behaviour,
+        b: libp2p_swarm::dummy::Behaviour,
+    }
+
+    fn require_clone_partial_eq<T>()
+    where
+        T: NetworkBehaviour,
+        <T as NetworkBehaviour>::ToSwarm: Clone + PartialEq,
+    {
+    }
+
+    require_clone_partial_eq::<Foo>();
+}
+
+#[test]
+fn generated_out_event_from_member_events() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Foo<A> {
+        #[behaviour(from = "ping::Event")]
+        ping: ping::Behaviour,
+        #[behaviour(from = "identify::Event")]
+        identify: identify::Behaviour,
+        dummy: dummy::Behaviour,
+        generic: A,
+    }
+
+    fn require_from<T>()
+    where
+        T: NetworkBehaviour,
+        <T as NetworkBehaviour>::ToSwarm: From<ping::Event> + From<identify::Event>,
+    {
+    }
+
+    require_from::<Foo<dummy::Behaviour>>();
+
+    let event: FooEvent<dummy::Behaviour> = ping::Event {
+        peer: libp2p_identity::PeerId::random(),
+        connection: libp2p_swarm::ConnectionId::new_unchecked(0),
+        result: Ok(std::time::Duration::ZERO),
+    }
+    .into();
+    assert!(matches!(event, FooEvent::Ping(_)));
+}
+
 #[test]
 fn multiple_behaviour_attributes() {
     #[allow(dead_code)]
@@ -609,6 +665,597 @@ fn custom_out_event_no_type_parameters() {
     require_net_behaviour::<Behaviour<()>>();
 }
 
+#[test]
+fn detects_crate_path() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    struct Foo {
+        ping: ping::Behaviour,
+    }
+
+    require_net_behaviour::<Foo>();
+}
+
+mod reexport {
+    pub(crate) use libp2p_swarm as swarm;
+}
+
+#[test]
+fn custom_crate_path() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(crate = "crate::reexport")]
+    struct Foo {
+        ping: ping::Behaviour,
+    }
+
+    require_net_behaviour::<Foo>();
+}
+
+mod prelude_reexport {
+    pub(crate) use libp2p_swarm::derive_prelude as libp2p_reexport;
+}
+
+#[test]
+fn custom_prelude_path_with_shadowed_std_items() {
+    #[allow(dead_code)]
+    type Result<T> = std::result::Result<T, ()>;
+    #[allow(dead_code)]
+    type Option = ();
+    #[allow(dead_code, non_snake_case)]
+    fn Ok() {}
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "crate::prelude_reexport::libp2p_reexport")]
+    struct Foo {
+        ping: ping::Behaviour,
+        identify: identify::Behaviour,
+    }
+
+    require_net_behaviour::<Foo>();
+}
+
+#[test]
+fn trace_swarm_events() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(trace_swarm_events)]
+    struct Foo {
+        ping: ping::Behaviour,
+        identify: identify::Behaviour,
+    }
+
+    #[derive(Clone, Default)]
+    struct Logs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+
+    let identity = libp2p_identity::Keypair::generate_ed25519();
+    let mut foo = Foo {
+        ping: ping::Behaviour::default(),
+        identify: identify::Behaviour::new(identify::Config::new(
+            "/foo/1.0.0".to_owned(),
+            identity.public(),
+        )),
+    };
+
+    tracing::subscriber::with_default(subscriber, || {
+        foo.on_swarm_event(FromSwarm::ExternalAddrConfirmed(
+            libp2p_swarm::behaviour::ExternalAddrConfirmed {
+                addr: &"/memory/1234".parse().unwrap(),
+            },
+        ));
+    });
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    for field in ["ping", "identify"] {
+        assert!(
+            logs.contains(&format!(
+                r#"NetworkBehaviour::on_swarm_event{{behaviour="Foo" field="{field}" event="ExternalAddrConfirmed"}}"#
+            )),
+            "{logs}"
+        );
+    }
+}
+
+#[test]
+fn as_ref_fields() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Foo {
+        #[behaviour(as_ref)]
+        ping: ping::Behaviour,
+        identify: identify::Behaviour,
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Bar<T: NetworkBehaviour> {
+        #[behaviour(as_ref)]
+        inner: T,
+    }
+
+    /// A helper generic over any behaviour containing a `ping::Behaviour`.
+    fn ping<B: NetworkBehaviour + AsRef<ping::Behaviour> + AsMut<ping::Behaviour>>(
+        behaviour: &mut B,
+    ) -> *const ping::Behaviour {
+        assert!(std::ptr::eq(behaviour.as_mut(), behaviour.as_ref()));
+        behaviour.as_ref()
+    }
+
+    let identity = libp2p_identity::Keypair::generate_ed25519();
+    let mut foo = Foo {
+        ping: ping::Behaviour::default(),
+        identify: identify::Behaviour::new(identify::Config::new(
+            "/foo/1.0.0".to_owned(),
+            identity.public(),
+        )),
+    };
+    assert!(std::ptr::eq(ping(&mut foo), &foo.ping));
+
+    let mut bar = Bar {
+        inner: ping::Behaviour::default(),
+    };
+    assert!(std::ptr::eq(ping(&mut bar), &bar.inner));
+}
+
+#[test]
+fn enum_of_behaviours() {
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    struct Server {
+        ping: ping::Behaviour,
+        identify: identify::Behaviour,
+    }
+
+    #[allow(dead_code, clippy::large_enum_variant)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    enum Mode {
+        Client(ping::Behaviour),
+        Server(Server),
+        Idle(dummy::Behaviour),
+    }
+
+    #[allow(
+        dead_code,
+        unreachable_code,
+        clippy::diverging_sub_expression,
+        clippy::used_underscore_binding
+    )]
+    fn foo() {
+        require_net_behaviour::<Mode>();
+
+        let _out_event: <Mode as NetworkBehaviour>::ToSwarm = unimplemented!();
+        match _out_event {
+            ModeEvent::Client(ping::Event { .. }) => {}
+            ModeEvent::Server(ServerEvent::Ping(ping::Event { .. })) => {}
+            ModeEvent::Server(ServerEvent::Identify(_)) => {}
+            ModeEvent::Idle(event) => void::unreachable(event),
+        }
+    }
+}
+
+#[test]
+fn enum_of_behaviours_with_generics_and_custom_event() {
+    #[allow(dead_code)]
+    enum Event {
+        Ping(ping::Event),
+        Other,
+    }
+
+    impl From<ping::Event> for Event {
+        fn from(event: ping::Event) -> Self {
+            Event::Ping(event)
+        }
+    }
+
+    impl From<void::Void> for Event {
+        fn from(_: void::Void) -> Self {
+            Event::Other
+        }
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(to_swarm = "Event", prelude = "libp2p_swarm::derive_prelude")]
+    enum Either<A: NetworkBehaviour<ToSwarm = void::Void>> {
+        Ping(ping::Behaviour),
+        Other(A),
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    enum Single {
+        Ping(#[behaviour(from = "ping::Event")] ping::Behaviour),
+    }
+
+    require_net_behaviour::<Either<dummy::Behaviour>>();
+    require_net_behaviour::<Single>();
+
+    let event: SingleEvent = ping::Event {
+        peer: libp2p_identity::PeerId::random(),
+        connection: libp2p_swarm::ConnectionId::new_unchecked(0),
+        result: Ok(std::time::Duration::ZERO),
+    }
+    .into();
+    assert!(matches!(event, SingleEvent::Ping(_)));
+}
+
+#[async_std::test]
+async fn enum_of_behaviours_dispatches_to_active_variant() {
+    use libp2p_swarm::Swarm;
+    use libp2p_swarm_test::SwarmExt;
+
+    #[allow(dead_code, clippy::large_enum_variant)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    enum Mode {
+        Idle(dummy::Behaviour),
+        Ping(ping::Behaviour),
+    }
+
+    let mut client = Swarm::new_ephemeral(|_| Mode::Ping(ping::Behaviour::default()));
+    let mut server = Swarm::new_ephemeral(|_| ping::Behaviour::default());
+    server.listen().with_memory_addr_external().await;
+
+    client.connect(&mut server).await;
+    async_std::task::spawn(server.loop_on_next());
+
+    match client.next_behaviour_event().await {
+        ModeEvent::Ping(event) => assert!(event.result.is_ok()),
+        ModeEvent::Idle(event) => void::unreachable(event),
+    }
+}
+
+#[async_std::test]
+async fn enum_of_behaviours_drops_events_for_handlers_of_previous_variant() {
+    use libp2p_core::upgrade::DeniedUpgrade;
+    use libp2p_swarm::{
+        handler::ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, ConnectionId,
+        NotificationId, NotifyHandler, SubstreamProtocol, Swarm, ToSwarm,
+    };
+    use libp2p_swarm_test::SwarmExt;
+    use std::task::{Context, Poll};
+
+    /// Notifies the handler on the connection to `peer`, recording whether the event was
+    /// delivered.
+    #[derive(Default)]
+    struct Notifier {
+        peer: Option<libp2p_identity::PeerId>,
+        delivered: Option<bool>,
+    }
+
+    impl NetworkBehaviour for Notifier {
+        type ConnectionHandler = Handler;
+        type ToSwarm = void::Void;
+
+        fn handle_established_inbound_connection(
+            &mut self,
+            _: ConnectionId,
+            _: libp2p_identity::PeerId,
+            _: &Multiaddr,
+            _: &Multiaddr,
+        ) -> Result<THandler<Self>, ConnectionDenied> {
+            Ok(Handler)
+        }
+
+        fn handle_established_outbound_connection(
+            &mut self,
+            _: ConnectionId,
+            _: libp2p_identity::PeerId,
+            _: &Multiaddr,
+            _: Endpoint,
+        ) -> Result<THandler<Self>, ConnectionDenied> {
+            Ok(Handler)
+        }
+
+        fn on_swarm_event(&mut self, event: FromSwarm) {
+            match event {
+                FromSwarm::HandlerNotified(_) => self.delivered = Some(true),
+                FromSwarm::HandlerNotificationDropped(_) => self.delivered = Some(false),
+                _ => {}
+            }
+        }
+
+        fn on_connection_handler_event(
+            &mut self,
+            _: libp2p_identity::PeerId,
+            _: ConnectionId,
+            event: THandlerOutEvent<Self>,
+        ) {
+            void::unreachable(event)
+        }
+
+        fn poll(
+            &mut self,
+            _: &mut Context<'_>,
+        ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+            match self.peer.take() {
+                Some(peer_id) => Poll::Ready(ToSwarm::NotifyHandlerWithAck {
+                    peer_id,
+                    handler: NotifyHandler::Any,
+                    event: (),
+                    id: NotificationId::next(),
+                }),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    struct Handler;
+
+    impl ConnectionHandler for Handler {
+        type FromBehaviour = ();
+        type ToBehaviour = void::Void;
+        type InboundProtocol = DeniedUpgrade;
+        type OutboundProtocol = DeniedUpgrade;
+        type InboundOpenInfo = ();
+        type OutboundOpenInfo = ();
+
+        fn listen_protocol(
+            &self,
+        ) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+            SubstreamProtocol::new(DeniedUpgrade, ())
+        }
+
+        fn connection_keep_alive(&self) -> bool {
+            true
+        }
+
+        fn poll(
+            &mut self,
+            _: &mut Context<'_>,
+        ) -> Poll<
+            ConnectionHandlerEvent<
+                Self::OutboundProtocol,
+                Self::OutboundOpenInfo,
+                Self::ToBehaviour,
+            >,
+        > {
+            Poll::Pending
+        }
+
+        fn on_behaviour_event(&mut self, _: Self::FromBehaviour) {}
+
+        fn on_connection_event(
+            &mut self,
+            _: ConnectionEvent<
+                Self::InboundProtocol,
+                Self::OutboundProtocol,
+                Self::InboundOpenInfo,
+                Self::OutboundOpenInfo,
+            >,
+        ) {
+        }
+    }
+
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    enum Mode {
+        First(Notifier),
+        Second(Notifier),
+    }
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| Mode::First(Notifier::default()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| Notifier::default());
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer = *swarm2.local_peer_id();
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    // The handler on the connection was created by `First`, the event is addressed to the one
+    // of `Second` and thus reported as dropped.
+    *swarm1.behaviour_mut() = Mode::Second(Notifier {
+        peer: Some(peer),
+        delivered: None,
+    });
+
+    let delivered = futures::future::poll_fn(|cx| {
+        while swarm1.poll_next_unpin(cx).is_ready() {}
+        match swarm1.behaviour() {
+            Mode::Second(Notifier {
+                delivered: Some(delivered),
+                ..
+            }) => Poll::Ready(*delivered),
+            _ => Poll::Pending,
+        }
+    })
+    .await;
+    assert!(!delivered);
+    assert!(swarm1.is_connected(&peer));
+}
+
+#[async_std::test]
+async fn enum_of_behaviours_reports_connections_to_the_active_variant() {
+    use libp2p_request_response as request_response;
+    use libp2p_swarm::{StreamProtocol, Swarm};
+    use libp2p_swarm_test::SwarmExt;
+
+    type Behaviour = request_response::cbor::Behaviour<String, String>;
+
+    fn behaviour() -> Behaviour {
+        Behaviour::new(
+            [(
+                StreamProtocol::new("/echo/1"),
+                request_response::ProtocolSupport::Full,
+            )],
+            request_response::Config::default(),
+        )
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+    enum Mode {
+        First(Behaviour),
+        Second(Behaviour),
+    }
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| Mode::First(behaviour()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| behaviour());
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    let peer = *swarm2.local_peer_id();
+
+    // `Second` never saw the connection established under `First`, thus it is closed before
+    // switching.
+    swarm1.disconnect_peer_id(peer).unwrap();
+    loop {
+        futures::select! {
+            event = swarm1.select_next_some() => {
+                if let SwarmEvent::ConnectionClosed { .. } = event {
+                    break;
+                }
+            }
+            _ = swarm2.select_next_some() => {}
+        }
+    }
+    *swarm1.behaviour_mut() = Mode::Second(behaviour());
+
+    // Connections established under `Second` are reported to it.
+    swarm1.connect(&mut swarm2).await;
+    let Mode::Second(behaviour) = swarm1.behaviour_mut() else {
+        unreachable!()
+    };
+    behaviour.send_request(&peer, "ping".to_owned());
+    async_std::task::spawn(async move {
+        loop {
+            if let SwarmEvent::Behaviour(request_response::Event::Message {
+                message: request_response::Message::Request { channel, .. },
+                ..
+            }) = swarm2.select_next_some().await
+            {
+                swarm2
+                    .behaviour_mut()
+                    .send_response(channel, "pong".to_owned())
+                    .unwrap();
+            }
+        }
+    });
+    loop {
+        if let SwarmEvent::Behaviour(ModeEvent::Second(request_response::Event::Message {
+            message: request_response::Message::Response { response, .. },
+            ..
+        })) = swarm1.select_next_some().await
+        {
+            assert_eq!(response, "pong");
+            break;
+        }
+    }
+}
+
+#[test]
+fn custom_event_with_mapped_member_events() {
+    #[allow(dead_code)]
+    enum Event {
+        Ping(Option<std::time::Duration>),
+        Identify(Box<identify::Event>),
+        Other,
+    }
+
+    impl From<void::Void> for Event {
+        fn from(_: void::Void) -> Self {
+            Event::Other
+        }
+    }
+
+    fn rtt(event: ping::Event) -> Option<std::time::Duration> {
+        event.result.ok()
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(to_swarm = "Event", prelude = "libp2p_swarm::derive_prelude")]
+    struct Foo {
+        #[behaviour(map_out_event = "|e| Event::Ping(rtt(e))")]
+        ping: ping::Behaviour,
+        #[behaviour(map_out_event = "|e| Event::Identify(Box::new(e))")]
+        identify: identify::Behaviour,
+        dummy: dummy::Behaviour,
+    }
+
+    #[allow(dead_code)]
+    #[derive(NetworkBehaviour)]
+    #[behaviour(to_swarm = "Event", prelude = "libp2p_swarm::derive_prelude")]
+    enum Bar {
+        Ping(#[behaviour(map_out_event = "rtt")] ping::Behaviour),
+        Other(dummy::Behaviour),
+    }
+
+    impl From<Option<std::time::Duration>> for Event {
+        fn from(rtt: Option<std::time::Duration>) -> Self {
+            Event::Ping(rtt)
+        }
+    }
+
+    require_net_behaviour::<Foo>();
+    require_net_behaviour::<Bar>();
+}
+
+#[async_std::test]
+async fn custom_event_with_mapped_member_events_through_poll() {
+    use libp2p_swarm::Swarm;
+    use libp2p_swarm_test::SwarmExt;
+
+    #[derive(Debug)]
+    enum Event {
+        Rtt(std::time::Duration),
+        Failure,
+    }
+
+    fn on_ping(event: ping::Event) -> Event {
+        match event.result {
+            Ok(rtt) => Event::Rtt(rtt),
+            Err(_) => Event::Failure,
+        }
+    }
+
+    #[derive(NetworkBehaviour)]
+    #[behaviour(to_swarm = "Event", prelude = "libp2p_swarm::derive_prelude")]
+    struct Behaviour {
+        #[behaviour(map_out_event = "on_ping")]
+        ping: ping::Behaviour,
+    }
+
+    let mut client = Swarm::new_ephemeral(|_| Behaviour {
+        ping: ping::Behaviour::default(),
+    });
+    let mut server = Swarm::new_ephemeral(|_| ping::Behaviour::default());
+    server.listen().with_memory_addr_external().await;
+
+    client.connect(&mut server).await;
+    async_std::task::spawn(server.loop_on_next());
+
+    match client.next_behaviour_event().await {
+        Event::Rtt(rtt) => assert!(rtt < std::time::Duration::from_secs(5)),
+        Event::Failure => panic!("Expected ping to succeed"),
+    }
+}
+
 #[test]
 fn ui() {
     let t = trybuild::TestCases::new();
@@ -0,0 +1,180 @@
+use futures::future::poll_fn;
+use futures::StreamExt;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_ping as ping;
+use libp2p_swarm::behaviour::switchable::SwitchableBehaviour;
+use libp2p_swarm::behaviour::DialFailure;
+use libp2p_swarm::{
+    dummy, ConnectionDenied, ConnectionId, DialError, FromSwarm, NetworkBehaviour, Swarm, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_swarm_test::SwarmExt;
+use std::task::{Context, Poll};
+
+#[async_std::test]
+async fn disabling_closes_handlers_but_keeps_connections() {
+    let mut swarm = Swarm::new_ephemeral(|_| SwitchableBehaviour::enabled(Counting::default()));
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+    assert_eq!(swarm.behaviour().get_ref().established.len(), 1);
+
+    swarm.behaviour_mut().disable();
+    drive_until(&mut swarm, |counting| !counting.closed.is_empty()).await;
+
+    let counting = swarm.behaviour().get_ref();
+    assert_eq!(counting.closed, counting.established);
+    assert!(swarm.is_connected(other.local_peer_id()));
+}
+
+#[async_std::test]
+async fn reenabled_behaviour_only_handles_new_connections() {
+    let mut swarm = Swarm::new_ephemeral(|_| SwitchableBehaviour::enabled(Counting::default()));
+    let mut other = Swarm::new_ephemeral(|_| dummy::Behaviour);
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+    swarm.behaviour_mut().disable();
+    drive_until(&mut swarm, |counting| !counting.closed.is_empty()).await;
+
+    swarm.connect(&mut other).await;
+    assert_eq!(
+        swarm.behaviour().get_ref().established.len(),
+        1,
+        "Connections established while disabled are not reported"
+    );
+
+    swarm.behaviour_mut().enable();
+    swarm.connect(&mut other).await;
+
+    let counting = swarm.behaviour().get_ref();
+    assert_eq!(counting.established.len(), 2);
+    assert_eq!(counting.closed.len(), 1);
+}
+
+#[async_std::test]
+async fn disabled_behaviour_denies_inbound_substreams() {
+    let mut swarm =
+        Swarm::new_ephemeral(|_| SwitchableBehaviour::disabled(ping::Behaviour::default()));
+    let mut other = Swarm::new_ephemeral(|_| ping::Behaviour::default());
+    other.listen().with_memory_addr_external().await;
+
+    swarm.connect(&mut other).await;
+    async_std::task::spawn(swarm.loop_on_next());
+
+    let event = other.next_behaviour_event().await;
+    assert!(matches!(event.result, Err(ping::Failure::Unsupported)));
+}
+
+#[async_std::test]
+async fn enabled_behaviour_handles_inbound_substreams() {
+    let mut swarm =
+        Swarm::new_ephemeral(|_| SwitchableBehaviour::disabled(ping::Behaviour::default()));
+    let mut other = Swarm::new_ephemeral(|_| ping::Behaviour::default());
+    other.listen().with_memory_addr_external().await;
+
+    swarm.behaviour_mut().enable();
+    swarm.connect(&mut other).await;
+    async_std::task::spawn(other.loop_on_next());
+
+    let event = swarm.next_behaviour_event().await;
+    assert!(event.result.is_ok());
+}
+
+#[test]
+fn connection_denied_by_other_behaviour_is_forgotten() {
+    let mut behaviour = SwitchableBehaviour::enabled(Counting::default());
+    let addr: Multiaddr = "/memory/1234".parse().unwrap();
+    let connection_id = ConnectionId::new_unchecked(0);
+
+    assert!(behaviour
+        .handle_established_outbound_connection(
+            connection_id,
+            PeerId::random(),
+            &addr,
+            Endpoint::Dialer
+        )
+        .is_ok());
+    // A behaviour composed with `SwitchableBehaviour` denied the connection.
+    behaviour.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+        peer_id: None,
+        error: &DialError::Aborted,
+        connection_id,
+    }));
+
+    // There is no handler left to close.
+    behaviour.disable();
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    assert!(behaviour.poll(&mut cx).is_pending());
+}
+
+/// Polls the swarm until the predicate holds for the wrapped behaviour.
+async fn drive_until<P>(swarm: &mut Swarm<SwitchableBehaviour<Counting>>, predicate: P)
+where
+    P: Fn(&Counting) -> bool,
+{
+    poll_fn(|cx| {
+        while swarm.poll_next_unpin(cx).is_ready() {}
+
+        if predicate(swarm.behaviour().get_ref()) {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// A [`NetworkBehaviour`] recording the connections it is informed about.
+#[derive(Default)]
+struct Counting {
+    established: Vec<ConnectionId>,
+    closed: Vec<ConnectionId>,
+}
+
+impl NetworkBehaviour for Counting {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = void::Void;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(e) => self.established.push(e.connection_id),
+            FromSwarm::ConnectionClosed(e) => self.closed.push(e.connection_id),
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
@@ -0,0 +1,11 @@
+use libp2p_ping as ping;
+
+#[derive(libp2p_swarm::NetworkBehaviour)]
+#[behaviour(crate = "libp2p", prelude = "libp2p_swarm::derive_prelude")]
+struct Foo {
+    ping: ping::Behaviour,
+}
+
+fn main() {
+
+}
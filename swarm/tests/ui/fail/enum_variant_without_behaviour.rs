@@ -0,0 +1,12 @@
+use libp2p_ping as ping;
+
+#[derive(libp2p_swarm::NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+enum Foo {
+    Ping(ping::Behaviour),
+    Off,
+}
+
+fn main() {
+
+}
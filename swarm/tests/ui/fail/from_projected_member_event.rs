@@ -0,0 +1,18 @@
+use libp2p_ping as ping;
+use libp2p_swarm::NetworkBehaviour;
+
+enum FooEvent {
+    Ping(ping::Event),
+}
+
+// The event of a member from another crate is not resolved when checking for overlap with
+// `impl<T> From<T> for T`, thus the macro can not generate this implementation on its own.
+impl From<<ping::Behaviour as NetworkBehaviour>::ToSwarm> for FooEvent {
+    fn from(event: ping::Event) -> Self {
+        FooEvent::Ping(event)
+    }
+}
+
+fn main() {
+
+}
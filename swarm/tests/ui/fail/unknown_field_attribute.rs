@@ -0,0 +1,12 @@
+use libp2p_ping as ping;
+
+#[derive(libp2p_swarm::NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+struct Foo {
+    #[behaviour(as_mut)]
+    ping: ping::Behaviour,
+}
+
+fn main() {
+
+}
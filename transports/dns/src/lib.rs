@@ -156,6 +156,7 @@ use parking_lot::Mutex;
 use smallvec::SmallVec;
 use std::io;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 use std::{
     error, fmt, iter,
     ops::DerefMut,
@@ -245,6 +246,18 @@ where
         self.inner.lock().address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.inner.lock().dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.inner.lock().address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.inner.lock().supports_dial(addr)
+    }
+
     fn poll(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
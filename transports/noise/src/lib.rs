@@ -104,6 +104,20 @@ impl Config {
         })
     }
 
+    /// Like [`Config::new`], signing via [`identity::Keypair::sign_async`].
+    ///
+    /// Required for keypairs that can only sign asynchronously, e.g. those held by WebCrypto.
+    pub async fn new_async(identity: &identity::Keypair) -> Result<Self, Error> {
+        let noise_keys = Keypair::new().into_authentic_async(identity).await?;
+
+        Ok(Self {
+            dh_keys: noise_keys,
+            params: PARAMS_XX.clone(),
+            webtransport_certhashes: None,
+            prologue: vec![],
+        })
+    }
+
     /// Set the noise prologue.
     pub fn with_prologue(mut self, prologue: Vec<u8>) -> Self {
         self.prologue = prologue;
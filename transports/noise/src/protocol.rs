@@ -102,6 +102,26 @@ impl Keypair {
         })
     }
 
+    /// Like [`Keypair::into_authentic`], signing via [`identity::Keypair::sign_async`].
+    pub(crate) async fn into_authentic_async(
+        self,
+        id_keys: &identity::Keypair,
+    ) -> Result<AuthenticKeypair, Error> {
+        let sig = id_keys
+            .sign_async(&[STATIC_KEY_DOMAIN.as_bytes(), self.public.as_ref()].concat())
+            .await?;
+
+        let identity = KeypairIdentity {
+            public: id_keys.public(),
+            signature: sig,
+        };
+
+        Ok(AuthenticKeypair {
+            keypair: self,
+            identity,
+        })
+    }
+
     /// An "empty" keypair as a starting state for DH computations in `snow`,
     /// which get manipulated through the `snow::types::Dh` interface.
     pub(crate) fn empty() -> Self {
@@ -128,6 +128,7 @@ async fn listen_on<B: NetworkBehaviour>(swarm: &mut Swarm<B>, addr: Multiaddr) -
             SwarmEvent::NewListenAddr {
                 address,
                 listener_id,
+                ..
             } if listener_id == expected_listener_id => break address,
             _ => continue,
         };
@@ -19,10 +19,15 @@
 // DEALINGS IN THE SOFTWARE.
 
 use quinn::{
+    congestion::{BbrConfig, CubicConfig, NewRenoConfig},
     crypto::rustls::{QuicClientConfig, QuicServerConfig},
     MtuDiscoveryConfig, VarInt,
 };
-use std::{sync::Arc, time::Duration};
+use socket2::Socket;
+use std::{io, sync::Arc, time::Duration};
+
+/// The minimum max UDP payload size every QUIC path supports, see RFC 9000, section 14.
+const MIN_MTU: u16 = 1200;
 
 /// Config for the transport.
 #[derive(Clone)]
@@ -30,6 +35,11 @@ pub struct Config {
     /// Timeout for the initial handshake when establishing a connection.
     /// The actual timeout is the minimum of this and the [`Config::max_idle_timeout`].
     pub handshake_timeout: Duration,
+    /// Default timeout for dialing an address, enforced by the `Swarm`.
+    ///
+    /// QUIC dials should fail fast: no response usually means UDP is blocked on the path and
+    /// waiting longer won't help. See [`libp2p_core::Transport::dial_timeout`].
+    pub dial_timeout: Option<Duration>,
     /// Maximum duration of inactivity in ms to accept before timing out the connection.
     pub max_idle_timeout: u32,
     /// Period of inactivity before sending a keep-alive packet.
@@ -60,6 +70,32 @@ pub struct Config {
     /// As client the version is chosen based on the remote's address.
     pub support_draft_29: bool,
 
+    /// The congestion control algorithm used for all connections.
+    ///
+    /// Defaults to [`CongestionController::Cubic`].
+    pub congestion_controller: CongestionController,
+
+    /// Whether listeners validate the address of a remote via a retry packet before
+    /// accepting its connection attempt.
+    ///
+    /// Defaults to [`AddressValidation::Disabled`].
+    pub address_validation: AddressValidation,
+
+    /// Minimum interval between the stateless reset packets sent by an endpoint.
+    ///
+    /// Limits the use of an endpoint for amplification attacks flooding it with packets of
+    /// unknown connections. Defaults to 20ms.
+    ///
+    /// See [`quinn::EndpointConfig::min_reset_interval`] for details.
+    pub min_reset_interval: Duration,
+
+    /// Size in bytes of the buffer for incoming unreliable datagrams, or `None` to disable them.
+    ///
+    /// Datagrams can only be sent to remotes with datagrams enabled, see
+    /// [`libp2p_core::muxing::StreamMuxer::max_datagram_size`]. Datagrams received while the
+    /// buffer is full replace the oldest ones. Defaults to `None`.
+    pub datagram_receive_buffer_size: Option<usize>,
+
     /// TLS client config for the inner [`quinn::ClientConfig`].
     client_tls_config: Arc<QuicClientConfig>,
     /// TLS server config for the inner [`quinn::ServerConfig`].
@@ -69,6 +105,75 @@ pub struct Config {
 
     /// Parameters governing MTU discovery. See [`MtuDiscoveryConfig`] for details.
     mtu_discovery_config: Option<MtuDiscoveryConfig>,
+    /// The max UDP payload size used before MTU discovery found a larger one.
+    initial_mtu: u16,
+    /// The max UDP payload size guaranteed to be supported by the network path.
+    min_mtu: u16,
+
+    /// Custom socket configuration applied to all new UDP sockets.
+    pub(crate) socket_config: Option<SocketConfigFn>,
+}
+
+/// Congestion control algorithm of a QUIC connection.
+///
+/// See the [`quinn::congestion`] module for details on the implementations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CongestionController {
+    /// NewReno, as specified in RFC 9002.
+    NewReno,
+    /// CUBIC, as specified in RFC 8312.
+    #[default]
+    Cubic,
+    /// Bottleneck Bandwidth and Round-trip propagation time (BBR).
+    ///
+    /// Can considerably increase throughput on links with a high bandwidth-delay product.
+    /// Note that the implementation of BBR in [`quinn`] is experimental.
+    Bbr,
+}
+
+/// How listeners validate the addresses of remotes attempting to connect, see RFC 9000,
+/// section 8.1.
+///
+/// Until the address of a remote is validated, a listener sends at most three times the data it
+/// received from it, limiting its use for amplification attacks with spoofed source addresses.
+/// Validating the address via a retry packet costs an additional round trip, but lets the
+/// listener defer any per-connection state until the remote proved to receive packets sent to
+/// its address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AddressValidation {
+    /// Accept connection attempts without retry.
+    #[default]
+    Disabled,
+    /// Require a retry for all connection attempts.
+    Always,
+    /// Require a retry once a listener received more than the given number of connection
+    /// attempts with unvalidated addresses within the current second.
+    ///
+    /// Retries only take effect under load, e.g. while the listener is flooded with connection
+    /// attempts.
+    AboveAttemptRate(u32),
+}
+
+/// The purpose a UDP socket is created for, passed to the callback installed via
+/// [`Config::socket_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketRole {
+    /// The socket is bound to a listen address. It is also used for outgoing connections
+    /// to peers of the same IP family.
+    Listen,
+    /// The socket is only used for outgoing connections.
+    Dial,
+}
+
+#[derive(Clone)]
+pub(crate) struct SocketConfigFn(
+    pub(crate) Arc<dyn Fn(&Socket, SocketRole) -> io::Result<()> + Send + Sync>,
+);
+
+impl std::fmt::Debug for SocketConfigFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SocketConfigFn").finish()
+    }
 }
 
 impl Config {
@@ -85,7 +190,12 @@ impl Config {
             client_tls_config,
             server_tls_config,
             support_draft_29: false,
+            congestion_controller: CongestionController::default(),
+            address_validation: AddressValidation::default(),
+            min_reset_interval: Duration::from_millis(20),
+            datagram_receive_buffer_size: None,
             handshake_timeout: Duration::from_secs(5),
+            dial_timeout: Some(Duration::from_secs(5)),
             max_idle_timeout: 10 * 1000,
             max_concurrent_stream_limit: 256,
             keep_alive_interval: Duration::from_secs(5),
@@ -95,6 +205,9 @@ impl Config {
             max_stream_data: 10_000_000,
             keypair: keypair.clone(),
             mtu_discovery_config: Some(Default::default()),
+            initial_mtu: MIN_MTU,
+            min_mtu: MIN_MTU,
+            socket_config: None,
         }
     }
 
@@ -106,11 +219,45 @@ impl Config {
         self
     }
 
+    /// Set the max UDP payload size used from the start of a connection, before MTU discovery
+    /// confirms a larger one. Defaults to 1200, the minimum every QUIC path supports.
+    ///
+    /// A larger value increases the throughput of short connections. If the path does not
+    /// support it, the resulting packet loss is detected as a black hole and the connection
+    /// falls back to [`Config::min_mtu`]. Values below 1200 are raised to 1200.
+    pub fn initial_mtu(mut self, value: u16) -> Self {
+        self.initial_mtu = value.max(MIN_MTU);
+        self
+    }
+
+    /// Set the max UDP payload size guaranteed to be supported by the network path, which
+    /// connections fall back to when a black hole is detected. Defaults to 1200.
+    ///
+    /// Connections fail to be established if the path doesn't support this size, thus prefer
+    /// raising [`Config::initial_mtu`]. Values below 1200 are raised to 1200.
+    pub fn min_mtu(mut self, value: u16) -> Self {
+        self.min_mtu = value.max(MIN_MTU);
+        self
+    }
+
     /// Disable MTU path discovery (it is enabled by default).
     pub fn disable_path_mtu_discovery(mut self) -> Self {
         self.mtu_discovery_config = None;
         self
     }
+
+    /// Configures a callback which is invoked for every new UDP socket before it is bound.
+    ///
+    /// This allows setting socket options like `IP_TOS` for DSCP marking or `SO_MARK` for
+    /// policy routing. An error returned by the callback fails the respective listen or dial
+    /// attempt.
+    pub fn socket_config<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Socket, SocketRole) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.socket_config = Some(SocketConfigFn(Arc::new(f)));
+        self
+    }
 }
 
 /// Represents the inner configuration for [`quinn`].
@@ -132,22 +279,42 @@ impl From<Config> for QuinnConfig {
             max_connection_data,
             max_stream_data,
             support_draft_29,
+            congestion_controller,
+            address_validation: _,
+            min_reset_interval,
+            datagram_receive_buffer_size,
             handshake_timeout: _,
+            dial_timeout: _,
             keypair,
             mtu_discovery_config,
+            initial_mtu,
+            min_mtu,
+            socket_config: _,
         } = config;
         let mut transport = quinn::TransportConfig::default();
         // Disable uni-directional streams.
         transport.max_concurrent_uni_streams(0u32.into());
         transport.max_concurrent_bidi_streams(max_concurrent_stream_limit.into());
-        // Disable datagrams.
-        transport.datagram_receive_buffer_size(None);
+        transport.datagram_receive_buffer_size(datagram_receive_buffer_size);
         transport.keep_alive_interval(Some(keep_alive_interval));
         transport.max_idle_timeout(Some(VarInt::from_u32(max_idle_timeout).into()));
         transport.allow_spin(false);
         transport.stream_receive_window(max_stream_data.into());
         transport.receive_window(max_connection_data.into());
         transport.mtu_discovery_config(mtu_discovery_config);
+        transport.initial_mtu(initial_mtu);
+        transport.min_mtu(min_mtu);
+        match congestion_controller {
+            CongestionController::NewReno => {
+                transport.congestion_controller_factory(Arc::new(NewRenoConfig::default()))
+            }
+            CongestionController::Cubic => {
+                transport.congestion_controller_factory(Arc::new(CubicConfig::default()))
+            }
+            CongestionController::Bbr => {
+                transport.congestion_controller_factory(Arc::new(BbrConfig::default()))
+            }
+        };
         let transport = Arc::new(transport);
 
         let mut server_config = quinn::ServerConfig::with_crypto(server_tls_config);
@@ -171,6 +338,7 @@ impl From<Config> for QuinnConfig {
         if !support_draft_29 {
             endpoint_config.supported_versions(vec![1]);
         }
+        endpoint_config.min_reset_interval(min_reset_interval);
 
         QuinnConfig {
             client_config,
@@ -24,13 +24,15 @@ mod stream;
 pub use connecting::Connecting;
 pub use stream::Stream;
 
-use crate::{ConnectionError, Error};
+use crate::Error;
 
 use futures::{future::BoxFuture, FutureExt};
-use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent};
+use libp2p_core::muxing::{CloseReason, DatagramError, StreamMuxer, StreamMuxerEvent};
 use std::{
+    io,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 /// State for a single opened QUIC connection.
@@ -45,8 +47,14 @@ pub struct Connection {
     outgoing: Option<
         BoxFuture<'static, Result<(quinn::SendStream, quinn::RecvStream), quinn::ConnectionError>>,
     >,
+    /// Whether receiving datagrams is enabled locally.
+    datagrams: bool,
+    /// Future for receiving the next datagram.
+    datagram: Option<BoxFuture<'static, Result<bytes::Bytes, quinn::ConnectionError>>>,
     /// Future to wait for the connection to be closed.
     closing: Option<BoxFuture<'static, quinn::ConnectionError>>,
+    /// The reason sent to the remote when closing the connection.
+    close_reason: Option<CloseReason>,
 }
 
 impl Connection {
@@ -54,16 +62,47 @@ impl Connection {
     ///
     /// This function assumes that the [`quinn::Connection`] is completely fresh and none of
     /// its methods has ever been called. Failure to comply might lead to logic errors and panics.
-    fn new(connection: quinn::Connection) -> Self {
+    fn new(connection: quinn::Connection, datagrams: bool) -> Self {
         Self {
             connection,
+            datagrams,
             incoming: None,
             outgoing: None,
+            datagram: None,
             closing: None,
+            close_reason: None,
+        }
+    }
+
+    /// Returns the current statistics of the connection's path.
+    pub fn stats(&self) -> ConnectionStats {
+        let path = self.connection.stats().path;
+        ConnectionStats {
+            rtt: path.rtt,
+            current_mtu: path.current_mtu,
+            black_holes_detected: path.black_holes_detected,
+            sent_packets: path.sent_packets,
+            lost_packets: path.lost_packets,
         }
     }
 }
 
+/// Statistics of the path of a [`Connection`], see [`Connection::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Current estimate of the round-trip time.
+    pub rtt: Duration,
+    /// Largest UDP payload size the path currently supports, as found by MTU discovery.
+    pub current_mtu: u16,
+    /// Number of times packet loss was detected as a black hole, making the connection fall
+    /// back to [`Config::min_mtu`](crate::Config::min_mtu).
+    pub black_holes_detected: u64,
+    /// Number of packets sent on the path.
+    pub sent_packets: u64,
+    /// Number of packets lost on the path.
+    pub lost_packets: u64,
+}
+
 impl StreamMuxer for Connection {
     type Substream = Stream;
     type Error = Error;
@@ -79,7 +118,7 @@ impl StreamMuxer for Connection {
             async move { connection.accept_bi().await }.boxed()
         });
 
-        let (send, recv) = futures::ready!(incoming.poll_unpin(cx)).map_err(ConnectionError)?;
+        let (send, recv) = futures::ready!(incoming.poll_unpin(cx))?;
         this.incoming.take();
         let stream = Stream::new(send, recv);
         Poll::Ready(Ok(stream))
@@ -96,7 +135,7 @@ impl StreamMuxer for Connection {
             async move { connection.open_bi().await }.boxed()
         });
 
-        let (send, recv) = futures::ready!(outgoing.poll_unpin(cx)).map_err(ConnectionError)?;
+        let (send, recv) = futures::ready!(outgoing.poll_unpin(cx))?;
         this.outgoing.take();
         let stream = Stream::new(send, recv);
         Poll::Ready(Ok(stream))
@@ -115,7 +154,9 @@ impl StreamMuxer for Connection {
         let this = self.get_mut();
 
         let closing = this.closing.get_or_insert_with(|| {
-            this.connection.close(From::from(0u32), &[]);
+            let CloseReason { code, reason } = this.close_reason.take().unwrap_or_default();
+            let code = quinn::VarInt::from_u64(code).unwrap_or(quinn::VarInt::MAX);
+            this.connection.close(code, &reason);
             let connection = this.connection.clone();
             async move { connection.closed().await }.boxed()
         });
@@ -123,9 +164,53 @@ impl StreamMuxer for Connection {
         match futures::ready!(closing.poll_unpin(cx)) {
             // Expected error given that `connection.close` was called above.
             quinn::ConnectionError::LocallyClosed => {}
-            error => return Poll::Ready(Err(Error::Connection(ConnectionError(error)))),
+            error => return Poll::Ready(Err(error.into())),
         };
 
         Poll::Ready(Ok(()))
     }
+
+    fn set_close_reason(self: Pin<&mut Self>, reason: CloseReason) {
+        self.get_mut().close_reason = Some(reason);
+    }
+
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.connection.max_datagram_size()
+    }
+
+    fn send_datagram(self: Pin<&mut Self>, data: Vec<u8>) -> Result<(), DatagramError> {
+        self.connection
+            .send_datagram(data.into())
+            .map_err(|e| match e {
+                quinn::SendDatagramError::UnsupportedByPeer
+                | quinn::SendDatagramError::Disabled => DatagramError::Unsupported,
+                quinn::SendDatagramError::TooLarge => DatagramError::TooLarge,
+                quinn::SendDatagramError::ConnectionLost(e) => connection_lost(e),
+            })
+    }
+
+    fn poll_datagram(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Vec<u8>, DatagramError>> {
+        let this = self.get_mut();
+        // Otherwise `quinn` never yields a datagram until the connection is lost.
+        if !this.datagrams {
+            return Poll::Ready(Err(DatagramError::Unsupported));
+        }
+
+        let datagram = this.datagram.get_or_insert_with(|| {
+            let connection = this.connection.clone();
+            async move { connection.read_datagram().await }.boxed()
+        });
+
+        let result = futures::ready!(datagram.poll_unpin(cx));
+        this.datagram.take();
+        let datagram = result.map_err(connection_lost)?;
+        Poll::Ready(Ok(datagram.into()))
+    }
+}
+
+fn connection_lost(error: quinn::ConnectionError) -> DatagramError {
+    DatagramError::Connection(io::Error::new(io::ErrorKind::Other, Error::from(error)))
 }
@@ -63,16 +63,18 @@ mod hole_punching;
 mod provider;
 mod transport;
 
+use libp2p_core::muxing::CloseReason;
 use std::net::SocketAddr;
 
-pub use config::Config;
-pub use connection::{Connecting, Connection, Stream};
+pub use config::{AddressValidation, Config, CongestionController, SocketRole};
+pub use connection::{Connecting, Connection, ConnectionStats, Stream};
 
 #[cfg(feature = "async-std")]
 pub use provider::async_std;
 #[cfg(feature = "tokio")]
 pub use provider::tokio;
 pub use provider::Provider;
+pub use socket2::Socket;
 pub use transport::GenTransport;
 
 /// Errors that may happen on the [`GenTransport`] or a single [`Connection`].
@@ -101,6 +103,24 @@ pub enum Error {
     /// Error when holepunching for a remote is already in progress
     #[error("Already punching hole for {0}).")]
     HolePunchInProgress(SocketAddr),
+
+    /// The remote closed the connection with an application error code.
+    #[error("Connection closed by the remote application.")]
+    ApplicationClosed(#[source] CloseReason),
+}
+
+impl From<quinn::ConnectionError> for Error {
+    fn from(error: quinn::ConnectionError) -> Self {
+        match error {
+            quinn::ConnectionError::ApplicationClosed(quinn::ApplicationClose {
+                error_code,
+                reason,
+            }) => {
+                Error::ApplicationClosed(CloseReason::new(error_code.into_inner(), reason.to_vec()))
+            }
+            error => Error::Connection(ConnectionError(error)),
+        }
+    }
 }
 
 /// Dialing a remote peer failed.
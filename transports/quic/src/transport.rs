@@ -18,10 +18,10 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::config::{Config, QuinnConfig};
+use crate::config::{AddressValidation, Config, QuinnConfig, SocketConfigFn};
 use crate::hole_punching::hole_puncher;
 use crate::provider::Provider;
-use crate::{ConnectError, Connecting, Connection, Error};
+use crate::{ConnectError, Connecting, Connection, Error, SocketRole};
 
 use futures::channel::oneshot;
 use futures::future::{BoxFuture, Either};
@@ -42,7 +42,7 @@ use std::collections::hash_map::{DefaultHasher, Entry};
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fmt, io};
 use std::{
     net::SocketAddr,
@@ -67,6 +67,12 @@ pub struct GenTransport<P: Provider> {
     quinn_config: QuinnConfig,
     /// Timeout for the [`Connecting`] future.
     handshake_timeout: Duration,
+    /// Whether receiving datagrams is enabled, see [`Config::datagram_receive_buffer_size`].
+    datagrams: bool,
+    /// Default dial timeout, see [`Config::dial_timeout`].
+    dial_timeout: Option<Duration>,
+    /// Address validation of listeners, see [`Config::address_validation`].
+    address_validation: AddressValidation,
     /// Whether draft-29 is supported for dialing and listening.
     support_draft_29: bool,
     /// Streams of active [`Listener`]s.
@@ -77,22 +83,32 @@ pub struct GenTransport<P: Provider> {
     waker: Option<Waker>,
     /// Holepunching attempts
     hole_punch_attempts: HashMap<SocketAddr, oneshot::Sender<Connecting>>,
+    /// Custom configuration applied to new sockets.
+    socket_config: Option<SocketConfigFn>,
 }
 
 impl<P: Provider> GenTransport<P> {
     /// Create a new [`GenTransport`] with the given [`Config`].
     pub fn new(config: Config) -> Self {
         let handshake_timeout = config.handshake_timeout;
+        let datagrams = config.datagram_receive_buffer_size.is_some();
+        let dial_timeout = config.dial_timeout;
+        let address_validation = config.address_validation;
         let support_draft_29 = config.support_draft_29;
+        let socket_config = config.socket_config.clone();
         let quinn_config = config.into();
         Self {
             listeners: SelectAll::new(),
             quinn_config,
             handshake_timeout,
+            datagrams,
+            dial_timeout,
+            address_validation,
             dialer: HashMap::new(),
             waker: None,
             support_draft_29,
             hole_punch_attempts: Default::default(),
+            socket_config,
         }
     }
 
@@ -181,7 +197,11 @@ impl<P: Provider> GenTransport<P> {
         }
     }
 
-    fn create_socket(&self, socket_addr: SocketAddr) -> io::Result<UdpSocket> {
+    fn create_socket(
+        socket_config: Option<&SocketConfigFn>,
+        socket_addr: SocketAddr,
+        role: SocketRole,
+    ) -> io::Result<UdpSocket> {
         let socket = Socket::new(
             Domain::for_address(socket_addr),
             Type::DGRAM,
@@ -190,6 +210,9 @@ impl<P: Provider> GenTransport<P> {
         if socket_addr.is_ipv6() {
             socket.set_only_v6(true)?;
         }
+        if let Some(SocketConfigFn(f)) = socket_config {
+            f(&socket, role)?;
+        }
 
         socket.bind(&socket_addr.into())?;
 
@@ -211,7 +234,9 @@ impl<P: Provider> Transport for GenTransport<P> {
         let (socket_addr, version, _peer_id) = self.remote_multiaddr_to_socketaddr(addr, false)?;
         let endpoint_config = self.quinn_config.endpoint_config.clone();
         let server_config = self.quinn_config.server_config.clone();
-        let socket = self.create_socket(socket_addr).map_err(Self::Error::from)?;
+        let socket =
+            Self::create_socket(self.socket_config.as_ref(), socket_addr, SocketRole::Listen)
+                .map_err(Self::Error::from)?;
 
         let socket_c = socket.try_clone().map_err(Self::Error::from)?;
         let endpoint = Self::new_endpoint(endpoint_config, Some(server_config), socket)?;
@@ -220,6 +245,8 @@ impl<P: Provider> Transport for GenTransport<P> {
             socket_c,
             endpoint,
             self.handshake_timeout,
+            self.datagrams,
+            self.address_validation,
             version,
         )?;
         self.listeners.push(listener);
@@ -256,6 +283,17 @@ impl<P: Provider> Transport for GenTransport<P> {
         Some(observed.clone())
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        if !is_quic_addr(addr, self.support_draft_29) {
+            return None;
+        }
+        self.dial_timeout
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        is_quic_addr(addr, self.support_draft_29)
+    }
+
     fn dial(&mut self, addr: Multiaddr) -> Result<Self::Dial, TransportError<Self::Error>> {
         let (socket_addr, version, _peer_id) = self.remote_multiaddr_to_socketaddr(addr, true)?;
 
@@ -273,8 +311,12 @@ impl<P: Provider> Transport for GenTransport<P> {
                             SocketFamily::Ipv4 => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
                             SocketFamily::Ipv6 => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
                         };
-                        let socket =
-                            UdpSocket::bind(listen_socket_addr).map_err(Self::Error::from)?;
+                        let socket = Self::create_socket(
+                            self.socket_config.as_ref(),
+                            listen_socket_addr,
+                            SocketRole::Dial,
+                        )
+                        .map_err(Self::Error::from)?;
                         let endpoint_config = self.quinn_config.endpoint_config.clone();
                         let endpoint = Self::new_endpoint(endpoint_config, None, socket)?;
 
@@ -287,6 +329,7 @@ impl<P: Provider> Transport for GenTransport<P> {
             Some(listener) => listener.endpoint.clone(),
         };
         let handshake_timeout = self.handshake_timeout;
+        let datagrams = self.datagrams;
         let mut client_config = self.quinn_config.client_config.clone();
         if version == ProtocolVersion::Draft29 {
             client_config.version(0xff00_001d);
@@ -298,7 +341,7 @@ impl<P: Provider> Transport for GenTransport<P> {
             let connecting = endpoint
                 .connect_with(client_config, socket_addr, "l")
                 .map_err(ConnectError)?;
-            Connecting::new(connecting, handshake_timeout).await
+            Connecting::new(connecting, handshake_timeout, datagrams).await
         }))
     }
 
@@ -428,6 +471,12 @@ struct Listener<P: Provider> {
     accept: BoxFuture<'static, Option<quinn::Incoming>>,
     /// Timeout for connection establishment on inbound connections.
     handshake_timeout: Duration,
+    /// Whether receiving datagrams is enabled, see [`Config::datagram_receive_buffer_size`].
+    datagrams: bool,
+    /// Whether to validate the addresses of remotes via a retry before accepting them.
+    address_validation: AddressValidation,
+    /// Connection attempts with unvalidated addresses within the current second.
+    attempts: AttemptCounter,
 
     /// Watcher for network interface changes.
     ///
@@ -452,6 +501,8 @@ impl<P: Provider> Listener<P> {
         socket: UdpSocket,
         endpoint: quinn::Endpoint,
         handshake_timeout: Duration,
+        datagrams: bool,
+        address_validation: AddressValidation,
         version: ProtocolVersion,
     ) -> Result<Self, Error> {
         let if_watcher;
@@ -481,6 +532,9 @@ impl<P: Provider> Listener<P> {
             listener_id,
             version,
             handshake_timeout,
+            datagrams,
+            address_validation,
+            attempts: AttemptCounter::default(),
             if_watcher,
             is_closed: false,
             pending_event,
@@ -508,6 +562,19 @@ impl<P: Provider> Listener<P> {
         }
     }
 
+    /// Whether to answer a connection attempt with a retry packet, requiring the remote to
+    /// validate its address.
+    fn requires_retry(&mut self, incoming: &quinn::Incoming) -> bool {
+        if incoming.remote_address_validated() {
+            return false;
+        }
+        match self.address_validation {
+            AddressValidation::Disabled => false,
+            AddressValidation::Always => true,
+            AddressValidation::AboveAttemptRate(max) => self.attempts.record(Instant::now()) > max,
+        }
+    }
+
     /// Clone underlying socket (for hole punching).
     fn try_clone_socket(&self) -> std::io::Result<UdpSocket> {
         self.socket.try_clone()
@@ -587,6 +654,22 @@ impl<P: Provider> Stream for Listener<P> {
                     let endpoint = self.endpoint.clone();
                     self.accept = async move { endpoint.accept().await }.boxed();
 
+                    let incoming = if self.requires_retry(&incoming) {
+                        let remote_addr = incoming.remote_address();
+                        match incoming.retry() {
+                            Ok(()) => {
+                                tracing::debug!(
+                                    address=%remote_addr,
+                                    "Requiring address validation via retry"
+                                );
+                                continue;
+                            }
+                            Err(e) => e.into_incoming(),
+                        }
+                    } else {
+                        incoming
+                    };
+
                     let connecting = match incoming.accept() {
                         Ok(connecting) => connecting,
                         Err(error) => {
@@ -602,7 +685,11 @@ impl<P: Provider> Stream for Listener<P> {
                     let send_back_addr = socketaddr_to_multiaddr(&remote_addr, self.version);
 
                     let event = TransportEvent::Incoming {
-                        upgrade: Connecting::new(connecting, self.handshake_timeout),
+                        upgrade: Connecting::new(
+                            connecting,
+                            self.handshake_timeout,
+                            self.datagrams,
+                        ),
                         local_addr,
                         send_back_addr,
                         listener_id: self.listener_id,
@@ -628,12 +715,35 @@ impl<P: Provider> fmt::Debug for Listener<P> {
         f.debug_struct("Listener")
             .field("listener_id", &self.listener_id)
             .field("handshake_timeout", &self.handshake_timeout)
+            .field("address_validation", &self.address_validation)
             .field("is_closed", &self.is_closed)
             .field("pending_event", &self.pending_event)
             .finish()
     }
 }
 
+/// Counts connection attempts within windows of one second.
+#[derive(Debug, Default)]
+struct AttemptCounter {
+    window_start: Option<Instant>,
+    attempts: u32,
+}
+
+impl AttemptCounter {
+    /// Records an attempt, returning the number of attempts within the current window.
+    fn record(&mut self, now: Instant) -> u32 {
+        match self.window_start {
+            Some(start) if now.duration_since(start) < Duration::from_secs(1) => {}
+            _ => {
+                self.window_start = Some(now);
+                self.attempts = 0;
+            }
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        self.attempts
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum ProtocolVersion {
     V1, // i.e. RFC9000
@@ -767,6 +877,17 @@ mod tests {
     use super::*;
     use futures::future::poll_fn;
 
+    #[test]
+    fn attempt_counter_resets_every_second() {
+        let mut counter = AttemptCounter::default();
+        let start = Instant::now();
+
+        assert_eq!(counter.record(start), 1);
+        assert_eq!(counter.record(start + Duration::from_millis(500)), 2);
+        assert_eq!(counter.record(start + Duration::from_millis(999)), 3);
+        assert_eq!(counter.record(start + Duration::from_secs(1)), 1);
+    }
+
     #[test]
     fn multiaddr_to_udp_conversion() {
         assert!(multiaddr_to_socketaddr(
@@ -961,4 +1082,34 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn socket_config_is_applied_to_listen_and_dial_sockets() {
+        let roles = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let keypair = libp2p_identity::Keypair::generate_ed25519();
+        let config = Config::new(&keypair).socket_config({
+            let roles = roles.clone();
+            move |_, role| {
+                roles.lock().unwrap().push(role);
+                Ok(())
+            }
+        });
+        let mut transport = crate::tokio::Transport::new(config);
+
+        let _dial = transport
+            .dial("/ip4/123.45.67.8/udp/1234/quic-v1".parse().unwrap())
+            .unwrap();
+        transport
+            .listen_on(
+                ListenerId::next(),
+                "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            *roles.lock().unwrap(),
+            vec![SocketRole::Dial, SocketRole::Listen]
+        );
+    }
 }
@@ -6,7 +6,9 @@ use futures::future::{poll_fn, Either};
 use futures::stream::StreamExt;
 use futures::{future, AsyncReadExt, AsyncWriteExt, FutureExt, SinkExt};
 use futures_timer::Delay;
-use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerExt, SubstreamBox};
+use libp2p_core::muxing::{
+    CloseReason, DatagramError, StreamMuxer, StreamMuxerBox, StreamMuxerExt, SubstreamBox,
+};
 use libp2p_core::transport::{Boxed, OrTransport, TransportEvent};
 use libp2p_core::transport::{ListenerId, TransportError};
 use libp2p_core::{multiaddr::Protocol, upgrade, Multiaddr, Transport};
@@ -67,6 +69,80 @@ async fn endpoint_reuse() {
     assert_eq!(a_send_back_addr, a_addr);
 }
 
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn close_reason_is_propagated() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let with_config = |cfg: &mut quic::Config| cfg.datagram_receive_buffer_size = Some(64 * 1024);
+    let (_, mut a_transport) = create_transport::<quic::tokio::Provider>(with_config);
+    let (_, mut b_transport) = create_transport::<quic::tokio::Provider>(with_config);
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let ((_, _, mut a_connection), (_, mut b_connection)) =
+        connect(&mut a_transport, &mut b_transport, a_addr).await;
+
+    let reason = CloseReason::new(42, "going away");
+    Pin::new(&mut a_connection).set_close_reason(reason.clone());
+    a_connection.close().await.unwrap();
+
+    let error = poll_fn(|cx| b_connection.poll_inbound_unpin(cx))
+        .await
+        .unwrap_err();
+    assert_eq!(CloseReason::from_error(&error), Some(&reason));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn datagrams() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let with_config = |cfg: &mut quic::Config| cfg.datagram_receive_buffer_size = Some(64 * 1024);
+    let (_, mut a_transport) = create_transport::<quic::tokio::Provider>(with_config);
+    let (_, mut b_transport) = create_transport::<quic::tokio::Provider>(with_config);
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let ((_, _, mut a_connection), (_, mut b_connection)) =
+        connect(&mut a_transport, &mut b_transport, a_addr).await;
+
+    let max_size = a_connection
+        .max_datagram_size()
+        .expect("QUIC to support datagrams");
+    Pin::new(&mut a_connection)
+        .send_datagram(b"ping".to_vec())
+        .unwrap();
+    let datagram = poll_fn(|cx| b_connection.poll_datagram_unpin(cx))
+        .await
+        .unwrap();
+    assert_eq!(datagram, b"ping");
+
+    assert!(matches!(
+        Pin::new(&mut a_connection).send_datagram(vec![0; max_size + 1]),
+        Err(DatagramError::TooLarge)
+    ));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn datagrams_disabled() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let (_, mut a_transport) = create_default_transport::<quic::tokio::Provider>();
+    let (_, mut b_transport) = create_default_transport::<quic::tokio::Provider>();
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let ((_, _, mut a_connection), _) = connect(&mut a_transport, &mut b_transport, a_addr).await;
+
+    assert!(a_connection.max_datagram_size().is_none());
+    assert!(matches!(
+        poll_fn(|cx| a_connection.poll_datagram_unpin(cx)).await,
+        Err(DatagramError::Unsupported)
+    ));
+}
+
 #[cfg(feature = "async-std")]
 #[async_std::test]
 async fn ipv4_dial_ipv6() {
@@ -256,6 +332,199 @@ async fn tcp_and_quic() {
     assert_eq!(b_connected, a_peer_id);
 }
 
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn congestion_controllers() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    for controller in [
+        quic::CongestionController::NewReno,
+        quic::CongestionController::Cubic,
+        quic::CongestionController::Bbr,
+    ] {
+        let (a_peer_id, mut a_transport) =
+            create_transport::<quic::tokio::Provider>(|cfg| cfg.congestion_controller = controller);
+        let (b_peer_id, mut b_transport) =
+            create_transport::<quic::tokio::Provider>(|cfg| cfg.congestion_controller = controller);
+
+        let addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+        let ((a_connected, _, _), (b_connected, _)) =
+            connect(&mut a_transport, &mut b_transport, addr).await;
+
+        assert_eq!(a_connected, b_peer_id);
+        assert_eq!(b_connected, a_peer_id);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn address_validation() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    for (address_validation, expected_retries) in [
+        (quic::AddressValidation::Disabled, 0),
+        (quic::AddressValidation::Always, 1),
+        (quic::AddressValidation::AboveAttemptRate(0), 1),
+    ] {
+        let (a_peer_id, mut a_transport) = create_transport::<quic::tokio::Provider>(|cfg| {
+            cfg.address_validation = address_validation
+        });
+        let (b_peer_id, mut b_transport) = create_default_transport::<quic::tokio::Provider>();
+
+        let addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+        let (relay_addr, retries) = spawn_retry_counting_relay(&addr).await;
+        let ((a_connected, _, _), (b_connected, _)) =
+            connect(&mut a_transport, &mut b_transport, relay_addr).await;
+
+        assert_eq!(a_connected, b_peer_id);
+        assert_eq!(b_connected, a_peer_id);
+        assert_eq!(
+            retries.load(std::sync::atomic::Ordering::SeqCst),
+            expected_retries,
+            "{address_validation:?}"
+        );
+    }
+}
+
+/// Relays the datagrams between a single dialer and the QUIC listener at `listen_addr`,
+/// counting the Retry packets sent by the listener.
+///
+/// Returns the address to dial the listener through the relay.
+#[cfg(feature = "tokio")]
+async fn spawn_retry_counting_relay(
+    listen_addr: &Multiaddr,
+) -> (Multiaddr, Arc<std::sync::atomic::AtomicUsize>) {
+    let listener = match (listen_addr.iter().next(), listen_addr.iter().nth(1)) {
+        (Some(Protocol::Ip4(ip)), Some(Protocol::Udp(port))) => {
+            std::net::SocketAddr::from((ip, port))
+        }
+        _ => panic!("Unexpected listen address {listen_addr}"),
+    };
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let relay_addr = format!(
+        "/ip4/127.0.0.1/udp/{}/quic-v1",
+        socket.local_addr().unwrap().port()
+    )
+    .parse()
+    .unwrap();
+
+    let retries = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let retries_clone = retries.clone();
+    tokio::spawn(async move {
+        let mut dialer = None;
+        let mut buf = vec![0; 65536];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+            let packet = &buf[..n];
+            if from != listener {
+                dialer = Some(from);
+                let _ = socket.send_to(packet, listener).await;
+                continue;
+            }
+            // A QUIC v1 long header packet of type Retry.
+            if n > 5 && packet[0] & 0xf0 == 0xf0 && packet[1..5] == [0, 0, 0, 1] {
+                retries_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            if let Some(dialer) = dialer {
+                let _ = socket.send_to(packet, dialer).await;
+            }
+        }
+    });
+
+    (relay_addr, retries)
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn initial_mtu() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    for initial_mtu in [1200, 1400] {
+        // Without MTU discovery, the initial MTU remains in use.
+        let new_transport = || {
+            let config = quic::Config::new(&generate_tls_keypair())
+                .initial_mtu(initial_mtu)
+                .disable_path_mtu_discovery();
+            quic::tokio::Transport::new(config).boxed()
+        };
+        let mut a_transport = new_transport();
+        let mut b_transport = new_transport();
+
+        a_transport
+            .listen_on(
+                ListenerId::next(),
+                "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap(),
+            )
+            .unwrap();
+        let addr = match a_transport.next().await {
+            Some(TransportEvent::NewAddress { listen_addr, .. }) => listen_addr,
+            e => panic!("{e:?}"),
+        };
+        let ((_, a_connection), _) = future::join(
+            async {
+                let (upgrade, _) = a_transport
+                    .select_next_some()
+                    .await
+                    .into_incoming()
+                    .unwrap();
+                upgrade.await.unwrap()
+            },
+            async { b_transport.dial(addr).unwrap().await.unwrap() },
+        )
+        .await;
+
+        assert_eq!(a_connection.stats().current_mtu, initial_mtu);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn connection_stats() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let new_transport = || {
+        let config = quic::Config::new(&generate_tls_keypair()).initial_mtu(1300);
+        quic::tokio::Transport::new(config).boxed()
+    };
+    let mut a_transport = new_transport();
+    let mut b_transport = new_transport();
+
+    a_transport
+        .listen_on(
+            ListenerId::next(),
+            "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap(),
+        )
+        .unwrap();
+    let addr = match a_transport.next().await {
+        Some(TransportEvent::NewAddress { listen_addr, .. }) => listen_addr,
+        e => panic!("{e:?}"),
+    };
+    let (_, (_, b_connection)) = future::join(
+        async {
+            let (upgrade, _) = a_transport
+                .select_next_some()
+                .await
+                .into_incoming()
+                .unwrap();
+            upgrade.await.unwrap()
+        },
+        async { b_transport.dial(addr).unwrap().await.unwrap() },
+    )
+    .await;
+
+    let stats = b_connection.stats();
+    assert!(stats.current_mtu >= 1300);
+    assert!(stats.sent_packets > 0);
+}
+
 // Note: This test should likely be ported to the muxer compliance test suite.
 #[cfg(feature = "async-std")]
 #[test]
@@ -29,12 +29,14 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod provider;
+mod rate_limit;
 
 #[cfg(feature = "async-io")]
 pub use provider::async_io;
 
 #[cfg(feature = "tokio")]
 pub use provider::tokio;
+pub use socket2::Socket;
 
 use futures::{future::Ready, prelude::*, stream::SelectAll};
 use futures_timer::Delay;
@@ -45,11 +47,13 @@ use libp2p_core::{
     transport::{ListenerId, TransportError, TransportEvent},
 };
 use provider::{Incoming, Provider};
-use socket2::{Domain, Socket, Type};
+use rate_limit::{AcceptLimiter, AcceptRateLimit};
+use socket2::{Domain, Type};
 use std::{
     collections::{HashSet, VecDeque},
-    io,
+    error, fmt, io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener},
+    num::NonZeroU32,
     pin::Pin,
     sync::{Arc, RwLock},
     task::{Context, Poll, Waker},
@@ -65,8 +69,33 @@ pub struct Config {
     nodelay: Option<bool>,
     /// Size of the listen backlog for listen sockets.
     backlog: u32,
+    /// Maximum rate at which each listener accepts connections, if any.
+    accept_rate_limit: Option<AcceptRateLimit>,
     /// Whether port reuse should be enabled.
     enable_port_reuse: bool,
+    /// Custom socket configuration applied to all new sockets.
+    socket_config: Option<SocketConfigFn>,
+    /// Default timeout for dialing an address.
+    dial_timeout: Option<Duration>,
+}
+
+/// The purpose a socket is created for, passed to the callback installed via
+/// [`Config::socket_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketRole {
+    /// The socket is used to listen for incoming connections.
+    Listen,
+    /// The socket is used for an outgoing connection.
+    Dial,
+}
+
+#[derive(Clone)]
+struct SocketConfigFn(Arc<dyn Fn(&Socket, SocketRole) -> io::Result<()> + Send + Sync>);
+
+impl std::fmt::Debug for SocketConfigFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SocketConfigFn").finish()
+    }
 }
 
 type Port = u16;
@@ -150,7 +179,7 @@ impl PortReuse {
 impl Config {
     /// Creates a new configuration for a TCP/IP transport:
     ///
-    ///   * Nagle's algorithm, i.e. `TCP_NODELAY`, is _enabled_.
+    ///   * Nagle's algorithm is _disabled_, i.e. `TCP_NODELAY` is set.
     ///     See [`Config::nodelay`].
     ///   * Reuse of listening ports is _disabled_.
     ///     See [`Config::port_reuse`].
@@ -158,12 +187,19 @@ impl Config {
     ///     See [`Config::ttl`].
     ///   * The size of the listen backlog for new listening sockets is `1024`.
     ///     See [`Config::listen_backlog`].
+    ///   * Connections are accepted as fast as they come in.
+    ///     See [`Config::accept_rate_limit`].
+    ///   * No dial timeout is set. The connect timeout of the OS TCP stack applies.
+    ///     See [`Config::dial_timeout`].
     pub fn new() -> Self {
         Self {
             ttl: None,
-            nodelay: None,
+            nodelay: Some(true),
             backlog: 1024,
+            accept_rate_limit: None,
             enable_port_reuse: false,
+            socket_config: None,
+            dial_timeout: None,
         }
     }
 
@@ -174,17 +210,73 @@ impl Config {
     }
 
     /// Configures the `TCP_NODELAY` option for new sockets.
+    ///
+    /// Libp2p protocols mostly exchange small, latency-sensitive messages and the multiplexers
+    /// already batch writes, thus Nagle's algorithm is disabled by default.
     pub fn nodelay(mut self, value: bool) -> Self {
         self.nodelay = Some(value);
         self
     }
 
     /// Configures the listen backlog for new listen sockets.
+    ///
+    /// Connections exceeding the [`Config::accept_rate_limit`] queue up in the backlog. Once it
+    /// is full, the OS refuses or drops new connection attempts.
     pub fn listen_backlog(mut self, backlog: u32) -> Self {
         self.backlog = backlog;
         self
     }
 
+    /// Limits the rate at which each listener accepts incoming connections to `per_second`,
+    /// allowing bursts of up to `burst` connections.
+    ///
+    /// This keeps a SYN flood or a storm of reconnecting peers from starving the event loop.
+    /// Connections exceeding the limit remain in the [listen backlog](Config::listen_backlog)
+    /// until the listener accepts connections again. Whenever a listener starts throttling, a
+    /// [`TransportEvent::ListenerError`] with an [`AcceptRateExceeded`] error is reported.
+    pub fn accept_rate_limit(mut self, per_second: NonZeroU32, burst: NonZeroU32) -> Self {
+        self.accept_rate_limit = Some(AcceptRateLimit { per_second, burst });
+        self
+    }
+
+    /// Configures the default timeout for dialing an address.
+    ///
+    /// The timeout is enforced by the `Swarm`, see
+    /// [`Transport::dial_timeout`](libp2p_core::Transport::dial_timeout).
+    pub fn dial_timeout(mut self, timeout: Duration) -> Self {
+        self.dial_timeout = Some(timeout);
+        self
+    }
+
+    /// Configures a callback which is invoked for every new socket, after the options of this
+    /// [`Config`] have been applied and before the socket is bound or connected.
+    ///
+    /// This allows setting socket options not covered by [`Config`], e.g. `IP_TOS` for DSCP
+    /// marking or `SO_MARK` for policy routing. An error returned by the callback fails the
+    /// respective listen or dial attempt.
+    ///
+    /// ```
+    /// # #[cfg(target_os = "linux")]
+    /// # {
+    /// use libp2p_tcp::{Config, SocketRole};
+    ///
+    /// let config = Config::new().socket_config(|socket, role| {
+    ///     // Mark outgoing traffic as `AF41`.
+    ///     if role == SocketRole::Dial {
+    ///         socket.set_tos(0x22 << 2)?;
+    ///     }
+    ///     socket.set_mark(42)
+    /// });
+    /// # }
+    /// ```
+    pub fn socket_config<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Socket, SocketRole) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.socket_config = Some(SocketConfigFn(Arc::new(f)));
+        self
+    }
+
     /// Configures port reuse for local sockets, which implies
     /// reuse of listening ports for outgoing connections to
     /// enhance NAT traversal capabilities.
@@ -292,6 +384,24 @@ impl Default for Config {
     }
 }
 
+/// The error of the [`TransportEvent::ListenerError`] reported when a listener starts
+/// throttling incoming connections, see [`Config::accept_rate_limit`].
+///
+/// It is wrapped in an [`io::Error`] and can be recovered via [`io::Error::get_ref`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptRateExceeded;
+
+impl fmt::Display for AcceptRateExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Accept rate limit exceeded, throttling incoming connections"
+        )
+    }
+}
+
+impl error::Error for AcceptRateExceeded {}
+
 /// An abstract [`libp2p_core::Transport`] implementation.
 ///
 /// You shouldn't need to use this type directly. Use one of the following instead:
@@ -342,7 +452,7 @@ where
         }
     }
 
-    fn create_socket(&self, socket_addr: SocketAddr) -> io::Result<Socket> {
+    fn create_socket(&self, socket_addr: SocketAddr, role: SocketRole) -> io::Result<Socket> {
         let socket = Socket::new(
             Domain::for_address(socket_addr),
             Type::STREAM,
@@ -362,6 +472,9 @@ where
         if let PortReuse::Enabled { .. } = &self.port_reuse {
             socket.set_reuse_port(true)?;
         }
+        if let Some(SocketConfigFn(f)) = &self.config.socket_config {
+            f(&socket, role)?;
+        }
         Ok(socket)
     }
 
@@ -370,7 +483,7 @@ where
         id: ListenerId,
         socket_addr: SocketAddr,
     ) -> io::Result<ListenStream<T>> {
-        let socket = self.create_socket(socket_addr)?;
+        let socket = self.create_socket(socket_addr, SocketRole::Listen)?;
         socket.bind(&socket_addr.into())?;
         socket.listen(self.config.backlog as _)?;
         socket.set_nonblocking(true)?;
@@ -383,6 +496,7 @@ where
                 listener,
                 Some(T::new_if_watcher()?),
                 self.port_reuse.clone(),
+                self.config.accept_rate_limit,
             );
         }
 
@@ -392,7 +506,13 @@ where
             listener_id: id,
             listen_addr,
         });
-        ListenStream::<T>::new(id, listener, None, self.port_reuse.clone())
+        ListenStream::<T>::new(
+            id,
+            listener,
+            None,
+            self.port_reuse.clone(),
+            self.config.accept_rate_limit,
+        )
     }
 }
 
@@ -468,7 +588,7 @@ where
         tracing::debug!(address=%socket_addr, "dialing address");
 
         let socket = self
-            .create_socket(socket_addr)
+            .create_socket(socket_addr, SocketRole::Dial)
             .map_err(TransportError::Other)?;
 
         if let Some(addr) = self.port_reuse.local_dial_addr(&socket_addr.ip()) {
@@ -530,6 +650,18 @@ where
         }
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        if !is_tcp_addr(addr) {
+            return None;
+        }
+        self.config.dial_timeout
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        // Hosts may still be DNS names to be resolved by an outer transport.
+        is_tcp_addr(addr) && addr.iter().skip(2).all(|p| matches!(p, Protocol::P2p(_)))
+    }
+
     /// Poll all listeners.
     #[tracing::instrument(level = "trace", name = "Transport::poll", skip(self, cx))]
     fn poll(
@@ -579,6 +711,8 @@ where
     sleep_on_error: Duration,
     /// The current pause, if any.
     pause: Option<Delay>,
+    /// Limits the rate of accepted connections, if configured.
+    accept_limiter: Option<AcceptLimiter>,
     /// Pending event to reported.
     pending_event: Option<<Self as Stream>::Item>,
     /// The listener can be manually closed with [`Transport::remove_listener`](libp2p_core::Transport::remove_listener).
@@ -598,6 +732,7 @@ where
         listener: TcpListener,
         if_watcher: Option<T::IfWatcher>,
         port_reuse: PortReuse,
+        accept_rate_limit: Option<AcceptRateLimit>,
     ) -> io::Result<Self> {
         let listen_addr = listener.local_addr()?;
         let listener = T::new_listener(listener)?;
@@ -609,6 +744,7 @@ where
             listen_addr,
             if_watcher,
             pause: None,
+            accept_limiter: accept_rate_limit.map(AcceptLimiter::new),
             sleep_on_error: Duration::from_millis(100),
             pending_event: None,
             is_closed: false,
@@ -745,6 +881,24 @@ where
             return Poll::Ready(Some(event));
         }
 
+        if let Some(limiter) = self.accept_limiter.as_mut() {
+            if limiter.poll_ready(cx).is_pending() {
+                if limiter.throttle() {
+                    tracing::debug!(
+                        listener=?self.listener_id,
+                        "Accept rate limit exceeded, throttling incoming connections"
+                    );
+                    return Poll::Ready(Some(TransportEvent::ListenerError {
+                        listener_id: self.listener_id,
+                        error: io::Error::new(io::ErrorKind::Other, AcceptRateExceeded),
+                    }));
+                }
+
+                self.close_listener_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+
         // Take the pending connection from the backlog.
         match T::poll_accept(&mut self.listener, cx) {
             Poll::Ready(Ok(Incoming {
@@ -752,6 +906,10 @@ where
                 remote_addr,
                 stream,
             })) => {
+                if let Some(limiter) = self.accept_limiter.as_mut() {
+                    limiter.consume();
+                }
+
                 let local_addr = ip_to_multiaddr(local_addr.ip(), local_addr.port());
                 let remote_addr = ip_to_multiaddr(remote_addr.ip(), remote_addr.port());
 
@@ -1311,6 +1469,94 @@ mod tests {
             .is_none());
     }
 
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn socket_config_is_applied_to_listen_and_dial_sockets() {
+        let roles = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = Config::new().socket_config({
+            let roles = roles.clone();
+            move |socket, role| {
+                socket.set_ttl(42)?;
+                roles.lock().unwrap().push(role);
+                Ok(())
+            }
+        });
+        let mut transport = async_io::Transport::new(config);
+
+        transport
+            .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+            .unwrap();
+        let listen_addr = async_std::task::block_on(async {
+            match poll_fn(|cx| Pin::new(&mut transport).poll(cx)).await {
+                TransportEvent::NewAddress { listen_addr, .. } => listen_addr,
+                e => panic!("Unexpected event: {e:?}"),
+            }
+        });
+        let _dial = transport.dial(listen_addr).unwrap();
+
+        assert_eq!(
+            *roles.lock().unwrap(),
+            vec![SocketRole::Listen, SocketRole::Dial]
+        );
+    }
+
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn socket_config_error_fails_listen_and_dial() {
+        let config = Config::new().socket_config(|_, _| Err(io::ErrorKind::Unsupported.into()));
+        let mut transport = async_io::Transport::new(config);
+
+        assert!(transport
+            .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+            .is_err());
+        assert!(transport
+            .dial("/ip4/127.0.0.1/tcp/4242".parse().unwrap())
+            .is_err());
+    }
+
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn accept_rate_limit_throttles_incoming_connections() {
+        let config = Config::new()
+            .accept_rate_limit(NonZeroU32::new(10).unwrap(), NonZeroU32::new(2).unwrap());
+        let mut transport = async_io::Transport::new(config);
+        transport
+            .listen_on(ListenerId::next(), "/ip4/127.0.0.1/tcp/0".parse().unwrap())
+            .unwrap();
+
+        async_std::task::block_on(async {
+            let listen_addr = match poll_fn(|cx| Pin::new(&mut transport).poll(cx)).await {
+                TransportEvent::NewAddress { listen_addr, .. } => listen_addr,
+                e => panic!("Unexpected event: {e:?}"),
+            };
+            let mut dials = Vec::new();
+            for _ in 0..3 {
+                dials.push(transport.dial(listen_addr.clone()).unwrap());
+            }
+            let _dials = future::try_join_all(dials).await.unwrap();
+
+            let mut num_incoming = 0;
+            let mut throttled = false;
+            while num_incoming < 3 {
+                match poll_fn(|cx| Pin::new(&mut transport).poll(cx)).await {
+                    TransportEvent::Incoming { .. } => num_incoming += 1,
+                    TransportEvent::ListenerError { error, .. } => {
+                        assert_eq!(num_incoming, 2, "Throttling engages after the burst");
+                        assert_eq!(
+                            error
+                                .get_ref()
+                                .and_then(|e| e.downcast_ref::<AcceptRateExceeded>()),
+                            Some(&AcceptRateExceeded)
+                        );
+                        throttled = true;
+                    }
+                    e => panic!("Unexpected event: {e:?}"),
+                }
+            }
+            assert!(throttled);
+        });
+    }
+
     #[test]
     fn test_remove_listener() {
         let _ = tracing_subscriber::fmt()
@@ -0,0 +1,122 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Token bucket limiting the rate at which a listener accepts connections.
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use std::num::NonZeroU32;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The limit configured via [`Config::accept_rate_limit`](crate::Config::accept_rate_limit).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AcceptRateLimit {
+    pub(crate) per_second: NonZeroU32,
+    pub(crate) burst: NonZeroU32,
+}
+
+/// Tracks the connections a single listener may still accept.
+pub(crate) struct AcceptLimiter {
+    limit: AcceptRateLimit,
+    /// The number of connections which can be accepted right away.
+    tokens: u32,
+    /// When the last token was added to the bucket.
+    last_refill: Instant,
+    /// Fires once the next token is available.
+    delay: Option<Delay>,
+    /// Whether the limit was hit and the bucket did not fill up since.
+    throttled: bool,
+}
+
+impl AcceptLimiter {
+    pub(crate) fn new(limit: AcceptRateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst.get(),
+            last_refill: Instant::now(),
+            delay: None,
+            throttled: false,
+        }
+    }
+
+    /// Polls for a token to accept a connection with.
+    ///
+    /// The token is only taken via [`AcceptLimiter::consume`], once a connection was accepted.
+    pub(crate) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            let now = Instant::now();
+            self.refill(now);
+
+            if self.tokens > 0 {
+                self.delay = None;
+                return Poll::Ready(());
+            }
+
+            let wait = (self.last_refill + self.interval()).saturating_duration_since(now);
+            let delay = self.delay.get_or_insert_with(|| Delay::new(wait));
+            match delay.poll_unpin(cx) {
+                Poll::Ready(()) => self.delay = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    pub(crate) fn consume(&mut self) {
+        self.tokens = self.tokens.saturating_sub(1);
+    }
+
+    /// Marks the listener as throttled, returning `true` if it wasn't before.
+    ///
+    /// The listener is considered throttled until the bucket filled up again, i.e. until the
+    /// rate of incoming connections dropped below the limit.
+    pub(crate) fn throttle(&mut self) -> bool {
+        !std::mem::replace(&mut self.throttled, true)
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(1) / self.limit.per_second.get()
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let burst = self.limit.burst.get();
+        if self.tokens == burst {
+            self.last_refill = now;
+            return;
+        }
+
+        let interval = self.interval();
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let new_tokens = elapsed.as_nanos() / interval.as_nanos().max(1);
+        if new_tokens == 0 {
+            return;
+        }
+
+        if new_tokens >= u128::from(burst - self.tokens) {
+            self.tokens = burst;
+            self.last_refill = now;
+            self.throttled = false;
+        } else {
+            // `new_tokens` is smaller than `burst` and thus fits into a `u32`.
+            self.tokens += new_tokens as u32;
+            self.last_refill += interval * new_tokens as u32;
+        }
+    }
+}
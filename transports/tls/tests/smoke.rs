@@ -19,6 +19,7 @@ async fn can_establish_connection() {
                 SwarmEvent::NewListenAddr {
                     address,
                     listener_id,
+                    ..
                 } if listener_id == expected_listener_id => break address,
                 _ => continue,
             };
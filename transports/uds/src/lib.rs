@@ -184,6 +184,10 @@ macro_rules! codegen {
                 None
             }
 
+            fn supports_dial(&self, addr: &Multiaddr) -> bool {
+                multiaddr_to_path(addr).is_ok()
+            }
+
             fn poll(
                 mut self: Pin<&mut Self>,
                 cx: &mut Context<'_>,
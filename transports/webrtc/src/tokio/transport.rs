@@ -165,6 +165,10 @@ impl libp2p_core::Transport for Transport {
     fn address_translation(&self, server: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr> {
         libp2p_core::address_translation(server, observed)
     }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        libp2p_webrtc_utils::parse_webrtc_dial_addr(addr).is_some()
+    }
 }
 
 /// A stream of incoming connections on one or more interfaces.
@@ -33,6 +33,7 @@ use soketto::{
     connection::{self, CloseReason},
     handshake,
 };
+use std::time::Duration;
 use std::{collections::HashMap, ops::DerefMut, sync::Arc};
 use std::{fmt, io, mem, pin::Pin, task::Context, task::Poll};
 use url::Url;
@@ -49,11 +50,15 @@ pub struct WsConfig<T> {
     max_data_size: usize,
     tls_config: tls::Config,
     max_redirects: u8,
+    /// Headers and subprotocols of all handshakes.
+    handshake: HandshakeConfig,
+    /// Headers and subprotocols of the handshakes with specific hosts, see
+    /// [`WsConfig::set_host_handshake_config`].
+    host_handshakes: Arc<HashMap<String, HandshakeConfig>>,
     /// Websocket protocol of the inner listener.
     ///
     /// This is the suffix of the address provided in `listen_on`.
-    /// Can only be [`Protocol::Ws`] or [`Protocol::Wss`].
-    listener_protos: HashMap<ListenerId, Protocol<'static>>,
+    listener_protos: HashMap<ListenerId, WsListenProto<'static>>,
 }
 
 impl<T> WsConfig<T>
@@ -67,6 +72,8 @@ where
             max_data_size: MAX_DATA_SIZE,
             tls_config: tls::Config::client(),
             max_redirects: 0,
+            handshake: HandshakeConfig::default(),
+            host_handshakes: Default::default(),
             listener_protos: HashMap::new(),
         }
     }
@@ -98,6 +105,82 @@ where
         self.tls_config = c;
         self
     }
+
+    /// Set the headers and subprotocols of all websocket handshakes.
+    ///
+    /// When dialing, the headers and subprotocols are sent with the HTTP upgrade request.
+    /// When listening, the first subprotocol requested by the remote that is contained in
+    /// [`HandshakeConfig::protocols`] is selected. A `Sec-WebSocket-Protocol` header listing
+    /// several subprotocols is not split, so the remote has to send each of them in its own
+    /// header, as it is the case when dialing with a single subprotocol.
+    pub fn set_handshake_config(&mut self, c: HandshakeConfig) -> &mut Self {
+        self.handshake = c;
+        self
+    }
+
+    /// Set additional headers and subprotocols of the handshakes when dialing the given host,
+    /// i.e. the DNS name or IP address of the dialed address.
+    ///
+    /// They are sent in addition to the ones of [`WsConfig::set_handshake_config`], e.g. to
+    /// authorize with an API gateway, and are not sent to other hosts the dial is redirected to.
+    pub fn set_host_handshake_config(
+        &mut self,
+        host: impl Into<String>,
+        c: HandshakeConfig,
+    ) -> &mut Self {
+        Arc::make_mut(&mut self.host_handshakes).insert(host.into(), c);
+        self
+    }
+}
+
+/// Additional HTTP headers and websocket subprotocols of a websocket handshake.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandshakeConfig {
+    headers: Vec<(String, Vec<u8>)>,
+    protocols: Vec<String>,
+}
+
+impl HandshakeConfig {
+    /// Create an empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an HTTP header to the upgrade request, e.g. `Authorization`.
+    ///
+    /// The header is not checked for validity. Headers set by the handshake itself, i.e.
+    /// `Host`, `Upgrade`, `Connection` and `Sec-WebSocket-*`, must not be added.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add a subprotocol, sent in the `Sec-WebSocket-Protocol` header when dialing and
+    /// accepted when listening.
+    pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocols.push(protocol.into());
+        self
+    }
+
+    /// The configured headers.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.headers.iter().map(|(n, v)| (n.as_str(), v.as_slice()))
+    }
+
+    /// The configured subprotocols.
+    pub fn protocols(&self) -> impl Iterator<Item = &str> {
+        self.protocols.iter().map(String::as_str)
+    }
+
+    /// Returns the headers and subprotocols of `self` followed by the ones of `other`.
+    fn extend(&self, other: Option<&HandshakeConfig>) -> HandshakeConfig {
+        let mut c = self.clone();
+        if let Some(other) = other {
+            c.headers.extend(other.headers.iter().cloned());
+            c.protocols.extend(other.protocols.iter().cloned());
+        }
+        c
+    }
 }
 
 type TlsOrPlain<T> = future::Either<future::Either<client::TlsStream<T>, server::TlsStream<T>>, T>;
@@ -122,20 +205,32 @@ where
     ) -> Result<(), TransportError<Self::Error>> {
         let mut inner_addr = addr.clone();
         let proto = match inner_addr.pop() {
-            Some(p @ Protocol::Wss(_)) => {
-                if self.tls_config.server.is_some() {
-                    p
-                } else {
-                    tracing::debug!("/wss address but TLS server support is not configured");
-                    return Err(TransportError::MultiaddrNotSupported(addr));
+            Some(Protocol::Wss(path)) => WsListenProto::Wss(path),
+            Some(Protocol::Ws(path)) => match inner_addr.iter().last() {
+                Some(Protocol::Tls) => {
+                    inner_addr.pop();
+                    WsListenProto::TlsWs(path)
                 }
-            }
-            Some(p @ Protocol::Ws(_)) => p,
+                Some(Protocol::Sni(_)) => match (inner_addr.pop(), inner_addr.pop()) {
+                    (Some(Protocol::Sni(sni)), Some(Protocol::Tls)) => {
+                        WsListenProto::TlsSniWs(sni, path)
+                    }
+                    _ => {
+                        tracing::debug!(address=%addr, "`/sni` is not preceded by `/tls`");
+                        return Err(TransportError::MultiaddrNotSupported(addr));
+                    }
+                },
+                _ => WsListenProto::Ws(path),
+            },
             _ => {
                 tracing::debug!(address=%addr, "Address is not a websocket multiaddr");
                 return Err(TransportError::MultiaddrNotSupported(addr));
             }
         };
+        if proto.use_tls() && self.tls_config.server.is_none() {
+            tracing::debug!(address=%addr, "TLS websocket address but TLS server support is not configured");
+            return Err(TransportError::MultiaddrNotSupported(addr));
+        }
         match self.transport.lock().listen_on(id, inner_addr) {
             Ok(()) => {
                 self.listener_protos.insert(id, proto);
@@ -164,6 +259,21 @@ where
         self.transport.lock().address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        let addr = parse_ws_dial_addr::<T::Error>(addr.clone()).ok()?;
+        self.transport.lock().dial_timeout(&addr.tcp_addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        let addr = parse_ws_dial_addr::<T::Error>(addr.clone()).ok()?;
+        self.transport.lock().address_score(&addr.tcp_addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        parse_ws_dial_addr::<T::Error>(addr.clone())
+            .is_ok_and(|addr| self.transport.lock().supports_dial(&addr.tcp_addr))
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -185,7 +295,7 @@ where
                     .listener_protos
                     .get(&listener_id)
                     .expect("Protocol was inserted in Transport::listen_on.");
-                listen_addr.push(proto.clone());
+                proto.append_on_addr(&mut listen_addr);
                 tracing::debug!(address=%listen_addr, "Listening on address");
                 TransportEvent::NewAddress {
                     listener_id,
@@ -200,7 +310,7 @@ where
                     .listener_protos
                     .get(&listener_id)
                     .expect("Protocol was inserted in Transport::listen_on.");
-                listen_addr.push(proto.clone());
+                proto.append_on_addr(&mut listen_addr);
                 TransportEvent::AddressExpired {
                     listener_id,
                     listen_addr,
@@ -232,13 +342,9 @@ where
                     .listener_protos
                     .get(&listener_id)
                     .expect("Protocol was inserted in Transport::listen_on.");
-                let use_tls = match proto {
-                    Protocol::Wss(_) => true,
-                    Protocol::Ws(_) => false,
-                    _ => unreachable!("Map contains only ws and wss protocols."),
-                };
-                local_addr.push(proto.clone());
-                send_back_addr.push(proto.clone());
+                let use_tls = proto.use_tls();
+                proto.append_on_addr(&mut local_addr);
+                proto.append_on_addr(&mut send_back_addr);
                 let upgrade = self.map_upgrade(upgrade, send_back_addr.clone(), use_tls);
                 TransportEvent::Incoming {
                     listener_id,
@@ -279,11 +385,20 @@ where
         let transport = self.transport.clone();
         let tls_config = self.tls_config.clone();
         let max_redirects = self.max_redirects;
+        let handshake = self.handshake.clone();
+        let host_handshakes = self.host_handshakes.clone();
 
         let future = async move {
             loop {
-                match Self::dial_once(transport.clone(), addr, tls_config.clone(), role_override)
-                    .await
+                let handshake = handshake.extend(host_handshakes.get(&addr.host));
+                match Self::dial_once(
+                    transport.clone(),
+                    addr,
+                    tls_config.clone(),
+                    handshake,
+                    role_override,
+                )
+                .await
                 {
                     Ok(Either::Left(redirect)) => {
                         if remaining_redirects == 0 {
@@ -307,6 +422,7 @@ where
         transport: Arc<Mutex<T>>,
         addr: WsAddress,
         tls_config: tls::Config,
+        handshake: HandshakeConfig,
         role_override: Endpoint,
     ) -> Result<Either<String, Connection<T::Output>>, Error<T::Error>> {
         tracing::trace!(address=?addr, "Dialing websocket address");
@@ -347,7 +463,15 @@ where
 
         tracing::trace!(port=%addr.host_port, "Sending websocket handshake");
 
+        let headers = handshake
+            .headers()
+            .map(|(name, value)| handshake::client::Header { name, value })
+            .collect::<Vec<_>>();
         let mut client = handshake::Client::new(stream, &addr.host_port, addr.path.as_ref());
+        client.set_headers(&headers);
+        for protocol in handshake.protocols() {
+            client.add_protocol(protocol);
+        }
 
         match client
             .handshake()
@@ -369,9 +493,13 @@ where
                 let msg = format!("server rejected handshake; status code = {status_code}");
                 Err(Error::Handshake(msg.into()))
             }
-            handshake::ServerResponse::Accepted { .. } => {
-                tracing::trace!(port=%addr.host_port, "websocket handshake successful");
-                Ok(Either::Right(Connection::new(client.into_builder())))
+            handshake::ServerResponse::Accepted { protocol } => {
+                tracing::trace!(port=%addr.host_port, ?protocol, "websocket handshake successful");
+                let protocol = protocol.map(|p| p.to_owned());
+                Ok(Either::Right(Connection::new(
+                    client.into_builder(),
+                    protocol,
+                )))
             }
         }
     }
@@ -385,6 +513,7 @@ where
         let remote_addr2 = remote_addr.clone(); // used for logging
         let tls_config = self.tls_config.clone();
         let max_size = self.max_data_size;
+        let handshake = self.handshake.clone();
 
         async move {
             let stream = upgrade.map_err(Error::Transport).await?;
@@ -420,23 +549,29 @@ where
             );
 
             let mut server = handshake::Server::new(stream);
+            for protocol in handshake.protocols() {
+                server.add_protocol(protocol);
+            }
 
-            let ws_key = {
+            let (ws_key, protocol) = {
                 let request = server
                     .receive_request()
                     .map_err(|e| Error::Handshake(Box::new(e)))
                     .await?;
-                request.key()
+                // Only the requested subprotocols that were added to the server are reported.
+                let protocol = request.protocols().next().map(str::to_owned);
+                (request.key(), protocol)
             };
 
             tracing::trace!(
                 address=%remote_addr2,
+                ?protocol,
                 "accepting websocket handshake request from address"
             );
 
             let response = handshake::server::Response::Accept {
                 key: ws_key,
-                protocol: None,
+                protocol: protocol.as_deref(),
             };
 
             server
@@ -448,7 +583,7 @@ where
                 let mut builder = server.into_builder();
                 builder.set_max_message_size(max_size);
                 builder.set_max_frame_size(max_size);
-                Connection::new(builder)
+                Connection::new(builder, protocol)
             };
 
             Ok(conn)
@@ -457,8 +592,51 @@ where
     }
 }
 
+/// Websocket suffix of a listen address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WsListenProto<'a> {
+    /// `/ws`
+    Ws(std::borrow::Cow<'a, str>),
+    /// `/wss`
+    Wss(std::borrow::Cow<'a, str>),
+    /// `/tls/ws`, equivalent to `/wss`.
+    TlsWs(std::borrow::Cow<'a, str>),
+    /// `/tls/sni/<name>/ws`.
+    ///
+    /// The server name is only kept to report it back in the listen address; incoming
+    /// connections are served with the configured TLS server certificate regardless of the
+    /// server name requested by the client.
+    TlsSniWs(std::borrow::Cow<'a, str>, std::borrow::Cow<'a, str>),
+}
+
+impl WsListenProto<'_> {
+    fn append_on_addr(&self, addr: &mut Multiaddr) {
+        match self {
+            WsListenProto::Ws(path) => addr.push(Protocol::Ws(path.clone())),
+            WsListenProto::Wss(path) => addr.push(Protocol::Wss(path.clone())),
+            WsListenProto::TlsWs(path) => {
+                addr.push(Protocol::Tls);
+                addr.push(Protocol::Ws(path.clone()));
+            }
+            WsListenProto::TlsSniWs(sni, path) => {
+                addr.push(Protocol::Tls);
+                addr.push(Protocol::Sni(sni.clone()));
+                addr.push(Protocol::Ws(path.clone()));
+            }
+        }
+    }
+
+    fn use_tls(&self) -> bool {
+        match self {
+            WsListenProto::Ws(_) => false,
+            WsListenProto::Wss(_) | WsListenProto::TlsWs(_) | WsListenProto::TlsSniWs(..) => true,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct WsAddress {
+    host: String,
     host_port: String,
     path: String,
     dns_name: Option<rustls::pki_types::ServerName<'static>>,
@@ -478,19 +656,23 @@ fn parse_ws_dial_addr<T>(addr: Multiaddr) -> Result<WsAddress, Error<T>> {
     let mut protocols = addr.iter();
     let mut ip = protocols.next();
     let mut tcp = protocols.next();
-    let (host_port, dns_name) = loop {
+    let (host, host_port, dns_name) = loop {
         match (ip, tcp) {
             (Some(Protocol::Ip4(ip)), Some(Protocol::Tcp(port))) => {
-                break (format!("{ip}:{port}"), None)
+                break (ip.to_string(), format!("{ip}:{port}"), None)
             }
             (Some(Protocol::Ip6(ip)), Some(Protocol::Tcp(port))) => {
-                break (format!("{ip}:{port}"), None)
+                break (ip.to_string(), format!("{ip}:{port}"), None)
             }
             (Some(Protocol::Dns(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dns4(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dns6(h)), Some(Protocol::Tcp(port)))
             | (Some(Protocol::Dnsaddr(h)), Some(Protocol::Tcp(port))) => {
-                break (format!("{}:{}", &h, port), Some(tls::dns_name_ref(&h)?))
+                break (
+                    h.to_string(),
+                    format!("{}:{}", &h, port),
+                    Some(tls::dns_name_ref(&h)?),
+                )
             }
             (Some(_), Some(p)) => {
                 ip = Some(p);
@@ -500,34 +682,49 @@ fn parse_ws_dial_addr<T>(addr: Multiaddr) -> Result<WsAddress, Error<T>> {
         }
     };
 
-    // Now consume the `Ws` / `Wss` protocol from the end of the address,
-    // preserving the trailing `P2p` protocol that identifies the remote,
-    // if any.
+    // Now consume the `Ws` / `Wss` / `Tls/Ws` / `Tls/Sni/Ws` protocols from
+    // the end of the address, preserving the trailing `P2p` protocol that
+    // identifies the remote, if any.
     let mut protocols = addr.clone();
     let mut p2p = None;
+    let mut dns_name = dns_name;
     let (use_tls, path) = loop {
         match protocols.pop() {
             p @ Some(Protocol::P2p(_)) => p2p = p,
-            Some(Protocol::Ws(path)) => break (false, path.into_owned()),
-            Some(Protocol::Wss(path)) => {
-                if dns_name.is_none() {
-                    tracing::debug!(address=%addr, "Missing DNS name in WSS address");
-                    return Err(Error::InvalidMultiaddr(addr));
+            Some(Protocol::Ws(path)) => match protocols.iter().last() {
+                Some(Protocol::Tls) => {
+                    protocols.pop();
+                    break (true, path.into_owned());
                 }
-                break (true, path.into_owned());
-            }
+                Some(Protocol::Sni(_)) => match (protocols.pop(), protocols.pop()) {
+                    (Some(Protocol::Sni(sni)), Some(Protocol::Tls)) => {
+                        // The server name given by `/sni` takes precedence
+                        // over the host of the address.
+                        dns_name = Some(tls::dns_name_ref(&sni)?);
+                        break (true, path.into_owned());
+                    }
+                    _ => return Err(Error::InvalidMultiaddr(addr)),
+                },
+                _ => break (false, path.into_owned()),
+            },
+            Some(Protocol::Wss(path)) => break (true, path.into_owned()),
             _ => return Err(Error::InvalidMultiaddr(addr)),
         }
     };
+    if use_tls && dns_name.is_none() {
+        tracing::debug!(address=%addr, "Missing DNS name in TLS websocket address");
+        return Err(Error::InvalidMultiaddr(addr));
+    }
 
-    // The original address, stripped of the `/ws` and `/wss` protocols,
-    // makes up the address for the inner TCP-based transport.
+    // The original address, stripped of the `/ws`, `/wss`, `/tls` and `/sni`
+    // protocols, makes up the address for the inner TCP-based transport.
     let tcp_addr = match p2p {
         Some(p) => protocols.with(p),
         None => protocols,
     };
 
     Ok(WsAddress {
+        host,
         host_port,
         dns_name,
         path,
@@ -572,6 +769,8 @@ fn location_to_multiaddr<T>(location: &str) -> Result<Multiaddr, Error<T>> {
 pub struct Connection<T> {
     receiver: BoxStream<'static, Result<Incoming, connection::Error>>,
     sender: Pin<Box<dyn Sink<OutgoingData, Error = connection::Error> + Send>>,
+    /// The subprotocol selected in the handshake, if any.
+    protocol: Option<String>,
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -657,7 +856,7 @@ impl<T> Connection<T>
 where
     T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
-    fn new(builder: connection::Builder<TlsOrPlain<T>>) -> Self {
+    fn new(builder: connection::Builder<TlsOrPlain<T>>, protocol: Option<String>) -> Self {
         let (sender, receiver) = builder.finish();
         let sink = quicksink::make_sink(sender, |mut sender, action| async move {
             match action {
@@ -704,10 +903,16 @@ where
         Connection {
             receiver: stream.boxed(),
             sender: Box::pin(sink),
+            protocol,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// The subprotocol selected by the listener in the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
     /// Send binary application data to the remote.
     pub fn send_data(&mut self, data: Vec<u8>) -> sink::Send<'_, Self, OutgoingData> {
         self.send(OutgoingData::Binary(data))
@@ -767,3 +972,148 @@ where
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_core::transport::ListenerId;
+    use libp2p_tcp as tcp;
+
+    #[test]
+    fn negotiates_protocol_of_host_handshake_config() {
+        futures::executor::block_on(async {
+            let mut listener = WsConfig::new(tcp::async_io::Transport::default());
+            listener.set_handshake_config(HandshakeConfig::new().with_protocol("gateway"));
+            let mut listener = listener.boxed();
+            listener
+                .listen_on(
+                    ListenerId::next(),
+                    "/ip4/127.0.0.1/tcp/0/ws".parse().unwrap(),
+                )
+                .unwrap();
+            let addr = listener.next().await.unwrap().into_new_address().unwrap();
+
+            let mut dialer = WsConfig::new(tcp::async_io::Transport::default());
+            dialer
+                .set_handshake_config(HandshakeConfig::new().with_header("X-Client", "test"))
+                .set_host_handshake_config(
+                    "127.0.0.1",
+                    HandshakeConfig::new()
+                        .with_header("Authorization", "Bearer token")
+                        .with_protocol("gateway"),
+                );
+
+            let inbound = async {
+                let (upgrade, _) = listener
+                    .select_next_some()
+                    .map(|ev| ev.into_incoming())
+                    .await
+                    .unwrap();
+                upgrade.await
+            };
+            let outbound = dialer.boxed().dial(addr).unwrap();
+            let (inbound, outbound) = futures::join!(inbound, outbound);
+
+            assert_eq!(inbound.unwrap().protocol(), Some("gateway"));
+            assert_eq!(outbound.unwrap().protocol(), Some("gateway"));
+        })
+    }
+
+    #[test]
+    fn parses_tls_ws_like_wss() {
+        let wss: Multiaddr = "/dns4/example.com/tcp/443/wss".parse().unwrap();
+        let tls_ws: Multiaddr = "/dns4/example.com/tcp/443/tls/ws".parse().unwrap();
+
+        let wss = parse_ws_dial_addr::<()>(wss).unwrap();
+        let tls_ws = parse_ws_dial_addr::<()>(tls_ws).unwrap();
+
+        assert!(tls_ws.use_tls);
+        assert_eq!(tls_ws.tcp_addr, wss.tcp_addr);
+        assert_eq!(tls_ws.host_port, wss.host_port);
+        assert_eq!(tls_ws.dns_name, wss.dns_name);
+    }
+
+    #[test]
+    fn tls_ws_preserves_p2p_suffix() {
+        let peer = libp2p_identity::PeerId::random();
+        let addr: Multiaddr = format!("/dns4/example.com/tcp/443/tls/ws/p2p/{peer}")
+            .parse()
+            .unwrap();
+
+        let addr = parse_ws_dial_addr::<()>(addr).unwrap();
+
+        assert_eq!(
+            addr.tcp_addr,
+            format!("/dns4/example.com/tcp/443/p2p/{peer}")
+                .parse::<Multiaddr>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn tls_ws_requires_dns_name() {
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/443/tls/ws".parse().unwrap();
+
+        assert!(matches!(
+            parse_ws_dial_addr::<()>(addr),
+            Err(Error::InvalidMultiaddr(_))
+        ));
+    }
+
+    #[test]
+    fn listen_proto_roundtrips_tls_ws() {
+        let mut addr: Multiaddr = "/ip4/127.0.0.1/tcp/1234".parse().unwrap();
+        WsListenProto::TlsWs("/".into()).append_on_addr(&mut addr);
+
+        assert_eq!(
+            addr,
+            "/ip4/127.0.0.1/tcp/1234/tls/ws"
+                .parse::<Multiaddr>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn tls_sni_ws_uses_sni_as_server_name() {
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/443/tls/sni/example.com/ws"
+            .parse()
+            .unwrap();
+
+        let addr = parse_ws_dial_addr::<()>(addr).unwrap();
+
+        assert!(addr.use_tls);
+        assert_eq!(
+            addr.dns_name,
+            Some(tls::dns_name_ref("example.com").unwrap())
+        );
+        assert_eq!(
+            addr.tcp_addr,
+            "/ip4/127.0.0.1/tcp/443".parse::<Multiaddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn sni_without_tls_is_rejected() {
+        let addr: Multiaddr = "/dns4/example.com/tcp/443/sni/example.com/ws"
+            .parse()
+            .unwrap();
+
+        assert!(matches!(
+            parse_ws_dial_addr::<()>(addr),
+            Err(Error::InvalidMultiaddr(_))
+        ));
+    }
+
+    #[test]
+    fn listen_proto_roundtrips_tls_sni_ws() {
+        let mut addr: Multiaddr = "/ip4/127.0.0.1/tcp/1234".parse().unwrap();
+        WsListenProto::TlsSniWs("example.com".into(), "/".into()).append_on_addr(&mut addr);
+
+        assert_eq!(
+            addr,
+            "/ip4/127.0.0.1/tcp/1234/tls/sni/example.com/ws"
+                .parse::<Multiaddr>()
+                .unwrap()
+        );
+    }
+}
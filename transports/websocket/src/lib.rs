@@ -37,6 +37,7 @@ use libp2p_core::{
     Transport,
 };
 use rw_stream_sink::RwStreamSink;
+use std::time::Duration;
 use std::{
     io,
     pin::Pin,
@@ -175,6 +176,28 @@ where
         self.transport.inner_mut().set_tls_config(c);
         self
     }
+
+    /// Set the headers and subprotocols of all websocket handshakes.
+    ///
+    /// See [`framed::WsConfig::set_handshake_config`].
+    pub fn set_handshake_config(&mut self, c: framed::HandshakeConfig) -> &mut Self {
+        self.transport.inner_mut().set_handshake_config(c);
+        self
+    }
+
+    /// Set additional headers and subprotocols of the handshakes when dialing the given host.
+    ///
+    /// See [`framed::WsConfig::set_host_handshake_config`].
+    pub fn set_host_handshake_config(
+        &mut self,
+        host: impl Into<String>,
+        c: framed::HandshakeConfig,
+    ) -> &mut Self {
+        self.transport
+            .inner_mut()
+            .set_host_handshake_config(host, c);
+        self
+    }
 }
 
 impl<T> Transport for WsConfig<T>
@@ -217,6 +240,18 @@ where
         self.transport.address_translation(server, observed)
     }
 
+    fn dial_timeout(&self, addr: &Multiaddr) -> Option<Duration> {
+        self.transport.dial_timeout(addr)
+    }
+
+    fn address_score(&self, addr: &Multiaddr) -> Option<i32> {
+        self.transport.address_score(addr)
+    }
+
+    fn supports_dial(&self, addr: &Multiaddr) -> bool {
+        self.transport.supports_dial(addr)
+    }
+
     fn poll(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -290,7 +325,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::WsConfig;
+    use super::{framed::HandshakeConfig, tls, WsConfig};
     use futures::prelude::*;
     use libp2p_core::{multiaddr::Protocol, transport::ListenerId, Multiaddr, Transport};
     use libp2p_identity::PeerId;
@@ -308,12 +343,84 @@ mod tests {
         futures::executor::block_on(connect(a))
     }
 
+    #[test]
+    fn dialer_connects_with_handshake_config() {
+        let a = "/ip4/127.0.0.1/tcp/0/ws".parse().unwrap();
+
+        let mut listener = new_ws_config();
+        listener.set_handshake_config(HandshakeConfig::new().with_protocol("gateway"));
+
+        let mut dialer = new_ws_config();
+        dialer
+            .set_handshake_config(HandshakeConfig::new().with_protocol("other"))
+            .set_host_handshake_config(
+                "127.0.0.1",
+                HandshakeConfig::new()
+                    .with_header("Authorization", "Bearer token")
+                    .with_protocol("gateway"),
+            );
+
+        futures::executor::block_on(connect_with(a, listener, dialer))
+    }
+
+    #[test]
+    fn dialer_connects_to_tls_listener_via_sni() {
+        let a = "/ip4/127.0.0.1/tcp/0/tls/sni/localhost/ws".parse().unwrap();
+
+        let rcgen_cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let priv_key = tls::PrivateKey::new(rcgen_cert.serialize_private_key_der());
+        let cert = tls::Certificate::new(rcgen_cert.serialize_der().unwrap());
+
+        let mut listener = new_ws_config();
+        listener.set_tls_config(tls::Config::new(priv_key, vec![cert.clone()]).unwrap());
+
+        let mut builder = tls::Config::builder();
+        builder.add_trust(&cert).unwrap();
+        let mut dialer = new_ws_config();
+        dialer.set_tls_config(builder.finish());
+
+        futures::executor::block_on(async move {
+            let mut listener = listener.boxed();
+            listener.listen_on(ListenerId::next(), a).expect("listener");
+
+            let addr = listener
+                .next()
+                .await
+                .expect("no error")
+                .into_new_address()
+                .expect("listen address");
+
+            assert_eq!(Some(Protocol::Sni("localhost".into())), addr.iter().nth(3));
+
+            let inbound = async move {
+                let (conn, _addr) = listener
+                    .select_next_some()
+                    .map(|ev| ev.into_incoming())
+                    .await
+                    .unwrap();
+                conn.await
+            };
+            let outbound = dialer.boxed().dial(addr).unwrap();
+
+            let (a, b) = futures::join!(inbound, outbound);
+            a.and(b).unwrap();
+        })
+    }
+
     fn new_ws_config() -> WsConfig<tcp::async_io::Transport> {
         WsConfig::new(tcp::async_io::Transport::new(tcp::Config::default()))
     }
 
     async fn connect(listen_addr: Multiaddr) {
-        let mut ws_config = new_ws_config().boxed();
+        connect_with(listen_addr, new_ws_config(), new_ws_config()).await
+    }
+
+    async fn connect_with(
+        listen_addr: Multiaddr,
+        listener: WsConfig<tcp::async_io::Transport>,
+        dialer: WsConfig<tcp::async_io::Transport>,
+    ) {
+        let mut ws_config = listener.boxed();
         ws_config
             .listen_on(ListenerId::next(), listen_addr)
             .expect("listener");
@@ -337,7 +444,7 @@ mod tests {
             conn.await
         };
 
-        let outbound = new_ws_config()
+        let outbound = dialer
             .boxed()
             .dial(addr.with(Protocol::P2p(PeerId::random())))
             .unwrap();
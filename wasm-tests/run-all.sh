@@ -5,3 +5,4 @@ set -e
 cd "$(dirname "${BASH_SOURCE[0]}")" || exit 1
 
 ./webtransport-tests/run.sh
+./webcrypto-tests/run.sh
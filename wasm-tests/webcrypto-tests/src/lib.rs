@@ -0,0 +1,64 @@
+use libp2p_identity::webcrypto::{self, Algorithm};
+use libp2p_identity::{Keypair, PeerId};
+use libp2p_noise as noise;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+async fn signs_via_webcrypto() {
+    let keypair = webcrypto::Keypair::generate(Algorithm::EcdsaP256)
+        .await
+        .unwrap();
+    let msg = b"hello world";
+
+    let signature = keypair.sign(msg).await.unwrap();
+
+    assert!(keypair.public().verify(msg, &signature));
+    assert!(!keypair.public().verify(b"other message", &signature));
+}
+
+#[wasm_bindgen_test]
+async fn restores_keypair_from_crypto_keys() {
+    let keypair = webcrypto::Keypair::generate(Algorithm::EcdsaP256)
+        .await
+        .unwrap();
+
+    let restored = webcrypto::Keypair::from_crypto_key_pair(&keypair.to_crypto_key_pair())
+        .await
+        .unwrap();
+
+    assert_eq!(restored.algorithm(), Algorithm::EcdsaP256);
+    assert_eq!(restored.public(), keypair.public());
+    let signature = restored.sign(b"msg").await.unwrap();
+    assert!(keypair.public().verify(b"msg", &signature));
+}
+
+#[wasm_bindgen_test]
+async fn converts_into_identity_keypair() {
+    let webcrypto = webcrypto::Keypair::generate(Algorithm::EcdsaP256)
+        .await
+        .unwrap();
+    let public = webcrypto.public();
+
+    let keypair = Keypair::from(webcrypto);
+
+    assert_eq!(keypair.public(), public);
+    assert_eq!(keypair.public().to_peer_id(), PeerId::from(public));
+    assert!(keypair.sign(b"msg").is_err());
+    assert!(keypair.to_protobuf_encoding().is_err());
+    let signature = keypair.sign_async(b"msg").await.unwrap();
+    assert!(keypair.public().verify(b"msg", &signature));
+}
+
+#[wasm_bindgen_test]
+async fn authenticates_noise_keys() {
+    let keypair = Keypair::from(
+        webcrypto::Keypair::generate(Algorithm::EcdsaP256)
+            .await
+            .unwrap(),
+    );
+
+    assert!(noise::Config::new(&keypair).is_err());
+    assert!(noise::Config::new_async(&keypair).await.is_ok());
+}